@@ -0,0 +1,663 @@
+//! Lints and diagrams for `state_machine!` definitions.
+//!
+//! Finds every `state_machine!` invocation in a tree of Rust sources, parses
+//! it with the macro's own parser, and checks it for problems the type checker
+//! can't see:
+//!
+//! - `unreachable-state`: a state no transition path reaches from `initial`
+//! - `unguarded-dangerous`: an event marked `dangerous: true` has a transition
+//!   without `guards` or `unless`
+//! - `no-terminal-state`: every state has a transition to some other state
+//! - `contradictory-guards`: a transition requires a guard to both pass and
+//!   fail, so it can never fire
+//! - `duplicate-transition`: two events move between the same states under
+//!   the same guards
+//! - `diagram-drift`: the committed Mermaid diagram doesn't match the definition
+//!
+//! The `cargo-state-machines` binary wraps this as `cargo state-machines lint`
+//! and `cargo state-machines diagram`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Ident;
+use syn::visit::Visit;
+
+use state_machines_dsl::extends::{Needed, Resolution};
+use state_machines_dsl::types::{Event, GuardExpr, StateMachine, Transition};
+use state_machines_dsl::{cfg, extends, fragment, params, utils};
+
+/// A check performed by [`Machine::lint`] or [`Machine::check_diagram`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnreachableState,
+    UnguardedDangerous,
+    NoTerminalState,
+    ContradictoryGuards,
+    DuplicateTransition,
+    DiagramDrift,
+    /// The invocation doesn't parse or fails the macro's validation.
+    InvalidDefinition,
+}
+
+impl Lint {
+    /// Every lint, in reporting order.
+    pub const ALL: [Lint; 7] = [
+        Lint::UnreachableState,
+        Lint::UnguardedDangerous,
+        Lint::NoTerminalState,
+        Lint::ContradictoryGuards,
+        Lint::DuplicateTransition,
+        Lint::DiagramDrift,
+        Lint::InvalidDefinition,
+    ];
+
+    /// The kebab-case name used on the command line and in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnreachableState => "unreachable-state",
+            Lint::UnguardedDangerous => "unguarded-dangerous",
+            Lint::NoTerminalState => "no-terminal-state",
+            Lint::ContradictoryGuards => "contradictory-guards",
+            Lint::DuplicateTransition => "duplicate-transition",
+            Lint::DiagramDrift => "diagram-drift",
+            Lint::InvalidDefinition => "invalid-definition",
+        }
+    }
+
+    /// Look up a lint by its kebab-case name.
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A problem found in a machine definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub file: PathBuf,
+    pub line: usize,
+    pub lint: Lint,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.file.display(),
+            self.line,
+            self.lint,
+            self.message
+        )
+    }
+}
+
+/// A parsed `state_machine!` invocation.
+pub struct Machine {
+    file: PathBuf,
+    line: usize,
+    def: StateMachine,
+}
+
+impl fmt::Debug for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Machine")
+            .field("name", &self.name())
+            .field("file", &self.file)
+            .field("line", &self.line)
+            .finish()
+    }
+}
+
+/// A transition between two leaf states.
+struct Edge<'a> {
+    from: Ident,
+    to: Ident,
+    event: &'a Event,
+    guards: Vec<String>,
+}
+
+impl Machine {
+    /// The machine's `name:`.
+    pub fn name(&self) -> String {
+        self.def.name.to_string()
+    }
+
+    /// The file the invocation was found in.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// The line of the `state_machine!` invocation.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The file name of the machine's committed diagram, e.g.
+    /// `traffic_light.mmd` for `TrafficLight`.
+    pub fn diagram_file(&self) -> String {
+        format!("{}.mmd", utils::to_snake_case(&self.name()))
+    }
+
+    /// Run the structural lints.
+    pub fn lint(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let edges = self.edges();
+        let name = &self.def.name;
+
+        // Breadth-first walk from the initial state
+        let initial = self.def.resolve_target(&self.def.initial);
+        let mut reached = vec![initial.clone()];
+        let mut queue = VecDeque::from([initial]);
+        while let Some(state) = queue.pop_front() {
+            for edge in edges.iter().filter(|edge| edge.from == state) {
+                if !reached.contains(&edge.to) {
+                    reached.push(edge.to.clone());
+                    queue.push_back(edge.to.clone());
+                }
+            }
+        }
+        for state in self
+            .def
+            .states
+            .iter()
+            .filter(|state| !reached.contains(state))
+        {
+            findings.push(self.finding(
+                state,
+                Lint::UnreachableState,
+                format!(
+                    "state `{}` of `{}` is unreachable from `{}`",
+                    state, name, self.def.initial
+                ),
+            ));
+        }
+
+        for edge in edges.iter().filter(|edge| edge.event.dangerous) {
+            if edge.guards.is_empty() {
+                findings.push(self.finding(
+                    &edge.event.name,
+                    Lint::UnguardedDangerous,
+                    format!(
+                        "dangerous event `{}` of `{}` has no guard on `{}` -> `{}`",
+                        edge.event.name, name, edge.from, edge.to
+                    ),
+                ));
+            }
+        }
+
+        let has_terminal = self.def.states.iter().any(|state| {
+            !edges
+                .iter()
+                .any(|edge| &edge.from == state && &edge.to != state)
+        });
+        if !has_terminal {
+            findings.push(self.finding(
+                name,
+                Lint::NoTerminalState,
+                format!("every state of `{}` has a transition out of it", name),
+            ));
+        }
+
+        for event in &self.def.events {
+            for transition in &event.transitions {
+                if let Some((at, guard)) = contradiction(event, transition) {
+                    findings.push(self.finding(
+                        at,
+                        Lint::ContradictoryGuards,
+                        format!(
+                            "`{}` of `{}` can never fire from {}: guard `{}` must both pass and fail",
+                            event.name,
+                            name,
+                            describe_sources(&transition.sources),
+                            guard
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Two sources of one event can't share a leaf, so repeats are
+        // across events; report each pair of events once
+        let mut repeated: Vec<(&Ident, &Ident)> = Vec::new();
+        for (index, edge) in edges.iter().enumerate() {
+            let Some(first) = edges[..index].iter().find(|earlier| {
+                earlier.event.name != edge.event.name
+                    && earlier.from == edge.from
+                    && earlier.to == edge.to
+                    && same_guards(&earlier.guards, &edge.guards)
+            }) else {
+                continue;
+            };
+            let pair = (&first.event.name, &edge.event.name);
+            if repeated.contains(&pair) {
+                continue;
+            }
+            repeated.push(pair);
+            findings.push(self.finding(
+                &edge.event.name,
+                Lint::DuplicateTransition,
+                format!(
+                    "`{}` of `{}` repeats `{}`'s `{}` -> `{}` transition with the same guards",
+                    edge.event.name, name, first.event.name, edge.from, edge.to
+                ),
+            ));
+        }
+
+        findings
+    }
+
+    /// Render the machine as a Mermaid state diagram.
+    ///
+    /// Superstates are flattened to their leaf states; edge labels list the
+    /// event and its guards.
+    pub fn to_mermaid(&self) -> String {
+        let mut lines = vec![
+            "stateDiagram-v2".to_string(),
+            format!("    [*] --> {}", self.def.resolve_target(&self.def.initial)),
+        ];
+        for edge in self.edges() {
+            let label = if edge.guards.is_empty() {
+                edge.event.name.to_string()
+            } else {
+                format!("{} [{}]", edge.event.name, edge.guards.join(", "))
+            };
+            let line = format!("    {} --> {}: {}", edge.from, edge.to, label);
+            if !lines.contains(&line) {
+                lines.push(line);
+            }
+        }
+
+        let mut out = lines.join("\n");
+        out.push('\n');
+        out
+    }
+
+    /// Compare the committed diagram (if any) against [`Machine::to_mermaid`].
+    ///
+    /// Line endings and trailing whitespace are ignored.
+    pub fn check_diagram(&self, path: &Path, committed: Option<&str>) -> Option<Finding> {
+        let message = match committed {
+            None => format!(
+                "no committed diagram for `{}` at {}",
+                self.def.name,
+                path.display()
+            ),
+            Some(text) if normalize(text) != normalize(&self.to_mermaid()) => format!(
+                "diagram {} is out of date with `{}`; regenerate it with `cargo state-machines diagram`",
+                path.display(),
+                self.def.name
+            ),
+            Some(_) => return None,
+        };
+        Some(self.finding(&self.def.name, Lint::DiagramDrift, message))
+    }
+
+    /// Every leaf-to-leaf transition, in declaration order.
+    fn edges(&self) -> Vec<Edge<'_>> {
+        let mut edges = Vec::new();
+        for event in &self.def.events {
+            for transition in &event.transitions {
+                let targets = match &transition.choice {
+                    Some(choice) => choice.targets.clone(),
+                    None => vec![transition.target.clone()],
+                };
+                let guards: Vec<String> = event
+                    .guards
+                    .iter()
+                    .chain(&transition.guards)
+                    .map(|guard| guard.describe())
+                    .chain(
+                        event
+                            .unless
+                            .iter()
+                            .chain(&transition.unless)
+                            .map(|guard| format!("unless {}", guard.describe())),
+                    )
+                    .collect();
+
+                for source in &transition.sources {
+                    for from in self.def.hierarchy.expand_state(source, &self.def.states) {
+                        for target in &targets {
+                            edges.push(Edge {
+                                from: from.clone(),
+                                to: self.def.resolve_target(target),
+                                event,
+                                guards: guards.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        edges
+    }
+
+    fn finding(&self, at: &Ident, lint: Lint, message: String) -> Finding {
+        Finding {
+            file: self.file.clone(),
+            line: at.span().start().line,
+            lint,
+            message,
+        }
+    }
+}
+
+/// The first guard a transition requires to both pass and fail, counting
+/// the event's guards and `unless` and treating `!guard` as `unless: [guard]`.
+fn contradiction<'a>(event: &'a Event, transition: &'a Transition) -> Option<(&'a Ident, String)> {
+    let requirements = event
+        .guards
+        .iter()
+        .chain(&transition.guards)
+        .map(|guard| (guard, true))
+        .chain(
+            event
+                .unless
+                .iter()
+                .chain(&transition.unless)
+                .map(|guard| (guard, false)),
+        )
+        .map(|(guard, pass)| match guard {
+            GuardExpr::Not(inner) => (inner.as_ref(), !pass),
+            _ => (guard, pass),
+        });
+
+    let mut seen: Vec<(String, bool)> = Vec::new();
+    for (guard, pass) in requirements {
+        let described = guard.describe();
+        if seen.contains(&(described.clone(), !pass)) {
+            let (at, _) = guard.calls()[0];
+            return Some((at, described));
+        }
+        seen.push((described, pass));
+    }
+    None
+}
+
+fn describe_sources(sources: &[Ident]) -> String {
+    let sources: Vec<String> = sources
+        .iter()
+        .map(|source| format!("`{}`", source))
+        .collect();
+    sources.join(", ")
+}
+
+/// Guard lists that require the same things, in any order.
+fn same_guards(lhs: &[String], rhs: &[String]) -> bool {
+    let mut lhs = lhs.to_vec();
+    let mut rhs = rhs.to_vec();
+    lhs.sort();
+    rhs.sort();
+    lhs == rhs
+}
+
+fn normalize(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// A `state_machine!` invocation, before its `extends:` base and `include:`
+/// fragments are merged in, or a `fragment!` one.
+struct Invocation {
+    file: PathBuf,
+    line: usize,
+    fragment: bool,
+    tokens: TokenStream,
+}
+
+/// Collects `state_machine!` and `fragment!` invocations from a syntax tree.
+struct Invocations<'a> {
+    file: &'a Path,
+    invocations: Vec<Invocation>,
+}
+
+impl<'ast> Visit<'ast> for Invocations<'_> {
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let Some(last) = mac.path.segments.last() else {
+            return;
+        };
+        let fragment = last.ident == "fragment";
+        if !fragment && last.ident != "state_machine" {
+            return;
+        }
+
+        let line = mac
+            .path
+            .segments
+            .first()
+            .map_or(0, |segment| segment.ident.span().start().line);
+        // `#[cfg]`-gated entries are all linted, as if every predicate held
+        self.invocations.push(Invocation {
+            file: self.file.to_path_buf(),
+            line,
+            fragment,
+            tokens: cfg::strip(mac.tokens.clone(), &|_| true),
+        });
+    }
+}
+
+/// The invocations in one source file, or the finding for a file that
+/// isn't valid Rust.
+fn invocations(file: &Path, source: &str) -> std::result::Result<Vec<Invocation>, Finding> {
+    let syntax = syn::parse_file(source).map_err(|err| Finding {
+        file: file.to_path_buf(),
+        line: err.span().start().line,
+        lint: Lint::InvalidDefinition,
+        message: err.to_string(),
+    })?;
+    let mut visitor = Invocations {
+        file,
+        invocations: Vec::new(),
+    };
+    visitor.visit_file(&syntax);
+    Ok(visitor.invocations)
+}
+
+/// Parse invocations, merging each `extends:` base and `include:` fragment
+/// in as the macro would.
+///
+/// Bases are looked up by name among the `extendable: true` machines found,
+/// and fragments among the `fragment!` invocations, in any order.
+fn parse_invocations(invocations: Vec<Invocation>) -> (Vec<Machine>, Vec<Finding>) {
+    let invalid = |invocation: &Invocation, err: syn::Error| Finding {
+        file: invocation.file.clone(),
+        line: err.span().start().line,
+        lint: Lint::InvalidDefinition,
+        message: err.to_string(),
+    };
+
+    let mut machines = Vec::new();
+    let mut findings = Vec::new();
+    let mut bases: HashMap<String, TokenStream> = HashMap::new();
+    let mut fragments: HashMap<String, TokenStream> = HashMap::new();
+    let (definitions, mut pending): (Vec<_>, Vec<_>) = invocations
+        .into_iter()
+        .partition(|invocation| invocation.fragment);
+    for invocation in definitions {
+        match fragment::definition(invocation.tokens.clone()) {
+            Ok((name, body)) => {
+                fragments.insert(name.to_string(), body);
+            }
+            Err(err) => findings.push(invalid(&invocation, err)),
+        }
+    }
+    loop {
+        let mut waiting = Vec::new();
+        let before = pending.len();
+        for invocation in pending {
+            let (tokens, extendable) = match resolve(&invocation, &bases, &fragments) {
+                Ok(Resolution::Ready { tokens, extendable }) => (tokens, extendable),
+                Ok(Resolution::Pending { .. }) => {
+                    waiting.push(invocation);
+                    continue;
+                }
+                Err(err) => {
+                    findings.push(invalid(&invocation, err));
+                    continue;
+                }
+            };
+            let parsed = params::substitute(tokens.clone())
+                .and_then(syn::parse2::<StateMachine>)
+                .and_then(|def| def.validate().map(|()| def));
+            match parsed {
+                Ok(def) => {
+                    if extendable {
+                        bases.insert(def.name.to_string(), tokens);
+                    }
+                    machines.push(Machine {
+                        file: invocation.file,
+                        line: invocation.line,
+                        def,
+                    });
+                }
+                Err(err) => findings.push(invalid(&invocation, err)),
+            }
+        }
+        // Stop once a round resolves nothing more
+        let stuck = waiting.len() == before;
+        pending = waiting;
+        if pending.is_empty() || stuck {
+            break;
+        }
+    }
+
+    // Bases and fragments that were never found
+    for invocation in pending {
+        let message = match resolve(&invocation, &bases, &fragments) {
+            Ok(Resolution::Pending {
+                needs: Needed::Base(base),
+                ..
+            }) => {
+                let base = quote! { #base }.to_string().replace(' ', "");
+                format!("`extends: {base}` names no `extendable: true` machine")
+            }
+            Ok(Resolution::Pending {
+                needs: Needed::Fragment(fragment),
+                ..
+            }) => {
+                let fragment = quote! { #fragment }.to_string().replace(' ', "");
+                format!("`include: {fragment}` names no `fragment!`")
+            }
+            _ => "unresolved `extends:`".to_owned(),
+        };
+        findings.push(Finding {
+            file: invocation.file,
+            line: invocation.line,
+            lint: Lint::InvalidDefinition,
+            message,
+        });
+    }
+    machines.sort_by_key(|machine| (machine.file.clone(), machine.line));
+    (machines, findings)
+}
+
+/// Merge in the definitions an invocation needs, as far as they're known.
+fn resolve(
+    invocation: &Invocation,
+    bases: &HashMap<String, TokenStream>,
+    fragments: &HashMap<String, TokenStream>,
+) -> syn::Result<Resolution> {
+    let last = |path: &syn::Path| {
+        path.segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+    };
+    let mut tokens = invocation.tokens.clone();
+    loop {
+        let resolution = extends::resolve(tokens.clone())?;
+        tokens = match &resolution {
+            Resolution::Pending {
+                needs: Needed::Base(base),
+                ..
+            } => match last(base).and_then(|name| bases.get(&name)) {
+                Some(definition) => quote! { extends: { #definition }, #tokens },
+                None => return Ok(resolution),
+            },
+            Resolution::Pending {
+                needs: Needed::Fragment(fragment),
+                ..
+            } => match last(fragment).and_then(|name| fragments.get(&name)) {
+                Some(body) => quote! { #tokens, __fragment: { #body } },
+                None => return Ok(resolution),
+            },
+            Resolution::Ready { .. } => return Ok(resolution),
+        };
+    }
+}
+
+/// Parse the machines defined in one source file.
+///
+/// Invocations the macro would reject are reported as
+/// [`Lint::InvalidDefinition`] findings; a file that isn't valid Rust yields
+/// a single such finding.
+pub fn parse_source(file: &Path, source: &str) -> (Vec<Machine>, Vec<Finding>) {
+    match invocations(file, source) {
+        Ok(invocations) => parse_invocations(invocations),
+        Err(finding) => (Vec::new(), vec![finding]),
+    }
+}
+
+/// Parse the machines in every `.rs` file under `root`.
+///
+/// Skips `target` directories and hidden directories. Files are visited in
+/// sorted order so reports are stable. A machine can extend one defined in
+/// any of the files, and include fragments from any of them.
+pub fn scan(root: &Path) -> io::Result<(Vec<Machine>, Vec<Finding>)> {
+    let mut files = Vec::new();
+    collect_sources(root, &mut files)?;
+    files.sort();
+
+    let mut all = Vec::new();
+    let mut findings = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        // Cheap pre-filter: most files never mention the macros
+        if !source.contains("state_machine!") && !source.contains("fragment!") {
+            continue;
+        }
+        match invocations(&file, &source) {
+            Ok(found) => all.extend(found),
+            Err(finding) => findings.push(finding),
+        }
+    }
+    let (machines, problems) = parse_invocations(all);
+    findings.extend(problems);
+    Ok((machines, findings))
+}
+
+fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_file() {
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path.to_path_buf());
+        }
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        let skip = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name == "target" || name.starts_with('.'));
+        if path.is_dir() && skip {
+            continue;
+        }
+        collect_sources(&path, files)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+//! `cargo state-machines`: lint `state_machine!` definitions and keep their
+//! Mermaid diagrams in sync.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use cargo_state_machines::{Finding, Lint, scan};
+
+const USAGE: &str = "\
+Usage:
+    cargo state-machines lint [PATH] [--diagrams DIR] [--allow LINT]...
+    cargo state-machines diagram [PATH] [--out DIR]
+
+Commands:
+    lint       Report problems in every state_machine! under PATH (default: .)
+    diagram    Write a Mermaid diagram per machine to DIR (default: PATH)
+
+Lints:
+    unreachable-state      a state can't be reached from `initial`
+    unguarded-dangerous    a `dangerous: true` event has an unguarded transition
+    no-terminal-state      every state has a transition out of it
+    contradictory-guards   a transition requires a guard to both pass and fail
+    duplicate-transition   two events repeat a transition under the same guards
+    diagram-drift          a diagram in --diagrams DIR is missing or stale
+    invalid-definition     the macro would reject the definition
+
+Exit status is 0 when clean, 1 when lints fire, and 2 on usage or I/O errors.";
+
+struct Options {
+    command: String,
+    path: PathBuf,
+    dir: Option<PathBuf>,
+    allow: HashSet<Lint>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let command = args.next().ok_or("missing command")?;
+    if command != "lint" && command != "diagram" {
+        return Err(format!("unknown command `{}`", command));
+    }
+
+    let mut options = Options {
+        command,
+        path: PathBuf::from("."),
+        dir: None,
+        allow: HashSet::new(),
+    };
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--diagrams" | "--out" => {
+                let expected = if options.command == "lint" {
+                    "--diagrams"
+                } else {
+                    "--out"
+                };
+                if arg != expected {
+                    return Err(format!(
+                        "`{}` is not an option of `{}`",
+                        arg, options.command
+                    ));
+                }
+                let dir = args.next().ok_or(format!("`{}` needs a directory", arg))?;
+                options.dir = Some(PathBuf::from(dir));
+            }
+            "--allow" if options.command == "lint" => {
+                let name = args.next().ok_or("`--allow` needs a lint name")?;
+                let lint = Lint::from_name(&name).ok_or(format!("unknown lint `{}`", name))?;
+                options.allow.insert(lint);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    if let Some(path) = path {
+        options.path = path;
+    }
+    Ok(options)
+}
+
+fn lint(options: &Options) -> Result<Vec<Finding>, String> {
+    let (machines, mut findings) =
+        scan(&options.path).map_err(|err| format!("{}: {}", options.path.display(), err))?;
+
+    for machine in &machines {
+        findings.extend(machine.lint());
+        if let Some(dir) = &options.dir {
+            let path = dir.join(machine.diagram_file());
+            let committed = fs::read_to_string(&path).ok();
+            findings.extend(machine.check_diagram(&path, committed.as_deref()));
+        }
+    }
+
+    findings.retain(|finding| !options.allow.contains(&finding.lint));
+    Ok(findings)
+}
+
+fn diagram(options: &Options) -> Result<(), String> {
+    let (machines, findings) =
+        scan(&options.path).map_err(|err| format!("{}: {}", options.path.display(), err))?;
+    for finding in &findings {
+        eprintln!("warning: {}", finding);
+    }
+
+    let dir = options.dir.as_ref().unwrap_or(&options.path);
+    for machine in &machines {
+        let path = dir.join(machine.diagram_file());
+        fs::write(&path, machine.to_mermaid())
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    // Cargo runs `cargo-state-machines state-machines <args>`
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "state-machines") {
+        args.next();
+    }
+    if args.peek().is_none_or(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if options.command == "diagram" {
+        return match diagram(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    match lint(&options) {
+        Ok(findings) if findings.is_empty() => ExitCode::SUCCESS,
+        Ok(findings) => {
+            for finding in &findings {
+                println!("{}", finding);
+            }
+            eprintln!("{} problem(s) found", findings.len());
+            ExitCode::from(1)
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_lint_defaults() {
+        let options = parse(&["lint"]).unwrap();
+        assert_eq!(options.command, "lint");
+        assert_eq!(options.path, PathBuf::from("."));
+        assert_eq!(options.dir, None);
+        assert!(options.allow.is_empty());
+    }
+
+    #[test]
+    fn test_lint_options() {
+        let options = parse(&[
+            "lint",
+            "src",
+            "--diagrams",
+            "docs",
+            "--allow",
+            "no-terminal-state",
+            "--allow",
+            "diagram-drift",
+        ])
+        .unwrap();
+        assert_eq!(options.path, PathBuf::from("src"));
+        assert_eq!(options.dir, Some(PathBuf::from("docs")));
+        assert_eq!(
+            options.allow,
+            HashSet::from([Lint::NoTerminalState, Lint::DiagramDrift])
+        );
+    }
+
+    #[test]
+    fn test_diagram_out() {
+        let options = parse(&["diagram", "--out", "docs", "src"]).unwrap();
+        assert_eq!(options.command, "diagram");
+        assert_eq!(options.path, PathBuf::from("src"));
+        assert_eq!(options.dir, Some(PathBuf::from("docs")));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |args: &[&str]| parse(args).err().unwrap();
+        assert_eq!(error(&[]), "missing command");
+        assert_eq!(error(&["check"]), "unknown command `check`");
+        assert_eq!(
+            error(&["lint", "--out", "docs"]),
+            "`--out` is not an option of `lint`"
+        );
+        assert_eq!(
+            error(&["diagram", "--diagrams", "docs"]),
+            "`--diagrams` is not an option of `diagram`"
+        );
+        assert_eq!(
+            error(&["lint", "--diagrams"]),
+            "`--diagrams` needs a directory"
+        );
+        assert_eq!(error(&["lint", "--allow"]), "`--allow` needs a lint name");
+        assert_eq!(
+            error(&["lint", "--allow", "everything"]),
+            "unknown lint `everything`"
+        );
+        assert_eq!(
+            error(&["diagram", "--allow", "diagram-drift"]),
+            "unknown option `--allow`"
+        );
+        assert_eq!(error(&["lint", "-v"]), "unknown option `-v`");
+        assert_eq!(
+            error(&["lint", "src", "tests"]),
+            "unexpected argument `tests`"
+        );
+    }
+}
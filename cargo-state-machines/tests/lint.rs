@@ -0,0 +1,444 @@
+use std::path::Path;
+
+use cargo_state_machines::{Lint, Machine, parse_source};
+
+fn parse_one(source: &str) -> Machine {
+    let (mut machines, findings) = parse_source(Path::new("src/lib.rs"), source);
+    assert!(findings.is_empty(), "{:?}", findings);
+    assert_eq!(machines.len(), 1);
+    machines.remove(0)
+}
+
+fn lints(machine: &Machine) -> Vec<(Lint, String)> {
+    machine
+        .lint()
+        .into_iter()
+        .map(|finding| (finding.lint, finding.message))
+        .collect()
+}
+
+const REACTOR: &str = r#"
+use state_machines::state_machine;
+
+state_machine! {
+    name: Reactor,
+    initial: Cold,
+    states: [Cold, Critical, Meltdown, Decommissioned],
+    events {
+        ignite {
+            transition: { from: Cold, to: Critical }
+        }
+        scram {
+            dangerous: true,
+            transition: { from: Critical, to: Cold }
+        }
+        vent {
+            dangerous: true,
+            guards: [pressure_high],
+            transition: { from: Critical, to: Cold }
+        }
+        overload {
+            transition: { from: Meltdown, to: Cold }
+        }
+    }
+}
+"#;
+
+#[test]
+fn finds_machines_anywhere_in_a_file() {
+    let source = r#"
+        mod nested {
+            fn build() {
+                let _ = 1;
+            }
+
+            state_machines::state_machine! {
+                name: Valve,
+                initial: Shut,
+                states: [Shut, Open],
+                events {
+                    open { transition: { from: Shut, to: Open } }
+                }
+            }
+        }
+    "#;
+    let machine = parse_one(source);
+    assert_eq!(machine.name(), "Valve");
+    assert_eq!(machine.line(), 7);
+    assert_eq!(machine.diagram_file(), "valve.mmd");
+}
+
+#[test]
+fn reports_unreachable_unguarded_and_missing_terminal_states() {
+    let machine = parse_one(REACTOR);
+    assert_eq!(
+        lints(&machine),
+        [
+            (
+                Lint::UnreachableState,
+                "state `Meltdown` of `Reactor` is unreachable from `Cold`".to_string()
+            ),
+            (
+                Lint::UnreachableState,
+                "state `Decommissioned` of `Reactor` is unreachable from `Cold`".to_string()
+            ),
+            (
+                Lint::UnguardedDangerous,
+                "dangerous event `scram` of `Reactor` has no guard on `Critical` -> `Cold`"
+                    .to_string()
+            ),
+        ]
+    );
+
+    let finding = &machine.lint()[2];
+    assert_eq!(finding.line, 12);
+    assert_eq!(
+        finding.to_string(),
+        "src/lib.rs:12: unguarded-dangerous: dangerous event `scram` of `Reactor` has no guard on `Critical` -> `Cold`"
+    );
+}
+
+#[test]
+fn machines_that_never_stop_have_no_terminal_state() {
+    let machine = parse_one(
+        r#"
+        state_machine! {
+            name: Blinker,
+            initial: On,
+            states: [On, Off],
+            events {
+                toggle {
+                    transition: { from: On, to: Off }
+                    transition: { from: Off, to: On }
+                }
+            }
+        }
+        "#,
+    );
+    assert_eq!(
+        lints(&machine),
+        [(
+            Lint::NoTerminalState,
+            "every state of `Blinker` has a transition out of it".to_string()
+        )]
+    );
+}
+
+#[test]
+fn transitions_that_need_a_guard_to_pass_and_fail_never_fire() {
+    let machine = parse_one(
+        r#"
+        state_machine! {
+            name: Door,
+            initial: Closed,
+            states: [Closed, Open, Locked],
+            events {
+                open {
+                    guards: [unlocked],
+                    transition: { from: Closed, to: Open, unless: [unlocked] }
+                }
+                lock {
+                    transition: { from: Closed, to: Locked, guards: [!has_key, has_key] }
+                }
+                close {
+                    transition: { from: Open, to: Closed, guards: [clear(1)], unless: [clear(2)] }
+                }
+            }
+        }
+        "#,
+    );
+    assert_eq!(
+        lints(&machine),
+        [
+            (
+                Lint::ContradictoryGuards,
+                "`open` of `Door` can never fire from `Closed`: guard `unlocked` must both pass and fail"
+                    .to_string()
+            ),
+            (
+                Lint::ContradictoryGuards,
+                "`lock` of `Door` can never fire from `Closed`: guard `has_key` must both pass and fail"
+                    .to_string()
+            ),
+        ]
+    );
+    // Spanned at the guard that contradicts an earlier one
+    assert_eq!(machine.lint()[0].line, 9);
+}
+
+#[test]
+fn events_repeating_a_transition_are_reported_once() {
+    let machine = parse_one(
+        r#"
+        state_machine! {
+            name: Player,
+            initial: Stopped,
+            states: [
+                Stopped,
+                Jammed,
+                superstate Active {
+                    state Playing,
+                    state Paused,
+                },
+            ],
+            events {
+                stop {
+                    guards: [loaded],
+                    transition: { from: Active, to: Stopped }
+                }
+                eject {
+                    transition: { from: Active, to: Stopped, guards: [loaded] }
+                }
+                halt {
+                    transition: { from: Active, to: Stopped, unless: [loaded] }
+                }
+                play {
+                    transition: { from: [Stopped, Paused], to: Playing }
+                }
+                pause {
+                    transition: { from: Playing, to: Paused }
+                }
+                jam {
+                    transition: { from: Stopped, to: Jammed }
+                }
+            }
+        }
+        "#,
+    );
+    assert_eq!(
+        lints(&machine),
+        [(
+            Lint::DuplicateTransition,
+            "`eject` of `Player` repeats `stop`'s `Playing` -> `Stopped` transition with the same guards"
+                .to_string()
+        )]
+    );
+}
+
+#[test]
+fn cfg_gated_entries_are_linted_too() {
+    let machine = parse_one(
+        r#"
+        state_machine! {
+            name: Pump,
+            initial: Idle,
+            states: [Idle, #[cfg(feature = "maintenance")] Servicing],
+            events {
+                #[cfg(feature = "maintenance")]
+                service {
+                    transition: { from: Idle, to: Servicing }
+                }
+            }
+        }
+        "#,
+    );
+    assert!(lints(&machine).is_empty());
+}
+
+#[test]
+fn superstates_are_flattened_in_diagrams_and_reachability() {
+    let machine = parse_one(
+        r#"
+        state_machine! {
+            name: Probe,
+            initial: Stowed,
+            states: [
+                Stowed,
+                superstate Deployed {
+                    state Scanning,
+                    state Sampling,
+                },
+                Lost,
+            ],
+            events {
+                deploy {
+                    transition: { from: Stowed, to: Deployed }
+                }
+                sample {
+                    unless: [low_power],
+                    transition: { from: Scanning, to: Sampling, guards: [on_surface] }
+                }
+                stow {
+                    transition: { from: Deployed, to: Stowed }
+                }
+            }
+        }
+        "#,
+    );
+    assert_eq!(
+        machine.to_mermaid(),
+        "\
+stateDiagram-v2
+    [*] --> Stowed
+    Stowed --> Scanning: deploy
+    Scanning --> Sampling: sample [on_surface, unless low_power]
+    Scanning --> Stowed: stow
+    Sampling --> Stowed: stow
+"
+    );
+    assert_eq!(
+        lints(&machine),
+        [(
+            Lint::UnreachableState,
+            "state `Lost` of `Probe` is unreachable from `Stowed`".to_string()
+        )]
+    );
+}
+
+#[test]
+fn diagram_drift_compares_against_the_committed_file() {
+    let machine = parse_one(REACTOR);
+    let path = Path::new("docs/reactor.mmd");
+    let current = machine.to_mermaid();
+
+    assert_eq!(machine.check_diagram(path, Some(&current)), None);
+    assert_eq!(
+        machine.check_diagram(path, Some(&current.replace('\n', "  \r\n"))),
+        None
+    );
+
+    let stale = machine
+        .check_diagram(path, Some("stateDiagram-v2\n    [*] --> Cold\n"))
+        .unwrap();
+    assert_eq!(stale.lint, Lint::DiagramDrift);
+    assert!(stale.message.contains("out of date"));
+
+    let missing = machine.check_diagram(path, None).unwrap();
+    assert_eq!(
+        missing.message,
+        "no committed diagram for `Reactor` at docs/reactor.mmd"
+    );
+}
+
+#[test]
+fn definitions_the_macro_rejects_are_reported() {
+    let (machines, findings) = parse_source(
+        Path::new("src/bad.rs"),
+        r#"
+        state_machine! {
+            name: Broken,
+            initial: Missing,
+            states: [Here],
+            events {}
+        }
+        "#,
+    );
+    assert!(machines.is_empty());
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].lint, Lint::InvalidDefinition);
+}
+
+#[test]
+fn extended_machines_are_linted_with_their_base() {
+    let (machines, findings) = parse_source(
+        Path::new("src/devices.rs"),
+        r#"
+        mod sensor {
+            state_machine! {
+                name: Sensor,
+                extends: super::Device,
+                states: [Calibrating],
+                events {
+                    booted {
+                        transition: { from: Booting, to: Calibrating }
+                    }
+                }
+            }
+        }
+
+        state_machine! {
+            name: Device,
+            extendable: true,
+            initial: Off,
+            states: [Off, Booting, Ready],
+            events {
+                power_on {
+                    transition: { from: Off, to: Booting }
+                }
+                booted {
+                    transition: { from: Booting, to: Ready }
+                }
+            }
+        }
+
+        state_machine! {
+            name: Lamp,
+            extends: Light,
+        }
+        "#,
+    );
+    let names: Vec<String> = machines.iter().map(Machine::name).collect();
+    assert_eq!(names, ["Sensor", "Device"]);
+    assert_eq!(
+        lints(&machines[0]),
+        [(
+            Lint::UnreachableState,
+            "state `Ready` of `Sensor` is unreachable from `Off`".to_string()
+        )]
+    );
+    assert_eq!(findings.len(), 1);
+    assert_eq!(
+        findings[0].message,
+        "`extends: Light` names no `extendable: true` machine"
+    );
+}
+
+#[test]
+fn included_fragments_are_linted_with_the_machine() {
+    let (machines, findings) = parse_source(
+        Path::new("src/jobs.rs"),
+        r#"
+        state_machine! {
+            name: Upload,
+            initial: Idle,
+            states: [Idle, Sending, Done],
+            include: [Retryable { prefix: Send }],
+            events {
+                send {
+                    transition: { from: Idle, to: Sending }
+                }
+                fail {
+                    transition: { from: Sending, to: SendFailed }
+                }
+            }
+        }
+
+        fragment! {
+            name: Retryable,
+            states: [Failed, Retrying],
+            events {
+                retry {
+                    transition: { from: Failed, to: Retrying }
+                }
+            }
+        }
+
+        state_machine! {
+            name: Lamp,
+            include: [Dimmable],
+        }
+        "#,
+    );
+    let names: Vec<String> = machines.iter().map(Machine::name).collect();
+    assert_eq!(names, ["Upload"]);
+    assert_eq!(
+        lints(&machines[0]),
+        [(
+            Lint::UnreachableState,
+            "state `Done` of `Upload` is unreachable from `Idle`".to_string()
+        )]
+    );
+    assert_eq!(findings.len(), 1);
+    assert_eq!(
+        findings[0].message,
+        "`include: Dimmable` names no `fragment!`"
+    );
+}
+
+#[test]
+fn lint_names_round_trip() {
+    for lint in Lint::ALL {
+        assert_eq!(Lint::from_name(lint.name()), Some(lint));
+    }
+    assert_eq!(Lint::from_name("unused-state"), None);
+}
@@ -0,0 +1,192 @@
+//! # Queue Consumer: Driving a Machine from a Broker
+//!
+//! This example teaches **queue-driven state machines** - feeding a dynamic
+//! machine from a message broker - using a cargo drone's delivery pipeline.
+//!
+//! ## The Problem: Events Arrive Out of Order
+//!
+//! Dispatch systems publish shipment events to a queue (Kafka, NATS
+//! JetStream, SQS...). The consumer must:
+//! - ✓ Decode each message into an event
+//! - ✓ Acknowledge events the machine accepted
+//! - ⚠️ Reject events the machine can't take *yet* so the broker redelivers them
+//! - ⚠️ Dead-letter events that will never be valid
+//!
+//! Every consumer ends up writing the same receive/dispatch/ack loop.
+//! `state_machines::source` provides it once.
+//!
+//! ## The Pieces
+//!
+//! ```rust,ignore
+//! impl EventSource for Consumer {
+//!     type Event = ShipmentEvent;
+//!     type Receipt = u64;          // offset, message handle, ...
+//!     type Error = BrokerError;
+//!
+//!     async fn next(&mut self) -> Result<Option<Delivery<..>>, BrokerError>;
+//!     async fn ack(&mut self, receipt: u64) -> Result<(), BrokerError>;
+//!     async fn nack(&mut self, receipt: u64, error: DynamicError) -> Result<(), BrokerError>;
+//! }
+//!
+//! let pumped = pump(&mut shipment, &mut consumer).await?;
+//! ```
+//!
+//! `pump` dispatches each delivery: accepted events are acked, and events
+//! the machine rejects (`InvalidTransition`, failed guards...) are nacked
+//! with the error. The source decides what a nack means - here, redeliver
+//! up to three times, then dead-letter.
+//!
+//! ## Caveats
+//!
+//! - Decoding belongs to the source: a message that isn't an event never
+//!   reaches the machine, so dead-letter it in `next()`.
+//! - Machines need `dynamic: true` and `persistent: true`, which implements
+//!   `DynamicMachine` for the dynamic wrapper.
+//! - Real brokers redeliver after a delay; this in-memory broker puts nacked
+//!   messages straight back at the end of the queue.
+
+use pollster::block_on;
+use state_machines::source::{Delivery, EventSource, pump};
+use state_machines::{DynamicError, state_machine};
+use std::collections::{HashMap, VecDeque};
+
+state_machine! {
+    name: Shipment,
+
+    dynamic: true,
+    persistent: true,  // ← implements DynamicMachine for DynamicShipment
+
+    initial: Booked,
+
+    states: [Booked, Loaded, InFlight, Delivered],
+
+    events {
+        load {
+            transition: { from: Booked, to: Loaded }
+        }
+        launch {
+            transition: { from: Loaded, to: InFlight }
+        }
+        land {
+            transition: { from: InFlight, to: Delivered }
+        }
+    }
+}
+
+/// How many times a rejected message is delivered before it's dead-lettered.
+const MAX_DELIVERIES: u32 = 3;
+
+/// A raw message on the broker.
+#[derive(Debug, Clone)]
+struct Message {
+    offset: u64,
+    body: &'static str,
+    deliveries: u32,
+}
+
+/// An in-memory stand-in for a Kafka topic or JetStream consumer.
+#[derive(Debug, Default)]
+struct Consumer {
+    queue: VecDeque<Message>,
+    in_flight: HashMap<u64, Message>,
+    dead_letters: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug)]
+struct BrokerError;
+
+impl Consumer {
+    fn publish(&mut self, body: &'static str) {
+        let offset = self.queue.len() as u64;
+        self.queue.push_back(Message {
+            offset,
+            body,
+            deliveries: 0,
+        });
+    }
+
+    fn decode(body: &str) -> Option<ShipmentEvent> {
+        match body {
+            r#"{"type":"load"}"# => Some(ShipmentEvent::Load),
+            r#"{"type":"launch"}"# => Some(ShipmentEvent::Launch),
+            r#"{"type":"land"}"# => Some(ShipmentEvent::Land),
+            _ => None,
+        }
+    }
+}
+
+impl EventSource for Consumer {
+    type Event = ShipmentEvent;
+    type Receipt = u64;
+    type Error = BrokerError;
+
+    async fn next(&mut self) -> Result<Option<Delivery<ShipmentEvent, u64>>, BrokerError> {
+        while let Some(mut message) = self.queue.pop_front() {
+            message.deliveries += 1;
+            let Some(event) = Self::decode(message.body) else {
+                println!("  ✗ #{} undecodable, dead-lettered", message.offset);
+                self.dead_letters
+                    .push((message.body, "undecodable".to_string()));
+                continue;
+            };
+            let receipt = message.offset;
+            println!(
+                "  → #{} {:?} (delivery {})",
+                receipt, event, message.deliveries
+            );
+            self.in_flight.insert(receipt, message);
+            return Ok(Some(Delivery { event, receipt }));
+        }
+        Ok(None)
+    }
+
+    async fn ack(&mut self, receipt: u64) -> Result<(), BrokerError> {
+        self.in_flight.remove(&receipt).ok_or(BrokerError)?;
+        println!("    ✓ acked");
+        Ok(())
+    }
+
+    async fn nack(&mut self, receipt: u64, error: DynamicError) -> Result<(), BrokerError> {
+        let message = self.in_flight.remove(&receipt).ok_or(BrokerError)?;
+        if message.deliveries < MAX_DELIVERIES {
+            println!("    ↻ nacked ({error:?}), redelivering");
+            self.queue.push_back(message);
+        } else {
+            println!("    ✗ nacked ({error:?}), dead-lettered");
+            self.dead_letters.push((message.body, format!("{error:?}")));
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    println!("=== Cargo Drone Shipment Consumer ===\n");
+
+    let mut consumer = Consumer::default();
+    // Published out of order, with a duplicate and a malformed message
+    consumer.publish(r#"{"type":"launch"}"#);
+    consumer.publish(r#"{"type":"load"}"#);
+    consumer.publish(r#"{"type":"lnad"}"#);
+    consumer.publish(r#"{"type":"land"}"#);
+    consumer.publish(r#"{"type":"load"}"#);
+
+    let mut shipment = DynamicShipment::new(());
+    println!("Shipment starts in: {}\n", shipment.current_state());
+
+    let pumped = block_on(pump(&mut shipment, &mut consumer)).expect("broker failed");
+
+    println!("\n--- Queue drained ---");
+    println!("Shipment is now: {}", shipment.current_state());
+    println!("Acked: {}, nacked: {}", pumped.acked, pumped.nacked);
+    println!("Dead letters:");
+    for (body, reason) in &consumer.dead_letters {
+        println!("  {body} - {reason}");
+    }
+
+    assert_eq!(shipment.current_state(), "Delivered");
+    assert_eq!(pumped.acked, 3);
+    assert_eq!(consumer.dead_letters.len(), 2);
+
+    println!("\n✓ Out-of-order events were redelivered until they applied");
+    println!("✓ The duplicate `load` was dead-lettered after {MAX_DELIVERIES} deliveries");
+}
@@ -0,0 +1,82 @@
+//! Markdown table of what each state of a [`MachineDefinition`] accepts.
+//!
+//! [`MachineDefinition::availability`] answers "what can I call now?" with
+//! one row per state and event, the guards that decide it, and where it
+//! leads:
+//!
+//! ```text
+//! | State | Event | Guards | Target |
+//! | --- | --- | --- | --- |
+//! | Pending | `pay` |  | Paid |
+//! | Pending | `cancel` |  | Cancelled |
+//! | Paid | `ship` | `in_stock`, `!on_hold` | Shipped |
+//! | Paid | `cancel` |  | Cancelled |
+//! | Shipped | — |  |  |
+//! | Cancelled | — |  |  |
+//! ```
+//!
+//! States without transitions get a single `—` row. Events that take a
+//! payload show its type, e.g. `` `telemetry(u32)` ``. The typestate
+//! machine's rustdoc carries the same table, so the documentation can't
+//! drift from the definition.
+
+use core::fmt::{self, Write};
+
+use crate::{MachineDefinition, MachineState, diagram};
+
+/// A markdown table of each state's events, guards, and targets; see the
+/// [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Availability<'a, S>
+where
+    S: MachineState,
+{
+    definition: &'a MachineDefinition<S>,
+}
+
+impl<S> MachineDefinition<S>
+where
+    S: MachineState + PartialEq,
+{
+    /// Tabulate which events each state accepts, as markdown.
+    pub fn availability(&self) -> Availability<'_, S> {
+        Availability { definition: self }
+    }
+}
+
+impl<S> fmt::Display for Availability<'_, S>
+where
+    S: MachineState + PartialEq,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("| State | Event | Guards | Target |\n")?;
+        f.write_str("| --- | --- | --- | --- |\n")?;
+
+        for state in self.definition.states {
+            let mut rows = 0;
+            for (event, transition) in diagram::edges(self.definition, state) {
+                rows += 1;
+                write!(f, "| {state:?} | `{}", event.name)?;
+                if let Some(payload) = event.payload {
+                    write!(f, "({payload})")?;
+                }
+                f.write_str("` | ")?;
+                for (index, (negated, guard)) in diagram::guards(event, transition).enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    f.write_char('`')?;
+                    if negated {
+                        f.write_char('!')?;
+                    }
+                    write!(f, "{guard}`")?;
+                }
+                writeln!(f, " | {:?} |", transition.target)?;
+            }
+            if rows == 0 {
+                writeln!(f, "| {state:?} | — |  |  |")?;
+            }
+        }
+        Ok(())
+    }
+}
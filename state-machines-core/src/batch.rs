@@ -0,0 +1,17 @@
+//! All-or-nothing dispatch of event sequences.
+
+use crate::DynamicError;
+
+/// What a dynamic machine's `handle_all()` returns: how many events it
+/// applied, or the event that failed the batch.
+pub type BatchResult = Result<usize, BatchError>;
+
+/// An event in a `handle_all()` batch failed, so the machine was put back
+/// the way it was before the batch, context included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    /// The failed event's position in the batch. The events before it had
+    /// been applied, and were rolled back with it.
+    pub index: usize,
+    pub error: DynamicError,
+}
@@ -0,0 +1,288 @@
+//! Guards and callbacks looked up by name at runtime.
+//!
+//! With `behavior: true`, a machine's guards and callbacks aren't inherent
+//! methods written by hand: the macro generates them to call a [`Behavior`]
+//! carried in the context, a [`WithBehavior`]. The topology stays fixed at
+//! compile time, while what each guard and callback does can be registered
+//! when the program runs, e.g. by plugins.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::fmt;
+#[cfg(feature = "alloc")]
+use core::ops::{Deref, DerefMut};
+
+/// Guards and callbacks by name, over the context `C`.
+///
+/// Names are the ones in the machine definition: `guards: [approved]` asks
+/// for `guard("approved", ctx)`, `after: [notify]` for
+/// `callback("notify", ctx)`. Global `before_transition` and
+/// `after_transition` callbacks go through [`callback`](Self::callback) too.
+///
+/// A guard the behavior doesn't have, per [`has_guard`](Self::has_guard),
+/// isn't evaluated: the transition is refused, whether the guard is in
+/// `guards` or `unless`.
+pub trait Behavior<C> {
+    /// Evaluate the guard `name`.
+    fn guard(&self, name: &str, ctx: &C) -> bool;
+
+    /// Run the callback `name`.
+    fn callback(&self, name: &str, ctx: &mut C);
+
+    /// Whether there is a guard named `name`.
+    fn has_guard(&self, name: &str) -> bool;
+
+    /// Whether there is a callback named `name`.
+    fn has_callback(&self, name: &str) -> bool;
+}
+
+/// The guards and callbacks a `behavior: true` machine looks up, generated
+/// as its `BEHAVIOR` constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BehaviorNames {
+    pub guards: &'static [&'static str],
+    /// Including global `before_transition` and `after_transition` callbacks.
+    pub callbacks: &'static [&'static str],
+}
+
+#[cfg(feature = "alloc")]
+impl BehaviorNames {
+    /// The names `behavior` has nothing for. Empty when it has them all.
+    pub fn missing<C>(&self, behavior: &(impl Behavior<C> + ?Sized)) -> MissingBehavior {
+        MissingBehavior {
+            guards: self
+                .guards
+                .iter()
+                .filter(|name| !behavior.has_guard(name))
+                .map(|&name| name.into())
+                .collect(),
+            callbacks: self
+                .callbacks
+                .iter()
+                .filter(|name| !behavior.has_callback(name))
+                .map(|&name| name.into())
+                .collect(),
+        }
+    }
+}
+
+/// The guards and callbacks a machine needs that a [`Behavior`] doesn't
+/// have, from [`BehaviorNames::missing`], [`WithBehavior::checked`], or
+/// `InterpretedMachine::new`.
+///
+/// Needs the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MissingBehavior {
+    pub guards: Vec<String>,
+    pub callbacks: Vec<String>,
+}
+
+#[cfg(feature = "alloc")]
+impl MissingBehavior {
+    pub fn is_empty(&self) -> bool {
+        self.guards.is_empty() && self.callbacks.is_empty()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for MissingBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lists = [("guards", &self.guards), ("callbacks", &self.callbacks)];
+        let mut first = true;
+        for (kind, names) in lists.into_iter().filter(|(_, names)| !names.is_empty()) {
+            f.write_str(if first { "missing " } else { "; missing " })?;
+            write!(f, "{kind}: ")?;
+            for (i, name) in names.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "`{name}`")?;
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// The context of a `behavior: true` machine: the user's context, and the
+/// [`Behavior`] its guards and callbacks call.
+///
+/// It derefs to the inner context. The behavior is shared, so one registry
+/// can serve every instance of a machine.
+///
+/// Needs the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct WithBehavior<C> {
+    pub ctx: C,
+    pub behavior: Arc<dyn Behavior<C> + Send + Sync>,
+}
+
+#[cfg(feature = "alloc")]
+impl<C> WithBehavior<C> {
+    pub fn new(ctx: C, behavior: Arc<dyn Behavior<C> + Send + Sync>) -> Self {
+        Self { ctx, behavior }
+    }
+
+    /// Like [`new`](Self::new), but fails if `behavior` lacks any of the
+    /// guards and callbacks in `names`, a machine's `BEHAVIOR` constant:
+    ///
+    /// ```rust,ignore
+    /// let ctx = WithBehavior::checked(claim, rules, &ClaimFlow::BEHAVIOR)?;
+    /// ```
+    pub fn checked(
+        ctx: C,
+        behavior: Arc<dyn Behavior<C> + Send + Sync>,
+        names: &BehaviorNames,
+    ) -> Result<Self, MissingBehavior> {
+        let missing = names.missing(&*behavior);
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        Ok(Self { ctx, behavior })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C: Clone> Clone for WithBehavior<C> {
+    fn clone(&self) -> Self {
+        Self {
+            ctx: self.ctx.clone(),
+            behavior: Arc::clone(&self.behavior),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C: fmt::Debug> fmt::Debug for WithBehavior<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithBehavior")
+            .field("ctx", &self.ctx)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> Deref for WithBehavior<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.ctx
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> DerefMut for WithBehavior<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.ctx
+    }
+}
+
+#[cfg(feature = "alloc")]
+type GuardFn<C> = Box<dyn Fn(&C) -> bool + Send + Sync>;
+#[cfg(feature = "alloc")]
+type CallbackFn<C> = Box<dyn Fn(&mut C) + Send + Sync>;
+
+/// A [`Behavior`] built from closures registered by name.
+///
+/// A guard that was never registered refuses the transition, and a
+/// callback that was never registered does nothing.
+///
+/// ```rust
+/// use state_machines_core::{Behavior, BehaviorRegistry};
+///
+/// let mut rules = BehaviorRegistry::<u32>::new();
+/// rules
+///     .register_guard("under_limit", |spent| *spent < 100)
+///     .register_callback("charge", |spent| *spent += 10);
+///
+/// let mut spent = 95;
+/// assert!(rules.guard("under_limit", &spent));
+/// rules.callback("charge", &mut spent);
+/// assert!(!rules.guard("under_limit", &spent));
+/// assert!(!rules.has_guard("unknown"));
+/// ```
+///
+/// Needs the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct BehaviorRegistry<C> {
+    guards: BTreeMap<String, GuardFn<C>>,
+    callbacks: BTreeMap<String, CallbackFn<C>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<C> BehaviorRegistry<C> {
+    pub fn new() -> Self {
+        Self {
+            guards: BTreeMap::new(),
+            callbacks: BTreeMap::new(),
+        }
+    }
+
+    /// Register the guard `name`, replacing any registered before.
+    pub fn register_guard(
+        &mut self,
+        name: impl Into<String>,
+        guard: impl Fn(&C) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.guards.insert(name.into(), Box::new(guard));
+        self
+    }
+
+    /// Register the callback `name`, replacing any registered before.
+    pub fn register_callback(
+        &mut self,
+        name: impl Into<String>,
+        callback: impl Fn(&mut C) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.callbacks.insert(name.into(), Box::new(callback));
+        self
+    }
+
+    /// Whether a guard or callback is registered as `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.guards.contains_key(name) || self.callbacks.contains_key(name)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> Default for BehaviorRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> Behavior<C> for BehaviorRegistry<C> {
+    fn guard(&self, name: &str, ctx: &C) -> bool {
+        self.guards.get(name).is_some_and(|guard| guard(ctx))
+    }
+
+    fn callback(&self, name: &str, ctx: &mut C) {
+        if let Some(callback) = self.callbacks.get(name) {
+            callback(ctx);
+        }
+    }
+
+    fn has_guard(&self, name: &str) -> bool {
+        self.guards.contains_key(name)
+    }
+
+    fn has_callback(&self, name: &str) -> bool {
+        self.callbacks.contains_key(name)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> fmt::Debug for BehaviorRegistry<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BehaviorRegistry")
+            .field("guards", &self.guards.keys())
+            .field("callbacks", &self.callbacks.keys())
+            .finish()
+    }
+}
@@ -0,0 +1,33 @@
+//! Time sources for the watchdog and transition records.
+//!
+//! Generated code reads the time through a [`Clock`] named by the machine's
+//! `clock:` key, so tests can swap the system clock for one they advance by
+//! hand. Timeouts and retry delays sleep through an
+//! [`AsyncTimer`](crate::AsyncTimer) instead, which a test clock can
+//! implement as well.
+
+use core::time::Duration;
+
+/// A monotonic time source.
+///
+/// The `std` feature of `state-machines` provides `SystemClock`, the
+/// default, and `TestClock`, which only moves when a test advances it:
+///
+/// ```rust,ignore
+/// state_machine! {
+///     name: Job,
+///     dynamic: true,
+///     watchdog: true,
+///     clock: TestClock,
+///     // ...
+/// }
+///
+/// let job = DynamicJob::new(());
+/// TestClock::advance(Duration::from_secs(90));
+/// assert_eq!(job.time_in_state(), Some(Duration::from_secs(90)));
+/// ```
+pub trait Clock {
+    /// The time since a fixed, arbitrary origin. Durations measured across
+    /// a step backwards come out as zero.
+    fn now() -> Duration;
+}
@@ -0,0 +1,298 @@
+//! Terminal rendering of a [`MachineDefinition`].
+//!
+//! [`MachineDefinition::diagram`] draws each state as a box, followed by the
+//! transitions leaving it, using box-drawing characters (or plain ASCII):
+//!
+//! ```text
+//! ┌───────────┐
+//! │ Pending   │ initial
+//! └─┬─────────┘
+//!   ├─ pay ─────▶ Paid
+//!   └─ cancel ──▶ Cancelled
+//! ╔═══════════╗
+//! ║ Paid      ║ current
+//! ╚═╤═════════╝
+//!   ├─ ship [in_stock, !on_hold] ──▶ Shipped
+//!   └─ cancel ─────────────────────▶ Cancelled
+//! ┌───────────┐
+//! │ Shipped   │
+//! └───────────┘
+//! ┌───────────┐
+//! │ Cancelled │
+//! └───────────┘
+//! ```
+//!
+//! That's `OrderState::definition().diagram().current(&OrderState::Paid)`.
+//! The current state gets a double border; `.ascii()` swaps in `+`, `-`
+//! and `|`, with `#` and `=` for the current state.
+//!
+//! Rendering goes through `Display` and allocates nothing, so it works in
+//! `no_std` logs as well as CLI tools. States are named by their `Debug`
+//! output.
+
+use core::fmt::{self, Write};
+
+use crate::{EventDefinition, MachineDefinition, MachineState, TransitionDefinition};
+
+/// A drawing of a machine's states and transitions; see the
+/// [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Diagram<'a, S>
+where
+    S: MachineState,
+{
+    definition: &'a MachineDefinition<S>,
+    current: Option<&'a S>,
+    ascii: bool,
+}
+
+impl<S> MachineDefinition<S>
+where
+    S: MachineState + PartialEq,
+{
+    /// Draw the machine with box-drawing characters.
+    pub fn diagram(&self) -> Diagram<'_, S> {
+        Diagram {
+            definition: self,
+            current: None,
+            ascii: false,
+        }
+    }
+}
+
+impl<'a, S> Diagram<'a, S>
+where
+    S: MachineState + PartialEq,
+{
+    /// Highlight `state`, e.g. a live machine's current state.
+    pub fn current(mut self, state: &'a S) -> Self {
+        self.current = Some(state);
+        self
+    }
+
+    /// Draw with `+`, `-`, `|` and `>` only, for terminals and logs that
+    /// can't show box-drawing characters.
+    pub fn ascii(mut self) -> Self {
+        self.ascii = true;
+        self
+    }
+
+    fn glyphs(&self, highlighted: bool) -> &'static Glyphs {
+        match (self.ascii, highlighted) {
+            (false, false) => &Glyphs::SINGLE,
+            (false, true) => &Glyphs::DOUBLE,
+            (true, false) => &Glyphs::ASCII,
+            (true, true) => &Glyphs::ASCII_BOLD,
+        }
+    }
+}
+
+/// The transitions leaving `state`, with their events.
+pub(crate) fn edges<'d, S>(
+    definition: &'d MachineDefinition<S>,
+    state: &'d S,
+) -> impl Iterator<Item = (&'d EventDefinition<S>, &'d TransitionDefinition<S>)> + 'd
+where
+    S: MachineState + PartialEq,
+{
+    definition.events.iter().flat_map(move |event| {
+        event
+            .transitions
+            .iter()
+            .filter(move |transition| transition.sources.contains(state))
+            .map(move |transition| (event, transition))
+    })
+}
+
+/// The guards a transition checks, `unless` guards last and flagged `true`.
+pub(crate) fn guards<'d, S: MachineState>(
+    event: &'d EventDefinition<S>,
+    transition: &'d TransitionDefinition<S>,
+) -> impl Iterator<Item = (bool, &'static str)> + 'd {
+    event
+        .guards
+        .iter()
+        .chain(transition.guards)
+        .map(|guard| (false, *guard))
+        .chain(transition.unless.iter().map(|guard| (true, *guard)))
+}
+
+impl<S> fmt::Display for Diagram<'_, S>
+where
+    S: MachineState + PartialEq,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .definition
+            .states
+            .iter()
+            .map(|state| width(|w| write!(w, "{state:?}")))
+            .max()
+            .unwrap_or(0);
+
+        for state in self.definition.states {
+            let highlighted = self.current == Some(state);
+            let glyphs = self.glyphs(highlighted);
+            let edge_count = edges(self.definition, state).count();
+
+            // The box, padded to the longest state name
+            f.write_str(glyphs.top_left)?;
+            repeat(f, glyphs.horizontal, name_width + 2)?;
+            writeln!(f, "{}", glyphs.top_right)?;
+
+            write!(f, "{} {state:?}", glyphs.vertical)?;
+            repeat(f, " ", name_width - width(|w| write!(w, "{state:?}")))?;
+            write!(f, " {}", glyphs.vertical)?;
+            let mut tags = [
+                (*state == self.definition.initial, "initial"),
+                (highlighted, "current"),
+            ]
+            .into_iter()
+            .filter_map(|(applies, tag)| applies.then_some(tag));
+            if let Some(tag) = tags.next() {
+                write!(f, " {tag}")?;
+                for tag in tags {
+                    write!(f, ", {tag}")?;
+                }
+            }
+            f.write_char('\n')?;
+
+            f.write_str(glyphs.bottom_left)?;
+            if edge_count == 0 {
+                repeat(f, glyphs.horizontal, name_width + 2)?;
+            } else {
+                f.write_str(glyphs.horizontal)?;
+                f.write_str(glyphs.tee)?;
+                repeat(f, glyphs.horizontal, name_width)?;
+            }
+            writeln!(f, "{}", glyphs.bottom_right)?;
+
+            // One branch per transition, arrows aligned on the longest label
+            let label_width = edges(self.definition, state)
+                .map(|(event, transition)| width(|w| label(w, event, transition)))
+                .max()
+                .unwrap_or(0);
+            let glyphs = self.glyphs(false);
+            for (index, (event, transition)) in edges(self.definition, state).enumerate() {
+                let branch = if index + 1 == edge_count {
+                    glyphs.last_branch
+                } else {
+                    glyphs.branch
+                };
+                write!(f, "  {branch}{} ", glyphs.horizontal)?;
+                label(f, event, transition)?;
+                f.write_char(' ')?;
+                let dashes = label_width - width(|w| label(w, event, transition)) + 2;
+                repeat(f, glyphs.horizontal, dashes)?;
+                writeln!(f, "{} {:?}", glyphs.arrow, transition.target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An event name with the guards its transition checks, e.g.
+/// `ship [in_stock, !on_hold]`.
+fn label<S: MachineState>(
+    w: &mut impl Write,
+    event: &EventDefinition<S>,
+    transition: &TransitionDefinition<S>,
+) -> fmt::Result {
+    w.write_str(event.name)?;
+    let mut first = true;
+    for (negated, guard) in guards(event, transition) {
+        w.write_str(if first { " [" } else { ", " })?;
+        first = false;
+        if negated {
+            w.write_char('!')?;
+        }
+        w.write_str(guard)?;
+    }
+    if !first {
+        w.write_char(']')?;
+    }
+    Ok(())
+}
+
+/// How many characters `draw` writes.
+fn width(draw: impl FnOnce(&mut Width) -> fmt::Result) -> usize {
+    let mut counter = Width(0);
+    let _ = draw(&mut counter);
+    counter.0
+}
+
+struct Width(usize);
+
+impl Write for Width {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.chars().count();
+        Ok(())
+    }
+}
+
+fn repeat(w: &mut impl Write, s: &str, times: usize) -> fmt::Result {
+    (0..times).try_for_each(|_| w.write_str(s))
+}
+
+struct Glyphs {
+    top_left: &'static str,
+    top_right: &'static str,
+    bottom_left: &'static str,
+    bottom_right: &'static str,
+    horizontal: &'static str,
+    vertical: &'static str,
+    tee: &'static str,
+    branch: &'static str,
+    last_branch: &'static str,
+    arrow: &'static str,
+}
+
+impl Glyphs {
+    const SINGLE: Glyphs = Glyphs {
+        top_left: "┌",
+        top_right: "┐",
+        bottom_left: "└",
+        bottom_right: "┘",
+        horizontal: "─",
+        vertical: "│",
+        tee: "┬",
+        branch: "├",
+        last_branch: "└",
+        arrow: "▶",
+    };
+
+    const DOUBLE: Glyphs = Glyphs {
+        top_left: "╔",
+        top_right: "╗",
+        bottom_left: "╚",
+        bottom_right: "╝",
+        horizontal: "═",
+        vertical: "║",
+        tee: "╤",
+        ..Glyphs::SINGLE
+    };
+
+    const ASCII: Glyphs = Glyphs {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        horizontal: "-",
+        vertical: "|",
+        tee: "+",
+        branch: "|",
+        last_branch: "`",
+        arrow: ">",
+    };
+
+    const ASCII_BOLD: Glyphs = Glyphs {
+        top_left: "#",
+        top_right: "#",
+        bottom_left: "#",
+        bottom_right: "#",
+        horizontal: "=",
+        vertical: "#",
+        tee: "+",
+        ..Glyphs::ASCII
+    };
+}
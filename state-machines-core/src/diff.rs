@@ -0,0 +1,173 @@
+//! Comparing two versions of a [`MachineDefinition`].
+//!
+//! Long-lived workflows outlive deployments: a snapshot taken by one version
+//! of a machine may be restored by the next. [`MachineDefinition::diff`]
+//! lists what changed between two definitions, so a rolling upgrade can
+//! refuse snapshots in removed states, and CI can compare a machine against
+//! a baseline kept in the code base, e.g. the previous version in a `v1`
+//! module:
+//!
+//! ```text
+//! - state Shipped
+//! + state Dispatched
+//! + event dispatch
+//! - transition ship: Paid -> Shipped
+//! + transition dispatch: Paid -> Dispatched
+//! ```
+//!
+//! States are compared by name, so the two definitions don't have to share a
+//! state type. Transitions are compared as `(event, from, to)` triples, with
+//! superstates expanded. Needs the `alloc` feature.
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{MachineDefinition, MachineState};
+
+/// One `(event, from, to)` edge of a definition, with states named.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TransitionChange {
+    pub event: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+impl fmt::Display for TransitionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.event, self.from, self.to)
+    }
+}
+
+/// What changed from one definition to another; see the
+/// [module docs](self).
+///
+/// `Display` prints one `+` or `-` line per change, removals first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefinitionDiff {
+    pub added_states: Vec<String>,
+    pub removed_states: Vec<String>,
+    pub added_events: Vec<&'static str>,
+    pub removed_events: Vec<&'static str>,
+    pub added_transitions: Vec<TransitionChange>,
+    pub removed_transitions: Vec<TransitionChange>,
+}
+
+impl DefinitionDiff {
+    /// Whether the definitions describe the same machine.
+    pub fn is_empty(&self) -> bool {
+        self.added_states.is_empty()
+            && self.removed_states.is_empty()
+            && self.added_events.is_empty()
+            && self.removed_events.is_empty()
+            && self.added_transitions.is_empty()
+            && self.removed_transitions.is_empty()
+    }
+
+    /// Whether everything the older definition could do, the newer one can
+    /// too: nothing was removed, only added.
+    pub fn is_backward_compatible(&self) -> bool {
+        self.removed_states.is_empty()
+            && self.removed_events.is_empty()
+            && self.removed_transitions.is_empty()
+    }
+
+    /// Whether a snapshot taken in `state` by the older definition names a
+    /// state the newer one still has.
+    pub fn can_restore(&self, state: &str) -> bool {
+        !self.removed_states.iter().any(|removed| removed == state)
+    }
+}
+
+impl fmt::Display for DefinitionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for state in &self.removed_states {
+            writeln!(f, "- state {state}")?;
+        }
+        for state in &self.added_states {
+            writeln!(f, "+ state {state}")?;
+        }
+        for event in &self.removed_events {
+            writeln!(f, "- event {event}")?;
+        }
+        for event in &self.added_events {
+            writeln!(f, "+ event {event}")?;
+        }
+        for transition in &self.removed_transitions {
+            writeln!(f, "- transition {transition}")?;
+        }
+        for transition in &self.added_transitions {
+            writeln!(f, "+ transition {transition}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> MachineDefinition<S>
+where
+    S: MachineState,
+{
+    /// What changed from this definition to `newer`.
+    pub fn diff<T>(&self, newer: &MachineDefinition<T>) -> DefinitionDiff
+    where
+        T: MachineState,
+    {
+        let (old_states, new_states) = (state_names(self), state_names(newer));
+        let (old_events, new_events) = (event_names(self), event_names(newer));
+        let (old_edges, new_edges) = (edges(self), edges(newer));
+
+        DefinitionDiff {
+            added_states: missing_from(&new_states, &old_states),
+            removed_states: missing_from(&old_states, &new_states),
+            added_events: missing_from(&new_events, &old_events),
+            removed_events: missing_from(&old_events, &new_events),
+            added_transitions: missing_from(&new_edges, &old_edges),
+            removed_transitions: missing_from(&old_edges, &new_edges),
+        }
+    }
+}
+
+/// The entries of `items` that `other` lacks, in order.
+fn missing_from<T: Clone + PartialEq>(items: &[T], other: &[T]) -> Vec<T> {
+    items
+        .iter()
+        .filter(|item| !other.contains(item))
+        .cloned()
+        .collect()
+}
+
+/// Definitions only exist for fieldless state enums, so `Debug` prints the
+/// variant name.
+fn state_names<S: MachineState>(definition: &MachineDefinition<S>) -> Vec<String> {
+    definition
+        .states
+        .iter()
+        .map(|state| format!("{state:?}"))
+        .collect()
+}
+
+fn event_names<S: MachineState>(definition: &MachineDefinition<S>) -> Vec<&'static str> {
+    definition.events.iter().map(|event| event.name).collect()
+}
+
+fn edges<S: MachineState>(definition: &MachineDefinition<S>) -> Vec<TransitionChange> {
+    let mut edges: Vec<TransitionChange> = Vec::new();
+    for event in definition.events {
+        for transition in event.transitions {
+            for source in transition.sources {
+                let edge = TransitionChange {
+                    event: event.name,
+                    from: format!("{source:?}"),
+                    to: format!("{:?}", transition.target),
+                };
+                if !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+    }
+    edges
+}
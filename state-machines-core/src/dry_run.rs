@@ -0,0 +1,116 @@
+//! Dry runs of an event sequence against a dynamic machine.
+//!
+//! Needs the `alloc` feature.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use crate::DynamicError;
+
+/// What one event of a dry run would do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DryRunOutcome<S> {
+    /// The event's guards pass and it moves the machine to `to`.
+    Transition { to: S },
+    /// The state ignores the event, or the machine's `unhandled` policy
+    /// accepts it without a transition.
+    Ignored,
+    /// The event would fail, leaving the machine where it is.
+    Rejected(DynamicError),
+}
+
+/// One event of a dry run and the state it was dispatched in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRunStep<S> {
+    pub event: &'static str,
+    pub from: S,
+    pub outcome: DryRunOutcome<S>,
+}
+
+/// The result of `simulate()` on a `Dynamic{Name}` machine: which
+/// transitions an event sequence would fire, without running callbacks or
+/// actions.
+///
+/// ```rust
+/// use state_machines_core::{DryRunOutcome, DryRunReport, DynamicError};
+///
+/// let mut report = DryRunReport::new("Draft");
+/// report.push("submit", "Draft", DryRunOutcome::Transition { to: "Review" });
+/// report.push(
+///     "publish",
+///     "Review",
+///     DryRunOutcome::Rejected(DynamicError::guard_failed("approved", "publish")),
+/// );
+///
+/// assert_eq!(report.final_state(), &"Review");
+/// assert_eq!(report.fired().collect::<Vec<_>>(), [(&"Draft", "submit", &"Review")]);
+/// assert!(!report.all_accepted());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRunReport<S> {
+    initial: S,
+    steps: Vec<DryRunStep<S>>,
+}
+
+impl<S> DryRunReport<S> {
+    /// An empty report for a run starting in `initial`.
+    pub fn new(initial: S) -> Self {
+        Self {
+            initial,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Record the outcome of `event`, dispatched in `from`.
+    pub fn push(&mut self, event: &'static str, from: S, outcome: DryRunOutcome<S>) {
+        self.steps.push(DryRunStep {
+            event,
+            from,
+            outcome,
+        });
+    }
+
+    /// The state the run started in.
+    pub fn initial_state(&self) -> &S {
+        &self.initial
+    }
+
+    /// The state the machine would end up in.
+    pub fn final_state(&self) -> &S {
+        self.steps
+            .iter()
+            .rev()
+            .find_map(|step| match &step.outcome {
+                DryRunOutcome::Transition { to } => Some(to),
+                _ => None,
+            })
+            .unwrap_or(&self.initial)
+    }
+
+    /// Every event in order, with what it would do.
+    pub fn steps(&self) -> &[DryRunStep<S>] {
+        &self.steps
+    }
+
+    /// The transitions that would fire, as `(from, event, to)`.
+    pub fn fired(&self) -> impl Iterator<Item = (&S, &'static str, &S)> + '_ {
+        self.steps.iter().filter_map(|step| match &step.outcome {
+            DryRunOutcome::Transition { to } => Some((&step.from, step.event, to)),
+            _ => None,
+        })
+    }
+
+    /// The first event that would fail, and why.
+    pub fn first_rejection(&self) -> Option<(&'static str, &DynamicError)> {
+        self.steps.iter().find_map(|step| match &step.outcome {
+            DryRunOutcome::Rejected(err) => Some((step.event, err)),
+            _ => None,
+        })
+    }
+
+    /// Whether no event would fail.
+    pub fn all_accepted(&self) -> bool {
+        self.first_rejection().is_none()
+    }
+}
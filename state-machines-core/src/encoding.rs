@@ -0,0 +1,181 @@
+//! Compact binary snapshots for flash and EEPROM.
+//!
+//! With the `postcard` feature, a [`Snapshot`] encodes into a few bytes
+//! without allocating:
+//!
+//! ```text
+//! [format: u8] [len: u8] [postcard (state, version): len bytes] [crc32: 4 bytes, LE]
+//! ```
+//!
+//! The format byte lets a later release change the layout and still
+//! recognise old records, and the CRC-32 (ISO-HDLC, as used by zlib) covers
+//! everything before it, so a torn write or erased flash decodes to an error
+//! instead of a wrong state. The state enum needs `serde` derives, e.g.
+//! `state_derives: [serde::Serialize, serde::Deserialize]`. postcard stores
+//! variants by position, so add new states at the end of `states` to keep
+//! old records readable.
+//!
+//! The `embedded-storage` feature adds [`Snapshot::persist_to`] and
+//! [`Snapshot::load_from`] for any `embedded_storage` driver.
+
+use core::fmt;
+
+use crc::{CRC_32_ISO_HDLC, Crc};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{MachineState, Snapshot};
+
+/// The layout written by [`Snapshot::encode`].
+pub const SNAPSHOT_FORMAT: u8 = 1;
+
+/// The most bytes an encoded snapshot takes, header and checksum included.
+///
+/// Fieldless states and the version take at most 15 of them.
+pub const MAX_SNAPSHOT_LEN: usize = 64;
+
+const HEADER_LEN: usize = 2;
+const CRC_LEN: usize = 4;
+const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// Why a snapshot couldn't be encoded or decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotCodecError {
+    /// The buffer can't hold the encoded snapshot.
+    BufferTooSmall,
+    /// The record starts with a format byte this version doesn't know, e.g.
+    /// erased flash or a record from a newer release.
+    UnknownFormat(u8),
+    /// The checksum doesn't match: the record is truncated or damaged.
+    Corrupted,
+    /// The checksum matches, but the bytes aren't a snapshot of this state
+    /// type.
+    Malformed,
+}
+
+impl fmt::Display for SnapshotCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferTooSmall => f.write_str("buffer too small for the snapshot"),
+            Self::UnknownFormat(format) => write!(f, "unknown snapshot format {format}"),
+            Self::Corrupted => f.write_str("snapshot checksum mismatch"),
+            Self::Malformed => f.write_str("snapshot doesn't match the state type"),
+        }
+    }
+}
+
+/// Why [`Snapshot::persist_to`] or [`Snapshot::load_from`] failed.
+#[cfg(feature = "embedded-storage")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistError<E> {
+    /// The storage driver failed.
+    Storage(E),
+    Codec(SnapshotCodecError),
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<E> From<SnapshotCodecError> for PersistError<E> {
+    fn from(error: SnapshotCodecError) -> Self {
+        Self::Codec(error)
+    }
+}
+
+impl<S> Snapshot<S>
+where
+    S: MachineState + Serialize,
+{
+    /// Write the snapshot into `buf`, returning how many bytes it took.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, SnapshotCodecError> {
+        if buf.len() < HEADER_LEN + CRC_LEN {
+            return Err(SnapshotCodecError::BufferTooSmall);
+        }
+        let payload_end = buf.len().min(MAX_SNAPSHOT_LEN) - CRC_LEN;
+        let payload = postcard::to_slice(
+            &(&self.state, self.version),
+            &mut buf[HEADER_LEN..payload_end],
+        )
+        .map_err(|_| SnapshotCodecError::BufferTooSmall)?;
+        let len = HEADER_LEN + payload.len();
+
+        buf[0] = SNAPSHOT_FORMAT;
+        buf[1] = (len - HEADER_LEN) as u8;
+        let crc = CRC.checksum(&buf[..len]);
+        buf[len..len + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+        Ok(len + CRC_LEN)
+    }
+
+    /// Encode the snapshot and write it to `storage` at `offset`.
+    ///
+    /// Erase the region first if the storage needs it; `embedded_storage`'s
+    /// `Storage` implementations usually handle that themselves.
+    #[cfg(feature = "embedded-storage")]
+    pub fn persist_to<St>(
+        &self,
+        storage: &mut St,
+        offset: u32,
+    ) -> Result<(), PersistError<St::Error>>
+    where
+        St: embedded_storage::Storage,
+    {
+        let mut buf = [0; MAX_SNAPSHOT_LEN];
+        let len = self.encode(&mut buf)?;
+        storage
+            .write(offset, &buf[..len])
+            .map_err(PersistError::Storage)
+    }
+}
+
+impl<S> Snapshot<S>
+where
+    S: MachineState + DeserializeOwned,
+{
+    /// Read a snapshot written by [`encode`](Self::encode). Bytes after the
+    /// checksum are ignored.
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotCodecError> {
+        let len = record_len(bytes)?;
+        let Some(record) = bytes.get(..len) else {
+            return Err(SnapshotCodecError::Corrupted);
+        };
+
+        let (body, crc) = record.split_at(len - CRC_LEN);
+        if CRC.checksum(body).to_le_bytes() != crc {
+            return Err(SnapshotCodecError::Corrupted);
+        }
+        let (state, version) = postcard::from_bytes::<(S, u64)>(&body[HEADER_LEN..])
+            .map_err(|_| SnapshotCodecError::Malformed)?;
+        Ok(Self { state, version })
+    }
+
+    /// Read a snapshot that [`persist_to`](Self::persist_to) wrote at
+    /// `offset`.
+    #[cfg(feature = "embedded-storage")]
+    pub fn load_from<St>(storage: &mut St, offset: u32) -> Result<Self, PersistError<St::Error>>
+    where
+        St: embedded_storage::ReadStorage,
+    {
+        let mut buf = [0; MAX_SNAPSHOT_LEN];
+        storage
+            .read(offset, &mut buf[..HEADER_LEN])
+            .map_err(PersistError::Storage)?;
+        let len = record_len(&buf)?;
+        storage
+            .read(offset, &mut buf[..len])
+            .map_err(PersistError::Storage)?;
+        Ok(Self::decode(&buf[..len])?)
+    }
+}
+
+/// The length of the record starting at `bytes`, from its header.
+fn record_len(bytes: &[u8]) -> Result<usize, SnapshotCodecError> {
+    match bytes {
+        [SNAPSHOT_FORMAT, payload, ..] => {
+            let len = HEADER_LEN + usize::from(*payload) + CRC_LEN;
+            if len > MAX_SNAPSHOT_LEN {
+                return Err(SnapshotCodecError::Corrupted);
+            }
+            Ok(len)
+        }
+        [] | [SNAPSHOT_FORMAT] => Err(SnapshotCodecError::Corrupted),
+        [format, ..] => Err(SnapshotCodecError::UnknownFormat(*format)),
+    }
+}
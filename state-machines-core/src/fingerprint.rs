@@ -0,0 +1,118 @@
+//! A stable hash of a [`MachineDefinition`]'s structure.
+//!
+//! Tools that derive something from a machine, such as a diagram, generated
+//! tests, or a migration, can key a cache by [`MachineDefinition::fingerprint`]
+//! and regenerate only when it changes. Unlike `Hash` with a `std` hasher,
+//! the value doesn't change between runs, builds, or platforms, so it can be
+//! stored; it changes whenever the definitions would compare unequal (up to
+//! hash collisions).
+//!
+//! States are hashed by name, so a definition's fingerprint doesn't depend
+//! on the generated state type.
+
+use core::fmt::{self, Write};
+
+use crate::{MachineDefinition, MachineState};
+
+/// 64-bit FNV-1a.
+struct Fnv(u64);
+
+impl Fnv {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// A string, ended by a byte UTF-8 never contains, so adjacent strings
+    /// can't run together.
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+        self.bytes(&[0xff]);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.bytes(&(len as u64).to_le_bytes());
+    }
+
+    fn strs(&mut self, values: &[&str]) {
+        self.len(values.len());
+        for value in values {
+            self.str(value);
+        }
+    }
+
+    /// A state by variant name, which is what `Debug` prints for the
+    /// fieldless enums definitions are generated for.
+    fn state<S: MachineState>(&mut self, state: &S) {
+        // Writing to the hasher can't fail
+        let _ = write!(self, "{state:?}");
+        self.bytes(&[0xff]);
+    }
+
+    fn states<S: MachineState>(&mut self, states: &[S]) {
+        self.len(states.len());
+        for state in states {
+            self.state(state);
+        }
+    }
+}
+
+impl Write for Fnv {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<S> MachineDefinition<S>
+where
+    S: MachineState,
+{
+    /// A stable hash of the definition; see the [module docs](self).
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv(Fnv::OFFSET);
+        hash.str(self.name);
+        hash.states(self.states);
+        hash.state(&self.initial);
+        hash.bytes(&[u8::from(self.async_mode)]);
+
+        hash.len(self.superstates.len());
+        for superstate in self.superstates {
+            hash.str(superstate.name);
+            hash.states(superstate.descendants);
+            hash.state(&superstate.initial);
+        }
+
+        hash.len(self.events.len());
+        for event in self.events {
+            hash.str(event.name);
+            hash.strs(event.guards);
+            hash.strs(event.before);
+            hash.strs(event.after);
+            hash.strs(event.around);
+            match event.payload {
+                Some(payload) => {
+                    hash.bytes(&[1]);
+                    hash.str(payload);
+                }
+                None => hash.bytes(&[0]),
+            }
+            hash.len(event.transitions.len());
+            for transition in event.transitions {
+                hash.states(transition.sources);
+                hash.state(&transition.target);
+                hash.strs(transition.guards);
+                hash.strs(transition.unless);
+                hash.strs(transition.before);
+                hash.strs(transition.after);
+                hash.strs(transition.around);
+            }
+        }
+        hash.0
+    }
+}
@@ -0,0 +1,188 @@
+//! Graph queries over a [`MachineDefinition`].
+//!
+//! The definition lists transitions per event; these methods walk it as a
+//! directed graph of states instead, so tests can assert structural properties
+//! ("every state can reach `Shutdown`") and tools can derive event sequences
+//! without rebuilding the adjacency by hand. Guards are not evaluated: an edge
+//! exists whenever some transition could fire.
+//!
+//! Everything except [`MachineDefinition::transitions_from`] needs the `alloc`
+//! feature.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{collections::VecDeque, vec, vec::Vec};
+
+use crate::{MachineDefinition, MachineState};
+
+impl<S> MachineDefinition<S>
+where
+    S: MachineState + PartialEq,
+{
+    /// The `(event, target)` pairs leaving `state`, in declaration order.
+    pub fn transitions_from<'a>(
+        &'a self,
+        state: &'a S,
+    ) -> impl Iterator<Item = (&'static str, &'static S)> + 'a {
+        self.events.iter().flat_map(move |event| {
+            event
+                .transitions
+                .iter()
+                .filter(move |transition| transition.sources.contains(state))
+                .map(move |transition| (event.name, &transition.target))
+        })
+    }
+
+    /// Whether some sequence of events leads from `from` to `to`.
+    ///
+    /// A state always reaches itself.
+    #[cfg(feature = "alloc")]
+    pub fn can_reach(&self, from: &S, to: &S) -> bool {
+        self.shortest_event_sequence(from, to).is_some()
+    }
+
+    /// The shortest sequence of events leading from `from` to `to`, or `None`
+    /// if `to` is unreachable.
+    ///
+    /// Ties are broken by declaration order, so the result is stable. The
+    /// sequence is empty when `from == to`.
+    #[cfg(feature = "alloc")]
+    pub fn shortest_event_sequence(&self, from: &S, to: &S) -> Option<Vec<&'static str>> {
+        let start = self.index_of(from)?;
+        let goal = self.index_of(to)?;
+
+        // `previous[i]` is the state and event that first reached state `i`
+        let mut previous: Vec<Option<(usize, &'static str)>> = vec![None; self.states.len()];
+        let mut visited = vec![false; self.states.len()];
+        let mut queue = VecDeque::new();
+        visited[start] = true;
+        queue.push_back(start);
+
+        while let Some(current) = queue.pop_front() {
+            if current == goal {
+                let mut events = Vec::new();
+                let mut at = goal;
+                while let Some((prev, event)) = previous[at] {
+                    events.push(event);
+                    at = prev;
+                }
+                events.reverse();
+                return Some(events);
+            }
+            for (event, next) in self.successors(current) {
+                if !visited[next] {
+                    visited[next] = true;
+                    previous[next] = Some((current, event));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Every event sequence leading from `from` to `to` without visiting a
+    /// state twice, in declaration order.
+    ///
+    /// Two events between the same pair of states yield two paths. When
+    /// `from == to` the only path is the empty one; see [`cycles`] for the
+    /// loops through a state. The number of paths grows quickly with the size
+    /// of the machine, so this is meant for tests and tooling.
+    ///
+    /// [`cycles`]: MachineDefinition::cycles
+    #[cfg(feature = "alloc")]
+    pub fn paths_between(&self, from: &S, to: &S) -> Vec<Vec<&'static str>> {
+        let (Some(start), Some(goal)) = (self.index_of(from), self.index_of(to)) else {
+            return Vec::new();
+        };
+
+        let mut paths = Vec::new();
+        let mut on_path = vec![false; self.states.len()];
+        let mut events = Vec::new();
+        self.collect_paths(start, goal, &mut on_path, &mut events, &mut paths);
+        paths
+    }
+
+    /// Every elementary cycle, as the states visited in order.
+    ///
+    /// Each cycle starts at its earliest-declared state and doesn't repeat it
+    /// at the end; a self-transition is a cycle of one state. Cycles that
+    /// differ only in the events taken are reported once.
+    #[cfg(feature = "alloc")]
+    pub fn cycles(&self) -> Vec<Vec<S>> {
+        let mut cycles: Vec<Vec<usize>> = Vec::new();
+        for start in 0..self.states.len() {
+            let mut stack = vec![start];
+            self.collect_cycles(start, start, &mut stack, &mut cycles);
+        }
+        cycles
+            .into_iter()
+            .map(|cycle| {
+                cycle
+                    .into_iter()
+                    .map(|index| self.states[index].clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn index_of(&self, state: &S) -> Option<usize> {
+        self.states.iter().position(|candidate| candidate == state)
+    }
+
+    /// `(event, target index)` pairs leaving the state at `index`.
+    #[cfg(feature = "alloc")]
+    fn successors(&self, index: usize) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.transitions_from(&self.states[index])
+            .filter_map(|(event, target)| Some((event, self.index_of(target)?)))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn collect_paths(
+        &self,
+        current: usize,
+        goal: usize,
+        on_path: &mut [bool],
+        events: &mut Vec<&'static str>,
+        paths: &mut Vec<Vec<&'static str>>,
+    ) {
+        if current == goal {
+            paths.push(events.clone());
+            return;
+        }
+        on_path[current] = true;
+        for (event, next) in self.successors(current) {
+            if !on_path[next] {
+                events.push(event);
+                self.collect_paths(next, goal, on_path, events, paths);
+                events.pop();
+            }
+        }
+        on_path[current] = false;
+    }
+
+    /// Extend `stack` from `current` through states declared after `start`,
+    /// recording each way back to `start`.
+    #[cfg(feature = "alloc")]
+    fn collect_cycles(
+        &self,
+        start: usize,
+        current: usize,
+        stack: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) {
+        for (_, next) in self.successors(current) {
+            if next == start {
+                if !cycles.contains(stack) {
+                    cycles.push(stack.clone());
+                }
+            } else if next > start && !stack.contains(&next) {
+                stack.push(next);
+                self.collect_cycles(start, next, stack, cycles);
+                stack.pop();
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! A fixed-capacity record of recent transitions.
+
+use crate::{MachineState, TransitionContext};
+
+/// The last `N` transitions of a machine, oldest first.
+///
+/// Kept inside the `Dynamic{Name}` wrapper of machines declared with
+/// `history: N`, so it needs no allocation: once full, each new transition
+/// overwrites the oldest one.
+///
+/// ```rust
+/// use state_machines_core::{TransitionContext, TransitionHistory};
+///
+/// let mut history = TransitionHistory::<u8, 2>::new();
+/// history.push(TransitionContext::new(0, 1, "start"));
+/// history.push(TransitionContext::new(1, 2, "step"));
+/// history.push(TransitionContext::new(2, 3, "step"));
+///
+/// let events: Vec<_> = history.iter().map(|t| (t.from, t.to)).collect();
+/// assert_eq!(events, [(1, 2), (2, 3)]);
+/// assert_eq!(history.last().map(|t| t.to), Some(3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransitionHistory<S, const N: usize>
+where
+    S: MachineState,
+{
+    entries: [Option<TransitionContext<S>>; N],
+    /// The slot the next transition goes into, which holds the oldest one
+    /// once the buffer is full.
+    next: usize,
+    len: usize,
+}
+
+impl<S, const N: usize> TransitionHistory<S, N>
+where
+    S: MachineState,
+{
+    pub const fn new() -> Self {
+        Self {
+            entries: [const { None }; N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Record a transition, dropping the oldest one if the history is full.
+    pub fn push(&mut self, transition: TransitionContext<S>) {
+        if N == 0 {
+            return;
+        }
+        self.entries[self.next] = Some(transition);
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// The most recent transition.
+    pub fn last(&self) -> Option<&TransitionContext<S>> {
+        let index = self.next.checked_sub(1).unwrap_or(N.checked_sub(1)?);
+        self.entries[index].as_ref()
+    }
+
+    /// Remove and return the most recent transition.
+    pub fn pop(&mut self) -> Option<TransitionContext<S>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.next = self.next.checked_sub(1).unwrap_or(N - 1);
+        self.len -= 1;
+        self.entries[self.next].take()
+    }
+
+    /// The recorded transitions, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &TransitionContext<S>> + '_ {
+        // Unwritten slots are all after `next`, so they're skipped in place
+        self.entries[self.next..]
+            .iter()
+            .chain(&self.entries[..self.next])
+            .filter_map(Option::as_ref)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many transitions are kept.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<S, const N: usize> Default for TransitionHistory<S, N>
+where
+    S: MachineState,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `rewind(n)` was asked to go back further than the machine's history
+/// reaches, and left the machine alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RewindTooFar {
+    pub requested: usize,
+    pub recorded: usize,
+}
+
+impl core::fmt::Display for RewindTooFar {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "can't rewind {} transitions, only {} recorded",
+            self.requested, self.recorded
+        )
+    }
+}
@@ -0,0 +1,383 @@
+//! Machines defined at runtime and interpreted, without generated code.
+//!
+//! An [`OwnedDefinition`] is built with [`OwnedDefinition::builder`], or
+//! loaded from a definition's JSON export with
+//! [`OwnedDefinition::from_json`]. An [`InterpretedMachine`] runs it, looking
+//! up each guard and callback by name in a [`Behavior`]. So a machine can be
+//! defined in Rust, exported, tweaked per deployment or tenant (a guard
+//! added, a transition removed), and the variant executed as-is:
+//!
+//! ```rust
+//! use state_machines_core::{BehaviorRegistry, InterpretedMachine, OwnedDefinition, OwnedTransition};
+//! use std::sync::Arc;
+//!
+//! let definition = OwnedDefinition::builder("Order", "Pending")
+//!     .state("Paid")
+//!     .transition("pay", OwnedTransition::new(["Pending"], "Paid").guard("under_limit"))
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut rules = BehaviorRegistry::<u32>::new();
+//! rules.register_guard("under_limit", |amount| *amount <= 100);
+//!
+//! let mut order = InterpretedMachine::new(Arc::new(definition), Arc::new(rules), 40).unwrap();
+//! order.handle("pay").unwrap();
+//! assert_eq!(order.current_state(), "Paid");
+//! ```
+//!
+//! Guards are looked up by their exported name, so a guard with arguments
+//! is registered as e.g. `"failures_below(3)"`. A machine can't be created
+//! with a behavior that lacks any guard or callback the definition names. Payloads, around callbacks,
+//! and global callbacks aren't part of a definition, so they don't run.
+//! Needs the `alloc` feature.
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Behavior, MissingBehavior};
+
+/// A machine definition built at runtime; see the [module docs](self).
+///
+/// Event-level guards and callbacks are folded into each of the event's
+/// transitions, the way generated transitions run them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedDefinition {
+    name: String,
+    initial: String,
+    states: Vec<String>,
+    events: Vec<(String, Vec<OwnedTransition>)>,
+}
+
+impl OwnedDefinition {
+    /// Start a definition named `name` whose machines start in `initial`.
+    pub fn builder(name: impl Into<String>, initial: impl Into<String>) -> OwnedDefinitionBuilder {
+        let initial = initial.into();
+        OwnedDefinitionBuilder {
+            definition: OwnedDefinition {
+                name: name.into(),
+                states: alloc::vec![initial.clone()],
+                initial,
+                events: Vec::new(),
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn initial(&self) -> &str {
+        &self.initial
+    }
+
+    /// The leaf states, in declaration order.
+    pub fn states(&self) -> impl Iterator<Item = &str> {
+        self.states.iter().map(String::as_str)
+    }
+
+    /// The event names, in declaration order.
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        self.events.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The transitions of `event`, in declaration order.
+    pub fn transitions(&self, event: &str) -> Option<&[OwnedTransition]> {
+        self.events
+            .iter()
+            .find(|(name, _)| name == event)
+            .map(|(_, transitions)| transitions.as_slice())
+    }
+
+    /// The guards and callbacks of the definition that `behavior` has
+    /// nothing for. Empty when it has them all.
+    pub fn missing<C>(&self, behavior: &(impl Behavior<C> + ?Sized)) -> MissingBehavior {
+        let mut missing = MissingBehavior::default();
+        let transitions = self.events.iter().flat_map(|(_, transitions)| transitions);
+        for transition in transitions {
+            for guard in transition.guards.iter().chain(&transition.unless) {
+                if !behavior.has_guard(guard) && !missing.guards.contains(guard) {
+                    missing.guards.push(guard.clone());
+                }
+            }
+            for callback in transition.before.iter().chain(&transition.after) {
+                if !behavior.has_callback(callback) && !missing.callbacks.contains(callback) {
+                    missing.callbacks.push(callback.clone());
+                }
+            }
+        }
+        missing
+    }
+
+    fn state_index(&self, state: &str) -> Option<usize> {
+        self.states.iter().position(|known| known == state)
+    }
+}
+
+/// Builds an [`OwnedDefinition`], checking it when done.
+#[derive(Debug, Clone)]
+pub struct OwnedDefinitionBuilder {
+    definition: OwnedDefinition,
+}
+
+impl OwnedDefinitionBuilder {
+    /// Declare a leaf state. Declaring one twice has no effect.
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        let state = state.into();
+        if self.definition.state_index(&state).is_none() {
+            self.definition.states.push(state);
+        }
+        self
+    }
+
+    /// Add a transition to `event`, declaring the event if it's new.
+    pub fn transition(mut self, event: impl Into<String>, transition: OwnedTransition) -> Self {
+        let event = event.into();
+        match self
+            .definition
+            .events
+            .iter_mut()
+            .find(|(name, _)| *name == event)
+        {
+            Some((_, transitions)) => transitions.push(transition),
+            None => self
+                .definition
+                .events
+                .push((event, alloc::vec![transition])),
+        }
+        self
+    }
+
+    /// The definition, once every transition joins declared states.
+    pub fn build(self) -> Result<OwnedDefinition, DefinitionError> {
+        let definition = self.definition;
+        for (_, transitions) in &definition.events {
+            for transition in transitions {
+                for state in transition.from.iter().chain([&transition.to]) {
+                    if definition.state_index(state).is_none() {
+                        return Err(DefinitionError::UnknownState {
+                            state: state.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(definition)
+    }
+}
+
+/// One transition of an [`OwnedDefinition`] event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedTransition {
+    pub from: Vec<String>,
+    pub to: String,
+    /// Guards that must pass, in order.
+    pub guards: Vec<String>,
+    /// Guards that must fail, in order.
+    pub unless: Vec<String>,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+impl OwnedTransition {
+    /// A transition from any of `from` to `to`, without guards or callbacks.
+    pub fn new<I>(from: I, to: impl Into<String>) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            from: from.into_iter().map(Into::into).collect(),
+            to: to.into(),
+            guards: Vec::new(),
+            unless: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    pub fn guard(mut self, guard: impl Into<String>) -> Self {
+        self.guards.push(guard.into());
+        self
+    }
+
+    pub fn unless(mut self, guard: impl Into<String>) -> Self {
+        self.unless.push(guard.into());
+        self
+    }
+
+    pub fn before(mut self, callback: impl Into<String>) -> Self {
+        self.before.push(callback.into());
+        self
+    }
+
+    pub fn after(mut self, callback: impl Into<String>) -> Self {
+        self.after.push(callback.into());
+        self
+    }
+}
+
+/// Why an [`OwnedDefinition`] couldn't be built or loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefinitionError {
+    /// A transition names a state the definition doesn't declare.
+    UnknownState { state: String },
+    /// The JSON is malformed, or lacks a key the schema requires.
+    InvalidJson { message: String },
+    /// The JSON was exported with a schema this version can't read.
+    UnsupportedSchema { found: u64 },
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownState { state } => write!(f, "unknown state `{state}`"),
+            Self::InvalidJson { message } => write!(f, "invalid definition JSON: {message}"),
+            Self::UnsupportedSchema { found } => {
+                write!(f, "unsupported definition schema {found}")
+            }
+        }
+    }
+}
+
+/// Why [`InterpretedMachine::handle`] rejected an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpretError {
+    /// The definition has no such event.
+    UnknownEvent { event: String },
+    /// The event has no transition from the current state.
+    InvalidTransition { from: String, event: String },
+    /// A guard failed, or an `unless` guard passed.
+    GuardFailed { guard: String, event: String },
+}
+
+impl fmt::Display for InterpretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownEvent { event } => write!(f, "unknown event `{event}`"),
+            Self::InvalidTransition { from, event } => {
+                write!(f, "event `{event}` has no transition from `{from}`")
+            }
+            Self::GuardFailed { guard, event } => {
+                write!(f, "guard `{guard}` rejected event `{event}`")
+            }
+        }
+    }
+}
+
+/// A machine running an [`OwnedDefinition`]; see the [module docs](self).
+pub struct InterpretedMachine<C> {
+    definition: Arc<OwnedDefinition>,
+    behavior: Arc<dyn Behavior<C> + Send + Sync>,
+    state: usize,
+    ctx: C,
+}
+
+impl<C> InterpretedMachine<C> {
+    /// A machine in the definition's initial state, once `behavior` has
+    /// every guard and callback the definition names.
+    pub fn new(
+        definition: Arc<OwnedDefinition>,
+        behavior: Arc<dyn Behavior<C> + Send + Sync>,
+        ctx: C,
+    ) -> Result<Self, MissingBehavior> {
+        let missing = definition.missing(&*behavior);
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let state = definition
+            .state_index(&definition.initial)
+            .expect("the initial state is declared by the builder");
+        Ok(Self {
+            definition,
+            behavior,
+            state,
+            ctx,
+        })
+    }
+
+    pub fn definition(&self) -> &OwnedDefinition {
+        &self.definition
+    }
+
+    pub fn current_state(&self) -> &str {
+        &self.definition.states[self.state]
+    }
+
+    pub fn ctx(&self) -> &C {
+        &self.ctx
+    }
+
+    pub fn ctx_mut(&mut self) -> &mut C {
+        &mut self.ctx
+    }
+
+    pub fn into_ctx(self) -> C {
+        self.ctx
+    }
+
+    /// Fire `event`: the first of its transitions from the current state
+    /// checks its guards, runs its `before` callbacks, changes state, then
+    /// runs its `after` callbacks. A rejected event leaves the state as it
+    /// was.
+    pub fn handle(&mut self, event: &str) -> Result<(), InterpretError> {
+        let definition = Arc::clone(&self.definition);
+        let transitions =
+            definition
+                .transitions(event)
+                .ok_or_else(|| InterpretError::UnknownEvent {
+                    event: event.into(),
+                })?;
+        let current = self.current_state();
+        let transition = transitions
+            .iter()
+            .find(|transition| transition.from.iter().any(|from| from == current))
+            .ok_or_else(|| InterpretError::InvalidTransition {
+                from: current.into(),
+                event: event.into(),
+            })?;
+
+        // A guard the behavior lost since `new` fails either way
+        let known = |guard: &String| self.behavior.has_guard(guard);
+        let failed = transition
+            .guards
+            .iter()
+            .find(|guard| !known(guard) || !self.behavior.guard(guard, &self.ctx))
+            .or_else(|| {
+                transition
+                    .unless
+                    .iter()
+                    .find(|guard| !known(guard) || self.behavior.guard(guard, &self.ctx))
+            });
+        if let Some(guard) = failed {
+            return Err(InterpretError::GuardFailed {
+                guard: guard.clone(),
+                event: event.into(),
+            });
+        }
+
+        for callback in &transition.before {
+            self.behavior.callback(callback, &mut self.ctx);
+        }
+        self.state = definition
+            .state_index(&transition.to)
+            .expect("the builder checked transition targets");
+        for callback in &transition.after {
+            self.behavior.callback(callback, &mut self.ctx);
+        }
+        Ok(())
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for InterpretedMachine<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterpretedMachine")
+            .field("definition", &self.definition.name)
+            .field("state", &self.current_state())
+            .field("ctx", &self.ctx)
+            .finish_non_exhaustive()
+    }
+}
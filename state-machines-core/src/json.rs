@@ -0,0 +1,216 @@
+//! JSON export of a [`MachineDefinition`].
+//!
+//! [`MachineDefinition::to_json`] describes the machine's structure for
+//! tools outside Rust, such as visualizers, documentation pipelines, and
+//! front ends:
+//!
+//! ```json
+//! {
+//!   "schema": 1,
+//!   "name": "Order",
+//!   "fingerprint": "7aff19a9a1f193b8",
+//!   "async": false,
+//!   "initial": "Pending",
+//!   "states": ["Pending", "Paid", "Shipped"],
+//!   "superstates": [
+//!     { "name": "Open", "initial": "Pending", "states": ["Pending", "Paid"] }
+//!   ],
+//!   "events": [
+//!     {
+//!       "name": "pay",
+//!       "payload": "u32",
+//!       "guards": [], "before": [], "after": [], "around": [],
+//!       "transitions": [
+//!         {
+//!           "from": ["Pending"], "to": "Paid",
+//!           "guards": ["funds_cleared"], "unless": [],
+//!           "before": [], "after": [], "around": []
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Every key is always present (`payload` is `null` without one), lists keep
+//! declaration order, and states are named as in the definition. `schema`
+//! is bumped whenever a key changes meaning or goes away; new keys may be
+//! added without a bump. `fingerprint` is
+//! [`MachineDefinition::fingerprint`] in hex, since JSON numbers can't hold
+//! every `u64`. Needs the `json` feature (enabled by the facade's `std`).
+//!
+//! [`OwnedDefinition::from_json`] reads the document back, to run with an
+//! [`InterpretedMachine`](crate::InterpretedMachine).
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde_json::{Value, json};
+
+use crate::{DefinitionError, MachineDefinition, MachineState, OwnedDefinition, OwnedTransition};
+
+/// The version of the exported schema; see the [module docs](self).
+pub const DEFINITION_SCHEMA: u32 = 1;
+
+impl<S> MachineDefinition<S>
+where
+    S: MachineState,
+{
+    /// The definition as a JSON document; see the [module docs](self).
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// The definition as a JSON value, to embed in a larger document.
+    pub fn to_json_value(&self) -> Value {
+        let superstates: Vec<Value> = self
+            .superstates
+            .iter()
+            .map(|superstate| {
+                json!({
+                    "name": superstate.name,
+                    "initial": state_name(&superstate.initial),
+                    "states": state_names(superstate.descendants),
+                })
+            })
+            .collect();
+
+        let events: Vec<Value> = self
+            .events
+            .iter()
+            .map(|event| {
+                let transitions: Vec<Value> = event
+                    .transitions
+                    .iter()
+                    .map(|transition| {
+                        json!({
+                            "from": state_names(transition.sources),
+                            "to": state_name(&transition.target),
+                            "guards": transition.guards,
+                            "unless": transition.unless,
+                            "before": transition.before,
+                            "after": transition.after,
+                            "around": transition.around,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "name": event.name,
+                    "payload": event.payload,
+                    "guards": event.guards,
+                    "before": event.before,
+                    "after": event.after,
+                    "around": event.around,
+                    "transitions": transitions,
+                })
+            })
+            .collect();
+
+        json!({
+            "schema": DEFINITION_SCHEMA,
+            "name": self.name,
+            "fingerprint": format!("{:016x}", self.fingerprint()),
+            "async": self.async_mode,
+            "initial": state_name(&self.initial),
+            "states": state_names(self.states),
+            "superstates": superstates,
+            "events": events,
+        })
+    }
+}
+
+/// The variant name, as `diff()` and `fingerprint()` name states too.
+fn state_name<S: MachineState>(state: &S) -> String {
+    format!("{state:?}")
+}
+
+fn state_names<S: MachineState>(states: &[S]) -> Vec<String> {
+    states.iter().map(state_name).collect()
+}
+
+impl OwnedDefinition {
+    /// Load a document written by [`MachineDefinition::to_json`], possibly
+    /// edited since.
+    ///
+    /// Event-level guards and callbacks are folded into each transition,
+    /// ahead of the transition's own. Keys an [`InterpretedMachine`] doesn't
+    /// use (`fingerprint`, `async`, `superstates`, `payload`, `around`) may
+    /// be left out.
+    ///
+    /// [`InterpretedMachine`]: crate::InterpretedMachine
+    pub fn from_json(json: &str) -> Result<Self, DefinitionError> {
+        let document: Value =
+            serde_json::from_str(json).map_err(|error| invalid(error.to_string()))?;
+        let schema = document
+            .get("schema")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("missing `schema`"))?;
+        if schema != u64::from(DEFINITION_SCHEMA) {
+            return Err(DefinitionError::UnsupportedSchema { found: schema });
+        }
+
+        let mut builder =
+            OwnedDefinition::builder(string(&document, "name")?, string(&document, "initial")?);
+        for state in strings(&document, "states")? {
+            builder = builder.state(state);
+        }
+        for event in list(&document, "events")? {
+            let name = string(event, "name")?;
+            let guards = optional_strings(event, "guards")?;
+            let before = optional_strings(event, "before")?;
+            let after = optional_strings(event, "after")?;
+            for transition in list(event, "transitions")? {
+                let from = strings(transition, "from")?;
+                let mut owned = OwnedTransition::new(from, string(transition, "to")?);
+                owned.guards = [guards.clone(), optional_strings(transition, "guards")?].concat();
+                owned.unless = optional_strings(transition, "unless")?;
+                owned.before = [before.clone(), optional_strings(transition, "before")?].concat();
+                owned.after = [after.clone(), optional_strings(transition, "after")?].concat();
+                builder = builder.transition(name.clone(), owned);
+            }
+        }
+        builder.build()
+    }
+}
+
+fn invalid(message: impl Into<String>) -> DefinitionError {
+    DefinitionError::InvalidJson {
+        message: message.into(),
+    }
+}
+
+fn string(value: &Value, key: &str) -> Result<String, DefinitionError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(String::from)
+        .ok_or_else(|| invalid(format!("`{key}` must be a string")))
+}
+
+fn list<'a>(value: &'a Value, key: &str) -> Result<&'a Vec<Value>, DefinitionError> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid(format!("`{key}` must be a list")))
+}
+
+fn strings(value: &Value, key: &str) -> Result<Vec<String>, DefinitionError> {
+    list(value, key)?
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .map(String::from)
+                .ok_or_else(|| invalid(format!("`{key}` must hold strings")))
+        })
+        .collect()
+}
+
+/// Like [`strings`], but a missing key is an empty list.
+fn optional_strings(value: &Value, key: &str) -> Result<Vec<String>, DefinitionError> {
+    match value.get(key) {
+        Some(_) => strings(value, key),
+        None => Ok(Vec::new()),
+    }
+}
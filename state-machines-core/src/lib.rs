@@ -1,11 +1,85 @@
 #![no_std]
 
+mod availability;
+mod batch;
+mod behavior;
+mod clock;
+mod diagram;
+#[cfg(feature = "alloc")]
+mod diff;
+#[cfg(feature = "alloc")]
+mod dry_run;
+#[cfg(feature = "postcard")]
+mod encoding;
+mod fingerprint;
+mod graph;
+mod history;
+#[cfg(feature = "alloc")]
+mod interpreter;
+#[cfg(feature = "json")]
+mod json;
+mod phase;
+mod projection;
+mod retry;
+#[cfg(feature = "alloc")]
+mod simulation;
+mod stats;
+mod table;
+mod timeout;
+
+pub use availability::Availability;
+pub use batch::{BatchError, BatchResult};
+pub use behavior::{Behavior, BehaviorNames};
+#[cfg(feature = "alloc")]
+pub use behavior::{BehaviorRegistry, MissingBehavior, WithBehavior};
+pub use clock::Clock;
+pub use diagram::Diagram;
+#[cfg(feature = "alloc")]
+pub use diff::{DefinitionDiff, TransitionChange};
+#[cfg(feature = "alloc")]
+pub use dry_run::{DryRunOutcome, DryRunReport, DryRunStep};
+#[cfg(feature = "embedded-storage")]
+pub use encoding::PersistError;
+#[cfg(feature = "postcard")]
+pub use encoding::{MAX_SNAPSHOT_LEN, SNAPSHOT_FORMAT, SnapshotCodecError};
+pub use history::{RewindTooFar, TransitionHistory};
+#[cfg(feature = "alloc")]
+pub use interpreter::{
+    DefinitionError, InterpretError, InterpretedMachine, OwnedDefinition, OwnedDefinitionBuilder,
+    OwnedTransition,
+};
+#[cfg(feature = "json")]
+pub use json::DEFINITION_SCHEMA;
+pub use phase::TransitionPhase;
+pub use projection::{ProjectionError, Projector};
+pub use retry::Backoff;
+#[cfg(feature = "alloc")]
+pub use simulation::{
+    Latency, Rng, Simulation, SimulationReport, SplitMix64, StateResidency, TransitionProfile,
+};
+pub use stats::{MachineStats, StateStats};
+pub use table::{TableEdge, TransitionTable};
+pub use timeout::{AsyncTimer, Elapsed, with_timeout};
+
+// The mailbox needs compare-and-swap, which some cores (e.g. Cortex-M0) lack
+#[cfg(target_has_atomic = "ptr")]
+mod mailbox;
+
+#[cfg(target_has_atomic = "ptr")]
+pub use mailbox::{Mailbox, MailboxOverflow};
+
 use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::time::Duration;
 
 /// Marker trait for states used by the generated state machines.
-pub trait MachineState: Copy + Eq + Debug + Send + Sync + 'static {}
+///
+/// Only `Clone` is required so state enums can carry data (`data_states: true`);
+/// fieldless state enums are also `Copy + Eq`.
+pub trait MachineState: Clone + Debug + Send + Sync + 'static {}
 
-impl<T> MachineState for T where T: Copy + Eq + Debug + Send + Sync + 'static {}
+impl<T> MachineState for T where T: Clone + Debug + Send + Sync + 'static {}
 
 /// Marker trait indicating that a state is a substate of a superstate.
 ///
@@ -24,6 +98,26 @@ impl<T> MachineState for T where T: Copy + Eq + Debug + Send + Sync + 'static {}
 /// ```
 pub trait SubstateOf<Super> {}
 
+/// Data a state can start with when a transition enters it.
+///
+/// Implemented for every `Default` type. Generated transitions build a
+/// target state's data through this trait, unless the machine sets
+/// `no_default_init: true` and supplies it with `init:` or the payload.
+#[diagnostic::on_unimplemented(
+    message = "state data `{Self}` must implement `Default`",
+    label = "entering this state starts its data at `Default::default()`",
+    note = "derive `Default`, or set `no_default_init: true` and supply the data with `init:` or the event payload"
+)]
+pub trait StateData: Sized {
+    fn initial() -> Self;
+}
+
+impl<T: Default> StateData for T {
+    fn initial() -> Self {
+        T::default()
+    }
+}
+
 /// Represents an error that occurred while attempting a transition.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TransitionError<S>
@@ -39,6 +133,7 @@ impl<S> TransitionError<S>
 where
     S: MachineState,
 {
+    #[cold]
     pub fn invalid_transition(from: S, event: &'static str) -> Self {
         Self {
             from,
@@ -47,6 +142,7 @@ where
         }
     }
 
+    #[cold]
     pub fn guard_failed(from: S, event: &'static str, guard: &'static str) -> Self {
         Self {
             from,
@@ -59,8 +155,17 @@ where
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransitionErrorKind {
     InvalidTransition,
-    GuardFailed { guard: &'static str },
-    ActionFailed { action: &'static str },
+    GuardFailed {
+        guard: &'static str,
+    },
+    ActionFailed {
+        action: &'static str,
+    },
+    /// An async guard, action, or callback didn't finish within the
+    /// machine's `callback_timeout`.
+    Timeout {
+        name: &'static str,
+    },
 }
 
 pub type TransitionResult<S> = Result<(), TransitionError<S>>;
@@ -69,28 +174,183 @@ pub type TransitionResult<S> = Result<(), TransitionError<S>>;
 ///
 /// In typestate machines, guards and around callbacks can fail even though the transition is valid.
 /// The machine is returned along with this error so the caller can retry or handle it.
+///
+/// Generated transition methods fill in `from_state`, so the error still says
+/// where the machine was once it reaches generic handling code. Attach more
+/// context with the builder methods:
+///
+/// ```
+/// use state_machines_core::GuardError;
+///
+/// let err = GuardError::new("fueled", "launch")
+///     .with_from_state("Docked")
+///     .with_message("fuel below launch minimum");
+/// assert_eq!(
+///     err.to_string(),
+///     "guard `fueled` failed for event `launch` in state `Docked`: fuel below launch minimum"
+/// );
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct GuardError {
     pub guard: &'static str,
     pub event: &'static str,
+    /// The state the machine was in; empty if unknown.
+    pub from_state: &'static str,
+    /// Optional human-readable explanation.
+    pub message: Option<&'static str>,
     pub kind: TransitionErrorKind,
+    /// Every guard entry that failed, for machines with
+    /// `evaluate_all_guards: true`; empty otherwise.
+    pub failed_guards: FailedGuards,
 }
 
 impl GuardError {
+    // Error constructors are marked `#[cold]` so the optimizer keeps the
+    // rejection path out of line and the successful transition stays tight.
+    #[cold]
     pub const fn new(guard: &'static str, event: &'static str) -> Self {
-        Self {
-            guard,
-            event,
-            kind: TransitionErrorKind::GuardFailed { guard },
-        }
+        Self::with_kind(guard, event, TransitionErrorKind::GuardFailed { guard })
     }
 
+    #[cold]
     pub const fn with_kind(
         guard: &'static str,
         event: &'static str,
         kind: TransitionErrorKind,
     ) -> Self {
-        Self { guard, event, kind }
+        Self {
+            guard,
+            event,
+            from_state: "",
+            message: None,
+            kind,
+            failed_guards: FailedGuards::EMPTY,
+        }
+    }
+
+    /// Record the state the machine was in.
+    #[cold]
+    pub const fn with_from_state(mut self, from_state: &'static str) -> Self {
+        self.from_state = from_state;
+        self
+    }
+
+    /// Attach a human-readable explanation.
+    #[cold]
+    pub const fn with_message(mut self, message: &'static str) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    /// Record every guard entry that failed, not just `guard`.
+    #[cold]
+    pub const fn with_failed_guards(mut self, failed_guards: FailedGuards) -> Self {
+        self.failed_guards = failed_guards;
+        self
+    }
+}
+
+/// The guard entries of one transition that rejected an event.
+///
+/// Machines with `evaluate_all_guards: true` run every entry and record the
+/// ones that failed in [`GuardError::failed_guards`], so a UI can list
+/// everything blocking an action at once:
+///
+/// ```
+/// use state_machines_core::FailedGuards;
+///
+/// const ENTRIES: &[&str] = &["has_address", "cart_not_empty", "payment_valid"];
+/// let failed = FailedGuards::new(ENTRIES, 0b011);
+/// assert_eq!(failed.iter().collect::<Vec<_>>(), ["has_address", "cart_not_empty"]);
+/// assert!(!failed.contains("payment_valid"));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct FailedGuards {
+    entries: &'static [&'static str],
+    /// Bit `i` is set if `entries[i]` failed.
+    failed: u64,
+}
+
+impl FailedGuards {
+    /// No failed guards.
+    pub const EMPTY: Self = Self {
+        entries: &[],
+        failed: 0,
+    };
+
+    /// The entries of `entries` whose bit is set in `failed`. Entries past
+    /// the 64th can't be recorded.
+    pub const fn new(entries: &'static [&'static str], failed: u64) -> Self {
+        let failed = if entries.len() < 64 {
+            failed & ((1 << entries.len()) - 1)
+        } else {
+            failed
+        };
+        Self { entries, failed }
+    }
+
+    /// The failed entries' descriptions, in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index < 64 && self.failed & (1 << index) != 0)
+            .map(|(_, entry)| *entry)
+    }
+
+    /// How many entries failed.
+    pub const fn len(&self) -> usize {
+        self.failed.count_ones() as usize
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Whether the entry described as `guard` failed.
+    pub fn contains(&self, guard: &str) -> bool {
+        self.iter().any(|entry| entry == guard)
+    }
+}
+
+// Lists the failed entries, rather than the bit set
+impl Debug for FailedGuards {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl core::fmt::Display for GuardError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.kind {
+            TransitionErrorKind::GuardFailed { .. } if self.failed_guards.len() > 1 => {
+                f.write_str("guards ")?;
+                for (index, guard) in self.failed_guards.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "`{}`", guard)?;
+                }
+                f.write_str(" failed")?
+            }
+            TransitionErrorKind::GuardFailed { guard } => write!(f, "guard `{}` failed", guard)?,
+            TransitionErrorKind::ActionFailed { action } => {
+                write!(f, "action `{}` failed", action)?
+            }
+            TransitionErrorKind::InvalidTransition => {
+                write!(f, "`{}` rejected the transition", self.guard)?
+            }
+            TransitionErrorKind::Timeout { name } => write!(f, "`{}` timed out", name)?,
+        }
+        write!(f, " for event `{}`", self.event)?;
+        if !self.from_state.is_empty() {
+            write!(f, " in state `{}`", self.from_state)?;
+        }
+        if let Some(message) = self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
     }
 }
 
@@ -109,12 +369,25 @@ pub enum DynamicError {
     GuardFailed {
         guard: &'static str,
         event: &'static str,
+        /// Every guard entry that failed, for machines with
+        /// `evaluate_all_guards: true`; empty otherwise.
+        failed_guards: FailedGuards,
     },
     /// An action callback failed during the transition.
     ActionFailed {
         action: &'static str,
         event: &'static str,
     },
+    /// An async guard, action, or callback exceeded the machine's
+    /// `callback_timeout`.
+    Timeout {
+        name: &'static str,
+        event: &'static str,
+    },
+    /// An event was dispatched while another dispatch on the same machine had
+    /// not completed, either re-entered from a callback or abandoned mid-way
+    /// by a panic or a dropped future.
+    ReentrantDispatch { event: &'static str },
     /// Attempted to access or modify state data when in wrong state.
     WrongState {
         expected: &'static str,
@@ -124,18 +397,35 @@ pub enum DynamicError {
 }
 
 impl DynamicError {
+    #[cold]
     pub fn invalid_transition(from: &'static str, event: &'static str) -> Self {
         Self::InvalidTransition { from, event }
     }
 
+    #[cold]
     pub fn guard_failed(guard: &'static str, event: &'static str) -> Self {
-        Self::GuardFailed { guard, event }
+        Self::GuardFailed {
+            guard,
+            event,
+            failed_guards: FailedGuards::EMPTY,
+        }
     }
 
+    #[cold]
     pub fn action_failed(action: &'static str, event: &'static str) -> Self {
         Self::ActionFailed { action, event }
     }
 
+    #[cold]
+    pub fn timeout(name: &'static str, event: &'static str) -> Self {
+        Self::Timeout { name, event }
+    }
+
+    #[cold]
+    pub fn reentrant_dispatch(event: &'static str) -> Self {
+        Self::ReentrantDispatch { event }
+    }
+
     pub fn wrong_state(
         expected: &'static str,
         actual: &'static str,
@@ -149,24 +439,60 @@ impl DynamicError {
     }
 
     /// Convert from GuardError to DynamicError.
+    #[cold]
     pub fn from_guard_error(err: GuardError) -> Self {
         match err.kind {
             TransitionErrorKind::GuardFailed { guard } => Self::GuardFailed {
                 guard,
                 event: err.event,
+                failed_guards: err.failed_guards,
             },
             TransitionErrorKind::ActionFailed { action } => Self::ActionFailed {
                 action,
                 event: err.event,
             },
             TransitionErrorKind::InvalidTransition => Self::InvalidTransition {
-                from: "",
+                from: err.from_state,
+                event: err.event,
+            },
+            TransitionErrorKind::Timeout { name } => Self::Timeout {
+                name,
                 event: err.event,
             },
         }
     }
 }
 
+/// A failed event, as passed to a machine's `on_error` handler.
+///
+/// Typestate transition methods report the `GuardError` they return; dynamic
+/// dispatch reports failures that happen before any transition method runs,
+/// such as an event with no transition from the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFailure<'a> {
+    /// A guard, around callback, action, or timeout rejected the transition.
+    Rejected(&'a GuardError),
+    /// `handle()` couldn't dispatch the event.
+    Dispatch(&'a DynamicError),
+}
+
+impl EventFailure<'_> {
+    /// The name of the event that failed.
+    pub fn event(&self) -> &'static str {
+        match self {
+            EventFailure::Rejected(err) => err.event,
+            EventFailure::Dispatch(err) => match *err {
+                DynamicError::InvalidTransition { event, .. }
+                | DynamicError::GuardFailed { event, .. }
+                | DynamicError::ActionFailed { event, .. }
+                | DynamicError::Timeout { event, .. }
+                | DynamicError::ReentrantDispatch { event } => event,
+                DynamicError::WrongState { operation, .. } => operation,
+            },
+        }
+    }
+}
+
 pub trait Machine {
     type State: MachineState;
 
@@ -192,6 +518,283 @@ where
     }
 }
 
+/// Conversion between a fieldless state enum and its `u8` discriminant.
+///
+/// Implemented by the generated `{Name}State` enum of machines declared
+/// with `atomic_state: true`.
+pub trait StateRepr: MachineState {
+    fn to_raw(self) -> u8;
+    fn from_raw(raw: u8) -> Self;
+}
+
+/// The `Dynamic{Name}` wrapper of a machine declared with `dynamic: true`.
+///
+/// Lets companion crates, such as a persistence layer, rebuild any generated
+/// machine from a stored state name and drive it with events. Implemented for
+/// machines with fieldless state enums whose events borrow nothing; the
+/// inherent methods of the same names are what application code calls.
+pub trait DynamicMachine: Sized {
+    type State: MachineState + PartialEq;
+    type Event;
+    type Context;
+    /// What a successful dispatch returns: `()`, or a `Transition` record
+    /// with `transition_records: true`.
+    type Output;
+
+    /// Rebuild the machine in `state` around `ctx` without running callbacks.
+    fn restore(ctx: Self::Context, state: Self::State) -> Self;
+
+    /// Move to `state` without dispatching an event, keeping the context.
+    fn restore_state(&mut self, state: Self::State);
+
+    /// The current state.
+    fn state(&self) -> Self::State;
+
+    /// Whether a dispatch never completed and took the state with it, e.g.
+    /// a callback panicked. A poisoned machine has no state to report.
+    fn is_poisoned(&self) -> bool {
+        false
+    }
+
+    /// The name a state is stored under.
+    fn state_name(state: &Self::State) -> &'static str;
+
+    /// The state stored under `name`, if there is one.
+    fn state_from_name(name: &str) -> Option<Self::State>;
+
+    /// The name of an event, as used in errors and transition history.
+    fn event_name(event: &Self::Event) -> &'static str;
+
+    /// The event named `name`, if it has one and takes no payload.
+    fn event_from_name(name: &str) -> Option<Self::Event>;
+
+    /// The machine's [`MachineId`], for machines declared with `identity: true`.
+    fn id(&self) -> Option<MachineId> {
+        None
+    }
+
+    /// The machine's states, superstates, events, and transitions.
+    fn definition() -> &'static MachineDefinition<Self::State>;
+
+    /// Dispatch `event` like `handle()`.
+    ///
+    /// Synchronous machines return an already completed future.
+    fn dispatch(
+        &mut self,
+        event: Self::Event,
+    ) -> impl Future<Output = Result<Self::Output, DynamicError>>;
+
+    /// Dispatch `event` like [`dispatch`](Self::dispatch), but hold the
+    /// transition's `after_commit` callbacks back until
+    /// [`commit`](Self::commit), for callers that may still undo the
+    /// transition with [`rollback`](Self::rollback), which discards them.
+    fn dispatch_uncommitted(
+        &mut self,
+        event: Self::Event,
+    ) -> impl Future<Output = Result<Self::Output, DynamicError>> {
+        self.dispatch(event)
+    }
+
+    /// Run the `after_commit` callbacks held back by the last
+    /// [`dispatch_uncommitted`](Self::dispatch_uncommitted).
+    fn commit(&mut self) -> impl Future<Output = ()> {
+        core::future::ready(())
+    }
+
+    /// Undo the last [`dispatch_uncommitted`](Self::dispatch_uncommitted),
+    /// which left `from`: move back to `from` with the data it held before
+    /// the dispatch, and discard the held `after_commit` callbacks. No
+    /// callbacks run.
+    ///
+    /// Unlike [`restore_state`](Self::restore_state), which starts the state
+    /// over, this keeps state data; generated machines clone it aside when
+    /// the dispatch starts, so it needs to be `Clone`.
+    fn rollback(&mut self, from: Self::State) {
+        self.restore_state(from);
+    }
+}
+
+/// Lock-free mirror of a dynamic machine's current state.
+///
+/// Attach one with `share_state()` and read it from any thread with
+/// [`load`](Self::load), without synchronising with the thread that
+/// dispatches events.
+#[derive(Debug)]
+pub struct AtomicState<S> {
+    raw: AtomicU8,
+    _state: PhantomData<fn() -> S>,
+}
+
+impl<S> AtomicState<S> {
+    /// Create a cell from a raw discriminant, e.g. `TrafficLightState::Red as u8`.
+    ///
+    /// Usable in `static` initialisers.
+    pub const fn from_raw(raw: u8) -> Self {
+        Self {
+            raw: AtomicU8::new(raw),
+            _state: PhantomData,
+        }
+    }
+}
+
+impl<S> AtomicState<S>
+where
+    S: StateRepr,
+{
+    pub fn new(initial: S) -> Self {
+        Self::from_raw(initial.to_raw())
+    }
+
+    pub fn load(&self) -> S {
+        S::from_raw(self.raw.load(Ordering::Acquire))
+    }
+
+    pub fn store(&self, state: S) {
+        self.raw.store(state.to_raw(), Ordering::Release);
+    }
+}
+
+/// Identifies one machine instance, e.g. by session or order number.
+///
+/// Machines declared with `identity: true` carry an optional id, set with
+/// `with_id()`. It is copied into their trace entries, notifications, and
+/// transition records so observers can tell instances of the same machine
+/// apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MachineId(pub u64);
+
+impl From<u64> for MachineId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl core::fmt::Display for MachineId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Record of a completed transition.
+///
+/// Returned by `handle()` on dynamic machines declared with
+/// `transition_records: true`. `duration` covers guards, callbacks, and the
+/// action; it is only measured when the `std` feature of `state-machines` is on.
+/// `id` is the machine's [`MachineId`], if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition<S>
+where
+    S: MachineState,
+{
+    pub from: S,
+    pub to: S,
+    pub event: &'static str,
+    pub duration: Option<Duration>,
+    pub id: Option<MachineId>,
+}
+
+/// Sent to a machine's `sink` when a transition declared with `emit:` succeeds.
+///
+/// `kind` is the generated `{Name}Notification` variant named by `emit:`;
+/// the rest describes the transition, with states by name so typestate-only
+/// machines can emit too. `id` is the machine's [`MachineId`], if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransitionNotification<N> {
+    pub kind: N,
+    pub event: &'static str,
+    pub from: &'static str,
+    pub to: &'static str,
+    pub id: Option<MachineId>,
+}
+
+/// Where a machine's `emit:` notifications go: a channel, a message
+/// producer, a webhook client.
+///
+/// `send` runs synchronously right after the transition, once its `after`
+/// callbacks are done, and can't fail it; sinks that do I/O should queue the
+/// notification and deliver it elsewhere. Closures taking a
+/// `TransitionNotification` are sinks.
+pub trait Sink<N> {
+    fn send(&mut self, notification: TransitionNotification<N>);
+}
+
+impl<N, F> Sink<N> for F
+where
+    F: FnMut(TransitionNotification<N>),
+{
+    fn send(&mut self, notification: TransitionNotification<N>) {
+        self(notification)
+    }
+}
+
+/// Where a transition's `forward:` events go: usually a sibling machine
+/// kept in the context.
+///
+/// `forward` runs last, after the `after` callbacks and the `emit:`
+/// notification, so the receiver only sees the event once the sending
+/// machine is in its new state; several forwards of one transition run in
+/// declaration order. It can't fail the transition. Synchronous `Dynamic{Name}`
+/// wrappers handle the event with `handle()`, so a rejection goes to their
+/// `on_error` handler; closures taking the event can queue it instead.
+pub trait Forward<E> {
+    fn forward(&mut self, event: E);
+}
+
+impl<E, F> Forward<E> for F
+where
+    F: FnMut(E),
+{
+    fn forward(&mut self, event: E) {
+        self(event)
+    }
+}
+
+/// A machine that has stayed in its current state for too long.
+///
+/// Returned by `stuck_check()` on dynamic machines declared with
+/// `watchdog: true`. `limit` is the state's `max_duration:`, or the threshold
+/// passed to `stuck_check()` for states without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StuckState<S>
+where
+    S: MachineState,
+{
+    pub state: S,
+    pub elapsed: Duration,
+    pub limit: Duration,
+}
+
+/// A dynamic machine's state and version, from `snapshot()`.
+///
+/// The version counts successful transitions, so comparing it tells whether
+/// the machine moved since the snapshot was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Snapshot<S>
+where
+    S: MachineState,
+{
+    pub state: S,
+    pub version: u64,
+}
+
+/// `restore_if_version()` found the machine at a different version than
+/// expected, and left it alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VersionMismatch {
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl core::fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "expected machine version {}, found {}",
+            self.expected, self.actual
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AroundStage {
     Before,
@@ -207,7 +810,70 @@ where
     Abort(TransitionError<S>),
 }
 
-#[derive(Debug, Clone)]
+/// Runs compensation hooks when dropped, unless disarmed first.
+///
+/// Generated transitions hold one of these while `before` callbacks run, so
+/// the transition's `compensate:` hooks still fire if a callback panics and
+/// the stack unwinds. The guard owns the borrow of the machine and derefs to
+/// it, so the callbacks in between can still take `&mut self`.
+#[must_use = "dropping the guard runs the compensation immediately"]
+pub struct CompensationGuard<'a, T, F>
+where
+    F: FnOnce(&mut T),
+{
+    target: &'a mut T,
+    compensate: Option<F>,
+}
+
+impl<'a, T, F> CompensationGuard<'a, T, F>
+where
+    F: FnOnce(&mut T),
+{
+    pub const fn new(target: &'a mut T, compensate: F) -> Self {
+        Self {
+            target,
+            compensate: Some(compensate),
+        }
+    }
+
+    /// Consume the guard without running the compensation.
+    pub fn disarm(mut self) {
+        self.compensate = None;
+    }
+}
+
+impl<T, F> core::ops::Deref for CompensationGuard<'_, T, F>
+where
+    F: FnOnce(&mut T),
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.target
+    }
+}
+
+impl<T, F> core::ops::DerefMut for CompensationGuard<'_, T, F>
+where
+    F: FnOnce(&mut T),
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.target
+    }
+}
+
+impl<T, F> Drop for CompensationGuard<'_, T, F>
+where
+    F: FnOnce(&mut T),
+{
+    fn drop(&mut self) {
+        if let Some(compensate) = self.compensate.take() {
+            compensate(self.target);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TransitionDefinition<S>
 where
     S: MachineState,
@@ -221,7 +887,7 @@ where
     pub around: &'static [&'static str],
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EventDefinition<S>
 where
     S: MachineState,
@@ -235,7 +901,7 @@ where
     pub transitions: &'static [TransitionDefinition<S>],
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SuperstateDefinition<S>
 where
     S: MachineState,
@@ -245,7 +911,13 @@ where
     pub initial: S,
 }
 
-#[derive(Debug, Clone)]
+/// A machine's structure: states, superstates, and events with their
+/// transitions, guards, and callbacks.
+///
+/// Definitions compare and hash structurally. For a hash that can be stored,
+/// e.g. to key a cache of generated artifacts, use
+/// [`fingerprint`](Self::fingerprint).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MachineDefinition<S>
 where
     S: MachineState,
@@ -257,3 +929,72 @@ where
     pub superstates: &'static [SuperstateDefinition<S>],
     pub events: &'static [EventDefinition<S>],
 }
+
+/// A handle to one edge of a [`MachineDefinition`]: an event's transition
+/// from one leaf state to one target.
+///
+/// Generated as constants on `{Name}Transitions`, or in `mod transitions`
+/// with a `module:`, e.g. `OrderTransitions::PAY_PENDING_TO_PAID`, so tests
+/// and tooling can name an edge without matching on event and state strings.
+#[derive(Debug, Clone, Copy)]
+pub struct TransitionRef<S>
+where
+    S: MachineState,
+{
+    definition: fn() -> &'static MachineDefinition<S>,
+    event: usize,
+    transition: usize,
+    from: S,
+    to: S,
+}
+
+impl<S> TransitionRef<S>
+where
+    S: MachineState,
+{
+    /// Refer to `definition().events[event].transitions[transition]`, taken
+    /// from `from` to `to`. Used by generated code.
+    pub const fn new(
+        definition: fn() -> &'static MachineDefinition<S>,
+        event: usize,
+        transition: usize,
+        from: S,
+        to: S,
+    ) -> Self {
+        Self {
+            definition,
+            event,
+            transition,
+            from,
+            to,
+        }
+    }
+
+    /// The definition this edge belongs to.
+    pub fn definition(&self) -> &'static MachineDefinition<S> {
+        (self.definition)()
+    }
+
+    /// The event that takes this edge.
+    pub fn event(&self) -> &'static EventDefinition<S> {
+        &self.definition().events[self.event]
+    }
+
+    /// The transition the edge comes from, with its guards and callbacks.
+    pub fn transition(&self) -> &'static TransitionDefinition<S> {
+        &self.event().transitions[self.transition]
+    }
+
+    /// The event's name.
+    pub fn name(&self) -> &'static str {
+        self.event().name
+    }
+
+    pub fn from(&self) -> &S {
+        &self.from
+    }
+
+    pub fn to(&self) -> &S {
+        &self.to
+    }
+}
@@ -0,0 +1,213 @@
+//! Fixed-capacity event queue that interrupt handlers can post into.
+//!
+//! The queue is a bounded lock-free ring (one sequence number per slot), so
+//! `post()` never blocks and never allocates. It needs atomic compare-and-swap,
+//! which rules out cores without it (e.g. Cortex-M0).
+//!
+//! It isn't a single-producer single-consumer queue, which would only need
+//! atomic loads and stores, for two reasons:
+//!
+//! - With `DropOldest`, `post()` takes the oldest event itself, so the
+//!   producer and the consumer both advance the head.
+//! - Interrupts nest: a handler that posts can be preempted by a
+//!   higher-priority one that posts too, which is a second producer.
+//!
+//! What that costs over SPSC is one compare-and-swap per `post()` and per
+//! `pop()`, and a sequence number per slot.
+//!
+//! The capacity must be a power of two. Positions are free-running `usize`
+//! counters that wrap, and only a power of two divides the wrap-around
+//! evenly, so a position keeps mapping to the same slot across the wrap.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// What `Mailbox::post` does when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailboxOverflow {
+    /// Discard the oldest queued event to make room for the new one.
+    DropOldest,
+    /// Hand the new event back to the caller.
+    Reject,
+}
+
+struct Slot<E> {
+    /// Position this slot is ready for: `pos` when free for the push at
+    /// `pos`, `pos + 1` once that push has written its value.
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<E>>,
+}
+
+/// Bounded event queue shared between interrupt handlers and the main loop.
+///
+/// Generated `{Name}Mailbox` types wrap this with the capacity and overflow
+/// policy declared in the macro; dynamic machines drain it with `process()`.
+/// `N` must be a power of two (or 0, which rejects every event).
+pub struct Mailbox<E, const N: usize> {
+    slots: [Slot<E>; N],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    overflow: MailboxOverflow,
+}
+
+// SAFETY: a slot's value is only accessed by the single producer or consumer
+// that claimed its position through `head`/`tail`, and ownership is handed over
+// through the slot's sequence number with release/acquire ordering.
+unsafe impl<E: Send, const N: usize> Sync for Mailbox<E, N> {}
+unsafe impl<E: Send, const N: usize> Send for Mailbox<E, N> {}
+
+impl<E, const N: usize> Mailbox<E, N> {
+    /// Index mask for a position; see the [module docs](self).
+    const MASK: usize = {
+        assert!(
+            N == 0 || N.is_power_of_two(),
+            "mailbox capacity must be a power of two"
+        );
+        N.wrapping_sub(1)
+    };
+
+    pub const fn new(overflow: MailboxOverflow) -> Self {
+        let _ = Self::MASK;
+        let mut slots = [const {
+            Slot {
+                seq: AtomicUsize::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }; N];
+        let mut index = 0;
+        while index < N {
+            slots[index].seq = AtomicUsize::new(index);
+            index += 1;
+        }
+
+        Self {
+            slots,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflow,
+        }
+    }
+
+    /// Queue an event. Safe to call from an interrupt handler.
+    ///
+    /// Returns the event if the queue is full and the policy is `Reject`, or
+    /// if no room could be made because the consumer is mid-way through
+    /// taking the oldest event.
+    pub fn post(&self, event: E) -> Result<(), E> {
+        match self.try_push(event) {
+            Ok(()) => Ok(()),
+            Err(event) => match self.overflow {
+                MailboxOverflow::Reject => Err(event),
+                MailboxOverflow::DropOldest => {
+                    // One attempt only: looping could spin forever in an
+                    // interrupt that preempted the consumer
+                    drop(self.pop());
+                    self.try_push(event)
+                }
+            },
+        }
+    }
+
+    /// Take the oldest queued event.
+    pub fn pop(&self) -> Option<E> {
+        if N == 0 {
+            return None;
+        }
+
+        let mut pos = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & Self::MASK];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let lag = seq.wrapping_sub(pos.wrapping_add(1)) as isize;
+
+            if lag == 0 {
+                match self.head.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: winning the CAS gives exclusive access to this
+                        // slot, and `seq == pos + 1` means it holds a value
+                        let event = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.seq.store(pos.wrapping_add(N), Ordering::Release);
+                        return Some(event);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if lag < 0 {
+                return None;
+            } else {
+                pos = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Number of queued events (a snapshot; may change immediately).
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(N)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    fn try_push(&self, event: E) -> Result<(), E> {
+        if N == 0 {
+            return Err(event);
+        }
+
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & Self::MASK];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let lag = seq.wrapping_sub(pos) as isize;
+
+            if lag == 0 {
+                match self.tail.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: winning the CAS gives exclusive access to this
+                        // slot, and `seq == pos` means it's empty
+                        unsafe { (*slot.value.get()).write(event) };
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if lag < 0 {
+                return Err(event);
+            } else {
+                pos = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<E, const N: usize> Drop for Mailbox<E, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+impl<E, const N: usize> core::fmt::Debug for Mailbox<E, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("len", &self.len())
+            .field("capacity", &N)
+            .field("overflow", &self.overflow)
+            .finish()
+    }
+}
@@ -0,0 +1,71 @@
+//! The fixed order of the steps in a transition.
+//!
+//! Generated transition methods are assembled from [`TransitionPhase::ORDER`],
+//! so typestate methods and dynamic dispatch (which calls them) run a
+//! transition's guards and callbacks in the same order, sync or async.
+
+/// One step of a transition, from the event's payload transform to its
+/// `after_commit` callbacks.
+///
+/// Steps before [`Enter`](Self::Enter) can still reject the transition
+/// (guards, `around` aborts, a vetoing action, or a timeout), leaving the
+/// machine in its source state; the steps from `Enter` on run on the machine
+/// in its new state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransitionPhase {
+    /// The event's `transform` rewrites the payload.
+    Transform,
+    /// `around` callbacks, at [`AroundStage::Before`](crate::AroundStage::Before).
+    AroundBefore,
+    /// `guards` and `unless` entries.
+    Guards,
+    /// A `choose` transition's chooser picks the target.
+    Choose,
+    /// Machine-wide `before_transition` callbacks.
+    BeforeTransition,
+    /// The event's and transition's `before` callbacks.
+    Before,
+    /// The transition's `action`.
+    Action,
+    /// The machine moves to the target state, with `init` building its data.
+    Enter,
+    /// The event's and transition's `after` callbacks.
+    After,
+    /// Machine-wide `after_transition` callbacks.
+    AfterTransition,
+    /// `around` callbacks, at
+    /// [`AroundStage::AfterSuccess`](crate::AroundStage::AfterSuccess).
+    AroundAfter,
+    /// The `emit` notification goes to the machine's sink.
+    Emit,
+    /// `forward` events go to other machines.
+    Forward,
+    /// `after_commit` callbacks, once the new state is stored.
+    AfterCommit,
+}
+
+impl TransitionPhase {
+    /// Every phase, in the order a transition runs them.
+    pub const ORDER: [Self; 14] = [
+        Self::Transform,
+        Self::AroundBefore,
+        Self::Guards,
+        Self::Choose,
+        Self::BeforeTransition,
+        Self::Before,
+        Self::Action,
+        Self::Enter,
+        Self::After,
+        Self::AfterTransition,
+        Self::AroundAfter,
+        Self::Emit,
+        Self::Forward,
+        Self::AfterCommit,
+    ];
+
+    /// Whether the phase runs on the machine in its new state, so it can no
+    /// longer reject the transition.
+    pub const fn is_entered(self) -> bool {
+        self as u8 >= Self::Enter as u8
+    }
+}
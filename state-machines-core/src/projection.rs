@@ -0,0 +1,81 @@
+//! Pure projections of recorded events onto states.
+//!
+//! A read model rebuilding where a machine is from its event log shouldn't
+//! run the machine's guards and callbacks again: they may read a clock, call
+//! a service, or send a notification. With the runtime wrapper, each
+//! generated `{Name}State` implements [`Projector`] over `{Name}Event`,
+//! following the definition's transitions alone, so the same events always
+//! project to the same state:
+//!
+//! ```ignore
+//! use state_machines::Projector;
+//!
+//! let state = OrderState::project(&[OrderEvent::Pay, OrderEvent::Ship])?;
+//! assert_eq!(state, OrderState::Shipped);
+//! ```
+//!
+//! Read models of their own, such as a running total, can implement the
+//! trait too, and be fed the same stream.
+
+use core::fmt;
+
+/// Folds events into a value, one at a time; see the [module docs](self).
+pub trait Projector<E>: Sized {
+    type Error;
+
+    /// The value before any event.
+    fn initial() -> Self;
+
+    /// The value after `event`.
+    fn apply(self, event: &E) -> Result<Self, Self::Error>;
+
+    /// Apply `events` in order, starting from `initial()`.
+    fn project<'e, I>(events: I) -> Result<Self, Self::Error>
+    where
+        I: IntoIterator<Item = &'e E>,
+        E: 'e,
+    {
+        Self::initial().project_from(events)
+    }
+
+    /// Apply `events` in order, starting from `self`, e.g. a value projected
+    /// up to a checkpoint.
+    fn project_from<'e, I>(self, events: I) -> Result<Self, Self::Error>
+    where
+        I: IntoIterator<Item = &'e E>,
+        E: 'e,
+    {
+        events.into_iter().try_fold(self, Self::apply)
+    }
+}
+
+/// Why a generated `{Name}State` couldn't apply an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionError {
+    /// The event has no transition from the state, so the stream wasn't
+    /// recorded by this machine, or the definition changed since.
+    InvalidTransition {
+        from: &'static str,
+        event: &'static str,
+    },
+    /// The transition's target depends on code a projection doesn't run:
+    /// a `choose:` method, or an `init:` method building the target's data.
+    Undetermined {
+        from: &'static str,
+        event: &'static str,
+    },
+}
+
+impl fmt::Display for ProjectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTransition { from, event } => {
+                write!(f, "event `{event}` has no transition from `{from}`")
+            }
+            Self::Undetermined { from, event } => write!(
+                f,
+                "event `{event}` from `{from}` leads to a state only the machine can determine"
+            ),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Delays between retried transitions.
+//!
+//! Events declared with `retry` re-run the transition after a guard, action,
+//! or timeout failure. The macro turns the declared backoff into a [`Backoff`]
+//! and sleeps for [`Backoff::delay`] with the machine's [`AsyncTimer`] between
+//! attempts.
+//!
+//! [`AsyncTimer`]: crate::AsyncTimer
+
+use core::time::Duration;
+
+/// How long to wait before retrying a failed transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Retry immediately.
+    None,
+    /// Wait the same duration before every retry.
+    Fixed(Duration),
+    /// Wait the base duration before the first retry, doubling each time.
+    Exponential(Duration),
+}
+
+impl Backoff {
+    /// The delay before retry number `retry`, counting from 1.
+    ///
+    /// Exponential delays saturate at `Duration::MAX` instead of overflowing.
+    pub fn delay(self, retry: u32) -> Duration {
+        match self {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential(base) => {
+                let factor = 1u32.checked_shl(retry.saturating_sub(1)).unwrap_or(0);
+                if factor == 0 {
+                    return Duration::MAX;
+                }
+                base.saturating_mul(factor)
+            }
+        }
+    }
+}
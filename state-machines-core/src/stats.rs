@@ -0,0 +1,89 @@
+//! Per-state counters kept by machines declared with `stats: true`.
+
+/// How often one state was entered, left, and refused a transition.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StateStats {
+    /// Transitions into the state, self-transitions included.
+    pub entries: u64,
+    /// Transitions out of the state, self-transitions included.
+    pub exits: u64,
+    /// Events a guard rejected while the machine was in the state.
+    pub guard_rejections: u64,
+}
+
+/// Counters for each of a machine's `N` states.
+///
+/// Returned by `stats()` on the dynamic wrapper. Without the `stats`
+/// feature nothing is counted and every counter reads zero.
+///
+/// ```rust
+/// use state_machines_core::MachineStats;
+///
+/// let mut stats = MachineStats::new(["Idle", "Running"]);
+/// stats.record_transition(0, 1);
+/// stats.record_guard_rejection(1);
+///
+/// assert_eq!(stats.get("Running").unwrap().entries, 1);
+/// assert_eq!(stats.get("Running").unwrap().guard_rejections, 1);
+/// assert_eq!(stats.get("Idle").unwrap().exits, 1);
+/// assert_eq!(stats.transitions(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineStats<const N: usize> {
+    states: [&'static str; N],
+    counters: [StateStats; N],
+}
+
+impl<const N: usize> MachineStats<N> {
+    /// Zeroed counters for the states named `states`, in declaration order.
+    pub const fn new(states: [&'static str; N]) -> Self {
+        Self {
+            states,
+            counters: [StateStats {
+                entries: 0,
+                exits: 0,
+                guard_rejections: 0,
+            }; N],
+        }
+    }
+
+    /// The counters of the state named `state`.
+    pub fn get(&self, state: &str) -> Option<&StateStats> {
+        let index = self.states.iter().position(|name| *name == state)?;
+        Some(&self.counters[index])
+    }
+
+    /// Every state with its counters, in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &StateStats)> + '_ {
+        self.states.iter().copied().zip(&self.counters)
+    }
+
+    /// How many transitions were counted in all.
+    pub fn transitions(&self) -> u64 {
+        self.counters.iter().map(|counters| counters.entries).sum()
+    }
+
+    /// How many guard rejections were counted in all.
+    pub fn guard_rejections(&self) -> u64 {
+        self.counters
+            .iter()
+            .map(|counters| counters.guard_rejections)
+            .sum()
+    }
+
+    /// Count a transition from the state at index `from` to the one at `to`.
+    pub fn record_transition(&mut self, from: usize, to: usize) {
+        self.counters[from].exits += 1;
+        self.counters[to].entries += 1;
+    }
+
+    /// Count a guard rejection in the state at index `state`.
+    pub fn record_guard_rejection(&mut self, state: usize) {
+        self.counters[state].guard_rejections += 1;
+    }
+
+    /// Zero every counter.
+    pub fn reset(&mut self) {
+        self.counters = [StateStats::default(); N];
+    }
+}
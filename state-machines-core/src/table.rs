@@ -0,0 +1,199 @@
+//! A const-evaluable view of a machine's transitions.
+//!
+//! [`MachineDefinition`](crate::MachineDefinition) holds the state enum
+//! itself, which `const fn`s can't compare. `{Name}State::TABLE` carries the
+//! same edges as names and indices instead, so structural properties can be
+//! asserted at compile time:
+//!
+//! ```ignore
+//! const _: () = assert!(OrderState::TABLE.has_transition("Paid", "ship"));
+//! const _: () = assert!(OrderState::TABLE.can_reach_from_all("Cancelled"));
+//! ```
+//!
+//! States and events are looked up by name; a misspelled name panics, which
+//! in a `const` item is a compile error. Like the graph queries, the table
+//! ignores guards: an edge exists whenever some transition could fire.
+
+/// Leaf states, events, and `(event, from, to)` edges, by index; see the
+/// [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct TransitionTable {
+    /// Leaf state names, in declaration order.
+    pub states: &'static [&'static str],
+    /// Event names, in declaration order.
+    pub events: &'static [&'static str],
+    /// Index of the initial state in `states`.
+    pub initial: usize,
+    /// One edge per leaf source and target, with superstates expanded.
+    pub edges: &'static [TableEdge],
+}
+
+/// One edge of a [`TransitionTable`], as indices into its `events` and
+/// `states`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TableEdge {
+    pub event: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl TransitionTable {
+    /// The index of the state called `name`.
+    ///
+    /// # Panics
+    ///
+    /// If there is no such state.
+    pub const fn state(&self, name: &str) -> usize {
+        match position(self.states, name) {
+            Some(index) => index,
+            None => panic!("no state with that name"),
+        }
+    }
+
+    /// The index of the event called `name`.
+    ///
+    /// # Panics
+    ///
+    /// If there is no such event.
+    pub const fn event(&self, name: &str) -> usize {
+        match position(self.events, name) {
+            Some(index) => index,
+            None => panic!("no event with that name"),
+        }
+    }
+
+    /// Whether `event` has a transition out of the state `from`.
+    pub const fn has_transition(&self, from: &str, event: &str) -> bool {
+        let (from, event) = (self.state(from), self.event(event));
+        let mut index = 0;
+        while index < self.edges.len() {
+            let edge = &self.edges[index];
+            if edge.from == from && edge.event == event {
+                return true;
+            }
+            index += 1;
+        }
+        false
+    }
+
+    /// Whether no transition leaves `state`.
+    pub const fn is_terminal(&self, state: &str) -> bool {
+        let state = self.state(state);
+        let mut index = 0;
+        while index < self.edges.len() {
+            if self.edges[index].from == state {
+                return false;
+            }
+            index += 1;
+        }
+        true
+    }
+
+    /// Whether some sequence of events leads from `from` to `to`.
+    ///
+    /// A state always reaches itself.
+    ///
+    /// # Panics
+    ///
+    /// For machines with more than 128 states.
+    pub const fn can_reach(&self, from: &str, to: &str) -> bool {
+        let reached = self.closure(self.state(from), true);
+        reached & (1 << self.state(to)) != 0
+    }
+
+    /// Whether every state can reach `to`, e.g. an error or shutdown state.
+    ///
+    /// # Panics
+    ///
+    /// For machines with more than 128 states.
+    pub const fn can_reach_from_all(&self, to: &str) -> bool {
+        let reaching = self.closure(self.state(to), false);
+        reaching.count_ones() as usize == self.states.len()
+    }
+
+    /// Whether the initial state reaches every other state.
+    ///
+    /// # Panics
+    ///
+    /// For machines with more than 128 states.
+    pub const fn all_states_reachable(&self) -> bool {
+        let reached = self.closure(self.initial, true);
+        reached.count_ones() as usize == self.states.len()
+    }
+
+    /// Whether each event leads from each state to at most one target.
+    ///
+    /// Choice transitions, and transitions of one event from one state told
+    /// apart only by guards, make a machine non-deterministic.
+    pub const fn is_deterministic(&self) -> bool {
+        let mut first = 0;
+        while first < self.edges.len() {
+            let mut second = first + 1;
+            while second < self.edges.len() {
+                let (a, b) = (&self.edges[first], &self.edges[second]);
+                if a.event == b.event && a.from == b.from && a.to != b.to {
+                    return false;
+                }
+                second += 1;
+            }
+            first += 1;
+        }
+        true
+    }
+
+    /// The state `start` and every state reachable from it, as a bitset; or,
+    /// against the edges, every state that reaches it.
+    const fn closure(&self, start: usize, forward: bool) -> u128 {
+        assert!(
+            self.states.len() <= 128,
+            "const reachability supports at most 128 states"
+        );
+        let mut reached = 1 << start;
+        loop {
+            let mut next = reached;
+            let mut index = 0;
+            while index < self.edges.len() {
+                let edge = &self.edges[index];
+                let (near, far) = if forward {
+                    (edge.from, edge.to)
+                } else {
+                    (edge.to, edge.from)
+                };
+                if reached & (1 << near) != 0 {
+                    next |= 1 << far;
+                }
+                index += 1;
+            }
+            if next == reached {
+                return reached;
+            }
+            reached = next;
+        }
+    }
+}
+
+const fn position(names: &[&str], name: &str) -> Option<usize> {
+    let mut index = 0;
+    while index < names.len() {
+        if str_eq(names[index], name) {
+            return Some(index);
+        }
+        index += 1;
+    }
+    None
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut index = 0;
+    while index < a.len() {
+        if a[index] != b[index] {
+            return false;
+        }
+        index += 1;
+    }
+    true
+}
@@ -0,0 +1,63 @@
+//! Bounding async guards and callbacks with a deadline.
+//!
+//! Machines declared with `callback_timeout` race each awaited guard, action,
+//! and callback against a sleep from an [`AsyncTimer`]. The timer is the only
+//! runtime-specific piece, so this module stays `no_std`.
+
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+use core::time::Duration;
+
+/// A runtime's sleep, used to bound async guards and callbacks.
+///
+/// The `tokio` feature of `state-machines` provides an implementation; for
+/// other runtimes, wrap their sleep function:
+///
+/// ```rust,ignore
+/// pub struct AsyncStd;
+///
+/// impl AsyncTimer for AsyncStd {
+///     type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+///
+///     fn sleep(duration: Duration) -> Self::Sleep {
+///         Box::pin(async_std::task::sleep(duration))
+///     }
+/// }
+/// ```
+pub trait AsyncTimer {
+    type Sleep: Future<Output = ()>;
+
+    /// A future that completes after `duration`.
+    fn sleep(duration: Duration) -> Self::Sleep;
+}
+
+/// The deadline passed before the future completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl core::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("deadline elapsed")
+    }
+}
+
+/// Run `future` to completion unless `duration` passes first.
+///
+/// The future is polled before the timer, so one that's already ready wins
+/// even with a zero duration. On timeout the future is dropped.
+pub async fn with_timeout<T, F>(duration: Duration, future: F) -> Result<F::Output, Elapsed>
+where
+    T: AsyncTimer,
+    F: Future,
+{
+    let mut future = pin!(future);
+    let mut sleep = pin!(T::sleep(duration));
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        sleep.as_mut().poll(cx).map(|()| Err(Elapsed))
+    })
+    .await
+}
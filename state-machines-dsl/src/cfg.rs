@@ -0,0 +1,164 @@
+//! `#[cfg(...)]` on states, events, and transitions.
+//!
+//! The macro can't evaluate a `cfg` predicate itself: features belong to the
+//! crate being compiled, not to the macro. Instead, every combination of the
+//! predicates in the input is expanded separately (see
+//! `codegen::expand_input`), with the gated entries removed or kept, and each
+//! expansion's items are put behind the matching `#[cfg(all(...))]`. Exactly
+//! one expansion survives compilation.
+//!
+//! ```ignore
+//! states: [Idle, Running, #[cfg(feature = "maintenance")] Servicing],
+//! ```
+//!
+//! An attribute covers the entry it precedes: everything up to the next
+//! comma, or through the entry's first `{ ... }` block. Each combination is
+//! validated on its own, so a transition into a gated state must be gated
+//! too. Attribute lists such as `machine_attrs` are passed through untouched.
+
+use proc_macro2::{Delimiter, Group, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::Result;
+
+/// Distinct predicates allowed per machine; each one doubles the expansions.
+const MAX_PREDICATES: usize = 4;
+
+/// The distinct `cfg` predicates in the input, in order.
+pub fn predicates(input: TokenStream2) -> Result<Vec<TokenStream2>> {
+    let mut predicates = Vec::new();
+    collect(input, &mut predicates)?;
+    Ok(predicates)
+}
+
+/// The input with every `cfg`-gated entry kept or removed, as `enabled`
+/// decides for its predicate.
+pub fn strip(input: TokenStream2, enabled: &dyn Fn(&TokenStream2) -> bool) -> TokenStream2 {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut output = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        if let Some(predicate) = cfg_predicate(&tokens, index) {
+            let end = entry_end(&tokens, index + 2);
+            if enabled(&predicate) {
+                let entry: TokenStream2 = tokens[index + 2..end].iter().cloned().collect();
+                output.extend(strip(entry, enabled));
+            }
+            index = end;
+            continue;
+        }
+
+        match &tokens[index] {
+            TokenTree::Group(group) if !follows_attrs_key(&tokens, index) => {
+                let mut stripped = Group::new(group.delimiter(), strip(group.stream(), enabled));
+                stripped.set_span(group.span());
+                output.push(TokenTree::Group(stripped));
+            }
+            token => output.push(token.clone()),
+        }
+        index += 1;
+    }
+    output.into_iter().collect()
+}
+
+fn collect(input: TokenStream2, predicates: &mut Vec<TokenStream2>) -> Result<()> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    for (index, token) in tokens.iter().enumerate() {
+        if let Some(predicate) = cfg_predicate(&tokens, index) {
+            if !predicates
+                .iter()
+                .any(|known| known.to_string() == predicate.to_string())
+            {
+                if predicates.len() == MAX_PREDICATES {
+                    return Err(syn::Error::new_spanned(
+                        &tokens[index + 1],
+                        format!(
+                            "a machine can use at most {MAX_PREDICATES} distinct `cfg` predicates"
+                        ),
+                    ));
+                }
+                predicates.push(predicate);
+            }
+        } else if let TokenTree::Group(group) = token
+            && !follows_attrs_key(&tokens, index)
+        {
+            collect(group.stream(), predicates)?;
+        }
+    }
+    Ok(())
+}
+
+/// The predicate of a `#[cfg(...)]` attribute starting at `index`.
+fn cfg_predicate(tokens: &[TokenTree], index: usize) -> Option<TokenStream2> {
+    let (TokenTree::Punct(pound), Some(TokenTree::Group(attr))) =
+        (&tokens[index], tokens.get(index + 1))
+    else {
+        return None;
+    };
+    if pound.as_char() != '#' || attr.delimiter() != Delimiter::Bracket {
+        return None;
+    }
+    let mut inner = attr.stream().into_iter();
+    match (inner.next(), inner.next(), inner.next()) {
+        (Some(TokenTree::Ident(name)), Some(TokenTree::Group(args)), None)
+            if name == "cfg" && args.delimiter() == Delimiter::Parenthesis =>
+        {
+            Some(args.stream())
+        }
+        _ => None,
+    }
+}
+
+/// Where the entry starting at `start` ends: after the next comma, or after
+/// its first brace block (and a comma following it).
+fn entry_end(tokens: &[TokenTree], start: usize) -> usize {
+    let is_comma = |token: Option<&TokenTree>| matches!(token, Some(TokenTree::Punct(p)) if p.as_char() == ',');
+    let mut index = start;
+    while index < tokens.len() {
+        if is_comma(tokens.get(index)) {
+            return index + 1;
+        }
+        if matches!(&tokens[index], TokenTree::Group(group) if group.delimiter() == Delimiter::Brace)
+        {
+            index += 1;
+            return if is_comma(tokens.get(index)) {
+                index + 1
+            } else {
+                index
+            };
+        }
+        index += 1;
+    }
+    index
+}
+
+/// Whether the group at `index` is the value of a `*_attrs:` key, whose
+/// attributes belong to the generated items.
+fn follows_attrs_key(tokens: &[TokenTree], index: usize) -> bool {
+    index >= 2
+        && matches!(&tokens[index - 1], TokenTree::Punct(p) if p.as_char() == ':')
+        && matches!(&tokens[index - 2], TokenTree::Ident(key) if key.to_string().ends_with("attrs"))
+}
+
+/// Put `item` behind `attr`.
+pub fn gate(mut item: syn::Item, attr: &syn::Attribute) -> TokenStream2 {
+    let attrs = match &mut item {
+        syn::Item::Const(item) => &mut item.attrs,
+        syn::Item::Enum(item) => &mut item.attrs,
+        syn::Item::ExternCrate(item) => &mut item.attrs,
+        syn::Item::Fn(item) => &mut item.attrs,
+        syn::Item::ForeignMod(item) => &mut item.attrs,
+        syn::Item::Impl(item) => &mut item.attrs,
+        syn::Item::Macro(item) => &mut item.attrs,
+        syn::Item::Mod(item) => &mut item.attrs,
+        syn::Item::Static(item) => &mut item.attrs,
+        syn::Item::Struct(item) => &mut item.attrs,
+        syn::Item::Trait(item) => &mut item.attrs,
+        syn::Item::TraitAlias(item) => &mut item.attrs,
+        syn::Item::Type(item) => &mut item.attrs,
+        syn::Item::Union(item) => &mut item.attrs,
+        syn::Item::Use(item) => &mut item.attrs,
+        _ => return quote! { #attr #item },
+    };
+    attrs.insert(0, attr.clone());
+    quote! { #item }
+}
@@ -0,0 +1,540 @@
+//! `extends: Base` builds a machine from another definition.
+//!
+//! A proc macro only sees its own invocation, so a base marked
+//! `extendable: true` also expands to a hidden `__extend_{Name}!` macro
+//! holding its definition. A machine with `extends: Base` expands to a call
+//! of that macro, which invokes `state_machine!` again with the base's
+//! definition in front of the child's:
+//!
+//! ```ignore
+//! state_machine! { name: Sensor, extendable: true, ... }
+//! state_machine! { name: Thermometer, extends: Sensor, ... }
+//! // becomes
+//! __extend_Sensor! { (::state_machines::state_machine) name: Thermometer, ... }
+//! // becomes
+//! ::state_machines::state_machine! { extends: { /* Sensor */ }, name: Thermometer, ... }
+//! ```
+//!
+//! The second expansion merges the two definitions as tokens, before any
+//! other processing, so `#[cfg]` entries and error spans from either side
+//! are kept. The merge rules:
+//!
+//! - `name`, `module`, and `extendable` aren't inherited.
+//! - `states` and `events` entries are matched by name: the child's replace
+//!   the base's in place, and new ones are added at the end.
+//! - An event declared by both keeps the base's keys, with the child's
+//!   replacing the ones it sets. The child's transitions replace the base's
+//!   from the source states they name, and the base's other transitions stay.
+//! - `callbacks` lists are concatenated, base first.
+//! - `params` are matched by name, like states.
+//! - Any other key the child sets replaces the base's.
+
+use crate::{fragment, struct_form};
+use proc_macro2::{Delimiter, Group, Ident, Spacing, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+use syn::Result;
+use syn::parse::{ParseStream, Parser};
+
+/// Keys that stay with the machine that declares them.
+const NOT_INHERITED: &[&str] = &["name", "module", "extendable", "extends"];
+
+/// A definition the macro input refers to, but doesn't contain yet.
+pub enum Needed {
+    /// The `extendable: true` machine named by `extends:`.
+    Base(syn::Path),
+    /// The next fragment named by `include:`.
+    Fragment(syn::Path),
+}
+
+/// The macro input once any `extends:` and `include:` have been dealt with.
+pub enum Resolution {
+    /// A definition hasn't been merged in yet: expand to `call`, a call of
+    /// its macro, which calls back with the definition added.
+    Pending {
+        /// What's missing; `cargo-state-machines` looks it up by path.
+        needs: Needed,
+        call: TokenStream2,
+    },
+    /// The complete definition, without `extends`, `extendable`, and
+    /// `include`.
+    Ready {
+        tokens: TokenStream2,
+        extendable: bool,
+    },
+}
+
+/// Merge the base named by `extends:` and the fragments named by `include:`
+/// into the input, or hand the input to the macro of the next one that
+/// hasn't been merged yet.
+pub fn resolve(input: TokenStream2) -> Result<Resolution> {
+    let entries = split(dsl(input)?);
+    let mut base = None;
+    let mut base_path = None;
+    let mut extendable = false;
+    let mut includes = Vec::new();
+    let mut fragments = Vec::new();
+    let mut rest = Vec::new();
+    for entry in &entries {
+        match key(entry).as_deref() {
+            Some("extends") => match value(entry) {
+                [TokenTree::Group(group)] if group.delimiter() == Delimiter::Brace => {
+                    base = Some(rehome(group.stream()));
+                }
+                path => base_path = Some(syn::parse2::<syn::Path>(path.iter().cloned().collect())?),
+            },
+            Some("extendable") => {
+                let value: syn::LitBool = syn::parse2(value(entry).iter().cloned().collect())?;
+                extendable = value.value();
+            }
+            Some("include") => includes = fragment::includes(value(entry))?,
+            Some(fragment::RESOLVED) => fragments.push(rehome(last_group(entry).stream())),
+            _ => rest.push(entry.clone()),
+        }
+    }
+
+    // The callback names the macro so the second expansion has the caller's
+    // hygiene, not the definition macro's
+    let callback = quote! { (::state_machines::state_machine) };
+    if let (None, Some(path)) = (&base, &base_path) {
+        let others = entries
+            .iter()
+            .filter(|entry| key(entry).as_deref() != Some("extends"))
+            .cloned()
+            .collect();
+        let others = join(others);
+        let definition = renamed(path, definition_macro);
+        return Ok(Resolution::Pending {
+            needs: Needed::Base(path.clone()),
+            call: quote! { #definition! { #callback #others } },
+        });
+    }
+    if let Some(next) = includes.get(fragments.len()) {
+        let all = join(entries);
+        let definition = renamed(&next.path, fragment::macro_name);
+        return Ok(Resolution::Pending {
+            needs: Needed::Fragment(next.path.clone()),
+            call: quote! { #definition! { #callback #all } },
+        });
+    }
+
+    let mut child = join(rest);
+    for (include, fragment) in includes.iter().zip(fragments) {
+        child = fragment::include(child, fragment, include.prefix.as_ref())?;
+    }
+    let tokens = match base {
+        Some(base) => merge(base, child)?,
+        None => child,
+    };
+    Ok(Resolution::Ready { tokens, extendable })
+}
+
+/// Tokens passed back through a definition macro, with the call site's
+/// hygiene. The `macro_rules!` body they come from would otherwise hide the
+/// generated methods' `self` from code spanned by them, like guard calls.
+fn rehome(tokens: TokenStream2) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut rehomed = Group::new(group.delimiter(), rehome(group.stream()));
+                rehomed.set_span(group.span());
+                token = TokenTree::Group(rehomed);
+            }
+            token.set_span(token.span().resolved_at(Span::call_site()));
+            token
+        })
+        .collect()
+}
+
+/// `path` with its last segment renamed by `rename`.
+fn renamed(path: &syn::Path, rename: fn(&Ident) -> Ident) -> syn::Path {
+    let mut path = path.clone();
+    let last = path.segments.last_mut().expect("paths have a segment");
+    last.ident = rename(&last.ident);
+    path
+}
+
+/// The `__extend_{Name}!` macro of an `extendable: true` machine, holding
+/// its complete definition.
+pub fn definition(tokens: &TokenStream2) -> Result<TokenStream2> {
+    let Some(name) = split(tokens.clone())
+        .into_iter()
+        .find(|entry| key(entry).as_deref() == Some("name"))
+    else {
+        // The parser reports the missing name
+        return Ok(TokenStream2::new());
+    };
+    let name: Ident = syn::parse2(value(&name).iter().cloned().collect())?;
+    let macro_name = definition_macro(&name);
+    Ok(quote! {
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #macro_name {
+            (($($callback:tt)*) $($child:tt)*) => {
+                $($callback)*! { extends: { #tokens }, $($child)* }
+            };
+        }
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #macro_name;
+    })
+}
+
+/// Merge a child definition into its base's.
+pub fn merge(base: TokenStream2, child: TokenStream2) -> Result<TokenStream2> {
+    let mut merged: Vec<Vec<TokenTree>> = split(dsl(base)?)
+        .into_iter()
+        .filter(|entry| !key(entry).is_some_and(|key| NOT_INHERITED.contains(&key.as_str())))
+        .collect();
+
+    for entry in split(dsl(child)?) {
+        let key = key(&entry);
+        let Some(existing) = merged.iter_mut().find(|known| self::key(known) == key) else {
+            merged.push(entry);
+            continue;
+        };
+        match key.as_deref() {
+            Some("states") => {
+                let states = merge_list(last_group(existing), last_group(&entry), merge_replace);
+                replace_last(existing, states);
+            }
+            Some("events") => {
+                let events = merge_list(last_group(existing), last_group(&entry), merge_event);
+                replace_last(existing, events);
+            }
+            Some("params") => {
+                let params = merge_list(last_group(existing), last_group(&entry), merge_replace);
+                replace_last(existing, params);
+            }
+            Some("callbacks") => {
+                let lists = [last_group(existing), last_group(&entry)]
+                    .map(|group| group.stream().into_iter().collect::<Vec<_>>());
+                let callbacks = lists.into_iter().filter(|list| !list.is_empty()).collect();
+                replace_last(existing, join(callbacks));
+            }
+            _ => *existing = entry,
+        }
+    }
+    Ok(join(merged))
+}
+
+/// `__extend_{Name}`.
+fn definition_macro(name: &Ident) -> Ident {
+    format_ident!("__extend_{}", name, span = name.span())
+}
+
+/// The input in the DSL, rewriting the struct form.
+pub fn dsl(input: TokenStream2) -> Result<TokenStream2> {
+    let rewrite = |input: ParseStream<'_>| {
+        if struct_form::is_struct_form(input) {
+            struct_form::desugar(input)
+        } else {
+            input.parse()
+        }
+    };
+    rewrite.parse2(input)
+}
+
+/// Split a list into its entries: up to a comma, or through a brace block
+/// not followed by one. Commas inside `<...>` don't count.
+pub fn split(tokens: TokenStream2) -> Vec<Vec<TokenTree>> {
+    let mut entries = Vec::new();
+    let mut entry = Vec::new();
+    let mut depth = 0usize;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' && depth == 0 => {
+                entries.push(std::mem::take(&mut entry));
+                continue;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '<' => depth += 1,
+            TokenTree::Punct(punct) if punct.as_char() == '>' => {
+                // `->` in a function type isn't a closing bracket
+                let arrow = matches!(entry.last(), Some(TokenTree::Punct(dash)) if dash.as_char() == '-' && dash.spacing() == Spacing::Joint);
+                if !arrow {
+                    depth = depth.saturating_sub(1);
+                }
+            }
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace && depth == 0 => {
+                entry.push(token);
+                let ends = !matches!(tokens.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == ',');
+                if ends {
+                    entries.push(std::mem::take(&mut entry));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        entry.push(token);
+    }
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+    entries.retain(|entry| !entry.is_empty());
+    entries
+}
+
+/// Entries separated by commas.
+pub fn join(entries: Vec<Vec<TokenTree>>) -> TokenStream2 {
+    let entries = entries
+        .into_iter()
+        .map(|entry| entry.into_iter().collect::<TokenStream2>());
+    quote! { #(#entries),* }
+}
+
+/// The name an entry is matched by: its key, or the name of a state or
+/// event, after any attributes and `superstate`.
+pub fn key(entry: &[TokenTree]) -> Option<String> {
+    let mut tokens = entry.iter();
+    loop {
+        match tokens.next()? {
+            TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                tokens.next();
+            }
+            TokenTree::Ident(ident) if ident == "superstate" || ident == "state" => {}
+            TokenTree::Ident(ident) => return Some(ident.to_string()),
+            _ => return None,
+        }
+    }
+}
+
+/// An entry's value: everything after its key and `:`.
+pub fn value(entry: &[TokenTree]) -> &[TokenTree] {
+    let key = entry
+        .iter()
+        .position(|token| matches!(token, TokenTree::Ident(_)))
+        .map_or(0, |index| index + 1);
+    match entry.get(key) {
+        Some(TokenTree::Punct(colon)) if colon.as_char() == ':' => &entry[key + 1..],
+        _ => &entry[key..],
+    }
+}
+
+/// The group closing an entry, like the `[...]` of `states: [...]`.
+pub fn last_group(entry: &[TokenTree]) -> &Group {
+    match entry.last() {
+        Some(TokenTree::Group(group)) => group,
+        _ => unreachable!("merged entries end in a group"),
+    }
+}
+
+/// Swap the contents of the group closing `entry`.
+pub fn replace_last(entry: &mut [TokenTree], stream: TokenStream2) {
+    if let Some(TokenTree::Group(group)) = entry.last_mut() {
+        let mut replaced = Group::new(group.delimiter(), stream);
+        replaced.set_span(group.span());
+        *group = replaced;
+    }
+}
+
+/// Merge two lists of named entries: `merge` combines an entry declared in
+/// both, and the child's new entries go at the end.
+pub fn merge_list(
+    base: &Group,
+    child: &Group,
+    merge: fn(Vec<TokenTree>, Vec<TokenTree>) -> Vec<TokenTree>,
+) -> TokenStream2 {
+    let mut merged = split(base.stream());
+    for entry in split(child.stream()) {
+        let name = key(&entry);
+        match merged.iter().position(|known| key(known) == name) {
+            Some(index) => {
+                let base = std::mem::take(&mut merged[index]);
+                merged[index] = merge(base, entry);
+            }
+            None => merged.push(entry),
+        }
+    }
+    join(merged)
+}
+
+fn merge_replace(_base: Vec<TokenTree>, child: Vec<TokenTree>) -> Vec<TokenTree> {
+    child
+}
+
+/// An event declared by both machines.
+pub fn merge_event(base: Vec<TokenTree>, child: Vec<TokenTree>) -> Vec<TokenTree> {
+    if !matches!(base.last(), Some(TokenTree::Group(_)))
+        || !matches!(child.last(), Some(TokenTree::Group(_)))
+    {
+        // Malformed; let the parser report it
+        return child;
+    }
+
+    let child_keys = split(last_group(&child).stream());
+    let covered: Vec<String> = child_keys
+        .iter()
+        .filter(|entry| key(entry).as_deref() == Some("transition"))
+        .flat_map(|transition| sources(transition))
+        .map(|(name, _)| name)
+        .collect();
+
+    let mut keys = Vec::new();
+    for mut entry in split(last_group(&base).stream()) {
+        if key(&entry).as_deref() != Some("transition") {
+            keys.push(entry);
+            continue;
+        }
+        let sources = sources(&entry);
+        let kept: Vec<Vec<TokenTree>> = sources
+            .iter()
+            .filter(|(name, _)| !covered.contains(name))
+            .map(|(_, tokens)| tokens.clone())
+            .collect();
+        if kept.len() == sources.len() {
+            keys.push(entry);
+        } else if !kept.is_empty() {
+            let kept = join(kept);
+            let block = split(last_group(&entry).stream())
+                .into_iter()
+                .map(|field| {
+                    if key(&field).as_deref() == Some("from") {
+                        let mut from: Vec<TokenTree> =
+                            field[..field.len() - value(&field).len()].to_vec();
+                        from.push(TokenTree::Group(Group::new(
+                            Delimiter::Bracket,
+                            kept.clone(),
+                        )));
+                        from
+                    } else {
+                        field
+                    }
+                })
+                .collect();
+            replace_last(&mut entry, join(block));
+            keys.push(entry);
+        }
+    }
+    for entry in child_keys {
+        let name = key(&entry);
+        match keys.iter().position(|known| key(known) == name) {
+            Some(index) if name.as_deref() != Some("transition") => keys[index] = entry,
+            _ => keys.push(entry),
+        }
+    }
+
+    // The child's header (attributes, name, and code) if it has more than a name
+    let header_len = |entry: &[TokenTree]| entry.len() - 1;
+    let mut event = if header_len(&child) > 1 {
+        child[..header_len(&child)].to_vec()
+    } else {
+        base[..header_len(&base)].to_vec()
+    };
+    let mut block = Group::new(Delimiter::Brace, join(keys));
+    block.set_span(last_group(&child).span());
+    event.push(TokenTree::Group(block));
+    event
+}
+
+/// The source states of a `transition: { from: ... }` entry, by name, with
+/// the tokens of each.
+fn sources(transition: &[TokenTree]) -> Vec<(String, Vec<TokenTree>)> {
+    let Some(TokenTree::Group(block)) = transition.last() else {
+        return Vec::new();
+    };
+    let Some(from) = split(block.stream())
+        .into_iter()
+        .find(|field| key(field).as_deref() == Some("from"))
+    else {
+        return Vec::new();
+    };
+    let states = match value(&from) {
+        [TokenTree::Group(list)] if list.delimiter() == Delimiter::Bracket => split(list.stream()),
+        single => vec![single.to_vec()],
+    };
+    states
+        .into_iter()
+        .filter_map(|state| Some((key(&state)?, state)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(base: TokenStream2, child: TokenStream2) -> String {
+        merge(base, child).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_child_keys_replace_the_base() {
+        let base = quote! {
+            name: Base, module: base, context: HashMap<String, u32>, initial: Idle,
+            states: [Idle, Busy], extendable: true,
+        };
+        let child = quote! { name: Child, initial: Busy };
+        assert_eq!(
+            merged(base, child),
+            quote! { context: HashMap<String, u32>, initial: Busy, states: [Idle, Busy], name: Child }
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_states_and_events_merge_by_name() {
+        let base = quote! {
+            states: [Idle, Busy, superstate Up { state A }],
+            events {
+                go { guards: [ready], transition: { from: [Idle, Busy], to: Busy } }
+                stop { transition: { from: Busy, to: Idle } }
+            }
+            callbacks: { after_transition [log] }
+        };
+        let child = quote! {
+            states: [Busy { max_duration: 5s }, Down],
+            events {
+                go { before: [warm_up], transition: { from: Idle, to: Down } }
+                halt { transition: { from: Down, to: Idle } }
+            }
+            callbacks: { after_transition [audit] }
+        };
+        assert_eq!(
+            merged(base, child),
+            quote! {
+                states: [Idle, Busy { max_duration: 5s }, superstate Up { state A }, Down],
+                events {
+                    go {
+                        guards: [ready],
+                        transition: { from: [Busy], to: Busy },
+                        before: [warm_up],
+                        transition: { from: Idle, to: Down }
+                    },
+                    stop { transition: { from: Busy, to: Idle } },
+                    halt { transition: { from: Down, to: Idle } }
+                },
+                callbacks: { after_transition [log], after_transition [audit] }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_fully_covered_transitions_are_dropped() {
+        let base = quote! {
+            events { go = 3 { transition: { from: Idle, to: Busy } } }
+        };
+        let child = quote! {
+            events { go { transition: { from: [Idle], to: Down } } }
+        };
+        assert_eq!(
+            merged(base, child),
+            quote! { events { go = 3 { transition: { from: [Idle], to: Down } } } }.to_string()
+        );
+    }
+
+    #[test]
+    fn test_resolve_calls_the_base_definition() {
+        let input = quote! { name: Child, extends: devices::Base, initial: Idle };
+        let Resolution::Pending { call, .. } = resolve(input).unwrap() else {
+            panic!("expected a call of the base's macro");
+        };
+        assert_eq!(
+            call.to_string(),
+            quote! {
+                devices::__extend_Base! { (::state_machines::state_machine) name: Child, initial: Idle }
+            }
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! `fragment!`: states and events shared by several machines.
+//!
+//! A fragment is a partial definition, with only `states`, `events`, and
+//! `callbacks`, that machines pull in with `include:`. Like an `extends:`
+//! base (see `extends`), it expands to a hidden `__fragment_{Name}!` macro
+//! that calls `state_machine!` back with the fragment's definition added as
+//! a `__fragment: { ... }` entry:
+//!
+//! ```ignore
+//! fragment! { name: Retryable, states: [Failed, Retrying], events { ... } }
+//!
+//! state_machine! {
+//!     name: Upload,
+//!     include: [Retryable, Retryable { prefix: Sync }],
+//!     ...
+//! }
+//! ```
+//!
+//! With a `prefix`, the fragment's states and events are renamed before it's
+//! merged in: `SyncFailed` and `sync_retry`. Every identifier in the
+//! fragment that names one of its states or events is renamed; names it
+//! doesn't declare, such as the machine's own states, are left alone. The
+//! machine's entries win over the fragment's: a state it also declares
+//! replaces the fragment's, and an event it also declares keeps its own keys
+//! and transitions on top of the fragment's (as with `extends:`).
+
+use crate::extends::{join, key, last_group, merge_event, merge_list, replace_last, split, value};
+use crate::parser::unexpected_key;
+use crate::utils::to_snake_case;
+use proc_macro2::{Group, Ident, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+use std::collections::HashMap;
+use syn::Result;
+
+const FRAGMENT_KEYS: &[&str] = &["name", "states", "events", "callbacks"];
+const INCLUDE_KEYS: &[&str] = &["prefix"];
+
+/// The key a fragment's definition is passed back to `state_machine!` under.
+pub const RESOLVED: &str = "__fragment";
+
+/// One entry of `include: [...]`.
+pub struct Include {
+    pub path: syn::Path,
+    pub prefix: Option<Ident>,
+}
+
+/// Expand `fragment! { ... }` to its definition macro.
+pub fn expand(input: TokenStream2) -> Result<TokenStream2> {
+    let (name, body) = definition(input)?;
+    let macro_name = macro_name(&name);
+    let resolved = Ident::new(RESOLVED, Span::call_site());
+    Ok(quote! {
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #macro_name {
+            (($($callback:tt)*) $($host:tt)*) => {
+                $($callback)*! { $($host)*, #resolved: { #body } }
+            };
+        }
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #macro_name;
+    })
+}
+
+/// A fragment's name, and its definition without it.
+pub fn definition(input: TokenStream2) -> Result<(Ident, TokenStream2)> {
+    let mut name = None;
+    let mut body = Vec::new();
+    for entry in split(input) {
+        let Some(TokenTree::Ident(key)) = entry.first() else {
+            let first = entry.first().expect("entries aren't empty");
+            return Err(syn::Error::new(first.span(), "expected a fragment key"));
+        };
+        match key.to_string().as_str() {
+            "name" => {
+                name = Some(syn::parse2::<Ident>(
+                    value(&entry).iter().cloned().collect(),
+                )?)
+            }
+            "states" | "events" | "callbacks" => body.push(entry),
+            _ => return Err(unexpected_key(key, "fragment key", FRAGMENT_KEYS)),
+        }
+    }
+    let name = name.ok_or_else(|| syn::Error::new(Span::call_site(), "missing `name` field"))?;
+    Ok((name, join(body)))
+}
+
+/// `__fragment_{Name}`.
+pub fn macro_name(name: &Ident) -> Ident {
+    format_ident!("__fragment_{}", name, span = name.span())
+}
+
+/// Parse the value of `include:`: `[Retryable, Timed { prefix: Upload }]`.
+pub fn includes(list: &[TokenTree]) -> Result<Vec<Include>> {
+    let list = match list {
+        [TokenTree::Group(list)] if list.delimiter() == proc_macro2::Delimiter::Bracket => {
+            list.stream()
+        }
+        single => single.iter().cloned().collect(),
+    };
+    split(list)
+        .into_iter()
+        .map(|entry| {
+            let (path, options) = match entry.split_last() {
+                Some((TokenTree::Group(options), path))
+                    if options.delimiter() == proc_macro2::Delimiter::Brace =>
+                {
+                    (path.to_vec(), Some(options))
+                }
+                _ => (entry, None),
+            };
+            let path = syn::parse2(path.into_iter().collect())?;
+            let mut prefix = None;
+            for option in options
+                .map(|group| split(group.stream()))
+                .unwrap_or_default()
+            {
+                let Some(TokenTree::Ident(key)) = option.first() else {
+                    continue;
+                };
+                match key.to_string().as_str() {
+                    "prefix" => {
+                        prefix = Some(syn::parse2(value(&option).iter().cloned().collect())?)
+                    }
+                    _ => return Err(unexpected_key(key, "include option", INCLUDE_KEYS)),
+                }
+            }
+            Ok(Include { path, prefix })
+        })
+        .collect()
+}
+
+/// Merge a fragment into a machine's definition, renaming it with `prefix`
+/// first.
+pub fn include(
+    host: TokenStream2,
+    fragment: TokenStream2,
+    prefix: Option<&Ident>,
+) -> Result<TokenStream2> {
+    let fragment = match prefix {
+        Some(prefix) => rename(fragment.clone(), &renames(&fragment, prefix)),
+        None => fragment,
+    };
+
+    let mut merged = split(host);
+    for entry in split(fragment) {
+        let key = key(&entry);
+        let Some(existing) = merged.iter_mut().find(|known| self::key(known) == key) else {
+            merged.push(entry);
+            continue;
+        };
+        let list = match key.as_deref() {
+            // The machine's own entries win
+            Some("states") => merge_list(last_group(existing), last_group(&entry), |host, _| host),
+            Some("events") => merge_list(
+                last_group(existing),
+                last_group(&entry),
+                |host, fragment| merge_event(fragment, host),
+            ),
+            _ => {
+                let lists = [last_group(existing), last_group(&entry)]
+                    .map(|group| group.stream().into_iter().collect::<Vec<_>>());
+                join(lists.into_iter().filter(|list| !list.is_empty()).collect())
+            }
+        };
+        replace_last(existing, list);
+    }
+    Ok(join(merged))
+}
+
+/// The new name of each state and event the fragment declares.
+fn renames(fragment: &TokenStream2, prefix: &Ident) -> HashMap<String, Ident> {
+    let mut renames = HashMap::new();
+    for entry in split(fragment.clone()) {
+        match key(&entry).as_deref() {
+            Some("states") => state_renames(last_group(&entry), prefix, &mut renames),
+            Some("events") => {
+                let prefix = to_snake_case(&prefix.to_string());
+                for event in split(last_group(&entry).stream()) {
+                    if let Some(name) = key(&event) {
+                        let renamed = format_ident!("{}_{}", prefix, name);
+                        renames.insert(name, renamed);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    renames
+}
+
+/// Superstates and their `state` entries, at any depth.
+fn state_renames(list: &Group, prefix: &Ident, renames: &mut HashMap<String, Ident>) {
+    for state in split(list.stream()) {
+        let Some(name) = key(&state) else {
+            continue;
+        };
+        let superstate = state
+            .iter()
+            .any(|token| matches!(token, TokenTree::Ident(ident) if ident == "superstate"));
+        // Inside a superstate block, `initial:` and `doc:` aren't states
+        let declares = superstate
+            || state
+                .iter()
+                .any(|token| matches!(token, TokenTree::Ident(ident) if ident == "state"))
+            || list.delimiter() == proc_macro2::Delimiter::Bracket;
+        if !declares {
+            continue;
+        }
+        renames.insert(name.clone(), format_ident!("{}{}", prefix, name));
+        if superstate && let Some(TokenTree::Group(block)) = state.last() {
+            state_renames(block, prefix, renames);
+        }
+    }
+}
+
+/// `tokens` with every identifier in `renames` renamed, keeping its span.
+fn rename(tokens: TokenStream2, renames: &HashMap<String, Ident>) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) => match renames.get(&ident.to_string()) {
+                Some(renamed) => TokenTree::Ident(Ident::new(&renamed.to_string(), ident.span())),
+                None => TokenTree::Ident(ident),
+            },
+            TokenTree::Group(group) => {
+                let mut renamed = Group::new(group.delimiter(), rename(group.stream(), renames));
+                renamed.set_span(group.span());
+                TokenTree::Group(renamed)
+            }
+            other => other,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retryable() -> TokenStream2 {
+        quote! {
+            states: [Failed, superstate Recovering { state Retrying, initial: Retrying }],
+            events {
+                retry { transition: { from: Failed, to: Retrying } }
+                give_up { transition: { from: [Failed, Recovering], to: Idle } }
+            }
+            callbacks: { after_transition [{ name: log_retry, on: [retry] }] }
+        }
+    }
+
+    #[test]
+    fn test_prefix_renames_declared_states_and_events() {
+        let host = quote! { name: Upload, states: [Idle] };
+        let prefix = format_ident!("Sync");
+        assert_eq!(
+            include(host, retryable(), Some(&prefix))
+                .unwrap()
+                .to_string(),
+            quote! {
+                name: Upload,
+                states: [
+                    Idle,
+                    SyncFailed,
+                    superstate SyncRecovering { state SyncRetrying, initial: SyncRetrying }
+                ],
+                events {
+                    sync_retry { transition: { from: SyncFailed, to: SyncRetrying } }
+                    sync_give_up { transition: { from: [SyncFailed, SyncRecovering], to: Idle } }
+                },
+                callbacks: { after_transition [{ name: log_retry, on: [sync_retry] }] }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_machine_entries_win() {
+        let host = quote! {
+            states: [Failed { max_duration: 5s }],
+            events { retry { guards: [online] } }
+        };
+        assert_eq!(
+            include(host, retryable(), None).unwrap().to_string(),
+            quote! {
+                states: [
+                    Failed { max_duration: 5s },
+                    superstate Recovering { state Retrying, initial: Retrying }
+                ],
+                events {
+                    retry { transition: { from: Failed, to: Retrying }, guards: [online] },
+                    give_up { transition: { from: [Failed, Recovering], to: Idle } }
+                },
+                callbacks: { after_transition [{ name: log_retry, on: [retry] }] }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_includes_take_a_prefix() {
+        let includes = includes(
+            &quote! { [Retryable, base::Timed { prefix: Upload }] }
+                .into_iter()
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        assert_eq!(includes.len(), 2);
+        assert!(includes[0].prefix.is_none());
+        assert_eq!(includes[1].prefix.as_ref().unwrap(), "Upload");
+    }
+}
@@ -0,0 +1,16 @@
+//! The `state_machine!` DSL: its parser, definition types, and validation.
+//!
+//! This is an internal crate. `state-machines-macro` generates code from the
+//! [`types::StateMachine`] parsed here, and `cargo-state-machines` lints the
+//! same definitions, so both agree on what a definition means. It has no
+//! stable API; depend on `state-machines` instead.
+
+pub mod cfg;
+pub mod extends;
+pub mod fragment;
+pub mod params;
+pub mod parser;
+pub mod struct_form;
+pub mod types;
+pub mod utils;
+pub mod validation;
@@ -0,0 +1,123 @@
+//! `params: { ... }`: named constants substituted into the definition.
+//!
+//! Parameters let one definition carry the numbers that vary between
+//! deployments, such as retry counts and timeouts, in one place:
+//!
+//! ```ignore
+//! params: { MaxRetries: 3, StartTimeout: 30s },
+//! states: [Idle, Starting { max_duration: StartTimeout }],
+//! events {
+//!     retry { guards: [attempts_below(MaxRetries)], ... }
+//! }
+//! ```
+//!
+//! Substitution is textual: every identifier in the input that names a
+//! parameter is replaced by its value, before the input is parsed, so a
+//! parameter can go wherever its literal could. Values must be literals
+//! (`3`, `-1`, `"eu"`, `30s`). A later entry for the same name wins, so a
+//! `#[cfg]`-gated entry can override a default; an `extends:` child's
+//! entries replace the base's by name as well.
+
+use crate::extends::{join, key, last_group, split, value};
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use std::collections::HashMap;
+use syn::Result;
+
+/// The input with its `params` entry removed and each parameter replaced by
+/// its value.
+pub fn substitute(input: TokenStream2) -> Result<TokenStream2> {
+    let mut params = HashMap::new();
+    let mut rest = Vec::new();
+    for entry in split(input.clone()) {
+        if key(&entry).as_deref() != Some("params") {
+            rest.push(entry);
+            continue;
+        }
+        for param in split(last_group(&entry).stream()) {
+            let Some(TokenTree::Ident(name)) = param.first() else {
+                let span = param.first().map_or_else(Span::call_site, TokenTree::span);
+                return Err(syn::Error::new(span, "expected a parameter name"));
+            };
+            let literal = value(&param);
+            let is_literal = match literal {
+                [TokenTree::Literal(_)] => true,
+                [TokenTree::Punct(minus), TokenTree::Literal(_)] => minus.as_char() == '-',
+                _ => false,
+            };
+            if !is_literal {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!(
+                        "parameter `{name}` must be a literal, e.g. `{name}: 3` or `{name}: 30s`"
+                    ),
+                ));
+            }
+            params.insert(name.to_string(), literal.to_vec());
+        }
+    }
+    if params.is_empty() {
+        return Ok(input);
+    }
+    let rest = join(rest);
+    Ok(replace(rest, &params))
+}
+
+/// `tokens` with every parameter replaced, at any depth.
+fn replace(tokens: TokenStream2, params: &HashMap<String, Vec<TokenTree>>) -> TokenStream2 {
+    let mut output = Vec::new();
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => match params.get(&ident.to_string()) {
+                Some(literal) => output.extend(literal.iter().cloned().map(|mut token| {
+                    // Errors about the value point at its use
+                    token.set_span(ident.span());
+                    token
+                })),
+                None => output.push(TokenTree::Ident(ident)),
+            },
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    proc_macro2::Group::new(group.delimiter(), replace(group.stream(), params));
+                replaced.set_span(group.span());
+                output.push(TokenTree::Group(replaced));
+            }
+            other => output.push(other),
+        }
+    }
+    output.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_params_are_replaced_and_removed() {
+        let input = quote! {
+            name: Job,
+            params: { MaxRetries: 3, Floor: -1, Timeout: 30s, MaxRetries: 5 },
+            states: [Running { max_duration: Timeout }],
+            events { retry { guards: [below(MaxRetries) && above(Floor)] } }
+        };
+        assert_eq!(
+            substitute(input).unwrap().to_string(),
+            quote! {
+                name: Job,
+                states: [Running { max_duration: 30s }],
+                events { retry { guards: [below(5) && above(-1)] } }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_values_must_be_literals() {
+        let input = quote! { params: { Timeout: Duration::from_secs(1) } };
+        let err = substitute(input).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("parameter `Timeout` must be a literal")
+        );
+    }
+}
@@ -0,0 +1,2287 @@
+//! Parsing logic for the state machine macro.
+//!
+//! This module handles converting the macro input tokens into our
+//! internal data structures. It includes parsers for:
+//! - The main StateMachine structure (via syn::Parse trait)
+//! - States section (including nested superstates)
+//! - Events and transitions
+//! - Helper utilities for parsing lists and sets
+//!
+//! The struct form (`Machine { ... }`) is rewritten into the DSL by
+//! `struct_form.rs` and then parsed here.
+
+use crate::struct_form;
+use crate::types::*;
+use proc_macro2::Span;
+use quote::format_ident;
+use std::collections::HashSet;
+use syn::{
+    Ident, LitInt, LitStr, Result, Token, braced, bracketed, parenthesized,
+    parse::{Parse, ParseBuffer, ParseStream},
+};
+
+// The keys each block accepts, listed in "unexpected key" errors
+const MACHINE_KEYS: &[&str] = &[
+    "name",
+    "context",
+    "initial",
+    "states",
+    "events",
+    "callbacks",
+    "async",
+    "dynamic",
+    "mode",
+    "wasm",
+    "http",
+    "compact_codegen",
+    "transition_records",
+    "atomic_state",
+    "data_states",
+    "visibility",
+    "generics",
+    "module",
+    "state_derives",
+    "event_derives",
+    "state_attrs",
+    "machine_attrs",
+    "mailbox",
+    "unhandled",
+    "callback_timeout",
+    "watchdog",
+    "clock",
+    "history",
+    "stats",
+    "persistent",
+    "sink",
+    "identity",
+    "no_default_init",
+    "behavior",
+    "on_error",
+    "evaluate_all_guards",
+];
+const SUPERSTATE_KEYS: &[&str] = &[
+    "state",
+    "superstate",
+    "initial",
+    "doc",
+    "ignore",
+    "requires",
+];
+const STATE_OPTION_KEYS: &[&str] = &["doc", "ignore", "max_duration", "requires"];
+const EVENT_KEYS: &[&str] = &[
+    "transition",
+    "guards",
+    "unless",
+    "before",
+    "after",
+    "after_commit",
+    "around",
+    "payload",
+    "transform",
+    "doc",
+    "alias",
+    "dangerous",
+    "retry",
+];
+const TRANSITION_KEYS: &[&str] = &[
+    "from",
+    "to",
+    "choose",
+    "guards",
+    "unless",
+    "before",
+    "after",
+    "after_commit",
+    "around",
+    "action",
+    "compensate",
+    "emit",
+    "init",
+    "forward",
+];
+const CALLBACK_TIMEOUT_KEYS: &[&str] = &["after", "timer"];
+const ON_ERROR_KEYS: &[&str] = &["handler", "recover", "after"];
+const RETRY_KEYS: &[&str] = &["attempts", "backoff", "timer"];
+const MAILBOX_KEYS: &[&str] = &["capacity", "overflow"];
+const FORWARD_KEYS: &[&str] = &["to", "as"];
+
+/// Implementation of syn::Parse for StateMachine.
+///
+/// This allows us to use `syn::parse_macro_input!(input as StateMachine)`
+/// to parse the entire macro input in one go.
+impl Parse for StateMachine {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        // `Machine { ... }` is the same definition in rustfmt-friendly syntax
+        if struct_form::is_struct_form(input) {
+            return syn::parse2(struct_form::desugar(input)?);
+        }
+
+        // Initialize all fields with defaults or None
+        let mut name = None;
+        let mut initial = None;
+        let mut context = None;
+        let mut states = None;
+        let mut events = None;
+        let mut async_mode = false;
+        let mut dynamic_mode = false;
+        let mut dynamic_key = None;
+        let mut mode = None;
+        let mut compact_codegen = false;
+        let mut transition_records = false;
+        let mut atomic_state = false;
+        let mut data_states = false;
+        let mut mailbox = None;
+        let mut callback_timeout = None;
+        let mut unhandled = UnhandledPolicy::default();
+        let mut on_error = None;
+        let mut watchdog = false;
+        let mut clock = None;
+        let mut history = None;
+        let mut stats = false;
+        let mut evaluate_all_guards = false;
+        let mut persistent = false;
+        let mut sink = None;
+        let mut identity = false;
+        let mut no_default_init = None;
+        let mut behavior = None;
+        let mut visibility: syn::Visibility = syn::parse_quote!(pub);
+        let mut module = None;
+        let mut generics = syn::Generics::default();
+        let mut state_derives = Vec::new();
+        let mut event_derives = Vec::new();
+        let mut state_attrs = Vec::new();
+        let mut machine_attrs = Vec::new();
+        let mut callbacks = GlobalCallbacks::default();
+        let mut wasm_mode = false;
+        let mut http_mode = false;
+        let mut state_storage = Vec::new();
+        let mut state_docs = Vec::new();
+        let mut state_ignores = Vec::new();
+        let mut state_max_durations = Vec::new();
+        let mut state_requirements = Vec::new();
+        let mut state_codes = Vec::new();
+        let mut hierarchy = Hierarchy::default();
+
+        // Parse each key-value pair in the macro input
+        while !input.is_empty() {
+            // Handle the special case of `async` keyword
+            if input.peek(Token![async]) {
+                let _: Token![async] = input.parse()?;
+                input.parse::<Token![:]>()?;
+                let value: syn::LitBool = input.parse()?;
+                async_mode = value.value();
+            } else {
+                let key: Ident = input.parse()?;
+                let key_str = key.to_string();
+
+                match key_str.as_str() {
+                    "dynamic" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        dynamic_mode = value.value();
+                        dynamic_key = Some(key);
+                    }
+                    "mode" => {
+                        parse_colon(input, &key)?;
+                        mode = Some(parse_machine_mode(input)?);
+                    }
+                    "wasm" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        wasm_mode = value.value();
+                    }
+                    "http" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        http_mode = value.value();
+                    }
+                    "compact_codegen" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        compact_codegen = value.value();
+                    }
+                    "transition_records" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        transition_records = value.value();
+                    }
+                    "atomic_state" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        atomic_state = value.value();
+                    }
+                    "data_states" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        data_states = value.value();
+                    }
+                    "visibility" => {
+                        parse_colon(input, &key)?;
+                        visibility = input.parse()?;
+                    }
+                    "generics" => {
+                        parse_colon(input, &key)?;
+                        generics = input.parse()?;
+                    }
+                    "module" => {
+                        parse_colon(input, &key)?;
+                        module = Some(input.parse()?);
+                    }
+                    "state_derives" => {
+                        parse_colon(input, &key)?;
+                        let content;
+                        bracketed!(content in input);
+                        state_derives = parse_path_list(&content)?;
+                    }
+                    "event_derives" => {
+                        parse_colon(input, &key)?;
+                        let content;
+                        bracketed!(content in input);
+                        event_derives = parse_path_list(&content)?;
+                    }
+                    "state_attrs" => {
+                        parse_colon(input, &key)?;
+                        let content;
+                        bracketed!(content in input);
+                        state_attrs = parse_attribute_list(&content)?;
+                    }
+                    "machine_attrs" => {
+                        parse_colon(input, &key)?;
+                        let content;
+                        bracketed!(content in input);
+                        machine_attrs = parse_attribute_list(&content)?;
+                    }
+                    "mailbox" => {
+                        parse_colon(input, &key)?;
+                        let content;
+                        let brace = braced!(content in input);
+                        mailbox = Some(parse_mailbox(&key, &content, brace.span.join())?);
+                    }
+                    "unhandled" => {
+                        parse_colon(input, &key)?;
+                        unhandled = parse_unhandled_policy(input)?;
+                    }
+                    "callback_timeout" => {
+                        parse_colon(input, &key)?;
+                        callback_timeout = Some(parse_callback_timeout(&key, input)?);
+                    }
+                    "watchdog" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        watchdog = value.value();
+                    }
+                    "clock" => {
+                        parse_colon(input, &key)?;
+                        clock = Some(input.parse()?);
+                    }
+                    "history" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitInt = input.parse()?;
+                        if value.base10_parse::<usize>()? == 0 {
+                            return Err(syn::Error::new(
+                                value.span(),
+                                "`history` must keep at least one transition",
+                            ));
+                        }
+                        history = Some(value);
+                    }
+                    "stats" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        stats = value.value();
+                    }
+                    "evaluate_all_guards" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        evaluate_all_guards = value.value();
+                    }
+                    "identity" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        identity = value.value();
+                    }
+                    "no_default_init" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        no_default_init = value.value().then_some(key.clone());
+                    }
+                    "behavior" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        behavior = value.value().then_some(key.clone());
+                    }
+                    "persistent" => {
+                        parse_colon(input, &key)?;
+                        let value: syn::LitBool = input.parse()?;
+                        persistent = value.value();
+                    }
+                    "sink" => {
+                        parse_colon(input, &key)?;
+                        sink = Some(input.parse()?);
+                    }
+                    "on_error" => {
+                        parse_colon(input, &key)?;
+                        on_error = Some(parse_on_error(&key, input)?);
+                    }
+                    "name" => {
+                        parse_colon(input, &key)?;
+                        name = Some(input.parse()?);
+                    }
+                    "initial" => {
+                        parse_colon(input, &key)?;
+                        initial = Some(input.parse()?);
+                    }
+                    "context" => {
+                        parse_colon(input, &key)?;
+                        context = Some(input.parse()?);
+                    }
+                    "states" => {
+                        if input.peek(syn::token::Brace) || input.peek2(syn::token::Brace) {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "`states` takes a list in brackets: `states: [Idle, Running]`",
+                            ));
+                        }
+                        parse_colon(input, &key)?;
+                        let content;
+                        bracketed!(content in input);
+                        let parsed_states = parse_states_section(&content)?;
+                        states = Some(parsed_states.leaves);
+                        hierarchy = parsed_states.hierarchy;
+                        state_storage = parsed_states.storage;
+                        state_docs = parsed_states.docs;
+                        state_ignores = parsed_states.ignores;
+                        state_max_durations = parsed_states.max_durations;
+                        state_requirements = parsed_states.requirements;
+                        state_codes = parsed_states.codes;
+                    }
+                    "events" => {
+                        // Optional colon for backwards compatibility
+                        if input.peek(Token![:]) {
+                            input.parse::<Token![:]>()?;
+                        }
+                        if input.peek(syn::token::Bracket) {
+                            return Err(input.error(
+                                "`events` takes a block in braces: `events { pay { ... } }`",
+                            ));
+                        }
+                        let content;
+                        braced!(content in input);
+                        events = Some(parse_events(&content)?);
+                    }
+                    "callbacks" => {
+                        parse_colon(input, &key)?;
+                        let content;
+                        braced!(content in input);
+                        callbacks = parse_global_callbacks(&content)?;
+                    }
+                    // Legacy fields - parse but ignore
+                    "state" | "action" => {
+                        parse_colon(input, &key)?;
+                        if input.peek(syn::token::Brace) {
+                            let _content;
+                            braced!(_content in input);
+                            // Consume but ignore
+                        } else {
+                            let _: Ident = input.parse()?;
+                        }
+                    }
+                    _ => return Err(unexpected_key(&key, "key", MACHINE_KEYS)),
+                }
+            }
+
+            // Optional trailing comma
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        // `mode` supersedes `dynamic`; runtime and both generate the wrapper
+        if let (Some(key), Some(_)) = (&dynamic_key, &mode) {
+            return Err(syn::Error::new(
+                key.span(),
+                "`dynamic` can't be combined with `mode`; use `mode: both` for both machines",
+            ));
+        }
+        if let Some((_, MachineMode::Runtime | MachineMode::Both)) = mode {
+            dynamic_mode = true;
+        }
+
+        // Build the StateMachine, returning errors for missing required fields
+        let mut machine = Self {
+            name: name.ok_or_else(|| syn::Error::new(Span::call_site(), "missing `name` field"))?,
+            initial: initial
+                .ok_or_else(|| syn::Error::new(Span::call_site(), "missing `initial` field"))?,
+            context,
+            states: states
+                .ok_or_else(|| syn::Error::new(Span::call_site(), "missing `states` field"))?,
+            state_storage,
+            state_docs,
+            state_ignores,
+            state_max_durations,
+            state_requirements,
+            state_codes,
+            hierarchy,
+            events: events.unwrap_or_default(),
+            async_mode,
+            dynamic_mode,
+            mode,
+            wasm_mode,
+            http_mode,
+            compact_codegen,
+            transition_records,
+            atomic_state,
+            data_states,
+            mailbox,
+            callback_timeout,
+            unhandled,
+            on_error,
+            watchdog,
+            clock,
+            history,
+            stats,
+            evaluate_all_guards,
+            persistent,
+            sink,
+            identity,
+            no_default_init,
+            behavior,
+            visibility,
+            module,
+            generics,
+            state_derives,
+            event_derives,
+            state_attrs,
+            machine_attrs,
+            callbacks,
+            transition_graph: TransitionGraph::default(),
+        };
+
+        // Build the transition graph from events
+        machine.build_transition_graph();
+
+        Ok(machine)
+    }
+}
+
+/// Parse the states section of the macro input.
+///
+/// The states section can contain:
+/// - Simple leaf states: `StateA, StateB`
+/// - States with data: `Active(ConnectionData)`
+/// - Superstates: `superstate Running { state Active, state Idle }`
+///
+/// Returns all leaf states, the hierarchy information, and storage specs.
+pub fn parse_states_section(input: &ParseBuffer<'_>) -> Result<ParsedStates> {
+    let mut leaves = Vec::new();
+    let mut hierarchy = Hierarchy::default();
+    let mut seen = HashSet::new();
+    let mut storage_specs = Vec::new();
+    let mut docs = Vec::new();
+    let mut ignores = Vec::new();
+    let mut max_durations = Vec::new();
+    let mut requirements = Vec::new();
+    let mut codes = Vec::new();
+
+    while !input.is_empty() {
+        let ident: Ident = input.parse()?;
+        let key = ident.to_string();
+
+        if key == "superstate" {
+            // Parse a superstate block
+            let superstate_name: Ident = input.parse()?;
+
+            // Check for optional data type
+            let superstate_ty = if input.peek(syn::token::Paren) {
+                let ty_content;
+                parenthesized!(ty_content in input);
+                Some(ty_content.parse()?)
+            } else {
+                None
+            };
+
+            // If the superstate has data, create a storage spec for it
+            if let Some(ty) = superstate_ty.clone() {
+                let field = storage_field_ident(&superstate_name);
+                storage_specs.push(StateStorageSpec {
+                    state_name: superstate_name.clone(),
+                    field,
+                    ty,
+                });
+            }
+
+            // Parse the superstate's contents
+            let mut ancestors = Vec::new();
+            let block_content;
+            braced!(block_content in input);
+            let parsed = parse_superstate_block(
+                &superstate_name,
+                &block_content,
+                &mut hierarchy,
+                &mut leaves,
+                &mut seen,
+                &mut ancestors,
+                &mut storage_specs,
+                &mut docs,
+                &mut ignores,
+                &mut max_durations,
+                &mut requirements,
+                &mut codes,
+            )?;
+
+            // Register this superstate in the hierarchy
+            hierarchy.register_superstate(superstate_name, parsed.descendants, parsed.initial);
+        } else {
+            // Parse a regular leaf state
+
+            // Check for duplicates
+            if !seen.insert(key.clone()) {
+                return Err(syn::Error::new(ident.span(), "duplicate state"));
+            }
+
+            // Check for optional data type
+            let data_ty = if input.peek(syn::token::Paren) {
+                let ty_content;
+                parenthesized!(ty_content in input);
+                Some(ty_content.parse()?)
+            } else {
+                None
+            };
+
+            let state_ident = ident;
+            if let Some(code) = parse_code(input)? {
+                codes.push((state_ident.clone(), code));
+            }
+            let options = parse_state_options(input)?;
+            if let Some(doc) = options.doc {
+                docs.push((state_ident.clone(), doc));
+            }
+            if !options.ignore.is_empty() {
+                ignores.push((state_ident.clone(), options.ignore));
+            }
+            if let Some(limit) = options.max_duration {
+                max_durations.push((state_ident.clone(), limit));
+            }
+            if !options.requires.is_empty() {
+                requirements.push((state_ident.clone(), options.requires));
+            }
+
+            // Register this leaf state (no ancestors at top level)
+            hierarchy.register_leaf(&state_ident, &[]);
+            leaves.push(state_ident.clone());
+
+            // If the state has data, create a storage spec for it
+            if let Some(ty) = data_ty {
+                let field = storage_field_ident(&state_ident);
+                storage_specs.push(StateStorageSpec {
+                    state_name: state_ident.clone(),
+                    field,
+                    ty,
+                });
+            }
+        }
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(ParsedStates {
+        docs,
+        ignores,
+        max_durations,
+        requirements,
+        codes,
+        leaves,
+        hierarchy,
+        storage: storage_specs,
+    })
+}
+
+/// Parse a superstate block.
+///
+/// A superstate can contain:
+/// - Child states: `state Active, state Idle`
+/// - Nested superstates: `superstate SubGroup { ... }`
+/// - Initial state specification: `initial: Active`
+/// - Documentation and ignored events: `doc: "..."`, `ignore: [heartbeat]`
+///
+/// The `ancestors` parameter tracks the chain of parent superstates,
+/// which is used for hierarchical transition resolution.
+#[allow(clippy::too_many_arguments)]
+pub fn parse_superstate_block(
+    superstate_name: &Ident,
+    content: &ParseBuffer<'_>,
+    hierarchy: &mut Hierarchy,
+    leaves: &mut Vec<Ident>,
+    seen: &mut HashSet<String>,
+    ancestors: &mut Vec<Ident>,
+    storage: &mut Vec<StateStorageSpec>,
+    docs: &mut Vec<(Ident, LitStr)>,
+    ignores: &mut Vec<(Ident, Vec<Ident>)>,
+    max_durations: &mut Vec<(Ident, syn::Expr)>,
+    requirements: &mut Vec<(Ident, Vec<syn::Path>)>,
+    codes: &mut Vec<(Ident, LitInt)>,
+) -> Result<SuperstateParseResult> {
+    let mut descendants = Vec::new();
+    let mut initial_spec: Option<Ident> = None;
+
+    // Add ourselves to the ancestor chain
+    ancestors.push(superstate_name.clone());
+
+    while !content.is_empty() {
+        let entry: Ident = content.parse()?;
+        let entry_key = entry.to_string();
+
+        match entry_key.as_str() {
+            "state" => {
+                // Parse a child state
+                let state_ident: Ident = content.parse()?;
+
+                // Check for duplicates
+                if !seen.insert(state_ident.to_string()) {
+                    return Err(syn::Error::new(state_ident.span(), "duplicate state"));
+                }
+
+                // Check for optional data type
+                let data_ty = if content.peek(syn::token::Paren) {
+                    let ty_content;
+                    parenthesized!(ty_content in content);
+                    Some(ty_content.parse()?)
+                } else {
+                    None
+                };
+                if let Some(code) = parse_code(content)? {
+                    codes.push((state_ident.clone(), code));
+                }
+                let options = parse_state_options(content)?;
+                if let Some(doc) = options.doc {
+                    docs.push((state_ident.clone(), doc));
+                }
+                if !options.ignore.is_empty() {
+                    ignores.push((state_ident.clone(), options.ignore));
+                }
+                if let Some(limit) = options.max_duration {
+                    max_durations.push((state_ident.clone(), limit));
+                }
+                if !options.requires.is_empty() {
+                    requirements.push((state_ident.clone(), options.requires));
+                }
+
+                // Register this leaf with its ancestor chain
+                hierarchy.register_leaf(&state_ident, ancestors);
+                leaves.push(state_ident.clone());
+                descendants.push(state_ident.clone());
+
+                // If the state has data, create a storage spec for it
+                if let Some(ty) = data_ty {
+                    let field = storage_field_ident(&state_ident);
+                    storage.push(StateStorageSpec {
+                        state_name: state_ident.clone(),
+                        field,
+                        ty,
+                    });
+                }
+            }
+            "superstate" => {
+                // Parse a nested superstate
+                let nested_name: Ident = content.parse()?;
+
+                // Check for optional data type
+                let super_data_ty = if content.peek(syn::token::Paren) {
+                    let ty_content;
+                    parenthesized!(ty_content in content);
+                    Some(ty_content.parse()?)
+                } else {
+                    None
+                };
+
+                // If the superstate has data, create a storage spec for it
+                if let Some(ty) = super_data_ty.clone() {
+                    let field = storage_field_ident(&nested_name);
+                    storage.push(StateStorageSpec {
+                        state_name: nested_name.clone(),
+                        field,
+                        ty,
+                    });
+                }
+
+                // Parse the nested superstate's contents
+                let block_content;
+                braced!(block_content in content);
+                let nested = parse_superstate_block(
+                    &nested_name,
+                    &block_content,
+                    hierarchy,
+                    leaves,
+                    seen,
+                    ancestors,
+                    storage,
+                    docs,
+                    ignores,
+                    max_durations,
+                    requirements,
+                    codes,
+                )?;
+
+                // Register the nested superstate
+                hierarchy.register_superstate(
+                    nested_name,
+                    nested.descendants.clone(),
+                    nested.initial.clone(),
+                );
+
+                // Add all nested descendants to our descendants
+                descendants.extend(nested.descendants);
+            }
+            "initial" => {
+                // Parse the initial state specification
+                content.parse::<Token![:]>()?;
+                let initial_ident: Ident = content.parse()?;
+                initial_spec = Some(initial_ident);
+            }
+            "doc" => {
+                // Documentation for the superstate itself
+                content.parse::<Token![:]>()?;
+                docs.push((superstate_name.clone(), content.parse()?));
+            }
+            "ignore" => {
+                // Events ignored in every descendant state
+                content.parse::<Token![:]>()?;
+                ignores.push((superstate_name.clone(), parse_ident_list_value(content)?));
+            }
+            "requires" => {
+                // Capabilities needed to enter any descendant state
+                content.parse::<Token![:]>()?;
+                requirements.push((superstate_name.clone(), parse_requires(content)?));
+            }
+            _ => return Err(unexpected_key(&entry, "key", SUPERSTATE_KEYS)),
+        }
+
+        // Optional trailing comma
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    // Remove ourselves from the ancestor chain
+    ancestors.pop();
+
+    // Validate that we have at least one child
+    if descendants.is_empty() {
+        return Err(syn::Error::new(
+            superstate_name.span(),
+            "superstate must declare at least one child state",
+        ));
+    }
+
+    // Determine the initial state
+    let initial_ident = if let Some(initial) = initial_spec {
+        let initial_name = initial.to_string();
+        // Validate that the initial state is a descendant
+        if !descendants.iter().any(|leaf| *leaf == initial_name) {
+            let names = |states: &[Ident]| {
+                states
+                    .iter()
+                    .map(|state| format!("`{state}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            // A nested superstate isn't a state the machine can be in
+            let message = match hierarchy.lookup.get(&initial_name) {
+                Some(nested) if descendants.iter().any(|leaf| nested.contains(leaf)) => format!(
+                    "`initial` must reference a leaf state; `{initial}` is a superstate, so name one of its states: {}",
+                    names(nested)
+                ),
+                _ => format!(
+                    "`initial` must reference a descendant state of `{superstate_name}`: one of {}",
+                    names(&descendants)
+                ),
+            };
+            return Err(syn::Error::new(initial.span(), message));
+        }
+        initial
+    } else {
+        // Default to first descendant if no initial specified
+        descendants[0].clone()
+    };
+
+    Ok(SuperstateParseResult {
+        descendants,
+        initial: initial_ident,
+    })
+}
+
+/// The optional `= 1` code after a state or event name.
+fn parse_code(input: &ParseBuffer<'_>) -> Result<Option<LitInt>> {
+    if !input.peek(Token![=]) {
+        return Ok(None);
+    }
+    input.parse::<Token![=]>()?;
+    let code: LitInt = input
+        .parse()
+        .map_err(|error| syn::Error::new(error.span(), "expected a code, e.g. `Closed = 1`"))?;
+    code.base10_parse::<u32>()?;
+    Ok(Some(code))
+}
+
+/// Parse the optional `{ doc: "...", ignore: [...], max_duration: 30s, requires: [Radio] }`
+/// block after a leaf state.
+pub fn parse_state_options(input: &ParseBuffer<'_>) -> Result<StateOptions> {
+    let mut options = StateOptions::default();
+    if !input.peek(syn::token::Brace) {
+        return Ok(options);
+    }
+
+    let content;
+    braced!(content in input);
+
+    while !content.is_empty() {
+        let key: Ident = content.parse()?;
+        content.parse::<Token![:]>()?;
+
+        match key.to_string().as_str() {
+            "doc" => {
+                options.doc = Some(content.parse()?);
+            }
+            "ignore" => {
+                options.ignore = parse_ident_list_value(&content)?;
+            }
+            "max_duration" => {
+                options.max_duration = Some(parse_duration(&content)?);
+            }
+            "requires" => {
+                options.requires = parse_requires(&content)?;
+            }
+            _ => return Err(unexpected_key(&key, "state option", STATE_OPTION_KEYS)),
+        }
+
+        // Optional trailing comma
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(options)
+}
+
+pub fn parse_events(input: &ParseBuffer<'_>) -> Result<Vec<Event>> {
+    let mut events = Vec::new();
+
+    while !input.is_empty() {
+        // `pub(crate) unsafe force_unlock { ... }` restricts the methods
+        let visibility = if input.peek(Token![pub]) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let unsafety: Option<Token![unsafe]> = input.parse()?;
+        let name: Ident = input.parse()?;
+        if input.peek(Token![:]) {
+            return Err(input.error(format!(
+                "events are declared without a `:`, as in `{name} {{ transition: {{ ... }} }}`"
+            )));
+        }
+        let code = parse_code(input)?;
+        let content;
+        braced!(content in input);
+
+        let mut transitions = Vec::new();
+        let mut guards = Vec::new();
+        let mut unless = Vec::new();
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut after_commit = Vec::new();
+        let mut around = Vec::new();
+        let mut payload = None;
+        let mut payload_by_value = false;
+        let mut transform = None;
+        let mut doc = None;
+        let mut aliases = Vec::new();
+        let mut dangerous = false;
+        let mut retry = None;
+        let mut guard_marks = GuardMarks::default();
+        let mut payload_around = Vec::new();
+
+        // Parse each field in the event block
+        while !content.is_empty() {
+            let key: Ident = content.parse()?;
+            let key_str = key.to_string();
+
+            match key_str.as_str() {
+                "transition" => {
+                    parse_colon(&content, &key)?;
+                    let block;
+                    let brace = braced!(block in content);
+                    let transition = parse_transition(&block, brace.span.join())?;
+                    guard_marks
+                        .cached
+                        .extend(transition.cached_guards.iter().cloned());
+                    guard_marks
+                        .sync
+                        .extend(transition.sync_guards.iter().cloned());
+                    payload_around.extend(transition.payload_around.iter().cloned());
+                    transitions.push(transition);
+                }
+                "guards" => {
+                    parse_colon(&content, &key)?;
+                    guards = parse_guard_list_value(&content, &mut guard_marks)?;
+                }
+                "unless" => {
+                    parse_colon(&content, &key)?;
+                    unless = parse_guard_list_value(&content, &mut guard_marks)?;
+                }
+                "before" => {
+                    parse_colon(&content, &key)?;
+                    before = parse_callback_list_value(&content)?;
+                }
+                "after" => {
+                    parse_colon(&content, &key)?;
+                    after = parse_callback_list_value(&content)?;
+                }
+                "after_commit" => {
+                    parse_colon(&content, &key)?;
+                    after_commit = parse_callback_list_value(&content)?;
+                }
+                "around" => {
+                    parse_colon(&content, &key)?;
+                    around = parse_around_list_value(&content, &mut payload_around)?;
+                }
+                "payload" => {
+                    parse_colon(&content, &key)?;
+                    let mut ty = content.parse()?;
+                    crate::utils::name_elided_lifetimes(&mut ty);
+                    payload = Some(ty);
+                    payload_by_value = parse_payload_passing(&content)?;
+                }
+                "transform" => {
+                    parse_colon(&content, &key)?;
+                    transform = Some(content.parse()?);
+                }
+                "doc" => {
+                    parse_colon(&content, &key)?;
+                    doc = Some(content.parse()?);
+                }
+                "alias" => {
+                    parse_colon(&content, &key)?;
+                    aliases = parse_ident_list_value(&content)?;
+                }
+                "dangerous" => {
+                    parse_colon(&content, &key)?;
+                    let value: syn::LitBool = content.parse()?;
+                    dangerous = value.value();
+                }
+                "retry" => {
+                    parse_colon(&content, &key)?;
+                    let block;
+                    braced!(block in content);
+                    retry = Some(parse_retry(&key, &block)?);
+                }
+                _ if TRANSITION_KEYS.contains(&key_str.as_str()) => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "`{key_str}` belongs inside the event's `transition: {{ from: ..., to: ... }}` block"
+                        ),
+                    ));
+                }
+                _ => return Err(unexpected_key(&key, "event key", EVENT_KEYS)),
+            }
+
+            // Optional trailing comma
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        events.push(Event {
+            name,
+            code,
+            doc,
+            aliases,
+            visibility,
+            unsafety,
+            dangerous,
+            retry,
+            payload,
+            payload_by_value,
+            transform,
+            transitions,
+            guards,
+            unless,
+            before,
+            after,
+            after_commit,
+            around,
+            cached_guards: guard_marks.cached,
+            sync_guards: guard_marks.sync,
+            payload_around,
+        });
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(events)
+}
+
+/// Parse the body of a `transition: { ... }` block; `span` covers the braces,
+/// where a missing `from` or `to` is reported.
+pub fn parse_transition(input: &ParseBuffer<'_>, span: Span) -> Result<Transition> {
+    let mut sources = None;
+    let mut target = None;
+    let mut guards = Vec::new();
+    let mut unless = Vec::new();
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let mut after_commit = Vec::new();
+    let mut around = Vec::new();
+    let mut action = None;
+    let mut compensate = Vec::new();
+    let mut emit = None;
+    let mut init: Option<Ident> = None;
+    let mut forward = Vec::new();
+    let mut chooser: Option<Ident> = None;
+    let mut choice_targets = None;
+    let mut guard_marks = GuardMarks::default();
+    let mut payload_around = Vec::new();
+
+    while !input.is_empty() {
+        let key: Ident = input.parse()?;
+        let key_str = key.to_string();
+        parse_colon(input, &key)?;
+
+        match key_str.as_str() {
+            "from" => {
+                sources = Some(parse_state_set(input)?);
+            }
+            "to" => {
+                // A bracketed list declares the targets of a `choose:` transition
+                if input.peek(syn::token::Bracket) {
+                    let content;
+                    bracketed!(content in input);
+                    choice_targets = Some((key.clone(), parse_ident_list(&content)?));
+                } else {
+                    target = Some(input.parse()?);
+                }
+            }
+            "choose" => {
+                chooser = Some(input.parse()?);
+            }
+            "guards" => {
+                guards = parse_guard_list_value(input, &mut guard_marks)?;
+            }
+            "unless" => {
+                unless = parse_guard_list_value(input, &mut guard_marks)?;
+            }
+            "before" => {
+                before = parse_callback_list_value(input)?;
+            }
+            "after" => {
+                after = parse_callback_list_value(input)?;
+            }
+            "after_commit" => {
+                after_commit = parse_callback_list_value(input)?;
+            }
+            "around" => {
+                around = parse_around_list_value(input, &mut payload_around)?;
+            }
+            "action" => {
+                action = Some(input.parse()?);
+            }
+            "compensate" => {
+                compensate = parse_ident_list_value(input)?;
+            }
+            "emit" => {
+                emit = Some(input.parse()?);
+            }
+            "init" => {
+                init = Some(input.parse()?);
+            }
+            "forward" => {
+                // One `{ to, as }` block, or a list of them
+                if input.peek(syn::token::Bracket) {
+                    let list;
+                    bracketed!(list in input);
+                    while !list.is_empty() {
+                        let block;
+                        braced!(block in list);
+                        forward.push(parse_forward(&key, &block)?);
+                        if list.peek(Token![,]) {
+                            list.parse::<Token![,]>()?;
+                        }
+                    }
+                } else {
+                    let block;
+                    braced!(block in input);
+                    forward.push(parse_forward(&key, &block)?);
+                }
+            }
+            _ => return Err(unexpected_key(&key, "transition key", TRANSITION_KEYS)),
+        }
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    let choice = match (chooser, choice_targets) {
+        (Some(chooser), Some((to_key, targets))) => {
+            if targets.len() < 2 {
+                return Err(syn::Error::new(
+                    to_key.span(),
+                    "`choose` needs at least two targets; use `to: State` for a single one",
+                ));
+            }
+            target = Some(targets[0].clone());
+            Some(ChoiceSpec { chooser, targets })
+        }
+        (Some(chooser), None) => {
+            return Err(syn::Error::new(
+                chooser.span(),
+                "`choose` needs the possible targets as a list, e.g. `to: [Approved, Rejected]`",
+            ));
+        }
+        (None, Some((to_key, _))) => {
+            return Err(syn::Error::new(
+                to_key.span(),
+                "a list of targets needs `choose: method` to pick one at runtime",
+            ));
+        }
+        (None, None) => None,
+    };
+    if let (Some(init), Some(_)) = (&init, &choice) {
+        return Err(syn::Error::new(
+            init.span(),
+            "`init` builds the data of a single target, so it can't be combined with `choose`",
+        ));
+    }
+
+    Ok(Transition {
+        sources: sources.ok_or_else(|| syn::Error::new(span, "transition missing `from`"))?,
+        target: target.ok_or_else(|| syn::Error::new(span, "transition missing `to`"))?,
+        guards,
+        unless,
+        before,
+        after,
+        after_commit,
+        around,
+        action,
+        compensate,
+        choice,
+        emit,
+        init,
+        forward,
+        cached_guards: guard_marks.cached,
+        sync_guards: guard_marks.sync,
+        payload_around,
+    })
+}
+
+/// Parse the value of `unhandled: ignore | error | panic | callback(name)`.
+pub fn parse_unhandled_policy(input: &ParseBuffer<'_>) -> Result<UnhandledPolicy> {
+    let policy: Ident = input.parse()?;
+    match policy.to_string().as_str() {
+        "error" => Ok(UnhandledPolicy::Error),
+        "ignore" => Ok(UnhandledPolicy::Ignore),
+        "panic" => Ok(UnhandledPolicy::Panic),
+        "callback" => {
+            let content;
+            parenthesized!(content in input);
+            Ok(UnhandledPolicy::Callback(content.parse()?))
+        }
+        _ => Err(syn::Error::new(
+            policy.span(),
+            "expected `ignore`, `error`, `panic`, or `callback(name)`",
+        )),
+    }
+}
+
+/// Parse a `mode: typestate | runtime | both` value.
+pub fn parse_machine_mode(input: &ParseBuffer<'_>) -> Result<(Ident, MachineMode)> {
+    let value: Ident = input.parse()?;
+    let mode = match value.to_string().as_str() {
+        "typestate" => MachineMode::Typestate,
+        "runtime" => MachineMode::Runtime,
+        "both" => MachineMode::Both,
+        _ => {
+            return Err(syn::Error::new(
+                value.span(),
+                "expected `typestate`, `runtime`, or `both`",
+            ));
+        }
+    };
+    Ok((value, mode))
+}
+
+/// Parse a `callback_timeout` value.
+///
+/// Either a bare `Duration` expression, bounded with the Tokio timer, or a
+/// `{ after: Duration::from_secs(2), timer: path::to::Timer }` block.
+pub fn parse_callback_timeout(key: &Ident, input: &ParseBuffer<'_>) -> Result<CallbackTimeout> {
+    if !input.peek(syn::token::Brace) {
+        return Ok(CallbackTimeout {
+            key: key.clone(),
+            after: input.parse()?,
+            timer: None,
+        });
+    }
+
+    let content;
+    braced!(content in input);
+    let mut after = None;
+    let mut timer = None;
+    while !content.is_empty() {
+        let option: Ident = content.parse()?;
+        content.parse::<Token![:]>()?;
+
+        match option.to_string().as_str() {
+            "after" => {
+                after = Some(content.parse()?);
+            }
+            "timer" => {
+                timer = Some(content.parse()?);
+            }
+            _ => return Err(unexpected_key(&option, "key", CALLBACK_TIMEOUT_KEYS)),
+        }
+
+        // Optional trailing comma
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(CallbackTimeout {
+        key: key.clone(),
+        after: after
+            .ok_or_else(|| syn::Error::new(key.span(), "`callback_timeout` missing `after`"))?,
+        timer,
+    })
+}
+
+/// Parse an `on_error` value.
+///
+/// Either a bare handler name or a
+/// `{ handler: log_failure, recover: fault, after: 3 }` block.
+pub fn parse_on_error(key: &Ident, input: &ParseBuffer<'_>) -> Result<OnError> {
+    if !input.peek(syn::token::Brace) {
+        return Ok(OnError {
+            key: key.clone(),
+            handler: Some(input.parse()?),
+            recover: None,
+            after: None,
+        });
+    }
+
+    let content;
+    braced!(content in input);
+    let mut handler = None;
+    let mut recover = None;
+    let mut after = None;
+    while !content.is_empty() {
+        let option: Ident = content.parse()?;
+        content.parse::<Token![:]>()?;
+
+        match option.to_string().as_str() {
+            "handler" => {
+                handler = Some(content.parse()?);
+            }
+            "recover" => {
+                recover = Some(content.parse()?);
+            }
+            "after" => {
+                after = Some(content.parse()?);
+            }
+            _ => return Err(unexpected_key(&option, "key", ON_ERROR_KEYS)),
+        }
+
+        // Optional trailing comma
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(OnError {
+        key: key.clone(),
+        handler,
+        recover,
+        after,
+    })
+}
+
+/// Parse the body of a `retry: { attempts: 3, backoff: exponential(100ms) }` block.
+///
+/// `backoff` defaults to `none`; `timer` names the `AsyncTimer` used to wait.
+pub fn parse_retry(key: &Ident, input: &ParseBuffer<'_>) -> Result<RetrySpec> {
+    let mut attempts = None;
+    let mut backoff = BackoffSpec::None;
+    let mut timer = None;
+
+    while !input.is_empty() {
+        let option: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+
+        match option.to_string().as_str() {
+            "attempts" => {
+                attempts = Some(input.parse()?);
+            }
+            "backoff" => {
+                backoff = parse_backoff(input)?;
+            }
+            "timer" => {
+                timer = Some(input.parse()?);
+            }
+            _ => return Err(unexpected_key(&option, "key", RETRY_KEYS)),
+        }
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(RetrySpec {
+        key: key.clone(),
+        attempts: attempts
+            .ok_or_else(|| syn::Error::new(key.span(), "`retry` missing `attempts`"))?,
+        backoff,
+        timer,
+    })
+}
+
+/// Parse the body of `forward: { to: method, as: Event::Variant }`.
+pub fn parse_forward(key: &Ident, input: &ParseBuffer<'_>) -> Result<ForwardSpec> {
+    let mut to = None;
+    let mut event = None;
+
+    while !input.is_empty() {
+        // `as` is a keyword; the struct form writes it `r#as`
+        if input.peek(Token![as]) {
+            input.parse::<Token![as]>()?;
+            input.parse::<Token![:]>()?;
+            event = Some(input.parse()?);
+        } else {
+            let option: Ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            match option.to_string().as_str() {
+                "to" => to = Some(input.parse()?),
+                "r#as" => event = Some(input.parse()?),
+                _ => return Err(unexpected_key(&option, "key", FORWARD_KEYS)),
+            }
+        }
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(ForwardSpec {
+        to: to.ok_or_else(|| syn::Error::new(key.span(), "`forward` missing `to`"))?,
+        event: event.ok_or_else(|| syn::Error::new(key.span(), "`forward` missing `as`"))?,
+    })
+}
+
+/// Parse `none`, `fixed(delay)`, or `exponential(delay)`.
+fn parse_backoff(input: &ParseBuffer<'_>) -> Result<BackoffSpec> {
+    let kind: Ident = input.parse()?;
+    if kind == "none" {
+        return Ok(BackoffSpec::None);
+    }
+
+    let content;
+    parenthesized!(content in input);
+    let delay = parse_duration(&content)?;
+
+    match kind.to_string().as_str() {
+        "fixed" => Ok(BackoffSpec::Fixed(delay)),
+        "exponential" => Ok(BackoffSpec::Exponential(delay)),
+        _ => Err(syn::Error::new(
+            kind.span(),
+            "expected `none`, `fixed(delay)`, or `exponential(delay)`",
+        )),
+    }
+}
+
+/// Parse a `Duration` expression, or a literal such as `100ms` or `2s`.
+fn parse_duration(input: &ParseBuffer<'_>) -> Result<syn::Expr> {
+    if !input.peek(syn::LitInt) || (!input.peek2(syn::parse::End) && !input.peek2(Token![,])) {
+        return input.parse();
+    }
+
+    let literal: syn::LitInt = input.parse()?;
+    let value: u64 = literal.base10_parse()?;
+    match literal.suffix() {
+        "ms" => Ok(syn::parse_quote!(::core::time::Duration::from_millis(#value))),
+        "s" => Ok(syn::parse_quote!(::core::time::Duration::from_secs(#value))),
+        _ => Err(syn::Error::new(
+            literal.span(),
+            "expected a duration such as `100ms` or `2s`",
+        )),
+    }
+}
+
+/// Parse the body of a `mailbox: { capacity: 16, overflow: drop_oldest }` block.
+///
+/// `overflow` defaults to `error`, which hands the event back to the poster.
+/// A missing `capacity` is reported at `span`, the block's braces.
+pub fn parse_mailbox(key: &Ident, input: &ParseBuffer<'_>, span: Span) -> Result<MailboxSpec> {
+    let mut capacity = None;
+    let mut drop_oldest = false;
+
+    while !input.is_empty() {
+        let option: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+
+        match option.to_string().as_str() {
+            "capacity" => {
+                capacity = Some(input.parse()?);
+            }
+            "overflow" => {
+                let policy: Ident = input.parse()?;
+                drop_oldest = match policy.to_string().as_str() {
+                    "drop_oldest" => true,
+                    "error" => false,
+                    _ => {
+                        return Err(syn::Error::new(
+                            policy.span(),
+                            "expected `drop_oldest` or `error`",
+                        ));
+                    }
+                };
+            }
+            _ => return Err(unexpected_key(&option, "key", MAILBOX_KEYS)),
+        }
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(MailboxSpec {
+        key: key.clone(),
+        capacity: capacity.ok_or_else(|| syn::Error::new(span, "mailbox missing `capacity`"))?,
+        drop_oldest,
+    })
+}
+
+// ========== Helper Functions ==========
+
+/// Parse the `:` after `key`, pointing at the block when it was left out.
+fn parse_colon(input: ParseStream<'_>, key: &Ident) -> Result<()> {
+    if input.peek(syn::token::Brace) {
+        return Err(input.error(format!(
+            "expected `:` after `{key}`, as in `{key}: {{ ... }}`"
+        )));
+    }
+    if input.peek(syn::token::Bracket) {
+        return Err(input.error(format!("expected `:` after `{key}`, as in `{key}: [...]`")));
+    }
+    input.parse::<Token![:]>()?;
+    Ok(())
+}
+
+/// The optional `by value` / `by ref` after an event's payload type:
+/// whether the last callback to see the payload takes it by value.
+fn parse_payload_passing(input: ParseStream<'_>) -> Result<bool> {
+    if !input.peek(Ident) || input.fork().parse::<Ident>()? != "by" {
+        return Ok(false);
+    }
+    input.parse::<Ident>()?;
+    if input.peek(Token![ref]) {
+        input.parse::<Token![ref]>()?;
+        return Ok(false);
+    }
+    let passing: Ident = input.parse()?;
+    if passing != "value" {
+        return Err(syn::Error::new(
+            passing.span(),
+            "expected `by value` or `by ref` after the payload type",
+        ));
+    }
+    Ok(true)
+}
+
+/// An error for a `kind` of key the block doesn't accept, suggesting the
+/// closest of the `expected` keys (e.g. `guards` for `guard`) and listing
+/// all of them.
+pub fn unexpected_key(key: &Ident, kind: &str, expected: &[&str]) -> syn::Error {
+    let name = key.to_string();
+    let mut message = format!("unexpected {kind} `{name}`");
+
+    // Allow about one typo per three characters
+    let suggestion = expected
+        .iter()
+        .map(|candidate| (edit_distance(&name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= name.len().div_ceil(3))
+        .min_by_key(|(distance, _)| *distance);
+    if let Some((_, candidate)) = suggestion {
+        message.push_str(&format!("; did you mean `{candidate}`?"));
+    }
+
+    let expected: Vec<String> = expected.iter().map(|key| format!("`{key}`")).collect();
+    message.push_str(&format!("\nexpected one of: {}", expected.join(", ")));
+    syn::Error::new(key.span(), message)
+}
+
+/// The number of insertions, deletions, substitutions, and swaps of adjacent
+/// characters that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows `i - 2`, `i - 1`, and `i` of the distance table
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Parse a comma-separated list of identifiers.
+///
+/// Used for parsing lists like `StateA, StateB, StateC`.
+pub fn parse_ident_list(input: &ParseBuffer<'_>) -> Result<Vec<Ident>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        items.push(input.parse()?);
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+    Ok(items)
+}
+
+/// Parse a `requires: [Radio, Spi<Bus>]` list of capability traits.
+fn parse_requires(input: &ParseBuffer<'_>) -> Result<Vec<syn::Path>> {
+    let content;
+    bracketed!(content in input);
+    let paths = content.parse_terminated(syn::Path::parse, Token![,])?;
+    Ok(paths.into_iter().collect())
+}
+
+/// Parse a comma-separated list of paths.
+///
+/// Used for derive lists like `Serialize, serde::Deserialize`.
+pub fn parse_path_list(input: &ParseBuffer<'_>) -> Result<Vec<syn::Path>> {
+    let paths = input.parse_terminated(syn::Path::parse_mod_style, Token![,])?;
+    Ok(paths.into_iter().collect())
+}
+
+/// Parse a list of outer attributes, optionally comma-separated.
+///
+/// Used for lists like `#[repr(u8)], #[non_exhaustive]`.
+pub fn parse_attribute_list(input: &ParseBuffer<'_>) -> Result<Vec<syn::Attribute>> {
+    let mut attrs = Vec::new();
+    while !input.is_empty() {
+        attrs.extend(input.call(syn::Attribute::parse_outer)?);
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+    Ok(attrs)
+}
+
+/// Parse a state set (either a single identifier or a bracketed list).
+///
+/// Examples:
+/// - `StateA` -> vec![StateA]
+/// - `[StateA, StateB]` -> vec![StateA, StateB]
+pub fn parse_state_set(input: &ParseBuffer<'_>) -> Result<Vec<Ident>> {
+    if input.peek(syn::token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        parse_ident_list(&content)
+    } else {
+        Ok(vec![input.parse()?])
+    }
+}
+
+/// Parse an identifier list value (either a single identifier or a bracketed list).
+///
+/// This is similar to parse_state_set but used for non-state lists
+/// like guards, callbacks, etc.
+pub fn parse_ident_list_value(input: &ParseBuffer<'_>) -> Result<Vec<Ident>> {
+    if input.peek(syn::token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        parse_ident_list(&content)
+    } else {
+        Ok(vec![input.parse()?])
+    }
+}
+
+/// Parse an `around` list value (either a single callback or a bracketed list).
+///
+/// Like parse_ident_list_value, but each entry may carry options:
+/// - `audit` -> `fn audit(&self, stage: AroundStage) -> AroundOutcome<_>`
+/// - `[audit { payload: true }]` -> `audit` also borrows the event payload;
+///   its name is pushed to `with_payload`
+pub fn parse_around_list_value(
+    input: &ParseBuffer<'_>,
+    with_payload: &mut Vec<Ident>,
+) -> Result<Vec<Ident>> {
+    let parse_entry = |input: &ParseBuffer<'_>, with_payload: &mut Vec<Ident>| -> Result<Ident> {
+        let callback: Ident = input.parse()?;
+        if input.peek(syn::token::Brace) {
+            let options;
+            braced!(options in input);
+            while !options.is_empty() {
+                let key: Ident = options.parse()?;
+                options.parse::<Token![:]>()?;
+                match key.to_string().as_str() {
+                    "payload" => {
+                        let value: syn::LitBool = options.parse()?;
+                        if value.value() {
+                            with_payload.push(callback.clone());
+                        }
+                    }
+                    _ => {
+                        return Err(unexpected_key(&key, "around callback option", &["payload"]));
+                    }
+                }
+
+                // Optional trailing comma
+                if options.peek(Token![,]) {
+                    options.parse::<Token![,]>()?;
+                }
+            }
+        }
+        Ok(callback)
+    };
+
+    if input.peek(syn::token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        let mut items = Vec::new();
+        while !content.is_empty() {
+            items.push(parse_entry(&content, with_payload)?);
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+        Ok(items)
+    } else {
+        Ok(vec![parse_entry(input, with_payload)?])
+    }
+}
+
+/// Parse a `before`/`after` list value (either a single callback or a bracketed list).
+///
+/// Each entry is a callback name or a filtered callback:
+/// - `log` -> runs on every edge
+/// - `[{ name: log, from: [Idle, Paused], to: Running }]` -> runs only on
+///   matching edges
+pub fn parse_callback_list_value(input: &ParseBuffer<'_>) -> Result<Vec<CallbackSpec>> {
+    if input.peek(syn::token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        let mut items = Vec::new();
+        while !content.is_empty() {
+            items.push(parse_callback_spec(&content)?);
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+        Ok(items)
+    } else {
+        Ok(vec![parse_callback_spec(input)?])
+    }
+}
+
+/// Parse a single callback: `name` or `{ name: cb, from: ..., to: ... }`.
+fn parse_callback_spec(input: &ParseBuffer<'_>) -> Result<CallbackSpec> {
+    if !input.peek(syn::token::Brace) {
+        return Ok(CallbackSpec {
+            name: input.parse()?,
+            from: Vec::new(),
+            to: Vec::new(),
+            split: false,
+        });
+    }
+
+    let content;
+    let brace = braced!(content in input);
+    let mut name = None;
+    let mut from = Vec::new();
+    let mut to = Vec::new();
+    let mut split = false;
+    while !content.is_empty() {
+        let key: Ident = content.parse()?;
+        content.parse::<Token![:]>()?;
+        match key.to_string().as_str() {
+            "name" => name = Some(content.parse()?),
+            "from" => from = parse_state_set(&content)?,
+            "to" => to = parse_state_set(&content)?,
+            "split" => split = content.parse::<syn::LitBool>()?.value(),
+            _ => {
+                return Err(unexpected_key(
+                    &key,
+                    "callback option",
+                    &["name", "from", "to", "split"],
+                ));
+            }
+        }
+
+        // Optional trailing comma
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    let name =
+        name.ok_or_else(|| syn::Error::new(brace.span.join(), "callback filter missing `name`"))?;
+    Ok(CallbackSpec {
+        name,
+        from,
+        to,
+        split,
+    })
+}
+
+/// Parse the `callbacks: { ... }` block.
+///
+/// ```text
+/// before_transition [{ name: log, from: [A], to: B, on: [go], priority: 10 }],
+/// after_transition [audit],
+/// ```
+pub fn parse_global_callbacks(input: &ParseBuffer<'_>) -> Result<GlobalCallbacks> {
+    let mut callbacks = GlobalCallbacks::default();
+
+    while !input.is_empty() {
+        let key: Ident = input.parse()?;
+        // Optional colon, as in `before_transition: [...]`
+        if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+        }
+
+        let mut list = Vec::new();
+        if input.peek(syn::token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            while !content.is_empty() {
+                list.push(parse_global_callback(&content)?);
+                if content.peek(Token![,]) {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+        } else {
+            list.push(parse_global_callback(input)?);
+        }
+
+        match key.to_string().as_str() {
+            "before_transition" => callbacks.before_transition.extend(list),
+            "after_transition" => callbacks.after_transition.extend(list),
+            "around_transition" => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "`around_transition` is not supported; use `around` on the events instead",
+                ));
+            }
+            _ => {
+                return Err(unexpected_key(
+                    &key,
+                    "callback hook",
+                    &["before_transition", "after_transition"],
+                ));
+            }
+        }
+
+        // Optional trailing comma
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    // Higher priorities run first; the sort is stable, so ties keep declaration order
+    callbacks
+        .before_transition
+        .sort_by_key(|callback| std::cmp::Reverse(callback.priority));
+
+    Ok(callbacks)
+}
+
+/// Parse a single global callback: `name` or
+/// `{ name: cb, from: ..., to: ..., on: ..., priority: N }`.
+fn parse_global_callback(input: &ParseBuffer<'_>) -> Result<GlobalCallback> {
+    if !input.peek(syn::token::Brace) {
+        return Ok(GlobalCallback {
+            callback: CallbackSpec {
+                name: input.parse()?,
+                from: Vec::new(),
+                to: Vec::new(),
+                split: false,
+            },
+            on: Vec::new(),
+            priority: 0,
+        });
+    }
+
+    let content;
+    let brace = braced!(content in input);
+    let mut name = None;
+    let mut from = Vec::new();
+    let mut to = Vec::new();
+    let mut on = Vec::new();
+    let mut priority = 0;
+    while !content.is_empty() {
+        let key: Ident = content.parse()?;
+        content.parse::<Token![:]>()?;
+        match key.to_string().as_str() {
+            "name" => name = Some(content.parse()?),
+            "from" => from = parse_state_set(&content)?,
+            "to" => to = parse_state_set(&content)?,
+            "on" => on = parse_ident_list_value(&content)?,
+            "priority" => {
+                let negative = content.parse::<Option<Token![-]>>()?.is_some();
+                let value: syn::LitInt = content.parse()?;
+                let value = value.base10_parse::<i32>()?;
+                priority = if negative { -value } else { value };
+            }
+            _ => {
+                return Err(unexpected_key(
+                    &key,
+                    "callback option",
+                    &["name", "from", "to", "on", "priority"],
+                ));
+            }
+        }
+
+        // Optional trailing comma
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    let name =
+        name.ok_or_else(|| syn::Error::new(brace.span.join(), "callback filter missing `name`"))?;
+    Ok(GlobalCallback {
+        callback: CallbackSpec {
+            name,
+            from,
+            to,
+            split: false,
+        },
+        on,
+        priority,
+    })
+}
+
+/// Guards named with options in a guard list, e.g. `fuel_ready { cache: true }`.
+#[derive(Default)]
+pub struct GuardMarks {
+    pub cached: Vec<Ident>,
+    pub sync: Vec<Ident>,
+}
+
+/// Parse a guard list value (either a single guard or a bracketed list).
+///
+/// Like parse_ident_list_value, but each entry is a guard expression:
+/// - `fuel_ready` -> plain guard
+/// - `[in_orbit && (engines_off || docked), !alarm]` -> boolean combinations
+/// - `[fuel_ready { cache: true }]` -> `fuel_ready` is evaluated at most once
+///   per transition; its name is pushed to `marks.cached`
+/// - `[in_memory { sync: true }]` -> `in_memory` is a plain `fn` even in an
+///   async machine; its name is pushed to `marks.sync`
+pub fn parse_guard_list_value(
+    input: &ParseBuffer<'_>,
+    marks: &mut GuardMarks,
+) -> Result<Vec<GuardExpr>> {
+    if input.peek(syn::token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        let mut items = Vec::new();
+        while !content.is_empty() {
+            items.push(parse_guard_or(&content, marks)?);
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+        Ok(items)
+    } else {
+        Ok(vec![parse_guard_or(input, marks)?])
+    }
+}
+
+/// Parse `a || b || ...` (lowest precedence).
+fn parse_guard_or(input: &ParseBuffer<'_>, marks: &mut GuardMarks) -> Result<GuardExpr> {
+    let mut expr = parse_guard_and(input, marks)?;
+    while input.peek(Token![||]) {
+        input.parse::<Token![||]>()?;
+        let rhs = parse_guard_and(input, marks)?;
+        expr = GuardExpr::Or(Box::new(expr), Box::new(rhs));
+    }
+    Ok(expr)
+}
+
+/// Parse `a && b && ...`.
+fn parse_guard_and(input: &ParseBuffer<'_>, marks: &mut GuardMarks) -> Result<GuardExpr> {
+    let mut expr = parse_guard_unary(input, marks)?;
+    while input.peek(Token![&&]) {
+        input.parse::<Token![&&]>()?;
+        let rhs = parse_guard_unary(input, marks)?;
+        expr = GuardExpr::And(Box::new(expr), Box::new(rhs));
+    }
+    Ok(expr)
+}
+
+/// Parse `!expr`, `(expr)`, or a single guard with its optional options block.
+fn parse_guard_unary(input: &ParseBuffer<'_>, marks: &mut GuardMarks) -> Result<GuardExpr> {
+    if input.peek(Token![!]) {
+        input.parse::<Token![!]>()?;
+        let inner = parse_guard_unary(input, marks)?;
+        return Ok(GuardExpr::Not(Box::new(inner)));
+    }
+
+    if input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        let expr = parse_guard_or(&content, marks)?;
+        if !content.is_empty() {
+            return Err(content.error("expected `&&`, `||`, or `)` in guard expression"));
+        }
+        return Ok(expr);
+    }
+
+    let guard: Ident = input.parse()?;
+
+    // Literal arguments, passed after the context and payload
+    let mut args = Vec::new();
+    if input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        for arg in content.parse_terminated(syn::Expr::parse, Token![,])? {
+            let literal = match &arg {
+                syn::Expr::Lit(_) => true,
+                syn::Expr::Unary(unary) => {
+                    matches!(unary.op, syn::UnOp::Neg(_))
+                        && matches!(*unary.expr, syn::Expr::Lit(_))
+                }
+                _ => false,
+            };
+            if !literal {
+                return Err(syn::Error::new_spanned(
+                    arg,
+                    "guard arguments must be literals, e.g. `min_altitude(100)`",
+                ));
+            }
+            args.push(arg);
+        }
+    }
+
+    if input.peek(syn::token::Brace) {
+        let options;
+        braced!(options in input);
+        while !options.is_empty() {
+            let key: Ident = options.parse()?;
+            options.parse::<Token![:]>()?;
+            match key.to_string().as_str() {
+                "cache" => {
+                    let value: syn::LitBool = options.parse()?;
+                    if value.value() {
+                        marks.cached.push(guard.clone());
+                    }
+                }
+                "sync" => {
+                    let value: syn::LitBool = options.parse()?;
+                    if value.value() {
+                        marks.sync.push(guard.clone());
+                    }
+                }
+                _ => return Err(unexpected_key(&key, "guard option", &["cache", "sync"])),
+            }
+
+            // Optional trailing comma
+            if options.peek(Token![,]) {
+                options.parse::<Token![,]>()?;
+            }
+        }
+    }
+
+    Ok(GuardExpr::Guard(guard, args))
+}
+
+/// Generate the storage field identifier for a state.
+///
+/// Converts a state name like `ConnectionActive` to a field name
+/// like `__state_data_connection_active`.
+pub fn storage_field_ident(name: &Ident) -> Ident {
+    let snake = crate::utils::to_snake_case(&name.to_string());
+    format_ident!("__state_data_{}", snake)
+}
+
+impl StateMachine {
+    /// Build the transition graph from the parsed events.
+    ///
+    /// This populates the transition_graph field by extracting all
+    /// transitions from events and creating edges in the graph.
+    pub fn build_transition_graph(&mut self) {
+        for event in &self.events {
+            for transition in &event.transitions {
+                // Expand source states (handle superstates)
+                for source in &transition.sources {
+                    let expanded_sources = self.hierarchy.expand_state(source, &self.states);
+                    let resolved_target = self
+                        .hierarchy
+                        .resolve_target(&transition.target)
+                        .unwrap_or_else(|| transition.target.clone());
+
+                    for actual_source in expanded_sources {
+                        // Merge event-level and transition-level guards/callbacks
+                        let mut all_guards = event.guards.clone();
+                        all_guards.extend(transition.guards.clone());
+
+                        let mut all_unless = event.unless.clone();
+                        all_unless.extend(transition.unless.clone());
+
+                        // Keep only the callbacks whose filters match this edge
+                        let applicable = |callbacks: &[CallbackSpec]| -> Vec<Ident> {
+                            callbacks
+                                .iter()
+                                .filter(|callback| {
+                                    callback.applies(
+                                        &self.hierarchy,
+                                        &self.states,
+                                        &actual_source,
+                                        &resolved_target,
+                                    )
+                                })
+                                .map(|callback| callback.name.clone())
+                                .collect()
+                        };
+
+                        let mut all_before = applicable(&event.before);
+                        all_before.extend(applicable(&transition.before));
+
+                        let mut all_after = applicable(&event.after);
+                        all_after.extend(applicable(&transition.after));
+
+                        let mut all_after_commit = applicable(&event.after_commit);
+                        all_after_commit.extend(applicable(&transition.after_commit));
+
+                        // `to` filters can't match `choose` transitions, whose target
+                        // is only known at runtime
+                        let global = |callbacks: &[GlobalCallback]| -> Vec<Ident> {
+                            callbacks
+                                .iter()
+                                .filter(|global| {
+                                    (transition.choice.is_none() || global.callback.to.is_empty())
+                                        && global.applies(
+                                            &self.hierarchy,
+                                            &self.states,
+                                            &event.name,
+                                            &actual_source,
+                                            &resolved_target,
+                                        )
+                                })
+                                .map(|global| global.callback.name.clone())
+                                .collect()
+                        };
+                        let before_transition = global(&self.callbacks.before_transition);
+                        let after_transition = global(&self.callbacks.after_transition);
+
+                        let mut all_around = event.around.clone();
+                        all_around.extend(transition.around.clone());
+
+                        self.transition_graph.add_edge(
+                            &actual_source,
+                            source.clone(),
+                            resolved_target.clone(),
+                            event.name.clone(),
+                            all_guards,
+                            all_unless,
+                            all_before,
+                            all_after,
+                            all_after_commit,
+                            before_transition,
+                            after_transition,
+                            all_around,
+                            transition.action.clone(),
+                            transition.compensate.clone(),
+                            transition.choice.clone(),
+                            transition.emit.clone(),
+                            transition.init.clone(),
+                            transition.forward.clone(),
+                            event.payload.clone(),
+                            event.payload_by_value,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    fn error(input: proc_macro2::TokenStream) -> String {
+        match syn::parse2::<StateMachine>(input) {
+            Ok(_) => panic!("expected the input to be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("guards", "guards"), 0);
+        assert_eq!(edit_distance("guard", "guards"), 1);
+        assert_eq!(edit_distance("form", "from"), 1);
+        assert_eq!(edit_distance("intial", "initial"), 1);
+        assert_eq!(edit_distance("", "to"), 2);
+        assert_eq!(edit_distance("payload", "emit"), 7);
+    }
+
+    #[test]
+    fn test_unexpected_key_suggests_the_closest() {
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states: [Closed, Open],
+            events {
+                open {
+                    guard: [unlocked],
+                    transition: { from: Closed, to: Open }
+                }
+            }
+        });
+        assert_eq!(
+            message,
+            "unexpected event key `guard`; did you mean `guards`?\nexpected one of: \
+             `transition`, `guards`, `unless`, `before`, `after`, `after_commit`, `around`, \
+             `payload`, `transform`, `doc`, `alias`, `dangerous`, `retry`"
+        );
+
+        let message = error(quote! {
+            name: Door,
+            intial: Closed,
+        });
+        assert!(message.starts_with("unexpected key `intial`; did you mean `initial`?\n"));
+
+        // Nothing close enough, so just the list
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states: [Closed, Open],
+            events {
+                open { transition: { from: Closed, to: Open, xyzzy: go } }
+            }
+        });
+        assert!(message.starts_with("unexpected transition key `xyzzy`\nexpected one of: `from`"));
+    }
+
+    #[test]
+    fn test_transition_keys_outside_a_transition() {
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states: [Closed, Open],
+            events {
+                open { from: Closed, to: Open }
+            }
+        });
+        assert_eq!(
+            message,
+            "`from` belongs inside the event's `transition: { from: ..., to: ... }` block"
+        );
+    }
+
+    #[test]
+    fn test_payload_passing() {
+        let machine = |payload: proc_macro2::TokenStream| {
+            syn::parse2::<StateMachine>(quote! {
+                name: Link,
+                initial: Idle,
+                states: [Idle, Sent],
+                events {
+                    send {
+                        payload: #payload,
+                        after: [keep],
+                        transition: { from: Idle, to: Sent }
+                    }
+                }
+            })
+        };
+        let by_value = |payload| machine(payload).unwrap().events[0].payload_by_value;
+        assert!(!by_value(quote! { Vec<u8> }));
+        assert!(!by_value(quote! { Vec<u8> by ref }));
+        assert!(by_value(quote! { Vec<u8> by value }));
+
+        let message = machine(quote! { Vec<u8> by copy })
+            .err()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            message,
+            "expected `by value` or `by ref` after the payload type"
+        );
+    }
+
+    #[test]
+    fn test_block_punctuation() {
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states: [Closed, Open],
+            events: {
+                open: { transition: { from: Closed, to: Open } }
+            }
+        });
+        assert_eq!(
+            message,
+            "events are declared without a `:`, as in `open { transition: { ... } }`"
+        );
+
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states: [Closed, Open],
+            events {
+                open { transition { from: Closed, to: Open } }
+            }
+        });
+        assert_eq!(
+            message,
+            "expected `:` after `transition`, as in `transition: { ... }`"
+        );
+
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states { Closed, Open },
+        });
+        assert_eq!(
+            message,
+            "`states` takes a list in brackets: `states: [Idle, Running]`"
+        );
+
+        let message = error(quote! {
+            name: Door,
+            initial: Closed,
+            states: { Closed, Open },
+        });
+        assert_eq!(
+            message,
+            "`states` takes a list in brackets: `states: [Idle, Running]`"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! The rustfmt-friendly struct form of the macro input.
+//!
+//! rustfmt leaves `state_machine! { ... }` alone because the DSL isn't Rust
+//! syntax. The struct form spells the same definition as one struct
+//! expression inside parentheses, which rustfmt formats like any other call:
+//!
+//! ```ignore
+//! state_machine!(Machine {
+//!     name: Door,
+//!     initial: Closed,
+//!     states: [Closed, Open, Superstate { name: Locked, states: [Bolted] }],
+//!     events: [
+//!         Event {
+//!             name: open,
+//!             guards: [unlocked],
+//!             transition: Transition { from: Closed, to: Open },
+//!         },
+//!     ],
+//! });
+//! ```
+//!
+//! Rather than a second parser, the struct expression is rewritten into the
+//! equivalent DSL tokens, keeping the original spans, and handed to the
+//! regular parser in `parser.rs`. The rewrite rules:
+//!
+//! - `Machine { key: value, .. }` becomes the top-level `key: value` list.
+//! - `events: [Event { name: pay, .. }]` becomes `events { pay { .. } }`,
+//!   `transitions: [Transition { .. }, ..]` a `transition:` entry for each,
+//!   and `code: 1` the event's `pay = 1` code. States take codes as written,
+//!   e.g. `states: [Closed = 1, Open = 2]`. An event's `visibility: "pub(crate)"`
+//!   and `r#unsafe: true` become the `pub(crate) unsafe pay { .. }` prefix.
+//! - `Superstate { name: Flight, states: [A, B], .. }` inside `states` becomes
+//!   `superstate Flight { state A, state B, .. }`.
+//! - The other option blocks (`Transition`, `Retry`, `Mailbox`, `OnError`,
+//!   `Timeout`, `Callbacks`, `Callback`, `Forward`, `Params`) become plain
+//!   `{ key: value }` blocks; `Forward` spells its `as` key `r#as`.
+//!   Any other struct expression, like `Connecting { max_duration: 30s }`
+//!   or `audit { payload: true }`, is passed through as written.
+//! - `r#async: true` becomes `async: true`.
+//! - Values that aren't expressions (`visibility`, `generics`, `payload` or
+//!   `context` types with generics, and the attribute lists) can be given as
+//!   string literals, which are spliced in as tokens.
+
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{ToTokens, quote};
+use syn::parse::ParseStream;
+use syn::{Expr, ExprStruct, FieldValue, Member, Result};
+
+/// Option blocks whose struct expressions become DSL `{ ... }` blocks.
+const BLOCKS: &[&str] = &[
+    "Transition",
+    "Retry",
+    "Mailbox",
+    "OnError",
+    "Timeout",
+    "Callbacks",
+    "Callback",
+    "Forward",
+    "Params",
+];
+
+/// Keys whose string literal values are spliced in as tokens.
+const TOKEN_KEYS: &[&str] = &[
+    "visibility",
+    "generics",
+    "context",
+    "payload",
+    "state_attrs",
+    "machine_attrs",
+];
+
+/// Whether the input starts with `Machine {`.
+pub fn is_struct_form(input: ParseStream<'_>) -> bool {
+    input
+        .fork()
+        .parse::<syn::Ident>()
+        .is_ok_and(|ident| ident == "Machine")
+        && input.peek2(syn::token::Brace)
+}
+
+/// Parse a `Machine { ... }` expression and rewrite it into DSL tokens.
+pub fn desugar(input: ParseStream<'_>) -> Result<TokenStream2> {
+    let machine: ExprStruct = input.parse()?;
+    if !input.is_empty() {
+        return Err(input.error("unexpected tokens after `Machine { ... }`"));
+    }
+    if let Some(rest) = &machine.rest {
+        return Err(syn::Error::new_spanned(rest, "`..` isn't supported here"));
+    }
+
+    let mut tokens = TokenStream2::new();
+    for field in &machine.fields {
+        let key = key(field)?;
+        match key.as_str() {
+            "async" | "r#async" => {
+                let value = &field.expr;
+                tokens.extend(quote! { async: #value, });
+            }
+            "events" => {
+                let ident = key_ident(field);
+                let events = events(&field.expr)?;
+                tokens.extend(quote! { #ident { #events } });
+            }
+            "states" => {
+                let ident = key_ident(field);
+                let states = states(&field.expr)?;
+                tokens.extend(quote! { #ident: [#states], });
+            }
+            _ => {
+                let ident = key_ident(field);
+                let value = value(&key, &field.expr)?;
+                tokens.extend(quote! { #ident: #value, });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// `events: [Event { name: pay, .. }, ..]` as `pay { .. } ..`.
+fn events(expr: &Expr) -> Result<TokenStream2> {
+    let mut tokens = TokenStream2::new();
+    for event in array(expr, "`events` takes a list of `Event { name: ..., ... }`")? {
+        let event = match event {
+            Expr::Struct(event) if is_named(event, "Event") => event,
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected `Event { name: ..., ... }`",
+                ));
+            }
+        };
+
+        let mut name = None;
+        let mut code = None;
+        let mut prefix = TokenStream2::new();
+        let mut unsafety = None;
+        let mut body = TokenStream2::new();
+        for field in &event.fields {
+            let key = key(field)?;
+            match key.as_str() {
+                "name" => name = Some(&field.expr),
+                "code" => {
+                    let value = &field.expr;
+                    code = Some(quote! { = #value });
+                }
+                "visibility" => prefix = value(&key, &field.expr)?,
+                "r#unsafe" => match &field.expr {
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Bool(flag),
+                        ..
+                    }) => {
+                        unsafety = flag
+                            .value
+                            .then(|| quote::quote_spanned!(flag.span=> unsafe))
+                    }
+                    other => {
+                        return Err(syn::Error::new_spanned(other, "expected `true` or `false`"));
+                    }
+                },
+                "transitions" => {
+                    let ident = syn::Ident::new("transition", key_ident(field).span());
+                    for transition in array(&field.expr, "`transitions` takes a list")? {
+                        let transition = value("transition", transition)?;
+                        body.extend(quote! { #ident: #transition, });
+                    }
+                }
+                _ => {
+                    let ident = key_ident(field);
+                    let value = value(&key, &field.expr)?;
+                    body.extend(quote! { #ident: #value, });
+                }
+            }
+        }
+        let name = name.ok_or_else(|| syn::Error::new_spanned(event, "`Event` missing `name`"))?;
+        let attrs = &event.attrs;
+        tokens.extend(quote! { #(#attrs)* #prefix #unsafety #name #code { #body } });
+    }
+    Ok(tokens)
+}
+
+/// The `states` list, with `Superstate { .. }` entries as superstate blocks.
+fn states(expr: &Expr) -> Result<TokenStream2> {
+    let mut tokens = TokenStream2::new();
+    for state in array(expr, "`states` takes a list")? {
+        match state {
+            Expr::Struct(superstate) if is_named(superstate, "Superstate") => {
+                let block = superstate_block(superstate)?;
+                tokens.extend(quote! { #block, });
+            }
+            other => tokens.extend(quote! { #other, }),
+        }
+    }
+    Ok(tokens)
+}
+
+/// `Superstate { name: Flight, states: [..], .. }` as `superstate Flight { .. }`.
+fn superstate_block(superstate: &ExprStruct) -> Result<TokenStream2> {
+    let mut name = None;
+    let mut body = TokenStream2::new();
+    for field in &superstate.fields {
+        let key = key(field)?;
+        match key.as_str() {
+            "name" => name = Some(&field.expr),
+            "states" => {
+                for state in array(&field.expr, "`states` takes a list")? {
+                    match state {
+                        Expr::Struct(nested) if is_named(nested, "Superstate") => {
+                            let block = superstate_block(nested)?;
+                            body.extend(quote! { #block, });
+                        }
+                        other => {
+                            // Attributes go before `state`
+                            let (attrs, state) = leading_attrs(quote! { #other });
+                            body.extend(quote! { #attrs state #state, });
+                        }
+                    }
+                }
+            }
+            _ => {
+                let ident = key_ident(field);
+                let value = value(&key, &field.expr)?;
+                body.extend(quote! { #ident: #value, });
+            }
+        }
+    }
+    let name =
+        name.ok_or_else(|| syn::Error::new_spanned(superstate, "`Superstate` missing `name`"))?;
+    let attrs = &superstate.attrs;
+    Ok(quote! { #(#attrs)* superstate #name { #body } })
+}
+
+/// Split the `#[...]` attributes off the front of `tokens`.
+fn leading_attrs(tokens: TokenStream2) -> (TokenStream2, TokenStream2) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut end = 0;
+    while let (Some(TokenTree::Punct(pound)), Some(TokenTree::Group(_))) =
+        (tokens.get(end), tokens.get(end + 1))
+    {
+        if pound.as_char() != '#' {
+            break;
+        }
+        end += 2;
+    }
+    (
+        tokens[..end].iter().cloned().collect(),
+        tokens[end..].iter().cloned().collect(),
+    )
+}
+
+/// The DSL form of the value of `key`.
+fn value(key: &str, expr: &Expr) -> Result<TokenStream2> {
+    match expr {
+        Expr::Struct(block) if BLOCKS.iter().any(|name| is_named(block, name)) => {
+            let mut body = TokenStream2::new();
+            for field in &block.fields {
+                let key = self::key(field)?;
+                let ident = key_ident(field);
+                let value = value(&key, &field.expr)?;
+                body.extend(quote! { #ident: #value, });
+            }
+            Ok(quote! { { #body } })
+        }
+        Expr::Array(array) => {
+            let elements = array
+                .elems
+                .iter()
+                .map(|element| value(key, element))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(quote! { [#(#elements),*] })
+        }
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(tokens),
+            ..
+        }) if TOKEN_KEYS.contains(&key) => tokens.parse(),
+        other => Ok(other.to_token_stream()),
+    }
+}
+
+fn array<'a>(expr: &'a Expr, message: &str) -> Result<impl Iterator<Item = &'a Expr>> {
+    match expr {
+        Expr::Array(array) => Ok(array.elems.iter()),
+        other => Err(syn::Error::new_spanned(other, message)),
+    }
+}
+
+fn is_named(expr: &ExprStruct, name: &str) -> bool {
+    expr.qself.is_none() && expr.path.is_ident(name)
+}
+
+fn key(field: &FieldValue) -> Result<String> {
+    match &field.member {
+        Member::Named(ident) => Ok(ident.to_string()),
+        Member::Unnamed(index) => Err(syn::Error::new(index.span, "expected a named field")),
+    }
+}
+
+/// The field's name as an identifier, with `r#` dropped.
+fn key_ident(field: &FieldValue) -> syn::Ident {
+    match &field.member {
+        Member::Named(ident) => {
+            let name = ident.to_string();
+            let name = name.strip_prefix("r#").unwrap_or(&name);
+            syn::Ident::new(name, ident.span())
+        }
+        Member::Unnamed(index) => syn::Ident::new("_", index.span),
+    }
+}
@@ -0,0 +1,1297 @@
+//! Type definitions for the state machine macro.
+//!
+//! This module contains all the core data structures used to represent
+//! state machines during macro expansion, including:
+//! - StateMachine: The root struct representing the entire machine
+//! - Event and Transition: Event definitions and their transitions
+//! - Hierarchy: Superstate tracking and resolution
+//! - Storage specifications for state-associated data
+
+use std::collections::{HashMap, HashSet};
+use syn::{Ident, Type};
+
+/// The main state machine definition parsed from the macro input.
+///
+/// Contains all the information needed to generate the state machine code,
+/// including states, events, transitions, and hierarchy information.
+pub struct StateMachine {
+    pub name: Ident,
+    pub initial: Ident,
+    pub context: Option<Type>,
+    pub states: Vec<Ident>,
+    pub state_storage: Vec<StateStorageSpec>,
+    /// `doc: "..."` strings for states, emitted on their marker types and enum variants.
+    pub state_docs: Vec<(Ident, syn::LitStr)>,
+    /// `ignore: [...]` events for states and superstates, dispatched as
+    /// successful no-ops (dynamic mode only).
+    pub state_ignores: Vec<(Ident, Vec<Ident>)>,
+    /// `max_duration:` dwell limits for leaf states, checked by `stuck_check()`.
+    pub state_max_durations: Vec<(Ident, syn::Expr)>,
+    /// `requires: [...]` capability traits the context must implement for
+    /// transitions into a state (or any state of a superstate).
+    pub state_requirements: Vec<(Ident, Vec<syn::Path>)>,
+    /// `State = 1` codes for leaf states, returned by `as_code()`.
+    pub state_codes: Vec<(Ident, syn::LitInt)>,
+    pub hierarchy: Hierarchy,
+    pub events: Vec<Event>,
+    pub async_mode: bool,
+    pub dynamic_mode: bool,
+    /// `mode: typestate | runtime | both`, with the value for error spans.
+    /// Without it, the dynamic wrapper follows `dynamic: true` and the
+    /// `dynamic` feature.
+    pub mode: Option<(Ident, MachineMode)>,
+    /// Generate a `wasm-bindgen` class around the dynamic wrapper (implies dynamic).
+    pub wasm_mode: bool,
+    /// Implement `HttpMachine` for the dynamic wrapper, so it can be served
+    /// by the `axum` router (implies dynamic).
+    pub http_mode: bool,
+    /// Emit a static transition table for callback-free edges instead of one
+    /// dispatch match arm per edge (dynamic mode only).
+    pub compact_codegen: bool,
+    /// Return a `Transition` record from `handle()` instead of `()` (dynamic mode only).
+    pub transition_records: bool,
+    /// Allow mirroring the current state into an `AtomicState` cell (dynamic mode only).
+    pub atomic_state: bool,
+    /// Fold leaf-state storage into the `{Name}State` variants, e.g.
+    /// `Failed(FailureInfo)`, instead of generating a fieldless enum (dynamic mode only).
+    pub data_states: bool,
+    /// Interrupt-safe event queue drained by `process()` (implies dynamic).
+    pub mailbox: Option<MailboxSpec>,
+    /// Deadline for each awaited guard, action, and callback (async mode only).
+    pub callback_timeout: Option<CallbackTimeout>,
+    /// What `handle()` does with events that have no transition from the
+    /// current state (dynamic mode only).
+    pub unhandled: UnhandledPolicy,
+    /// Machine-wide failure handler and automatic recovery event.
+    pub on_error: Option<OnError>,
+    /// Track when the current state was entered, for `time_in_state()` and
+    /// `stuck_check()` (dynamic mode only).
+    pub watchdog: bool,
+    /// `clock: Type`: the `Clock` the watchdog and transition records read,
+    /// instead of the `SystemClock` (dynamic mode only).
+    pub clock: Option<syn::Type>,
+    /// `history: N`: keep the last N transitions in the dynamic wrapper
+    /// (dynamic mode only).
+    pub history: Option<syn::LitInt>,
+    /// Count entries, exits, and guard rejections per state, exposed by
+    /// `stats()` (dynamic mode only; counting needs the `stats` feature).
+    pub stats: bool,
+    /// `evaluate_all_guards: true`: run every guard entry of a transition
+    /// before rejecting it, instead of stopping at the first that fails, and
+    /// list the failed entries in `GuardError::failed_guards`.
+    pub evaluate_all_guards: bool,
+    /// Implement `DynamicMachine` for the dynamic wrapper, so persistence
+    /// layers can rebuild it from a stored state and tools can drive it by
+    /// event name (dynamic mode only).
+    pub persistent: bool,
+    /// `sink: method` returning the `Sink` that `emit:` notifications go to.
+    pub sink: Option<Ident>,
+    /// `identity: true`: machines carry an optional `MachineId`, set with
+    /// `with_id()`, that is reported in traces, notifications, and records.
+    pub identity: bool,
+    /// `behavior: true`: guards and callbacks are generated to call the
+    /// context's `Behavior` by name. Holds the key, for error spans.
+    pub behavior: Option<Ident>,
+    /// `no_default_init: true`: entering a state with data needs the
+    /// transition's `init:` method or a payload of the data's type, instead
+    /// of `Default`. Holds the key, for error spans.
+    pub no_default_init: Option<Ident>,
+    /// Visibility of the generated types (or of `module`, when set). Defaults to `pub`.
+    pub visibility: syn::Visibility,
+    /// Wrap all generated items in a module of this name.
+    pub module: Option<Ident>,
+    /// User type parameters from `generics: <T: Sensor>`, placed after the
+    /// implicit context parameter and before the state parameter.
+    pub generics: syn::Generics,
+    /// Extra derives for the `{Name}State` enum (dynamic mode only).
+    pub state_derives: Vec<syn::Path>,
+    /// Extra derives for the `{Name}Event` enum (dynamic mode only).
+    pub event_derives: Vec<syn::Path>,
+    /// Extra outer attributes for the `{Name}State` enum (dynamic mode only).
+    pub state_attrs: Vec<syn::Attribute>,
+    /// Extra outer attributes for the typestate machine struct.
+    pub machine_attrs: Vec<syn::Attribute>,
+    /// Machine-wide `before_transition`/`after_transition` callbacks.
+    pub callbacks: GlobalCallbacks,
+    pub transition_graph: TransitionGraph,
+}
+
+/// Configuration of the `mailbox: { capacity: N, overflow: policy }` block.
+pub struct MailboxSpec {
+    /// The `mailbox` key, for error spans.
+    pub key: Ident,
+    pub capacity: syn::LitInt,
+    /// `true` for `overflow: drop_oldest`, `false` for `overflow: error`.
+    pub drop_oldest: bool,
+}
+
+/// The `callback_timeout` option.
+pub struct CallbackTimeout {
+    /// The `callback_timeout` key, for error spans.
+    pub key: Ident,
+    /// A `core::time::Duration` expression.
+    pub after: syn::Expr,
+    /// An `AsyncTimer` implementation; `None` means the Tokio adapter.
+    pub timer: Option<syn::Path>,
+}
+
+/// The `on_error` option.
+pub struct OnError {
+    /// The `on_error` key, for error spans.
+    pub key: Ident,
+    /// Method called with an `EventFailure` whenever an event fails.
+    pub handler: Option<Ident>,
+    /// Event `handle()` fires after `after` consecutive failures (dynamic mode only).
+    pub recover: Option<Ident>,
+    /// Consecutive failures before `recover` fires; defaults to 1.
+    pub after: Option<syn::LitInt>,
+}
+
+/// The `forward: { to: session, as: SessionEvent::Start }` transition option.
+#[derive(Clone)]
+pub struct ForwardSpec {
+    /// Method returning the `Forward` receiver, e.g. a sibling machine in
+    /// the context: `fn(&mut self) -> &mut impl Forward<E>`.
+    pub to: Ident,
+    /// The event to forward.
+    pub event: syn::Expr,
+}
+
+/// The `retry: { attempts: 3, backoff: exponential(100ms) }` event option.
+pub struct RetrySpec {
+    /// The `retry` key, for error spans.
+    pub key: Ident,
+    /// Total number of attempts, including the first.
+    pub attempts: syn::LitInt,
+    pub backoff: BackoffSpec,
+    /// An `AsyncTimer` implementation for the delays; `None` means the
+    /// `callback_timeout` timer, or the Tokio adapter.
+    pub timer: Option<syn::Path>,
+}
+
+/// The delay between retry attempts.
+pub enum BackoffSpec {
+    /// `none`: retry immediately.
+    None,
+    /// `fixed(50ms)`: the same `Duration` every time.
+    Fixed(syn::Expr),
+    /// `exponential(100ms)`: the `Duration` doubles after each attempt.
+    Exponential(syn::Expr),
+}
+
+/// How generated code calls a method a `behavior: true` machine looks up.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BehaviorRole {
+    /// `fn(&self, ctx[, payload]) -> bool`; `negated` when the transition
+    /// needs it to fail, in `unless` or under `!`.
+    Guard { negated: bool },
+    /// `fn(&mut self[, payload])`
+    Callback,
+    /// A global `before_transition` callback, returning `ControlFlow<()>`.
+    BeforeTransition,
+}
+
+impl BehaviorRole {
+    pub fn describe(self) -> &'static str {
+        match self {
+            BehaviorRole::Guard { negated: false } => "a guard",
+            BehaviorRole::Guard { negated: true } => "a negated guard",
+            BehaviorRole::Callback => "a callback",
+            BehaviorRole::BeforeTransition => "a `before_transition` callback",
+        }
+    }
+}
+
+/// The `callbacks: { before_transition [...], after_transition [...] }` block.
+#[derive(Default)]
+pub struct GlobalCallbacks {
+    /// Sorted by descending `priority`, ties in declaration order.
+    pub before_transition: Vec<GlobalCallback>,
+    /// Run in declaration order.
+    pub after_transition: Vec<GlobalCallback>,
+}
+
+/// A machine-wide callback:
+/// `{ name: cb, from: [A], to: [B], on: [event], priority: 10 }`.
+///
+/// `before_transition` callbacks return `ControlFlow<()>`; `Break` skips the
+/// rest of the chain without aborting the transition.
+#[derive(Clone)]
+pub struct GlobalCallback {
+    /// The name and its `from`/`to` filters.
+    pub callback: CallbackSpec,
+    /// Events the callback runs on; empty means every event.
+    pub on: Vec<Ident>,
+    pub priority: i32,
+}
+
+impl GlobalCallback {
+    /// Whether the callback runs on `event`'s edge between two leaf states.
+    pub fn applies(
+        &self,
+        hierarchy: &Hierarchy,
+        states: &[Ident],
+        event: &Ident,
+        source: &Ident,
+        target: &Ident,
+    ) -> bool {
+        (self.on.is_empty() || self.on.contains(event))
+            && self.callback.applies(hierarchy, states, source, target)
+    }
+}
+
+/// The `mode: typestate | runtime | both` choice of generated API.
+///
+/// The runtime machine (`Dynamic{Name}`) stores typestate machines and
+/// dispatches to their transition methods, so both modes share state data,
+/// hierarchy, guards, and callbacks; the mode only picks what's generated
+/// and documented.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MachineMode {
+    /// Only the typestate machine, even with the `dynamic` feature enabled.
+    Typestate,
+    /// The runtime machine, with the typestate machine it wraps hidden
+    /// from the docs.
+    Runtime,
+    /// Both, as with `dynamic: true`.
+    Both,
+}
+
+/// The `unhandled: ignore | error | panic | callback(name)` policy.
+#[derive(Default)]
+pub enum UnhandledPolicy {
+    /// Return `DynamicError::InvalidTransition` (the default).
+    #[default]
+    Error,
+    /// Succeed without changing state.
+    Ignore,
+    /// Panic with the state and event names.
+    Panic,
+    /// Pass the event to `fn name(&self, state, event)`, then succeed
+    /// without changing state.
+    Callback(Ident),
+}
+
+/// Graph of all possible transitions between states.
+///
+/// Maps each state to a list of (target_state, event, transition) tuples.
+/// Used for typestate validation and code generation.
+#[derive(Default)]
+pub struct TransitionGraph {
+    /// Maps source state -> Vec<(target, event_name, transition)>
+    pub edges: HashMap<String, Vec<TransitionEdge>>,
+}
+
+/// A single edge in the transition graph.
+#[derive(Clone)]
+pub struct TransitionEdge {
+    /// The declared source: the leaf itself, or a superstate containing it.
+    pub declared_source: Ident,
+    pub target: Ident,
+    pub event: Ident,
+    pub guards: Vec<GuardExpr>,
+    pub unless: Vec<GuardExpr>,
+    pub before: Vec<Ident>,
+    pub after: Vec<Ident>,
+    /// Callbacks run once the transition can no longer be undone: last in
+    /// the typestate method, and in dynamic mode only after `handle()` has
+    /// stored the new state (and a `SharedMachine` has saved it). They don't
+    /// get the payload.
+    pub after_commit: Vec<Ident>,
+    /// Global `before_transition` callbacks, in priority order.
+    pub before_transition: Vec<Ident>,
+    /// Global `after_transition` callbacks.
+    pub after_transition: Vec<Ident>,
+    pub around: Vec<Ident>,
+    pub action: Option<Ident>,
+    pub compensate: Vec<Ident>,
+    /// Runtime target selection; `target` is then the first of its targets.
+    pub choice: Option<ChoiceSpec>,
+    /// `{Name}Notification` variant sent to the machine's `sink` on success.
+    pub emit: Option<Ident>,
+    /// Method building the target state's data instead of `Default`.
+    pub init: Option<Ident>,
+    pub forward: Vec<ForwardSpec>,
+    pub payload: Option<Type>,
+    /// `payload: T by value`: see [`TransitionEdge::payload_owner`].
+    pub payload_by_value: bool,
+}
+
+impl TransitionEdge {
+    /// The callback that takes a `by value` payload instead of borrowing it:
+    /// the last one to see it, once the transition can't be rejected. That's
+    /// the final `after` entry, or `init:` without any.
+    pub fn payload_owner(&self) -> Option<&Ident> {
+        if !self.payload_by_value {
+            return None;
+        }
+        self.after.last().or(self.init.as_ref())
+    }
+
+    /// Check whether this edge has no guards or callbacks of any kind.
+    ///
+    /// Such an edge only changes the state tag, so it can be driven from a
+    /// table instead of calling its typed transition method.
+    pub fn is_plain(&self) -> bool {
+        self.guards.is_empty()
+            && self.unless.is_empty()
+            && self.before.is_empty()
+            && self.after.is_empty()
+            && self.after_commit.is_empty()
+            && self.before_transition.is_empty()
+            && self.after_transition.is_empty()
+            && self.around.is_empty()
+            && self.action.is_none()
+            && self.compensate.is_empty()
+            && self.choice.is_none()
+            && self.emit.is_none()
+            && self.init.is_none()
+            && self.forward.is_empty()
+    }
+}
+
+impl TransitionGraph {
+    /// Add a transition edge to the graph.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_edge(
+        &mut self,
+        source: &Ident,
+        declared_source: Ident,
+        target: Ident,
+        event: Ident,
+        guards: Vec<GuardExpr>,
+        unless: Vec<GuardExpr>,
+        before: Vec<Ident>,
+        after: Vec<Ident>,
+        after_commit: Vec<Ident>,
+        before_transition: Vec<Ident>,
+        after_transition: Vec<Ident>,
+        around: Vec<Ident>,
+        action: Option<Ident>,
+        compensate: Vec<Ident>,
+        choice: Option<ChoiceSpec>,
+        emit: Option<Ident>,
+        init: Option<Ident>,
+        forward: Vec<ForwardSpec>,
+        payload: Option<Type>,
+        payload_by_value: bool,
+    ) {
+        self.edges
+            .entry(source.to_string())
+            .or_default()
+            .push(TransitionEdge {
+                declared_source,
+                target,
+                event,
+                guards,
+                unless,
+                before,
+                after,
+                after_commit,
+                before_transition,
+                after_transition,
+                around,
+                action,
+                compensate,
+                choice,
+                emit,
+                init,
+                forward,
+                payload,
+                payload_by_value,
+            });
+    }
+
+    /// Get all outgoing transitions from a state.
+    pub fn outgoing(&self, state: &Ident) -> Option<&Vec<TransitionEdge>> {
+        self.edges.get(&state.to_string())
+    }
+}
+
+impl StateMachine {
+    /// How a transition into `target` fills in the target's data.
+    ///
+    /// With `no_default_init: true`, a payload of the data's type is moved
+    /// in; without an `init:` or such a payload, validation reports the edge.
+    pub fn data_init<'a>(
+        &'a self,
+        target: &Ident,
+        init: Option<&'a Ident>,
+        payload: Option<&Type>,
+    ) -> DataInit<'a> {
+        let Some(spec) = self.state_storage_for(target) else {
+            return DataInit::None;
+        };
+        let same_type = |payload: &Type| {
+            let (payload, ty) = (&payload, &spec.ty);
+            quote::quote!(#payload).to_string() == quote::quote!(#ty).to_string()
+        };
+        match init {
+            Some(init) => DataInit::Init(init),
+            None if self.no_default_init.is_some() && payload.is_some_and(same_type) => {
+                DataInit::Payload
+            }
+            None => DataInit::Default(&spec.ty),
+        }
+    }
+
+    /// The transition `event` takes from the leaf state `source`.
+    ///
+    /// The most specific declared source wins: the leaf itself, then the
+    /// smallest superstate around it. Validation rejects overlapping sources
+    /// today, but dispatch, `can_handle()`, guard probes, and dry runs all
+    /// pick their edge here, so they can't disagree about which applies.
+    pub fn edge_for(&self, source: &Ident, event: &Ident) -> Option<&TransitionEdge> {
+        self.transition_graph
+            .outgoing(source)?
+            .iter()
+            .filter(|edge| &edge.event == event)
+            .min_by_key(|edge| {
+                self.hierarchy
+                    .expand_state(&edge.declared_source, &self.states)
+                    .len()
+            })
+    }
+
+    /// Whether any transition has `after_commit` callbacks, which the
+    /// dynamic wrapper then runs once it has stored the new state.
+    pub fn has_after_commit(&self) -> bool {
+        self.transition_graph
+            .edges
+            .values()
+            .flatten()
+            .any(|edge| !edge.after_commit.is_empty())
+    }
+
+    /// Storage declared directly on a state, e.g. `Failed(FailureInfo)`.
+    pub fn state_storage_for(&self, state: &Ident) -> Option<&StateStorageSpec> {
+        self.state_storage
+            .iter()
+            .find(|spec| &spec.state_name == state)
+    }
+
+    /// Lifetimes the machine is generic over: those declared in `generics:`,
+    /// then any others named in the `context:` type.
+    pub fn machine_lifetimes(&self) -> Vec<syn::Lifetime> {
+        let mut lifetimes: Vec<_> = self
+            .generics
+            .lifetimes()
+            .map(|param| param.lifetime.clone())
+            .collect();
+        if let Some(ctx) = &self.context {
+            crate::utils::collect_lifetimes(ctx, &mut lifetimes);
+        }
+        lifetimes
+    }
+
+    /// The machine's generic parameters ahead of the state, with bounds:
+    /// its lifetimes, `C` (unless `context:` is concrete), then the
+    /// `generics:` type parameters.
+    pub fn generic_params(&self) -> Vec<proc_macro2::TokenStream> {
+        let declared: Vec<_> = self.generics.lifetimes().collect();
+        let lifetimes = self.machine_lifetimes().into_iter().map(|lifetime| {
+            match declared.iter().find(|param| param.lifetime == lifetime) {
+                Some(param) => quote::quote! { #param },
+                None => quote::quote! { #lifetime },
+            }
+        });
+        let ctx = self.context.is_none().then(|| quote::quote! { C });
+        lifetimes
+            .chain(ctx)
+            .chain(
+                self.generics
+                    .type_params()
+                    .map(|param| quote::quote! { #param }),
+            )
+            .collect()
+    }
+
+    /// The same parameters as `generic_params()`, as bare arguments.
+    pub fn generic_args(&self) -> Vec<proc_macro2::TokenStream> {
+        let lifetimes = self
+            .machine_lifetimes()
+            .into_iter()
+            .map(|lifetime| quote::quote! { #lifetime });
+        let ctx = self.context.is_none().then(|| quote::quote! { C });
+        lifetimes
+            .chain(ctx)
+            .chain(self.generics.type_params().map(|param| {
+                let ident = &param.ident;
+                quote::quote! { #ident }
+            }))
+            .collect()
+    }
+
+    /// Lifetimes named in event payload types, which the `{Name}Event` enum
+    /// is generic over.
+    pub fn event_lifetimes(&self) -> Vec<syn::Lifetime> {
+        let mut lifetimes = Vec::new();
+        for payload in self
+            .events
+            .iter()
+            .filter_map(|event| event.payload.as_ref())
+        {
+            crate::utils::collect_lifetimes(payload, &mut lifetimes);
+        }
+        lifetimes
+    }
+
+    /// Payload lifetimes that the machine isn't generic over, which methods
+    /// taking the payload declare themselves, e.g.
+    /// `fn receive<'p>(self, payload: &'p [u8])`. Without a payload type,
+    /// covers every event payload (for methods taking a `{Name}Event`).
+    pub fn method_lifetimes(&self, payload: Option<&Type>) -> proc_macro2::TokenStream {
+        let mut lifetimes = Vec::new();
+        match payload {
+            Some(payload) => crate::utils::collect_lifetimes(payload, &mut lifetimes),
+            None => lifetimes = self.event_lifetimes(),
+        }
+        let machine_lifetimes = self.machine_lifetimes();
+        lifetimes.retain(|lifetime| !machine_lifetimes.contains(lifetime));
+        if lifetimes.is_empty() {
+            quote::quote! {}
+        } else {
+            quote::quote! { <#(#lifetimes),*> }
+        }
+    }
+
+    /// The `{Name}Event` type, e.g. `FrameEvent<'p>`. With `static_lifetimes`
+    /// every lifetime is `'static`, for events that outlive any borrow.
+    pub fn event_type(&self, static_lifetimes: bool) -> proc_macro2::TokenStream {
+        let event_name = quote::format_ident!("{}Event", self.name);
+        let lifetimes = self.event_lifetimes();
+        if lifetimes.is_empty() {
+            quote::quote! { #event_name }
+        } else if static_lifetimes {
+            let lifetimes = lifetimes.iter().map(|_| quote::quote! { 'static });
+            quote::quote! { #event_name<#(#lifetimes),*> }
+        } else {
+            quote::quote! { #event_name<#(#lifetimes),*> }
+        }
+    }
+
+    /// `<C, T: Sensor>` for the impl blocks of types without a state
+    /// parameter (`Dynamic{Name}`, `Any{Name}State`), or nothing.
+    pub fn impl_generics(&self) -> proc_macro2::TokenStream {
+        let params = self.generic_params();
+        if params.is_empty() {
+            quote::quote! {}
+        } else {
+            quote::quote! { <#(#params),*> }
+        }
+    }
+
+    /// `<C, T>` to name `Dynamic{Name}` and `Any{Name}State`, or nothing.
+    pub fn type_generics(&self) -> proc_macro2::TokenStream {
+        let args = self.generic_args();
+        if args.is_empty() {
+            quote::quote! {}
+        } else {
+            quote::quote! { <#(#args),*> }
+        }
+    }
+
+    /// The typestate machine in `state`, e.g. `Name<C, T, Docked>`.
+    pub fn machine_type(&self, state: impl quote::ToTokens) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let args = self.generic_args();
+        quote::quote! { #name<#(#args,)* #state> }
+    }
+
+    /// The context type: the concrete `context:` type, or the `C` parameter.
+    pub fn ctx_type(&self) -> proc_macro2::TokenStream {
+        match &self.context {
+            Some(ctx) => quote::quote! { #ctx },
+            None => quote::quote! { C },
+        }
+    }
+
+    /// `where` bounds requiring `bound` of every state data type, and of the
+    /// context with `with_ctx`, for impls that only apply when those hold.
+    ///
+    /// Each bound gets an unused `for<'__b>` binder: a bound on a concrete
+    /// type would otherwise be checked where the impl is declared, failing
+    /// to compile instead of leaving the impl out.
+    pub fn data_bounds(
+        &self,
+        with_ctx: bool,
+        bound: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let ctx = with_ctx.then(|| self.ctx_type());
+        let data = self.state_storage.iter().map(|spec| {
+            let ty = &spec.ty;
+            quote::quote! { #ty }
+        });
+        let types = ctx.into_iter().chain(data);
+        quote::quote! { where #(for<'__b> #types: #bound,)* }
+    }
+
+    /// Visibility for generated types.
+    ///
+    /// Inside a `module:` wrapper the module itself carries the configured
+    /// visibility, so the items are `pub` and the module limits their reach.
+    pub fn item_visibility(&self) -> proc_macro2::TokenStream {
+        if self.module.is_some() {
+            quote::quote! { pub }
+        } else {
+            let vis = &self.visibility;
+            quote::quote! { #vis }
+        }
+    }
+
+    /// Visibility for the machine's `ctx` field.
+    ///
+    /// Guards and callbacks live next to the macro invocation, so with a
+    /// `module:` wrapper the field must be visible to the parent module.
+    pub fn ctx_visibility(&self) -> proc_macro2::TokenStream {
+        if self.module.is_some() {
+            quote::quote! { pub(super) }
+        } else {
+            quote::quote! {}
+        }
+    }
+
+    /// The `{Name}{Event}Choice` and `{Name}{Event}Outcome` enums of an
+    /// event with `choose:` transitions.
+    pub fn choice_enum_names(&self, event: &Ident) -> (Ident, Ident) {
+        let pascal = crate::utils::to_pascal_case(&event.to_string());
+        (
+            quote::format_ident!("{}{}Choice", self.name, pascal),
+            quote::format_ident!("{}{}Outcome", self.name, pascal),
+        )
+    }
+
+    /// The leaf state a transition target enters (superstates enter their
+    /// initial child).
+    pub fn resolve_target(&self, target: &Ident) -> Ident {
+        self.hierarchy
+            .resolve_target(target)
+            .unwrap_or_else(|| target.clone())
+    }
+
+    /// The `doc: "..."` string declared for a state, if any.
+    pub fn state_doc(&self, state: &Ident) -> Option<&syn::LitStr> {
+        self.state_docs
+            .iter()
+            .find(|(name, _)| name == state)
+            .map(|(_, doc)| doc)
+    }
+
+    /// The `max_duration:` dwell limit declared on `state`, if any.
+    pub fn state_max_duration(&self, state: &Ident) -> Option<&syn::Expr> {
+        self.state_max_durations
+            .iter()
+            .find(|(name, _)| name == state)
+            .map(|(_, limit)| limit)
+    }
+
+    /// The capabilities the context needs to enter the leaf state `state`:
+    /// its own `requires:`, then its superstates'.
+    pub fn state_requirements(&self, state: &Ident) -> Vec<&syn::Path> {
+        let ancestors = self
+            .hierarchy
+            .ancestors
+            .get(&state.to_string())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut requirements = Vec::new();
+        for owner in std::iter::once(state).chain(ancestors) {
+            for (_, paths) in self
+                .state_requirements
+                .iter()
+                .filter(|(name, _)| name == owner)
+            {
+                requirements.extend(paths);
+            }
+        }
+        requirements
+    }
+
+    /// `where` bounds requiring the capabilities of every state in
+    /// `targets` of the context, for methods that enter them, or nothing.
+    ///
+    /// Like `data_bounds()`, each bound gets an unused `for<'__b>` binder, so
+    /// with a concrete context a missing capability fails the call rather
+    /// than the definition.
+    pub fn requirement_bounds(&self, targets: &[Ident]) -> proc_macro2::TokenStream {
+        let mut paths: Vec<&syn::Path> = Vec::new();
+        let mut seen = HashSet::new();
+        for target in targets {
+            for path in self.state_requirements(target) {
+                if seen.insert(quote::quote! { #path }.to_string()) {
+                    paths.push(path);
+                }
+            }
+        }
+        if paths.is_empty() {
+            return quote::quote! {};
+        }
+        let ctx = self.ctx_type();
+        quote::quote! { where #(for<'__b> #ctx: #paths,)* }
+    }
+
+    /// The `State = 1` code declared for a leaf state, if any.
+    pub fn state_code(&self, state: &Ident) -> Option<&syn::LitInt> {
+        self.state_codes
+            .iter()
+            .find(|(name, _)| name == state)
+            .map(|(_, code)| code)
+    }
+
+    /// Events ignored in the leaf state `state`, including those its
+    /// superstates ignore.
+    pub fn ignored_events(&self, state: &Ident) -> Vec<&Ident> {
+        let mut events: Vec<&Ident> = Vec::new();
+        for (owner, ignored) in &self.state_ignores {
+            if !self
+                .hierarchy
+                .expand_state(owner, &self.states)
+                .contains(state)
+            {
+                continue;
+            }
+            for event in ignored {
+                if !events.contains(&event) {
+                    events.push(event);
+                }
+            }
+        }
+        events
+    }
+
+    /// The `doc: "..."` string declared for an event, if any.
+    pub fn event_doc(&self, event: &Ident) -> Option<&syn::LitStr> {
+        self.events
+            .iter()
+            .find(|candidate| &candidate.name == event)
+            .and_then(|candidate| candidate.doc.as_ref())
+    }
+
+    /// The `MachineId` of the typed machine `machine`, e.g. `self`; `None`
+    /// without `identity: true`.
+    pub fn id_of(&self, machine: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.identity {
+            quote::quote! { #machine.id }
+        } else {
+            quote::quote! { ::core::option::Option::None }
+        }
+    }
+
+    /// The `id` field of a typed machine built from `value`, with
+    /// `identity: true`.
+    pub fn id_field_init(
+        &self,
+        value: proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        self.identity.then(|| quote::quote! { id: #value, })
+    }
+
+    /// The `alias: [...]` method names declared for an event.
+    pub fn event_aliases(&self, event: &Ident) -> &[Ident] {
+        self.events
+            .iter()
+            .find(|candidate| &candidate.name == event)
+            .map_or(&[], |candidate| candidate.aliases.as_slice())
+    }
+
+    /// All guards declared with `{ cache: true }`, without duplicates.
+    ///
+    /// Caching is a property of the guard method itself, so marking it once
+    /// applies to every event that references it.
+    pub fn cached_guard_names(&self) -> Vec<&Ident> {
+        let mut names: Vec<&Ident> = Vec::new();
+        for guard in self.events.iter().flat_map(|event| &event.cached_guards) {
+            if !names.contains(&guard) {
+                names.push(guard);
+            }
+        }
+        names
+    }
+
+    /// Whether calls to a guard need `.await`.
+    ///
+    /// In async machines every guard is awaited unless it's marked
+    /// `{ sync: true }` somewhere; like caching, that applies to every event
+    /// that references it.
+    pub fn guard_is_async(&self, guard: &Ident) -> bool {
+        self.async_mode
+            && !self
+                .events
+                .iter()
+                .any(|event| event.sync_guards.contains(guard))
+    }
+
+    /// The `retry` option of an event, if any.
+    pub fn event_retry(&self, event: &Ident) -> Option<&RetrySpec> {
+        self.events
+            .iter()
+            .find(|candidate| &candidate.name == event)
+            .and_then(|event| event.retry.as_ref())
+    }
+
+    /// Whether `callback` is declared `split: true`. The flag belongs to the
+    /// method, since one method can only have one signature.
+    pub fn split_callback(&self, callback: &Ident) -> bool {
+        self.events.iter().any(|event| {
+            event
+                .before
+                .iter()
+                .chain(&event.after)
+                .chain(
+                    event
+                        .transitions
+                        .iter()
+                        .flat_map(|t| t.before.iter().chain(&t.after)),
+                )
+                .any(|spec| spec.split && &spec.name == callback)
+        })
+    }
+
+    /// The visibility of an event's methods.
+    pub fn event_visibility(&self, event: &Ident) -> proc_macro2::TokenStream {
+        match self
+            .events
+            .iter()
+            .find(|candidate| &candidate.name == event)
+            .and_then(|event| event.visibility.as_ref())
+        {
+            Some(visibility) => quote::quote! { #visibility },
+            None => quote::quote! { pub },
+        }
+    }
+
+    /// `unsafe`, for an event whose transition methods are `unsafe fn`s.
+    pub fn event_unsafety(&self, event: &Ident) -> Option<syn::Token![unsafe]> {
+        self.events
+            .iter()
+            .find(|candidate| &candidate.name == event)
+            .and_then(|event| event.unsafety)
+    }
+
+    /// The `transform` method of an event, if any.
+    pub fn event_transform(&self, event: &Ident) -> Option<&Ident> {
+        self.events
+            .iter()
+            .find(|candidate| &candidate.name == event)
+            .and_then(|event| event.transform.as_ref())
+    }
+
+    /// Every guard and callback a `behavior: true` machine looks up by name,
+    /// once per use, in declaration order: the name, how it's called, and
+    /// whether a payload is passed.
+    pub fn behavior_calls(&self) -> Vec<(&Ident, BehaviorRole, bool)> {
+        let mut calls = Vec::new();
+        let edges = self
+            .states
+            .iter()
+            .filter_map(|state| self.transition_graph.outgoing(state))
+            .flatten();
+        for edge in edges {
+            let payload = edge.payload.is_some();
+            let exprs = (edge.guards.iter().map(|expr| (expr, false)))
+                .chain(edge.unless.iter().map(|expr| (expr, true)));
+            for (expr, unless) in exprs {
+                for (guard, negated) in expr.polarized_calls(unless) {
+                    calls.push((guard, BehaviorRole::Guard { negated }, payload));
+                }
+            }
+            for callback in edge.before.iter().chain(&edge.after) {
+                calls.push((callback, BehaviorRole::Callback, payload));
+            }
+            for callback in edge.after_commit.iter().chain(&edge.after_transition) {
+                calls.push((callback, BehaviorRole::Callback, false));
+            }
+            for callback in &edge.before_transition {
+                calls.push((callback, BehaviorRole::BeforeTransition, false));
+            }
+        }
+        calls
+    }
+
+    /// Whether an around callback was declared with `{ payload: true }`.
+    ///
+    /// Like guard caching, this is a property of the method: its signature
+    /// gains a `&payload` argument on every event that uses it.
+    pub fn around_takes_payload(&self, callback: &Ident) -> bool {
+        self.events
+            .iter()
+            .any(|event| event.payload_around.contains(callback))
+    }
+}
+
+/// A guard condition: a single guard method or a boolean combination of them.
+///
+/// Parsed from guard lists such as `guards: [in_orbit && (engines_off || docked)]`.
+/// `!` binds tighter than `&&`, which binds tighter than `||`. A guard may
+/// take literal arguments, as in `min_altitude(100)`, which are passed after
+/// the context and payload.
+#[derive(Clone)]
+pub enum GuardExpr {
+    Guard(Ident, Vec<syn::Expr>),
+    Not(Box<GuardExpr>),
+    And(Box<GuardExpr>, Box<GuardExpr>),
+    Or(Box<GuardExpr>, Box<GuardExpr>),
+}
+
+impl GuardExpr {
+    /// Collect every guard method referenced by this expression, with its
+    /// literal arguments, in source order.
+    pub fn calls(&self) -> Vec<(&Ident, &[syn::Expr])> {
+        match self {
+            GuardExpr::Guard(ident, args) => vec![(ident, args.as_slice())],
+            GuardExpr::Not(inner) => inner.calls(),
+            GuardExpr::And(lhs, rhs) | GuardExpr::Or(lhs, rhs) => {
+                let mut calls = lhs.calls();
+                calls.extend(rhs.calls());
+                calls
+            }
+        }
+    }
+
+    /// Every guard referenced by this expression, with whether the
+    /// expression needs it to fail: under an odd number of `!`, flipped
+    /// once more by `negated`.
+    pub fn polarized_calls(&self, negated: bool) -> Vec<(&Ident, bool)> {
+        match self {
+            GuardExpr::Guard(ident, _) => vec![(ident, negated)],
+            GuardExpr::Not(inner) => inner.polarized_calls(!negated),
+            GuardExpr::And(lhs, rhs) | GuardExpr::Or(lhs, rhs) => {
+                let mut calls = lhs.polarized_calls(negated);
+                calls.extend(rhs.polarized_calls(negated));
+                calls
+            }
+        }
+    }
+
+    /// Render a single guard call, e.g. `has_role("admin")`.
+    pub fn describe_call(guard: &Ident, args: &[syn::Expr]) -> String {
+        if args.is_empty() {
+            return guard.to_string();
+        }
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| match arg {
+                syn::Expr::Unary(unary) => {
+                    let operand = &unary.expr;
+                    format!("-{}", quote::quote!(#operand))
+                }
+                _ => quote::quote!(#arg).to_string(),
+            })
+            .collect();
+        format!("{}({})", guard, args.join(", "))
+    }
+
+    /// Render the expression as source-like text.
+    ///
+    /// This is the name reported in `GuardError::guard` when the condition
+    /// fails, so a plain guard is reported by its method name as before.
+    pub fn describe(&self) -> String {
+        match self {
+            GuardExpr::Guard(ident, args) => Self::describe_call(ident, args),
+            GuardExpr::Not(inner) => match **inner {
+                GuardExpr::Guard(..) | GuardExpr::Not(_) => format!("!{}", inner.describe()),
+                _ => format!("!({})", inner.describe()),
+            },
+            GuardExpr::And(lhs, rhs) => {
+                let side = |expr: &GuardExpr| match expr {
+                    GuardExpr::Or(..) => format!("({})", expr.describe()),
+                    _ => expr.describe(),
+                };
+                format!("{} && {}", side(lhs), side(rhs))
+            }
+            GuardExpr::Or(lhs, rhs) => format!("{} || {}", lhs.describe(), rhs.describe()),
+        }
+    }
+}
+
+/// An event definition with its transitions and callbacks.
+///
+/// Events are the triggers that cause state transitions. Each event can have:
+/// - Multiple transitions (from different source states to different targets)
+/// - Guards that must pass before any transition can occur
+/// - Before/after callbacks that run around transitions
+/// - Around callbacks that wrap the entire transition
+/// - An optional payload type for passing data
+pub struct Event {
+    pub name: Ident,
+    /// `event = 1 { ... }`: the stable code returned by `as_code()`.
+    pub code: Option<syn::LitInt>,
+    /// `doc: "..."` text, emitted on the generated event methods and enum variant.
+    pub doc: Option<syn::LitStr>,
+    /// `alias: [...]`: more method names for the event's transition methods.
+    pub aliases: Vec<Ident>,
+    /// `pub(crate) force_unlock { ... }`: the visibility of the event's
+    /// methods, `pub` when not given.
+    pub visibility: Option<syn::Visibility>,
+    /// `unsafe force_unlock { ... }`: the event's transition methods are
+    /// `unsafe fn`s.
+    pub unsafety: Option<syn::Token![unsafe]>,
+    /// `dangerous: true`: every transition should be guarded. Noted in the
+    /// generated docs and checked by the `cargo state-machines` linter.
+    pub dangerous: bool,
+    /// `retry: { ... }`: retry failed transitions before returning the error
+    /// (async mode only).
+    pub retry: Option<RetrySpec>,
+    pub payload: Option<Type>,
+    /// `payload: T by value`: the last callback to see the payload owns it.
+    pub payload_by_value: bool,
+    /// `transform: method`: rewrites the payload before anything else sees it.
+    pub transform: Option<Ident>,
+    pub transitions: Vec<Transition>,
+    pub guards: Vec<GuardExpr>,
+    pub unless: Vec<GuardExpr>,
+    pub before: Vec<CallbackSpec>,
+    pub after: Vec<CallbackSpec>,
+    /// Run once nothing can undo the transition; see `TransitionEdge::after_commit`.
+    pub after_commit: Vec<CallbackSpec>,
+    pub around: Vec<Ident>,
+    /// Guards marked `{ cache: true }` anywhere in this event (including its transitions).
+    pub cached_guards: Vec<Ident>,
+    /// Guards marked `{ sync: true }` anywhere in this event (including its
+    /// transitions); they aren't awaited in async machines.
+    pub sync_guards: Vec<Ident>,
+    /// Around callbacks marked `{ payload: true }` anywhere in this event
+    /// (including its transitions).
+    pub payload_around: Vec<Ident>,
+}
+
+/// A single transition within an event.
+///
+/// Defines a transition from one or more source states to a target state.
+/// Can have its own guards and callbacks in addition to event-level ones.
+pub struct Transition {
+    pub sources: Vec<Ident>,
+    /// The target state; for a `choose:` transition, the first of its targets.
+    pub target: Ident,
+    pub guards: Vec<GuardExpr>,
+    pub unless: Vec<GuardExpr>,
+    pub before: Vec<CallbackSpec>,
+    pub after: Vec<CallbackSpec>,
+    pub after_commit: Vec<CallbackSpec>,
+    pub around: Vec<Ident>,
+    /// Transition-scoped action: `fn(&mut ctx[, &payload]) -> bool`, `false` vetoes.
+    pub action: Option<Ident>,
+    /// Undo hooks run in reverse order if the transition fails after `before` callbacks.
+    pub compensate: Vec<Ident>,
+    /// `choose: method` with `to: [A, B]`: the method picks the target at runtime.
+    pub choice: Option<ChoiceSpec>,
+    /// `emit: Variant`: send `{Name}Notification::Variant` to the machine's
+    /// `sink` once the transition succeeds.
+    pub emit: Option<Ident>,
+    /// `init: method`: builds the target state's data,
+    /// `fn(&self, &ctx[, &payload]) -> Data`.
+    pub init: Option<Ident>,
+    /// Events handed to other machines once the transition is done, in order.
+    pub forward: Vec<ForwardSpec>,
+    pub cached_guards: Vec<Ident>,
+    pub sync_guards: Vec<Ident>,
+    pub payload_around: Vec<Ident>,
+}
+
+/// A `before`/`after` callback of an event or transition.
+///
+/// Written either as a bare name or as `{ name: cb, from: [A, B], to: C }`
+/// to run only on some of the edges in its scope. Empty filters match every
+/// state; superstates match all of their descendants.
+#[derive(Clone)]
+pub struct CallbackSpec {
+    pub name: Ident,
+    pub from: Vec<Ident>,
+    pub to: Vec<Ident>,
+    /// `split: true`: called as `fn(data: &mut D, ctx: &C[, payload])`, with
+    /// the data of the state it runs in (the source before the transition,
+    /// the target after it) and the context borrowed separately.
+    pub split: bool,
+}
+
+impl CallbackSpec {
+    /// Whether the callback runs on the edge between two leaf states.
+    pub fn applies(
+        &self,
+        hierarchy: &Hierarchy,
+        states: &[Ident],
+        source: &Ident,
+        target: &Ident,
+    ) -> bool {
+        let matches = |filter: &[Ident], leaf: &Ident| {
+            filter.is_empty()
+                || filter
+                    .iter()
+                    .any(|state| hierarchy.expand_state(state, states).contains(leaf))
+        };
+        matches(&self.from, source) && matches(&self.to, target)
+    }
+}
+
+/// A choice pseudo-state: `chooser` returns which of `targets` to enter.
+///
+/// Called like a guard, `fn(&self, ctx: &C[, payload: &P])`, and returns the
+/// generated `{Name}{Event}Choice` enum. Targets keep their declared names;
+/// superstate targets are resolved to their initial child when entered.
+#[derive(Clone)]
+pub struct ChoiceSpec {
+    pub chooser: Ident,
+    pub targets: Vec<Ident>,
+}
+
+/// Where a transition gets its target state's data from.
+pub enum DataInit<'a> {
+    /// The target has no data.
+    None,
+    /// `StateData::initial()`, i.e. `Default::default()`.
+    Default(&'a Type),
+    /// The transition's `init:` method.
+    Init(&'a Ident),
+    /// The event payload, moved in.
+    Payload,
+}
+
+/// Specification for state-associated storage.
+///
+/// When a state has associated data (e.g., `Active(ConnectionData)`),
+/// this describes how to store and manage that data in the machine struct.
+pub struct StateStorageSpec {
+    pub state_name: Ident,
+    pub field: Ident,
+    pub ty: Type,
+}
+
+/// Information about a superstate.
+///
+/// Superstates are composite states that contain multiple leaf states.
+/// They enable hierarchical state machines.
+#[derive(Clone)]
+pub struct SuperstateInfo {
+    pub name: Ident,
+    pub descendants: Vec<Ident>,
+    pub initial: Ident,
+}
+
+/// Hierarchy tracking for superstates.
+///
+/// Maintains mappings for:
+/// - Which leaf states belong to which superstates
+/// - Which superstates are ancestors of which leaf states
+/// - Initial child states for superstates
+#[derive(Default)]
+pub struct Hierarchy {
+    pub superstates: Vec<SuperstateInfo>,
+    pub lookup: HashMap<String, Vec<Ident>>,
+    pub ancestors: HashMap<String, Vec<Ident>>,
+    pub initial_children: HashMap<String, Ident>,
+}
+
+impl Hierarchy {
+    /// Register a superstate with its descendants and initial state.
+    pub fn register_superstate(&mut self, name: Ident, descendants: Vec<Ident>, initial: Ident) {
+        let lookup_key = name.to_string();
+        self.lookup.insert(lookup_key.clone(), descendants.clone());
+        self.initial_children.insert(lookup_key, initial.clone());
+        self.superstates.push(SuperstateInfo {
+            name,
+            descendants,
+            initial,
+        });
+    }
+
+    /// Register a leaf state with its ancestor chain.
+    pub fn register_leaf(&mut self, leaf: &Ident, ancestors: &[Ident]) {
+        if ancestors.is_empty() {
+            return;
+        }
+        self.ancestors.insert(leaf.to_string(), ancestors.to_vec());
+    }
+
+    /// Expand a state identifier to its leaf states.
+    ///
+    /// If the identifier is a superstate, returns all its descendants.
+    /// If it's a leaf state, returns a single-element vector with that state.
+    /// If it's neither, returns an empty vector.
+    pub fn expand_state(&self, ident: &Ident, leaves: &[Ident]) -> Vec<Ident> {
+        if let Some(descendants) = self.lookup.get(&ident.to_string()) {
+            return descendants.clone();
+        }
+        if leaves.iter().any(|leaf| leaf == ident) {
+            return vec![ident.clone()];
+        }
+        Vec::new()
+    }
+
+    /// Check if an identifier refers to a superstate.
+    pub fn is_superstate(&self, ident: &Ident) -> bool {
+        self.lookup.contains_key(&ident.to_string())
+    }
+
+    /// Get the initial child state of a superstate.
+    ///
+    /// Returns the explicitly specified initial state if available,
+    /// otherwise returns the first descendant.
+    pub fn initial_child(&self, ident: &Ident) -> Option<Ident> {
+        self.initial_children
+            .get(&ident.to_string())
+            .cloned()
+            .or_else(|| {
+                self.lookup
+                    .get(&ident.to_string())
+                    .and_then(|desc| desc.first().cloned())
+            })
+    }
+
+    /// Resolve a target identifier to a concrete leaf state.
+    ///
+    /// If the identifier is a superstate, returns its initial child.
+    /// If it's a leaf state, returns it unchanged.
+    pub fn resolve_target(&self, ident: &Ident) -> Option<Ident> {
+        if self.is_superstate(ident) {
+            self.initial_child(ident)
+        } else {
+            Some(ident.clone())
+        }
+    }
+
+    /// Get all superstate names as identifiers, in declaration order and
+    /// with the spans they were declared with.
+    pub fn all_superstates(&self) -> Vec<Ident> {
+        self.superstates
+            .iter()
+            .map(|info| info.name.clone())
+            .collect()
+    }
+}
+
+/// Result of parsing the states section.
+///
+/// Contains the leaf states, hierarchy information, and storage specifications.
+pub struct ParsedStates {
+    pub leaves: Vec<Ident>,
+    /// `doc: "..."` strings attached to leaf states and superstates.
+    pub docs: Vec<(Ident, syn::LitStr)>,
+    /// `ignore: [...]` event lists attached to leaf states and superstates.
+    pub ignores: Vec<(Ident, Vec<Ident>)>,
+    /// `max_duration: ...` dwell limits attached to leaf states.
+    pub max_durations: Vec<(Ident, syn::Expr)>,
+    /// `requires: [...]` capabilities attached to leaf states and superstates.
+    pub requirements: Vec<(Ident, Vec<syn::Path>)>,
+    /// `= 1` codes attached to leaf states.
+    pub codes: Vec<(Ident, syn::LitInt)>,
+    pub hierarchy: Hierarchy,
+    pub storage: Vec<StateStorageSpec>,
+}
+
+/// The optional `{ doc: "...", ignore: [...], max_duration: 30s, requires: [Radio] }`
+/// block after a leaf state.
+#[derive(Default)]
+pub struct StateOptions {
+    pub doc: Option<syn::LitStr>,
+    pub ignore: Vec<Ident>,
+    pub max_duration: Option<syn::Expr>,
+    pub requires: Vec<syn::Path>,
+}
+
+/// Result of parsing a superstate block.
+///
+/// Contains the descendants and the initial state for that superstate.
+pub struct SuperstateParseResult {
+    pub descendants: Vec<Ident>,
+    pub initial: Ident,
+}
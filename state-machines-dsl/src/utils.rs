@@ -1,4 +1,4 @@
-//! Utility functions for code generation.
+//! Naming and type helpers for parsing and code generation.
 //!
 //! ## A Note on Naming Conventions
 //!
@@ -10,7 +10,8 @@
 //! And thus, this module was born, converting between PascalCase and snake_case
 //! so both the compiler and developers can live in harmony.
 
-use proc_macro2::Ident;
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::ToTokens;
 
 /// Convert PascalCase or camelCase to snake_case.
 ///
@@ -84,6 +85,84 @@ pub fn to_pascal_case(s: &str) -> String {
         .collect()
 }
 
+/// Collect the named lifetimes used in a type, in order of first appearance.
+///
+/// `'static` and `'_` never need declaring, so they're skipped. Lifetimes
+/// already in `out` aren't added twice.
+pub fn collect_lifetimes(ty: &syn::Type, out: &mut Vec<syn::Lifetime>) {
+    fn walk(tokens: TokenStream, out: &mut Vec<syn::Lifetime>) {
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                    if let Some(TokenTree::Ident(name)) = tokens.peek() {
+                        let lifetime = syn::Lifetime::new(&format!("'{}", name), punct.span());
+                        let builtin = lifetime.ident == "static" || lifetime.ident == "_";
+                        if !builtin && !out.contains(&lifetime) {
+                            out.push(lifetime);
+                        }
+                        tokens.next();
+                    }
+                }
+                TokenTree::Group(group) => walk(group.stream(), out),
+                _ => {}
+            }
+        }
+    }
+
+    walk(ty.to_token_stream(), out);
+}
+
+/// Name the elided lifetimes of a payload type, e.g. `&[u8]` becomes
+/// `&'payload [u8]`.
+///
+/// An elided lifetime can't be written in the event enum, so borrowed
+/// payloads get one shared lifetime instead, which `collect_lifetimes`
+/// then declares on each transition method, scoped to the call. `'_` is
+/// named too. Lifetimes inside `fn(&T)` types and `Fn(&T)` bounds belong
+/// to those, so they're left alone.
+pub fn name_elided_lifetimes(ty: &mut syn::Type) {
+    fn path(path: &mut syn::Path) {
+        for segment in &mut path.segments {
+            if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                for arg in &mut args.args {
+                    match arg {
+                        syn::GenericArgument::Lifetime(lifetime) => named(lifetime),
+                        syn::GenericArgument::Type(ty) => name_elided_lifetimes(ty),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    fn named(lifetime: &mut syn::Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = syn::Lifetime::new("'payload", lifetime.span());
+        }
+    }
+
+    match ty {
+        syn::Type::Reference(reference) => {
+            match &mut reference.lifetime {
+                Some(lifetime) => named(lifetime),
+                None => {
+                    let span = reference.and_token.span;
+                    reference.lifetime = Some(syn::Lifetime::new("'payload", span));
+                }
+            }
+            name_elided_lifetimes(&mut reference.elem);
+        }
+        syn::Type::Slice(slice) => name_elided_lifetimes(&mut slice.elem),
+        syn::Type::Array(array) => name_elided_lifetimes(&mut array.elem),
+        syn::Type::Ptr(ptr) => name_elided_lifetimes(&mut ptr.elem),
+        syn::Type::Paren(paren) => name_elided_lifetimes(&mut paren.elem),
+        syn::Type::Group(group) => name_elided_lifetimes(&mut group.elem),
+        syn::Type::Tuple(tuple) => tuple.elems.iter_mut().for_each(name_elided_lifetimes),
+        syn::Type::Path(type_path) => path(&mut type_path.path),
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,6 +192,18 @@ mod tests {
         assert_eq!(snake.to_string(), "enter_half_open");
     }
 
+    #[test]
+    fn test_collect_lifetimes() {
+        let mut lifetimes = Vec::new();
+        collect_lifetimes(&syn::parse_quote!(&'a [u8]), &mut lifetimes);
+        collect_lifetimes(
+            &syn::parse_quote!(Frame<'b, &'a str, &'static str, &'_ u8>),
+            &mut lifetimes,
+        );
+        let names: Vec<String> = lifetimes.iter().map(ToString::to_string).collect();
+        assert_eq!(names, ["'a", "'b"]);
+    }
+
     #[test]
     fn test_to_pascal_case() {
         assert_eq!(to_pascal_case("next"), "Next");
@@ -123,4 +214,23 @@ mod tests {
         assert_eq!(to_pascal_case("a"), "A");
         assert_eq!(to_pascal_case("abc"), "Abc");
     }
+
+    #[test]
+    fn test_name_elided_lifetimes() {
+        let named = |ty: &str| {
+            let mut ty: syn::Type = syn::parse_str(ty).unwrap();
+            name_elided_lifetimes(&mut ty);
+            ty.to_token_stream().to_string()
+        };
+        assert_eq!(named("&[u8]"), "& 'payload [u8]");
+        assert_eq!(named("&mut [u8]"), "& 'payload mut [u8]");
+        assert_eq!(
+            named("(&str, Cow<'_, [u8]>)"),
+            "(& 'payload str , Cow < 'payload , [u8] >)"
+        );
+        assert_eq!(named("&'p [u8]"), "& 'p [u8]");
+        assert_eq!(named("&'static str"), "& 'static str");
+        assert_eq!(named("fn(&u8) -> bool"), "fn (& u8) -> bool");
+        assert_eq!(named("Vec<u8>"), "Vec < u8 >");
+    }
 }
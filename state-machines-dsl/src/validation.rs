@@ -0,0 +1,1612 @@
+//! Validation logic for state machine definitions.
+//!
+//! This module ensures that the state machine definition is valid
+//! before we try to generate code. It checks for:
+//! - Valid initial state (must be a leaf, not a superstate)
+//! - No duplicate states
+//! - Event names follow snake_case convention
+//! - All events have at least one transition
+//! - All transitions have source and target states
+//! - Compensation hooks have something to compensate
+//! - `choose` transitions of an event agree on distinct, declared targets
+//! - `generics` only declares lifetimes and unreserved type parameters
+//! - Extra derives don't repeat the built-in ones
+//! - Event names and `alias` names are unique snake_case method names that
+//!   don't collide with generated methods
+//! - A method isn't referenced in roles that need different signatures, and
+//!   `split` callbacks run in states with data
+//! - `behavior: true` machines have a context to carry the `Behavior`, and
+//!   their guards and callbacks can be looked up by name alone
+//! - All referenced states exist
+//! - `requires:` capabilities are on a concrete context when a runtime
+//!   wrapper is generated
+//! - `unsafe` events don't have a runtime wrapper
+//! - Superstates that are used as targets have initial states
+//! - Superstate names are unique, and an event's sources don't overlap once
+//!   superstates are expanded to their leaf states
+//! - Ignored events exist and aren't also handled by the ignoring state
+//! - Transitions into a state with data can build it: `init:` only targets
+//!   such states, and `no_default_init` machines supply every one
+//! - A `by value` payload has a callback to take it, and nothing borrows it
+//!   afterwards
+//!
+//! Each error has a compile-fail case in `state-machines/tests/ui/` with its
+//! expected diagnostic; a new check should come with one.
+
+use crate::types::*;
+use crate::utils::to_snake_case;
+use quote::quote;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use syn::{Ident, Result};
+
+/// Methods generated on the machine struct, for every machine or with some
+/// option, that an event or alias method would collide with.
+const RESERVED_METHOD_NAMES: &[&str] = &["new", "id", "with_id", "state_name", "into_dynamic"];
+
+/// Check if a string is in snake_case format.
+///
+/// Valid snake_case:
+/// - All lowercase letters, digits, and underscores
+/// - No consecutive underscores
+/// - Doesn't start or end with underscore
+/// - At least one character
+fn is_snake_case(s: &str) -> bool {
+    if s.is_empty() {
+        return false;
+    }
+
+    // Must not start or end with underscore
+    if s.starts_with('_') || s.ends_with('_') {
+        return false;
+    }
+
+    let mut prev_was_underscore = false;
+    for ch in s.chars() {
+        // Only allow lowercase, digits, and underscores
+        if !ch.is_lowercase() && !ch.is_ascii_digit() && ch != '_' {
+            return false;
+        }
+
+        // No consecutive underscores
+        if ch == '_' {
+            if prev_was_underscore {
+                return false;
+            }
+            prev_was_underscore = true;
+        } else {
+            prev_was_underscore = false;
+        }
+    }
+
+    true
+}
+
+impl StateMachine {
+    /// Validate the state machine definition.
+    ///
+    /// This performs comprehensive validation to catch errors early
+    /// and provide helpful error messages. It's called before code
+    /// generation begins.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `syn::Error` if any validation check fails.
+    /// The error includes a span pointing to the problematic element
+    /// and a descriptive message.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_superstate_names()?;
+
+        // Validate initial state
+
+        // The initial state must be a leaf state, not a superstate
+        // This prevents ambiguity about which child state to start in
+        if self.hierarchy.is_superstate(&self.initial) {
+            return Err(syn::Error::new(
+                self.initial.span(),
+                "`initial` must reference a leaf state",
+            ));
+        }
+
+        // The initial state must be declared in the states list
+        if !self.states.iter().any(|state| state == &self.initial) {
+            return Err(syn::Error::new(
+                self.initial.span(),
+                "`initial` must be a member of `states`",
+            ));
+        }
+
+        // `new()` has no data to start the initial state with
+        if let Some(key) = &self.no_default_init
+            && self.state_storage_for(&self.initial).is_some()
+        {
+            return Err(syn::Error::new(
+                key.span(),
+                format!(
+                    "with `no_default_init: true`, the initial state `{}` can't carry data",
+                    self.initial
+                ),
+            ));
+        }
+
+        // The wasm-bindgen class exposes a synchronous `handle()`
+        if self.wasm_mode && self.async_mode {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`wasm: true` is not supported for async machines",
+            ));
+        }
+
+        // These are all built on the runtime machine
+        if let Some((mode, MachineMode::Typestate)) = &self.mode {
+            let needs_runtime = if self.wasm_mode {
+                Some("wasm: true")
+            } else if self.http_mode {
+                Some("http: true")
+            } else if self.mailbox.is_some() {
+                Some("mailbox")
+            } else {
+                None
+            };
+            if let Some(key) = needs_runtime {
+                return Err(syn::Error::new(
+                    mode.span(),
+                    format!(
+                        "`mode: typestate` can't be combined with `{key}`, which needs the runtime machine"
+                    ),
+                ));
+            }
+        }
+
+        // Sync calls can't be raced against a timer
+        if let Some(timeout) = &self.callback_timeout
+            && !self.async_mode
+        {
+            return Err(syn::Error::new(
+                timeout.key.span(),
+                "`callback_timeout` needs `async: true`",
+            ));
+        }
+
+        // The cache holds one value per guard, whatever its arguments
+        let cached = self.cached_guard_names();
+        for event in &self.events {
+            let exprs = event.guards.iter().chain(&event.unless).chain(
+                event
+                    .transitions
+                    .iter()
+                    .flat_map(|t| t.guards.iter().chain(&t.unless)),
+            );
+            for (guard, args) in exprs.flat_map(GuardExpr::calls) {
+                if !args.is_empty() && cached.contains(&guard) {
+                    return Err(syn::Error::new(
+                        guard.span(),
+                        format!("`{guard}` takes arguments, so it can't be cached"),
+                    ));
+                }
+            }
+        }
+
+        // A transform rewrites the payload, so there has to be one
+        if let Some((event, transform)) = self.events.iter().find_map(|event| {
+            let transform = event.transform.as_ref()?;
+            event.payload.is_none().then_some((event, transform))
+        }) {
+            return Err(syn::Error::new(
+                transform.span(),
+                format!("`transform` needs a `payload` on `{}`", event.name),
+            ));
+        }
+
+        self.validate_payload_ownership()?;
+
+        for event in &self.events {
+            let Some(retry) = &event.retry else {
+                continue;
+            };
+            if !self.async_mode {
+                return Err(syn::Error::new(
+                    retry.key.span(),
+                    "`retry` needs `async: true`",
+                ));
+            }
+            if retry.attempts.base10_parse::<u32>()? == 0 {
+                return Err(syn::Error::new(
+                    retry.attempts.span(),
+                    "retry `attempts` must be at least 1",
+                ));
+            }
+        }
+
+        // Dwell limits are only checked by the dynamic wrapper's watchdog
+        if let Some((state, _)) = self.state_max_durations.first()
+            && !self.watchdog
+        {
+            return Err(syn::Error::new(
+                state.span(),
+                "`max_duration` needs `watchdog: true`",
+            ));
+        }
+        if self.watchdog && !self.dynamic_mode {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`watchdog: true` needs `dynamic: true`",
+            ));
+        }
+        if let Some(clock) = &self.clock
+            && !self.watchdog
+            && !self.transition_records
+        {
+            return Err(syn::Error::new_spanned(
+                clock,
+                "`clock` needs `watchdog: true` or `transition_records: true`",
+            ));
+        }
+        // The runtime wrapper can enter every state, so over a generic
+        // context it would need every capability up front
+        let runtime = match &self.mode {
+            Some((_, mode)) => *mode != MachineMode::Typestate,
+            None => self.dynamic_mode || self.wasm_mode || self.http_mode || self.mailbox.is_some(),
+        };
+        if let Some((state, _)) = self.state_requirements.first()
+            && self.context.is_none()
+            && runtime
+        {
+            return Err(syn::Error::new(
+                state.span(),
+                "`requires` with a runtime wrapper needs a concrete `context:` type, since the wrapper can enter every state",
+            ));
+        }
+        // `handle()` is safe, so it can't fire an `unsafe` event
+        if let Some(unsafety) = self.events.iter().find_map(|event| event.unsafety)
+            && runtime
+        {
+            return Err(syn::Error::new(
+                unsafety.span,
+                "`unsafe` events can't have a runtime wrapper, which would fire them from safe code",
+            ));
+        }
+        if let (Some(history), false) = (&self.history, self.dynamic_mode) {
+            return Err(syn::Error::new(
+                history.span(),
+                "`history: N` needs `dynamic: true`",
+            ));
+        }
+        if self.stats && !self.dynamic_mode {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`stats: true` needs `dynamic: true`",
+            ));
+        }
+
+        // `DynamicMachine` looks states up by name and takes owned events
+        if self.persistent {
+            let reason = if !self.dynamic_mode {
+                Some("`persistent: true` needs `dynamic: true`")
+            } else if self.data_states {
+                Some("`persistent: true` can't be combined with `data_states: true`")
+            } else if !self.method_lifetimes(None).is_empty() {
+                Some("`persistent: true` needs event payloads without borrowed lifetimes")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(syn::Error::new(self.name.span(), reason));
+            }
+        }
+
+        if let Some(on_error) = &self.on_error {
+            self.validate_on_error(on_error)?;
+        }
+
+        // `emit:` notifications go to the machine-level `sink:`
+        let first_emit = self
+            .events
+            .iter()
+            .flat_map(|event| &event.transitions)
+            .find_map(|transition| transition.emit.as_ref());
+        match (first_emit, &self.sink) {
+            (Some(emit), None) => {
+                return Err(syn::Error::new(
+                    emit.span(),
+                    "`emit` needs a machine-level `sink: method` to send notifications to",
+                ));
+            }
+            (None, Some(sink)) => {
+                return Err(syn::Error::new(
+                    sink.span(),
+                    "`sink` is unused: no transition has `emit`",
+                ));
+            }
+            _ => {}
+        }
+
+        self.validate_generics()?;
+
+        // A zero-capacity mailbox would reject every event, and the ring's
+        // wrapping positions only map onto power-of-two slot counts
+        if let Some(mailbox) = &self.mailbox {
+            let capacity = mailbox.capacity.base10_parse::<usize>()?;
+            if capacity == 0 {
+                return Err(syn::Error::new(
+                    mailbox.capacity.span(),
+                    "mailbox `capacity` must be at least 1",
+                ));
+            }
+            if !capacity.is_power_of_two() {
+                return Err(syn::Error::new(
+                    mailbox.capacity.span(),
+                    format!(
+                        "mailbox `capacity` must be a power of two, e.g. {}",
+                        capacity.next_power_of_two()
+                    ),
+                ));
+            }
+        }
+
+        // `AtomicState` stores the state discriminant in a `u8`
+        if self.atomic_state && self.states.len() > usize::from(u8::MAX) + 1 {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`atomic_state: true` supports at most 256 states",
+            ));
+        }
+
+        // Data-carrying variants have no `u8` discriminant to store or to
+        // look up in the transition table
+        if self.data_states && (self.atomic_state || self.compact_codegen) {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`data_states: true` can't be combined with `atomic_state` or `compact_codegen`",
+            ));
+        }
+
+        // Deriving a trait the generated enums already implement would
+        // produce a confusing "conflicting implementations" error
+        let state_builtins: &[&str] = if self.data_states {
+            &["Debug", "Clone"]
+        } else {
+            &["Debug", "Clone", "Copy", "PartialEq", "Eq", "Hash"]
+        };
+        Self::validate_extra_derives(&self.state_derives, state_builtins, "state enum")?;
+        Self::validate_extra_derives(&self.event_derives, &["Debug"], "event enum")?;
+        self.validate_machine_derives()?;
+
+        // Validate states
+
+        // Check for duplicate state names
+        // Using a HashSet to track seen names
+        let mut seen = HashSet::new();
+        for state in &self.states {
+            if !seen.insert(state.to_string()) {
+                return Err(syn::Error::new(state.span(), "duplicate state"));
+            }
+        }
+
+        // Validate events and transitions
+
+        // Each event becomes a method, so names must be unique
+        let mut seen_events = HashSet::new();
+        for event in &self.events {
+            if !seen_events.insert(event.name.to_string()) {
+                return Err(syn::Error::new(
+                    event.name.span(),
+                    format!(
+                        "duplicate event `{0}`; move its transitions into the first `{0}`, or rename it",
+                        event.name
+                    ),
+                ));
+            }
+        }
+        let event_names = seen_events.clone();
+
+        // Aliases become methods too, alongside the event names
+        for alias in self.events.iter().flat_map(|event| &event.aliases) {
+            let name = alias.to_string();
+            if !is_snake_case(&name) {
+                return Err(syn::Error::new(
+                    alias.span(),
+                    format!(
+                        "event aliases must be in snake_case (e.g., '{}' instead of '{}')",
+                        to_snake_case(&name),
+                        name
+                    ),
+                ));
+            }
+            if !seen_events.insert(name) {
+                return Err(syn::Error::new(
+                    alias.span(),
+                    format!("`{}` is already an event or alias name", alias),
+                ));
+            }
+        }
+        for name in self
+            .events
+            .iter()
+            .flat_map(|event| std::iter::once(&event.name).chain(&event.aliases))
+        {
+            self.validate_unreserved_method(name, &event_names)?;
+        }
+
+        self.validate_method_signatures()?;
+        if let Some(key) = &self.behavior {
+            self.validate_behavior(key)?;
+        }
+
+        for event in &self.events {
+            // Validate event naming convention
+            // Events must be in snake_case to generate proper method names
+            let event_name = event.name.to_string();
+            if !is_snake_case(&event_name) {
+                let suggested = to_snake_case(&event_name);
+                return Err(syn::Error::new(
+                    event.name.span(),
+                    format!(
+                        "event names must be in snake_case (e.g., '{}' instead of '{}')",
+                        suggested, event_name
+                    ),
+                ));
+            }
+
+            // Each event must have at least one transition
+            // An event with no transitions would be useless
+            if event.transitions.is_empty() {
+                return Err(syn::Error::new(
+                    event.name.span(),
+                    "event must declare at least one transition",
+                ));
+            }
+
+            // An event's `choose` transitions share one generated Choice enum
+            let mut choices = event.transitions.iter().filter_map(|t| t.choice.as_ref());
+            if let Some(first) = choices.next() {
+                for choice in choices {
+                    if choice.targets != first.targets {
+                        return Err(syn::Error::new(
+                            choice.chooser.span(),
+                            "all `choose` transitions of an event must declare the same targets",
+                        ));
+                    }
+                }
+            }
+
+            // Which source claimed each leaf state, across all transitions
+            let mut claimed: HashMap<String, &Ident> = HashMap::new();
+            for transition in &event.transitions {
+                // Each transition must have at least one source state
+                if transition.sources.is_empty() {
+                    return Err(syn::Error::new(
+                        transition.target.span(),
+                        "transition must declare at least one source state",
+                    ));
+                }
+
+                // Compensation only undoes work done by `before` callbacks or the action
+                let has_work = !event.before.is_empty()
+                    || !transition.before.is_empty()
+                    || transition.action.is_some();
+                if !has_work && !transition.compensate.is_empty() {
+                    return Err(syn::Error::new(
+                        transition.compensate[0].span(),
+                        "`compensate` requires `before` callbacks or an `action` to undo",
+                    ));
+                }
+
+                // Validate and resolve the target state(s)
+                let targets = match &transition.choice {
+                    Some(choice) => choice.targets.as_slice(),
+                    None => std::slice::from_ref(&transition.target),
+                };
+                let mut resolved_targets = Vec::new();
+                for target in targets {
+                    // If the target is a superstate, resolve it to its initial child
+                    // This is allowed, and we'll use the superstate's initial state
+                    let resolved_target = if self.hierarchy.is_superstate(target) {
+                        match self.hierarchy.resolve_target(target) {
+                            Some(resolved) => resolved,
+                            None => {
+                                // Superstate has no initial state specified
+                                return Err(syn::Error::new(
+                                    target.span(),
+                                    format!(
+                                        "superstate target `{target}` must declare an initial child"
+                                    ),
+                                ));
+                            }
+                        }
+                    } else {
+                        target.clone()
+                    };
+
+                    // The resolved target must be a declared leaf state
+                    if !self.states.iter().any(|state| state == &resolved_target) {
+                        let message = if resolved_target == *target {
+                            "target state not declared in `states`".to_string()
+                        } else {
+                            format!(
+                                "superstate target `{target}` enters `{resolved_target}`, which is not declared in `states`"
+                            )
+                        };
+                        return Err(syn::Error::new(target.span(), message));
+                    }
+
+                    // Each choice must enter a different state
+                    if resolved_targets.contains(&resolved_target) {
+                        return Err(syn::Error::new(target.span(), "duplicate `choose` target"));
+                    }
+                    resolved_targets.push(resolved_target);
+                }
+                self.validate_data_init(event, transition, &resolved_targets)?;
+
+                // Validate source states, expanding superstates to their leaves
+
+                for source in &transition.sources {
+                    // Check if this is a declared leaf state
+                    let is_leaf = self.states.iter().any(|state| state == source);
+                    // Check if this is a declared superstate
+                    let is_super = self.hierarchy.is_superstate(source);
+
+                    // Source must be either a leaf or a superstate
+                    if !(is_leaf || is_super) {
+                        return Err(syn::Error::new(
+                            source.span(),
+                            "source state not declared in `states` or superstates",
+                        ));
+                    }
+
+                    // If it's a superstate, verify it has descendants
+                    // This catches edge cases where a superstate was declared
+                    // but has no children (which should be caught earlier,
+                    // but we check again for robustness)
+                    let leaves = self.hierarchy.expand_state(source, &self.states);
+                    if leaves.is_empty() {
+                        return Err(syn::Error::new(
+                            source.span(),
+                            format!("superstate `{source}` does not resolve to any leaf states"),
+                        ));
+                    }
+
+                    // Each leaf gets one method per event, so two sources of
+                    // the same event can't cover the same leaf
+                    for leaf in leaves {
+                        let previous = match claimed.entry(leaf.to_string()) {
+                            Entry::Vacant(entry) => {
+                                entry.insert(source);
+                                continue;
+                            }
+                            Entry::Occupied(entry) => *entry.get(),
+                        };
+                        let through = if *previous == leaf {
+                            String::new()
+                        } else {
+                            format!(" through superstate `{previous}`")
+                        };
+                        let message = if *source == leaf {
+                            format!("`{leaf}` is already a source of `{event_name}`{through}")
+                        } else {
+                            format!(
+                                "superstate `{source}` includes `{leaf}`, which is already a source of `{event_name}`{through}"
+                            )
+                        };
+                        return Err(syn::Error::new(source.span(), message));
+                    }
+                }
+            }
+        }
+
+        self.validate_ignored_events()?;
+
+        let states: Vec<_> = self
+            .states
+            .iter()
+            .map(|state| (state, self.state_code(state)))
+            .collect();
+        self.validate_codes("state", &states)?;
+        let events: Vec<_> = self
+            .events
+            .iter()
+            .map(|event| (&event.name, event.code.as_ref()))
+            .collect();
+        self.validate_codes("event", &events)?;
+        self.validate_callback_filters()?;
+        self.validate_global_callbacks()?;
+
+        // All validation passed!
+        Ok(())
+    }
+
+    /// Transitions into a state with data build it with `init:`, move the
+    /// payload in (`no_default_init` only), or start it at `Default`.
+    fn validate_data_init(
+        &self,
+        event: &Event,
+        transition: &Transition,
+        targets: &[Ident],
+    ) -> Result<()> {
+        let event_name = &event.name;
+        for target in targets {
+            match self.data_init(target, transition.init.as_ref(), event.payload.as_ref()) {
+                DataInit::None => {
+                    if let Some(init) = &transition.init {
+                        return Err(syn::Error::new(
+                            init.span(),
+                            format!("`{target}` has no data for `init` to build"),
+                        ));
+                    }
+                }
+                DataInit::Default(ty) if self.no_default_init.is_some() => {
+                    return Err(syn::Error::new(
+                        target.span(),
+                        format!(
+                            "`{target}` has data and `no_default_init` is set, so `{event_name}` needs `init: method` or a `{}` payload",
+                            quote!(#ty)
+                        ),
+                    ));
+                }
+                DataInit::Payload => {
+                    // Once moved into the data, the payload can't be lent to
+                    // the callbacks that run after the transition
+                    let borrowed_after = !event.after.is_empty()
+                        || !transition.after.is_empty()
+                        || event
+                            .around
+                            .iter()
+                            .chain(&transition.around)
+                            .any(|callback| self.around_takes_payload(callback));
+                    if borrowed_after {
+                        return Err(syn::Error::new(
+                            target.span(),
+                            format!(
+                                "`{event_name}` moves its payload into `{target}`'s data, so its `after` and payload `around` callbacks can't borrow it; use `init:` instead"
+                            ),
+                        ));
+                    }
+                }
+                DataInit::Default(_) | DataInit::Init(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// A `by value` payload moves into the last `after` entry, or `init:`
+    /// without any, on every edge of the event; payload `around` callbacks
+    /// run after both, so they can't borrow it any more.
+    fn validate_payload_ownership(&self) -> Result<()> {
+        let edges = self
+            .states
+            .iter()
+            .filter_map(|state| self.transition_graph.outgoing(state))
+            .flatten()
+            .filter(|edge| edge.payload_by_value);
+        for edge in edges {
+            let event = &edge.event;
+            if edge.payload_owner().is_none() {
+                return Err(syn::Error::new(
+                    event.span(),
+                    format!(
+                        "`{event}` takes its payload `by value`, but its transition to `{}` has no `after` callback or `init:` to take it",
+                        edge.target
+                    ),
+                ));
+            }
+            if let Some(around) = edge
+                .around
+                .iter()
+                .find(|callback| self.around_takes_payload(callback))
+            {
+                return Err(syn::Error::new(
+                    around.span(),
+                    format!(
+                        "`{event}` moves its payload into its last `after` callback or `init:`, so around callback `{around}` can't borrow it afterwards; use `by ref`"
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Superstate names share the state namespace: they're matched against
+    /// leaf names in `from:`/`to:` lists and filters, so they must be unique.
+    fn validate_superstate_names(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for superstate in &self.hierarchy.superstates {
+            let name = &superstate.name;
+            if self.states.iter().any(|state| state == name) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("superstate `{name}` has the same name as a state"),
+                ));
+            }
+            if !seen.insert(name.to_string()) {
+                return Err(syn::Error::new(name.span(), "duplicate superstate"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the `ignore: [...]` lists on states.
+    ///
+    /// Each entry must name a declared event, and a state can't both ignore
+    /// an event and transition on it.
+    fn validate_ignored_events(&self) -> Result<()> {
+        for (state, events) in &self.state_ignores {
+            for event in events {
+                if !self.events.iter().any(|declared| &declared.name == event) {
+                    return Err(syn::Error::new(
+                        event.span(),
+                        "ignored event not declared in `events`",
+                    ));
+                }
+
+                let leaves = self.hierarchy.expand_state(state, &self.states);
+                let handled = leaves
+                    .iter()
+                    .find(|leaf| self.edge_for(leaf, event).is_some());
+                if let Some(leaf) = handled {
+                    return Err(syn::Error::new(
+                        event.span(),
+                        format!(
+                            "`{}` has a transition on `{}`, so it can't ignore it",
+                            leaf, event
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the `Closed = 1` codes of states or events.
+    ///
+    /// `as_code()` is generated on the dynamic enums, so once one entry has a
+    /// code every entry needs a distinct one.
+    fn validate_codes(&self, kind: &str, entries: &[(&Ident, Option<&syn::LitInt>)]) -> Result<()> {
+        let Some(first) = entries.iter().find_map(|(_, code)| *code) else {
+            return Ok(());
+        };
+        if !self.dynamic_mode {
+            return Err(syn::Error::new(
+                first.span(),
+                format!("{kind} codes need `dynamic: true`"),
+            ));
+        }
+
+        let mut used: Vec<(u32, &Ident)> = Vec::new();
+        for (name, code) in entries {
+            let Some(code) = code else {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("`{name}` needs a code, since other {kind}s have one"),
+                ));
+            };
+            let value = code.base10_parse::<u32>()?;
+            if let Some((_, other)) = used.iter().find(|(known, _)| *known == value) {
+                return Err(syn::Error::new(
+                    code.span(),
+                    format!("code {value} is already used by `{other}`"),
+                ));
+            }
+            used.push((value, name));
+        }
+        Ok(())
+    }
+
+    /// Check the `from`/`to` filters of event and transition callbacks.
+    ///
+    /// Filters must name declared states, can't depend on the target of a
+    /// `choose` transition, and must match at least one edge in their scope.
+    fn validate_callback_filters(&self) -> Result<()> {
+        for event in &self.events {
+            let edges = |transitions: &[&Transition]| -> Vec<(syn::Ident, syn::Ident)> {
+                let mut edges = Vec::new();
+                for transition in transitions {
+                    let target = self.resolve_target(&transition.target);
+                    for source in &transition.sources {
+                        for leaf in self.hierarchy.expand_state(source, &self.states) {
+                            edges.push((leaf, target.clone()));
+                        }
+                    }
+                }
+                edges
+            };
+
+            let event_scope: Vec<&Transition> = event.transitions.iter().collect();
+            let scopes = std::iter::once((
+                event
+                    .before
+                    .iter()
+                    .chain(&event.after)
+                    .chain(&event.after_commit),
+                event_scope,
+            ))
+            .chain(event.transitions.iter().map(|transition| {
+                (
+                    transition
+                        .before
+                        .iter()
+                        .chain(&transition.after)
+                        .chain(&transition.after_commit),
+                    vec![transition],
+                )
+            }));
+
+            for (callbacks, scope) in scopes {
+                let scope_edges = edges(&scope);
+                for callback in callbacks {
+                    for state in callback.from.iter().chain(&callback.to) {
+                        let declared = self.states.iter().any(|leaf| leaf == state)
+                            || self.hierarchy.is_superstate(state);
+                        if !declared {
+                            return Err(syn::Error::new(
+                                state.span(),
+                                "callback filter state not declared in `states` or superstates",
+                            ));
+                        }
+                    }
+
+                    if let Some(state) = callback.to.first()
+                        && scope.iter().any(|transition| transition.choice.is_some())
+                    {
+                        return Err(syn::Error::new(
+                            state.span(),
+                            "`to` filters can't be used with `choose` transitions",
+                        ));
+                    }
+
+                    let matched = scope_edges.iter().any(|(source, target)| {
+                        callback.applies(&self.hierarchy, &self.states, source, target)
+                    });
+                    if !matched {
+                        return Err(syn::Error::new(
+                            callback.name.span(),
+                            format!(
+                                "the filter on `{}` matches none of the transitions of `{}`",
+                                callback.name, event.name
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the `callbacks: { ... }` block.
+    ///
+    /// Filters must name declared states and events, and each callback must
+    /// run on at least one transition.
+    fn validate_global_callbacks(&self) -> Result<()> {
+        let globals = self
+            .callbacks
+            .before_transition
+            .iter()
+            .chain(&self.callbacks.after_transition);
+        for global in globals {
+            let callback = &global.callback;
+            for state in callback.from.iter().chain(&callback.to) {
+                let declared = self.states.iter().any(|leaf| leaf == state)
+                    || self.hierarchy.is_superstate(state);
+                if !declared {
+                    return Err(syn::Error::new(
+                        state.span(),
+                        "callback filter state not declared in `states` or superstates",
+                    ));
+                }
+            }
+            for event in &global.on {
+                if !self.events.iter().any(|declared| &declared.name == event) {
+                    return Err(syn::Error::new(
+                        event.span(),
+                        "callback filter event not declared in `events`",
+                    ));
+                }
+            }
+
+            let used = self.transition_graph.edges.values().flatten().any(|edge| {
+                edge.before_transition
+                    .iter()
+                    .chain(&edge.after_transition)
+                    .any(|name| name == &callback.name)
+            });
+            if !used {
+                return Err(syn::Error::new(
+                    callback.name.span(),
+                    format!("the filter on `{}` matches no transitions", callback.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the `on_error` block: something to do, and a recovery event
+    /// `handle()` can fire on its own.
+    fn validate_on_error(&self, on_error: &OnError) -> Result<()> {
+        if on_error.handler.is_none() && on_error.recover.is_none() {
+            return Err(syn::Error::new(
+                on_error.key.span(),
+                "`on_error` needs a `handler`, a `recover` event, or both",
+            ));
+        }
+
+        if let Some(after) = &on_error.after {
+            if on_error.recover.is_none() {
+                return Err(syn::Error::new(
+                    after.span(),
+                    "`after` only applies with a `recover` event",
+                ));
+            }
+            if after.base10_parse::<u32>()? == 0 {
+                return Err(syn::Error::new(after.span(), "`after` must be at least 1"));
+            }
+        }
+
+        let Some(recover) = &on_error.recover else {
+            return Ok(());
+        };
+        if !self.dynamic_mode {
+            return Err(syn::Error::new(
+                recover.span(),
+                "`recover` needs `dynamic: true`",
+            ));
+        }
+        match self.events.iter().find(|event| &event.name == recover) {
+            None => Err(syn::Error::new(
+                recover.span(),
+                format!("`recover` names unknown event `{}`", recover),
+            )),
+            Some(event) if event.payload.is_some() => Err(syn::Error::new(
+                recover.span(),
+                format!("recovery event `{}` can't take a payload", recover),
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Check a `behavior: true` machine.
+    ///
+    /// Its guards and callbacks are generated, one method per name, to call
+    /// `ctx.behavior` with `ctx.ctx`, so the context must be a
+    /// `WithBehavior`. The `Behavior` only gets a name and the context:
+    /// guards can't take arguments, and a name needs one shape wherever
+    /// it's used. A guard that isn't registered fails closed, so it can't
+    /// be required in one place and negated in another.
+    fn validate_behavior(&self, key: &Ident) -> Result<()> {
+        if self.context.is_none() {
+            return Err(syn::Error::new(
+                key.span(),
+                "`behavior: true` needs `context: WithBehavior<...>`, which carries the `Behavior`",
+            ));
+        }
+        if self.async_mode {
+            return Err(syn::Error::new(
+                key.span(),
+                "`behavior: true` is not supported for async machines",
+            ));
+        }
+
+        for event in &self.events {
+            let exprs = event.guards.iter().chain(&event.unless).chain(
+                event
+                    .transitions
+                    .iter()
+                    .flat_map(|t| t.guards.iter().chain(&t.unless)),
+            );
+            for (guard, args) in exprs.flat_map(GuardExpr::calls) {
+                if !args.is_empty() {
+                    return Err(syn::Error::new(
+                        guard.span(),
+                        format!(
+                            "with `behavior: true`, guards are looked up by name, so `{}` can't take arguments",
+                            guard
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let split = self.events.iter().find_map(|event| {
+            event
+                .before
+                .iter()
+                .chain(&event.after)
+                .chain(
+                    event
+                        .transitions
+                        .iter()
+                        .flat_map(|t| t.before.iter().chain(&t.after)),
+                )
+                .find(|callback| callback.split)
+        });
+        if let Some(callback) = split {
+            return Err(syn::Error::new(
+                callback.name.span(),
+                "with `behavior: true`, callbacks are looked up by name, so they can't be `split`",
+            ));
+        }
+
+        let mut seen: HashMap<String, (BehaviorRole, bool)> = HashMap::new();
+        for (name, role, payload) in self.behavior_calls() {
+            let (other_role, other_payload) =
+                *seen.entry(name.to_string()).or_insert((role, payload));
+            let guards = matches!(
+                (other_role, role),
+                (BehaviorRole::Guard { .. }, BehaviorRole::Guard { .. })
+            );
+            if guards && other_role != role {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!(
+                        "with `behavior: true`, a guard that isn't registered fails wherever it's used, so `{}` can't be both required and negated (in `unless` or under `!`)",
+                        name
+                    ),
+                ));
+            }
+            if other_role != role {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!(
+                        "with `behavior: true`, `{}` is generated once, but it's used as {} and as {}",
+                        name,
+                        other_role.describe(),
+                        role.describe()
+                    ),
+                ));
+            }
+            if other_payload != payload {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!(
+                        "with `behavior: true`, `{}` is generated once, but it's used on events with and without a payload",
+                        name
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject an event or alias method named like a generated one, which
+    /// would otherwise fail as a duplicate definition inside the expansion.
+    fn validate_unreserved_method(&self, name: &Ident, events: &HashSet<String>) -> Result<()> {
+        let text = name.to_string();
+        let taken_by = if RESERVED_METHOD_NAMES.contains(&text.as_str()) {
+            "a generated method".to_string()
+        } else if let Some(event) = text.strip_prefix("can_")
+            && events.contains(event)
+        {
+            format!("the generated `{}` guard probe", event)
+        } else {
+            return Ok(());
+        };
+        Err(syn::Error::new(
+            name.span(),
+            format!(
+                "`{}` is reserved for {}; rename it, e.g. `{}_{}`",
+                text,
+                taken_by,
+                text,
+                to_snake_case(&self.name.to_string())
+            ),
+        ))
+    }
+
+    /// The signature of a `split: true` callback running in `state`, or an
+    /// error if the state has no data to pass it.
+    fn split_signature(
+        &self,
+        callback: &Ident,
+        state: &Ident,
+        ctx_ty: &str,
+        payload_arg: &str,
+    ) -> Result<String> {
+        let Some(spec) = self.state_storage_for(state) else {
+            return Err(syn::Error::new(
+                callback.span(),
+                format!(
+                    "`split` callback `{}` runs in `{}`, which has no state data to pass it",
+                    callback, state
+                ),
+            ));
+        };
+        let data_ty = &spec.ty;
+        Ok(format!(
+            "fn {}(data: &mut {}, ctx: &{}{})",
+            callback,
+            quote!(#data_ty),
+            ctx_ty,
+            payload_arg
+        ))
+    }
+
+    /// Check the `generics: <...>` parameters.
+    ///
+    /// Only type and lifetime parameters are supported: const parameters
+    /// would need their own marker types. `S` (and `C` for a generic context)
+    /// are already taken by the generated struct.
+    fn validate_generics(&self) -> Result<()> {
+        for param in &self.generics.params {
+            let param = match param {
+                syn::GenericParam::Type(param) => param,
+                syn::GenericParam::Lifetime(_) => continue,
+                syn::GenericParam::Const(_) => {
+                    return Err(syn::Error::new_spanned(
+                        param,
+                        "`generics` only supports type and lifetime parameters",
+                    ));
+                }
+            };
+            if param.ident == "S" || (self.context.is_none() && param.ident == "C") {
+                return Err(syn::Error::new(
+                    param.ident.span(),
+                    format!(
+                        "`{}` is reserved for the generated machine's own parameters",
+                        param.ident
+                    ),
+                ));
+            }
+        }
+
+        // JavaScript classes can't be generic or borrow
+        if self.wasm_mode && !self.generics.params.is_empty() {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`wasm: true` is not supported for machines with `generics`",
+            ));
+        }
+        if self.wasm_mode
+            && (!self.machine_lifetimes().is_empty() || !self.event_lifetimes().is_empty())
+        {
+            return Err(syn::Error::new(
+                self.name.span(),
+                "`wasm: true` is not supported for borrowed context or payload types",
+            ));
+        }
+
+        // The router serves one concrete machine type, looked up by state name
+        if self.http_mode {
+            let reason = if !self.generics.params.is_empty() {
+                Some("`http: true` is not supported for machines with `generics`")
+            } else if self.data_states {
+                Some("`http: true` can't be combined with `data_states: true`")
+            } else if !self.machine_lifetimes().is_empty() || !self.event_lifetimes().is_empty() {
+                Some("`http: true` is not supported for borrowed context or payload types")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(syn::Error::new(self.name.span(), reason));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reject `state_derives`/`event_derives` entries the generated enum already derives.
+    fn validate_extra_derives(derives: &[syn::Path], builtin: &[&str], kind: &str) -> Result<()> {
+        for derive in derives {
+            if let Some(last) = derive.segments.last()
+                && builtin.iter().any(|name| last.ident == name)
+            {
+                return Err(syn::Error::new_spanned(
+                    derive,
+                    format!(
+                        "`{}` is already derived for the generated {}",
+                        last.ident, kind
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the `#[derive(...)]` attributes in `machine_attrs` against the
+    /// impls the machine struct always gets.
+    fn validate_machine_derives(&self) -> Result<()> {
+        for attr in &self.machine_attrs {
+            if !attr.path().is_ident("derive") {
+                continue;
+            }
+            let derives = attr.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+            )?;
+            let derives: Vec<_> = derives.into_iter().collect();
+            Self::validate_extra_derives(&derives, &["Debug", "Clone"], "machine struct")?;
+        }
+        Ok(())
+    }
+
+    /// Check that every guard/callback method is referenced with one signature.
+    ///
+    /// Generated code calls guards as `fn(&self, ctx: &C[, payload: &P]) -> bool`
+    /// and callbacks as `fn(&self[, payload: &P])`, so the same name used as
+    /// both (or by events with different payloads) can't compile. Catching it
+    /// here reports the expected signature at the offending identifier rather
+    /// than a type error inside generated code.
+    fn validate_method_signatures(&self) -> Result<()> {
+        let ctx_ty = match &self.context {
+            Some(ty) => quote!(#ty).to_string(),
+            None => "C".to_string(),
+        };
+
+        // method name -> (role, expected signature)
+        let mut seen: HashMap<String, (&'static str, String)> = HashMap::new();
+        let mut check = |method: &syn::Ident, role: &'static str, signature: String| {
+            let name = method.to_string();
+            match seen.get(&name) {
+                Some((other_role, other_signature)) if *other_signature != signature => {
+                    Err(syn::Error::new(
+                        method.span(),
+                        format!(
+                            "conflicting signatures for `{}`: used as {} here, expected method `{}`, \
+                             but used as {} elsewhere, expecting `{}`",
+                            name, role, signature, other_role, other_signature
+                        ),
+                    ))
+                }
+                Some(_) => Ok(()),
+                None => {
+                    seen.insert(name, (role, signature));
+                    Ok(())
+                }
+            }
+        };
+
+        for event in &self.events {
+            let payload_arg = match &event.payload {
+                Some(ty) => format!(", payload: &{}", quote!(#ty)),
+                None => String::new(),
+            };
+            let guard_sig = |m: &syn::Ident, args: &[syn::Expr]| {
+                let args: String = args.iter().map(|_| ", _").collect();
+                format!(
+                    "fn {}(&self, ctx: &{}{}{}) -> bool",
+                    m, ctx_ty, payload_arg, args
+                )
+            };
+            let callback_sig = |m: &syn::Ident| format!("fn {}(&self{})", m, payload_arg);
+            let around_sig = |m: &syn::Ident| {
+                let payload_arg = if self.around_takes_payload(m) {
+                    payload_arg.as_str()
+                } else {
+                    ""
+                };
+                format!(
+                    "fn {}(&self, stage: AroundStage{}) -> AroundOutcome<_>",
+                    m, payload_arg
+                )
+            };
+            let action_sig =
+                |m: &syn::Ident| format!("fn {}(ctx: &mut {}{}) -> bool", m, ctx_ty, payload_arg);
+
+            let transitions = &event.transitions;
+            let guard_exprs = event.guards.iter().chain(&event.unless).chain(
+                transitions
+                    .iter()
+                    .flat_map(|t| t.guards.iter().chain(&t.unless)),
+            );
+            for expr in guard_exprs {
+                for (guard, args) in expr.calls() {
+                    check(guard, "a guard", guard_sig(guard, args))?;
+                }
+            }
+
+            // With a `by value` payload, which `after` entry takes it
+            // depends on the edge's filters, so those are checked per edge
+            let afters = (!event.payload_by_value)
+                .then(|| {
+                    event
+                        .after
+                        .iter()
+                        .chain(transitions.iter().flat_map(|t| &t.after))
+                })
+                .into_iter()
+                .flatten();
+            let filtered = event
+                .before
+                .iter()
+                .chain(transitions.iter().flat_map(|t| &t.before))
+                .chain(afters);
+            let callbacks = filtered
+                .map(|callback| &callback.name)
+                .filter(|callback| !self.split_callback(callback))
+                .chain(transitions.iter().flat_map(|t| &t.compensate));
+            for callback in callbacks {
+                check(callback, "a callback", callback_sig(callback))?;
+            }
+            if let Some(ty) = event.payload.as_ref().filter(|_| event.payload_by_value) {
+                let edges = self
+                    .states
+                    .iter()
+                    .filter_map(|state| self.transition_graph.outgoing(state))
+                    .flatten()
+                    .filter(|edge| edge.event == event.name);
+                for edge in edges {
+                    let plain = edge
+                        .after
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, callback)| !self.split_callback(callback));
+                    for (i, callback) in plain {
+                        let signature = if i + 1 == edge.after.len() {
+                            format!("fn {}(&self, payload: {})", callback, quote!(#ty))
+                        } else {
+                            callback_sig(callback)
+                        };
+                        check(callback, "a callback", signature)?;
+                    }
+                }
+            }
+
+            // `split` callbacks borrow the data of the state they run in:
+            // the source before the transition, the target after it
+            let edges = self.states.iter().flat_map(|source| {
+                self.transition_graph
+                    .outgoing(source)
+                    .into_iter()
+                    .flatten()
+                    .filter(|edge| edge.event == event.name)
+                    .map(move |edge| (source, edge))
+            });
+            for (source, edge) in edges {
+                let targets = match &edge.choice {
+                    Some(choice) => choice
+                        .targets
+                        .iter()
+                        .map(|target| self.resolve_target(target))
+                        .collect(),
+                    None => vec![edge.target.clone()],
+                };
+                for callback in edge.before.iter().filter(|c| self.split_callback(c)) {
+                    let signature =
+                        self.split_signature(callback, source, &ctx_ty, &payload_arg)?;
+                    check(callback, "a split callback", signature)?;
+                }
+                let splits = edge
+                    .after
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, callback)| self.split_callback(callback));
+                for (i, callback) in splits {
+                    let payload_arg = match &event.payload {
+                        Some(ty) if event.payload_by_value && i + 1 == edge.after.len() => {
+                            format!(", payload: {}", quote!(#ty))
+                        }
+                        _ => payload_arg.clone(),
+                    };
+                    for target in &targets {
+                        let signature =
+                            self.split_signature(callback, target, &ctx_ty, &payload_arg)?;
+                        check(callback, "a split callback", signature)?;
+                    }
+                }
+            }
+
+            let commits = event
+                .after_commit
+                .iter()
+                .chain(transitions.iter().flat_map(|t| &t.after_commit));
+            for callback in commits {
+                if callback.split {
+                    return Err(syn::Error::new(
+                        callback.name.span(),
+                        "`split` is only supported on `before` and `after` callbacks",
+                    ));
+                }
+                let callback = &callback.name;
+                check(
+                    callback,
+                    "an after_commit callback",
+                    format!("fn {}(&self)", callback),
+                )?;
+            }
+
+            let arounds = event
+                .around
+                .iter()
+                .chain(transitions.iter().flat_map(|t| t.around.iter()));
+            for around in arounds {
+                if event.payload.is_none() && self.around_takes_payload(around) {
+                    return Err(syn::Error::new(
+                        around.span(),
+                        format!(
+                            "around callback `{}` takes the payload (`{{ payload: true }}`), \
+                             but event `{}` has no payload",
+                            around, event.name
+                        ),
+                    ));
+                }
+                check(around, "an around callback", around_sig(around))?;
+            }
+
+            for action in transitions.iter().filter_map(|t| t.action.as_ref()) {
+                check(action, "an action", action_sig(action))?;
+            }
+
+            let (choice_enum, _) = self.choice_enum_names(&event.name);
+            for choice in transitions.iter().filter_map(|t| t.choice.as_ref()) {
+                let chooser = &choice.chooser;
+                let signature = format!(
+                    "fn {}(&self, ctx: &{}{}) -> {}",
+                    chooser, ctx_ty, payload_arg, choice_enum
+                );
+                check(chooser, "a chooser", signature)?;
+            }
+        }
+
+        for global in &self.callbacks.before_transition {
+            let callback = &global.callback.name;
+            let signature = format!("fn {}(&self) -> ControlFlow<()>", callback);
+            check(callback, "a before_transition callback", signature)?;
+        }
+        for global in &self.callbacks.after_transition {
+            let callback = &global.callback.name;
+            check(
+                callback,
+                "an after_transition callback",
+                format!("fn {}(&self)", callback),
+            )?;
+        }
+
+        if let UnhandledPolicy::Callback(callback) = &self.unhandled {
+            let signature = format!(
+                "fn {}(&self, state: {}State, event: {}Event)",
+                callback, self.name, self.name
+            );
+            check(callback, "an unhandled-event callback", signature)?;
+        }
+
+        if let Some(handler) = self
+            .on_error
+            .as_ref()
+            .and_then(|on_error| on_error.handler.as_ref())
+        {
+            let signature = format!("fn {}(&self, failure: EventFailure<'_>)", handler);
+            check(handler, "an on_error handler", signature)?;
+        }
+
+        if let Some(sink) = &self.sink {
+            let signature = format!(
+                "fn {}(&mut self) -> &mut impl Sink<{}Notification>",
+                sink, self.name
+            );
+            check(sink, "a notification sink", signature)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: proc_macro2::TokenStream) -> String {
+        match syn::parse2::<StateMachine>(input).and_then(|machine| machine.validate()) {
+            Ok(()) => panic!("expected the definition to be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_overlapping_superstate_sources() {
+        let message = error(quote! {
+            name: Ship,
+            initial: Docked,
+            states: [Docked, Orbit, superstate Flight { state Cruising, state Landing }],
+            events {
+                land {
+                    transition: { from: Flight, to: Docked }
+                    transition: { from: Cruising, to: Orbit }
+                }
+            }
+        });
+        assert_eq!(
+            message,
+            "`Cruising` is already a source of `land` through superstate `Flight`"
+        );
+
+        let message = error(quote! {
+            name: Ship,
+            initial: Docked,
+            states: [Docked, superstate Flight { state Cruising, state Landing }],
+            events {
+                land {
+                    transition: { from: [Cruising, Flight], to: Docked }
+                }
+            }
+        });
+        assert_eq!(
+            message,
+            "superstate `Flight` includes `Cruising`, which is already a source of `land`"
+        );
+    }
+
+    #[test]
+    fn test_superstate_names_are_unique() {
+        let message = error(quote! {
+            name: Ship,
+            initial: Docked,
+            states: [Docked, superstate Docked { state Cruising }],
+            events {
+                moor { transition: { from: Cruising, to: Docked } }
+            }
+        });
+        assert_eq!(message, "superstate `Docked` has the same name as a state");
+
+        let message = error(quote! {
+            name: Ship,
+            initial: Docked,
+            states: [
+                Docked,
+                superstate Flight { state Cruising },
+                superstate Flight { state Landing },
+            ],
+            events {
+                moor { transition: { from: Cruising, to: Docked } }
+            }
+        });
+        assert_eq!(message, "duplicate superstate");
+    }
+
+    #[test]
+    fn test_superstate_initial_lists_the_candidates() {
+        let message = error(quote! {
+            name: Ship,
+            initial: Docked,
+            states: [
+                Docked,
+                superstate Flight {
+                    initial: Inner,
+                    superstate Inner { state Climbing, state Cruising },
+                },
+            ],
+            events {
+                moor { transition: { from: Flight, to: Docked } }
+            }
+        });
+        assert_eq!(
+            message,
+            "`initial` must reference a leaf state; `Inner` is a superstate, so name one of its states: `Climbing`, `Cruising`"
+        );
+
+        let message = error(quote! {
+            name: Ship,
+            initial: Docked,
+            states: [Docked, superstate Flight { initial: Docked, state Cruising }],
+            events {
+                moor { transition: { from: Flight, to: Docked } }
+            }
+        });
+        assert_eq!(
+            message,
+            "`initial` must reference a descendant state of `Flight`: one of `Cruising`"
+        );
+    }
+
+    #[test]
+    fn test_codes_need_the_dynamic_enums() {
+        let message = error(quote! {
+            name: Valve,
+            initial: Closed,
+            states: [Closed = 1, Open = 2],
+            events {
+                open { transition: { from: Closed, to: Open } }
+            }
+        });
+        assert_eq!(message, "state codes need `dynamic: true`");
+
+        let message = error(quote! {
+            name: Valve,
+            dynamic: true,
+            initial: Closed,
+            states: [Closed, superstate Active { state Open = 1 }],
+            events {
+                open { transition: { from: Closed, to: Open } }
+            }
+        });
+        assert_eq!(
+            message,
+            "`Closed` needs a code, since other states have one"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! The `assert_machine!` macro: review rules as compile-time checks.
+//!
+//! ```ignore
+//! assert_machine!(Door: can_reach(Closed => Open), no_orphan_states, deterministic);
+//! ```
+//!
+//! Each check becomes a `const _: () = assert!(...)` against the machine's
+//! `{Name}State::TABLE` (see `TransitionTable` in the core crate), so a
+//! definition that breaks a rule fails to compile, with the check's span and
+//! a message naming the rule. The macro only knows the machine's name; state
+//! and event names are looked up by the table's `const fn`s, so a typo there
+//! is a compile error as well.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Ident, Path, Result, Token, parenthesized};
+
+use state_machines_dsl::parser::unexpected_key;
+
+/// The checks `assert_machine!` understands.
+const CHECKS: &[&str] = &[
+    "can_reach",
+    "can_reach_from_all",
+    "has_transition",
+    "terminal",
+    "no_orphan_states",
+    "deterministic",
+];
+
+/// `Door: check, check, ...`
+pub struct MachineAssertions {
+    machine: Path,
+    checks: Punctuated<Check, Token![,]>,
+}
+
+/// One check, with the identifier that names it for error spans.
+struct Check {
+    name: Ident,
+    kind: CheckKind,
+}
+
+enum CheckKind {
+    /// `can_reach(From => To)`
+    CanReach(Ident, Ident),
+    /// `can_reach_from_all(State)`
+    CanReachFromAll(Ident),
+    /// `has_transition(State, event)`
+    HasTransition(Ident, Ident),
+    /// `terminal(State)`
+    Terminal(Ident),
+    NoOrphanStates,
+    Deterministic,
+}
+
+impl Parse for MachineAssertions {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let machine = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let checks = Punctuated::parse_terminated(input)?;
+        Ok(Self { machine, checks })
+    }
+}
+
+impl Parse for Check {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        let kind = match name.to_string().as_str() {
+            "can_reach" => {
+                let args;
+                parenthesized!(args in input);
+                let from = args.parse()?;
+                args.parse::<Token![=>]>()?;
+                CheckKind::CanReach(from, args.parse()?)
+            }
+            "can_reach_from_all" => {
+                let args;
+                parenthesized!(args in input);
+                CheckKind::CanReachFromAll(args.parse()?)
+            }
+            "has_transition" => {
+                let args;
+                parenthesized!(args in input);
+                let state = args.parse()?;
+                args.parse::<Token![,]>()?;
+                CheckKind::HasTransition(state, args.parse()?)
+            }
+            "terminal" => {
+                let args;
+                parenthesized!(args in input);
+                CheckKind::Terminal(args.parse()?)
+            }
+            "no_orphan_states" => CheckKind::NoOrphanStates,
+            "deterministic" => CheckKind::Deterministic,
+            _ => return Err(unexpected_key(&name, "check", CHECKS)),
+        };
+        Ok(Self { name, kind })
+    }
+}
+
+impl MachineAssertions {
+    pub fn expand(&self) -> TokenStream2 {
+        let mut table = self.machine.clone();
+        let Some(last) = table.segments.last_mut() else {
+            return TokenStream2::new();
+        };
+        let machine = last.ident.to_string();
+        last.ident = format_ident!("{}State", last.ident);
+
+        self.checks
+            .iter()
+            .map(|check| {
+                let (condition, message) = match &check.kind {
+                    CheckKind::CanReach(from, to) => (
+                        quote_spanned! {check.name.span()=>
+                            #table::TABLE.can_reach(stringify!(#from), stringify!(#to))
+                        },
+                        format!("`{machine}`: `{from}` can't reach `{to}`"),
+                    ),
+                    CheckKind::CanReachFromAll(state) => (
+                        quote_spanned! {check.name.span()=>
+                            #table::TABLE.can_reach_from_all(stringify!(#state))
+                        },
+                        format!("`{machine}`: not every state can reach `{state}`"),
+                    ),
+                    CheckKind::HasTransition(state, event) => (
+                        quote_spanned! {check.name.span()=>
+                            #table::TABLE.has_transition(stringify!(#state), stringify!(#event))
+                        },
+                        format!("`{machine}`: `{event}` has no transition from `{state}`"),
+                    ),
+                    CheckKind::Terminal(state) => (
+                        quote_spanned! {check.name.span()=>
+                            #table::TABLE.is_terminal(stringify!(#state))
+                        },
+                        format!("`{machine}`: `{state}` has outgoing transitions"),
+                    ),
+                    CheckKind::NoOrphanStates => (
+                        quote_spanned! {check.name.span()=>
+                            #table::TABLE.all_states_reachable()
+                        },
+                        format!("`{machine}` has states its initial state can't reach"),
+                    ),
+                    CheckKind::Deterministic => (
+                        quote_spanned! {check.name.span()=>
+                            #table::TABLE.is_deterministic()
+                        },
+                        format!("`{machine}` has an event with several targets from one state"),
+                    ),
+                };
+                quote_spanned! {check.name.span()=>
+                    const _: () = ::core::assert!(#condition, #message);
+                }
+            })
+            .collect()
+    }
+}
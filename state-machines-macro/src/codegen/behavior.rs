@@ -0,0 +1,132 @@
+//! Guard and callback methods for `behavior: true` machines.
+//!
+//! Instead of calling methods the user writes, such a machine's guards and
+//! callbacks are generated here, one per name, and call the `Behavior` in
+//! its `WithBehavior` context:
+//!
+//! ```rust,ignore
+//! fn approved(&self, ctx: &WithBehavior<Claim>) -> bool {
+//!     Behavior::has_guard(&*ctx.behavior, "approved")
+//!         && Behavior::guard(&*ctx.behavior, "approved", &ctx.ctx)
+//! }
+//!
+//! fn notify<__P>(&mut self, _payload: __P) {
+//!     Behavior::callback(&*self.ctx.behavior, "notify", &mut self.ctx.ctx)
+//! }
+//! ```
+//!
+//! The rest of the generated code calls them like any other guard or
+//! callback. A payload is accepted but not passed on: the `Behavior` only
+//! sees the context.
+//!
+//! A guard the `Behavior` doesn't have fails closed: one in `unless` (or
+//! under `!`) reports `true`, so the transition is refused either way. The
+//! names are listed in the machine's `BEHAVIOR` constant, so they can be
+//! checked when the context is built, with `WithBehavior::checked`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use state_machines_dsl::types::*;
+use syn::Ident;
+
+/// The generated methods, or nothing without `behavior: true`.
+pub fn generate_behavior_methods(machine: &StateMachine) -> Option<TokenStream2> {
+    machine.behavior.as_ref()?;
+
+    let ctx_ty = machine.ctx_type();
+    let behavior = quote! { ::state_machines::core::Behavior };
+    let mut generated: Vec<&Ident> = Vec::new();
+    let mut methods = Vec::new();
+    // Validation made each name's uses agree, so the first one decides
+    for (name, role, payload) in machine.behavior_calls() {
+        if generated.contains(&name) {
+            continue;
+        }
+        generated.push(name);
+        let key = name.to_string();
+        let method = match (role, payload) {
+            (BehaviorRole::Guard { negated }, payload) => {
+                // An unknown guard fails whichever way the transition needs it
+                let lookup = if negated {
+                    quote! {
+                        !#behavior::has_guard(&*ctx.behavior, #key)
+                            || #behavior::guard(&*ctx.behavior, #key, &ctx.ctx)
+                    }
+                } else {
+                    quote! {
+                        #behavior::has_guard(&*ctx.behavior, #key)
+                            && #behavior::guard(&*ctx.behavior, #key, &ctx.ctx)
+                    }
+                };
+                if payload {
+                    quote_spanned! {name.span()=>
+                        fn #name<__P: ?::core::marker::Sized>(&self, ctx: &#ctx_ty, _payload: &__P) -> bool {
+                            #lookup
+                        }
+                    }
+                } else {
+                    quote_spanned! {name.span()=>
+                        fn #name(&self, ctx: &#ctx_ty) -> bool {
+                            #lookup
+                        }
+                    }
+                }
+            }
+            (BehaviorRole::Callback, false) => quote_spanned! {name.span()=>
+                fn #name(&mut self) {
+                    #behavior::callback(&*self.ctx.behavior, #key, &mut self.ctx.ctx)
+                }
+            },
+            (BehaviorRole::Callback, true) => quote_spanned! {name.span()=>
+                fn #name<__P>(&mut self, _payload: __P) {
+                    #behavior::callback(&*self.ctx.behavior, #key, &mut self.ctx.ctx)
+                }
+            },
+            (BehaviorRole::BeforeTransition, _) => quote_spanned! {name.span()=>
+                fn #name(&mut self) -> ::core::ops::ControlFlow<()> {
+                    #behavior::callback(&*self.ctx.behavior, #key, &mut self.ctx.ctx);
+                    ::core::ops::ControlFlow::Continue(())
+                }
+            },
+        };
+        methods.push(method);
+    }
+
+    let params = machine.generic_params();
+    let machine_ty = machine.machine_type(quote! { S });
+    Some(quote! {
+        impl<#(#params,)* S> #machine_ty {
+            #( #methods )*
+        }
+    })
+}
+
+/// The `BEHAVIOR` constant naming the guards and callbacks the machine looks
+/// up, or nothing without `behavior: true`. It goes with `new()`, on the
+/// initial state, so `{Name}::BEHAVIOR` needs no state type.
+pub fn generate_behavior_names(machine: &StateMachine) -> Option<TokenStream2> {
+    machine.behavior.as_ref()?;
+
+    let mut guards: Vec<String> = Vec::new();
+    let mut callbacks: Vec<String> = Vec::new();
+    for (name, role, _) in machine.behavior_calls() {
+        let names = match role {
+            BehaviorRole::Guard { .. } => &mut guards,
+            BehaviorRole::Callback | BehaviorRole::BeforeTransition => &mut callbacks,
+        };
+        let name = name.to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    Some(quote! {
+        /// The guards and callbacks this machine looks up in its `Behavior`;
+        /// see `WithBehavior::checked`.
+        pub const BEHAVIOR: ::state_machines::core::BehaviorNames =
+            ::state_machines::core::BehaviorNames {
+                guards: &[#(#guards),*],
+                callbacks: &[#(#callbacks),*],
+            };
+    })
+}
@@ -5,27 +5,45 @@
 //! - The `dynamic` feature flag is enabled, OR
 //! - The macro explicitly specifies `dynamic: true`
 
-use crate::codegen::utils::{to_pascal_case, to_snake_case, to_snake_case_ident};
-use crate::types::*;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use state_machines_dsl::types::*;
+use state_machines_dsl::utils::{to_pascal_case, to_snake_case, to_snake_case_ident};
 use syn::Result;
+use syn::spanned::Spanned;
 
 /// Generate dynamic dispatch wrapper code for the state machine.
 ///
 /// This generates:
 /// - Event enum for runtime event dispatch
+/// - State enum for exhaustive matching on the current state
 /// - AnyState enum wrapping all typed state machines
 /// - DynamicMachine struct with handle() method
 /// - Conversion methods between typestate and dynamic modes
 pub fn generate_dynamic_wrapper(machine: &StateMachine) -> Result<TokenStream2> {
+    // `restore()` can only fill in state data that `Default` doesn't build
+    // when the `{Name}State` value carries it
+    if let Some(key) = &machine.no_default_init
+        && !machine.data_states
+        && !machine.state_storage.is_empty()
+    {
+        return Err(syn::Error::new(
+            key.span(),
+            "dynamic machines with `no_default_init: true` need `data_states: true`, so restoring a state gets its data",
+        ));
+    }
+
     let event_enum = generate_event_enum(machine)?;
+    let state_enum = generate_state_enum(machine)?;
+    let projector = generate_projector(machine);
     let any_state_enum = generate_any_state_enum(machine)?;
     let dynamic_machine = generate_dynamic_machine(machine)?;
     let conversions = generate_conversions(machine)?;
 
     Ok(quote! {
         #event_enum
+        #state_enum
+        #projector
         #any_state_enum
         #dynamic_machine
         #conversions
@@ -44,16 +62,18 @@ pub fn generate_dynamic_wrapper(machine: &StateMachine) -> Result<TokenStream2>
 /// }
 /// ```
 fn generate_event_enum(machine: &StateMachine) -> Result<TokenStream2> {
+    let vis = machine.item_visibility();
     let machine_name = &machine.name;
     let event_name = quote::format_ident!("{}Event", machine_name);
 
     let enum_variants = machine.events.iter().map(|event| {
         let pascal_name =
             syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+        let doc = event.doc.as_ref().map(|doc| quote! { #[doc = #doc] });
         if let Some(payload_ty) = &event.payload {
-            quote! { #pascal_name(#payload_ty) }
+            quote! { #doc #pascal_name(#payload_ty) }
         } else {
-            quote! { #pascal_name }
+            quote! { #doc #pascal_name }
         }
     });
 
@@ -68,527 +88,3177 @@ fn generate_event_enum(machine: &StateMachine) -> Result<TokenStream2> {
         }
     });
 
+    // Stable `event = 1` codes; payload events can't be built from a code
+    let code_methods = machine
+        .events
+        .first()
+        .and_then(|event| event.code.as_ref())
+        .map(|_| {
+            let events = || {
+                machine.events.iter().filter_map(|event| {
+                    let pascal_name = syn::Ident::new(
+                        &to_pascal_case(&event.name.to_string()),
+                        event.name.span(),
+                    );
+                    Some((event, pascal_name, code_literal(event.code.as_ref()?)))
+                })
+            };
+            let code_arms = events().map(|(event, pascal_name, code)| {
+                if event.payload.is_some() {
+                    quote! { Self::#pascal_name(_) => #code }
+                } else {
+                    quote! { Self::#pascal_name => #code }
+                }
+            });
+            let from_code_arms = events().filter(|(event, ..)| event.payload.is_none()).map(
+                |(_, pascal_name, code)| {
+                    quote! { #code => ::core::option::Option::Some(Self::#pascal_name) }
+                },
+            );
+            quote! {
+                /// The stable code declared for this event, e.g. `close = 3`.
+                pub fn as_code(&self) -> u32 {
+                    match self {
+                        #(#code_arms,)*
+                    }
+                }
+
+                /// Look up an event without a payload by its `as_code()`.
+                pub fn from_code(code: u32) -> ::core::option::Option<Self> {
+                    match code {
+                        #(#from_code_arms,)*
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+        });
+
+    let extra_derives = &machine.event_derives;
+
+    // Generic over the lifetimes of borrowed payloads, e.g. `&'p [u8]`
+    let lifetimes = machine.event_lifetimes();
+    let generics = if lifetimes.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#lifetimes),*> }
+    };
+
     Ok(quote! {
-        #[derive(Debug)]
-        pub enum #event_name {
+        #[derive(Debug #(, #extra_derives)*)]
+        #vis enum #event_name #generics {
             #(#enum_variants,)*
         }
 
-        impl #event_name {
+        impl #generics #event_name #generics {
             /// Get the name of this event as a static string.
             pub fn name(&self) -> &'static str {
                 match self {
                     #(#match_arms,)*
                 }
             }
+
+            #code_methods
         }
     })
 }
 
-/// Generate the AnyState enum that wraps all typed state machines.
+/// Generate the State enum with one unit variant per leaf state.
+///
+/// Unlike the string returned by `current_state()`, this enum can be matched
+/// exhaustively, so a renamed or misspelled state is a compile error.
 ///
 /// Example output:
 /// ```ignore
-/// enum AnyFlightState {
-///     Docked(FlightController<Docked>),
-///     InFlight(FlightController<InFlight>),
-///     Landed(FlightController<Landed>),
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// pub enum FlightState {
+///     Docked,
+///     InFlight,
+///     Landed,
 /// }
 /// ```
-fn generate_any_state_enum(machine: &StateMachine) -> Result<TokenStream2> {
-    let machine_name = &machine.name;
-    let any_state_name = quote::format_ident!("Any{}State", machine_name);
+fn generate_state_enum(machine: &StateMachine) -> Result<TokenStream2> {
+    let vis = machine.item_visibility();
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let states = &machine.states;
+    let variants = states.iter().map(|state| {
+        let doc = machine.state_doc(state).map(|doc| quote! { #[doc = #doc] });
+        match machine.state_storage_for(state) {
+            Some(spec) if machine.data_states => {
+                let ty = &spec.ty;
+                quote! { #doc #state(#ty) }
+            }
+            _ => quote! { #doc #state },
+        }
+    });
 
-    // Generate enum variants for each state
-    let variants = if machine.context.is_some() {
-        // Concrete context: Machine<S> only
-        machine
-            .states
+    // `{ .. }` matches unit and tuple variants alike
+    let name_arms = states.iter().map(|state| {
+        let state_str = state.to_string();
+        quote! { Self::#state { .. } => #state_str }
+    });
+
+    // The inverse of `name()`; data-carrying variants can't be built from a name
+    let from_name_method = (!machine.data_states).then(|| {
+        let arms = states.iter().map(|state| {
+            let state_str = state.to_string();
+            quote! { #state_str => ::core::option::Option::Some(Self::#state) }
+        });
+        quote! {
+            /// Look up a state by its `name()`.
+            pub fn from_name(name: &str) -> ::core::option::Option<Self> {
+                match name {
+                    #(#arms,)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        }
+    });
+
+    // Stable `State = 1` codes; validation makes sure every state has one
+    let code_methods = states
+        .first()
+        .and_then(|state| machine.state_code(state))
+        .map(|_| {
+            let codes = || {
+                states
+                    .iter()
+                    .filter_map(|state| Some((state, code_literal(machine.state_code(state)?))))
+            };
+            let code_arms = codes().map(|(state, code)| quote! { Self::#state { .. } => #code });
+            // Data-carrying variants can't be built from a code
+            let from_code_method = (!machine.data_states).then(|| {
+                let arms = codes().map(|(state, code)| {
+                    quote! { #code => ::core::option::Option::Some(Self::#state) }
+                });
+                quote! {
+                    /// Look up a state by its `as_code()`.
+                    pub fn from_code(code: u32) -> ::core::option::Option<Self> {
+                        match code {
+                            #(#arms,)*
+                            _ => ::core::option::Option::None,
+                        }
+                    }
+                }
+            });
+            quote! {
+                /// The stable code declared for this state, e.g. `Closed = 1`.
+                pub fn as_code(&self) -> u32 {
+                    match self {
+                        #(#code_arms,)*
+                    }
+                }
+
+                #from_code_method
+            }
+        });
+
+    // Watchdog dwell limits from each state's `max_duration:`
+    let max_duration_method = machine.watchdog.then(|| {
+        let arms = states
             .iter()
-            .map(|state| {
-                quote! { #state(#machine_name<#state>) }
-            })
-            .collect::<Vec<_>>()
+            .map(|state| match machine.state_max_duration(state) {
+                Some(limit) => {
+                    quote! { Self::#state { .. } => ::core::option::Option::Some(#limit) }
+                }
+                None => quote! { Self::#state { .. } => ::core::option::Option::None },
+            });
+        quote! {
+            /// The `max_duration:` declared on this state, if any.
+            pub fn max_duration(&self) -> ::core::option::Option<::core::time::Duration> {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    });
+
+    // `AtomicState` support: round-trip through the `u8` discriminant
+    let repr_impl = if machine.atomic_state {
+        let raw_arms = states.iter().enumerate().map(|(index, state)| {
+            let raw = index as u8;
+            quote! { #raw => Self::#state }
+        });
+        quote! {
+            impl state_machines::StateRepr for #state_enum_name {
+                fn to_raw(self) -> u8 {
+                    self as u8
+                }
+
+                fn from_raw(raw: u8) -> Self {
+                    match raw {
+                        #(#raw_arms,)*
+                        _ => panic!("invalid {} discriminant: {}", stringify!(#state_enum_name), raw),
+                    }
+                }
+            }
+        }
     } else {
-        // Generic context: Machine<C, S>
-        machine
-            .states
-            .iter()
-            .map(|state| {
-                quote! { #state(#machine_name<C, #state>) }
-            })
-            .collect::<Vec<_>>()
+        quote! {}
     };
 
-    // Generate match arms for the name() method
-    let name_arms = machine.states.iter().map(|state| {
-        let state_str = state.to_string();
-        quote! { Self::#state(_) => #state_str }
-    });
+    let extra_derives = &machine.state_derives;
+    let extra_attrs = &machine.state_attrs;
+
+    let predicates = state_predicates(machine, |state| quote! { Self::#state { .. } });
 
-    // Determine enum generics
-    let (enum_generics, impl_generics) = if machine.context.is_some() {
-        // Concrete context: no generics
+    // Data may not be `Copy` or comparable, so data-carrying enums only get the
+    // derives `MachineState` needs
+    let derives = if machine.data_states {
+        quote! { Debug, Clone }
+    } else {
+        quote! { Debug, Clone, Copy, PartialEq, Eq, Hash }
+    };
+
+    // Data-carrying variants can't be built in a `static`
+    let (definition, transition_refs) = if machine.data_states {
         (quote! {}, quote! {})
     } else {
-        // Generic context
-        (quote! { <C> }, quote! { <C> })
+        (
+            generate_definition(machine),
+            generate_transition_refs(machine),
+        )
     };
 
     Ok(quote! {
-        /// Internal enum wrapping all typed state machines.
-        ///
-        /// This enables runtime polymorphism over different states while
-        /// preserving the compile-time safety of the typestate pattern.
-        #[derive(Debug)]
-        enum #any_state_name #enum_generics {
+        #[derive(#derives #(, #extra_derives)*)]
+        #[allow(clippy::enum_variant_names)]
+        #(#extra_attrs)*
+        #vis enum #state_enum_name {
             #(#variants,)*
         }
 
-        impl #impl_generics #any_state_name #enum_generics {
-            /// Get the name of the current state.
-            fn name(&self) -> &'static str {
+        impl #state_enum_name {
+            /// Get the name of this state as a static string.
+            pub fn name(&self) -> &'static str {
                 match self {
                     #(#name_arms,)*
                 }
             }
+
+            #from_name_method
+
+            #code_methods
+
+            #(#predicates)*
+
+            #max_duration_method
+
+            #definition
         }
+
+        #transition_refs
+
+        #repr_impl
     })
 }
 
-/// Generate the DynamicMachine struct with handle() method.
+/// Generate `mod transitions`, with a `TransitionRef` constant per edge of
+/// the definition, e.g. `PAY_PENDING_TO_PAID`, and `ALL` of them in order.
 ///
-/// Example output:
-/// ```ignore
-/// pub struct DynamicFlightController {
-///     inner: AnyFlightState,
-/// }
+/// Without a `module:` wrapper the constants go on `{Name}Transitions`
+/// instead: the machine may be declared inside a function, where a nested
+/// module couldn't name `{Name}State`, and a fixed module name would clash
+/// between machines declared side by side.
 ///
-/// impl DynamicFlightController {
-///     pub fn handle(&mut self, event: FlightEvent) -> Result<(), DynamicError> {
-///         // Runtime dispatch logic
-///     }
-/// }
-/// ```
-fn generate_dynamic_machine(machine: &StateMachine) -> Result<TokenStream2> {
-    let machine_name = &machine.name;
-    let dynamic_name = quote::format_ident!("Dynamic{}", machine_name);
-    let any_state_name = quote::format_ident!("Any{}State", machine_name);
-    let event_name = quote::format_ident!("{}Event", machine_name);
-    let initial_state = &machine.initial;
-    let is_async = machine.async_mode;
+/// Indices follow `generate_definition()`: superstate sources are expanded
+/// and a `choose:` transition counts once per target.
+fn generate_transition_refs(machine: &StateMachine) -> TokenStream2 {
+    let vis = machine.item_visibility();
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let state = match machine.module {
+        Some(_) => quote! { super::#state_enum_name },
+        None => quote! { #state_enum_name },
+    };
+    let upper = |ident: &syn::Ident| to_snake_case(&ident.to_string()).to_uppercase();
 
-    // Helper to convert snake_case to PascalCase for enum variants
-    let to_pascal_case = |s: &str| -> String {
-        s.split('_')
-            .map(|word| {
-                let mut chars = word.chars();
-                match chars.next() {
-                    None => String::new(),
-                    Some(first) => first.to_uppercase().chain(chars).collect(),
+    let mut consts = Vec::new();
+    let mut names = Vec::new();
+    for (event_index, event) in machine.events.iter().enumerate() {
+        let mut transition_index = 0usize;
+        for transition in &event.transitions {
+            let targets = match &transition.choice {
+                Some(choice) => choice.targets.clone(),
+                None => vec![transition.target.clone()],
+            };
+            for target in targets {
+                let target = machine.resolve_target(&target);
+                let sources = transition
+                    .sources
+                    .iter()
+                    .flat_map(|source| machine.hierarchy.expand_state(source, &machine.states));
+                for source in sources {
+                    let name = quote::format_ident!(
+                        "{}_{}_TO_{}",
+                        upper(&event.name),
+                        upper(&source),
+                        upper(&target),
+                        span = event.name.span()
+                    );
+                    let doc = format!(" `{}` from `{}` to `{}`.", event.name, source, target);
+                    consts.push(quote! {
+                        #[doc = #doc]
+                        pub const #name: state_machines::TransitionRef<#state> =
+                            state_machines::TransitionRef::new(
+                                #state::definition,
+                                #event_index,
+                                #transition_index,
+                                #state::#source,
+                                #state::#target,
+                            );
+                    });
+                    names.push(name);
                 }
-            })
-            .collect()
-    };
+                transition_index += 1;
+            }
+        }
+    }
 
-    // Generate match arms for handle() method
-    let mut match_arms = Vec::new();
+    let doc = format!(
+        " Handles to each transition of `{}State::definition()`.",
+        machine.name
+    );
+    if machine.module.is_some() {
+        return quote! {
+            #[doc = #doc]
+            pub mod transitions {
+                #( #consts )*
 
-    for event in &machine.events {
-        let event_snake = &event.name; // snake_case from macro definition
-        let event_pascal = syn::Ident::new(
-            &to_pascal_case(&event_snake.to_string()),
-            event_snake.span(),
-        );
-        let event_method = to_snake_case_ident(event_snake); // method name (already snake_case)
-
-        // Get all transitions for this event from the transition graph
-        for state in &machine.states {
-            if let Some(edges) = machine.transition_graph.outgoing(state) {
-                for edge in edges {
-                    if edge.event == *event_snake {
-                        let source_state = state;
-                        let target_state = &edge.target;
+                /// Every transition, in declaration order.
+                pub const ALL: &[state_machines::TransitionRef<#state>] = &[#( #names ),*];
+            }
+        };
+    }
 
-                        // Generate the match arm for this transition
-                        // Use event_pascal for enum variant matching
-                        // Use event_method for calling the snake_case typestate method
-                        let arm = if event.payload.is_some() {
-                            if is_async {
-                                quote! {
-                                    (#any_state_name::#source_state(m), #event_name::#event_pascal(payload)) => {
-                                        match m.#event_method(payload).await {
-                                            Ok(new_machine) => #any_state_name::#target_state(new_machine),
-                                            Err((old_machine, err)) => {
-                                                self.inner = ::core::option::Option::Some(#any_state_name::#source_state(old_machine));
-                                                return Err(state_machines::DynamicError::from_guard_error(err));
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                quote! {
-                                    (#any_state_name::#source_state(m), #event_name::#event_pascal(payload)) => {
-                                        match m.#event_method(payload) {
-                                            Ok(new_machine) => #any_state_name::#target_state(new_machine),
-                                            Err((old_machine, err)) => {
-                                                self.inner = ::core::option::Option::Some(#any_state_name::#source_state(old_machine));
-                                                return Err(state_machines::DynamicError::from_guard_error(err));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else if is_async {
-                            quote! {
-                                (#any_state_name::#source_state(m), #event_name::#event_pascal) => {
-                                    match m.#event_method().await {
-                                        Ok(new_machine) => #any_state_name::#target_state(new_machine),
-                                        Err((old_machine, err)) => {
-                                            self.inner = ::core::option::Option::Some(#any_state_name::#source_state(old_machine));
-                                            return Err(state_machines::DynamicError::from_guard_error(err));
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            quote! {
-                                (#any_state_name::#source_state(m), #event_name::#event_pascal) => {
-                                    match m.#event_method() {
-                                        Ok(new_machine) => #any_state_name::#target_state(new_machine),
-                                        Err((old_machine, err)) => {
-                                            self.inner = ::core::option::Option::Some(#any_state_name::#source_state(old_machine));
-                                            return Err(state_machines::DynamicError::from_guard_error(err));
-                                        }
-                                    }
-                                }
-                            }
-                        };
+    let refs_name = quote::format_ident!("{}Transitions", machine.name);
+    quote! {
+        #[doc = #doc]
+        #vis struct #refs_name;
 
-                        match_arms.push(arm);
-                    }
-                }
-            }
+        impl #refs_name {
+            #( #consts )*
+
+            /// Every transition, in declaration order.
+            pub const ALL: &'static [state_machines::TransitionRef<#state>] =
+                &[#( Self::#names ),*];
         }
     }
+}
 
-    // Add a catch-all arm for invalid transitions
-    let catch_all = quote! {
-        (state, event) => {
-            let state_name = state.name();
-            self.inner = ::core::option::Option::Some(state);
-            return Err(state_machines::DynamicError::invalid_transition(
-                state_name,
-                event.name(),
-            ));
+/// Implement `Projector` for `{Name}State` over `{Name}Event`: each
+/// (state, event) pair maps to the edge's target without running guards or
+/// callbacks, and ignored events leave the state as it is.
+///
+/// Entered data starts at `StateData::initial()` or a clone of the payload;
+/// data built by `init:` and `choose:` targets can't be projected.
+fn generate_projector(machine: &StateMachine) -> TokenStream2 {
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let event_name = quote::format_ident!("{}Event", machine.name);
+    let event_ty = machine.event_type(false);
+    let lifetimes = machine.event_lifetimes();
+    let generics = if lifetimes.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#lifetimes),*> }
+    };
+    let error = quote! { state_machines::ProjectionError };
+    // The initial state's data starts where `new()` starts it
+    let initial = machine.resolve_target(&machine.initial);
+    let initial = match machine.state_storage_for(&initial) {
+        Some(spec) if machine.data_states => {
+            let ty = &spec.ty;
+            quote! { Self::#initial(<#ty as state_machines::StateData>::initial()) }
         }
+        _ => quote! { Self::#initial },
     };
 
-    let handle_sig = if is_async {
-        quote! { pub async fn handle(&mut self, event: #event_name) -> Result<(), state_machines::DynamicError> }
-    } else {
-        quote! { pub fn handle(&mut self, event: #event_name) -> Result<(), state_machines::DynamicError> }
-    };
+    let mut arms = Vec::new();
+    for source_state in &machine.states {
+        let (from, ignored) = (
+            source_state.to_string(),
+            machine.ignored_events(source_state),
+        );
+        for event in &machine.events {
+            let event_pascal =
+                syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+            let event_str = event.name.to_string();
+            let undetermined = quote! {
+                Err(#error::Undetermined { from: #from, event: #event_str })
+            };
+            let (value, uses_payload) = match machine.edge_for(source_state, &event.name) {
+                Some(edge) if edge.choice.is_none() => {
+                    let target = &edge.target;
+                    let data = machine.data_states.then(|| {
+                        machine.data_init(target, edge.init.as_ref(), edge.payload.as_ref())
+                    });
+                    match data {
+                        None | Some(DataInit::None) => (quote! { Ok(Self::#target) }, false),
+                        Some(DataInit::Default(ty)) => (
+                            quote! { Ok(Self::#target(<#ty as state_machines::StateData>::initial())) },
+                            false,
+                        ),
+                        Some(DataInit::Payload) => (
+                            quote! { Ok(Self::#target(::core::clone::Clone::clone(payload))) },
+                            true,
+                        ),
+                        Some(DataInit::Init(_)) => (undetermined, false),
+                    }
+                }
+                Some(_) => (undetermined, false),
+                None if ignored.contains(&&event.name) => (quote! { Ok(self) }, false),
+                None => continue,
+            };
+            let event_pattern = match (&event.payload, uses_payload) {
+                (Some(_), true) => quote! { #event_name::#event_pascal(payload) },
+                (Some(_), false) => quote! { #event_name::#event_pascal(_) },
+                (None, _) => quote! { #event_name::#event_pascal },
+            };
+            arms.push(quote! {
+                (Self::#source_state { .. }, #event_pattern) => #value
+            });
+        }
+    }
 
-    // Determine struct and impl generics based on context
-    let (struct_generics, impl_generics, ctx_param_ty, any_state_generics) =
-        if let Some(concrete_ctx) = &machine.context {
-            // Concrete context: no generics, specific context type
-            (quote! {}, quote! {}, quote! { #concrete_ctx }, quote! {})
-        } else {
-            // Generic context
-            (quote! { <C> }, quote! { <C> }, quote! { C }, quote! { <C> })
-        };
+    quote! {
+        impl #generics state_machines::Projector<#event_ty> for #state_enum_name {
+            type Error = #error;
 
-    // Default impl only for generic context with Default bound, or concrete context with Default
-    let default_impl = if machine.context.is_some() {
-        // Concrete context: only generate Default impl if the concrete type has Default
-        // We can't check that at macro time, so we conditionally generate with where clause
-        let concrete_ctx = machine.context.as_ref().unwrap();
-        quote! {
-            impl Default for #dynamic_name where #concrete_ctx: ::core::default::Default {
-                fn default() -> Self {
-                    Self::new(<#concrete_ctx as ::core::default::Default>::default())
+            fn initial() -> Self {
+                #initial
+            }
+
+            #[allow(unreachable_patterns)]
+            fn apply(self, event: &#event_ty) -> ::core::result::Result<Self, #error> {
+                match (&self, event) {
+                    #(#arms,)*
+                    (state, event) => Err(#error::InvalidTransition {
+                        from: state.name(),
+                        event: event.name(),
+                    }),
                 }
             }
         }
-    } else {
-        // Generic context: use C: Default bound
+    }
+}
+
+/// A validated `= 1` code as an unsuffixed `u32` literal.
+fn code_literal(code: &syn::LitInt) -> proc_macro2::Literal {
+    proc_macro2::Literal::u32_unsuffixed(code.base10_parse().unwrap_or_default())
+}
+
+/// Generate `is_<state>()` for each leaf state and `within_<superstate>()`
+/// for each superstate, on an enum with one variant per leaf state.
+///
+/// `pattern` builds the match pattern for a leaf's variant of `Self`.
+fn state_predicates(
+    machine: &StateMachine,
+    pattern: impl Fn(&syn::Ident) -> TokenStream2,
+) -> Vec<TokenStream2> {
+    let leaves = machine.states.iter().map(|state| {
+        let method_name = quote::format_ident!(
+            "is_{}",
+            to_snake_case(&state.to_string()),
+            span = state.span()
+        );
+        let doc = format!(" Whether this is the `{}` state.", state);
+        let pattern = pattern(state);
         quote! {
-            impl<C: ::core::default::Default> Default for #dynamic_name<C> {
-                fn default() -> Self {
-                    Self::new(C::default())
-                }
+            #[doc = #doc]
+            pub fn #method_name(&self) -> bool {
+                matches!(self, #pattern)
             }
         }
-    };
-
-    // Generate state data accessor methods
-    let state_data_accessors = if machine.state_storage.is_empty() {
-        quote! {}
-    } else {
-        let accessor_methods = machine.state_storage.iter().map(|spec| {
-            let state_name = &spec.state_name;
-            let data_ty = &spec.ty;
-            let field = &spec.field;
-            let state_snake = to_snake_case(&state_name.to_string());
-            let read_method = quote::format_ident!("{}_data", state_snake);
-            let write_method = quote::format_ident!("{}_data_mut", state_snake);
-            let set_method = quote::format_ident!("set_{}_data", state_snake);
-            let state_str = state_name.to_string();
-            let reachable_states = machine
+    });
+    let superstates = machine
+        .hierarchy
+        .all_superstates()
+        .into_iter()
+        .map(|superstate| {
+            let method_name = quote::format_ident!(
+                "within_{}",
+                to_snake_case(&superstate.to_string()),
+                span = superstate.span()
+            );
+            let doc = format!(" Whether this is one of the `{}` substates.", superstate);
+            let patterns = machine
                 .hierarchy
-                .expand_state(state_name, &machine.states);
+                .expand_state(&superstate, &machine.states)
+                .into_iter()
+                .map(|leaf| pattern(&leaf));
+            quote! {
+                #[doc = #doc]
+                pub fn #method_name(&self) -> bool {
+                    matches!(self, #(#patterns)|*)
+                }
+            }
+        });
+    leaves.chain(superstates).collect()
+}
 
-            if reachable_states.is_empty() {
-                quote! {}
-            } else {
-                let read_match_arms = reachable_states.iter().map(|reachable| {
-                    quote! { #any_state_name::#reachable(machine) => machine.#field.as_ref(), }
-                });
+/// Generate `{Name}State::definition()`, the machine's structure as a
+/// `MachineDefinition` for runtime introspection.
+///
+/// Superstate sources are expanded to their leaves and superstate targets
+/// resolved to their initial leaf, so the definition only refers to variants
+/// of the state enum. A `choose:` transition is listed once per target, and
+/// event-level `unless` guards are repeated on each transition since
+/// `EventDefinition` has no field for them.
+fn generate_definition(machine: &StateMachine) -> TokenStream2 {
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let name = machine.name.to_string();
+    let states = &machine.states;
+    let initial = machine.resolve_target(&machine.initial);
+    let async_mode = machine.async_mode;
 
-                let write_match_arms = reachable_states.iter().map(|reachable| {
-                    quote! { #any_state_name::#reachable(machine) => machine.#field.as_mut(), }
-                });
+    let describe =
+        |guards: &[GuardExpr]| -> Vec<String> { guards.iter().map(GuardExpr::describe).collect() };
+    let callback_names = |callbacks: &[CallbackSpec]| -> Vec<String> {
+        callbacks
+            .iter()
+            .map(|callback| callback.name.to_string())
+            .collect()
+    };
+    let names =
+        |idents: &[syn::Ident]| -> Vec<String> { idents.iter().map(ToString::to_string).collect() };
 
-                let set_match_arms = reachable_states.iter().map(|reachable| {
-                    quote! {
-                        #any_state_name::#reachable(machine) => {
-                            machine.#field = ::core::option::Option::Some(data);
-                            ::core::result::Result::Ok(())
-                        }
-                    }
-                });
+    let superstates = machine.hierarchy.superstates.iter().map(|info| {
+        let name = info.name.to_string();
+        let descendants = &info.descendants;
+        let initial = machine.resolve_target(&info.initial);
+        quote! {
+            state_machines::SuperstateDefinition {
+                name: #name,
+                descendants: &[#(#state_enum_name::#descendants),*],
+                initial: #state_enum_name::#initial,
+            }
+        }
+    });
 
-                quote! {
-                    /// Read access to state data when in the `#state_name` state.
-                    ///
-                    /// Returns `None` if not currently in this state or if the machine
-                    /// has been extracted via `into_{state}()` methods.
-                    pub fn #read_method(&self) -> ::core::option::Option<&#data_ty> {
-                        match self.inner.as_ref()? {
-                            #(#read_match_arms)*
-                            _ => ::core::option::Option::None,
-                        }
-                    }
+    let events = machine.events.iter().map(|event| {
+        let name = event.name.to_string();
+        let guards = describe(&event.guards);
+        let before = callback_names(&event.before);
+        let after = callback_names(&event.after);
+        let around = names(&event.around);
+        let payload = match &event.payload {
+            Some(ty) => quote! { ::core::option::Option::Some(stringify!(#ty)) },
+            None => quote! { ::core::option::Option::None },
+        };
 
-                    /// Mutable access to state data when in the `#state_name` state.
-                    ///
-                    /// Returns `None` if not currently in this state or if the machine
-                    /// has been extracted via `into_{state}()` methods.
-                    pub fn #write_method(&mut self) -> ::core::option::Option<&mut #data_ty> {
-                        match self.inner.as_mut()? {
-                            #(#write_match_arms)*
-                            _ => ::core::option::Option::None,
-                        }
-                    }
+        let transitions = event.transitions.iter().flat_map(|transition| {
+            let sources: Vec<syn::Ident> = transition
+                .sources
+                .iter()
+                .flat_map(|source| machine.hierarchy.expand_state(source, &machine.states))
+                .collect();
+            let targets = match &transition.choice {
+                Some(choice) => choice.targets.clone(),
+                None => vec![transition.target.clone()],
+            };
+            let guards = describe(&transition.guards);
+            let mut unless = describe(&event.unless);
+            unless.extend(describe(&transition.unless));
+            let before = callback_names(&transition.before);
+            let after = callback_names(&transition.after);
+            let around = names(&transition.around);
 
-                    /// Set state data when in the `#state_name` state.
-                    ///
-                    /// Returns an error if:
-                    /// - Not currently in the `#state_name` state
-                    /// - The machine has been extracted via `into_{state}()` methods
-                    pub fn #set_method(&mut self, data: #data_ty) -> Result<(), state_machines::DynamicError> {
-                        match self.inner.as_mut() {
-                            ::core::option::Option::Some(state) => match state {
-                                #(#set_match_arms)*
-                                other => Err(state_machines::DynamicError::wrong_state(
-                                    #state_str,
-                                    other.name(),
-                                    stringify!(#set_method),
-                                )),
-                            },
-                            ::core::option::Option::None => Err(state_machines::DynamicError::wrong_state(
-                                #state_str,
-                                "<extracted>",
-                                stringify!(#set_method),
-                            )),
+            targets
+                .iter()
+                .map(|target| {
+                    let target = machine.resolve_target(target);
+                    quote! {
+                        state_machines::TransitionDefinition {
+                            sources: &[#(#state_enum_name::#sources),*],
+                            target: #state_enum_name::#target,
+                            guards: &[#(#guards),*],
+                            unless: &[#(#unless),*],
+                            before: &[#(#before),*],
+                            after: &[#(#after),*],
+                            around: &[#(#around),*],
                         }
                     }
-                }
-            }
-        }).collect::<Vec<_>>();
+                })
+                .collect::<Vec<_>>()
+        });
 
         quote! {
-            #(#accessor_methods)*
-        }
-    };
-
-    Ok(quote! {
-        /// Dynamic wrapper for runtime event dispatch.
-        ///
-        /// This struct wraps the typestate machine and provides a `handle()` method
-        /// for dispatching events at runtime. Use this when events come from external
-        /// sources and can't be determined at compile time.
-        #[derive(Debug)]
-        pub struct #dynamic_name #struct_generics {
-            inner: ::core::option::Option<#any_state_name #any_state_generics>,
+            state_machines::EventDefinition {
+                name: #name,
+                guards: &[#(#guards),*],
+                before: &[#(#before),*],
+                after: &[#(#after),*],
+                around: &[#(#around),*],
+                payload: #payload,
+                transitions: &[#(#transitions),*],
+            }
         }
+    });
 
-        impl #impl_generics #dynamic_name #struct_generics {
-            /// Create a new dynamic machine in the initial state.
-            pub fn new(ctx: #ctx_param_ty) -> Self {
-                Self {
-                    inner: ::core::option::Option::Some(#any_state_name::#initial_state(#machine_name::new(ctx))),
+    // The same edges by index, for `const` assertions
+    let state_names = names(states);
+    let event_names: Vec<String> = machine
+        .events
+        .iter()
+        .map(|event| event.name.to_string())
+        .collect();
+    let index_of = |state: &syn::Ident| {
+        states
+            .iter()
+            .position(|leaf| leaf == state)
+            .expect("validated edges join leaf states")
+    };
+    let initial_index = index_of(&initial);
+    let mut edges = Vec::new();
+    for (event_index, event) in machine.events.iter().enumerate() {
+        for transition in &event.transitions {
+            let targets = match &transition.choice {
+                Some(choice) => choice.targets.clone(),
+                None => vec![transition.target.clone()],
+            };
+            for source in transition
+                .sources
+                .iter()
+                .flat_map(|source| machine.hierarchy.expand_state(source, &machine.states))
+            {
+                for target in &targets {
+                    let from = index_of(&source);
+                    let to = index_of(&machine.resolve_target(target));
+                    edges.push(quote! {
+                        state_machines::TableEdge { event: #event_index, from: #from, to: #to }
+                    });
                 }
             }
+        }
+    }
 
-            /// Dispatch an event to the state machine at runtime.
-            ///
-            /// Returns an error if:
-            /// - The event is not valid from the current state
-            /// - A guard callback fails
-            /// - An action callback fails
-            #handle_sig {
-                // Take ownership of inner state temporarily
-                let current = self.inner.take().expect("dynamic machine in invalid state");
+    quote! {
+        /// The machine's transitions as names and indices, usable in `const`
+        /// items, e.g. `const _: () = assert!(TABLE.has_transition("A", "go"));`.
+        pub const TABLE: state_machines::TransitionTable = state_machines::TransitionTable {
+            states: &[#(#state_names),*],
+            events: &[#(#event_names),*],
+            initial: #initial_index,
+            edges: &[#(#edges),*],
+        };
 
-                let new_state = match (current, event) {
-                    #(#match_arms)*
-                    #catch_all
+        /// The machine's states, superstates, events, and transitions.
+        ///
+        /// Superstates are expanded to their leaf states. With the `alloc`
+        /// feature, the definition also answers graph queries such as
+        /// `shortest_event_sequence()` and `cycles()`.
+        pub fn definition() -> &'static state_machines::MachineDefinition<#state_enum_name> {
+            static DEFINITION: state_machines::MachineDefinition<#state_enum_name> =
+                state_machines::MachineDefinition {
+                    name: #name,
+                    states: &[#(#state_enum_name::#states),*],
+                    initial: #state_enum_name::#initial,
+                    async_mode: #async_mode,
+                    superstates: &[#(#superstates),*],
+                    events: &[#(#events),*],
                 };
-
-                self.inner = ::core::option::Option::Some(new_state);
-                Ok(())
-            }
-
-            /// Get the name of the current state.
-            pub fn current_state(&self) -> &'static str {
-                self.inner.as_ref()
-                    .expect("dynamic machine in invalid state")
-                    .name()
-            }
-
-            #state_data_accessors
+            &DEFINITION
         }
-
-        #default_impl
-    })
+    }
 }
 
-/// Generate conversion methods between typestate and dynamic modes.
+/// Generate the AnyState enum that wraps all typed state machines.
 ///
 /// Example output:
 /// ```ignore
-/// impl<S> FlightController<S> {
-///     pub fn into_dynamic(self) -> DynamicFlightController { ... }
-/// }
-///
-/// impl DynamicFlightController {
-///     pub fn into_docked(self) -> Result<FlightController<Docked>, Self> { ... }
-///     pub fn into_in_flight(self) -> Result<FlightController<InFlight>, Self> { ... }
+/// pub enum AnyFlightState {
+///     Docked(FlightController<Docked>),
+///     InFlight(FlightController<InFlight>),
+///     Landed(FlightController<Landed>),
 /// }
 /// ```
-fn generate_conversions(machine: &StateMachine) -> Result<TokenStream2> {
+fn generate_any_state_enum(machine: &StateMachine) -> Result<TokenStream2> {
+    let vis = machine.item_visibility();
     let machine_name = &machine.name;
-    let dynamic_name = quote::format_ident!("Dynamic{}", machine_name);
     let any_state_name = quote::format_ident!("Any{}State", machine_name);
 
-    // Determine context type for conversions
-    let (impl_generics, dynamic_generics) = if machine.context.is_some() {
-        // Concrete context
+    // Generate enum variants for each state
+    let variants = machine.states.iter().map(|state| {
+        let typed = machine.machine_type(state);
+        quote! { #state(#typed) }
+    });
+
+    let clone_bounds = machine.data_bounds(true, quote! { ::core::clone::Clone });
+    let clone_arms = machine.states.iter().map(|state| {
+        quote! { Self::#state(m) => Self::#state(::core::clone::Clone::clone(m)) }
+    });
+
+    // Generate match arms for the name() method
+    let name_arms = machine.states.iter().map(|state| {
+        let state_str = state.to_string();
+        quote! { Self::#state(_) => #state_str }
+    });
+
+    // Generate match arms for the state() method
+    let state_enum_name = quote::format_ident!("{}State", machine_name);
+    let state_arms = machine
+        .states
+        .iter()
+        .map(|state| match machine.state_storage_for(state) {
+            Some(spec) if machine.data_states => {
+                let field = &spec.field;
+                quote! {
+                    Self::#state(m) => #state_enum_name::#state(
+                        ::core::clone::Clone::clone(&m.#field)
+                            .expect("state data is set when the state is entered")
+                    )
+                }
+            }
+            _ => quote! { Self::#state(_) => #state_enum_name::#state },
+        });
+
+    // Generic over the context (unless concrete) and user parameters
+    let impl_generics = machine.impl_generics();
+    let enum_generics = machine.type_generics();
+
+    // Generate From<Machine<.., State>> impls so typed machines can be stored directly
+    let from_impls = machine.states.iter().map(|state| {
+        let typed = machine.machine_type(state);
+        quote! {
+            impl #impl_generics ::core::convert::From<#typed> for #any_state_name #enum_generics {
+                fn from(machine: #typed) -> Self {
+                    Self::#state(machine)
+                }
+            }
+        }
+    });
+
+    // Generate match arms for dispatch(): one per (source state, event) edge.
+    // Use the PascalCase variant for matching and the snake_case typestate method for calling.
+    let event_name = quote::format_ident!("{}Event", machine_name);
+    let is_async = machine.async_mode;
+    let mut dispatch_arms = Vec::new();
+    let mut table_rows = Vec::new();
+
+    for event in &machine.events {
+        let event_pascal =
+            syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+        let event_method = to_snake_case_ident(&event.name);
+
+        for source_state in &machine.states {
+            if let Some(edge) = machine.edge_for(source_state, &event.name) {
+                let target_state = &edge.target;
+
+                // Compact mode: callback-free edges become table rows instead of match arms
+                let moves_payload = matches!(
+                    machine.data_init(target_state, None, edge.payload.as_ref()),
+                    DataInit::Payload
+                );
+                if machine.compact_codegen
+                    && edge.is_plain()
+                    && !moves_payload
+                    && machine.event_transform(&event.name).is_none()
+                {
+                    let event_str = event.name.to_string();
+                    table_rows.push(quote! {
+                        (#state_enum_name::#source_state, #event_str, #state_enum_name::#target_state)
+                    });
+                    continue;
+                }
+
+                // `handle()` runs `after_commit` callbacks itself, once the
+                // new state is stored
+                let event_method = if edge.after_commit.is_empty() {
+                    event_method.clone()
+                } else {
+                    quote::format_ident!("__{}_uncommitted", event_method)
+                };
+                let (pattern, call) = if event.payload.is_some() {
+                    (
+                        quote! { #event_name::#event_pascal(payload) },
+                        quote! { m.#event_method(payload) },
+                    )
+                } else {
+                    (
+                        quote! { #event_name::#event_pascal },
+                        quote! { m.#event_method() },
+                    )
+                };
+                let call = if is_async {
+                    quote! { #call.await }
+                } else {
+                    call
+                };
+
+                // Choice edges return the event's Outcome enum; unwrap each
+                // variant into the AnyState variant for its leaf state
+                let ok_arms = match &edge.choice {
+                    Some(choice) => {
+                        let (_, outcome_enum) = machine.choice_enum_names(&event.name);
+                        let arms = choice.targets.iter().map(|target| {
+                            let leaf = machine.resolve_target(target);
+                            quote! {
+                                Ok(#outcome_enum::#target(new_machine)) => Ok(Self::#leaf(new_machine)),
+                            }
+                        });
+                        quote! { #( #arms )* }
+                    }
+                    None => quote! {
+                        Ok(new_machine) => Ok(Self::#target_state(new_machine)),
+                    },
+                };
+
+                dispatch_arms.push(quote! {
+                    (Self::#source_state(m), #pattern) => match #call {
+                        #ok_arms
+                        Err((old_machine, err)) => Err((
+                            Self::#source_state(old_machine),
+                            state_machines::DynamicError::from_guard_error(err),
+                        )),
+                    },
+                });
+            }
+        }
+    }
+
+    // Ignored events succeed without leaving the current state
+    for source_state in &machine.states {
+        for event in machine.ignored_events(source_state) {
+            let event_pascal = syn::Ident::new(&to_pascal_case(&event.to_string()), event.span());
+            let has_payload = machine
+                .events
+                .iter()
+                .any(|declared| &declared.name == event && declared.payload.is_some());
+            let pattern = if has_payload {
+                quote! { #event_name::#event_pascal(_) }
+            } else {
+                quote! { #event_name::#event_pascal }
+            };
+            dispatch_arms.push(quote! {
+                (Self::#source_state(m), #pattern) => Ok(Self::#source_state(m)),
+            });
+        }
+    }
+
+    // can_handle(): which (state, event) pairs have a transition or are ignored
+    let mut handled_arms = Vec::new();
+    for source_state in &machine.states {
+        let ignored = machine.ignored_events(source_state);
+        for event in &machine.events {
+            let has_edge = machine.edge_for(source_state, &event.name).is_some();
+            if !has_edge && !ignored.contains(&&event.name) {
+                continue;
+            }
+            let event_pascal =
+                syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+            let pattern = if event.payload.is_some() {
+                quote! { #event_name::#event_pascal(_) }
+            } else {
+                quote! { #event_name::#event_pascal }
+            };
+            handled_arms.push(quote! { (Self::#source_state(_), #pattern) => true, });
+        }
+    }
+
+    // Invalid transitions never reach a typed method, so the fallback arm
+    // reports them to the `on_error` handler itself
+    let handler = machine
+        .on_error
+        .as_ref()
+        .and_then(|on_error| on_error.handler.as_ref());
+    let await_token = is_async.then(|| quote! { .await });
+    let report_invalid = handler.map(|_| {
+        quote! { state.report_dispatch_failure(&err) #await_token; }
+    });
+    let report_method = handler.map(|handler| {
+        let report_arms = machine.states.iter().map(|state| {
+            quote! {
+                Self::#state(m) => m.#handler(state_machines::EventFailure::Dispatch(err)) #await_token,
+            }
+        });
+        let sig = if is_async {
+            quote! { async fn report_dispatch_failure(&self, err: &state_machines::DynamicError) }
+        } else {
+            quote! { fn report_dispatch_failure(&self, err: &state_machines::DynamicError) }
+        };
+        quote! {
+            /// Pass a dispatch failure to the machine's `on_error` handler.
+            #sig {
+                match self {
+                    #(#report_arms)*
+                }
+            }
+        }
+    });
+
+    // Take the context out of the current typed machine, or wrap one around it
+    // in any state; used by the compact table and by `restore()`
+    let into_context_arms = machine.states.iter().map(|state| {
+        quote! { Self::#state(m) => m.ctx }
+    });
+    let enter_arms = machine.states.iter().map(|target| {
+        let data_state = machine.data_states && machine.state_storage_for(target).is_some();
+        let storage_inits = machine.state_storage.iter().map(|spec| {
+            let field = &spec.field;
+            let ty = &spec.ty;
+            if spec.state_name != *target {
+                quote! { #field: ::core::option::Option::None }
+            } else if data_state {
+                quote! { #field: ::core::option::Option::Some(data) }
+            } else {
+                quote_spanned! {ty.span()=>
+                    #field: ::core::option::Option::Some(<#ty as state_machines::StateData>::initial())
+                }
+            }
+        });
+        let pattern = if data_state {
+            quote! { #state_enum_name::#target(data) }
+        } else {
+            quote! { #state_enum_name::#target }
+        };
+        let id_init = machine.id_field_init(quote! { ::core::option::Option::None });
+        quote! {
+            #pattern => Self::#target(#machine_name {
+                ctx,
+                _state: ::core::marker::PhantomData,
+                #(#storage_inits,)*
+                #id_init
+            }),
+        }
+    });
+    let ctx_ty = machine.ctx_type();
+
+    // With `identity: true` the id moves along when re-entering a state
+    let id_items = machine.identity.then(|| {
+        let id_arms = machine.states.iter().map(|state| {
+            quote! { Self::#state(m) => m.id }
+        });
+        let set_id_arms = machine.states.iter().map(|state| {
+            quote! { Self::#state(m) => m.id = id }
+        });
+        quote! {
+            /// The current typed machine's id, if one was set with `with_id()`.
+            pub fn id(&self) -> ::core::option::Option<state_machines::MachineId> {
+                match self {
+                    #(#id_arms,)*
+                }
+            }
+
+            fn set_id(&mut self, id: ::core::option::Option<state_machines::MachineId>) {
+                match self {
+                    #(#set_id_arms,)*
+                }
+            }
+        }
+    });
+    let reenter_body = if machine.identity {
+        quote! {
+            let id = self.id();
+            let mut next = Self::enter(self.into_context(), target);
+            next.set_id(id);
+            next
+        }
+    } else {
+        quote! { Self::enter(self.into_context(), target) }
+    };
+    let context_items = quote! {
+        /// Take the context out of whichever typed machine is current.
+        fn into_context(self) -> #ctx_ty {
+            match self {
+                #(#into_context_arms,)*
+            }
+        }
+
+        /// Build a typed machine in `target` around an existing context.
+        fn enter(ctx: #ctx_ty, target: #state_enum_name) -> Self {
+            match target {
+                #(#enter_arms)*
+            }
+        }
+
+        /// Move the current context (and id) into a typed machine in `target`.
+        fn reenter(self, target: #state_enum_name) -> Self {
+            #reenter_body
+        }
+
+        #id_items
+    };
+
+    // Compact mode: a static (from, event, to) table and a fallback arm that
+    // interprets it, entering the target state around the bare context.
+    let (compact_items, fallback_arm) = if machine.compact_codegen {
+        // Table rows skip the typed methods, so report their state changes here
+        let table_trace = cfg!(feature = "trace").then(|| {
+            let machine_name = machine_name.to_string();
+            let id = if machine.identity {
+                quote! { state.id() }
+            } else {
+                quote! { ::core::option::Option::None }
+            };
+            quote! {
+                ::state_machines::__private::trace(
+                    #machine_name,
+                    #id,
+                    event_name,
+                    ::state_machines::trace::TraceStep::Transition {
+                        from: from.name(),
+                        to: target.name(),
+                    },
+                );
+            }
+        });
+
+        let items = quote! {
+            /// Transitions without guards or callbacks, as `(from, event, to)` rows.
+            const TRANSITION_TABLE: &'static [(#state_enum_name, &'static str, #state_enum_name)] = &[
+                #(#table_rows,)*
+            ];
+        };
+
+        let arm = quote! {
+            (state, event) => {
+                let from = state.state();
+                let event_name = event.name();
+                match Self::TRANSITION_TABLE
+                    .iter()
+                    .find(|(source, name, _)| *source == from && *name == event_name)
+                {
+                    ::core::option::Option::Some(&(_, _, target)) => {
+                        #table_trace
+                        Ok(state.reenter(target))
+                    }
+                    ::core::option::Option::None => {
+                        let err = state_machines::DynamicError::invalid_transition(from.name(), event_name);
+                        #report_invalid
+                        Err((state, err))
+                    }
+                }
+            }
+        };
+        (items, arm)
+    } else {
+        let arm = quote! {
+            (state, event) => {
+                let err = state_machines::DynamicError::invalid_transition(state.name(), event.name());
+                #report_invalid
+                Err((state, err))
+            }
+        };
+        (quote! {}, arm)
+    };
+
+    let dry_run = generate_dry_run(machine);
+    let after_commit = generate_after_commit(machine);
+    let predicates = state_predicates(machine, |state| quote! { Self::#state(_) });
+
+    let event_ty = machine.event_type(false);
+    let event_lifetimes = machine.method_lifetimes(None);
+    let dispatch_sig = if is_async {
+        quote! {
+            pub async fn dispatch #event_lifetimes(self, event: #event_ty) -> ::core::result::Result<Self, (Self, state_machines::DynamicError)>
+        }
+    } else {
+        quote! {
+            pub fn dispatch #event_lifetimes(self, event: #event_ty) -> ::core::result::Result<Self, (Self, state_machines::DynamicError)>
+        }
+    };
+
+    Ok(quote! {
+        /// Enum wrapping all typed state machines.
+        ///
+        /// This enables runtime polymorphism over different states while
+        /// preserving the compile-time safety of the typestate pattern.
+        /// Obtain one with `into_typed()` and `match` on it to recover the
+        /// typed machine for whichever state the dynamic wrapper was in.
+        #[derive(Debug)]
+        #[allow(clippy::enum_variant_names)]
+        #vis enum #any_state_name #impl_generics {
+            #(#variants,)*
+        }
+
+        impl #impl_generics ::core::clone::Clone for #any_state_name #enum_generics #clone_bounds {
+            fn clone(&self) -> Self {
+                match self {
+                    #(#clone_arms,)*
+                }
+            }
+        }
+
+        impl #impl_generics #any_state_name #enum_generics {
+            /// Get the name of the current state.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms,)*
+                }
+            }
+
+            /// Get the current state as a state enum value.
+            pub fn state(&self) -> #state_enum_name {
+                match self {
+                    #(#state_arms,)*
+                }
+            }
+
+            #(#predicates)*
+
+            /// Whether `event` has a transition (or is ignored) in the current state.
+            ///
+            /// Guards aren't evaluated, so `dispatch()` can still reject it.
+            pub fn can_handle #event_lifetimes(&self, event: &#event_ty) -> bool {
+                match (self, event) {
+                    #(#handled_arms)*
+                    #[allow(unreachable_patterns)]
+                    _ => false,
+                }
+            }
+
+            /// Dispatch an event by calling the matching typed transition method.
+            ///
+            /// Returns the machine in its new state, or the unchanged machine
+            /// together with the error if the event is invalid from the current
+            /// state or a guard/callback rejected it.
+            ///
+            /// `after_commit` callbacks don't run here; the dynamic wrapper's
+            /// `handle()` runs them once it has stored the new state.
+            #[allow(clippy::result_large_err)]
+            #dispatch_sig {
+                match (self, event) {
+                    #(#dispatch_arms)*
+                    #fallback_arm
+                }
+            }
+
+            #context_items
+
+            #compact_items
+
+            #report_method
+
+            #dry_run
+
+            #after_commit
+        }
+
+        #(#from_impls)*
+    })
+}
+
+/// Generate `__after_commit()` on the AnyState enum, which runs the
+/// `after_commit` callbacks of the transition that led to the current state.
+///
+/// Empty unless some transition has them.
+fn generate_after_commit(machine: &StateMachine) -> TokenStream2 {
+    let mut arms = Vec::new();
+    for event in &machine.events {
+        let event_str = event.name.to_string();
+        for source in &machine.states {
+            let Some(edge) = machine.edge_for(source, &event.name) else {
+                continue;
+            };
+            if edge.after_commit.is_empty() {
+                continue;
+            }
+            let targets = match &edge.choice {
+                Some(choice) => choice
+                    .targets
+                    .iter()
+                    .map(|target| machine.resolve_target(target))
+                    .collect(),
+                None => vec![edge.target.clone()],
+            };
+            let source_str = source.to_string();
+            let calls = super::typestate::after_commit_calls(
+                machine,
+                &edge.after_commit,
+                quote! { new_machine },
+            );
+            for target in targets {
+                arms.push(quote! {
+                    (Self::#target(new_machine), #source_str, #event_str) => { #calls }
+                });
+            }
+        }
+    }
+    if arms.is_empty() {
+        return quote! {};
+    }
+
+    let asyncness = machine.async_mode.then(|| quote! { async });
+    quote! {
+        #[doc(hidden)]
+        #asyncness fn __after_commit(&self, from: &'static str, event: &'static str) {
+            #[allow(unreachable_patterns)]
+            match (self, from, event) {
+                #( #arms )*
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Generate `dry_run()` on the AnyState enum for `simulate()`.
+///
+/// One arm per edge hands a clone of the payload to the typed machine's
+/// hidden `__{event}_dry_run` method. That runs the steps of the transition
+/// method that decide the outcome, so guards, `choose` methods, and their
+/// timeouts accept or reject the event as they would in `handle()`.
+fn generate_dry_run(machine: &StateMachine) -> TokenStream2 {
+    let event_name = quote::format_ident!("{}Event", machine.name);
+    let error = quote! { state_machines::DynamicError };
+    let await_token = machine.async_mode.then(|| quote! { .await });
+
+    let mut arms = Vec::new();
+    for event in &machine.events {
+        let event_pascal =
+            syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+        let dry_run = quote::format_ident!("__{}_dry_run", to_snake_case_ident(&event.name));
+        let (pattern, call) = if event.payload.is_some() {
+            (
+                quote! { #event_name::#event_pascal(payload) },
+                quote! { m.#dry_run(::core::clone::Clone::clone(payload)) #await_token },
+            )
+        } else {
+            (
+                quote! { #event_name::#event_pascal },
+                quote! { m.#dry_run() #await_token },
+            )
+        };
+
+        for source_state in &machine.states {
+            if let Some(edge) = machine.edge_for(source_state, &event.name) {
+                let ok_arms = match &edge.choice {
+                    Some(choice) => {
+                        let (_, outcome_enum) = machine.choice_enum_names(&event.name);
+                        let arms = choice.targets.iter().map(|target| {
+                            let leaf = machine.resolve_target(target);
+                            quote! {
+                                Ok(#outcome_enum::#target(new_machine)) => Ok((Self::#leaf(new_machine), true)),
+                            }
+                        });
+                        quote! { #( #arms )* }
+                    }
+                    None => {
+                        let target_state = &edge.target;
+                        quote! {
+                            Ok(new_machine) => Ok((Self::#target_state(new_machine), true)),
+                        }
+                    }
+                };
+                arms.push(quote! {
+                    (Self::#source_state(m), #pattern) => match #call {
+                        #ok_arms
+                        Err((old_machine, err)) => Err((
+                            Self::#source_state(old_machine),
+                            #error::from_guard_error(err),
+                        )),
+                    },
+                });
+            }
+        }
+    }
+
+    for source_state in &machine.states {
+        for event in machine.ignored_events(source_state) {
+            let event_pascal = syn::Ident::new(&to_pascal_case(&event.to_string()), event.span());
+            let has_payload = machine
+                .events
+                .iter()
+                .any(|declared| &declared.name == event && declared.payload.is_some());
+            let pattern = if has_payload {
+                quote! { #event_name::#event_pascal(_) }
+            } else {
+                quote! { #event_name::#event_pascal }
+            };
+            arms.push(quote! {
+                (Self::#source_state(m), #pattern) => Ok((Self::#source_state(m), false)),
+            });
+        }
+    }
+
+    // Events without a transition follow the `unhandled` policy, except that
+    // a dry run reports instead of panicking and doesn't call the callback
+    let fallback = match &machine.unhandled {
+        UnhandledPolicy::Error | UnhandledPolicy::Panic => quote! {
+            (state, event) => {
+                let err = #error::invalid_transition(state.name(), event.name());
+                Err((state, err))
+            }
+        },
+        UnhandledPolicy::Ignore | UnhandledPolicy::Callback(_) => quote! {
+            (state, _) => Ok((state, false)),
+        },
+    };
+
+    // Payloads are cloned for the dry run; the `for` keeps the bound from
+    // being rejected as trivially false for a concrete type that isn't Clone
+    let payload_tys = machine
+        .events
+        .iter()
+        .filter_map(|event| event.payload.as_ref());
+    let event_ty = machine.event_type(false);
+    let event_lifetimes = machine.method_lifetimes(None);
+    let asyncness = machine.async_mode.then(|| quote! { async });
+    quote! {
+        state_machines::__private::with_alloc! {
+            /// Dry-run `event`: the machine it would leave, and whether it
+            /// would change state. Side-effecting steps of the transition
+            /// don't run; see `simulate()`.
+            #[allow(unused_variables, unreachable_patterns)]
+            #asyncness fn dry_run #event_lifetimes(
+                self,
+                event: &#event_ty,
+            ) -> ::core::result::Result<(Self, bool), (Self, #error)>
+            where
+                #( for<'__b> #payload_tys: ::core::clone::Clone, )*
+            {
+                match (self, event) {
+                    #( #arms )*
+                    #fallback
+                }
+            }
+        }
+    }
+}
+
+/// Generate the DynamicMachine struct with handle() method.
+///
+/// Example output:
+/// ```ignore
+/// pub struct DynamicFlightController {
+///     inner: AnyFlightState,
+/// }
+///
+/// impl DynamicFlightController {
+///     pub fn handle(&mut self, event: FlightEvent) -> Result<(), DynamicError> {
+///         // Runtime dispatch logic
+///     }
+/// }
+/// ```
+///
+/// Each optional part of the wrapper (the state cell, watchdog, history,
+/// stats, `after_commit`, and `on_error` recovery) is built by its own
+/// helper, as a `WrapperFeature` of a field and the steps it adds to
+/// `handle()`.
+fn generate_dynamic_machine(machine: &StateMachine) -> Result<TokenStream2> {
+    let vis = machine.item_visibility();
+    let machine_name = &machine.name;
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine_name);
+    let any_state_name = quote::format_ident!("Any{}State", machine_name);
+    let initial_state = &machine.initial;
+    let is_async = machine.async_mode;
+
+    let state_enum_name = quote::format_ident!("{}State", machine_name);
+    let handle_output = handle_output(machine);
+    let event_ty = machine.event_type(false);
+    let event_lifetimes = machine.method_lifetimes(None);
+    let (handle_sig, handle_or_ignore_sig) = if is_async {
+        (
+            quote! { pub async fn handle #event_lifetimes(&mut self, event: #event_ty) -> Result<#handle_output, state_machines::DynamicError> },
+            quote! { pub async fn handle_or_ignore #event_lifetimes(&mut self, event: #event_ty) -> Result<#handle_output, state_machines::DynamicError> },
+        )
+    } else {
+        (
+            quote! { pub fn handle #event_lifetimes(&mut self, event: #event_ty) -> Result<#handle_output, state_machines::DynamicError> },
+            quote! { pub fn handle_or_ignore #event_lifetimes(&mut self, event: #event_ty) -> Result<#handle_output, state_machines::DynamicError> },
+        )
+    };
+
+    let WrapperFeature {
+        field: state_cell_field,
+        init: state_cell_init,
+        record: publish_state,
+        methods: share_state_method,
+        ..
+    } = state_cell_feature(machine);
+    let WrapperFeature {
+        field: entered_field,
+        init: entered_init,
+        capture: capture_from_name,
+        record: enter_state,
+        methods: watchdog_methods,
+        ..
+    } = watchdog_feature(machine);
+    let WrapperFeature {
+        field: history_field,
+        init: history_init,
+        capture: capture_history,
+        record: record_history,
+        methods: history_methods,
+        ..
+    } = history_feature(machine);
+    let WrapperFeature {
+        field: stats_field,
+        init: stats_init,
+        capture: capture_stats,
+        record: record_stats,
+        reject: record_stats_rejection,
+        methods: stats_methods,
+    } = stats_feature(machine);
+    let CommitSteps {
+        field: commit_field,
+        init: commit_init,
+        take_hold,
+        capture: capture_commit,
+        stage: stage_commit,
+        run: run_commit,
+        discard: discard_commit,
+        methods: commit_methods,
+    } = commit_steps(machine);
+    let WrapperFeature {
+        field: failures_field,
+        init: failures_init,
+        record: reset_failures,
+        reject: recover_call,
+        ..
+    } = recovery_feature(
+        machine,
+        &publish_state,
+        &enter_state,
+        !stage_commit.is_empty(),
+    );
+
+    // A recovered machine enters its state afresh
+    let restart_clock = machine.watchdog.then(|| {
+        let start_clock = start_clock(machine);
+        quote! { self.entered = #start_clock; }
+    });
+
+    let (wrapper_id_items, dynamic_machine_id) = identity_items(machine);
+    let (record_start, record_finish, handle_ok) = transition_record_steps(machine);
+    let dispatch_call = if is_async {
+        quote! { current.dispatch(event).await }
+    } else {
+        quote! { current.dispatch(event) }
+    };
+    let unhandled_check = unhandled_check(machine, &handle_ok);
+    let ignore_record = ignore_record(machine);
+    let handle_call = if is_async {
+        quote! { self.handle(event).await }
+    } else {
+        quote! { self.handle(event) }
+    };
+
+    // Generic over the context (unless concrete) and user parameters
+    let impl_generics = machine.impl_generics();
+    let struct_generics = machine.type_generics();
+    let ctx_param_ty = machine.ctx_type();
+
+    // Default impl whenever the context has one; we can't check that at
+    // macro time, so it's conditional on a where clause (with an unused
+    // binder, or a concrete context without `Default` fails to compile)
+    let default_impl = quote! {
+        impl #impl_generics Default for #dynamic_name #struct_generics
+        where
+            for<'__b> #ctx_param_ty: ::core::default::Default,
+        {
+            fn default() -> Self {
+                Self::new(<#ctx_param_ty as ::core::default::Default>::default())
+            }
+        }
+    };
+
+    // Other machines' `forward:` transitions hand events over through
+    // `handle()`, which can't be awaited there
+    let forward_impl = (!is_async && machine.event_lifetimes().is_empty()).then(|| {
+        quote! {
+            impl #impl_generics state_machines::Forward<#event_ty> for #dynamic_name #struct_generics {
+                fn forward(&mut self, event: #event_ty) {
+                    let _ = self.handle(event);
+                }
+            }
+        }
+    });
+
+    let dynamic_machine_impl = dynamic_machine_impl(machine, dynamic_machine_id);
+    let introspection_methods = introspection_methods(machine);
+    let state_predicates = wrapper_predicates(machine);
+    let state_data_accessors = state_data_accessors(machine);
+
+    // A clone starts out detached from any shared `AtomicState`, so what-if
+    // runs don't publish their states; the other bookkeeping is copied
+    let clone_bookkeeping = [
+        machine
+            .atomic_state
+            .then(|| quote! { state_cell: ::core::option::Option::None, }),
+        (!failures_field.is_empty()).then(|| quote! { failures: self.failures, }),
+        machine.watchdog.then(|| quote! { entered: self.entered, }),
+        machine
+            .history
+            .is_some()
+            .then(|| quote! { history: ::core::clone::Clone::clone(&self.history), }),
+        counts_stats(machine).then(|| quote! { stats: self.stats, }),
+        // Callbacks held for the original aren't the clone's to run
+        (!commit_init.is_empty()).then(|| commit_init.clone()),
+    ];
+    let clone_bounds = machine.data_bounds(true, quote! { ::core::clone::Clone });
+
+    // Equal in the same state with the same state data (each typestate
+    // machine carries every storage field, the inactive ones empty)
+    let eq_bounds = machine.data_bounds(false, quote! { ::core::cmp::PartialEq });
+    let storage_fields: Vec<_> = machine
+        .state_storage
+        .iter()
+        .map(|spec| &spec.field)
+        .collect();
+    let eq_arms = machine.states.iter().map(|state| {
+        if storage_fields.is_empty() {
+            quote! {
+                (::core::option::Option::Some(#any_state_name::#state(_)),
+                 ::core::option::Option::Some(#any_state_name::#state(_))) => true
+            }
+        } else {
+            quote! {
+                (::core::option::Option::Some(#any_state_name::#state(left)),
+                 ::core::option::Option::Some(#any_state_name::#state(right))) => {
+                    #(left.#storage_fields == right.#storage_fields)&&*
+                }
+            }
+        }
+    });
+
+    let simulate_impl = simulate_impl(machine);
+    let handle_all_impl = handle_all_impl(machine);
+
+    Ok(quote! {
+        /// Dynamic wrapper for runtime event dispatch.
+        ///
+        /// This struct wraps the typestate machine and provides a `handle()` method
+        /// for dispatching events at runtime. Use this when events come from external
+        /// sources and can't be determined at compile time.
+        #[derive(Debug)]
+        #vis struct #dynamic_name #impl_generics {
+            inner: ::core::option::Option<#any_state_name #struct_generics>,
+            version: u64,
+            #state_cell_field
+            #failures_field
+            #entered_field
+            #history_field
+            #stats_field
+            #commit_field
+        }
+
+        /// Clones the machine with its context and state data, e.g. to try
+        /// an event sequence without touching the original. A clone doesn't
+        /// mirror its state into the original's `share_state()` cell.
+        impl #impl_generics ::core::clone::Clone for #dynamic_name #struct_generics #clone_bounds {
+            fn clone(&self) -> Self {
+                Self {
+                    inner: ::core::clone::Clone::clone(&self.inner),
+                    version: self.version,
+                    #(#clone_bookkeeping)*
+                }
+            }
+        }
+
+        /// Machines are equal in the same state with equal state data. The
+        /// context, version, and history aren't compared.
+        impl #impl_generics ::core::cmp::PartialEq for #dynamic_name #struct_generics #eq_bounds {
+            fn eq(&self, other: &Self) -> bool {
+                match (&self.inner, &other.inner) {
+                    #(#eq_arms,)*
+                    (::core::option::Option::None, ::core::option::Option::None) => true,
+                    _ => false,
+                }
+            }
+        }
+
+        #simulate_impl
+
+        #handle_all_impl
+
+        impl #impl_generics #dynamic_name #struct_generics {
+            /// Create a new dynamic machine in the initial state.
+            pub fn new(ctx: #ctx_param_ty) -> Self {
+                Self {
+                    inner: ::core::option::Option::Some(#any_state_name::#initial_state(#machine_name::new(ctx))),
+                    version: 0,
+                    #state_cell_init
+                    #failures_init
+                    #entered_init
+                    #history_init
+                    #stats_init
+                    #commit_init
+                }
+            }
+
+            /// Rebuild a machine in `state` around `ctx`, e.g. from a stored
+            /// state name.
+            ///
+            /// No callbacks run. State data starts at its default, or with
+            /// `data_states: true`, takes the data carried by `state`.
+            pub fn restore(ctx: #ctx_param_ty, state: #state_enum_name) -> Self {
+                Self {
+                    inner: ::core::option::Option::Some(#any_state_name::enter(ctx, state)),
+                    version: 0,
+                    #state_cell_init
+                    #failures_init
+                    #entered_init
+                    #history_init
+                    #stats_init
+                    #commit_init
+                }
+            }
+
+            /// Move the machine to `state` without dispatching an event,
+            /// keeping its context, e.g. to undo a transition whose side
+            /// effects were rolled back elsewhere.
+            ///
+            /// No callbacks run, state data is reset as in `restore()`, and
+            /// the version is left as it is.
+            ///
+            /// # Panics
+            ///
+            /// If a previous dispatch never completed.
+            pub fn restore_state(&mut self, state: #state_enum_name) {
+                let current = self.inner.take().expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`");
+                #capture_from_name
+                let new_state = current.reenter(state);
+                #publish_state
+                #enter_state
+                #discard_commit
+                self.inner = ::core::option::Option::Some(new_state);
+            }
+
+            /// Whether a dispatch never completed and took the machine's
+            /// state with it, e.g. a callback panicked or the `handle()`
+            /// future was dropped part-way.
+            ///
+            /// A poisoned machine fails every event with
+            /// `DynamicError::ReentrantDispatch`, and the methods that read its
+            /// state panic, until `recover()` is called.
+            pub fn is_poisoned(&self) -> bool {
+                self.inner.is_none()
+            }
+
+            /// Put a poisoned machine back in `state` around `ctx`, as
+            /// `restore()` would, keeping its version and history.
+            ///
+            /// The abandoned dispatch dropped the context, so it's passed in
+            /// again. No callbacks run. Returns `false` without changing
+            /// anything if the machine isn't poisoned.
+            pub fn recover(&mut self, ctx: #ctx_param_ty, state: #state_enum_name) -> bool {
+                if self.inner.is_some() {
+                    return false;
+                }
+                let new_state = #any_state_name::enter(ctx, state);
+                #publish_state
+                #restart_clock
+                #discard_commit
+                self.inner = ::core::option::Option::Some(new_state);
+                true
+            }
+
+            /// Rebuild a machine from a `snapshot()` around `ctx`, at the
+            /// snapshot's version.
+            ///
+            /// No callbacks run, as with `restore()`.
+            pub fn from_snapshot(
+                ctx: #ctx_param_ty,
+                snapshot: state_machines::Snapshot<#state_enum_name>,
+            ) -> Self {
+                let mut machine = Self::restore(ctx, snapshot.state);
+                machine.version = snapshot.version;
+                machine
+            }
+
+            /// How many transitions the machine has made: every successful
+            /// `handle()` adds one, including self-transitions, while ignored
+            /// and rejected events don't.
+            ///
+            /// Starts at 0 for `new()`, `restore()`, and `into_dynamic()`.
+            pub fn version(&self) -> u64 {
+                self.version
+            }
+
+            /// The current state and version, e.g. to persist.
+            pub fn snapshot(&self) -> state_machines::Snapshot<#state_enum_name> {
+                state_machines::Snapshot {
+                    state: self.state_enum(),
+                    version: self.version,
+                }
+            }
+
+            /// Move to `snapshot`'s state and version, keeping the context,
+            /// but only if the machine is still at version `expected`.
+            ///
+            /// Use it to apply a snapshot written elsewhere without
+            /// clobbering a transition made since, e.g. `expected` being the
+            /// version the snapshot was based on. No callbacks run.
+            pub fn restore_if_version(
+                &mut self,
+                snapshot: state_machines::Snapshot<#state_enum_name>,
+                expected: u64,
+            ) -> ::core::result::Result<(), state_machines::VersionMismatch> {
+                if self.version != expected {
+                    return Err(state_machines::VersionMismatch {
+                        expected,
+                        actual: self.version,
+                    });
+                }
+                self.restore_state(snapshot.state);
+                self.version = snapshot.version;
+                Ok(())
+            }
+
+            /// Dispatch an event to the state machine at runtime.
+            ///
+            /// With `transition_records: true`, a successful dispatch returns a
+            /// `Transition` record describing it.
+            ///
+            /// Returns an error if:
+            /// - The event is not valid from the current state (unless the
+            ///   machine's `unhandled` policy says otherwise)
+            /// - A guard callback fails
+            /// - An action callback fails
+            /// - A previous dispatch never completed (see `DynamicError::ReentrantDispatch`)
+            #handle_sig {
+                #take_hold
+                // Take ownership of inner state temporarily; it's only missing
+                // while another dispatch is in progress (or was abandoned)
+                let ::core::option::Option::Some(current) = self.inner.take() else {
+                    return Err(state_machines::DynamicError::reentrant_dispatch(event.name()));
+                };
+                #record_start
+                #capture_from_name
+                #capture_history
+                #capture_stats
+                #capture_commit
+                #unhandled_check
+
+                match #dispatch_call {
+                    Ok(new_state) => {
+                        #record_finish
+                        #publish_state
+                        #enter_state
+                        #reset_failures
+                        #record_history
+                        #record_stats
+                        self.version += 1;
+                        self.inner = ::core::option::Option::Some(new_state);
+                        #stage_commit
+                        #run_commit
+                        Ok(#handle_ok)
+                    }
+                    Err((old_state, err)) => {
+                        #record_stats_rejection
+                        #recover_call
+                        self.inner = ::core::option::Option::Some(old_state);
+                        #run_commit
+                        Err(err)
+                    }
+                }
+            }
+
+            /// Dispatch an event like `handle()`, but treat events with no
+            /// transition from the current state as successful no-ops,
+            /// whatever the machine's `unhandled` policy.
+            ///
+            /// Guard and action failures are still returned as errors.
+            #handle_or_ignore_sig {
+                if self.inner.is_some() && !self.can_handle(&event) {
+                    #ignore_record
+                    return Ok(#handle_ok);
+                }
+                #handle_call
+            }
+
+            /// Whether `event` has a transition (or is ignored) in the current state.
+            ///
+            /// Guards aren't evaluated, so `handle()` can still reject it.
+            pub fn can_handle #event_lifetimes(&self, event: &#event_ty) -> bool {
+                self.inner
+                    .as_ref()
+                    .is_some_and(|state| state.can_handle(event))
+            }
+
+            /// Get the name of the current state.
+            pub fn current_state(&self) -> &'static str {
+                self.inner.as_ref()
+                    .expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`")
+                    .name()
+            }
+
+            #wrapper_id_items
+
+            /// Get the current state as an enum value for exhaustive matching.
+            ///
+            /// With `data_states: true`, variants carry a clone of the state's data.
+            pub fn state_enum(&self) -> #state_enum_name {
+                self.inner.as_ref()
+                    .expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`")
+                    .state()
+            }
+
+            #introspection_methods
+
+            #state_predicates
+
+            #share_state_method
+
+            #watchdog_methods
+            #history_methods
+            #commit_methods
+            #stats_methods
+
+            #state_data_accessors
+        }
+
+        #default_impl
+
+        #dynamic_machine_impl
+        #forward_impl
+    })
+}
+
+/// What an optional part of the dynamic wrapper adds to it: a field and its
+/// value in a new machine, steps in `handle()`, and methods. Parts a
+/// machine doesn't use are left empty.
+#[derive(Default)]
+struct WrapperFeature {
+    field: TokenStream2,
+    init: TokenStream2,
+    /// Before dispatching, with `current` and `event` in scope
+    capture: TokenStream2,
+    /// After a successful dispatch, with `new_state` in scope
+    record: TokenStream2,
+    /// After a rejected dispatch, with `old_state` and `err` in scope
+    reject: TokenStream2,
+    methods: TokenStream2,
+}
+
+/// What `handle()` returns on success: a `Transition` record with
+/// `transition_records: true`.
+fn handle_output(machine: &StateMachine) -> TokenStream2 {
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    if machine.transition_records {
+        quote! { state_machines::Transition<#state_enum_name> }
+    } else {
+        quote! { () }
+    }
+}
+
+/// The optional lock-free mirror of the current state, published whenever
+/// the state changes.
+fn state_cell_feature(machine: &StateMachine) -> WrapperFeature {
+    if !machine.atomic_state {
+        return WrapperFeature::default();
+    }
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    WrapperFeature {
+        field: quote! {
+            state_cell: ::core::option::Option<&'static state_machines::AtomicState<#state_enum_name>>,
+        },
+        init: quote! { state_cell: ::core::option::Option::None, },
+        record: quote! {
+            if let ::core::option::Option::Some(cell) = self.state_cell {
+                cell.store(new_state.state());
+            }
+        },
+        methods: quote! {
+            /// Mirror the current state into `cell` after every successful dispatch.
+            ///
+            /// Other threads can then read the state with `cell.load()`
+            /// without locking the machine.
+            pub fn share_state(&mut self, cell: &'static state_machines::AtomicState<#state_enum_name>) {
+                cell.store(self.state_enum());
+                self.state_cell = ::core::option::Option::Some(cell);
+            }
+        },
+        ..WrapperFeature::default()
+    }
+}
+
+/// Watchdog: restart the entry clock whenever a dispatch changes the state.
+fn watchdog_feature(machine: &StateMachine) -> WrapperFeature {
+    if !machine.watchdog {
+        return WrapperFeature::default();
+    }
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let start_clock = start_clock(machine);
+    let time_in_state = clock_elapsed(machine, quote! { self.entered });
+    WrapperFeature {
+        field: quote! { entered: state_machines::__private::Stopwatch, },
+        init: quote! { entered: #start_clock, },
+        capture: quote! { let from_name = current.name(); },
+        record: quote! {
+            if new_state.name() != from_name {
+                self.entered = #start_clock;
+            }
+        },
+        methods: quote! {
+            /// How long the machine has been in its current state.
+            ///
+            /// Self-transitions and ignored events don't restart the clock.
+            /// Returns `None` without the `std` feature of `state-machines`,
+            /// which provides the system clock, unless the machine names
+            /// its own `clock:`.
+            pub fn time_in_state(&self) -> ::core::option::Option<::core::time::Duration> {
+                #time_in_state
+            }
+
+            /// Report the current state if the machine has stayed in it
+            /// longer than its `max_duration:`, or than `threshold` for
+            /// states without one.
+            ///
+            /// Always `None` without the `std` feature.
+            pub fn stuck_check(
+                &self,
+                threshold: ::core::time::Duration,
+            ) -> ::core::option::Option<state_machines::StuckState<#state_enum_name>> {
+                let elapsed = self.time_in_state()?;
+                let state = self.state_enum();
+                let limit = state.max_duration().unwrap_or(threshold);
+                (elapsed > limit).then_some(state_machines::StuckState {
+                    state,
+                    elapsed,
+                    limit,
+                })
+            }
+        },
+        ..WrapperFeature::default()
+    }
+}
+
+/// History: push every successful dispatch into a fixed-size ring buffer.
+fn history_feature(machine: &StateMachine) -> WrapperFeature {
+    let Some(capacity) = &machine.history else {
+        return WrapperFeature::default();
+    };
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+
+    // `rewind()` restores the state recorded in the history, which only
+    // carries the state's data with `data_states: true`; without it a state
+    // with data would come back at its default, so there's no `rewind()`
+    let rewind = (machine.data_states || machine.state_storage.is_empty()).then(|| {
+        quote! {
+            /// Put the machine back where it was `n` transitions ago, for
+            /// tests and debugging sessions.
+            ///
+            /// The state (with its data) comes from the history, the
+            /// version goes down by `n`, and the undone transitions are
+            /// dropped from the history. Like `restore_state()`, no
+            /// callbacks run and the context is left as it is. Fails
+            /// without changing anything if fewer than `n` transitions
+            /// are recorded.
+            pub fn rewind(
+                &mut self,
+                n: usize,
+            ) -> ::core::result::Result<(), state_machines::RewindTooFar> {
+                if n > self.history.len() {
+                    return Err(state_machines::RewindTooFar {
+                        requested: n,
+                        recorded: self.history.len(),
+                    });
+                }
+                let mut earliest = ::core::option::Option::None;
+                for _ in 0..n {
+                    earliest = self.history.pop();
+                }
+                if let ::core::option::Option::Some(transition) = earliest {
+                    self.restore_state(transition.from);
+                    self.version = self.version.saturating_sub(n as u64);
+                }
+                Ok(())
+            }
+        }
+    });
+
+    WrapperFeature {
+        field: quote! { history: state_machines::TransitionHistory<#state_enum_name, #capacity>, },
+        init: quote! { history: state_machines::TransitionHistory::new(), },
+        capture: quote! {
+            let history_from = current.state();
+            let history_event = event.name();
+        },
+        record: quote! {
+            self.history.push(state_machines::TransitionContext::new(
+                history_from,
+                new_state.state(),
+                history_event,
+            ));
+        },
+        methods: quote! {
+            /// The last transitions dispatched through `handle()`, oldest first.
+            ///
+            /// `restore()` and `restore_state()` aren't transitions, so
+            /// they aren't recorded.
+            pub fn history(&self) -> &state_machines::TransitionHistory<#state_enum_name, #capacity> {
+                &self.history
+            }
+
+            /// The most recent transition, i.e. how the machine got to its
+            /// current state, unless it was restored there.
+            pub fn last_transition(
+                &self,
+            ) -> ::core::option::Option<&state_machines::TransitionContext<#state_enum_name>> {
+                self.history.last()
+            }
+
+            #rewind
+        },
+        ..WrapperFeature::default()
+    }
+}
+
+/// Whether the wrapper keeps per-state counters: only when the `stats`
+/// feature is on.
+fn counts_stats(machine: &StateMachine) -> bool {
+    machine.stats && cfg!(feature = "stats")
+}
+
+/// Stats: per-state entry, exit, and guard-rejection counters. Without the
+/// `stats` feature, `stats()` still exists and reports zeros.
+fn stats_feature(machine: &StateMachine) -> WrapperFeature {
+    if !machine.stats {
+        return WrapperFeature::default();
+    }
+    let any_state_name = quote::format_ident!("Any{}State", machine.name);
+    let state_count = machine.states.len();
+    let state_names = machine.states.iter().map(|state| state.to_string());
+    let stats_ty = quote! { state_machines::MachineStats<#state_count> };
+    if !counts_stats(machine) {
+        return WrapperFeature {
+            methods: quote! {
+                /// Entries, exits, and guard rejections per state. Nothing is
+                /// counted without the `stats` feature, so every counter is zero.
+                pub fn stats(&self) -> #stats_ty {
+                    state_machines::MachineStats::new([#(#state_names),*])
+                }
+
+                /// Zero every counter; a no-op without the `stats` feature.
+                pub fn reset_stats(&mut self) {}
+            },
+            ..WrapperFeature::default()
+        };
+    }
+
+    let stats_slot_arms = machine.states.iter().enumerate().map(|(index, state)| {
+        quote! { #any_state_name::#state(_) => #index }
+    });
+    let any_state_generics = machine.type_generics();
+    WrapperFeature {
+        field: quote! { stats: #stats_ty, },
+        init: quote! { stats: state_machines::MachineStats::new([#(#state_names),*]), },
+        capture: quote! { let stats_from = Self::stats_slot(&current); },
+        record: quote! { self.stats.record_transition(stats_from, Self::stats_slot(&new_state)); },
+        reject: quote! {
+            if let state_machines::DynamicError::GuardFailed { .. } = err {
+                self.stats.record_guard_rejection(stats_from);
+            }
+        },
+        methods: quote! {
+            /// Entries, exits, and guard rejections per state, counted by
+            /// `handle()` since the machine was created or the counters
+            /// were last reset. `new()`, `restore()`, and `restore_state()`
+            /// aren't counted.
+            pub fn stats(&self) -> #stats_ty {
+                self.stats
+            }
+
+            /// Zero every counter.
+            pub fn reset_stats(&mut self) {
+                self.stats.reset();
+            }
+
+            /// The index of `state` among the declared states.
+            fn stats_slot(state: &#any_state_name #any_state_generics) -> usize {
+                match state {
+                    #(#stats_slot_arms,)*
+                }
+            }
+        },
+    }
+}
+
+/// What `after_commit` callbacks add to the dynamic wrapper, including the
+/// data `rollback()` puts back, which is held alongside the commits.
+#[derive(Default)]
+struct CommitSteps {
+    field: TokenStream2,
+    init: TokenStream2,
+    /// First thing in `handle()`, before anything can return early
+    take_hold: TokenStream2,
+    capture: TokenStream2,
+    /// Once the new state is stored
+    stage: TokenStream2,
+    /// Last thing in `handle()`, whether the dispatch succeeded or not
+    run: TokenStream2,
+    /// Wherever the state is replaced without a transition
+    discard: TokenStream2,
+    methods: TokenStream2,
+}
+
+/// `after_commit`: note the transition once the new state is stored, and
+/// run its callbacks unless `dispatch_uncommitted()` asked to hold them
+/// until `commit()`. The request to hold is taken before anything can
+/// return early, so it never outlives the dispatch it was made for.
+fn commit_steps(machine: &StateMachine) -> CommitSteps {
+    let (uncommitted_field, uncommitted_init) = rollback_field(machine).unzip();
+    if !machine.has_after_commit() {
+        return CommitSteps {
+            field: quote! { #uncommitted_field },
+            init: quote! { #uncommitted_init },
+            ..CommitSteps::default()
+        };
+    }
+    let is_async = machine.async_mode;
+    let asyncness = is_async.then(|| quote! { async });
+    let await_token = is_async.then(|| quote! { .await });
+    CommitSteps {
+        field: quote! {
+            hold_commits: bool,
+            pending_commit: ::core::option::Option<(&'static str, &'static str)>,
+            #uncommitted_field
+        },
+        init: quote! {
+            hold_commits: false,
+            pending_commit: ::core::option::Option::None,
+            #uncommitted_init
+        },
+        take_hold: quote! { let hold_commits = ::core::mem::take(&mut self.hold_commits); },
+        capture: quote! {
+            let commit_from = current.name();
+            let commit_event = event.name();
+        },
+        stage: quote! {
+            self.pending_commit = ::core::option::Option::Some((commit_from, commit_event));
+        },
+        run: quote! {
+            if !hold_commits {
+                self.run_after_commit() #await_token;
+            }
+        },
+        discard: quote! {
+            self.hold_commits = false;
+            self.pending_commit = ::core::option::Option::None;
+        },
+        methods: quote! {
+            /// Run the `after_commit` callbacks of the last transition, if
+            /// they haven't run yet.
+            #asyncness fn run_after_commit(&mut self) {
+                if let (
+                    ::core::option::Option::Some((from, event)),
+                    ::core::option::Option::Some(state),
+                ) = (self.pending_commit.take(), &self.inner)
+                {
+                    state.__after_commit(from, event) #await_token;
+                }
+            }
+        },
+    }
+}
+
+/// `on_error` recovery: count consecutive failed dispatches and fire the
+/// recovery event once there are `after` of them. A successful dispatch
+/// resets the count.
+///
+/// The recovery transition is published, timed, recorded, and staged for
+/// `after_commit` like one from `handle()`.
+fn recovery_feature(
+    machine: &StateMachine,
+    publish_state: &TokenStream2,
+    enter_state: &TokenStream2,
+    stages_commits: bool,
+) -> WrapperFeature {
+    let recover = machine
+        .on_error
+        .as_ref()
+        .and_then(|on_error| Some((on_error.recover.as_ref()?, on_error.after.as_ref())));
+    let Some((event, after)) = recover else {
+        return WrapperFeature::default();
+    };
+    let event_enum = quote::format_ident!("{}Event", machine.name);
+    let variant = syn::Ident::new(&to_pascal_case(&event.to_string()), event.span());
+    let after = match after {
+        Some(after) => quote! { #after },
+        None => quote! { 1 },
+    };
+    let (capture_recovery, record_recovery) = if machine.history.is_some() {
+        let name = event.to_string();
+        (
+            quote! { let history_from = old_state.state(); },
+            quote! {
+                self.history.push(state_machines::TransitionContext::new(
+                    history_from,
+                    new_state.state(),
+                    #name,
+                ));
+            },
+        )
+    } else {
         (quote! {}, quote! {})
+    };
+    let (capture_stats_recovery, record_stats_recovery) = if counts_stats(machine) {
+        (
+            quote! { let stats_recovery_from = Self::stats_slot(&old_state); },
+            quote! {
+                self.stats.record_transition(stats_recovery_from, Self::stats_slot(&new_state));
+            },
+        )
     } else {
-        // Generic context
-        (quote! { <C> }, quote! { <C> })
+        (quote! {}, quote! {})
     };
+    let recover_dispatch = if machine.async_mode {
+        quote! { old_state.dispatch(#event_enum::#variant).await }
+    } else {
+        quote! { old_state.dispatch(#event_enum::#variant) }
+    };
+    let (capture_commit_recovery, stage_commit_recovery) = if stages_commits {
+        let name = event.to_string();
+        (
+            quote! { let commit_from = old_state.name(); },
+            quote! {
+                self.pending_commit = ::core::option::Option::Some((commit_from, #name));
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    WrapperFeature {
+        field: quote! { failures: u32, },
+        init: quote! { failures: 0, },
+        record: quote! { self.failures = 0; },
+        reject: quote! {
+            self.failures += 1;
+            let old_state = if self.failures >= #after {
+                self.failures = 0;
+                #capture_recovery
+                #capture_stats_recovery
+                #capture_commit_recovery
+                match #recover_dispatch {
+                    Ok(new_state) => {
+                        #publish_state
+                        #enter_state
+                        #record_recovery
+                        #record_stats_recovery
+                        #stage_commit_recovery
+                        self.version += 1;
+                        new_state
+                    }
+                    Err((old_state, _)) => old_state,
+                }
+            } else {
+                old_state
+            };
+        },
+        ..WrapperFeature::default()
+    }
+}
+
+/// The id of an `Any{Name}State` or the wrapper, for records.
+fn id_of(machine: &StateMachine, value: TokenStream2) -> TokenStream2 {
+    if machine.identity {
+        quote! { #value.id() }
+    } else {
+        quote! { ::core::option::Option::None }
+    }
+}
+
+/// With `identity: true`, the wrapper's `id()` and `with_id()`, and the
+/// `DynamicMachine::id()` forwarding to them.
+fn identity_items(machine: &StateMachine) -> (TokenStream2, TokenStream2) {
+    if !machine.identity {
+        return (quote! {}, quote! {});
+    }
+    (
+        quote! {
+            /// This machine's id, if one was set with `with_id()`.
+            pub fn id(&self) -> ::core::option::Option<state_machines::MachineId> {
+                self.inner.as_ref()
+                    .expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`")
+                    .id()
+            }
+
+            /// Tag this machine with an id, carried into its trace entries,
+            /// notifications, and transition records.
+            pub fn with_id(mut self, id: impl ::core::convert::Into<state_machines::MachineId>) -> Self {
+                self.inner.as_mut()
+                    .expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`")
+                    .set_id(::core::option::Option::Some(id.into()));
+                self
+            }
+        },
+        quote! {
+            fn id(&self) -> ::core::option::Option<state_machines::MachineId> {
+                self.id()
+            }
+        },
+    )
+}
+
+/// With `transition_records: true`, capture the pre-state and timing before
+/// dispatching and build the record after: `(start, finish, value)`, where
+/// `value` is what a successful `handle()` returns.
+fn transition_record_steps(machine: &StateMachine) -> (TokenStream2, TokenStream2, TokenStream2) {
+    if !machine.transition_records {
+        return (quote! {}, quote! {}, quote! { () });
+    }
+    let start_clock = start_clock(machine);
+    let stopwatch_elapsed = clock_elapsed(machine, quote! { stopwatch });
+    let new_state_id = id_of(machine, quote! { new_state });
+    (
+        quote! {
+            let stopwatch = #start_clock;
+            let from = current.state();
+            let event_label = event.name();
+        },
+        quote! {
+            let record = state_machines::Transition {
+                from,
+                to: new_state.state(),
+                event: event_label,
+                duration: #stopwatch_elapsed,
+                id: #new_state_id,
+            };
+        },
+        quote! { record },
+    )
+}
+
+/// The record `handle_or_ignore()` returns for an event it ignores.
+fn ignore_record(machine: &StateMachine) -> Option<TokenStream2> {
+    machine.transition_records.then(|| {
+        let start_clock = start_clock(machine);
+        let stopwatch_elapsed = clock_elapsed(machine, quote! { stopwatch });
+        let self_id = id_of(machine, quote! { self });
+        quote! {
+            let stopwatch = #start_clock;
+            let record = state_machines::Transition {
+                from: self.state_enum(),
+                to: self.state_enum(),
+                event: event.name(),
+                duration: #stopwatch_elapsed,
+                id: #self_id,
+            };
+        }
+    })
+}
 
-    // Generate into_dynamic() methods for each state
-    let into_dynamic_methods =
-        if machine.context.is_some() {
-            // Concrete context: Machine<S>
-            machine.states.iter().map(|state| {
+/// `unhandled:` policy for events with no transition from the current state.
+fn unhandled_check(machine: &StateMachine, handle_ok: &TokenStream2) -> TokenStream2 {
+    let any_state_name = quote::format_ident!("Any{}State", machine.name);
+    let unchanged_record = machine.transition_records.then(|| {
+        let stopwatch_elapsed = clock_elapsed(machine, quote! { stopwatch });
+        let current_id = id_of(machine, quote! { current });
+        quote! {
+            let record = state_machines::Transition {
+                from,
+                to: current.state(),
+                event: event_label,
+                duration: #stopwatch_elapsed,
+                id: #current_id,
+            };
+        }
+    });
+    let keep_state = quote! {
+        #unchanged_record
+        self.inner = ::core::option::Option::Some(current);
+        return Ok(#handle_ok);
+    };
+    match &machine.unhandled {
+        UnhandledPolicy::Error => quote! {},
+        UnhandledPolicy::Ignore => quote! {
+            if !current.can_handle(&event) {
+                #keep_state
+            }
+        },
+        UnhandledPolicy::Panic => quote! {
+            if !current.can_handle(&event) {
+                let state_name = current.name();
+                self.inner = ::core::option::Option::Some(current);
+                panic!("unhandled event `{}` in state `{}`", event.name(), state_name);
+            }
+        },
+        UnhandledPolicy::Callback(callback) => {
+            let await_token = machine.async_mode.then(|| quote! { .await });
+            let callback_arms = machine.states.iter().map(|state| {
+                quote_spanned! {callback.span()=>
+                    #any_state_name::#state(m) => m.#callback(state, event) #await_token,
+                }
+            });
             quote! {
-                impl #machine_name<#state> {
-                    /// Convert this typestate machine into a dynamic wrapper.
-                    ///
-                    /// This allows runtime event dispatch at the cost of losing
-                    /// compile-time guarantees about state transitions.
-                    pub fn into_dynamic(self) -> #dynamic_name {
-                        #dynamic_name {
-                            inner: ::core::option::Option::Some(#any_state_name::#state(self)),
-                        }
+                if !current.can_handle(&event) {
+                    let state = current.state();
+                    match &current {
+                        #(#callback_arms)*
                     }
+                    #keep_state
                 }
             }
-        }).collect::<Vec<_>>()
-        } else {
-            // Generic context: Machine<C, S>
-            machine.states.iter().map(|state| {
+        }
+    }
+}
+
+/// The `DynamicMachine` impl persistence layers drive the machine through.
+/// Opt-in, since its associated types make a private context type an error
+/// rather than a lint on a public machine.
+fn dynamic_machine_impl(machine: &StateMachine, dynamic_machine_id: TokenStream2) -> TokenStream2 {
+    if !machine.persistent && !machine.http_mode {
+        return quote! {};
+    }
+    let is_async = machine.async_mode;
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine.name);
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let impl_generics = machine.impl_generics();
+    let struct_generics = machine.type_generics();
+    let event_ty = machine.event_type(false);
+    let ctx_param_ty = machine.ctx_type();
+    let handle_output = handle_output(machine);
+
+    let dispatch_body = if is_async {
+        quote! { self.handle(event) }
+    } else {
+        quote! { ::core::future::ready(self.handle(event)) }
+    };
+    // Persistence layers that can still roll the machine back hold
+    // `after_commit` callbacks until their own write succeeds, and keep
+    // the data of the state the dispatch left for `rollback()`
+    let hold_commits = machine
+        .has_after_commit()
+        .then(|| quote! { self.hold_commits = true; });
+    let (keep_rollback, rollback_method) = rollback_items(machine).unzip();
+    let dispatch_uncommitted = (hold_commits.is_some() || keep_rollback.is_some()).then(|| {
+        let body = if is_async {
             quote! {
-                impl<C> #machine_name<C, #state> {
-                    /// Convert this typestate machine into a dynamic wrapper.
-                    ///
-                    /// This allows runtime event dispatch at the cost of losing
-                    /// compile-time guarantees about state transitions.
-                    pub fn into_dynamic(self) -> #dynamic_name<C> {
-                        #dynamic_name {
-                            inner: ::core::option::Option::Some(#any_state_name::#state(self)),
-                        }
-                    }
+                async move {
+                    #keep_rollback
+                    #hold_commits
+                    self.handle(event).await
                 }
             }
-        }).collect::<Vec<_>>()
+        } else {
+            quote! {
+                #keep_rollback
+                #hold_commits
+                ::core::future::ready(self.handle(event))
+            }
+        };
+        quote! {
+            fn dispatch_uncommitted(
+                &mut self,
+                event: Self::Event,
+            ) -> impl ::core::future::Future<
+                Output = ::core::result::Result<Self::Output, state_machines::DynamicError>,
+            > {
+                #body
+            }
+        }
+    });
+    let commit = machine.has_after_commit().then(|| {
+        let body = if is_async {
+            quote! { self.run_after_commit() }
+        } else {
+            quote! {
+                self.run_after_commit();
+                ::core::future::ready(())
+            }
         };
+        quote! {
+            fn commit(&mut self) -> impl ::core::future::Future<Output = ()> {
+                #body
+            }
+        }
+    });
+    // Payload events can't be built from their name alone
+    let event_enum_name = quote::format_ident!("{}Event", machine.name);
+    let event_from_name_arms = machine
+        .events
+        .iter()
+        .filter(|event| event.payload.is_none())
+        .map(|event| {
+            let name_str = event.name.to_string();
+            let pascal_name =
+                syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+            quote! { #name_str => ::core::option::Option::Some(#event_enum_name::#pascal_name) }
+        });
+    quote! {
+        impl #impl_generics state_machines::DynamicMachine for #dynamic_name #struct_generics {
+            type State = #state_enum_name;
+            type Event = #event_ty;
+            type Context = #ctx_param_ty;
+            type Output = #handle_output;
 
-    // Generate into_{state}() methods for extracting typed machines
-    let extract_methods = if machine.context.is_some() {
-        // Concrete context: Machine<S>
-        machine
-            .states
-            .iter()
-            .map(|state| {
-                let method_name =
-                    quote::format_ident!("into_{}", to_snake_case(&state.to_string()));
-                quote! {
-                    /// Try to extract a typestate machine in the `#state` state.
-                    ///
-                    /// Returns `Ok` if the machine is currently in this state,
-                    /// otherwise returns `Err(self)` so you can try another state.
-                    pub fn #method_name(mut self) -> Result<#machine_name<#state>, Self> {
-                        match self.inner.take() {
-                            ::core::option::Option::Some(#any_state_name::#state(m)) => Ok(m),
-                            other => {
-                                self.inner = other;
-                                Err(self)
-                            }
-                        }
+            fn restore(ctx: Self::Context, state: Self::State) -> Self {
+                Self::restore(ctx, state)
+            }
+
+            fn restore_state(&mut self, state: Self::State) {
+                self.restore_state(state)
+            }
+
+            fn state(&self) -> Self::State {
+                self.state_enum()
+            }
+
+            fn is_poisoned(&self) -> bool {
+                self.is_poisoned()
+            }
+
+            fn state_name(state: &Self::State) -> &'static str {
+                state.name()
+            }
+
+            fn state_from_name(name: &str) -> ::core::option::Option<Self::State> {
+                #state_enum_name::from_name(name)
+            }
+
+            fn event_name(event: &Self::Event) -> &'static str {
+                event.name()
+            }
+
+            fn event_from_name(name: &str) -> ::core::option::Option<Self::Event> {
+                match name {
+                    #(#event_from_name_arms,)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            fn definition() -> &'static state_machines::MachineDefinition<Self::State> {
+                #state_enum_name::definition()
+            }
+
+            #dynamic_machine_id
+
+            fn dispatch(
+                &mut self,
+                event: Self::Event,
+            ) -> impl ::core::future::Future<
+                Output = ::core::result::Result<Self::Output, state_machines::DynamicError>,
+            > {
+                #dispatch_body
+            }
+
+            #dispatch_uncommitted
+            #commit
+            #rollback_method
+        }
+    }
+}
+
+/// Introspection: `events_from()`, the events each state has a transition
+/// on, in declaration order, and `available_events()`, which of them
+/// currently pass their guards.
+fn introspection_methods(machine: &StateMachine) -> TokenStream2 {
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let any_state_name = quote::format_ident!("Any{}State", machine.name);
+    let event_count = machine.events.len();
+    let mut events_from_arms = Vec::new();
+    let mut available_arms = Vec::new();
+    for state in &machine.states {
+        let mut names = Vec::new();
+        let mut checks = Vec::new();
+        for (index, event) in machine.events.iter().enumerate() {
+            let Some(edge) = machine.edge_for(state, &event.name) else {
+                continue;
+            };
+            let name = event.name.to_string();
+
+            // Guards of payload events need the payload, so they're assumed to pass
+            let check = if event.payload.is_some() {
+                quote! { true }
+            } else {
+                let guard_value = |guard: &syn::Ident, args: &[syn::Expr]| {
+                    let call = quote_spanned! {guard.span()=> m.#guard(&m.ctx #(, #args)*) };
+                    if machine.guard_is_async(guard) {
+                        super::typestate::await_bounded(machine, call, quote! { false })
+                    } else {
+                        call
                     }
+                };
+                let guards: Vec<_> = edge.guards.iter().collect();
+                let unless: Vec<_> = edge.unless.iter().collect();
+                super::typestate::guards_pass_tokens(&guards, &unless, &guard_value)
+            };
+            checks.push(quote! {
+                #[allow(unused_parens)]
+                if #check {
+                    available[#index] = ::core::option::Option::Some(#name);
                 }
-            })
-            .collect::<Vec<_>>()
+            });
+            names.push(name);
+        }
+        events_from_arms.push(quote! { #state_enum_name::#state { .. } => &[#(#names),*], });
+        available_arms.push(quote! { #any_state_name::#state(m) => { #(#checks)* } });
+    }
+    let available_sig = if machine.async_mode {
+        quote! { pub async fn available_events(&self) -> impl Iterator<Item = &'static str> }
     } else {
-        // Generic context: Machine<C, S>
-        machine
-            .states
-            .iter()
-            .map(|state| {
-                let method_name =
-                    quote::format_ident!("into_{}", to_snake_case(&state.to_string()));
-                quote! {
-                    /// Try to extract a typestate machine in the `#state` state.
-                    ///
-                    /// Returns `Ok` if the machine is currently in this state,
-                    /// otherwise returns `Err(self)` so you can try another state.
-                    pub fn #method_name(mut self) -> Result<#machine_name<C, #state>, Self> {
-                        match self.inner.take() {
-                            ::core::option::Option::Some(#any_state_name::#state(m)) => Ok(m),
-                            other => {
-                                self.inner = other;
-                                Err(self)
+        quote! { pub fn available_events(&self) -> impl Iterator<Item = &'static str> }
+    };
+    quote! {
+        /// The events with a transition from `state`, in declaration order.
+        ///
+        /// Only the definition is consulted; see `available_events()` for
+        /// the events whose guards pass right now.
+        pub fn events_from(state: &#state_enum_name) -> &'static [&'static str] {
+            match state {
+                #(#events_from_arms)*
+            }
+        }
+
+        /// The events with a transition from the current state whose guards
+        /// currently pass, in declaration order, e.g. to render only the
+        /// actionable buttons of a UI.
+        ///
+        /// Guards of events that carry a payload can't be evaluated without
+        /// it, so those events are listed whenever they have a transition.
+        /// Ignored events aren't listed.
+        #available_sig {
+            #[allow(unused_mut)]
+            let mut available: [::core::option::Option<&'static str>; #event_count] =
+                [::core::option::Option::None; #event_count];
+            match self.inner.as_ref().expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`") {
+                #(#available_arms)*
+            }
+            available.into_iter().flatten()
+        }
+    }
+}
+
+/// `is_<state>()` predicates for leaf states and superstates, and
+/// `within_<superstate>()` for superstates like the state enums have.
+fn wrapper_predicates(machine: &StateMachine) -> TokenStream2 {
+    let any_state_name = quote::format_ident!("Any{}State", machine.name);
+    let mut predicate_targets = machine.states.clone();
+    predicate_targets.extend(machine.hierarchy.all_superstates());
+    let within_predicates = machine
+        .hierarchy
+        .all_superstates()
+        .into_iter()
+        .map(|superstate| {
+            let snake = to_snake_case(&superstate.to_string());
+            let method_name = quote::format_ident!("within_{}", snake, span = superstate.span());
+            let is_method = quote::format_ident!("is_{}", snake, span = superstate.span());
+            let doc = format!(
+                " Whether the machine is in one of the `{}` substates.",
+                superstate
+            );
+            quote! {
+                #[doc = #doc]
+                pub fn #method_name(&self) -> bool {
+                    self.#is_method()
+                }
+            }
+        });
+    let state_predicates = predicate_targets.iter().map(|state| {
+        let method_name = quote::format_ident!(
+            "is_{}",
+            to_snake_case(&state.to_string()),
+            span = state.span()
+        );
+        let leaves = machine.hierarchy.expand_state(state, &machine.states);
+        quote! {
+            /// Check whether the machine is currently in this state (or one of its substates).
+            pub fn #method_name(&self) -> bool {
+                matches!(
+                    self.inner.as_ref().expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`"),
+                    #(#any_state_name::#leaves(_))|*
+                )
+            }
+        }
+    });
+    quote! {
+        #(#state_predicates)*
+
+        #(#within_predicates)*
+    }
+}
+
+/// `{state}_data()`, `{state}_data_mut()`, and `set_{state}_data()` for
+/// each state with data.
+fn state_data_accessors(machine: &StateMachine) -> TokenStream2 {
+    let any_state_name = quote::format_ident!("Any{}State", machine.name);
+    let accessor_methods = machine.state_storage.iter().map(|spec| {
+        let state_name = &spec.state_name;
+        let data_ty = &spec.ty;
+        let field = &spec.field;
+        let state_snake = to_snake_case(&state_name.to_string());
+        // Spanned to the state so go-to-definition lands on its declaration
+        let span = state_name.span();
+        let read_method = quote::format_ident!("{}_data", state_snake, span = span);
+        let write_method = quote::format_ident!("{}_data_mut", state_snake, span = span);
+        let set_method = quote::format_ident!("set_{}_data", state_snake, span = span);
+        let set_method_str = set_method.to_string();
+        let state_str = state_name.to_string();
+        let reachable_states = machine
+            .hierarchy
+            .expand_state(state_name, &machine.states);
+
+        if reachable_states.is_empty() {
+            return quote! {};
+        }
+        let read_match_arms = reachable_states.iter().map(|reachable| {
+            quote! { #any_state_name::#reachable(machine) => machine.#field.as_ref(), }
+        });
+
+        let write_match_arms = reachable_states.iter().map(|reachable| {
+            quote! { #any_state_name::#reachable(machine) => machine.#field.as_mut(), }
+        });
+
+        let set_match_arms = reachable_states.iter().map(|reachable| {
+            quote! {
+                #any_state_name::#reachable(machine) => {
+                    machine.#field = ::core::option::Option::Some(data);
+                    ::core::result::Result::Ok(())
+                }
+            }
+        });
+
+        quote! {
+            /// Read access to state data when in the `#state_name` state.
+            ///
+            /// Returns `None` if not currently in this state or if the machine
+            /// has been extracted via `into_{state}()` methods.
+            pub fn #read_method(&self) -> ::core::option::Option<&#data_ty> {
+                match self.inner.as_ref()? {
+                    #(#read_match_arms)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            /// Mutable access to state data when in the `#state_name` state.
+            ///
+            /// Returns `None` if not currently in this state or if the machine
+            /// has been extracted via `into_{state}()` methods.
+            pub fn #write_method(&mut self) -> ::core::option::Option<&mut #data_ty> {
+                match self.inner.as_mut()? {
+                    #(#write_match_arms)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            /// Set state data when in the `#state_name` state.
+            ///
+            /// Returns an error if:
+            /// - Not currently in the `#state_name` state
+            /// - The machine has been extracted via `into_{state}()` methods
+            pub fn #set_method(&mut self, data: #data_ty) -> Result<(), state_machines::DynamicError> {
+                match self.inner.as_mut() {
+                    ::core::option::Option::Some(state) => match state {
+                        #(#set_match_arms)*
+                        other => Err(state_machines::DynamicError::wrong_state(
+                            #state_str,
+                            other.name(),
+                            #set_method_str,
+                        )),
+                    },
+                    ::core::option::Option::None => Err(state_machines::DynamicError::wrong_state(
+                        #state_str,
+                        "<extracted>",
+                        #set_method_str,
+                    )),
+                }
+            }
+        }
+    });
+    quote! {
+        #(#accessor_methods)*
+    }
+}
+
+/// `simulate()` steps a clone of the current typed machine through the
+/// events, on the transition methods' dry-run path.
+fn simulate_impl(machine: &StateMachine) -> TokenStream2 {
+    let is_async = machine.async_mode;
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine.name);
+    let state_enum_name = quote::format_ident!("{}State", machine.name);
+    let impl_generics = machine.impl_generics();
+    let struct_generics = machine.type_generics();
+    let clone_bounds = machine.data_bounds(true, quote! { ::core::clone::Clone });
+    let event_ty = machine.event_type(false);
+    let event_lifetimes = machine.method_lifetimes(None);
+    let await_token = is_async.then(|| quote! { .await });
+    let simulate_sig = if is_async {
+        quote! { pub async fn simulate #event_lifetimes(&self, events: &[#event_ty]) }
+    } else {
+        quote! { pub fn simulate #event_lifetimes(&self, events: &[#event_ty]) }
+    };
+    let payload_tys = machine
+        .events
+        .iter()
+        .filter_map(|event| event.payload.as_ref());
+    quote! {
+        state_machines::__private::with_alloc! {
+            impl #impl_generics #dynamic_name #struct_generics #clone_bounds {
+                /// Dry-run `events` from the current state without changing the
+                /// machine or running its side effects.
+                ///
+                /// Each event goes through the transition method's own guards,
+                /// `choose` methods, and payload transform on a clone of the
+                /// machine, with its payload cloned, so a failing guard or a
+                /// timeout rejects the event as `handle()` would. No callbacks
+                /// run, `around` and `before_transition` included, and neither
+                /// do actions, so an abort or veto from them isn't consulted.
+                /// Rejected events leave the clone where it was and the run
+                /// continues with the next one.
+                #simulate_sig -> state_machines::DryRunReport<#state_enum_name>
+                where
+                    #( for<'__b> #payload_tys: ::core::clone::Clone, )*
+                {
+                    let mut current = ::core::clone::Clone::clone(
+                        self.inner.as_ref().expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`"),
+                    );
+                    let mut report = state_machines::DryRunReport::new(current.state());
+                    for event in events {
+                        let from = current.state();
+                        let (next, outcome) = match current.dry_run(event) #await_token {
+                            Ok((next, true)) => {
+                                let to = next.state();
+                                (next, state_machines::DryRunOutcome::Transition { to })
                             }
-                        }
+                            Ok((next, false)) => (next, state_machines::DryRunOutcome::Ignored),
+                            Err((next, err)) => (next, state_machines::DryRunOutcome::Rejected(err)),
+                        };
+                        current = next;
+                        report.push(event.name(), from, outcome);
                     }
+                    report
+                }
+            }
+        }
+    }
+}
+
+/// `handle_all()` applies a batch to the machine itself, rolling back to a
+/// clone taken up front. The `after_commit` callbacks of the batch's
+/// transitions are held, each with a clone of the machine that transition
+/// left, and run once the whole batch is in.
+fn handle_all_impl(machine: &StateMachine) -> TokenStream2 {
+    let is_async = machine.async_mode;
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine.name);
+    let impl_generics = machine.impl_generics();
+    let struct_generics = machine.type_generics();
+    let clone_bounds = machine.data_bounds(true, quote! { ::core::clone::Clone });
+    let event_ty = machine.event_type(false);
+    let event_lifetimes = machine.method_lifetimes(None);
+    let await_token = is_async.then(|| quote! { .await });
+    let handle_all_sig = if is_async {
+        quote! { pub async fn handle_all #event_lifetimes(&mut self, events: impl ::core::iter::IntoIterator<Item = #event_ty>) }
+    } else {
+        quote! { pub fn handle_all #event_lifetimes(&mut self, events: impl ::core::iter::IntoIterator<Item = #event_ty>) }
+    };
+    let (keep_state_cell, restore_state_cell) = if machine.atomic_state {
+        (
+            quote! { let state_cell = self.state_cell; },
+            quote! {
+                self.state_cell = state_cell;
+                if let ::core::option::Option::Some(cell) = self.state_cell {
+                    cell.store(self.state_enum());
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    let (hold_batch_commits, queue_commit, run_batch_commits) = if machine.has_after_commit() {
+        (
+            quote! { let mut commits = state_machines::__private::Vec::new(); },
+            quote! {
+                self.hold_commits = true;
+                let result = self.handle(event) #await_token;
+                if let (
+                    ::core::option::Option::Some((from, event)),
+                    ::core::option::Option::Some(state),
+                ) = (self.pending_commit.take(), &self.inner)
+                {
+                    commits.push((::core::clone::Clone::clone(state), from, event));
+                }
+            },
+            quote! {
+                for (state, from, event) in commits {
+                    state.__after_commit(from, event) #await_token;
+                }
+            },
+        )
+    } else {
+        (
+            quote! {},
+            quote! { let result = self.handle(event) #await_token; },
+            quote! {},
+        )
+    };
+    let handle_all_method = quote! {
+        /// Dispatch `events` in order, all or nothing.
+        ///
+        /// If an event fails, the machine is put back the way it was before
+        /// the batch (state, data, context, version, and history) and the
+        /// error says which event it was. Callbacks that already ran aren't
+        /// undone, but `after_commit` callbacks only run once every event
+        /// has been applied. Returns the number of events applied.
+        #handle_all_sig -> state_machines::BatchResult {
+            let snapshot = ::core::clone::Clone::clone(self);
+            #hold_batch_commits
+            let mut applied = 0;
+            for event in events {
+                #queue_commit
+                if let ::core::result::Result::Err(error) = result {
+                    #keep_state_cell
+                    *self = snapshot;
+                    #restore_state_cell
+                    return ::core::result::Result::Err(state_machines::BatchError {
+                        index: applied,
+                        error,
+                    });
+                }
+                applied += 1;
+            }
+            #run_batch_commits
+            ::core::result::Result::Ok(applied)
+        }
+    };
+    // Holding `after_commit` callbacks takes a list
+    if machine.has_after_commit() {
+        quote! {
+            state_machines::__private::with_alloc! {
+                impl #impl_generics #dynamic_name #struct_generics #clone_bounds {
+                    #handle_all_method
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl #impl_generics #dynamic_name #struct_generics #clone_bounds {
+                #handle_all_method
+            }
+        }
+    }
+}
+
+/// Whether `DynamicMachine::rollback()` has state data to put back: data
+/// kept outside the state enum, which `restore_state()` would reset.
+fn keeps_rollback_data(machine: &StateMachine) -> bool {
+    (machine.persistent || machine.http_mode)
+        && !machine.data_states
+        && !machine.state_storage.is_empty()
+}
+
+/// The wrapper field holding each storage field of the state the last
+/// `dispatch_uncommitted()` left, and its initializer.
+fn rollback_field(machine: &StateMachine) -> Option<(TokenStream2, TokenStream2)> {
+    keeps_rollback_data(machine).then(|| {
+        let tys = machine.state_storage.iter().map(|spec| &spec.ty);
+        (
+            quote! { uncommitted: ::core::option::Option<(#(::core::option::Option<#tys>,)*)>, },
+            quote! { uncommitted: ::core::option::Option::None, },
+        )
+    })
+}
+
+/// The statement in `dispatch_uncommitted()` that clones the current state's
+/// data aside, and the `rollback()` that puts it back after re-entering the
+/// state. State data must be `Clone` for that.
+fn rollback_items(machine: &StateMachine) -> Option<(TokenStream2, TokenStream2)> {
+    if !keeps_rollback_data(machine) {
+        return None;
+    }
+    let any_state_name = quote::format_ident!("Any{}State", machine.name);
+    let fields: Vec<_> = machine
+        .state_storage
+        .iter()
+        .map(|spec| &spec.field)
+        .collect();
+    let clones = machine.state_storage.iter().map(|spec| {
+        let (field, ty) = (&spec.field, &spec.ty);
+        quote_spanned! {ty.span()=>
+            <::core::option::Option<#ty> as ::core::clone::Clone>::clone(&m.#field)
+        }
+    });
+    let keep_arms = machine.states.iter().map(|state| {
+        let clones = clones.clone();
+        quote! { #any_state_name::#state(m) => (#(#clones,)*) }
+    });
+    let put_back_arms = machine.states.iter().map(|state| {
+        quote! { #any_state_name::#state(m) => { #(m.#fields = #fields;)* } }
+    });
+    let keep = quote! {
+        self.uncommitted = self.inner.as_ref().map(|state| match state {
+            #(#keep_arms,)*
+        });
+    };
+    let rollback = quote! {
+        fn rollback(&mut self, from: Self::State) {
+            let uncommitted = self.uncommitted.take();
+            self.restore_state(from);
+            if let (
+                ::core::option::Option::Some((#(#fields,)*)),
+                ::core::option::Option::Some(state),
+            ) = (uncommitted, self.inner.as_mut())
+            {
+                match state {
+                    #(#put_back_arms)*
+                }
+            }
+        }
+    };
+    Some((keep, rollback))
+}
+
+/// Start a `Stopwatch` on the machine's `clock:`, or the system clock.
+fn start_clock(machine: &StateMachine) -> TokenStream2 {
+    match &machine.clock {
+        Some(clock) => quote! { state_machines::__private::Stopwatch::start_on::<#clock>() },
+        None => quote! { state_machines::__private::Stopwatch::start() },
+    }
+}
+
+/// Read `stopwatch` on the machine's `clock:`, or the system clock.
+fn clock_elapsed(machine: &StateMachine, stopwatch: TokenStream2) -> TokenStream2 {
+    match &machine.clock {
+        Some(clock) => quote! { #stopwatch.elapsed_on::<#clock>() },
+        None => quote! { #stopwatch.elapsed() },
+    }
+}
+
+/// Generate conversion methods between typestate and dynamic modes.
+///
+/// Every leaf state marker implements `{Name}StateMarker`, so the typed
+/// machine converts from whichever state it's in, including from code
+/// generic over the state.
+///
+/// Example output:
+/// ```ignore
+/// pub trait FlightControllerStateMarker: Sized {
+///     const NAME: &'static str;
+///     fn wrap(machine: FlightController<Self>) -> AnyFlightState;
+/// }
+///
+/// impl<S: FlightControllerStateMarker> FlightController<S> {
+///     pub fn state_name(&self) -> &'static str { S::NAME }
+///     pub fn into_dynamic(self) -> DynamicFlightController { ... }
+/// }
+///
+/// impl DynamicFlightController {
+///     pub fn into_docked(self) -> Result<FlightController<Docked>, Self> { ... }
+///     pub fn into_in_flight(self) -> Result<FlightController<InFlight>, Self> { ... }
+///     pub fn into_typed(self) -> AnyFlightState { ... }
+/// }
+/// ```
+fn generate_conversions(machine: &StateMachine) -> Result<TokenStream2> {
+    let machine_name = &machine.name;
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine_name);
+    let any_state_name = quote::format_ident!("Any{}State", machine_name);
+    let state_cell_init = if machine.atomic_state {
+        quote! { state_cell: ::core::option::Option::None, }
+    } else {
+        quote! {}
+    };
+    let failures_init = machine
+        .on_error
+        .as_ref()
+        .filter(|on_error| on_error.recover.is_some())
+        .map(|_| quote! { failures: 0, });
+    let start_clock = start_clock(machine);
+    let entered_init = machine.watchdog.then(|| quote! { entered: #start_clock, });
+    let history_init = machine
+        .history
+        .as_ref()
+        .map(|_| quote! { history: state_machines::TransitionHistory::new(), });
+    let state_names = machine.states.iter().map(|state| state.to_string());
+    let stats_init = (machine.stats && cfg!(feature = "stats"))
+        .then(|| quote! { stats: state_machines::MachineStats::new([#(#state_names),*]), });
+    let commit_init = machine.has_after_commit().then(|| {
+        quote! {
+            hold_commits: false,
+            pending_commit: ::core::option::Option::None,
+        }
+    });
+    let uncommitted_init = rollback_field(machine).map(|(_, init)| init);
+
+    // Generic over the context (unless concrete) and user parameters
+    let impl_generics = machine.impl_generics();
+    let dynamic_generics = machine.type_generics();
+
+    // The marker trait, implemented by each leaf state, lets one impl over
+    // every state name the state and wrap the machine in its variant
+    let vis = machine.item_visibility();
+    let marker_trait = quote::format_ident!("{}StateMarker", machine_name);
+    let params = machine.generic_params();
+    let self_ty = machine.machine_type(quote! { Self });
+    let marker_impls = machine.states.iter().map(|state| {
+        let state_str = state.to_string();
+        let typed = machine.machine_type(state);
+        quote! {
+            impl #marker_trait for #state {
+                const NAME: &'static str = #state_str;
+
+                fn wrap<#(#params),*>(machine: #typed) -> #any_state_name #dynamic_generics {
+                    #any_state_name::#state(machine)
+                }
+            }
+        }
+    });
+    let machine_ty = machine.machine_type(quote! { S });
+    let doc = format!(" A leaf state of [`{machine_name}`], which it can be converted from.");
+    let into_dynamic = quote! {
+        #[doc = #doc]
+        #vis trait #marker_trait: ::core::marker::Sized {
+            /// The state's name, as `current_state()` reports it.
+            const NAME: &'static str;
+
+            #[doc(hidden)]
+            fn wrap<#(#params),*>(machine: #self_ty) -> #any_state_name #dynamic_generics;
+        }
+
+        #( #marker_impls )*
+
+        impl<#(#params,)* S: #marker_trait> #machine_ty {
+            /// The name of the state this machine is in.
+            pub fn state_name(&self) -> &'static str {
+                S::NAME
+            }
+
+            /// Convert this typestate machine into a dynamic wrapper, in
+            /// the same state.
+            ///
+            /// This allows runtime event dispatch at the cost of losing
+            /// compile-time guarantees about state transitions.
+            pub fn into_dynamic(self) -> #dynamic_name #dynamic_generics {
+                #dynamic_name {
+                    inner: ::core::option::Option::Some(S::wrap(self)),
+                    version: 0,
+                    #state_cell_init
+                    #failures_init
+                    #entered_init
+                    #history_init
+                    #stats_init
+                    #commit_init
+                    #uncommitted_init
                 }
-            })
-            .collect::<Vec<_>>()
+            }
+        }
     };
 
+    // Generate into_{state}() methods for extracting typed machines
+    let extract_methods = machine.states.iter().map(|state| {
+        let method_name = quote::format_ident!(
+            "into_{}",
+            to_snake_case(&state.to_string()),
+            span = state.span()
+        );
+        let typed = machine.machine_type(state);
+        quote! {
+            /// Try to extract a typestate machine in the `#state` state.
+            ///
+            /// Returns `Ok` if the machine is currently in this state,
+            /// otherwise returns `Err(self)` so you can try another state.
+            pub fn #method_name(mut self) -> Result<#typed, Self> {
+                match self.inner.take() {
+                    ::core::option::Option::Some(#any_state_name::#state(m)) => Ok(m),
+                    other => {
+                        self.inner = other;
+                        Err(self)
+                    }
+                }
+            }
+        }
+    });
+
     Ok(quote! {
-        #(#into_dynamic_methods)*
+        #into_dynamic
 
         impl #impl_generics #dynamic_name #dynamic_generics {
             #(#extract_methods)*
+
+            /// Extract the typed machine for whichever state is current.
+            ///
+            /// Unlike the `into_{state}()` methods this never fails: the returned
+            /// enum has one variant per state, so callers can `match` exhaustively.
+            pub fn into_typed(mut self) -> #any_state_name #dynamic_generics {
+                self.inner.take().expect("dynamic machine is poisoned by an abandoned dispatch; see `recover()`")
+            }
         }
     })
 }
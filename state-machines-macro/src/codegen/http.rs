@@ -0,0 +1,92 @@
+//! `HttpMachine` implementation for the `axum` router.
+//!
+//! Only generated when the macro specifies `http: true`, which requires the
+//! `axum` feature of the `state-machines` crate (it provides the trait, the
+//! router, and the re-exported `serde_json` path used below).
+//!
+//! # Example Generated Code
+//!
+//! ```rust,ignore
+//! impl state_machines::http::HttpMachine for DynamicTrafficLight<()> {
+//!     fn event_from_json(name: &str, payload: Option<Value>) -> Result<TrafficLightEvent, EventError> { ... }
+//!     fn dispatch_send(&mut self, event: TrafficLightEvent) -> impl Future<...> + Send { ... }
+//! }
+//! ```
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use state_machines_dsl::types::*;
+use state_machines_dsl::utils::to_pascal_case;
+use syn::Result;
+
+/// Implement `HttpMachine` for the dynamic wrapper of a machine declared with
+/// `http: true`.
+///
+/// Events are looked up by their snake_case name. Payload events decode
+/// their payload from the request body, so payload types must implement
+/// `serde::Deserialize`.
+pub fn generate_http_impl(machine: &StateMachine) -> Result<TokenStream2> {
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine.name);
+    let event_name = quote::format_ident!("{}Event", machine.name);
+
+    let http = quote!(::state_machines::http);
+    let serde_json = quote!(::state_machines::__private::serde_json);
+
+    // Routes serve one concrete machine, so generic-context machines use `()`
+    let dynamic_ty = if machine.context.is_some() {
+        quote! { #dynamic_name }
+    } else {
+        quote! { #dynamic_name<()> }
+    };
+
+    let event_arms = machine.events.iter().map(|event| {
+        let name_str = event.name.to_string();
+        let pascal_name =
+            syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+        // `alias:` names resolve to the same event
+        let aliases = event.aliases.iter().map(|alias| alias.to_string());
+        let pattern = quote! { #name_str #( | #aliases )* };
+
+        if event.payload.is_some() {
+            quote! {
+                #pattern => {
+                    let payload = payload.ok_or(#http::EventError::MissingPayload(#name_str))?;
+                    let payload = #serde_json::from_value(payload).map_err(|error| {
+                        #http::EventError::InvalidPayload { event: #name_str, error }
+                    })?;
+                    ::core::result::Result::Ok(#event_name::#pascal_name(payload))
+                }
+            }
+        } else {
+            quote! { #pattern => ::core::result::Result::Ok(#event_name::#pascal_name) }
+        }
+    });
+
+    Ok(quote! {
+        impl #http::HttpMachine for #dynamic_ty {
+            fn event_from_json(
+                name: &str,
+                payload: ::core::option::Option<#serde_json::Value>,
+            ) -> ::core::result::Result<#event_name, #http::EventError> {
+                match name {
+                    #(#event_arms,)*
+                    other => ::core::result::Result::Err(#http::EventError::UnknownEvent(
+                        ::core::convert::Into::into(other),
+                    )),
+                }
+            }
+
+            fn dispatch_send(
+                &mut self,
+                event: #event_name,
+            ) -> impl ::core::future::Future<
+                Output = ::core::result::Result<
+                    <Self as state_machines::DynamicMachine>::Output,
+                    state_machines::DynamicError,
+                >,
+            > + ::core::marker::Send {
+                <Self as state_machines::DynamicMachine>::dispatch(self, event)
+            }
+        }
+    })
+}
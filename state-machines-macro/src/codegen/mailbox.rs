@@ -0,0 +1,131 @@
+//! Interrupt-safe event mailbox generation.
+//!
+//! Only generated when the macro declares a `mailbox` block. The mailbox is a
+//! fixed-capacity queue (no allocation) that interrupt handlers post events
+//! into, while the main loop drains it into the dynamic machine.
+//!
+//! The queue needs compare-and-swap, so on targets without it the items are
+//! left out and a `compile_error!` points at the `mailbox` key instead.
+//!
+//! # Example Generated Code
+//!
+//! ```rust,ignore
+//! pub struct PumpMailbox { queue: Mailbox<PumpEvent, 16> }
+//!
+//! impl PumpMailbox {
+//!     pub const fn new() -> Self { ... }
+//!     pub fn post(&self, event: PumpEvent) -> Result<(), PumpEvent> { ... }
+//! }
+//!
+//! impl DynamicPump {
+//!     pub fn process(&mut self, mailbox: &PumpMailbox) -> Result<usize, DynamicError> { ... }
+//! }
+//! ```
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use state_machines_dsl::types::*;
+use syn::Result;
+
+/// Generate the `{Name}Mailbox` type and `Dynamic{Name}::process()`.
+pub fn generate_mailbox(machine: &StateMachine) -> Result<TokenStream2> {
+    let Some(spec) = &machine.mailbox else {
+        return Ok(quote! {});
+    };
+
+    let machine_name = &machine.name;
+    let mailbox_name = quote::format_ident!("{}Mailbox", machine_name);
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine_name);
+    // Queued events can't borrow, so payload lifetimes are `'static`
+    let event_name = machine.event_type(true);
+    let capacity = &spec.capacity;
+    let vis = machine.item_visibility();
+    let overflow = if spec.drop_oldest {
+        quote! { state_machines::core::MailboxOverflow::DropOldest }
+    } else {
+        quote! { state_machines::core::MailboxOverflow::Reject }
+    };
+
+    let impl_generics = machine.impl_generics();
+    let dynamic_generics = machine.type_generics();
+    let unsupported = quote_spanned! {spec.key.span()=>
+        #[cfg(not(target_has_atomic = "ptr"))]
+        ::core::compile_error!(
+            "`mailbox` needs atomic compare-and-swap, which this target doesn't have (e.g. Cortex-M0)"
+        );
+    };
+
+    let (process_sig, handle_call) = if machine.async_mode {
+        (
+            quote! { pub async fn process(&mut self, mailbox: &#mailbox_name) -> Result<usize, state_machines::DynamicError> },
+            quote! { self.handle(event).await?; },
+        )
+    } else {
+        (
+            quote! { pub fn process(&mut self, mailbox: &#mailbox_name) -> Result<usize, state_machines::DynamicError> },
+            quote! { self.handle(event)?; },
+        )
+    };
+
+    Ok(quote! {
+        #unsupported
+
+        /// Fixed-capacity event queue that interrupt handlers can post into.
+        ///
+        /// Declare it as a `static` and drain it with `process()` from the main loop.
+        #[cfg(target_has_atomic = "ptr")]
+        #[derive(Debug)]
+        #vis struct #mailbox_name {
+            queue: state_machines::core::Mailbox<#event_name, #capacity>,
+        }
+
+        #[cfg(target_has_atomic = "ptr")]
+        impl #mailbox_name {
+            pub const fn new() -> Self {
+                Self {
+                    queue: state_machines::core::Mailbox::new(#overflow),
+                }
+            }
+
+            /// Queue an event without blocking. Safe to call from an interrupt handler.
+            ///
+            /// Returns the event if the mailbox is full and can't make room for it.
+            pub fn post(&self, event: #event_name) -> Result<(), #event_name> {
+                self.queue.post(event)
+            }
+
+            /// Number of events waiting to be processed.
+            pub fn len(&self) -> usize {
+                self.queue.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.queue.is_empty()
+            }
+        }
+
+        #[cfg(target_has_atomic = "ptr")]
+        impl ::core::default::Default for #mailbox_name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #[cfg(target_has_atomic = "ptr")]
+        impl #impl_generics #dynamic_name #dynamic_generics {
+            /// Dispatch every queued event in the order it was posted.
+            ///
+            /// Returns how many events were handled. Stops at the first event
+            /// that fails to dispatch; that event is consumed, and the rest
+            /// stay queued for the next call.
+            #process_sig {
+                let mut handled = 0;
+                while let ::core::option::Option::Some(event) = mailbox.queue.pop() {
+                    #handle_call
+                    handled += 1;
+                }
+                Ok(handled)
+            }
+        }
+    })
+}
@@ -3,39 +3,187 @@
 //! Supports both typestate pattern (compile-time safety) and dynamic dispatch
 //! (runtime flexibility). Dynamic mode is opt-in via feature flag or explicit config.
 
+pub mod behavior;
 pub mod dynamic;
+pub mod http;
+pub mod mailbox;
 pub mod typestate;
-pub mod utils;
+pub mod wasm;
 
-use crate::types::*;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
+use state_machines_dsl::cfg;
+use state_machines_dsl::extends::{self, Resolution};
+use state_machines_dsl::params;
+use state_machines_dsl::types::*;
 use syn::Result;
 
-impl StateMachine {
-    /// Generate the complete state machine code.
-    ///
-    /// Generates typestate pattern for compile-time type safety.
-    /// Optionally also generates dynamic dispatch wrapper when:
-    /// - The `dynamic` feature flag is enabled, OR
-    /// - The macro explicitly specifies `dynamic: true`
-    pub fn expand(&self) -> Result<TokenStream2> {
-        self.validate()?;
+/// Merge in the machine's `extends:` base, and add the definition macro of
+/// an `extendable: true` machine.
+pub fn expand_input(input: TokenStream2) -> Result<TokenStream2> {
+    match extends::resolve(input)? {
+        Resolution::Pending { call, .. } => Ok(call),
+        Resolution::Ready {
+            tokens,
+            extendable: false,
+        } => expand_cfgs(tokens),
+        Resolution::Ready {
+            tokens,
+            extendable: true,
+        } => {
+            let definition = extends::definition(&tokens)?;
+            let code = expand_cfgs(tokens)?;
+            Ok(quote! {
+                #code
+                #definition
+            })
+        }
+    }
+}
+
+/// Parse and expand the macro input, once per combination of the `cfg`
+/// predicates on its entries. `params` are substituted after the entries
+/// are stripped, so a gated parameter applies to its combinations only.
+fn expand_cfgs(input: TokenStream2) -> Result<TokenStream2> {
+    let predicates = cfg::predicates(input.clone())?;
+    if predicates.is_empty() {
+        return expand(&syn::parse2::<StateMachine>(params::substitute(input)?)?);
+    }
+
+    let mut expansions = Vec::new();
+    for combination in 0..1usize << predicates.len() {
+        let enabled = |predicate: &TokenStream2| {
+            let index = predicates
+                .iter()
+                .position(|known| known.to_string() == predicate.to_string())
+                .expect("predicates were collected from the same input");
+            combination & (1 << index) != 0
+        };
+        let conditions: Vec<TokenStream2> = predicates
+            .iter()
+            .enumerate()
+            .map(|(index, predicate)| {
+                if combination & (1 << index) != 0 {
+                    quote! { #predicate }
+                } else {
+                    quote! { not(#predicate) }
+                }
+            })
+            .collect();
+
+        // Name the combination an error only occurs in
+        let code = params::substitute(cfg::strip(input.clone(), &enabled))
+            .and_then(syn::parse2::<StateMachine>)
+            .and_then(|machine| expand(&machine))
+            .map_err(|err| {
+                let when = quote! { all(#(#conditions),*) };
+                err.into_iter()
+                    .map(|err| syn::Error::new(err.span(), format!("{err} (with `cfg({when})`)")))
+                    .reduce(|mut all, err| {
+                        all.combine(err);
+                        all
+                    })
+                    .expect("errors aren't empty")
+            })?;
+
+        let attr: syn::Attribute = syn::parse_quote! { #[cfg(all(#(#conditions),*))] };
+        let file: syn::File = syn::parse2(code)?;
+        expansions.extend(file.items.into_iter().map(|item| cfg::gate(item, &attr)));
+    }
+    Ok(quote! { #(#expansions)* })
+}
+
+/// Generate the complete code of a parsed machine.
+///
+/// Generates typestate pattern for compile-time type safety.
+/// Optionally also generates dynamic dispatch wrapper when:
+/// - The `dynamic` feature flag is enabled, OR
+/// - The macro explicitly specifies `dynamic: true` (or `wasm: true`,
+///   `http: true`, or a `mailbox`)
+///
+/// An explicit `mode:` overrides both: `typestate` never generates the
+/// wrapper, `runtime` and `both` always do.
+///
+/// With `wasm: true`, a `wasm-bindgen` class wrapping the dynamic
+/// machine is generated as well; `http: true` implements `HttpMachine`
+/// for the dynamic wrapper; a `mailbox` block adds the event queue
+/// type and `process()`. With `module: name`, everything is wrapped in
+/// `mod name`.
+pub fn expand(machine: &StateMachine) -> Result<TokenStream2> {
+    machine.validate()?;
 
-        // Always generate typestate-based machine
-        let typestate_code = typestate::generate_typestate_machine(self)?;
+    // Always generate typestate-based machine
+    let typestate_code = typestate::generate_typestate_machine(machine)?;
 
-        // Conditionally generate dynamic dispatch wrapper
-        let should_generate_dynamic = self.dynamic_mode || cfg!(feature = "dynamic");
+    // Conditionally generate dynamic dispatch wrapper
+    let code = if generates_dynamic(machine) {
+        let dynamic_code = dynamic::generate_dynamic_wrapper(machine)?;
+        let wasm_code = if machine.wasm_mode {
+            wasm::generate_wasm_bindings(machine)?
+        } else {
+            quote! {}
+        };
+        let http_code = if machine.http_mode {
+            http::generate_http_impl(machine)?
+        } else {
+            quote! {}
+        };
+        let mailbox_code = if machine.mailbox.is_some() {
+            mailbox::generate_mailbox(machine)?
+        } else {
+            quote! {}
+        };
+        quote! {
+            #typestate_code
+            #dynamic_code
+            #wasm_code
+            #http_code
+            #mailbox_code
+        }
+    } else {
+        typestate_code
+    };
 
-        if should_generate_dynamic {
-            let dynamic_code = dynamic::generate_dynamic_wrapper(self)?;
+    // Optionally keep the generated items in their own module; the glob
+    // import makes context and payload types from the invocation site visible.
+    // Unused parts of the generated API shouldn't warn when the module is
+    // crate-private.
+    match &machine.module {
+        Some(module) => {
+            let vis = &machine.visibility;
             Ok(quote! {
-                #typestate_code
-                #dynamic_code
+                #[allow(dead_code)]
+                #vis mod #module {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    #code
+                }
             })
-        } else {
-            Ok(typestate_code)
+        }
+        None => Ok(code),
+    }
+}
+
+/// Whether the machine gets the dynamic dispatch wrapper alongside its
+/// typestate API.
+pub(super) fn generates_dynamic(machine: &StateMachine) -> bool {
+    match &machine.mode {
+        Some((_, mode)) => *mode != MachineMode::Typestate,
+        None => {
+            // The feature skips machines the runtime wrapper couldn't
+            // restore, couldn't enter every state of, or would fire
+            // `unsafe` events of from safe code, rather than failing them
+            let restorable = machine.no_default_init.is_none()
+                || machine.data_states
+                || machine.state_storage.is_empty();
+            let enterable = machine.context.is_some() || machine.state_requirements.is_empty();
+            let safe = machine.events.iter().all(|event| event.unsafety.is_none());
+            machine.dynamic_mode
+                || machine.wasm_mode
+                || machine.http_mode
+                || machine.mailbox.is_some()
+                || (cfg!(feature = "dynamic") && restorable && enterable && safe)
         }
     }
 }
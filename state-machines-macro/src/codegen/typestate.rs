@@ -38,10 +38,13 @@
 //! }
 //! ```
 
-use crate::codegen::utils::to_snake_case_ident;
-use crate::types::*;
+use crate::codegen::behavior::{generate_behavior_methods, generate_behavior_names};
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
+use state_machines_core::TransitionPhase;
+use state_machines_dsl::types::*;
+use state_machines_dsl::utils::to_snake_case_ident;
+use syn::spanned::Spanned;
 use syn::{Ident, Result};
 
 /// Generate all typestate code for the machine.
@@ -54,10 +57,16 @@ pub fn generate_typestate_machine(machine: &StateMachine) -> Result<TokenStream2
     let impls = generate_state_impls(machine)?;
     let substate_impls = generate_substate_impls(machine)?;
     let superstate_transition_impls = generate_superstate_transition_impls(machine)?;
+    let choice_enums = generate_choice_enums(machine);
+    let notification_enum = generate_notification_enum(machine);
+    let behavior_methods = generate_behavior_methods(machine);
 
     Ok(quote! {
         #markers
         #machine_struct
+        #behavior_methods
+        #( #choice_enums )*
+        #notification_enum
         #( #impls )*
         #( #substate_impls )*
         #( #superstate_transition_impls )*
@@ -84,12 +93,15 @@ fn generate_state_markers(machine: &StateMachine) -> Result<TokenStream2> {
     // Add superstates to the list
     all_states.extend(machine.hierarchy.all_superstates());
 
+    let vis = machine.item_visibility();
     let markers: Vec<_> = all_states
         .iter()
         .map(|state| {
+            let doc = machine.state_doc(state).map(|doc| quote! { #[doc = #doc] });
             quote! {
+                #doc
                 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-                pub struct #state;
+                #vis struct #state;
             }
         })
         .collect();
@@ -148,25 +160,244 @@ fn generate_machine_struct(machine: &StateMachine) -> Result<TokenStream2> {
         })
         .collect();
 
-    // Determine struct generics and context field type
-    let (struct_generics, ctx_ty) = if let Some(concrete_ctx) = &machine.context {
-        // Concrete context: only generic over state
-        (quote! { <S> }, quote! { #concrete_ctx })
+    // Generic over the context (unless concrete), user parameters, and state
+    let params = machine.generic_params();
+    let ctx_ty = machine.ctx_type();
+
+    // User parameters may only appear in bounds or state data; `fn() -> T`
+    // and `&'a ()` mark them used without affecting auto traits
+    let user_params: Vec<_> = machine
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect();
+    let user_lifetimes: Vec<_> = machine
+        .generics
+        .lifetimes()
+        .map(|param| &param.lifetime)
+        .collect();
+    let phantom_ty = if user_params.is_empty() && user_lifetimes.is_empty() {
+        quote! { S }
     } else {
-        // Generic context: generic over both context and state
-        (quote! { <C, S> }, quote! { C })
+        quote! { (S, #(&#user_lifetimes (),)* #(fn() -> #user_params,)*) }
     };
 
+    let vis = machine.item_visibility();
+    let ctx_vis = machine.ctx_visibility();
+    let extra_attrs = &machine.machine_attrs;
+    // With `mode: runtime`, the wrapper is the documented API
+    let hidden =
+        matches!(machine.mode, Some((_, MachineMode::Runtime))).then(|| quote! { #[doc(hidden)] });
+
+    // Cloneable whenever the context and state data are, whatever `S` and
+    // the user parameters are
+    let args = machine.generic_args();
+    let clone_bounds = machine.data_bounds(true, quote! { ::core::clone::Clone });
+    let storage_names = machine.state_storage.iter().map(|spec| &spec.field);
+
+    let id_field = machine
+        .identity
+        .then(|| quote! { id: ::core::option::Option<::state_machines::core::MachineId>, });
+    let id_clone = machine.id_field_init(quote! { self.id });
+    let machine_ty = machine.machine_type(quote! { S });
+    let id_methods = machine.identity.then(|| {
+        quote! {
+            impl<#(#params,)* S> #machine_ty {
+                /// This instance's id, if one was set with `with_id()`.
+                pub fn id(&self) -> ::core::option::Option<::state_machines::core::MachineId> {
+                    self.id
+                }
+
+                /// Identify this instance in traces, notifications, and
+                /// transition records. The id follows it through transitions.
+                pub fn with_id(
+                    mut self,
+                    id: impl ::core::convert::Into<::state_machines::core::MachineId>,
+                ) -> Self {
+                    self.id = ::core::option::Option::Some(id.into());
+                    self
+                }
+            }
+        }
+    });
+
+    let availability = availability_doc(machine);
+
     Ok(quote! {
         #[derive(Debug)]
-        pub struct #machine_name #struct_generics {
-            ctx: #ctx_ty,
-            _state: ::core::marker::PhantomData<S>,
+        #hidden
+        #(#extra_attrs)*
+        #( #[doc = #availability] )*
+        #vis struct #machine_name<#(#params,)* S> {
+            #ctx_vis ctx: #ctx_ty,
+            _state: ::core::marker::PhantomData<#phantom_ty>,
             #( #storage_fields, )*
+            #id_field
+        }
+
+        impl<#(#params,)* S> ::core::clone::Clone for #machine_name<#(#args,)* S> #clone_bounds {
+            fn clone(&self) -> Self {
+                Self {
+                    ctx: ::core::clone::Clone::clone(&self.ctx),
+                    _state: ::core::marker::PhantomData,
+                    #( #storage_names: ::core::clone::Clone::clone(&self.#storage_names), )*
+                    #id_clone
+                }
+            }
+        }
+
+        #id_methods
+    })
+}
+
+/// Generate the `{Name}{Event}Choice` and `{Name}{Event}Outcome` enums for
+/// events with `choose:` transitions.
+///
+/// The chooser returns a `Choice` variant; the transition method returns the
+/// matching `Outcome` variant holding the machine in that state.
+///
+/// # Example Output
+///
+/// ```rust,ignore
+/// pub enum ReviewDecideChoice { Approved, Rejected }
+///
+/// pub enum ReviewDecideOutcome<C> {
+///     Approved(Review<C, Approved>),
+///     Rejected(Review<C, Rejected>),
+/// }
+/// ```
+fn generate_choice_enums(machine: &StateMachine) -> Vec<TokenStream2> {
+    let vis = machine.item_visibility();
+    let impl_generics = machine.impl_generics();
+
+    machine
+        .events
+        .iter()
+        .filter_map(|event| {
+            let choice = event
+                .transitions
+                .iter()
+                .find_map(|transition| transition.choice.as_ref())?;
+            let (choice_enum, outcome_enum) = machine.choice_enum_names(&event.name);
+            let targets = &choice.targets;
+            let machine_types = targets
+                .iter()
+                .map(|target| machine.machine_type(machine.resolve_target(target)));
+            let choice_doc = format!(
+                " Target picked by `{}` for the `{}` event.",
+                choice.chooser, event.name
+            );
+            let outcome_doc = format!(
+                " The machine after a `{}` transition, in the state `{}` picked.",
+                event.name, choice.chooser
+            );
+
+            Some(quote! {
+                #[doc = #choice_doc]
+                #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+                #vis enum #choice_enum {
+                    #( #targets, )*
+                }
+
+                #[doc = #outcome_doc]
+                #[derive(Debug)]
+                #vis enum #outcome_enum #impl_generics {
+                    #( #targets(#machine_types), )*
+                }
+            })
+        })
+        .collect()
+}
+
+/// Generate the `{Name}Notification` enum, one variant per distinct `emit:`
+/// name, for machines with a `sink`.
+///
+/// # Example Output
+///
+/// ```rust,ignore
+/// pub enum OrderNotification { OrderPaid, OrderShipped }
+/// ```
+fn generate_notification_enum(machine: &StateMachine) -> Option<TokenStream2> {
+    let sink = machine.sink.as_ref()?;
+    let vis = machine.item_visibility();
+    let name = format_ident!("{}Notification", machine.name);
+    let mut variants: Vec<&Ident> = Vec::new();
+    for emit in machine
+        .events
+        .iter()
+        .flat_map(|event| &event.transitions)
+        .filter_map(|transition| transition.emit.as_ref())
+    {
+        if !variants.contains(&emit) {
+            variants.push(emit);
+        }
+    }
+    let doc = format!(
+        " What a `{}` transition with `emit:` reports to `{}`.",
+        machine.name, sink
+    );
+    Some(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #vis enum #name {
+            #( #variants, )*
         }
     })
 }
 
+/// Markdown table for the machine's rustdoc: each state's events, the
+/// guards that decide them, and their targets.
+///
+/// Mirrors `MachineDefinition::availability()`, row for row, so the docs
+/// answer "what can I call now?" without drifting from the definition.
+fn availability_doc(machine: &StateMachine) -> Vec<String> {
+    let mut lines = vec![
+        " # Available events".to_string(),
+        String::new(),
+        " | State | Event | Guards | Target |".to_string(),
+        " | --- | --- | --- | --- |".to_string(),
+    ];
+    for state in &machine.states {
+        let mut rows = 0;
+        for event in &machine.events {
+            let Some(edge) = machine.edge_for(state, &event.name) else {
+                continue;
+            };
+            let call = match &edge.payload {
+                Some(payload) => format!("{}({})", event.name, quote!(#payload)),
+                None => event.name.to_string(),
+            };
+            let guards = edge
+                .guards
+                .iter()
+                .map(|guard| format!("`{}`", guard.describe()))
+                .chain(
+                    edge.unless
+                        .iter()
+                        .map(|guard| format!("`!{}`", guard.describe())),
+                )
+                .collect::<Vec<_>>()
+                .join(", ");
+            let targets = match &edge.choice {
+                Some(choice) => choice
+                    .targets
+                    .iter()
+                    .map(|target| machine.resolve_target(target))
+                    .collect(),
+                None => vec![edge.target.clone()],
+            };
+            for target in targets {
+                rows += 1;
+                lines.push(format!(" | {state} | `{call}` | {guards} | {target} |"));
+            }
+        }
+        if rows == 0 {
+            lines.push(format!(" | {state} | — |  |  |"));
+        }
+    }
+    lines
+}
+
 /// Generate impl blocks for each state.
 ///
 /// For each state, we create an `impl Machine<State>` block containing:
@@ -190,6 +421,7 @@ fn generate_state_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
         if state == &machine.initial {
             let constructor = generate_constructor(machine, state)?;
             methods.push(constructor);
+            methods.extend(generate_behavior_names(machine));
         }
 
         // Generate transition methods for outgoing transitions
@@ -200,19 +432,11 @@ fn generate_state_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
             }
         }
 
-        let machine_name = &machine.name;
-
-        // Determine impl generics and type parameters
-        let (impl_generics, type_params) = if machine.context.is_some() {
-            // Concrete context: impl for specific state only (struct is Machine<S>)
-            (quote! {}, quote! { <#state> })
-        } else {
-            // Generic context: impl generic over C (struct is Machine<C, S>)
-            (quote! { <C> }, quote! { <C, #state> })
-        };
+        let impl_generics = machine.impl_generics();
+        let machine_ty = machine.machine_type(state);
 
         let impl_block = quote! {
-            impl #impl_generics #machine_name #type_params {
+            impl #impl_generics #machine_ty {
                 #( #methods )*
             }
         };
@@ -223,19 +447,13 @@ fn generate_state_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
     // Generate generic impl block with storage accessors (Option-based)
     if !machine.state_storage.is_empty() {
         let storage_accessors = generate_storage_accessors(machine)?;
-        let machine_name = &machine.name;
 
-        // Determine impl generics for storage accessors
-        let (impl_generics, type_params) = if machine.context.is_some() {
-            // Concrete context: impl generic only over state (struct is Machine<S>)
-            (quote! { <S> }, quote! { <S> })
-        } else {
-            // Generic context: impl generic over both (struct is Machine<C, S>)
-            (quote! { <C, S> }, quote! { <C, S> })
-        };
+        // Generic over the state as well
+        let params = machine.generic_params();
+        let machine_ty = machine.machine_type(quote! { S });
 
         let generic_impl = quote! {
-            impl #impl_generics #machine_name #type_params {
+            impl<#(#params,)* S> #machine_ty {
                 #( #storage_accessors )*
             }
         };
@@ -251,8 +469,8 @@ fn generate_state_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
 
 /// Generate a constructor method for the initial state.
 ///
-/// Creates a new machine instance in the initial state with all storage fields
-/// initialized to None. Takes a context parameter for hardware/external dependencies.
+/// Creates a new machine instance in the initial state. The initial state's
+/// storage starts at `StateData::initial()`, and every other field at None. Takes a context parameter for hardware/external dependencies.
 ///
 /// The context parameter type depends on whether a concrete context was specified:
 /// - Generic context: `ctx: C`
@@ -266,6 +484,7 @@ fn generate_state_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
 ///     Self {
 ///         ctx,
 ///         _state: core::marker::PhantomData,
+///         __initial_data: Some(StateData::initial()),
 ///         __data_field: None,
 ///     }
 /// }
@@ -287,18 +506,23 @@ fn generate_constructor(machine: &StateMachine, _state: &Ident) -> Result<TokenS
         .iter()
         .map(|spec| {
             let field = &spec.field;
-            quote! {
-                #field: ::core::option::Option::None
+            let ty = &spec.ty;
+            // The initial state's data starts at its default, like a
+            // state's data does when a transition enters it
+            if spec.state_name == machine.initial {
+                quote! {
+                    #field: ::core::option::Option::Some(<#ty as ::state_machines::core::StateData>::initial())
+                }
+            } else {
+                quote! {
+                    #field: ::core::option::Option::None
+                }
             }
         })
         .collect();
 
-    // Determine context parameter type
-    let ctx_param_ty = if let Some(concrete_ctx) = &machine.context {
-        quote! { #concrete_ctx }
-    } else {
-        quote! { C }
-    };
+    let ctx_param_ty = machine.ctx_type();
+    let id_init = machine.id_field_init(quote! { ::core::option::Option::None });
 
     Ok(quote! {
         pub fn new(ctx: #ctx_param_ty) -> Self {
@@ -306,6 +530,7 @@ fn generate_constructor(machine: &StateMachine, _state: &Ident) -> Result<TokenS
                 ctx,
                 _state: ::core::marker::PhantomData,
                 #( #storage_inits, )*
+                #id_init
             }
         }
     })
@@ -321,9 +546,13 @@ fn generate_constructor(machine: &StateMachine, _state: &Ident) -> Result<TokenS
 /// 1. Evaluates event-level guards
 /// 2. Evaluates transition-level guards
 /// 3. Runs before callbacks
-/// 4. Creates new machine with target state
-/// 5. Runs after callbacks
-/// 6. Returns Ok(new_machine) or Err((self, GuardError))
+/// 4. Runs the transition action (if any), which may veto
+///    (`compensate` hooks run in reverse if it does, or if a before callback panics)
+/// 5. Creates new machine with target state
+/// 6. Runs after callbacks
+/// 7. Returns Ok(new_machine) or Err((self, GuardError))
+///
+/// Each step is built by its phase's method on `Transition`.
 ///
 /// # Example Output
 ///
@@ -343,10 +572,10 @@ fn generate_constructor(machine: &StateMachine, _state: &Ident) -> Result<TokenS
 /// ```
 fn generate_transition_method(
     machine: &StateMachine,
-    _source_state: &Ident,
+    source_state: &Ident,
     edge: &TransitionEdge,
 ) -> Result<TokenStream2> {
-    let machine_name = &machine.name;
+    let transition = Transition::new(machine, source_state, edge);
     let event_name = &edge.event;
 
     // Convert event name to snake_case for the method name
@@ -357,350 +586,1444 @@ fn generate_transition_method(
     let target_state = &edge.target;
     let is_async = machine.async_mode;
     let core_path = quote!(::state_machines::core);
+    let docs = transition_method_docs(machine, source_state, edge);
+
+    // With `retry` or an `on_error` handler, the transition body becomes a
+    // hidden single-attempt method and the public method wraps it
+    let retry = machine.event_retry(event_name);
+    let handler = machine
+        .on_error
+        .as_ref()
+        .and_then(|on_error| on_error.handler.as_ref());
+    let wrapped = retry.is_some() || handler.is_some();
+    // With `after_commit` callbacks, the transition (with any retry and
+    // handler) is a hidden method that dynamic dispatch calls, and the public
+    // method runs the callbacks on its result
+    let committing = !edge.after_commit.is_empty();
+    let inner_name = if committing {
+        format_ident!("__{}_uncommitted", method_name)
+    } else {
+        method_name.clone()
+    };
+    let body_name = if wrapped {
+        format_ident!("__{}_attempt", method_name)
+    } else {
+        inner_name.clone()
+    };
+    let vis = machine.event_visibility(event_name);
+    let unsafety = machine.event_unsafety(event_name);
+    let (body_vis, body_unsafety) = if wrapped || committing {
+        (quote! { #[doc(hidden)] }, None)
+    } else {
+        (vis.clone(), unsafety)
+    };
 
     // Build method signature using snake_case method name
-    let (method_sig, payload_ref) = if let Some(payload_ty) = &edge.payload {
-        let sig = if is_async {
+    let asyncness = is_async.then(|| quote! { async });
+    let sig = |vis: &TokenStream2, unsafety: Option<syn::Token![unsafe]>, name: &Ident| {
+        if let Some(payload_ty) = &edge.payload {
+            // Payload lifetimes the machine isn't generic over, e.g. `&'p [u8]`
+            let lifetimes = machine.method_lifetimes(Some(payload_ty));
             quote! {
-                pub async fn #method_name(mut self, payload: #payload_ty)
+                #vis #asyncness #unsafety fn #name #lifetimes(mut self, payload: #payload_ty)
             }
         } else {
             quote! {
-                pub fn #method_name(mut self, payload: #payload_ty)
+                #vis #asyncness #unsafety fn #name(mut self)
             }
-        };
-        (sig, quote! { &payload })
+        }
+    };
+    let method_sig = sig(&body_vis, body_unsafety, &body_name);
+
+    let target_ty = match &edge.choice {
+        Some(_) => {
+            let (_, outcome_enum) = machine.choice_enum_names(event_name);
+            let generics = machine.type_generics();
+            quote! { #outcome_enum #generics }
+        }
+        None => machine.machine_type(target_state),
+    };
+    let return_type = quote! {
+        ::core::result::Result<#target_ty, (Self, #core_path::GuardError)>
+    };
+    // Capabilities the context needs in the states this method can enter
+    let targets = match &edge.choice {
+        Some(choice) => choice
+            .targets
+            .iter()
+            .map(|target| machine.resolve_target(target))
+            .collect(),
+        None => vec![target_state.clone()],
+    };
+    let requires = machine.requirement_bounds(&targets);
+
+    let body_docs = if wrapped || committing {
+        quote! {}
     } else {
-        let sig = if is_async {
-            quote! {
-                pub async fn #method_name(mut self)
-            }
-        } else {
-            quote! {
-                pub fn #method_name(mut self)
+        docs.clone()
+    };
+    // The steps on the machine in its source state, in phase order; any of
+    // them can still reject the transition
+    let steps = transition.source_steps(false);
+    let finish_transition = transition.finish(false);
+    let body = quote! {
+        #body_docs
+        #method_sig -> #return_type #requires {
+            #steps
+
+            // Enter the target state
+            #finish_transition
+        }
+    };
+
+    // The dry run `simulate()` steps a clone through: the steps that decide
+    // whether and where the transition goes, i.e. the payload transform,
+    // guards, and `choose`. Callbacks (`around` and `before_transition`
+    // included), the action, and everything after `Enter` are there for their
+    // side effects, so they don't run.
+    let dry_run = super::generates_dynamic(machine).then(|| {
+        let dry_run_sig = sig(
+            &quote! { #[doc(hidden)] },
+            None,
+            &format_ident!("__{}_dry_run", method_name),
+        );
+        let steps = transition.source_steps(true);
+        let finish = transition.finish(true);
+        quote! {
+            ::state_machines::__private::with_alloc! {
+                #[allow(unused_mut, unused_variables)]
+                #dry_run_sig -> #return_type #requires {
+                    #steps
+                    #finish
+                }
             }
-        };
-        (sig, quote! {})
+        }
+    });
+
+    let aliases = alias_methods(
+        machine,
+        event_name,
+        edge.payload.as_ref(),
+        &return_type,
+        &requires,
+    );
+    let await_token = is_async.then(|| quote! { .await });
+    let (params, payload_arg) = match &edge.payload {
+        Some(payload_ty) => {
+            let lifetimes = machine.method_lifetimes(Some(payload_ty));
+            (
+                quote! { #lifetimes(self, payload: #payload_ty) },
+                Some(quote! { payload }),
+            )
+        }
+        None => (quote! { (self) }, None),
     };
 
-    // Determine return type - depends on whether context is concrete or generic
-    let return_type = if machine.context.is_some() {
-        // Concrete context: struct is Machine<S>, so return Machine<TargetState>
+    let commit = committing.then(|| {
+        let run_calls = transition.after_commit_calls();
         quote! {
-            ::core::result::Result<#machine_name<#target_state>, (Self, #core_path::GuardError)>
+            #docs
+            #vis #asyncness #unsafety fn #method_name #params -> #return_type #requires {
+                let result = self.#inner_name(#payload_arg) #await_token;
+                #run_calls
+                result
+            }
         }
+    });
+
+    if !wrapped {
+        return Ok(quote! {
+            #body
+            #dry_run
+            #commit
+            #aliases
+        });
+    }
+
+    let wrapper_sig = if committing {
+        quote! { #[doc(hidden)] #asyncness fn #inner_name #params }
     } else {
-        // Generic context: struct is Machine<C, S>, so return Machine<C, TargetState>
-        quote! {
-            ::core::result::Result<#machine_name<C, #target_state>, (Self, #core_path::GuardError)>
+        quote! { #docs #vis #asyncness #unsafety fn #inner_name #params }
+    };
+
+    let run = match retry {
+        Some(retry) => retry_loop(machine, retry, &body_name, payload_arg),
+        None => quote! { self.#body_name(#payload_arg) #await_token },
+    };
+
+    let wrapper_body = match handler {
+        Some(handler) => quote! {
+            let result = #run;
+            if let ::core::result::Result::Err((machine, err)) = &result {
+                machine.#handler(#core_path::EventFailure::Rejected(err)) #await_token;
+            }
+            result
+        },
+        None => run,
+    };
+
+    Ok(quote! {
+        #body
+        #dry_run
+
+        #wrapper_sig -> #return_type #requires {
+            #wrapper_body
+        }
+
+        #commit
+        #aliases
+    })
+}
+
+/// Generate the attempts of a `retry` event: call the single-attempt
+/// `body_name` until it succeeds, the attempts run out, or it fails in a way
+/// retrying can't fix.
+fn retry_loop(
+    machine: &StateMachine,
+    retry: &RetrySpec,
+    body_name: &Ident,
+    payload_arg: Option<TokenStream2>,
+) -> TokenStream2 {
+    let core_path = quote!(::state_machines::core);
+    // Only failures that might clear up are retried; an invalid
+    // transition never will
+    let attempts = &retry.attempts;
+    let backoff = match &retry.backoff {
+        BackoffSpec::None => None,
+        BackoffSpec::Fixed(delay) => Some(quote! { #core_path::Backoff::Fixed(#delay) }),
+        BackoffSpec::Exponential(delay) => {
+            Some(quote! { #core_path::Backoff::Exponential(#delay) })
         }
     };
+    let wait = backoff.map(|backoff| {
+        let timer = match (&retry.timer, &machine.callback_timeout) {
+            (Some(timer), _)
+            | (
+                None,
+                Some(CallbackTimeout {
+                    timer: Some(timer), ..
+                }),
+            ) => {
+                quote! { #timer }
+            }
+            _ => quote! { ::state_machines::timer::Tokio },
+        };
+        quote! {
+            <#timer as #core_path::AsyncTimer>::sleep(#backoff.delay(__attempt)).await;
+        }
+    });
+    let payload_arg = payload_arg.map(|payload| {
+        quote! { ::core::clone::Clone::clone(&#payload) }
+    });
+    quote! {{
+        let mut __machine = self;
+        let mut __attempt: u32 = 1;
+        loop {
+            match __machine.#body_name(#payload_arg).await {
+                ::core::result::Result::Err((back, err))
+                    if __attempt < #attempts
+                        && !::core::matches!(
+                            err.kind,
+                            #core_path::TransitionErrorKind::InvalidTransition
+                        ) =>
+                {
+                    __machine = back;
+                    #wait
+                    __attempt += 1;
+                }
+                result => break result,
+            }
+        }
+    }}
+}
 
-    // Build guard checks
-    let mut guard_checks = Vec::new();
+/// One edge's transition, taken from one source state: what the tokens of
+/// each `TransitionPhase` are built from, with a method per phase.
+struct Transition<'a> {
+    machine: &'a StateMachine,
+    source_state: &'a Ident,
+    edge: &'a TransitionEdge,
+    // Error values name the event and states with plain literals; a
+    // `stringify!` of the user's tokens would give rust-analyzer a second,
+    // unresolvable place to map them to
+    event_str: String,
+    source_str: String,
+    payload_ref: TokenStream2,
+    core_path: TokenStream2,
+}
 
-    // Event-level guards
-    for guard in &edge.guards {
-        let check = if edge.payload.is_some() {
-            if is_async {
+impl<'a> Transition<'a> {
+    fn new(machine: &'a StateMachine, source_state: &'a Ident, edge: &'a TransitionEdge) -> Self {
+        let payload_ref = if edge.payload.is_some() {
+            quote! { &payload }
+        } else {
+            quote! {}
+        };
+        Self {
+            machine,
+            source_state,
+            edge,
+            event_str: edge.event.to_string(),
+            source_str: source_state.to_string(),
+            payload_ref,
+            core_path: quote!(::state_machines::core),
+        }
+    }
+
+    /// The steps on the machine in its source state, in phase order. A dry
+    /// run only keeps the ones that decide whether and where it goes.
+    fn source_steps(&self, dry_run: bool) -> TokenStream2 {
+        let steps = TransitionPhase::ORDER
+            .into_iter()
+            .filter(|phase| !phase.is_entered())
+            .map(|phase| match phase {
+                TransitionPhase::Transform => self.transform_call(),
+                TransitionPhase::Guards => self.guard_checks(),
+                TransitionPhase::Choose => self.choose_call(),
+                _ if dry_run => quote! {},
+                TransitionPhase::AroundBefore => self.around_before_checks(),
+                TransitionPhase::BeforeTransition => self.before_transition_chain(),
+                TransitionPhase::Before => self.before_calls(),
+                // A veto leaves the machine in its source state
+                TransitionPhase::Action => self.action_call(),
+                _ => unreachable!("`{phase:?}` runs in the target state"),
+            });
+        quote! { #( #steps )* }
+    }
+
+    /// Enter `target` and run the steps on the new machine, in phase order.
+    /// A dry run only enters it.
+    ///
+    /// `AfterCommit` runs from the public method, once the transition can't
+    /// be undone.
+    fn enter(&self, target: &Ident, dry_run: bool) -> TokenStream2 {
+        if dry_run {
+            return self.enter_step(target);
+        }
+        let steps = TransitionPhase::ORDER
+            .into_iter()
+            .filter(|phase| phase.is_entered() && *phase != TransitionPhase::AfterCommit)
+            .map(|phase| match phase {
+                TransitionPhase::Enter => self.enter_step(target),
+                TransitionPhase::After => self.after_calls(target),
+                TransitionPhase::AfterTransition => self.after_transition_calls(),
+                TransitionPhase::AroundAfter => self.around_after_checks(),
+                TransitionPhase::Emit => self.emit(target),
+                TransitionPhase::Forward => self.forwards(),
+                _ => unreachable!("`{phase:?}` runs before the target state is entered"),
+            });
+        quote! { #( #steps )* }
+    }
+
+    /// Choice transitions ask the chooser for the target once guards pass,
+    /// and return the typed machine for it wrapped in the event's Outcome enum.
+    fn finish(&self, dry_run: bool) -> TokenStream2 {
+        match &self.edge.choice {
+            None => {
+                let enter_target = self.enter(&self.edge.target, dry_run);
                 quote! {
-                    if !self.#guard(&self.ctx, #payload_ref).await {
-                        return ::core::result::Result::Err((
-                            self,
-                            #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
-                        ));
+                    #enter_target
+                    ::core::result::Result::Ok(new_machine)
+                }
+            }
+            Some(choice) => {
+                let (choice_enum, outcome_enum) = self.machine.choice_enum_names(&self.edge.event);
+                let arms = choice.targets.iter().map(|target| {
+                    let enter_target = self.enter(&self.machine.resolve_target(target), dry_run);
+                    quote! {
+                        #choice_enum::#target => {
+                            #enter_target
+                            ::core::result::Result::Ok(#outcome_enum::#target(new_machine))
+                        }
+                    }
+                });
+                quote! {
+                    match __choice {
+                        #( #arms )*
                     }
                 }
+            }
+        }
+    }
+
+    /// With `callback_timeout`, a call that misses the deadline fails the
+    /// transition like a rejecting guard, naming the method that timed out.
+    fn timeout_return(&self, name: &Ident, cleanup: TokenStream2) -> TokenStream2 {
+        let core_path = &self.core_path;
+        let (event_str, source_str) = (&self.event_str, &self.source_str);
+        let name = name.to_string();
+        quote! {{
+            #cleanup
+            return ::core::result::Result::Err((
+                self,
+                #core_path::GuardError::with_kind(
+                    #name,
+                    #event_str,
+                    #core_path::TransitionErrorKind::Timeout { name: #name },
+                )
+                .with_from_state(#source_str)
+            ));
+        }}
+    }
+
+    /// `Transform`: the event's transform rewrites the payload before
+    /// anything borrows it.
+    fn transform_call(&self) -> TokenStream2 {
+        let ctx_ty = self.machine.ctx_type();
+        let transform = self
+            .machine
+            .event_transform(&self.edge.event)
+            .zip(self.edge.payload.as_ref())
+            .map(|(transform, payload_ty)| {
+                quote_spanned! {transform.span()=>
+                    let _: fn(&Self, &#ctx_ty, #payload_ty) -> #payload_ty = Self::#transform;
+                    let payload = self.#transform(&self.ctx, payload);
+                }
+            });
+        quote! { #transform }
+    }
+
+    /// `AroundBefore`: each around callback's Before stage, on `self`, which
+    /// can abort the transition.
+    fn around_before_checks(&self) -> TokenStream2 {
+        let core_path = &self.core_path;
+        let (event_str, source_str) = (&self.event_str, &self.source_str);
+        let checks = self.edge.around.iter().map(|callback| {
+            let trace = self.around_trace(callback, quote! { Before });
+            let around_payload = self.around_payload(callback);
+            let call = quote! { self.#callback(#core_path::AroundStage::Before #around_payload) };
+            let call = if self.machine.async_mode {
+                await_bounded(self.machine, call, self.timeout_return(callback, quote! {}))
             } else {
-                quote! {
-                    if !self.#guard(&self.ctx, #payload_ref) {
+                call
+            };
+            let callback_name = self.around_abort_name(callback);
+            quote! {
+                #trace
+                match #call {
+                    #core_path::AroundOutcome::Proceed => {},
+                    #core_path::AroundOutcome::Abort(err) => {
+                        // Preserve the full TransitionError kind (GuardFailed, ActionFailed, etc.)
+                        let callback_name = #callback_name;
                         return ::core::result::Result::Err((
                             self,
-                            #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
+                            #core_path::GuardError::with_kind(callback_name, #event_str, err.kind)
+                                .with_from_state(#source_str)
                         ));
                     }
                 }
             }
-        } else if is_async {
+        });
+        quote! { #( #checks )* }
+    }
+
+    /// `AroundAfter`: each around callback's AfterSuccess stage, on the new
+    /// machine.
+    fn around_after_checks(&self) -> TokenStream2 {
+        let core_path = &self.core_path;
+        let event_str = &self.event_str;
+        let checks = self.edge.around.iter().map(|callback| {
+            let trace = self.around_trace(callback, quote! { AfterSuccess });
+            let around_payload = self.around_payload(callback);
+            let call = quote! {
+                new_machine.#callback(#core_path::AroundStage::AfterSuccess #around_payload)
+            };
+            let call = if self.machine.async_mode {
+                await_bounded(self.machine, call, quote! { #core_path::AroundOutcome::Proceed })
+            } else {
+                call
+            };
+            let callback_name = self.around_abort_name(callback);
             quote! {
-                if !self.#guard(&self.ctx).await {
-                    return ::core::result::Result::Err((
-                        self,
-                        #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
-                    ));
+                #trace
+                match #call {
+                    #core_path::AroundOutcome::Proceed => {},
+                    #core_path::AroundOutcome::Abort(err) => {
+                        // LIMITATION: AfterSuccess aborts cannot be properly handled with current typestate return type.
+                        // The transition has already occurred, so we can't return the old machine.
+                        // We panic here to make this limitation explicit rather than silently ignoring the error.
+                        let callback_name = #callback_name;
+                        panic!(
+                            "Around callback '{}' aborted at AfterSuccess stage during event '{}', but typestate machines \
+                             cannot properly surface this error because the state transition has already occurred. \
+                             Consider using Before stage aborts instead, or changing your callback to return Proceed.",
+                            callback_name, #event_str
+                        );
+                    }
                 }
             }
+        });
+        quote! { #( #checks )* }
+    }
+
+    fn around_trace(&self, callback: &Ident, stage: TokenStream2) -> TokenStream2 {
+        let core_path = &self.core_path;
+        let callback_name = callback.to_string();
+        trace_step(
+            self.machine,
+            &self.edge.event,
+            quote! { Around { callback: #callback_name, stage: #core_path::AroundStage::#stage } },
+        )
+    }
+
+    /// Around callbacks declared with `{ payload: true }` also borrow the
+    /// payload.
+    fn around_payload(&self, callback: &Ident) -> TokenStream2 {
+        if self.machine.around_takes_payload(callback) {
+            let payload_ref = &self.payload_ref;
+            quote! { , #payload_ref }
         } else {
-            quote! {
-                if !self.#guard(&self.ctx) {
-                    return ::core::result::Result::Err((
-                        self,
-                        #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
-                    ));
-                }
+            quote! {}
+        }
+    }
+
+    /// The name an around callback's abort is reported under: the guard or
+    /// action its error names, or the callback itself.
+    fn around_abort_name(&self, callback: &Ident) -> TokenStream2 {
+        let core_path = &self.core_path;
+        let name = callback.to_string();
+        quote! {
+            match &err.kind {
+                #core_path::TransitionErrorKind::GuardFailed { guard } => *guard,
+                #core_path::TransitionErrorKind::ActionFailed { action } => *action,
+                #core_path::TransitionErrorKind::InvalidTransition => #name,
+                #core_path::TransitionErrorKind::Timeout { name } => *name,
             }
-        };
-        guard_checks.push(check);
+        }
     }
 
-    // Unless guards (inverted)
-    for guard in &edge.unless {
-        let check = if edge.payload.is_some() {
-            if is_async {
+    /// `Guards`: each `guards` entry must evaluate to true and each `unless`
+    /// entry to false. Entries may be boolean expressions; `&&`/`||` keep
+    /// Rust's short-circuiting.
+    ///
+    /// Guards marked `{ cache: true }` that are referenced more than once in
+    /// this transition get a lazily-filled local, so the guard method runs at
+    /// most once.
+    fn guard_checks(&self) -> TokenStream2 {
+        let (machine, edge) = (self.machine, self.edge);
+        let core_path = &self.core_path;
+        let (event_str, source_str) = (&self.event_str, &self.source_str);
+        let payload_ref = &self.payload_ref;
+
+        let mut references: Vec<(&Ident, &[syn::Expr])> = Vec::new();
+        for expr in edge.guards.iter().chain(&edge.unless) {
+            references.extend(expr.calls());
+        }
+        let cached: Vec<&Ident> = machine
+            .cached_guard_names()
+            .into_iter()
+            .filter(|guard| references.iter().filter(|(used, _)| used == guard).count() > 1)
+            .collect();
+
+        let mut guard_checks = Vec::new();
+        for guard in &cached {
+            let cache_var = quote::format_ident!("__cached_guard_{}", guard);
+            guard_checks.push(quote! {
+                let mut #cache_var: ::core::option::Option<bool> = ::core::option::Option::None;
+            });
+        }
+
+        // Pin each guard's expected signature to its identifier, so a missing or
+        // mis-typed guard method is reported where it's named in the macro input.
+        // Async guards return futures, so only their calls are spanned. Literal
+        // arguments are left for the compiler to infer.
+        let ctx_ty = machine.ctx_type();
+        let payload_arg = edge.payload.as_ref().map(|ty| quote! { , &#ty });
+        let mut checked: Vec<&Ident> = Vec::new();
+        for (guard, args) in references {
+            if checked.contains(&guard) || machine.guard_is_async(guard) {
+                continue;
+            }
+            checked.push(guard);
+            let arg_tys = args.iter().map(|_| quote! { _ });
+            guard_checks.push(quote_spanned! {guard.span()=>
+                let _: fn(&Self, &#ctx_ty #payload_arg #(, #arg_tys)*) -> bool = Self::#guard;
+            });
+        }
+
+        let guard_value = |guard: &Ident, args: &[syn::Expr]| {
+            let call = if edge.payload.is_some() {
+                quote_spanned! {guard.span()=> self.#guard(&self.ctx, #payload_ref #(, #args)*) }
+            } else {
+                quote_spanned! {guard.span()=> self.#guard(&self.ctx #(, #args)*) }
+            };
+            let call = if machine.guard_is_async(guard) {
+                await_bounded(machine, call, self.timeout_return(guard, quote! {}))
+            } else {
+                call
+            };
+            let call = if cfg!(feature = "trace") {
+                let guard_name = GuardExpr::describe_call(guard, args);
+                let trace = trace_step(
+                    machine,
+                    &edge.event,
+                    quote! { Guard { guard: #guard_name, result: __result } },
+                );
+                quote! {
+                    {
+                        let __result = #call;
+                        #trace
+                        __result
+                    }
+                }
+            } else {
+                call
+            };
+
+            if cached.contains(&guard) {
+                let cache_var = quote::format_ident!("__cached_guard_{}", guard);
                 quote! {
-                    if self.#guard(&self.ctx, #payload_ref).await {
+                    match #cache_var {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => {
+                            let value = #call;
+                            #cache_var = ::core::option::Option::Some(value);
+                            value
+                        }
+                    }
+                }
+            } else {
+                call
+            }
+        };
+
+        let checks = edge
+            .guards
+            .iter()
+            .map(|expr| (expr, false))
+            .chain(edge.unless.iter().map(|expr| (expr, true)));
+
+        // With `evaluate_all_guards: true`, every entry runs before the first
+        // failure is reported, and the error lists all entries that failed
+        let mut deferred = Vec::new();
+        let mut descriptions = Vec::new();
+        let mut failed_vars = Vec::new();
+        for (index, (expr, inverted)) in checks.enumerate() {
+            let value = guard_expr_tokens(expr, &guard_value);
+            let description = expr.describe();
+
+            // `guards` must pass; `unless` guards are inverted
+            let failed = if inverted {
+                quote! { #value }
+            } else {
+                quote! { !(#value) }
+            };
+
+            if machine.evaluate_all_guards {
+                let failed_var = quote::format_ident!("__guard_failed_{}", index);
+                guard_checks.push(quote! {
+                    #[allow(unused_parens)]
+                    let #failed_var: bool = #failed;
+                });
+                deferred.push(quote! {
+                    if #failed_var {
                         return ::core::result::Result::Err((
                             self,
-                            #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
+                            #core_path::GuardError::new(#description, #event_str)
+                                .with_from_state(#source_str)
+                                .with_failed_guards(__failed_guards)
                         ));
                     }
-                }
+                });
+                descriptions.push(description);
+                failed_vars.push(failed_var);
             } else {
-                quote! {
-                    if self.#guard(&self.ctx, #payload_ref) {
+                guard_checks.push(quote! {
+                    #[allow(unused_parens)]
+                    if #failed {
                         return ::core::result::Result::Err((
                             self,
-                            #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
+                            #core_path::GuardError::new(#description, #event_str)
+                                .with_from_state(#source_str)
                         ));
                     }
-                }
+                });
             }
-        } else if is_async {
+        }
+        if !deferred.is_empty() {
+            // The mask has room for 64 entries; later failures still reject
+            let failed_vars = failed_vars.iter().take(64);
+            let shifts = 0..64u32;
+            guard_checks.push(quote! {
+                let __failed_guards = #core_path::FailedGuards::new(
+                    &[#(#descriptions),*],
+                    0 #(| ((#failed_vars as u64) << #shifts))*,
+                );
+            });
+        }
+        guard_checks.extend(deferred);
+        quote! { #( #guard_checks )* }
+    }
+
+    /// `Choose`: ask the chooser for the target into `__choice`.
+    fn choose_call(&self) -> TokenStream2 {
+        let Some(choice) = &self.edge.choice else {
+            return quote! {};
+        };
+        let chooser = &choice.chooser;
+        let (choice_enum, _) = self.machine.choice_enum_names(&self.edge.event);
+        let payload_ref = &self.payload_ref;
+        let call = if self.edge.payload.is_some() {
+            quote_spanned! {chooser.span()=> self.#chooser(&self.ctx, #payload_ref) }
+        } else {
+            quote_spanned! {chooser.span()=> self.#chooser(&self.ctx) }
+        };
+        let chooser_name = chooser.to_string();
+        let trace = trace_step(
+            self.machine,
+            &self.edge.event,
+            quote! { Choose { chooser: #chooser_name } },
+        );
+        if self.machine.async_mode {
+            let bounded =
+                await_bounded(self.machine, call, self.timeout_return(chooser, quote! {}));
             quote! {
-                if self.#guard(&self.ctx).await {
-                    return ::core::result::Result::Err((
-                        self,
-                        #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
-                    ));
-                }
+                #trace
+                let __choice = #bounded;
             }
         } else {
+            let ctx_ty = self.machine.ctx_type();
+            let payload_arg = self.edge.payload.as_ref().map(|ty| quote! { , &#ty });
             quote! {
-                if self.#guard(&self.ctx) {
-                    return ::core::result::Result::Err((
-                        self,
-                        #core_path::GuardError::new(stringify!(#guard), stringify!(#event_name))
-                    ));
-                }
+                let _: fn(&Self, &#ctx_ty #payload_arg) -> #choice_enum = Self::#chooser;
+                #trace
+                let __choice = #call;
             }
+        }
+    }
+
+    /// Whether before callbacks run under the compensation guard, which owns
+    /// the borrow of `self` as `__this`.
+    fn guarded(&self) -> bool {
+        !self.edge.compensate.is_empty()
+            && (!self.edge.before_transition.is_empty() || !self.edge.before.is_empty())
+    }
+
+    /// The receiver of before callbacks.
+    fn this(&self) -> TokenStream2 {
+        if self.guarded() {
+            quote! { __this }
+        } else {
+            quote! { self }
+        }
+    }
+
+    /// Under the compensation guard, a timed-out before callback must fire it
+    /// and release its borrow before returning `self`.
+    fn before_timeout(&self, callback: &Ident) -> TokenStream2 {
+        let cleanup = if self.guarded() {
+            quote! { ::core::mem::drop(__compensation); }
+        } else {
+            quote! {}
         };
-        guard_checks.push(check);
+        self.timeout_return(callback, cleanup)
     }
 
-    // Build before callback calls
-    let before_calls: Vec<_> = edge
-        .before
-        .iter()
-        .map(|callback| {
-            if edge.payload.is_some() {
-                if is_async {
-                    quote! { self.#callback(#payload_ref).await; }
+    /// `BeforeTransition`: arm the compensation guard (if any), then run the
+    /// global before_transition chain, in priority order. Each callback
+    /// returns `ControlFlow`; `Break` skips the rest of the chain.
+    fn before_transition_chain(&self) -> TokenStream2 {
+        let this = self.this();
+        let calls: Vec<_> = self
+            .edge
+            .before_transition
+            .iter()
+            .map(|callback| {
+                let callback_name = callback.to_string();
+                let trace = trace_step_on(
+                    self.machine,
+                    &self.edge.event,
+                    &this,
+                    quote! { Before { callback: #callback_name } },
+                );
+                let call = if self.machine.async_mode {
+                    await_bounded(
+                        self.machine,
+                        quote! { #this.#callback() },
+                        self.before_timeout(callback),
+                    )
                 } else {
-                    quote! { self.#callback(#payload_ref); }
+                    quote! { #this.#callback() }
+                };
+                quote! {
+                    #trace
+                    if ::core::ops::ControlFlow::is_break(&#call) {
+                        break '__before_transition;
+                    }
+                }
+            })
+            .collect();
+        let chain = (!calls.is_empty()).then(|| {
+            quote! {
+                '__before_transition: {
+                    #( #calls )*
                 }
-            } else if is_async {
-                quote! { self.#callback().await; }
+            }
+        });
+        let arm_compensation = self.guarded().then(|| {
+            let core_path = &self.core_path;
+            let guarded_calls = self.compensate_calls(&quote! { __this });
+            quote! {
+                let mut __compensation = #core_path::CompensationGuard::new(
+                    &mut self,
+                    |__this: &mut Self| {
+                        #guarded_calls
+                    },
+                );
+                let __this: &mut Self = &mut __compensation;
+            }
+        });
+        quote! {
+            #arm_compensation
+            #chain
+        }
+    }
+
+    /// `Before`: the event's before callbacks, then disarm the compensation
+    /// guard.
+    fn before_calls(&self) -> TokenStream2 {
+        let (machine, edge) = (self.machine, self.edge);
+        let this = self.this();
+        let payload_ref = &self.payload_ref;
+        let calls = edge.before.iter().map(|callback| {
+            let callback_name = callback.to_string();
+            let trace = trace_step_on(
+                machine,
+                &edge.event,
+                &this,
+                quote! { Before { callback: #callback_name } },
+            );
+            let payload = edge.payload.is_some().then(|| quote! { , #payload_ref });
+            let call = if machine.split_callback(callback) {
+                let data = split_data(machine, self.source_state, this.clone());
+                quote! { Self::#callback(#data, &#this.ctx #payload) }
+            } else if edge.payload.is_some() {
+                quote! { #this.#callback(#payload_ref) }
+            } else {
+                quote! { #this.#callback() }
+            };
+            let call = if machine.async_mode {
+                let bounded = await_bounded(machine, call, self.before_timeout(callback));
+                quote! { #bounded; }
             } else {
-                quote! { self.#callback(); }
+                quote! { #call; }
+            };
+            quote! { #trace #call }
+        });
+        let disarm_compensation = self.guarded().then(|| quote! { __compensation.disarm(); });
+        quote! {
+            #( #calls )*
+            #disarm_compensation
+        }
+    }
+
+    /// The compensation hooks on `this`, run in reverse declaration order.
+    ///
+    /// Compensation hooks are synchronous even in async machines, because
+    /// they must also run from a drop guard while a panicking `before`
+    /// callback unwinds.
+    fn compensate_calls(&self, this: &TokenStream2) -> TokenStream2 {
+        let payload_ref = &self.payload_ref;
+        let calls = self.edge.compensate.iter().rev().map(|hook| {
+            let hook_name = hook.to_string();
+            let trace = trace_step_on(
+                self.machine,
+                &self.edge.event,
+                this,
+                quote! { Compensate { hook: #hook_name } },
+            );
+            let call = if self.edge.payload.is_some() {
+                quote! { #this.#hook(#payload_ref); }
+            } else {
+                quote! { #this.#hook(); }
+            };
+            quote! { #trace #call }
+        });
+        quote! { #( #calls )* }
+    }
+
+    /// `Action`: the transition-scoped action. Actions get mutable context
+    /// access, so they're associated functions rather than `&self` methods;
+    /// returning `false` vetoes the transition.
+    fn action_call(&self) -> TokenStream2 {
+        let Some(action) = &self.edge.action else {
+            return quote! {};
+        };
+        let core_path = &self.core_path;
+        let (event_str, source_str) = (&self.event_str, &self.source_str);
+        let payload_ref = &self.payload_ref;
+        let compensate_calls = self.compensate_calls(&quote! { self });
+        let action_name = action.to_string();
+        let call = if self.edge.payload.is_some() {
+            quote! { Self::#action(&mut self.ctx, #payload_ref) }
+        } else {
+            quote! { Self::#action(&mut self.ctx) }
+        };
+        let call = if self.machine.async_mode {
+            await_bounded(
+                self.machine,
+                call,
+                self.timeout_return(action, compensate_calls.clone()),
+            )
+        } else {
+            call
+        };
+        let call = if cfg!(feature = "trace") {
+            let trace = trace_step(
+                self.machine,
+                &self.edge.event,
+                quote! { Action { action: #action_name, accepted: __accepted } },
+            );
+            quote! {
+                {
+                    let __accepted = #call;
+                    #trace
+                    __accepted
+                }
             }
-        })
-        .collect();
+        } else {
+            call
+        };
+        quote! {
+            if !#call {
+                #compensate_calls
+                return ::core::result::Result::Err((
+                    self,
+                    #core_path::GuardError::with_kind(
+                        #action_name,
+                        #event_str,
+                        #core_path::TransitionErrorKind::ActionFailed { action: #action_name },
+                    )
+                    .with_from_state(#source_str)
+                ));
+            }
+        }
+    }
 
-    // Build storage field transfers with initialization for target state
-    let storage_transfers: Vec<_> = machine
-        .state_storage
-        .iter()
-        .map(|spec| {
+    /// `Enter`: build the new machine, filling in the target's storage and
+    /// clearing the rest.
+    fn enter_step(&self, target: &Ident) -> TokenStream2 {
+        let (machine, edge) = (self.machine, self.edge);
+        let machine_name = &machine.name;
+        let (from, to) = (self.source_str.as_str(), target.to_string());
+        let trace = trace_step(
+            machine,
+            &edge.event,
+            quote! { Transition { from: #from, to: #to } },
+        );
+        let init_owns_payload = edge.payload_by_value && edge.after.is_empty();
+        let data = target_data(
+            machine,
+            target,
+            edge.init.as_ref(),
+            edge.payload.as_ref(),
+            init_owns_payload,
+        )
+        .map(|data| quote! { let __data = #data; });
+        let storage_transfers = machine.state_storage.iter().map(|spec| {
             let field = &spec.field;
-            let state_name = &spec.state_name;
-            let ty = &spec.ty;
-
-            // If transitioning to this state, initialize with Default
-            // Otherwise, clear the data (set to None) since we're not in that state
-            if state_name == target_state {
+            if &spec.state_name == target {
                 quote! {
-                    #field: ::core::option::Option::Some(<#ty as ::core::default::Default>::default())
+                    #field: ::core::option::Option::Some(__data)
                 }
             } else {
                 quote! {
                     #field: ::core::option::Option::None
                 }
             }
-        })
-        .collect();
+        });
+        let id_transfer = machine.id_field_init(quote! { self.id });
+        quote! {
+            #data
+            let mut new_machine = #machine_name {
+                ctx: self.ctx,
+                _state: ::core::marker::PhantomData,
+                #( #storage_transfers, )*
+                #id_transfer
+            };
+            #trace
+        }
+    }
 
-    // Build after callback calls (on new machine)
-    let after_calls: Vec<_> = edge
-        .after
-        .iter()
-        .map(|callback| {
-            if edge.payload.is_some() {
-                if is_async {
-                    quote! { new_machine.#callback(#payload_ref).await; }
-                } else {
-                    quote! { new_machine.#callback(#payload_ref); }
-                }
-            } else if is_async {
-                quote! { new_machine.#callback().await; }
+    /// `After`: the event's after callbacks, on the new machine. A `by value`
+    /// payload moves into the final entry, and `split` ones are called on
+    /// the target's type with its data.
+    fn after_calls(&self, target: &Ident) -> TokenStream2 {
+        let edge = self.edge;
+        let calls = edge.after.iter().enumerate().map(|(i, callback)| {
+            let payload = if edge.payload_by_value && i + 1 == edge.after.len() {
+                quote! { payload }
             } else {
-                quote! { new_machine.#callback(); }
-            }
-        })
-        .collect();
-
-    // Check if we have around callbacks
-    let has_around = !edge.around.is_empty();
+                self.payload_ref.clone()
+            };
+            let split = self.machine.split_callback(callback).then_some(target);
+            self.after_call(callback, edge.payload.is_some().then_some(payload), split)
+        });
+        quote! { #( #calls )* }
+    }
 
-    // Assemble the complete method
-    if has_around {
-        // Generate around callback invocations - Before stage (on self)
-        let around_before_checks: Vec<_> = edge
-            .around
+    /// `AfterTransition`: the global after_transition callbacks, which never
+    /// receive the payload, since they span events.
+    fn after_transition_calls(&self) -> TokenStream2 {
+        let calls = self
+            .edge
+            .after_transition
             .iter()
-            .map(|callback| {
-                if is_async {
-                    quote! {
-                        match self.#callback(#core_path::AroundStage::Before).await {
-                            #core_path::AroundOutcome::Proceed => {},
-                            #core_path::AroundOutcome::Abort(err) => {
-                                // Preserve the full TransitionError kind (GuardFailed, ActionFailed, etc.)
-                                let callback_name = match &err.kind {
-                                    #core_path::TransitionErrorKind::GuardFailed { guard } => *guard,
-                                    #core_path::TransitionErrorKind::ActionFailed { action } => *action,
-                                    #core_path::TransitionErrorKind::InvalidTransition => stringify!(#callback),
-                                };
-                                return ::core::result::Result::Err((
-                                    self,
-                                    #core_path::GuardError::with_kind(callback_name, stringify!(#event_name), err.kind)
-                                ));
-                            }
-                        }
-                    }
-                } else {
-                    quote! {
-                        match self.#callback(#core_path::AroundStage::Before) {
-                            #core_path::AroundOutcome::Proceed => {},
-                            #core_path::AroundOutcome::Abort(err) => {
-                                // Preserve the full TransitionError kind (GuardFailed, ActionFailed, etc.)
-                                let callback_name = match &err.kind {
-                                    #core_path::TransitionErrorKind::GuardFailed { guard } => *guard,
-                                    #core_path::TransitionErrorKind::ActionFailed { action } => *action,
-                                    #core_path::TransitionErrorKind::InvalidTransition => stringify!(#callback),
-                                };
-                                return ::core::result::Result::Err((
-                                    self,
-                                    #core_path::GuardError::with_kind(callback_name, stringify!(#event_name), err.kind)
-                                ));
-                            }
-                        }
-                    }
+            .map(|callback| self.after_call(callback, None, None));
+        quote! { #( #calls )* }
+    }
+
+    fn after_call(
+        &self,
+        callback: &Ident,
+        payload: Option<TokenStream2>,
+        split: Option<&Ident>,
+    ) -> TokenStream2 {
+        let machine = self.machine;
+        let callback_name = callback.to_string();
+        let trace = trace_step(
+            machine,
+            &self.edge.event,
+            quote! { After { callback: #callback_name } },
+        );
+        let call = if let Some(target) = split {
+            let target_ty = machine.machine_type(target);
+            let data = split_data(machine, target, quote! { new_machine });
+            let payload = payload.map(|payload| quote! { , #payload });
+            quote! { <#target_ty>::#callback(#data, &new_machine.ctx #payload) }
+        } else if let Some(payload) = payload {
+            quote! { new_machine.#callback(#payload) }
+        } else {
+            quote! { new_machine.#callback() }
+        };
+        // The state has already changed, so a timed-out after callback is
+        // abandoned rather than failing the transition
+        let call = if machine.async_mode {
+            let bounded = await_bounded(machine, call, quote! { () });
+            quote! { #bounded; }
+        } else {
+            quote! { #call; }
+        };
+        quote! { #trace #call }
+    }
+
+    /// `Emit`: notify the sink last, once nothing can fail the transition
+    /// any more.
+    fn emit(&self, target: &Ident) -> TokenStream2 {
+        let machine = self.machine;
+        let core_path = &self.core_path;
+        let emit = self
+            .edge
+            .emit
+            .as_ref()
+            .zip(machine.sink.as_ref())
+            .map(|(kind, sink)| {
+                let notification = format_ident!("{}Notification", machine.name);
+                let (event, from, to) = (&self.event_str, &self.source_str, target.to_string());
+                let id = machine.id_of(quote! { new_machine });
+                quote_spanned! {sink.span()=>
+                    let notification = #core_path::TransitionNotification {
+                        kind: #notification::#kind,
+                        event: #event,
+                        from: #from,
+                        to: #to,
+                        id: #id,
+                    };
+                    #core_path::Sink::send(new_machine.#sink(), notification);
                 }
-            })
-            .collect();
+            });
+        quote! { #emit }
+    }
 
-        // Generate around callback invocations - AfterSuccess stage (on new_machine)
-        let around_after_checks: Vec<_> = edge
-            .around
-            .iter()
-            .map(|callback| {
-                if is_async {
-                    quote! {
-                        match new_machine.#callback(#core_path::AroundStage::AfterSuccess).await {
-                            #core_path::AroundOutcome::Proceed => {},
-                            #core_path::AroundOutcome::Abort(err) => {
-                                // LIMITATION: AfterSuccess aborts cannot be properly handled with current typestate return type.
-                                // The transition has already occurred, so we can't return the old machine.
-                                // We panic here to make this limitation explicit rather than silently ignoring the error.
-                                let callback_name = match &err.kind {
-                                    #core_path::TransitionErrorKind::GuardFailed { guard } => *guard,
-                                    #core_path::TransitionErrorKind::ActionFailed { action } => *action,
-                                    #core_path::TransitionErrorKind::InvalidTransition => stringify!(#callback),
-                                };
-                                panic!(
-                                    "Around callback '{}' aborted at AfterSuccess stage during event '{}', but typestate machines \
-                                     cannot properly surface this error because the state transition has already occurred. \
-                                     Consider using Before stage aborts instead, or changing your callback to return Proceed.",
-                                    callback_name, stringify!(#event_name)
-                                );
-                            }
-                        }
-                    }
-                } else {
-                    quote! {
-                        match new_machine.#callback(#core_path::AroundStage::AfterSuccess) {
-                            #core_path::AroundOutcome::Proceed => {},
-                            #core_path::AroundOutcome::Abort(err) => {
-                                // LIMITATION: AfterSuccess aborts cannot be properly handled with current typestate return type.
-                                // The transition has already occurred, so we can't return the old machine.
-                                // We panic here to make this limitation explicit rather than silently ignoring the error.
-                                let callback_name = match &err.kind {
-                                    #core_path::TransitionErrorKind::GuardFailed { guard } => *guard,
-                                    #core_path::TransitionErrorKind::ActionFailed { action } => *action,
-                                    #core_path::TransitionErrorKind::InvalidTransition => stringify!(#callback),
-                                };
-                                panic!(
-                                    "Around callback '{}' aborted at AfterSuccess stage during event '{}', but typestate machines \
-                                     cannot properly surface this error because the state transition has already occurred. \
-                                     Consider using Before stage aborts instead, or changing your callback to return Proceed.",
-                                    callback_name, stringify!(#event_name)
-                                );
-                            }
+    /// `Forward`: hand the forwarded events on, in declaration order.
+    fn forwards(&self) -> TokenStream2 {
+        let core_path = &self.core_path;
+        let forwards = self.edge.forward.iter().map(|forward| {
+            let (to, event) = (&forward.to, &forward.event);
+            quote_spanned! {to.span()=>
+                #core_path::Forward::forward(new_machine.#to(), #event);
+            }
+        });
+        quote! { #( #forwards )* }
+    }
+
+    /// `AfterCommit`, from the public method: run the callbacks on the
+    /// result, if the transition went through.
+    fn after_commit_calls(&self) -> TokenStream2 {
+        let calls = after_commit_calls(
+            self.machine,
+            &self.edge.after_commit,
+            quote! { new_machine },
+        );
+        match &self.edge.choice {
+            None => quote! {
+                if let ::core::result::Result::Ok(new_machine) = &result {
+                    #calls
+                }
+            },
+            Some(choice) => {
+                let (_, outcome_enum) = self.machine.choice_enum_names(&self.edge.event);
+                let arms = choice.targets.iter().map(|target| {
+                    quote! { #outcome_enum::#target(new_machine) => { #calls } }
+                });
+                quote! {
+                    if let ::core::result::Result::Ok(outcome) = &result {
+                        match outcome {
+                            #( #arms )*
                         }
                     }
                 }
-            })
-            .collect();
+            }
+        }
+    }
+}
 
-        Ok(quote! {
-            #method_sig -> #return_type {
-                // Around callbacks - Before stage
-                #( #around_before_checks )*
+/// Generate the calls to a transition's `after_commit` callbacks on
+/// `receiver`, the machine in its new state.
+///
+/// The transition can't be undone any more, so like `after` callbacks, a
+/// timed-out one is abandoned.
+pub(super) fn after_commit_calls(
+    machine: &StateMachine,
+    callbacks: &[Ident],
+    receiver: TokenStream2,
+) -> TokenStream2 {
+    let calls = callbacks.iter().map(|callback| {
+        let call = quote_spanned! {callback.span()=> #receiver.#callback() };
+        if machine.async_mode {
+            let bounded = await_bounded(machine, call, quote! { () });
+            quote! { #bounded; }
+        } else {
+            quote! { #call; }
+        }
+    });
+    quote! { #( #calls )* }
+}
 
-                // Check guards
-                #( #guard_checks )*
+/// The state data a `split: true` callback gets, borrowed from `receiver`
+/// apart from its `ctx`. Validation ensures the state has data.
+fn split_data(machine: &StateMachine, state: &Ident, receiver: TokenStream2) -> TokenStream2 {
+    let field = &machine
+        .state_storage_for(state)
+        .expect("split callbacks run in states with data")
+        .field;
+    quote! { #receiver.#field.as_mut().unwrap() }
+}
 
-                // Execute before callbacks on current machine
-                #( #before_calls )*
+/// Generate the methods for an event's `alias:` names.
+///
+/// Each alias takes the same arguments as the event method and forwards to
+/// it, so callers can move between names without behavior changes.
+fn alias_methods(
+    machine: &StateMachine,
+    event_name: &Ident,
+    payload: Option<&syn::Type>,
+    return_type: &TokenStream2,
+    requires: &TokenStream2,
+) -> TokenStream2 {
+    let method_name = to_snake_case_ident(event_name);
+    let doc = format!(" Alias for [`{0}`](Self::{0}).", method_name);
+    let lifetimes = machine.method_lifetimes(payload);
+    let (params, args) = match payload {
+        Some(payload_ty) => (quote! { , payload: #payload_ty }, quote! { payload }),
+        None => (quote! {}, quote! {}),
+    };
+    let (asyncness, await_token) = if machine.async_mode {
+        (quote! { async }, quote! { .await })
+    } else {
+        (quote! {}, quote! {})
+    };
+    let vis = machine.event_visibility(event_name);
+    let unsafety = machine.event_unsafety(event_name);
+    let call = quote! { self.#method_name(#args) #await_token };
+    // The alias's caller already vouched for the event's safety contract
+    let call = match unsafety {
+        Some(_) => quote! { unsafe { #call } },
+        None => call,
+    };
+    let methods = machine.event_aliases(event_name).iter().map(|alias| {
+        quote! {
+            #[doc = #doc]
+            #[inline]
+            #vis #asyncness #unsafety fn #alias #lifetimes(self #params) -> #return_type #requires {
+                #call
+            }
+        }
+    });
+    quote! { #( #methods )* }
+}
 
-                // Create new machine with target state
-                let mut new_machine = #machine_name {
-                    ctx: self.ctx,
-                    _state: ::core::marker::PhantomData,
-                    #( #storage_transfers, )*
-                };
+/// Generate rustdoc for a transition method from the machine definition.
+///
+/// Summarizes the source and target states, the event's `doc:` text, and every
+/// guard and callback involved, so IDE hover shows the workflow semantics.
+fn transition_method_docs(
+    machine: &StateMachine,
+    source: &Ident,
+    edge: &TransitionEdge,
+) -> TokenStream2 {
+    let targets = match &edge.choice {
+        Some(choice) => choice
+            .targets
+            .iter()
+            .map(|target| format!("`{}`", target))
+            .collect::<Vec<_>>()
+            .join(" or "),
+        None => format!("`{}`", edge.target),
+    };
+    let mut lines = vec![format!(
+        " Fire `{}`: `{}` → {}.",
+        edge.event, source, targets
+    )];
+
+    if let Some(doc) = machine.event_doc(&edge.event) {
+        lines.push(String::new());
+        lines.extend(doc.value().lines().map(|line| format!(" {}", line.trim())));
+    }
 
-                // Execute after callbacks on new machine
-                #( #after_calls )*
+    let dangerous = machine
+        .events
+        .iter()
+        .any(|event| event.name == edge.event && event.dangerous);
+    if dangerous {
+        lines.push(String::new());
+        lines.push(" **Dangerous:** this transition is expected to be guarded.".to_string());
+    }
 
-                // Around callbacks - AfterSuccess stage
-                #( #around_after_checks )*
+    let code_list = |items: Vec<String>| {
+        items
+            .iter()
+            .map(|item| format!("`{}`", item))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let names = |idents: &[Ident]| idents.iter().map(|ident| ident.to_string()).collect();
+    let sections = [
+        (
+            "Guards",
+            edge.guards.iter().map(GuardExpr::describe).collect(),
+        ),
+        (
+            "Unless",
+            edge.unless.iter().map(GuardExpr::describe).collect(),
+        ),
+        ("Around", names(&edge.around)),
+        (
+            "Choose",
+            edge.choice
+                .iter()
+                .map(|choice| choice.chooser.to_string())
+                .collect(),
+        ),
+        ("Before transition", names(&edge.before_transition)),
+        ("Before", names(&edge.before)),
+        (
+            "Action",
+            edge.action
+                .iter()
+                .map(|action| action.to_string())
+                .collect(),
+        ),
+        ("Compensate", names(&edge.compensate)),
+        ("After", names(&edge.after)),
+        ("After transition", names(&edge.after_transition)),
+    ];
+    let summary: Vec<String> = sections
+        .into_iter()
+        .filter(|(_, items): &(&str, Vec<String>)| !items.is_empty())
+        .map(|(label, items)| format!(" - {}: {}", label, code_list(items)))
+        .collect();
+    if !summary.is_empty() {
+        lines.push(String::new());
+        lines.extend(summary);
+    }
 
-                ::core::result::Result::Ok(new_machine)
-            }
-        })
-    } else {
-        // No around callbacks - generate simpler code
-        Ok(quote! {
-            #method_sig -> #return_type {
-                // Check guards
-                #( #guard_checks )*
-
-                // Execute before callbacks on current machine
-                #( #before_calls )*
-
-                // Create new machine with target state
-                let mut new_machine = #machine_name {
-                    ctx: self.ctx,
-                    _state: ::core::marker::PhantomData,
-                    #( #storage_transfers, )*
-                };
+    let can_fail = !edge.guards.is_empty()
+        || !edge.unless.is_empty()
+        || !edge.around.is_empty()
+        || edge.action.is_some();
+    if can_fail {
+        lines.push(String::new());
+        lines.push(" # Errors".to_string());
+        lines.push(String::new());
+        lines.push(
+            " Returns the unchanged machine with a `GuardError` if a guard, around callback, \
+             or the action rejects the transition."
+                .to_string(),
+        );
+    }
 
-                // Execute after callbacks on new machine
-                #( #after_calls )*
+    if let Some(retry) = machine.event_retry(&edge.event) {
+        lines.push(String::new());
+        lines.push(format!(
+            " Failed attempts are retried, up to {} in total, before the error is returned.",
+            retry.attempts
+        ));
+    }
 
-                ::core::result::Result::Ok(new_machine)
-            }
-        })
+    if let Some(handler) = machine
+        .on_error
+        .as_ref()
+        .and_then(|on_error| on_error.handler.as_ref())
+    {
+        lines.push(String::new());
+        lines.push(format!(
+            " Errors are passed to `{}` before they are returned.",
+            handler
+        ));
+    }
+
+    if machine.event_unsafety(&edge.event).is_some() {
+        lines.push(String::new());
+        lines.push(" # Safety".to_string());
+        lines.push(String::new());
+        lines.push(
+            " The event is declared `unsafe`: callers must uphold whatever contract its \
+             documentation states before firing it."
+                .to_string(),
+        );
+    }
+
+    // rustdoc search finds the method under its aliases too
+    let aliases = machine
+        .event_aliases(&edge.event)
+        .iter()
+        .map(|alias| alias.to_string());
+    quote! {
+        #( #[doc = #lines] )*
+        #( #[doc(alias = #aliases)] )*
+    }
+}
+
+/// The data a transition stores for its target state, if the target has
+/// any: the `init:` method's result, the moved payload, or
+/// `StateData::initial()`. With `by_value`, `init:` takes the payload by
+/// value.
+///
+/// Evaluated while `self` is still the source machine.
+fn target_data(
+    machine: &StateMachine,
+    target: &Ident,
+    init: Option<&Ident>,
+    payload: Option<&syn::Type>,
+    by_value: bool,
+) -> Option<TokenStream2> {
+    let core_path = quote!(::state_machines::core);
+    match machine.data_init(target, init, payload) {
+        DataInit::None => None,
+        DataInit::Default(ty) => Some(quote_spanned! {ty.span()=>
+            <#ty as #core_path::StateData>::initial()
+        }),
+        DataInit::Init(init) => {
+            let ctx_ty = machine.ctx_type();
+            let ty = &machine.state_storage_for(target)?.ty;
+            let (payload_arg, payload_ref) = match payload {
+                Some(payload_ty) if by_value => (quote! { , #payload_ty }, quote! { , payload }),
+                Some(payload_ty) => (quote! { , &#payload_ty }, quote! { , &payload }),
+                None => (quote! {}, quote! {}),
+            };
+            Some(quote_spanned! {init.span()=> {
+                let _: fn(&Self, &#ctx_ty #payload_arg) -> #ty = Self::#init;
+                self.#init(&self.ctx #payload_ref)
+            }})
+        }
+        DataInit::Payload => Some(quote! { payload }),
+    }
+}
+
+/// Report a transition step to the active `TraceRecorder`.
+///
+/// Emits nothing unless the `trace` feature is enabled; `step` is a
+/// `TraceStep` variant, e.g. `Before { callback: "log" }`.
+fn trace_step(machine: &StateMachine, event: &Ident, step: TokenStream2) -> TokenStream2 {
+    trace_step_on(machine, event, &quote! { self }, step)
+}
+
+/// Like `trace_step`, for steps that reach the machine through `receiver`.
+fn trace_step_on(
+    machine: &StateMachine,
+    event: &Ident,
+    receiver: &TokenStream2,
+    step: TokenStream2,
+) -> TokenStream2 {
+    if !cfg!(feature = "trace") {
+        return quote! {};
+    }
+    let machine_name = machine.name.to_string();
+    let event_name = event.to_string();
+    let id = machine.id_of(receiver.clone());
+    quote! {
+        ::state_machines::__private::trace(
+            #machine_name,
+            #id,
+            #event_name,
+            ::state_machines::trace::TraceStep::#step,
+        );
+    }
+}
+
+/// Generate the boolean expression for a guard condition.
+///
+/// `leaf` produces the tokens that call a single guard method with its
+/// literal arguments; operators map directly to Rust's `!`, `&&`, and `||`
+/// so evaluation short-circuits.
+pub(super) fn guard_expr_tokens(
+    expr: &GuardExpr,
+    leaf: &dyn Fn(&Ident, &[syn::Expr]) -> TokenStream2,
+) -> TokenStream2 {
+    match expr {
+        GuardExpr::Guard(guard, args) => leaf(guard, args),
+        GuardExpr::Not(inner) => {
+            let inner = guard_expr_tokens(inner, leaf);
+            quote! { !(#inner) }
+        }
+        GuardExpr::And(lhs, rhs) => {
+            let lhs = guard_expr_tokens(lhs, leaf);
+            let rhs = guard_expr_tokens(rhs, leaf);
+            quote! { ((#lhs) && (#rhs)) }
+        }
+        GuardExpr::Or(lhs, rhs) => {
+            let lhs = guard_expr_tokens(lhs, leaf);
+            let rhs = guard_expr_tokens(rhs, leaf);
+            quote! { ((#lhs) || (#rhs)) }
+        }
+    }
+}
+
+/// Generate a `bool` expression that's true when every `guards` entry passes
+/// and no `unless` entry does, without tracing or caching.
+pub(super) fn guards_pass_tokens(
+    guards: &[&GuardExpr],
+    unless: &[&GuardExpr],
+    leaf: &dyn Fn(&Ident, &[syn::Expr]) -> TokenStream2,
+) -> TokenStream2 {
+    let passes = guards.iter().map(|expr| guard_expr_tokens(expr, leaf));
+    let blocks = unless.iter().map(|expr| guard_expr_tokens(expr, leaf));
+    quote! { true #( && (#passes) )* #( && !(#blocks) )* }
+}
+
+/// `.await` a guard, action, or callback call.
+///
+/// With `callback_timeout`, the call is raced against the machine's timer and
+/// `on_timeout` (usually an early return) is evaluated if the deadline passes.
+pub(super) fn await_bounded(
+    machine: &StateMachine,
+    call: TokenStream2,
+    on_timeout: TokenStream2,
+) -> TokenStream2 {
+    let Some(timeout) = &machine.callback_timeout else {
+        return quote! { #call.await };
+    };
+    let after = &timeout.after;
+    let timer = match &timeout.timer {
+        Some(timer) => quote! { #timer },
+        None => quote! { ::state_machines::timer::Tokio },
+    };
+    quote! {
+        match ::state_machines::core::with_timeout::<#timer, _>(#after, #call).await {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(_) => #on_timeout,
+        }
     }
 }
 
@@ -777,7 +2100,6 @@ fn generate_storage_accessors(machine: &StateMachine) -> Result<Vec<TokenStream2
 /// The method names are unique per state to avoid conflicts.
 fn generate_state_specific_accessors(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
     let mut impls = Vec::new();
-    let machine_name = &machine.name;
 
     for spec in &machine.state_storage {
         let state_name = &spec.state_name;
@@ -786,22 +2108,16 @@ fn generate_state_specific_accessors(machine: &StateMachine) -> Result<Vec<Token
 
         // Generate method names from state name: LaunchPrep -> launch_prep_data
         let state_str = state_name.to_string();
-        let snake = crate::codegen::utils::to_snake_case(&state_str);
+        let snake = state_machines_dsl::utils::to_snake_case(&state_str);
         let data_method = syn::Ident::new(&format!("{}_data", snake), state_name.span());
         let data_mut_method = syn::Ident::new(&format!("{}_data_mut", snake), state_name.span());
 
-        // Determine impl generics and type parameters
-        let (impl_generics, type_params) = if machine.context.is_some() {
-            // Concrete context (struct is Machine<S>)
-            (quote! {}, quote! { <#state_name> })
-        } else {
-            // Generic context (struct is Machine<C, S>)
-            (quote! { <C> }, quote! { <C, #state_name> })
-        };
+        let impl_generics = machine.impl_generics();
+        let machine_ty = machine.machine_type(state_name);
 
         // Generate state-specific impl block
         let impl_block = quote! {
-            impl #impl_generics #machine_name #type_params {
+            impl #impl_generics #machine_ty {
                 /// Access the state-associated data for this specific state.
                 ///
                 /// This method is guaranteed to return a reference because
@@ -865,52 +2181,127 @@ fn generate_substate_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>>
 /// This allows the transition to be called from any substate of the superstate.
 fn generate_superstate_transition_impls(machine: &StateMachine) -> Result<Vec<TokenStream2>> {
     let mut impls = Vec::new();
-    let machine_name = &machine.name;
 
     // Group transitions by superstate
     for superstate in machine.hierarchy.all_superstates() {
         // Find all transitions that originate from this superstate
-        if let Some(edges) = machine.transition_graph.outgoing(&superstate) {
-            let methods: Vec<_> = edges
-                .iter()
-                .map(|edge| generate_superstate_transition_method(machine, &superstate, edge))
-                .collect::<Result<Vec<_>>>()?;
-
-            if !methods.is_empty() {
-                // Determine impl generics and type parameters for superstate transitions
-                let (impl_generics, type_params) = if machine.context.is_some() {
-                    // Concrete context (struct is Machine<S>)
-                    (
-                        quote! { <S: ::state_machines::SubstateOf<#superstate>> },
-                        quote! { <S> },
-                    )
-                } else {
-                    // Generic context (struct is Machine<C, S>)
-                    (
-                        quote! { <C, S: ::state_machines::SubstateOf<#superstate>> },
-                        quote! { <C, S> },
-                    )
-                };
-
-                let impl_block = quote! {
-                    impl #impl_generics #machine_name #type_params {
-                        #( #methods )*
-                    }
-                };
-                impls.push(impl_block);
-            }
+        let mut methods: Vec<_> = machine
+            .transition_graph
+            .outgoing(&superstate)
+            .into_iter()
+            .flatten()
+            .map(|edge| generate_superstate_transition_method(machine, &superstate, edge))
+            .collect::<Result<Vec<_>>>()?;
+        methods.extend(generate_superstate_probes(machine, &superstate));
+
+        if !methods.is_empty() {
+            // Generic over any substate of the superstate
+            let params = machine.generic_params();
+            let machine_ty = machine.machine_type(quote! { S });
+
+            let impl_block = quote! {
+                impl<#(#params,)* S: ::state_machines::SubstateOf<#superstate>> #machine_ty {
+                    #( #methods )*
+                }
+            };
+            impls.push(impl_block);
         }
     }
 
     Ok(impls)
 }
 
+/// Generate guard-only `can_*` probes for events leaving a superstate.
+///
+/// The transition methods themselves are generated per leaf state; the probe
+/// lives on the `SubstateOf` impl so code generic over the substate can check
+/// feasibility without consuming the machine. An event whose transitions
+/// leave more than one superstate gets no probe, since the impls would overlap.
+///
+/// # Example Output
+///
+/// ```rust,ignore
+/// impl<C, S: SubstateOf<Flight>> Machine<C, S> {
+///     pub fn can_abort(&self) -> bool { self.abort_allowed(&self.ctx) }
+/// }
+/// ```
+fn generate_superstate_probes(machine: &StateMachine, superstate: &Ident) -> Vec<TokenStream2> {
+    let is_async = machine.async_mode;
+
+    machine
+        .events
+        .iter()
+        .filter_map(|event| {
+            let mut superstates: Vec<&Ident> = Vec::new();
+            for source in event.transitions.iter().flat_map(|t| &t.sources) {
+                if machine.hierarchy.is_superstate(source) && !superstates.contains(&source) {
+                    superstates.push(source);
+                }
+            }
+            if superstates != [superstate] {
+                return None;
+            }
+
+            // Substates share the superstate's edge, so the first leaf speaks for all
+            let leaf = machine
+                .hierarchy
+                .expand_state(superstate, &machine.states)
+                .into_iter()
+                .next()?;
+            let edge = machine.edge_for(&leaf, &event.name)?;
+            let method_name = quote::format_ident!("can_{}", to_snake_case_ident(&event.name));
+
+            let (params, payload_ref) = match &event.payload {
+                Some(payload_ty) => (quote! { , payload: &#payload_ty }, quote! { , payload }),
+                None => (quote! {}, quote! {}),
+            };
+            let lifetimes = machine.method_lifetimes(event.payload.as_ref());
+            let guard_value = |guard: &Ident, args: &[syn::Expr]| {
+                let call =
+                    quote_spanned! {guard.span()=> self.#guard(&self.ctx #payload_ref #(, #args)*) };
+                if machine.guard_is_async(guard) {
+                    await_bounded(machine, call, quote! { false })
+                } else {
+                    call
+                }
+            };
+            let guards: Vec<_> = edge.guards.iter().collect();
+            let unless: Vec<_> = edge.unless.iter().collect();
+            let check = guards_pass_tokens(&guards, &unless, &guard_value);
+            // Guards see the transformed payload, so probes transform a copy
+            let transform = machine.event_transform(&event.name).map(|transform| {
+                quote! {
+                    let payload = &self.#transform(&self.ctx, ::core::clone::Clone::clone(payload));
+                }
+            });
+
+            let doc = format!(
+                " Whether `{}` would pass its guards from any substate of `{}`.",
+                to_snake_case_ident(&event.name),
+                superstate
+            );
+            let asyncness = is_async.then(|| quote! { async });
+            let vis = machine.event_visibility(&event.name);
+            Some(quote! {
+                #[doc = #doc]
+                #[doc = ""]
+                #[doc = " Only guards and `unless` guards run; callbacks and actions don't."]
+                #[allow(unused_parens)]
+                #vis #asyncness fn #method_name #lifetimes(&self #params) -> bool {
+                    #transform
+                    #check
+                }
+            })
+        })
+        .collect()
+}
+
 /// Generate a transition method for a superstate transition.
 ///
 /// Similar to generate_transition_method but works with generic substates.
 fn generate_superstate_transition_method(
     machine: &StateMachine,
-    _superstate: &Ident,
+    superstate: &Ident,
     edge: &TransitionEdge,
 ) -> Result<TokenStream2> {
     // This is similar to generate_transition_method but simpler
@@ -923,44 +2314,40 @@ fn generate_superstate_transition_method(
 
     let target_state = &edge.target;
     let is_async = machine.async_mode;
+    let docs = transition_method_docs(machine, superstate, edge);
     let core_path = quote!(::state_machines::core);
 
     // Build method signature (no payload support for now)
+    let vis = machine.event_visibility(event_name);
+    let unsafety = machine.event_unsafety(event_name);
     let method_sig = if is_async {
         quote! {
-            pub async fn #method_name(self)
+            #vis async #unsafety fn #method_name(self)
         }
     } else {
         quote! {
-            pub fn #method_name(self)
+            #vis #unsafety fn #method_name(self)
         }
     };
 
-    // Determine return type - depends on whether context is concrete or generic
-    let return_type = if machine.context.is_some() {
-        // Concrete context: struct is Machine<S>, so return Machine<TargetState>
-        quote! {
-            ::core::result::Result<#machine_name<#target_state>, (Self, #core_path::GuardError)>
-        }
-    } else {
-        // Generic context: struct is Machine<C, S>, so return Machine<C, TargetState>
-        quote! {
-            ::core::result::Result<#machine_name<C, #target_state>, (Self, #core_path::GuardError)>
-        }
+    let target_ty = machine.machine_type(target_state);
+    let return_type = quote! {
+        ::core::result::Result<#target_ty, (Self, #core_path::GuardError)>
     };
 
     // Build storage field transfers for target state
+    let data = target_data(machine, target_state, edge.init.as_ref(), None, false)
+        .map(|data| quote! { let __data = #data; });
     let storage_transfers: Vec<_> = machine
         .state_storage
         .iter()
         .map(|spec| {
             let field = &spec.field;
             let state_name = &spec.state_name;
-            let ty = &spec.ty;
 
             if state_name == target_state {
                 quote! {
-                    #field: ::core::option::Option::Some(<#ty as ::core::default::Default>::default())
+                    #field: ::core::option::Option::Some(__data)
                 }
             } else {
                 quote! {
@@ -970,13 +2357,22 @@ fn generate_superstate_transition_method(
         })
         .collect();
 
+    let requires = machine.requirement_bounds(std::slice::from_ref(target_state));
+    let aliases = alias_methods(machine, event_name, None, &return_type, &requires);
+    let id_transfer = machine.id_field_init(quote! { self.id });
+
     Ok(quote! {
-        #method_sig -> #return_type {
+        #aliases
+
+        #docs
+        #method_sig -> #return_type #requires {
+            #data
             // Create new machine with target state
             let new_machine = #machine_name {
                 ctx: self.ctx,
                 _state: ::core::marker::PhantomData,
                 #( #storage_transfers, )*
+                #id_transfer
             };
 
             ::core::result::Result::Ok(new_machine)
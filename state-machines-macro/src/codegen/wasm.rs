@@ -0,0 +1,155 @@
+//! `wasm-bindgen` class generation.
+//!
+//! Wraps the dynamic machine in a JavaScript-facing class so the same machine
+//! definition can drive a Rust backend and a browser front-end. Only generated
+//! when the macro specifies `wasm: true`, which requires the `wasm-bindgen`
+//! feature of the `state-machines` crate (it provides the re-exported
+//! `wasm-bindgen`, `js-sys`, and `serde_json` paths used below).
+//!
+//! # Example Generated Code
+//!
+//! ```rust,ignore
+//! #[wasm_bindgen]
+//! pub struct WasmTrafficLight { /* dynamic machine + callback */ }
+//!
+//! #[wasm_bindgen]
+//! impl WasmTrafficLight {
+//!     #[wasm_bindgen(constructor)]
+//!     pub fn new() -> Self { ... }
+//!     pub fn handle(&mut self, event_name: &str, payload_json: Option<String>) -> Result<(), JsValue> { ... }
+//!     #[wasm_bindgen(js_name = currentState)]
+//!     pub fn current_state(&self) -> String { ... }
+//!     #[wasm_bindgen(js_name = onTransition)]
+//!     pub fn on_transition(&mut self, callback: js_sys::Function) { ... }
+//! }
+//! ```
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use state_machines_dsl::types::*;
+use state_machines_dsl::utils::to_pascal_case;
+use syn::Result;
+
+/// Generate the `Wasm{Name}` class for a machine declared with `wasm: true`.
+///
+/// Events are looked up by their snake_case name. Payload events decode
+/// their payload from the JSON string argument, so payload types must
+/// implement `serde::Deserialize`.
+pub fn generate_wasm_bindings(machine: &StateMachine) -> Result<TokenStream2> {
+    let machine_name = &machine.name;
+    let wasm_name = quote::format_ident!("Wasm{}", machine_name);
+    let dynamic_name = quote::format_ident!("Dynamic{}", machine_name);
+    let event_name = quote::format_ident!("{}Event", machine_name);
+
+    let private = quote!(::state_machines::__private);
+    let wasm_bindgen = quote!(#private::wasm_bindgen);
+    let js_value = quote!(#wasm_bindgen::JsValue);
+    let string = quote!(#private::String);
+    let format = quote!(#private::format);
+
+    // JavaScript classes can't be generic, so generic-context machines use `()`
+    let dynamic_ty = if machine.context.is_some() {
+        quote! { #dynamic_name }
+    } else {
+        quote! { #dynamic_name<()> }
+    };
+
+    // Map event names (and JSON payloads) to event enum values
+    let event_arms = machine.events.iter().map(|event| {
+        let name_str = event.name.to_string();
+        let pascal_name =
+            syn::Ident::new(&to_pascal_case(&event.name.to_string()), event.name.span());
+        // `alias:` names resolve to the same event
+        let aliases = event.aliases.iter().map(|alias| alias.to_string());
+        let pattern = quote! { #name_str #( | #aliases )* };
+
+        if event.payload.is_some() {
+            quote! {
+                #pattern => {
+                    let json = payload_json.as_deref().ok_or_else(|| {
+                        #js_value::from_str(&#format!("event `{}` requires a JSON payload", #name_str))
+                    })?;
+                    let payload = #private::serde_json::from_str(json).map_err(|err| {
+                        #js_value::from_str(&#format!("invalid payload for `{}`: {}", #name_str, err))
+                    })?;
+                    #event_name::#pascal_name(payload)
+                }
+            }
+        } else {
+            quote! { #pattern => #event_name::#pascal_name }
+        }
+    });
+
+    Ok(quote! {
+        /// JavaScript-facing wrapper around the dynamic machine.
+        #[#wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = #wasm_bindgen)]
+        pub struct #wasm_name {
+            machine: #dynamic_ty,
+            on_transition: ::core::option::Option<#private::js_sys::Function>,
+        }
+
+        #[#wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = #wasm_bindgen)]
+        impl #wasm_name {
+            /// Create a machine in the initial state with a default context.
+            #[wasm_bindgen(constructor)]
+            pub fn new() -> Self {
+                Self {
+                    machine: <#dynamic_ty>::new(::core::default::Default::default()),
+                    on_transition: ::core::option::Option::None,
+                }
+            }
+
+            /// Dispatch an event by name, decoding its payload from JSON if it has one.
+            ///
+            /// On success the `onTransition` callback (if any) is invoked with
+            /// `(from, to, eventName)`.
+            pub fn handle(
+                &mut self,
+                event_name: &str,
+                payload_json: ::core::option::Option<#string>,
+            ) -> ::core::result::Result<(), #js_value> {
+                let event = match event_name {
+                    #(#event_arms,)*
+                    other => {
+                        return ::core::result::Result::Err(#js_value::from_str(
+                            &#format!("unknown event `{}`", other),
+                        ));
+                    }
+                };
+
+                let from = self.machine.current_state();
+                self.machine
+                    .handle(event)
+                    .map_err(|err| #js_value::from_str(&#format!("{:?}", err)))?;
+
+                if let ::core::option::Option::Some(callback) = &self.on_transition {
+                    callback.call3(
+                        &#js_value::NULL,
+                        &#js_value::from_str(from),
+                        &#js_value::from_str(self.machine.current_state()),
+                        &#js_value::from_str(event_name),
+                    )?;
+                }
+                ::core::result::Result::Ok(())
+            }
+
+            /// Name of the current state.
+            #[wasm_bindgen(js_name = currentState)]
+            pub fn current_state(&self) -> #string {
+                #string::from(self.machine.current_state())
+            }
+
+            /// Register a `(from, to, eventName) => void` callback run after each transition.
+            #[wasm_bindgen(js_name = onTransition)]
+            pub fn on_transition(&mut self, callback: #private::js_sys::Function) {
+                self.on_transition = ::core::option::Option::Some(callback);
+            }
+        }
+
+        impl ::core::default::Default for #wasm_name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    })
+}
@@ -5,12 +5,21 @@
 //!
 //! # Architecture
 //!
-//! The macro implementation is split into several modules:
+//! The definition itself is handled by the internal `state-machines-dsl`
+//! crate, which `cargo-state-machines` shares:
 //!
 //! - **types**: Data structures representing state machines
 //! - **parser**: Parsing macro input into our data structures
-//! - **codegen**: Generating Rust code from the parsed structures
 //! - **validation**: Validating state machine definitions
+//! - **struct_form**: Rewriting the rustfmt-friendly `Machine { ... }` form into the DSL
+//! - **extends**: Merging an `extends:` base definition into the input
+//! - **fragment**: The `fragment!` macro, and merging `include:` fragments
+//! - **params**: Substituting `params:` constants into the definition
+//!
+//! This crate generates the code:
+//!
+//! - **codegen**: Generating Rust code from the parsed structures
+//! - **assertions**: The `assert_machine!` compile-time checks
 //!
 //! # Example
 //!
@@ -36,10 +45,8 @@
 use proc_macro::TokenStream;
 
 // Module declarations
+mod assertions;
 mod codegen;
-mod parser;
-mod types;
-mod validation;
 
 /// Define a state machine with compile-time guarantees.
 ///
@@ -59,25 +66,89 @@ mod validation;
 ///     state: StateName,             // Required: name of the state enum
 ///     initial: InitialState,        // Required: initial state
 ///     async: true,                  // Optional: enable async support
+///     dynamic: true,                // Optional: generate the runtime dispatch wrapper
+///     mode: both,                   // Optional, instead of `dynamic`: `typestate`, `runtime`,
+///                                   // or `both` machines, regardless of the `dynamic` feature
+///     compact_codegen: true,        // Optional: table-driven dispatch for callback-free edges
+///     transition_records: true,     // Optional: dynamic `handle()` returns a `Transition` record
+///     atomic_state: true,           // Optional: mirror the dynamic state into an `AtomicState`
+///     data_states: true,            // Optional: `{Name}State` variants carry their state data
+///     watchdog: true,               // Optional: dynamic `time_in_state()` and `stuck_check()`
+///     clock: TestClock,             // Optional: the `Clock` for the watchdog and records (default: `SystemClock`)
+///     history: 32,                  // Optional: dynamic `history()` of the last 32 transitions
+///     stats: true,                  // Optional: dynamic per-state `stats()` (`stats` feature)
+///     persistent: true,             // Optional: implement `DynamicMachine` (persistence, queues, REPL)
+///     identity: true,               // Optional: `with_id()` tags traces, notifications, and records
+///     evaluate_all_guards: true,    // Optional: run every guard entry before rejecting (default: stop at the first failure)
+///     no_default_init: true,        // Optional: state data comes from `init:` or the payload, not `Default`
+///     mailbox: { capacity: 16, overflow: drop_oldest }, // Optional: ISR-safe event queue
+///     unhandled: ignore,            // Optional: `error` (default), `ignore`, `panic`, or
+///                                   // `callback(name)` for events with no transition
+///     visibility: pub(crate),       // Optional: visibility of generated types (default `pub`)
+///     module: door_machine,         // Optional: wrap generated items in `mod door_machine`
+///     extendable: true,             // Optional: other machines in the crate can `extends:` this one
+///     extends: base::Device,        // Optional: start from an `extendable` machine's definition;
+///                                   // `states` and `events` entries add to or replace the base's
+///     include: [Retryable, Retryable { prefix: Upload }], // Optional: merge in `fragment!` states
+///                                   // and events, optionally renamed `UploadFailed`/`upload_retry`
+///     behavior: true,               // Optional: generate guards and callbacks that call the
+///                                   // `Behavior` in `context: WithBehavior<...>` by name
+///     params: { MaxRetries: 3, StartTimeout: 30s }, // Optional: literals substituted wherever
+///                                   // the names appear, e.g. `guards: [below(MaxRetries)]`
+///     generics: <'a, T: Sensor>,    // Optional: extra lifetime/type parameters on the machine
+///     state_derives: [PartialOrd, Ord], // Optional: extra derives on `{Name}State`
+///     event_derives: [Clone],       // Optional: extra derives on `{Name}Event`
+///     state_attrs: [#[repr(u8)]],   // Optional: extra attributes on `{Name}State`
+///     machine_attrs: [#[must_use]], // Optional: extra attributes on the machine struct
+///     wasm: true,                   // Optional: wasm-bindgen class (needs `wasm-bindgen` feature)
+///     http: true,                   // Optional: serve with `state_machines::http::router` (needs `axum` feature)
 ///     action: action_method,        // Optional: method called on every transition
+///     sink: outbox,                 // Optional: `fn(&mut self) -> &mut impl Sink<{Name}Notification>`
+///                                   // receiving the transitions' `emit:` notifications
 ///
 ///     states: [                     // Required: list of states
 ///         StateA,
 ///         StateB(DataType),         // States can have associated data
+///         StateC { doc: "..." },    // Optional: rustdoc for the state
+///         StateD { ignore: [tick] }, // Optional: events dispatched as no-ops (dynamic mode)
+///         StateE { max_duration: 30s }, // Optional: dwell limit checked by `stuck_check()`
+///         StateH { requires: [Radio] }, // Optional: traits the context needs to enter the state
+///         #[cfg(feature = "x")] StateF, // Optional: `#[cfg]` on states, events, and transitions
+///         StateG = 7,               // Optional: stable code for `as_code()` (dynamic mode;
+///                                   // events too: `event_name = 3 { ... }`)
 ///         superstate Parent {       // Superstates for hierarchical machines
 ///             state Child1,
 ///             state Child2,
 ///             initial: Child1,      // Superstate's initial child
+///             doc: "...",           // Optional: rustdoc for the superstate
+///             ignore: [tick],       // Optional: ignored in every child state
+///             requires: [Power],    // Optional: needed to enter any child state
 ///         }
 ///     ],
 ///
 ///     events: {                     // Optional: event definitions
-///         event_name {
+///         event_name {              // Optional prefix: `pub(crate) unsafe event_name { ... }`
+///                                   // restricts the event's methods (`unsafe` needs no
+///                                   //  runtime wrapper)
+///             doc: "...",           // Optional: shown on the generated methods
+///             alias: [other_name],  // Optional: more method names for the event
 ///             payload: PayloadType, // Optional: event payload type
+///                                   // (`PayloadType by value` moves it into the last `after`
+///                                   //  callback, or `init:` without any)
+///             transform: normalize, // Optional: rewrite the payload before guards and callbacks
 ///             guards: [guard1],     // Optional: event-level guards
+///                                   // (`guard1 { cache: true }` evaluates it once per transition;
+///                                   //  entries may combine guards: `[a && (b || !c)]`
+///                                   //  and pass literals: `[min_altitude(100)]`)
 ///             unless: [guard2],     // Optional: inverted guards
 ///             before: [callback1],  // Optional: before callbacks
 ///             after: [callback2],   // Optional: after callbacks
+///                                   // (`{ name: cb, from: [A, B], to: C }` runs `cb` only on
+///                                   //  matching edges, here and at transition level;
+///                                   //  `{ name: cb, split: true }` calls
+///                                   //  `fn cb(&mut StateData, &C[, &payload])`)
+///             after_commit: [notify], // Optional: `fn(&self)`, once nothing can undo the transition
+///             dangerous: true,      // Optional: `cargo state-machines lint` requires a guard
 ///
 ///             transition: {
 ///                 from: SourceState,
@@ -86,19 +157,30 @@ mod validation;
 ///                 unless: [guard4], // Optional: transition-level unless
 ///                 before: [cb3],    // Optional: transition-level before
 ///                 after: [cb4],     // Optional: transition-level after
+///                 after_commit: [cb5], // Optional: transition-level after_commit
+///                 action: act,      // Optional: `fn(&mut ctx[, &payload]) -> bool`,
+///                                   // runs after before callbacks; `false` vetoes
+///                 compensate: [undo], // Optional: run in reverse if the action vetoes
+///                                   // or a before callback panics
+///                 emit: OrderPaid,  // Optional: send `{Name}Notification::OrderPaid` to `sink`
+///                 init: build,      // Optional: `fn(&self, &ctx[, &payload]) -> Data` builds the
+///                                   // target state's data instead of `Default`
+///                 forward: { to: session, as: SessionEvent::Start }, // Optional: hand an event
+///                                   // to `fn session(&mut self) -> &mut impl Forward<E>` afterwards
 ///             }
+///             // Or pick the target at runtime: `to` lists the candidates and
+///             // `choose` returns one of them as a `{Name}{Event}Choice`
+///             transition: { from: SourceState, to: [StateB, StateC], choose: chooser }
 ///         }
 ///     },
 ///
-///     callbacks: {                  // Optional: global callbacks
-///         before_transition [
-///             { name: log_transition, from: [StateA], to: [StateB], on: [event] }
+///     callbacks: {                  // Optional: global callbacks, filtered by `from`/`to`/`on`
+///         before_transition [       // `fn(&self) -> ControlFlow<()>`, highest `priority` first;
+///                                   // `Break` skips the rest of the chain
+///             { name: log_transition, from: [StateA], to: [StateB], on: [event], priority: 10 }
 ///         ],
-///         after_transition [
+///         after_transition [        // `fn(&self)`, after the event's own after callbacks
 ///             { name: after_cb }
-///         ],
-///         around_transition [
-///             { name: wrap_cb }
 ///         ]
 ///     }
 /// }
@@ -115,7 +197,7 @@ mod validation;
 ///    - Event methods (e.g., `activate()`)
 ///    - Can methods (e.g., `can_activate()`)
 ///    - Storage accessors for state-associated data
-/// 3. A `DEFINITION` constant for runtime introspection
+/// 3. In dynamic mode, `{Name}State::definition()` for runtime introspection
 /// 4. Trait implementations (Machine, Default, Debug)
 ///
 /// # Features
@@ -135,15 +217,70 @@ mod validation;
 /// extensive documentation explaining the what, why, and how.
 #[proc_macro]
 pub fn state_machine(input: TokenStream) -> TokenStream {
-    // Parse the macro input into our StateMachine structure
-    // The Parse trait implementation is in parser.rs
-    let machine = syn::parse_macro_input!(input as types::StateMachine);
+    // Parse the macro input into our StateMachine structure and expand it,
+    // once per combination of `#[cfg]` predicates on its entries
+    // The Parse trait implementation is in state-machines-dsl, expand() in codegen
+    match codegen::expand_input(input.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
 
-    // Expand the machine into generated code
-    // This validates the definition and generates all the code
-    // The expand() method is in codegen.rs
-    match machine.expand() {
+/// A named set of states and events that machines pull in with `include:`.
+///
+/// ```ignore
+/// fragment! {
+///     name: Retryable,
+///     states: [Failed, Retrying],
+///     events {
+///         retry { transition: { from: Failed, to: Retrying } }
+///         give_up { transition: { from: [Failed, Retrying], to: Idle } }
+///     }
+/// }
+///
+/// state_machine! {
+///     name: Upload,
+///     initial: Idle,
+///     states: [Idle, Sending],
+///     include: [Retryable, Retryable { prefix: Verify }],
+///     // ...
+/// }
+/// ```
+///
+/// A fragment takes `states`, `events`, and `callbacks`, which may name the
+/// including machine's states, like `Idle` above. With a `prefix`, its
+/// states and events are renamed (`VerifyFailed`, `verify_retry`), so one
+/// machine can include a fragment more than once. The machine's own entries
+/// win over the fragment's. Like `extends:`, it only works within a crate.
+#[proc_macro]
+pub fn fragment(input: TokenStream) -> TokenStream {
+    match state_machines_dsl::fragment::expand(input.into()) {
         Ok(tokens) => tokens.into(),
         Err(err) => err.to_compile_error().into(),
     }
 }
+
+/// Compile-time checks of a machine's structure, for rules a review would
+/// otherwise have to catch.
+///
+/// ```ignore
+/// assert_machine!(Door:
+///     can_reach(Closed => Open),     // some sequence of events leads there
+///     can_reach_from_all(Locked),    // every state can get there
+///     has_transition(Open, close),   // `close` leaves `Open`
+///     terminal(Scrapped),            // no transition leaves `Scrapped`
+///     no_orphan_states,              // the initial state reaches every state
+///     deterministic,                 // one target per event and state
+/// );
+/// ```
+///
+/// Each check expands to a `const` assertion against `{Name}State::TABLE`,
+/// so the machine needs the dynamic state enum, and a failing check is a
+/// compile error. Guards aren't evaluated.
+#[proc_macro]
+pub fn assert_machine(input: TokenStream) -> TokenStream {
+    match syn::parse::<assertions::MachineAssertions>(input) {
+        Ok(assertions) => assertions.expand().into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
@@ -0,0 +1,135 @@
+//! A Redis-backed [`StateStore`] for
+//! [`state-machines`](https://docs.rs/state-machines).
+//!
+//! Wrap a `persistent: true` machine in a [`SharedMachine`] over a
+//! [`RedisStore`] and every node of a horizontally scaled service can drive
+//! the same machine: each transition is saved with a compare-and-swap on the
+//! state's version, so the node that loses a race gets
+//! [`SharedError::StaleState`](state_machines::store::SharedError::StaleState)
+//! instead of overwriting the winner.
+//!
+//! ```ignore
+//! let conn = redis::Client::open("redis://127.0.0.1/")?
+//!     .get_multiplexed_async_connection()
+//!     .await?;
+//! let store = RedisStore::new(conn).prefix("orders:");
+//! let mut order = SharedMachine::open(store, "42", DynamicOrder::new(ctx)).await?;
+//! order.transition(OrderEvent::Pay).await?;
+//! ```
+//!
+//! Each machine is a hash at `{prefix}{id}` with `state` and `version` fields.
+
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::{RedisError, Script};
+pub use state_machines::store::{SharedError, SharedMachine, StateStore, Swap, Versioned};
+
+/// Write `ARGV[2]` if the stored version is `ARGV[1]` (0 when the key is
+/// missing). Returns `{1, new_version, state}` or `{0, version, state}`.
+const COMPARE_AND_SWAP: &str = r"
+local current = tonumber(redis.call('HGET', KEYS[1], 'version')) or 0
+if current ~= tonumber(ARGV[1]) then
+    return {0, current, redis.call('HGET', KEYS[1], 'state')}
+end
+redis.call('HSET', KEYS[1], 'state', ARGV[2], 'version', current + 1)
+return {1, current + 1, ARGV[2]}
+";
+
+/// Write `ARGV[1]` unconditionally and return the new version.
+const SAVE: &str = r"
+local version = redis.call('HINCRBY', KEYS[1], 'version', 1)
+redis.call('HSET', KEYS[1], 'state', ARGV[1])
+return version
+";
+
+/// Machine states stored in Redis hashes.
+///
+/// Works over any cloneable async connection; clones are expected to share
+/// the underlying connection, as `MultiplexedConnection` and
+/// `ConnectionManager` do.
+#[derive(Clone)]
+pub struct RedisStore<C = MultiplexedConnection> {
+    conn: C,
+    prefix: String,
+    compare_and_swap: Script,
+    save: Script,
+}
+
+impl<C> RedisStore<C> {
+    /// Store machines under `state-machines:{id}`.
+    pub fn new(conn: C) -> Self {
+        Self {
+            conn,
+            prefix: "state-machines:".to_string(),
+            compare_and_swap: Script::new(COMPARE_AND_SWAP),
+            save: Script::new(SAVE),
+        }
+    }
+
+    /// Store machines under `{prefix}{id}` instead.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, id: &str) -> String {
+        format!("{}{id}", self.prefix)
+    }
+}
+
+impl<C> core::fmt::Debug for RedisStore<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RedisStore")
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> StateStore for RedisStore<C>
+where
+    C: ConnectionLike + Clone + Send + Sync,
+{
+    type Error = RedisError;
+
+    async fn load(&self, id: &str) -> Result<Option<Versioned>, RedisError> {
+        let mut conn = self.conn.clone();
+        let (state, version): (Option<String>, Option<u64>) = redis::cmd("HMGET")
+            .arg(self.key(id))
+            .arg("state")
+            .arg("version")
+            .query_async(&mut conn)
+            .await?;
+        Ok(state
+            .zip(version)
+            .map(|(state, version)| Versioned { state, version }))
+    }
+
+    async fn save(&self, id: &str, state: &str) -> Result<u64, RedisError> {
+        let mut conn = self.conn.clone();
+        self.save
+            .key(self.key(id))
+            .arg(state)
+            .invoke_async(&mut conn)
+            .await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        id: &str,
+        expected: u64,
+        state: &str,
+    ) -> Result<Swap, RedisError> {
+        let mut conn = self.conn.clone();
+        let (swapped, version, stored): (bool, u64, Option<String>) = self
+            .compare_and_swap
+            .key(self.key(id))
+            .arg(expected)
+            .arg(state)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(if swapped {
+            Swap::Swapped(version)
+        } else {
+            Swap::Stale(stored.map(|state| Versioned { state, version }))
+        })
+    }
+}
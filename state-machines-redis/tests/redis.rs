@@ -0,0 +1,112 @@
+//! The tests are `#[ignore]`d, since they need the server in `REDIS_URL`;
+//! run them with `cargo test -p state-machines-redis -- --ignored`.
+//! Each test works under its own key prefix, deleted on success.
+
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use redis::aio::MultiplexedConnection;
+use state_machines::state_machine;
+use state_machines_redis::{RedisStore, SharedError, SharedMachine, StateStore, Swap, Versioned};
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    persistent: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            transition: { from: Paid, to: Shipped }
+        }
+    }
+}
+
+async fn store(prefix: &str) -> RedisStore {
+    let url = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
+    let conn = redis::Client::open(url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .expect("REDIS_URL unreachable");
+    clear(&conn, prefix).await;
+    RedisStore::new(conn).prefix(prefix)
+}
+
+async fn clear(conn: &MultiplexedConnection, prefix: &str) {
+    let _: () = redis::cmd("DEL")
+        .arg(format!("{prefix}order:1"))
+        .query_async(&mut conn.clone())
+        .await
+        .unwrap();
+}
+
+fn versioned(state: &str, version: u64) -> Versioned {
+    Versioned {
+        state: state.to_string(),
+        version,
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs REDIS_URL"]
+async fn missing_keys_load_as_none() {
+    let store = store("sm-test-missing:").await;
+    assert_eq!(store.load("order:1").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "needs REDIS_URL"]
+async fn compare_and_swap_checks_the_version() {
+    let store = store("sm-test-cas:").await;
+
+    assert_eq!(
+        store
+            .compare_and_swap("order:1", 0, "Pending")
+            .await
+            .unwrap(),
+        Swap::Swapped(1)
+    );
+    assert_eq!(
+        store.compare_and_swap("order:1", 0, "Paid").await.unwrap(),
+        Swap::Stale(Some(versioned("Pending", 1)))
+    );
+    assert_eq!(
+        store.compare_and_swap("order:1", 1, "Paid").await.unwrap(),
+        Swap::Swapped(2)
+    );
+    assert_eq!(store.save("order:1", "Shipped").await.unwrap(), 3);
+    assert_eq!(
+        store.load("order:1").await.unwrap(),
+        Some(versioned("Shipped", 3))
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs REDIS_URL"]
+async fn nodes_racing_on_a_machine_see_stale_state() {
+    let store = store("sm-test-race:").await;
+
+    let mut first = SharedMachine::open(store.clone(), "order:1", DynamicOrder::new(()))
+        .await
+        .unwrap();
+    let mut second = SharedMachine::open(store.clone(), "order:1", DynamicOrder::new(()))
+        .await
+        .unwrap();
+
+    first.transition(OrderEvent::Pay).await.unwrap();
+    let err = second.transition(OrderEvent::Pay).await.unwrap_err();
+    assert!(matches!(err, SharedError::StaleState { expected: 1, .. }));
+    assert_eq!(second.machine().current_state(), "Pending");
+
+    second.refresh().await.unwrap();
+    let task = tokio::spawn(async move { second.transition(OrderEvent::Ship).await.unwrap() });
+    task.await.unwrap();
+    assert_eq!(
+        store.load("order:1").await.unwrap(),
+        Some(versioned("Shipped", 3))
+    );
+}
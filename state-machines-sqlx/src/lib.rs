@@ -0,0 +1,173 @@
+//! Database persistence for [`state-machines`](https://docs.rs/state-machines)
+//! dynamic machines.
+//!
+//! [`PersistentMachine`] keeps a machine declared with `dynamic: true,
+//! persistent: true` in step with a row of a database table: it restores the
+//! machine from the row's state column, and runs each transition inside a
+//! transaction that updates the column and appends a row to a history table.
+//! If the write fails, the machine is moved back to the state it was in.
+//!
+//! Postgres is supported behind the default `postgres` feature.
+
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg(feature = "postgres")]
+mod postgres;
+
+#[cfg(feature = "postgres")]
+pub use postgres::PersistentMachine;
+
+use core::fmt;
+use state_machines::DynamicError;
+
+/// Where a machine's state and transition history are stored.
+///
+/// The state lives in `state_column` of `table`, on the row whose `id_column`
+/// matches the machine's id. Each transition adds a row to `history_table`:
+///
+/// ```sql
+/// CREATE TABLE order_transitions (
+///     id          BIGSERIAL PRIMARY KEY,
+///     machine_id  BIGINT NOT NULL,  -- same type as the machine table's id
+///     event       TEXT NOT NULL,
+///     from_state  TEXT NOT NULL,
+///     to_state    TEXT NOT NULL,
+///     created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+/// );
+/// ```
+///
+/// Names are spliced into SQL, so they're restricted to plain identifiers,
+/// optionally schema-qualified (`billing.orders`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateTable {
+    table: &'static str,
+    id_column: &'static str,
+    state_column: &'static str,
+    history_table: &'static str,
+}
+
+impl StateTable {
+    /// States in `table.state`, keyed by `table.id`, with history in `history_table`.
+    ///
+    /// # Panics
+    ///
+    /// If a name isn't a plain SQL identifier.
+    pub fn new(table: &'static str, history_table: &'static str) -> Self {
+        Self {
+            table: identifier(table),
+            id_column: "id",
+            state_column: "state",
+            history_table: identifier(history_table),
+        }
+    }
+
+    /// Use `column` instead of `id` to find the machine's row.
+    ///
+    /// # Panics
+    ///
+    /// If `column` isn't a plain SQL identifier.
+    pub fn id_column(mut self, column: &'static str) -> Self {
+        self.id_column = identifier(column);
+        self
+    }
+
+    /// Store the state name in `column` instead of `state`.
+    ///
+    /// # Panics
+    ///
+    /// If `column` isn't a plain SQL identifier.
+    pub fn state_column(mut self, column: &'static str) -> Self {
+        self.state_column = identifier(column);
+        self
+    }
+
+    /// `SELECT state FROM table WHERE id = $1`, with `FOR UPDATE` when `lock` is set.
+    fn select_sql(&self, lock: bool) -> String {
+        format!(
+            "SELECT {} FROM {} WHERE {} = $1{}",
+            self.state_column,
+            self.table,
+            self.id_column,
+            if lock { " FOR UPDATE" } else { "" }
+        )
+    }
+
+    fn update_sql(&self) -> String {
+        format!(
+            "UPDATE {} SET {} = $2 WHERE {} = $1",
+            self.table, self.state_column, self.id_column
+        )
+    }
+
+    fn history_sql(&self) -> String {
+        format!(
+            "INSERT INTO {} (machine_id, event, from_state, to_state) VALUES ($1, $2, $3, $4)",
+            self.history_table
+        )
+    }
+}
+
+/// Accept `name` or `schema.name` made of ASCII letters, digits, and underscores.
+fn identifier(name: &'static str) -> &'static str {
+    let valid = name.split('.').count() <= 2
+        && name.split('.').all(|part| {
+            part.chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    assert!(valid, "`{name}` is not a plain SQL identifier");
+    name
+}
+
+/// Why a [`PersistentMachine`] couldn't load or transition.
+#[derive(Debug)]
+pub enum PersistError {
+    /// The machine rejected the event; nothing was written.
+    Dynamic(DynamicError),
+    /// The database query failed; the machine is back in its previous state.
+    Database(sqlx::Error),
+    /// The state column holds a name the machine doesn't have.
+    UnknownState(String),
+    /// Another writer moved the row to `stored` while this machine was in
+    /// `expected`; nothing was written. Call `refresh()` to catch up.
+    Stale {
+        stored: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::Dynamic(err) => write!(f, "transition failed: {err:?}"),
+            PersistError::Database(err) => write!(f, "database error: {err}"),
+            PersistError::UnknownState(name) => write!(f, "unknown stored state `{name}`"),
+            PersistError::Stale { stored, expected } => write!(
+                f,
+                "stored state `{stored}` doesn't match the machine's state `{expected}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PersistError::Database(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for PersistError {
+    fn from(err: sqlx::Error) -> Self {
+        PersistError::Database(err)
+    }
+}
+
+impl From<DynamicError> for PersistError {
+    fn from(err: DynamicError) -> Self {
+        PersistError::Dynamic(err)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{PersistError, StateTable};
+use sqlx::{Encode, PgPool, Postgres, Type};
+use state_machines::DynamicMachine;
+
+/// A dynamic machine backed by a row of a Postgres table.
+///
+/// ```ignore
+/// let table = StateTable::new("orders", "order_transitions");
+/// let mut order = PersistentMachine::<DynamicOrder, i64>::load(pool, table, 42, Ledger::default()).await?;
+/// order.transition(OrderEvent::Pay).await?;
+/// ```
+///
+/// Each `transition()` locks the row, checks that it still holds the
+/// machine's state, dispatches the event, then writes the new state and a
+/// history row in the same transaction. Callbacks run while the row is
+/// locked. Changes they made to the context aren't undone if the write fails,
+/// but `after_commit` callbacks only run once the transaction has committed.
+#[derive(Debug)]
+pub struct PersistentMachine<M, Id> {
+    pool: PgPool,
+    table: StateTable,
+    id: Id,
+    machine: M,
+}
+
+impl<M, Id> PersistentMachine<M, Id>
+where
+    M: DynamicMachine,
+    Id: for<'q> Encode<'q, Postgres> + Type<Postgres> + Send + Sync,
+{
+    /// Restore the machine with `ctx` in the state stored on row `id`.
+    ///
+    /// Fails with `sqlx::Error::RowNotFound` if there's no such row.
+    pub async fn load(
+        pool: PgPool,
+        table: StateTable,
+        id: Id,
+        ctx: M::Context,
+    ) -> Result<Self, PersistError> {
+        let stored: String = sqlx::query_scalar(&table.select_sql(false))
+            .bind(&id)
+            .fetch_one(&pool)
+            .await?;
+        let state = parse_state::<M>(stored)?;
+        Ok(Self {
+            pool,
+            table,
+            id,
+            machine: M::restore(ctx, state),
+        })
+    }
+
+    /// Dispatch `event` and persist the resulting state.
+    ///
+    /// On error, the machine is left in the state it was in, with the data it
+    /// held, nothing is written, and the transition's `after_commit`
+    /// callbacks don't run.
+    pub async fn transition(&mut self, event: M::Event) -> Result<M::Output, PersistError> {
+        let from = self.machine.state();
+        let from_name = M::state_name(&from);
+        let event_name = M::event_name(&event);
+
+        let mut tx = self.pool.begin().await?;
+        let stored: String = sqlx::query_scalar(&self.table.select_sql(true))
+            .bind(&self.id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if stored != from_name {
+            return Err(PersistError::Stale {
+                stored,
+                expected: from_name,
+            });
+        }
+
+        let output = self.machine.dispatch_uncommitted(event).await?;
+        let to_name = M::state_name(&self.machine.state());
+
+        let written = async {
+            sqlx::query(&self.table.update_sql())
+                .bind(&self.id)
+                .bind(to_name)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&self.table.history_sql())
+                .bind(&self.id)
+                .bind(event_name)
+                .bind(from_name)
+                .bind(to_name)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await
+        }
+        .await;
+
+        match written {
+            Ok(()) => {
+                self.machine.commit().await;
+                Ok(output)
+            }
+            Err(err) => {
+                self.machine.rollback(from);
+                Err(PersistError::Database(err))
+            }
+        }
+    }
+
+    /// Move the machine to the state currently stored on its row, e.g. after
+    /// a `Stale` error.
+    pub async fn refresh(&mut self) -> Result<(), PersistError> {
+        let stored: String = sqlx::query_scalar(&self.table.select_sql(false))
+            .bind(&self.id)
+            .fetch_one(&self.pool)
+            .await?;
+        let state = parse_state::<M>(stored)?;
+        self.machine.restore_state(state);
+        Ok(())
+    }
+
+    /// The row id the machine is stored under.
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+
+    /// The wrapped machine, for queries such as `current_state()`.
+    pub fn machine(&self) -> &M {
+        &self.machine
+    }
+
+    /// Stop persisting and return the machine.
+    pub fn into_inner(self) -> M {
+        self.machine
+    }
+}
+
+fn parse_state<M: DynamicMachine>(stored: String) -> Result<M::State, PersistError> {
+    M::state_from_name(&stored).ok_or(PersistError::UnknownState(stored))
+}
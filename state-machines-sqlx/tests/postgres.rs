@@ -0,0 +1,369 @@
+//! The database tests are `#[ignore]`d, since they need the database in
+//! `DATABASE_URL`; run them with `cargo test -p state-machines-sqlx -- --ignored`.
+//! Each test works in its own tables, dropped on success.
+
+#![cfg(feature = "postgres")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use sqlx::PgPool;
+use state_machines::{DynamicError, state_machine};
+use state_machines_sqlx::{PersistError, PersistentMachine, StateTable};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[derive(Debug, Default)]
+pub struct Ledger {
+    pub paid: u32,
+    pub receipts_sent: AtomicU32,
+}
+
+state_machine! {
+    name: Order,
+    context: Ledger,
+    dynamic: true,
+    persistent: true,
+    async: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped],
+    events {
+        pay {
+            after: [record_payment],
+            after_commit: [send_receipt],
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            transition: { from: Paid, to: Shipped }
+        }
+    }
+}
+
+impl<S> Order<S> {
+    async fn record_payment(&mut self) {
+        self.ctx.paid += 1;
+    }
+
+    async fn send_receipt(&self) {
+        self.ctx.receipts_sent.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Items {
+    pub skus: Vec<u32>,
+}
+
+state_machine! {
+    name: Cart,
+    context: Ledger,
+    dynamic: true,
+    persistent: true,
+    async: true,
+    initial: Filling,
+    states: [Filling(Items), CheckedOut],
+    events {
+        add_item {
+            after: [add_sku],
+            transition: { from: Filling, to: Filling }
+        }
+        check_out {
+            transition: { from: Filling, to: CheckedOut }
+        }
+    }
+}
+
+impl Cart<Filling> {
+    async fn add_sku(&mut self) {
+        self.filling_data_mut().skus.push(7);
+    }
+}
+
+fn receipts_sent(order: Persisted) -> u32 {
+    let ledger = match order.into_inner().into_typed() {
+        AnyOrderState::Pending(m) => m.ctx,
+        AnyOrderState::Paid(m) => m.ctx,
+        AnyOrderState::Shipped(m) => m.ctx,
+    };
+    ledger.receipts_sent.load(Ordering::Relaxed)
+}
+
+type Persisted = PersistentMachine<DynamicOrder, i64>;
+
+async fn pool() -> PgPool {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    PgPool::connect(&url)
+        .await
+        .expect("DATABASE_URL unreachable")
+}
+
+/// Create `{prefix}_orders` with one order (id 1) in `state`, and an empty
+/// `{prefix}_history`.
+async fn schema(pool: &PgPool, prefix: &'static str, state: &str) -> StateTable {
+    let orders = format!("{prefix}_orders");
+    let history = format!("{prefix}_history");
+    for sql in [
+        format!("DROP TABLE IF EXISTS {orders}, {history}"),
+        format!("CREATE TABLE {orders} (order_id BIGINT PRIMARY KEY, status TEXT NOT NULL)"),
+        format!(
+            "CREATE TABLE {history} (id BIGSERIAL PRIMARY KEY, machine_id BIGINT NOT NULL, \
+             event TEXT NOT NULL, from_state TEXT NOT NULL, to_state TEXT NOT NULL, \
+             created_at TIMESTAMPTZ NOT NULL DEFAULT now())"
+        ),
+    ] {
+        sqlx::query(&sql).execute(pool).await.unwrap();
+    }
+    sqlx::query(&format!("INSERT INTO {orders} VALUES (1, $1)"))
+        .bind(state)
+        .execute(pool)
+        .await
+        .unwrap();
+    StateTable::new(orders.leak(), history.leak())
+        .id_column("order_id")
+        .state_column("status")
+}
+
+async fn drop_schema(pool: &PgPool, prefix: &str) {
+    sqlx::query(&format!("DROP TABLE {prefix}_orders, {prefix}_history"))
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn stored_state(pool: &PgPool, prefix: &str) -> String {
+    sqlx::query_scalar(&format!(
+        "SELECT status FROM {prefix}_orders WHERE order_id = 1"
+    ))
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+async fn history(pool: &PgPool, prefix: &str) -> Vec<(String, String, String)> {
+    sqlx::query_as(&format!(
+        "SELECT event, from_state, to_state FROM {prefix}_history ORDER BY id"
+    ))
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+fn row(event: &str, from: &str, to: &str) -> (String, String, String) {
+    (event.into(), from.into(), to.into())
+}
+
+#[test]
+#[should_panic(expected = "is not a plain SQL identifier")]
+fn table_names_must_be_identifiers() {
+    StateTable::new("orders; DROP TABLE orders", "history");
+}
+
+#[test]
+fn schema_qualified_names_are_accepted() {
+    let table = StateTable::new("billing.orders", "billing.order_transitions");
+    assert_eq!(
+        table,
+        StateTable::new("billing.orders", "billing.order_transitions")
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn load_restores_the_stored_state() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_load", "Paid").await;
+
+    let order = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    assert_eq!(order.machine().current_state(), "Paid");
+    assert_eq!(*order.id(), 1);
+
+    let missing = Persisted::load(pool.clone(), table, 2, Ledger::default()).await;
+    assert!(matches!(
+        missing,
+        Err(PersistError::Database(sqlx::Error::RowNotFound))
+    ));
+    drop_schema(&pool, "sm_load").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn unknown_stored_states_are_rejected() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_unknown", "Refunded").await;
+
+    let err = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PersistError::UnknownState(name) if name == "Refunded"));
+    drop_schema(&pool, "sm_unknown").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn transitions_write_the_state_and_history() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_write", "Pending").await;
+
+    let mut order = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    order.transition(OrderEvent::Pay).await.unwrap();
+    order.transition(OrderEvent::Ship).await.unwrap();
+
+    assert_eq!(stored_state(&pool, "sm_write").await, "Shipped");
+    assert_eq!(receipts_sent(order), 1);
+    assert_eq!(
+        history(&pool, "sm_write").await,
+        [
+            row("pay", "Pending", "Paid"),
+            row("ship", "Paid", "Shipped"),
+        ]
+    );
+    drop_schema(&pool, "sm_write").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn rejected_events_write_nothing() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_reject", "Pending").await;
+
+    let mut order = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    let err = order.transition(OrderEvent::Ship).await.unwrap_err();
+    assert!(matches!(
+        err,
+        PersistError::Dynamic(DynamicError::InvalidTransition { .. })
+    ));
+    assert_eq!(order.machine().current_state(), "Pending");
+    assert_eq!(stored_state(&pool, "sm_reject").await, "Pending");
+    assert!(history(&pool, "sm_reject").await.is_empty());
+    drop_schema(&pool, "sm_reject").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn failed_writes_roll_back_the_machine() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_rollback", "Pending").await;
+    // The history insert fails, so the state update must be rolled back too
+    sqlx::query("ALTER TABLE sm_rollback_history ADD CONSTRAINT no_pay CHECK (event <> 'pay')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let mut order = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    let err = order.transition(OrderEvent::Pay).await.unwrap_err();
+    assert!(matches!(err, PersistError::Database(_)));
+    assert_eq!(order.machine().current_state(), "Pending");
+    assert_eq!(stored_state(&pool, "sm_rollback").await, "Pending");
+    assert!(history(&pool, "sm_rollback").await.is_empty());
+
+    // The context keeps the callback's changes
+    match order.into_inner().into_typed() {
+        AnyOrderState::Pending(m) => assert_eq!(m.ctx.paid, 1),
+        other => panic!("unexpected state {}", other.name()),
+    }
+    drop_schema(&pool, "sm_rollback").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn failed_writes_keep_the_state_data() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_cart", "Filling").await;
+    sqlx::query(
+        "ALTER TABLE sm_cart_history ADD CONSTRAINT no_check_out CHECK (event <> 'check_out')",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let mut cart =
+        PersistentMachine::<DynamicCart, i64>::load(pool.clone(), table, 1, Ledger::default())
+            .await
+            .unwrap();
+    cart.transition(CartEvent::AddItem).await.unwrap();
+
+    let err = cart.transition(CartEvent::CheckOut).await.unwrap_err();
+    assert!(matches!(err, PersistError::Database(_)));
+    assert!(cart.machine().is_filling());
+    assert_eq!(cart.machine().filling_data().unwrap().skus, [7]);
+    drop_schema(&pool, "sm_cart").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn concurrent_writers_are_detected() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_stale", "Pending").await;
+
+    let mut first = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    let mut second = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    first.transition(OrderEvent::Pay).await.unwrap();
+
+    let err = second.transition(OrderEvent::Pay).await.unwrap_err();
+    assert!(matches!(
+        &err,
+        PersistError::Stale { stored, expected: "Pending" } if stored == "Paid"
+    ));
+    second.refresh().await.unwrap();
+    second.transition(OrderEvent::Ship).await.unwrap();
+    assert_eq!(stored_state(&pool, "sm_stale").await, "Shipped");
+    drop_schema(&pool, "sm_stale").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn failed_commits_skip_after_commit_callbacks() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_commit", "Pending").await;
+    // A deferred constraint is only checked by COMMIT, after every statement
+    // of the transition has succeeded
+    for sql in [
+        "DROP TABLE IF EXISTS sm_commit_events",
+        "CREATE TABLE sm_commit_events (name TEXT PRIMARY KEY)",
+        "ALTER TABLE sm_commit_history ADD CONSTRAINT known_event FOREIGN KEY (event) \
+         REFERENCES sm_commit_events (name) DEFERRABLE INITIALLY DEFERRED",
+    ] {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+
+    let mut order = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    let err = order.transition(OrderEvent::Pay).await.unwrap_err();
+    assert!(matches!(err, PersistError::Database(_)));
+    assert_eq!(order.machine().current_state(), "Pending");
+    assert_eq!(stored_state(&pool, "sm_commit").await, "Pending");
+    assert_eq!(receipts_sent(order), 0);
+
+    drop_schema(&pool, "sm_commit").await;
+    sqlx::query("DROP TABLE sm_commit_events")
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn transitions_can_run_on_spawned_tasks() {
+    let pool = pool().await;
+    let table = schema(&pool, "sm_spawn", "Pending").await;
+
+    let mut order = Persisted::load(pool.clone(), table, 1, Ledger::default())
+        .await
+        .unwrap();
+    tokio::spawn(async move { order.transition(OrderEvent::Pay).await.unwrap() })
+        .await
+        .unwrap();
+    assert_eq!(stored_state(&pool, "sm_spawn").await, "Paid");
+    drop_schema(&pool, "sm_spawn").await;
+}
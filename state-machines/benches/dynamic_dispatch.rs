@@ -0,0 +1,119 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use state_machines::state_machine;
+use std::hint::black_box;
+
+// ============================================================================
+// Dynamic dispatch: match-per-edge vs. compact transition table
+// ============================================================================
+
+state_machine! {
+    name: MatchLight,
+    dynamic: true,
+    initial: Red,
+    states: [Red, Yellow, Green],
+    events {
+        next {
+            transition: { from: Red, to: Green }
+            transition: { from: Green, to: Yellow }
+            transition: { from: Yellow, to: Red }
+        }
+    }
+}
+
+state_machine! {
+    name: TableLight,
+    dynamic: true,
+    compact_codegen: true,
+    initial: TableRed,
+    states: [TableRed, TableYellow, TableGreen],
+    events {
+        next {
+            transition: { from: TableRed, to: TableGreen }
+            transition: { from: TableGreen, to: TableYellow }
+            transition: { from: TableYellow, to: TableRed }
+        }
+    }
+}
+
+fn benchmark_dynamic_dispatch(c: &mut Criterion) {
+    c.bench_function("dynamic_dispatch", |b| {
+        let mut light = DynamicMatchLight::new(());
+        b.iter(|| {
+            light.handle(black_box(MatchLightEvent::Next)).unwrap();
+            black_box(light.state_enum())
+        });
+    });
+}
+
+fn benchmark_compact_dispatch(c: &mut Criterion) {
+    c.bench_function("compact_dispatch", |b| {
+        let mut light = DynamicTableLight::new(());
+        b.iter(|| {
+            light.handle(black_box(TableLightEvent::Next)).unwrap();
+            black_box(light.state_enum())
+        });
+    });
+}
+
+// ============================================================================
+// Rejection paths: invalid transitions and failing guards
+// ============================================================================
+
+state_machine! {
+    name: Vault,
+    dynamic: true,
+    initial: Locked,
+    states: [Locked, Unlocked],
+    events {
+        unlock {
+            guards: [code_accepted],
+            transition: { from: Locked, to: Unlocked }
+        }
+        lock {
+            transition: { from: Unlocked, to: Locked }
+        }
+    }
+}
+
+impl<C, S> Vault<C, S> {
+    fn code_accepted(&self, _ctx: &C) -> bool {
+        black_box(false)
+    }
+}
+
+fn benchmark_typestate_guard_rejection(c: &mut Criterion) {
+    c.bench_function("typestate_guard_rejection", |b| {
+        b.iter(|| {
+            let vault = Vault::new(());
+            let (vault, err) = vault.unlock().unwrap_err();
+            black_box((vault, err))
+        });
+    });
+}
+
+fn benchmark_dynamic_guard_rejection(c: &mut Criterion) {
+    c.bench_function("dynamic_guard_rejection", |b| {
+        let mut vault = DynamicVault::new(());
+        b.iter(|| black_box(vault.handle(black_box(VaultEvent::Unlock)).unwrap_err()));
+    });
+}
+
+fn benchmark_dynamic_invalid_transition(c: &mut Criterion) {
+    c.bench_function("dynamic_invalid_transition", |b| {
+        let mut vault = DynamicVault::new(());
+        b.iter(|| black_box(vault.handle(black_box(VaultEvent::Lock)).unwrap_err()));
+    });
+}
+
+criterion_group!(
+    benches,
+    benchmark_dynamic_dispatch,
+    benchmark_compact_dispatch,
+    benchmark_typestate_guard_rejection,
+    benchmark_dynamic_guard_rejection,
+    benchmark_dynamic_invalid_transition,
+);
+criterion_main!(benches);
@@ -0,0 +1,116 @@
+//! Clocks for the watchdog and transition records.
+//!
+//! Machines read the time from [`SystemClock`] unless they name another
+//! [`Clock`] with `clock: Type`. [`TestClock`] only moves when a test
+//! advances it, and as an [`AsyncTimer`] it turns timeouts and retry delays
+//! into virtual time as well:
+//!
+//! ```rust,ignore
+//! state_machine! {
+//!     name: Job,
+//!     dynamic: true,
+//!     async: true,
+//!     watchdog: true,
+//!     clock: TestClock,
+//!     callback_timeout: { after: Duration::from_secs(5), timer: TestClock },
+//!     // ...
+//! }
+//! ```
+
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::sync::OnceLock;
+use std::time::Instant;
+use std::vec::Vec;
+
+use crate::{AsyncTimer, Clock};
+
+/// The monotonic system clock, `std::time::Instant`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now() -> Duration {
+        static ORIGIN: OnceLock<Instant> = OnceLock::new();
+        ORIGIN.get_or_init(Instant::now).elapsed()
+    }
+}
+
+std::thread_local! {
+    static TEST_NOW: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    static TEST_SLEEPS: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A manual clock for deterministic tests.
+///
+/// Each thread has its own time, starting at zero, so tests running in
+/// parallel don't see each other's. It only moves through
+/// [`advance`](Self::advance) and [`set`](Self::set), or when a machine
+/// sleeps on it as its [`AsyncTimer`]. A sleep doesn't wait: it moves the
+/// clock forward by its duration and completes, as if nothing else happened
+/// in the meantime. So a retry delay passes instantly, and a
+/// `callback_timeout` fires as soon as the guarded future is pending.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TestClock;
+
+impl TestClock {
+    /// Move this thread's clock forward by `duration`.
+    pub fn advance(duration: Duration) {
+        TEST_NOW.with(|now| now.set(now.get().saturating_add(duration)));
+    }
+
+    /// Set this thread's clock to `now`.
+    ///
+    /// Machines measuring from a later time see no time pass until the
+    /// clock catches up.
+    pub fn set(now: Duration) {
+        TEST_NOW.with(|cell| cell.set(now));
+    }
+
+    /// Rewind this thread's clock to zero and forget its sleeps.
+    pub fn reset() {
+        Self::set(Duration::ZERO);
+        TEST_SLEEPS.with(|sleeps| sleeps.borrow_mut().clear());
+    }
+
+    /// The sleeps machines on this thread have taken, oldest first, e.g.
+    /// to check a retry policy's backoff.
+    pub fn sleeps() -> Vec<Duration> {
+        TEST_SLEEPS.with(|sleeps| sleeps.borrow().clone())
+    }
+}
+
+impl Clock for TestClock {
+    fn now() -> Duration {
+        TEST_NOW.with(Cell::get)
+    }
+}
+
+impl AsyncTimer for TestClock {
+    type Sleep = TestSleep;
+
+    fn sleep(duration: Duration) -> Self::Sleep {
+        TestSleep(duration)
+    }
+}
+
+/// A [`TestClock`] sleep. The clock moves when it's first polled, so a
+/// timeout whose future finishes first leaves the clock alone.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct TestSleep(Duration);
+
+impl Future for TestSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        TEST_SLEEPS.with(|sleeps| sleeps.borrow_mut().push(self.0));
+        TestClock::advance(self.0);
+        Poll::Ready(())
+    }
+}
@@ -0,0 +1,245 @@
+//! Checking that tests exercise every transition.
+//!
+//! With the `trace` feature enabled, every state change a generated machine
+//! makes on the current thread is reported to the active
+//! [`TransitionCoverage`]. Compare it against the machine's definition to
+//! find the `(event, from, to)` triples no test reached:
+//!
+//! ```rust
+//! use state_machines::{coverage::TransitionCoverage, state_machine};
+//!
+//! state_machine! {
+//!     name: Door,
+//!     dynamic: true,
+//!     initial: Closed,
+//!     states: [Closed, Open],
+//!     events {
+//!         open { transition: { from: Closed, to: Open } }
+//!         close { transition: { from: Open, to: Closed } }
+//!     }
+//! }
+//!
+//! let coverage = TransitionCoverage::start(DoorState::definition());
+//! let _open = Door::new(()).open();
+//!
+//! assert!(!coverage.complete());
+//! assert_eq!(coverage.report().to_string(), "1 of 2 transitions covered; missing:\n  close: Open -> Closed\n");
+//! ```
+//!
+//! Definitions come from dynamic mode (`{Name}State::definition()`), but both
+//! typestate methods and dynamic dispatch are recorded.
+
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use std::format;
+use std::vec::Vec;
+
+use crate::{MachineDefinition, MachineState, TransitionRef};
+
+/// A state change reported by a generated machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Recorded {
+    machine: &'static str,
+    event: &'static str,
+    from: &'static str,
+    to: &'static str,
+}
+
+std::thread_local! {
+    static RECORDED: RefCell<Vec<Recorded>> = const { RefCell::new(Vec::new()) };
+    static RECORDERS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// One transition of a machine definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionTriple<S> {
+    pub event: &'static str,
+    pub from: S,
+    pub to: S,
+}
+
+impl<S: fmt::Debug> fmt::Display for TransitionTriple<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?} -> {:?}", self.event, self.from, self.to)
+    }
+}
+
+/// Records which transitions of one machine run on the current thread.
+///
+/// Several recorders can be active at once, e.g. one per machine; each only
+/// sees state changes made after it started. Recording stops when the last
+/// recorder is dropped. Async machines are recorded as long as they're polled
+/// on the recording thread.
+#[derive(Debug)]
+pub struct TransitionCoverage<S>
+where
+    S: MachineState + PartialEq,
+{
+    definition: &'static MachineDefinition<S>,
+    /// Position in `RECORDED` when this recorder started.
+    start: usize,
+    _not_send: core::marker::PhantomData<*const ()>,
+}
+
+impl<S> TransitionCoverage<S>
+where
+    S: MachineState + PartialEq,
+{
+    /// Start recording transitions of the machine `definition` describes.
+    pub fn start(definition: &'static MachineDefinition<S>) -> Self {
+        RECORDERS.with(|recorders| recorders.set(recorders.get() + 1));
+        let start = RECORDED.with(|recorded| recorded.borrow().len());
+        Self {
+            definition,
+            start,
+            _not_send: core::marker::PhantomData,
+        }
+    }
+
+    /// Every transition in the definition, in declaration order.
+    ///
+    /// Multi-source transitions count once per source state and choice
+    /// transitions once per target.
+    pub fn expected(&self) -> Vec<TransitionTriple<S>> {
+        let mut triples: Vec<TransitionTriple<S>> = Vec::new();
+        for event in self.definition.events {
+            for transition in event.transitions {
+                for source in transition.sources {
+                    let triple = TransitionTriple {
+                        event: event.name,
+                        from: source.clone(),
+                        to: transition.target.clone(),
+                    };
+                    if !triples.contains(&triple) {
+                        triples.push(triple);
+                    }
+                }
+            }
+        }
+        triples
+    }
+
+    /// The transitions from the definition that ran since recording started.
+    pub fn covered(&self) -> Vec<TransitionTriple<S>> {
+        self.partition().0
+    }
+
+    /// The transitions from the definition that haven't run yet.
+    pub fn missing(&self) -> Vec<TransitionTriple<S>> {
+        self.partition().1
+    }
+
+    /// Whether the transition `edge` refers to ran, e.g.
+    /// `coverage.covers(&OrderTransitions::PAY_PENDING_TO_PAID)`.
+    pub fn covers(&self, edge: &TransitionRef<S>) -> bool {
+        self.covered().iter().any(|triple| {
+            triple.event == edge.name() && &triple.from == edge.from() && &triple.to == edge.to()
+        })
+    }
+
+    /// Whether every transition in the definition ran.
+    pub fn complete(&self) -> bool {
+        self.missing().is_empty()
+    }
+
+    /// A summary listing the missing transitions.
+    pub fn report(&self) -> CoverageReport<S> {
+        let (covered, missing) = self.partition();
+        CoverageReport {
+            covered: covered.len(),
+            missing,
+        }
+    }
+
+    /// Panic with the report unless every transition ran.
+    #[track_caller]
+    pub fn assert_complete(&self) {
+        let report = self.report();
+        assert!(report.missing.is_empty(), "{}", report);
+    }
+
+    /// Split the expected transitions into covered and missing.
+    fn partition(&self) -> (Vec<TransitionTriple<S>>, Vec<TransitionTriple<S>>) {
+        // Generated state enums derive `Debug`, and the definition only
+        // exists for fieldless ones, so `Debug` prints the variant name
+        let name = |state: &S| format!("{:?}", state);
+        RECORDED.with(|recorded| {
+            let recorded = recorded.borrow();
+            let seen = recorded.get(self.start..).unwrap_or_default();
+            self.expected().into_iter().partition(|triple| {
+                let (from, to) = (name(&triple.from), name(&triple.to));
+                seen.iter().any(|entry| {
+                    entry.machine == self.definition.name
+                        && entry.event == triple.event
+                        && entry.from == from
+                        && entry.to == to
+                })
+            })
+        })
+    }
+}
+
+impl<S> Drop for TransitionCoverage<S>
+where
+    S: MachineState + PartialEq,
+{
+    fn drop(&mut self) {
+        // `try_with` because the thread-locals may already be gone at thread exit
+        let _ = RECORDERS.try_with(|recorders| {
+            let remaining = recorders.get().saturating_sub(1);
+            recorders.set(remaining);
+            if remaining == 0 {
+                let _ = RECORDED.try_with(|recorded| recorded.borrow_mut().clear());
+            }
+        });
+    }
+}
+
+/// Covered count and missing transitions, from [`TransitionCoverage::report`].
+///
+/// `Display` lists one missing transition per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport<S> {
+    pub covered: usize,
+    pub missing: Vec<TransitionTriple<S>>,
+}
+
+impl<S: fmt::Debug> fmt::Display for CoverageReport<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.covered + self.missing.len();
+        if self.missing.is_empty() {
+            return writeln!(f, "all {} transitions covered", total);
+        }
+        writeln!(
+            f,
+            "{} of {} transitions covered; missing:",
+            self.covered, total
+        )?;
+        for triple in &self.missing {
+            writeln!(f, "  {}", triple)?;
+        }
+        Ok(())
+    }
+}
+
+/// Note a state change for the active recorders, if any.
+pub(crate) fn record(
+    machine: &'static str,
+    event: &'static str,
+    from: &'static str,
+    to: &'static str,
+) {
+    let active = RECORDERS.try_with(Cell::get).unwrap_or(0) > 0;
+    if active {
+        let _ = RECORDED.try_with(|recorded| {
+            recorded.borrow_mut().push(Recorded {
+                machine,
+                event,
+                from,
+                to,
+            })
+        });
+    }
+}
@@ -0,0 +1,218 @@
+//! Serving a dynamic machine over HTTP with `axum`.
+//!
+//! Machines declared with `http: true` implement [`HttpMachine`], and
+//! [`router`] exposes one of them for admin tooling and debugging:
+//!
+//! | Route | Response |
+//! |-------|----------|
+//! | `GET /state` | `{"state": "Paid"}` |
+//! | `GET /definition` | states, superstates, events, and transitions |
+//! | `POST /events/{name}` | dispatches the event; the body is its JSON payload |
+//!
+//! ```rust,ignore
+//! let order = Arc::new(Mutex::new(DynamicOrder::new(ctx)));
+//! let app = Router::new().nest("/orders/42", state_machines::http::router(order.clone()));
+//! axum::serve(listener, app).await?;
+//! ```
+//!
+//! Rejected events answer `409 Conflict` with the machine's current state,
+//! unknown events `404 Not Found`, and missing or malformed payloads
+//! `422 Unprocessable Entity`. Every error body has an `"error"` message.
+
+extern crate std;
+
+use axum::Json;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use core::fmt;
+use core::future::Future;
+use serde_json::{Value, json};
+use state_machines_core::{DynamicError, DynamicMachine, MachineDefinition};
+use std::format;
+use std::string::{String, ToString};
+use std::sync::Arc;
+use std::vec::Vec;
+use tokio::sync::Mutex;
+
+/// A dynamic machine the [`router`] can serve.
+///
+/// Implemented by the `Dynamic{Name}` wrapper of machines declared with
+/// `http: true` (with a `()` context for machines without `context:`).
+pub trait HttpMachine: DynamicMachine {
+    /// The event named `name`, its payload deserialized from `payload`.
+    fn event_from_json(name: &str, payload: Option<Value>) -> Result<Self::Event, EventError>;
+
+    /// [`dispatch`](DynamicMachine::dispatch), for handlers that must be `Send`.
+    fn dispatch_send(
+        &mut self,
+        event: Self::Event,
+    ) -> impl Future<Output = Result<Self::Output, DynamicError>> + Send;
+}
+
+/// Why a request couldn't be turned into an event.
+#[derive(Debug)]
+pub enum EventError {
+    /// The machine has no event with this name.
+    UnknownEvent(String),
+    /// The event takes a payload and the request body was empty.
+    MissingPayload(&'static str),
+    /// The payload didn't deserialize into the event's payload type.
+    InvalidPayload {
+        event: &'static str,
+        error: serde_json::Error,
+    },
+}
+
+impl EventError {
+    fn status(&self) -> StatusCode {
+        match self {
+            EventError::UnknownEvent(_) => StatusCode::NOT_FOUND,
+            EventError::MissingPayload(_) | EventError::InvalidPayload { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+        }
+    }
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventError::UnknownEvent(name) => write!(f, "unknown event `{name}`"),
+            EventError::MissingPayload(event) => write!(f, "event `{event}` requires a payload"),
+            EventError::InvalidPayload { event, error } => {
+                write!(f, "invalid payload for `{event}`: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EventError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EventError::InvalidPayload { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Routes for `GET /state`, `GET /definition`, and `POST /events/{name}`
+/// over `machine`.
+///
+/// Events are dispatched one at a time while holding the lock; keep a clone
+/// of `machine` to drive or inspect it outside the router.
+pub fn router<M>(machine: Arc<Mutex<M>>) -> Router
+where
+    M: HttpMachine + Send + 'static,
+    M::Event: Send,
+{
+    Router::new()
+        .route("/state", get(current_state::<M>))
+        .route("/definition", get(definition::<M>))
+        .route("/events/{name}", post(dispatch::<M>))
+        .with_state(machine)
+}
+
+async fn current_state<M: DynamicMachine>(State(machine): State<Arc<Mutex<M>>>) -> Json<Value> {
+    let state = machine.lock().await.state();
+    Json(json!({ "state": M::state_name(&state) }))
+}
+
+async fn definition<M: DynamicMachine>() -> Json<Value> {
+    Json(definition_json::<M>(M::definition()))
+}
+
+async fn dispatch<M>(
+    State(machine): State<Arc<Mutex<M>>>,
+    Path(name): Path<String>,
+    body: Bytes,
+) -> Response
+where
+    M: HttpMachine,
+{
+    let payload = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(payload) => Some(payload),
+            Err(err) => {
+                let message = format!("invalid JSON body: {err}");
+                return error(StatusCode::BAD_REQUEST, message, Value::Null);
+            }
+        }
+    };
+    let event = match M::event_from_json(&name, payload) {
+        Ok(event) => event,
+        Err(err) => return error(err.status(), err.to_string(), Value::Null),
+    };
+
+    let mut machine = machine.lock().await;
+    let result = machine.dispatch_send(event).await;
+    let state = M::state_name(&machine.state());
+    match result {
+        Ok(_) => Json(json!({ "state": state })).into_response(),
+        Err(err) => error(StatusCode::CONFLICT, format!("{err:?}"), state.into()),
+    }
+}
+
+fn error(status: StatusCode, message: String, state: Value) -> Response {
+    let body = if state.is_null() {
+        json!({ "error": message })
+    } else {
+        json!({ "error": message, "state": state })
+    };
+    (status, Json(body)).into_response()
+}
+
+fn definition_json<M: DynamicMachine>(definition: &MachineDefinition<M::State>) -> Value {
+    let names = |states: &[M::State]| -> Vec<&'static str> {
+        states.iter().map(|state| M::state_name(state)).collect()
+    };
+    let superstates: Vec<Value> = definition
+        .superstates
+        .iter()
+        .map(|superstate| {
+            json!({
+                "name": superstate.name,
+                "initial": M::state_name(&superstate.initial),
+                "states": names(superstate.descendants),
+            })
+        })
+        .collect();
+    let events: Vec<Value> = definition
+        .events
+        .iter()
+        .map(|event| {
+            let transitions: Vec<Value> = event
+                .transitions
+                .iter()
+                .map(|transition| {
+                    json!({
+                        "from": names(transition.sources),
+                        "to": M::state_name(&transition.target),
+                        "guards": transition.guards,
+                        "unless": transition.unless,
+                    })
+                })
+                .collect();
+            json!({
+                "name": event.name,
+                "payload": event.payload,
+                "guards": event.guards,
+                "transitions": transitions,
+            })
+        })
+        .collect();
+
+    json!({
+        "name": definition.name,
+        "initial": M::state_name(&definition.initial),
+        "async": definition.async_mode,
+        "states": names(definition.states),
+        "superstates": superstates,
+        "events": events,
+    })
+}
@@ -7,12 +7,158 @@ pub mod core {
     pub use state_machines_core::*;
 }
 
+#[cfg(feature = "std")]
+pub use state_machines_core::DEFINITION_SCHEMA;
+#[cfg(feature = "embedded-storage")]
+pub use state_machines_core::PersistError;
 pub use state_machines_core::{
-    AroundOutcome, AroundStage, DynamicError, EventDefinition, Machine, MachineDefinition,
-    MachineState, SubstateOf, SuperstateDefinition, TransitionContext, TransitionDefinition,
-    TransitionError, TransitionErrorKind, TransitionResult,
+    AroundOutcome, AroundStage, AsyncTimer, AtomicState, Availability, Backoff, BatchError,
+    BatchResult, Behavior, BehaviorNames, Clock, Diagram, DynamicError, DynamicMachine,
+    EventDefinition, EventFailure, Forward, Machine, MachineDefinition, MachineId, MachineState,
+    MachineStats, ProjectionError, Projector, RewindTooFar, Sink, Snapshot, StateData, StateRepr,
+    StateStats, StuckState, SubstateOf, SuperstateDefinition, TableEdge, Transition,
+    TransitionContext, TransitionDefinition, TransitionError, TransitionErrorKind,
+    TransitionHistory, TransitionNotification, TransitionPhase, TransitionRef, TransitionResult,
+    TransitionTable, VersionMismatch,
 };
-pub use state_machines_macro::state_machine;
+#[cfg(feature = "alloc")]
+pub use state_machines_core::{
+    BehaviorRegistry, DefinitionDiff, DefinitionError, DryRunOutcome, DryRunReport, DryRunStep,
+    InterpretError, InterpretedMachine, MissingBehavior, OwnedDefinition, OwnedDefinitionBuilder,
+    OwnedTransition, TransitionChange, WithBehavior,
+};
+#[cfg(feature = "postcard")]
+pub use state_machines_core::{MAX_SNAPSHOT_LEN, SNAPSHOT_FORMAT, SnapshotCodecError};
+pub use state_machines_macro::{assert_machine, fragment, state_machine};
+
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(feature = "trace")]
+pub mod coverage;
+#[cfg(feature = "axum")]
+pub mod http;
+#[cfg(feature = "std")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod repl;
+#[cfg(feature = "alloc")]
+pub mod saga;
+pub mod source;
+#[cfg(feature = "alloc")]
+pub mod store;
+#[cfg(feature = "tokio")]
+pub mod timer;
+#[cfg(feature = "trace")]
+pub mod trace;
+
+/// Support items for generated code.
+///
+/// Not public API: generated code refers to these paths so user crates don't
+/// need their own `wasm-bindgen`, `js-sys`, or `serde_json` dependencies.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(any(feature = "alloc", feature = "wasm-bindgen"))]
+    extern crate alloc;
+    #[cfg(feature = "std")]
+    extern crate std;
+
+    #[cfg(feature = "alloc")]
+    pub use alloc::vec::Vec;
+
+    #[cfg(feature = "wasm-bindgen")]
+    pub use alloc::format;
+    #[cfg(feature = "wasm-bindgen")]
+    pub use alloc::string::String;
+    #[cfg(feature = "wasm-bindgen")]
+    pub use js_sys;
+    #[cfg(any(feature = "wasm-bindgen", feature = "axum"))]
+    pub use serde_json;
+    #[cfg(feature = "wasm-bindgen")]
+    pub use wasm_bindgen;
+
+    /// Report a transition step to the active `TraceRecorder`, if any.
+    #[cfg(feature = "trace")]
+    #[inline]
+    pub fn trace(
+        machine: &'static str,
+        id: Option<crate::MachineId>,
+        event: &'static str,
+        step: crate::trace::TraceStep,
+    ) {
+        crate::trace::record(machine, id, event, step);
+    }
+
+    pub use crate::__with_alloc as with_alloc;
+
+    /// Times a dispatch for `transition_records: true` machines, and the
+    /// current state for `watchdog: true` ones.
+    ///
+    /// `start()` and `elapsed()` read the `SystemClock`; without the `std`
+    /// feature there is none, so they measure nothing. Machines declared
+    /// with `clock: Type` use `start_on()` and `elapsed_on()` instead.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Stopwatch {
+        start: ::core::option::Option<::core::time::Duration>,
+    }
+
+    impl Stopwatch {
+        #[inline]
+        pub fn start() -> Self {
+            #[cfg(feature = "std")]
+            {
+                Self::start_on::<crate::clock::SystemClock>()
+            }
+            #[cfg(not(feature = "std"))]
+            {
+                Self {
+                    start: ::core::option::Option::None,
+                }
+            }
+        }
+
+        #[inline]
+        pub fn start_on<C: crate::Clock>() -> Self {
+            Self {
+                start: ::core::option::Option::Some(C::now()),
+            }
+        }
+
+        #[inline]
+        pub fn elapsed(&self) -> ::core::option::Option<::core::time::Duration> {
+            #[cfg(feature = "std")]
+            {
+                self.elapsed_on::<crate::clock::SystemClock>()
+            }
+            #[cfg(not(feature = "std"))]
+            {
+                ::core::option::Option::None
+            }
+        }
+
+        #[inline]
+        pub fn elapsed_on<C: crate::Clock>(
+            &self,
+        ) -> ::core::option::Option<::core::time::Duration> {
+            self.start.map(|start| C::now().saturating_sub(start))
+        }
+    }
+}
+
+/// Keeps generated items that allocate, like `simulate()`, only when the
+/// `alloc` feature is enabled.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_alloc {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "alloc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_alloc {
+    ($($item:tt)*) => {};
+}
 
 /// Convenience macro for aborting an around callback with a guard-style error.
 ///
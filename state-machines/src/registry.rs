@@ -0,0 +1,266 @@
+//! A process-wide directory of live dynamic machines.
+//!
+//! Services that run many machines, say one per session, register each with
+//! a [`MachineRegistry`] under a name. The registry then lists them, reports
+//! their current states for health checks and debug endpoints, and draws any
+//! of them on demand:
+//!
+//! ```rust
+//! use state_machines::{registry::MachineRegistry, state_machine};
+//!
+//! state_machine! {
+//!     name: Session,
+//!     dynamic: true,
+//!     persistent: true,
+//!     initial: Active,
+//!     states: [Active, Idle],
+//!     events {
+//!         pause { transition: { from: Active, to: Idle } }
+//!     }
+//! }
+//!
+//! let registry = MachineRegistry::new();
+//! let alice = registry.register("session:alice", DynamicSession::new(()));
+//! let _bob = registry.register("session:bob", DynamicSession::new(()));
+//! alice.lock().unwrap().handle(SessionEvent::Pause).unwrap();
+//!
+//! assert_eq!(registry.state("session:alice"), Some("Idle"));
+//! assert_eq!(registry.state_counts()[&("Session", "Active")], 1);
+//!
+//! // A machine leaves the registry once its last handle is dropped
+//! drop(alice);
+//! assert_eq!(registry.names(), ["session:bob"]);
+//! ```
+//!
+//! [`MachineRegistry::global`] is a registry shared by the whole process.
+//! Machines need `persistent: true`, which implements `DynamicMachine`.
+
+extern crate std;
+
+use core::any::Any;
+use std::collections::BTreeMap;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, Weak};
+use std::vec::Vec;
+
+use state_machines_core::{DynamicMachine, MachineId};
+
+/// One registered machine, as listed by [`MachineRegistry::snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineStatus {
+    /// The name the machine was registered under.
+    pub name: String,
+    /// The machine's definition name, e.g. `"Session"`.
+    pub machine: &'static str,
+    /// The name of its current state, or `None` if the machine is poisoned:
+    /// a dispatch never completed, e.g. a callback panicked, and took the
+    /// state with it.
+    pub state: Option<&'static str>,
+    /// The machine's own id, with `identity: true`.
+    pub id: Option<MachineId>,
+}
+
+/// Type-erased access to a registered machine.
+trait Entry: Send + Sync {
+    /// The definition name, which outlives the machine.
+    fn machine(&self) -> &'static str;
+
+    /// The current state name and the machine's id, or `None` once the
+    /// machine is dropped. A poisoned machine has neither.
+    fn current(&self) -> Option<(Option<&'static str>, Option<MachineId>)>;
+
+    /// The diagram with the current state highlighted.
+    fn diagram(&self) -> Option<String>;
+
+    /// The shared machine, for downcasting in [`MachineRegistry::get`].
+    fn handle(&self) -> Option<Arc<dyn Any + Send + Sync>>;
+}
+
+impl<M> Entry for Weak<Mutex<M>>
+where
+    M: DynamicMachine + Send + 'static,
+    M::State: PartialEq,
+{
+    fn machine(&self) -> &'static str {
+        M::definition().name
+    }
+
+    fn current(&self) -> Option<(Option<&'static str>, Option<MachineId>)> {
+        let machine = self.upgrade()?;
+        let machine = lock(&machine);
+        if machine.is_poisoned() {
+            return Some((None, None));
+        }
+        Some((Some(M::state_name(&machine.state())), machine.id()))
+    }
+
+    fn diagram(&self) -> Option<String> {
+        let machine = self.upgrade()?;
+        let machine = lock(&machine);
+        let diagram = M::definition().diagram();
+        if machine.is_poisoned() {
+            return Some(diagram.to_string());
+        }
+        Some(diagram.current(&machine.state()).to_string())
+    }
+
+    fn handle(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        Some(self.upgrade()?)
+    }
+}
+
+/// A callback that panics mid-dispatch poisons the mutex, but the machine
+/// stays listed, reported as poisoned itself if the panic took its state.
+fn lock<M>(machine: &Mutex<M>) -> MutexGuard<'_, M> {
+    machine.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Named, weakly held dynamic machines; see the [module docs](self).
+#[derive(Default)]
+pub struct MachineRegistry {
+    entries: Mutex<BTreeMap<String, Arc<dyn Entry>>>,
+}
+
+impl core::fmt::Debug for MachineRegistry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MachineRegistry")
+            .field("machines", &self.names())
+            .finish()
+    }
+}
+
+impl MachineRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The registry shared by the whole process.
+    pub fn global() -> &'static MachineRegistry {
+        static GLOBAL: OnceLock<MachineRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(MachineRegistry::new)
+    }
+
+    /// Share `machine` and register it under `name`.
+    ///
+    /// The registry only holds a weak reference: the machine is listed until
+    /// the returned handle and its clones are dropped. Registering another
+    /// machine under the same name replaces the entry.
+    pub fn register<M>(&self, name: impl Into<String>, machine: M) -> Arc<Mutex<M>>
+    where
+        M: DynamicMachine + Send + 'static,
+        M::State: PartialEq,
+    {
+        let machine = Arc::new(Mutex::new(machine));
+        self.register_shared(name, &machine);
+        machine
+    }
+
+    /// Register a machine that is already shared, like [`register`](Self::register).
+    pub fn register_shared<M>(&self, name: impl Into<String>, machine: &Arc<Mutex<M>>)
+    where
+        M: DynamicMachine + Send + 'static,
+        M::State: PartialEq,
+    {
+        let entry: Arc<dyn Entry> = Arc::new(Arc::downgrade(machine));
+        self.entries().insert(name.into(), entry);
+    }
+
+    /// Remove the machine registered under `name`; returns whether there
+    /// was a live one.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.entries()
+            .remove(name)
+            .is_some_and(|entry| entry.current().is_some())
+    }
+
+    /// The machine registered under `name`, if it's still alive and an `M`.
+    pub fn get<M>(&self, name: &str) -> Option<Arc<Mutex<M>>>
+    where
+        M: Send + 'static,
+    {
+        let handle = self.entry(name)?.handle()?;
+        handle.downcast::<Mutex<M>>().ok()
+    }
+
+    /// The names of the live machines, in order.
+    pub fn names(&self) -> Vec<String> {
+        self.live().into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// The number of live machines.
+    pub fn len(&self) -> usize {
+        self.live().len()
+    }
+
+    /// Whether no live machine is registered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The current state of the machine registered under `name`; `None` if
+    /// it's gone or poisoned.
+    pub fn state(&self, name: &str) -> Option<&'static str> {
+        self.entry(name)?.current()?.0
+    }
+
+    /// The diagram of the machine registered under `name`, with its current
+    /// state highlighted unless the machine is poisoned.
+    pub fn diagram(&self, name: &str) -> Option<String> {
+        self.entry(name)?.diagram()
+    }
+
+    /// Every live machine with its current state, in name order.
+    ///
+    /// Each machine is locked briefly in turn, so the states aren't one
+    /// consistent point in time. Don't call this while holding the lock of
+    /// a registered machine.
+    pub fn snapshot(&self) -> Vec<MachineStatus> {
+        self.live()
+            .into_iter()
+            .filter_map(|(name, entry)| {
+                let (state, id) = entry.current()?;
+                Some(MachineStatus {
+                    machine: entry.machine(),
+                    state,
+                    id,
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    /// How many live machines are in each state, keyed by definition name
+    /// and state name. Poisoned machines aren't in any state, so they aren't
+    /// counted.
+    pub fn state_counts(&self) -> BTreeMap<(&'static str, &'static str), usize> {
+        let mut counts = BTreeMap::new();
+        for status in self.snapshot() {
+            if let Some(state) = status.state {
+                *counts.entry((status.machine, state)).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    fn entries(&self) -> MutexGuard<'_, BTreeMap<String, Arc<dyn Entry>>> {
+        lock(&self.entries)
+    }
+
+    fn entry(&self, name: &str) -> Option<Arc<dyn Entry>> {
+        self.entries().get(name).cloned()
+    }
+
+    /// Drop the entries of machines that are gone and return the rest.
+    ///
+    /// The entries are cloned out so no machine is locked while the
+    /// registry is.
+    fn live(&self) -> Vec<(String, Arc<dyn Entry>)> {
+        let mut entries = self.entries();
+        entries.retain(|_, entry| entry.handle().is_some());
+        entries
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,263 @@
+//! An interactive prompt for driving a dynamic machine by hand.
+//!
+//! [`Repl`] reads commands line by line: list the states and events, fire
+//! events by name, review what was fired, and print an ASCII diagram of the
+//! whole machine. Handy for demos, debugging, and teaching:
+//!
+//! ```rust
+//! use state_machines::{repl::Repl, state_machine};
+//!
+//! state_machine! {
+//!     name: Door,
+//!     dynamic: true,
+//!     persistent: true,
+//!     initial: Closed,
+//!     states: [Closed, Open],
+//!     events {
+//!         open { transition: { from: Closed, to: Open } }
+//!         close { transition: { from: Open, to: Closed } }
+//!     }
+//! }
+//!
+//! let mut repl = Repl::new(DynamicDoor::new(()));
+//! let mut output = Vec::new();
+//! repl.run_with("open\nclose\nclose\n".as_bytes(), &mut output).unwrap();
+//! assert_eq!(repl.machine().current_state(), "Closed");
+//! ```
+//!
+//! In a binary, `Repl::new(machine).run()` talks to the terminal instead.
+//! Machines need `persistent: true`, which implements `DynamicMachine`.
+//! Events with a payload are listed but can't be fired from the prompt.
+
+extern crate std;
+
+use core::fmt::Write as _;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use std::format;
+use std::io::{self, BufRead, Write};
+use std::string::String;
+use std::sync::Arc;
+use std::task::Wake;
+use std::thread::{self, Thread};
+use std::vec::Vec;
+
+use state_machines_core::{DynamicError, DynamicMachine};
+
+const HELP: &str = "\
+commands:
+  state            show the current state
+  states           list every state
+  events           list the events and where they lead from here
+  fire <event>     dispatch an event (or just type its name)
+  history          list the events fired so far
+  diagram          draw the machine
+  help             show this message
+  quit             leave (or end the input)
+";
+
+/// One event fired from the prompt.
+#[derive(Debug)]
+struct Fired {
+    event: &'static str,
+    from: &'static str,
+    to: &'static str,
+    error: Option<DynamicError>,
+}
+
+/// A line-oriented prompt around a dynamic machine.
+#[derive(Debug)]
+pub struct Repl<M> {
+    machine: M,
+    history: Vec<Fired>,
+}
+
+impl<M: DynamicMachine> Repl<M> {
+    /// A prompt around `machine`, with an empty history.
+    pub fn new(machine: M) -> Self {
+        Self {
+            machine,
+            history: Vec::new(),
+        }
+    }
+
+    /// Prompt on standard output and read commands from standard input
+    /// until `quit` or end of input.
+    pub fn run(&mut self) -> io::Result<()> {
+        self.run_with(io::stdin().lock(), io::stdout().lock())
+    }
+
+    /// Like [`run`](Self::run), over any input and output.
+    pub fn run_with(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        let name = M::definition().name;
+        writeln!(output, "{name}: type `help` for commands")?;
+        write!(output, "{}> ", self.current())?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            let reply = match self.command(line.trim()) {
+                Some(reply) => reply,
+                None => return Ok(()),
+            };
+            output.write_all(reply.as_bytes())?;
+            write!(output, "{}> ", self.current())?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+
+    /// The machine being driven.
+    pub fn machine(&self) -> &M {
+        &self.machine
+    }
+
+    /// Stop prompting and return the machine.
+    pub fn into_inner(self) -> M {
+        self.machine
+    }
+
+    fn current(&self) -> &'static str {
+        M::state_name(&self.machine.state())
+    }
+
+    /// Run one command, returning what to print, or `None` to quit.
+    fn command(&mut self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let reply = match words[..] {
+            [] => String::new(),
+            ["quit" | "exit"] => return None,
+            ["help" | "?"] => String::from(HELP),
+            ["state"] => format!("{}\n", self.current()),
+            ["states"] => self.states(),
+            ["events"] => self.events(),
+            ["history"] => self.history(),
+            ["diagram"] => self.diagram(),
+            ["fire"] => String::from("usage: fire <event>\n"),
+            ["fire", event] | [event] => self.fire(event),
+            _ => format!("unknown command `{line}`; try `help`\n"),
+        };
+        Some(reply)
+    }
+
+    fn fire(&mut self, name: &str) -> String {
+        let definition = M::definition();
+        let Some(declared) = definition.events.iter().find(|event| event.name == name) else {
+            return format!("unknown command or event `{name}`; try `help`\n");
+        };
+        let Some(event) = M::event_from_name(name) else {
+            let payload = declared.payload.unwrap_or("a payload");
+            return format!("`{name}` takes {payload} and can't be fired from the prompt\n");
+        };
+
+        let from = self.current();
+        let error = block_on(self.machine.dispatch(event)).err();
+        let to = self.current();
+        let reply = match &error {
+            None => format!("{from} --{name}--> {to}\n"),
+            Some(err) => format!("rejected: {err:?}\n"),
+        };
+        self.history.push(Fired {
+            event: declared.name,
+            from,
+            to,
+            error,
+        });
+        reply
+    }
+
+    fn states(&self) -> String {
+        let current = self.current();
+        let mut out = String::new();
+        for state in M::definition().states {
+            let name = M::state_name(state);
+            let marker = if name == current { '*' } else { ' ' };
+            let _ = writeln!(out, "{marker} {name}");
+        }
+        out
+    }
+
+    fn events(&self) -> String {
+        let current = self.current();
+        let mut out = String::new();
+        let mut unavailable = Vec::new();
+        for event in M::definition().events {
+            let targets: Vec<&str> = event
+                .transitions
+                .iter()
+                .filter(|transition| {
+                    transition
+                        .sources
+                        .iter()
+                        .any(|source| M::state_name(source) == current)
+                })
+                .map(|transition| M::state_name(&transition.target))
+                .collect();
+            if targets.is_empty() {
+                unavailable.push(event.name);
+                continue;
+            }
+            let _ = write!(out, "  {} -> {}", event.name, targets.join(" | "));
+            if let Some(payload) = event.payload {
+                let _ = write!(out, " (payload: {payload})");
+            }
+            out.push('\n');
+        }
+        if !unavailable.is_empty() {
+            let _ = writeln!(out, "  (not from {current}: {})", unavailable.join(", "));
+        }
+        out
+    }
+
+    fn history(&self) -> String {
+        if self.history.is_empty() {
+            return String::from("no events fired yet\n");
+        }
+        let mut out = String::new();
+        for (index, fired) in self.history.iter().enumerate() {
+            let _ = write!(
+                out,
+                "{:>3}. {}: {} -> {}",
+                index + 1,
+                fired.event,
+                fired.from,
+                fired.to
+            );
+            if let Some(err) = &fired.error {
+                let _ = write!(out, " (rejected: {err:?})");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn diagram(&self) -> String {
+        let state = self.machine.state();
+        format!("{}", M::definition().diagram().current(&state))
+    }
+}
+
+/// Wakes the prompt's thread.
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run an async dispatch to completion on the current thread.
+///
+/// Synchronous machines hand back a ready future, so this returns at once.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
@@ -0,0 +1,243 @@
+//! Running many instances of one machine, keyed by correlation ID.
+//!
+//! An orchestration service runs a saga per order, payment, or booking, and
+//! every message it receives names the saga it belongs to. A
+//! [`SagaCoordinator`] owns those instances: it routes each event to the
+//! instance with its ID, creates the instance when the event is one of the
+//! configured start events, and lets go of it once it reaches a terminal
+//! state:
+//!
+//! ```rust,ignore
+//! let mut orders = SagaCoordinator::new(|id: &u64| DynamicOrder::new(OrderCtx::for_order(*id)))
+//!     .start_on("place");
+//!
+//! orders.handle(42, OrderEvent::Place).await?; // creates order 42
+//! orders.handle(42, OrderEvent::Pay).await?;
+//! let shipped = orders.handle(42, OrderEvent::Ship).await?;
+//! if let Some(order) = shipped.finished {
+//!     archive(42, order); // `Shipped` is terminal; the coordinator dropped it
+//! }
+//! ```
+//!
+//! When events carry their own ID, say in a payload, install a correlation
+//! function with [`SagaCoordinator::correlate_with`] and call
+//! [`route`](SagaCoordinator::route) with the bare event. IDs can be any
+//! `Ord + Clone` type. Machines need `persistent: true`, which implements
+//! `DynamicMachine`.
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use state_machines_core::{DynamicError, DynamicMachine};
+
+type Spawn<K, M> = Box<dyn FnMut(&K) -> M + Send>;
+type Correlate<K, E> = Box<dyn Fn(&E) -> Option<K> + Send>;
+
+/// What [`SagaCoordinator::handle`] did with an accepted event.
+#[derive(Debug)]
+pub struct Dispatched<K, M: DynamicMachine> {
+    /// The instance the event went to.
+    pub id: K,
+    pub output: M::Output,
+    /// Whether the event created the instance.
+    pub started: bool,
+    /// The instance, if the event left it in a terminal state. The
+    /// coordinator no longer holds it.
+    pub finished: Option<M>,
+}
+
+/// Why a [`SagaCoordinator`] couldn't handle an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SagaError<K> {
+    /// [`route`](SagaCoordinator::route) found no ID in the event, or no
+    /// correlation function is installed.
+    Uncorrelated { event: &'static str },
+    /// No instance has the ID, and the event doesn't start one.
+    NoInstance { id: K, event: &'static str },
+    /// The instance rejected the event. An instance the event was to start
+    /// is discarded with it.
+    Dynamic { id: K, error: DynamicError },
+}
+
+impl<K: fmt::Debug> fmt::Display for SagaError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SagaError::Uncorrelated { event } => {
+                write!(f, "no correlation ID in event `{event}`")
+            }
+            SagaError::NoInstance { id, event } => {
+                write!(f, "no saga {id:?}, and event `{event}` doesn't start one")
+            }
+            SagaError::Dynamic { id, error } => write!(f, "saga {id:?} failed: {error:?}"),
+        }
+    }
+}
+
+/// Dynamic machines keyed by correlation ID; see the [module docs](self).
+pub struct SagaCoordinator<K, M: DynamicMachine> {
+    instances: BTreeMap<K, M>,
+    spawn: Spawn<K, M>,
+    correlate: Option<Correlate<K, M::Event>>,
+    starts: Vec<&'static str>,
+}
+
+impl<K: fmt::Debug, M: DynamicMachine> fmt::Debug for SagaCoordinator<K, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SagaCoordinator")
+            .field("machine", &M::definition().name)
+            .field("instances", &self.instances.keys().collect::<Vec<_>>())
+            .field("starts", &self.starts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, M> SagaCoordinator<K, M>
+where
+    K: Ord + Clone,
+    M: DynamicMachine,
+{
+    /// A coordinator without instances, building new ones with `spawn`.
+    ///
+    /// No event starts an instance until one is named with
+    /// [`start_on`](Self::start_on).
+    pub fn new(spawn: impl FnMut(&K) -> M + Send + 'static) -> Self {
+        Self {
+            instances: BTreeMap::new(),
+            spawn: Box::new(spawn),
+            correlate: None,
+            starts: Vec::new(),
+        }
+    }
+
+    /// Create an instance when an `event` arrives for an ID without one.
+    ///
+    /// # Panics
+    ///
+    /// If the machine has no event named `event`.
+    pub fn start_on(mut self, event: &'static str) -> Self {
+        let definition = M::definition();
+        assert!(
+            definition.events.iter().any(|known| known.name == event),
+            "`{event}` isn't an event of `{}`",
+            definition.name
+        );
+        self.starts.push(event);
+        self
+    }
+
+    /// Read correlation IDs out of events for [`route`](Self::route);
+    /// `correlate` returns `None` for events that don't carry one.
+    pub fn correlate_with(
+        mut self,
+        correlate: impl Fn(&M::Event) -> Option<K> + Send + 'static,
+    ) -> Self {
+        self.correlate = Some(Box::new(correlate));
+        self
+    }
+
+    /// Dispatch `event` to the instance with `id`, creating it first if
+    /// there is none and `event` is a start event.
+    ///
+    /// An instance that ends up in a state without outgoing transitions is
+    /// removed and returned in [`Dispatched::finished`].
+    pub async fn handle(
+        &mut self,
+        id: K,
+        event: M::Event,
+    ) -> Result<Dispatched<K, M>, SagaError<K>> {
+        let name = M::event_name(&event);
+        let started = !self.instances.contains_key(&id);
+        if started && !self.starts.contains(&name) {
+            return Err(SagaError::NoInstance { id, event: name });
+        }
+        let spawn = &mut self.spawn;
+        let machine = self
+            .instances
+            .entry(id.clone())
+            .or_insert_with_key(|id| spawn(id));
+
+        match machine.dispatch(event).await {
+            Ok(output) => {
+                let terminal = M::definition()
+                    .transitions_from(&machine.state())
+                    .next()
+                    .is_none();
+                let finished = if terminal {
+                    self.instances.remove(&id)
+                } else {
+                    None
+                };
+                Ok(Dispatched {
+                    id,
+                    output,
+                    started,
+                    finished,
+                })
+            }
+            Err(error) => {
+                if started {
+                    self.instances.remove(&id);
+                }
+                Err(SagaError::Dynamic { id, error })
+            }
+        }
+    }
+
+    /// Dispatch `event` to the instance its correlation ID names, like
+    /// [`handle`](Self::handle).
+    pub async fn route(&mut self, event: M::Event) -> Result<Dispatched<K, M>, SagaError<K>> {
+        let id = self
+            .correlate
+            .as_ref()
+            .and_then(|correlate| correlate(&event));
+        match id {
+            Some(id) => self.handle(id, event).await,
+            None => Err(SagaError::Uncorrelated {
+                event: M::event_name(&event),
+            }),
+        }
+    }
+
+    /// Take over `machine` as the instance with `id`, e.g. one restored from
+    /// storage after a restart. Returns the instance it replaces.
+    pub fn insert(&mut self, id: K, machine: M) -> Option<M> {
+        self.instances.insert(id, machine)
+    }
+
+    /// Stop coordinating the instance with `id` and return it, e.g. to
+    /// abandon a saga that timed out.
+    pub fn remove(&mut self, id: &K) -> Option<M> {
+        self.instances.remove(id)
+    }
+
+    pub fn get(&self, id: &K) -> Option<&M> {
+        self.instances.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: &K) -> Option<&mut M> {
+        self.instances.get_mut(id)
+    }
+
+    /// The IDs of the running instances, in order.
+    pub fn ids(&self) -> impl Iterator<Item = &K> {
+        self.instances.keys()
+    }
+
+    /// The running instances with their IDs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &M)> {
+        self.instances.iter()
+    }
+
+    /// The number of running instances.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Whether no instance is running.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+}
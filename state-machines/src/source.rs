@@ -0,0 +1,125 @@
+//! Driving a machine from a message queue.
+//!
+//! An [`EventSource`] hands out typed events one delivery at a time, and
+//! takes each one back as acknowledged or rejected: a Kafka consumer
+//! committing offsets, a NATS JetStream subscription, an SQS queue.
+//! [`pump`] feeds a dynamic machine from one, acking every event the machine
+//! accepts and nacking every event it rejects, so the broker can redeliver
+//! or dead-letter it:
+//!
+//! ```rust,ignore
+//! let mut order = DynamicOrder::new(ctx);
+//! let pumped = pump(&mut order, &mut consumer).await?;
+//! tracing::info!(acked = pumped.acked, nacked = pumped.nacked, "queue drained");
+//! ```
+//!
+//! Sources decode messages into the machine's event enum themselves, so a
+//! message that isn't an event at all never reaches the machine.
+
+use core::future::Future;
+use state_machines_core::{DynamicError, DynamicMachine};
+
+/// One event taken from an [`EventSource`], with the receipt needed to
+/// acknowledge it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery<E, R> {
+    pub event: E,
+    pub receipt: R,
+}
+
+type DeliveryOf<Src> = Delivery<<Src as EventSource>::Event, <Src as EventSource>::Receipt>;
+
+/// An async stream of typed events with acknowledgements.
+///
+/// Every delivery returned by `next` is settled exactly once, with `ack` or
+/// `nack`, before `next` is called again.
+pub trait EventSource {
+    type Event;
+    /// Identifies a delivery to the broker, e.g. a partition and offset or a
+    /// message handle.
+    type Receipt;
+    type Error;
+
+    /// The next delivery, waiting for one if necessary. `None` once the
+    /// source is closed.
+    fn next(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<DeliveryOf<Self>>, Self::Error>> + Send;
+
+    /// The event was handled; don't deliver it again.
+    fn ack(
+        &mut self,
+        receipt: Self::Receipt,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// The machine rejected the event with `error`. Redeliver it later, or
+    /// move it to a dead-letter queue.
+    fn nack(
+        &mut self,
+        receipt: Self::Receipt,
+        error: DynamicError,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// What [`pump_one`] did with a delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Handled<O> {
+    /// The machine accepted the event and it was acked.
+    Acked(O),
+    /// The machine rejected the event and it was nacked.
+    Nacked(DynamicError),
+}
+
+/// Counts of the deliveries [`pump`] settled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pumped {
+    pub acked: u64,
+    pub nacked: u64,
+}
+
+/// Dispatch the next delivery from `source` and settle it.
+///
+/// Returns `None` if the source is closed. Errors from the source itself are
+/// returned as they are; the delivery in hand, if any, is left unsettled for
+/// the broker to redeliver.
+pub async fn pump_one<M, Src>(
+    machine: &mut M,
+    source: &mut Src,
+) -> Result<Option<Handled<M::Output>>, Src::Error>
+where
+    M: DynamicMachine,
+    Src: EventSource<Event = M::Event>,
+{
+    let Some(Delivery { event, receipt }) = source.next().await? else {
+        return Ok(None);
+    };
+    match machine.dispatch(event).await {
+        Ok(output) => {
+            source.ack(receipt).await?;
+            Ok(Some(Handled::Acked(output)))
+        }
+        Err(err) => {
+            source.nack(receipt, err.clone()).await?;
+            Ok(Some(Handled::Nacked(err)))
+        }
+    }
+}
+
+/// Dispatch deliveries from `source` until it closes.
+///
+/// Stops at the first source error; everything settled before it stays
+/// settled.
+pub async fn pump<M, Src>(machine: &mut M, source: &mut Src) -> Result<Pumped, Src::Error>
+where
+    M: DynamicMachine,
+    Src: EventSource<Event = M::Event>,
+{
+    let mut pumped = Pumped::default();
+    while let Some(handled) = pump_one(machine, source).await? {
+        match handled {
+            Handled::Acked(_) => pumped.acked += 1,
+            Handled::Nacked(_) => pumped.nacked += 1,
+        }
+    }
+    Ok(pumped)
+}
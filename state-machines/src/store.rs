@@ -0,0 +1,243 @@
+//! Sharing a machine between processes through an external state store.
+//!
+//! A [`StateStore`] keeps each machine's state name together with a version
+//! that goes up on every write. [`SharedMachine`] dispatches events locally
+//! and then saves the new state only if the version is still the one it last
+//! saw, so when two nodes transition the same machine concurrently, one of
+//! them gets [`SharedError::StaleState`] instead of silently overwriting the
+//! other:
+//!
+//! ```rust,ignore
+//! let mut order = SharedMachine::open(store, "order:42", DynamicOrder::new(ctx)).await?;
+//! match order.transition(OrderEvent::Pay).await {
+//!     Err(SharedError::StaleState { .. }) => order.refresh().await?, // another node won
+//!     other => other?,
+//! }
+//! ```
+//!
+//! Machines need `persistent: true`. `state-machines-redis` provides a
+//! Redis-backed store.
+
+extern crate alloc;
+
+use alloc::string::String;
+use core::fmt;
+use core::future::Future;
+use state_machines_core::{DynamicError, DynamicMachine};
+
+/// A stored state name and the number of writes that produced it.
+///
+/// Version 0 means nothing is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Versioned {
+    pub state: String,
+    pub version: u64,
+}
+
+/// Result of [`StateStore::compare_and_swap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Swap {
+    /// The state was written; this is its new version.
+    Swapped(u64),
+    /// The stored version didn't match; nothing was written. Holds what is
+    /// stored instead (`None` if nothing is).
+    Stale(Option<Versioned>),
+}
+
+/// Storage for machine states with optimistic concurrency.
+///
+/// Implementations must make `compare_and_swap` atomic with respect to
+/// every other writer of the same `id`.
+pub trait StateStore {
+    type Error;
+
+    /// The state stored under `id`, if any.
+    fn load(&self, id: &str)
+    -> impl Future<Output = Result<Option<Versioned>, Self::Error>> + Send;
+
+    /// Store `state` whatever the current version, returning the new version.
+    fn save(&self, id: &str, state: &str) -> impl Future<Output = Result<u64, Self::Error>> + Send;
+
+    /// Store `state` only if the stored version is `expected` (0 for "nothing
+    /// stored yet").
+    fn compare_and_swap(
+        &self,
+        id: &str,
+        expected: u64,
+        state: &str,
+    ) -> impl Future<Output = Result<Swap, Self::Error>> + Send;
+}
+
+/// Why a [`SharedMachine`] couldn't open or transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharedError<E> {
+    /// The machine rejected the event; nothing was written.
+    Dynamic(DynamicError),
+    /// The store failed; the machine is back in its previous state.
+    Store(E),
+    /// Another node wrote the machine first; the local machine is back in
+    /// its previous state. Call `refresh()` to adopt the stored one.
+    StaleState {
+        stored: Option<Versioned>,
+        expected: u64,
+    },
+    /// The store holds a state name the machine doesn't have.
+    UnknownState(String),
+}
+
+impl<E: fmt::Display> fmt::Display for SharedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharedError::Dynamic(err) => write!(f, "transition failed: {err:?}"),
+            SharedError::Store(err) => write!(f, "state store error: {err}"),
+            SharedError::StaleState {
+                stored: Some(stored),
+                expected,
+            } => write!(
+                f,
+                "stale state: expected version {expected}, store has `{}` at version {}",
+                stored.state, stored.version
+            ),
+            SharedError::StaleState {
+                stored: None,
+                expected,
+            } => write!(
+                f,
+                "stale state: expected version {expected}, store is empty"
+            ),
+            SharedError::UnknownState(name) => write!(f, "unknown stored state `{name}`"),
+        }
+    }
+}
+
+impl<E> From<DynamicError> for SharedError<E> {
+    fn from(err: DynamicError) -> Self {
+        SharedError::Dynamic(err)
+    }
+}
+
+/// A dynamic machine whose state is shared through a [`StateStore`].
+#[derive(Debug)]
+pub struct SharedMachine<M, St> {
+    store: St,
+    id: String,
+    version: u64,
+    machine: M,
+}
+
+impl<M, St> SharedMachine<M, St>
+where
+    M: DynamicMachine,
+    St: StateStore,
+{
+    /// Attach `machine` to the state stored under `id`.
+    ///
+    /// If something is stored, the machine moves to that state (keeping its
+    /// context); otherwise the machine's current state is stored as version 1.
+    pub async fn open(
+        store: St,
+        id: impl Into<String>,
+        machine: M,
+    ) -> Result<Self, SharedError<St::Error>> {
+        let mut shared = Self {
+            store,
+            id: id.into(),
+            version: 0,
+            machine,
+        };
+        if !shared.refresh().await? {
+            let name = M::state_name(&shared.machine.state());
+            match shared.store.compare_and_swap(&shared.id, 0, name).await {
+                Ok(Swap::Swapped(version)) => shared.version = version,
+                // Another node created it first; follow that one
+                Ok(Swap::Stale(_)) => {
+                    shared.refresh().await?;
+                }
+                Err(err) => return Err(SharedError::Store(err)),
+            }
+        }
+        Ok(shared)
+    }
+
+    /// Dispatch `event` and store the new state, if no other node has
+    /// written the machine since this one last loaded or saved it.
+    ///
+    /// Self-transitions are stored too, so every successful call bumps the
+    /// version. On error the machine keeps its previous state, with the
+    /// data it held, and the transition's `after_commit` callbacks don't
+    /// run; they run only once the new state is stored.
+    pub async fn transition(
+        &mut self,
+        event: M::Event,
+    ) -> Result<M::Output, SharedError<St::Error>> {
+        let from = self.machine.state();
+        let output = self.machine.dispatch_uncommitted(event).await?;
+        let to = M::state_name(&self.machine.state());
+
+        match self
+            .store
+            .compare_and_swap(&self.id, self.version, to)
+            .await
+        {
+            Ok(Swap::Swapped(version)) => {
+                self.version = version;
+                self.machine.commit().await;
+                Ok(output)
+            }
+            Ok(Swap::Stale(stored)) => {
+                self.machine.rollback(from);
+                Err(SharedError::StaleState {
+                    stored,
+                    expected: self.version,
+                })
+            }
+            Err(err) => {
+                self.machine.rollback(from);
+                Err(SharedError::Store(err))
+            }
+        }
+    }
+
+    /// Move the machine to the stored state and version. Returns `false`
+    /// (leaving the machine alone) if nothing is stored.
+    pub async fn refresh(&mut self) -> Result<bool, SharedError<St::Error>> {
+        let Some(stored) = self
+            .store
+            .load(&self.id)
+            .await
+            .map_err(SharedError::Store)?
+        else {
+            return Ok(false);
+        };
+        let state =
+            M::state_from_name(&stored.state).ok_or(SharedError::UnknownState(stored.state))?;
+        self.machine.restore_state(state);
+        self.version = stored.version;
+        Ok(true)
+    }
+
+    /// The key the machine is stored under.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The version of the stored state this machine last loaded or wrote.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The wrapped machine, for queries such as `current_state()`.
+    pub fn machine(&self) -> &M {
+        &self.machine
+    }
+
+    /// The store the machine is shared through.
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+
+    /// Stop sharing and return the machine.
+    pub fn into_inner(self) -> M {
+        self.machine
+    }
+}
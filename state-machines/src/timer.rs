@@ -0,0 +1,22 @@
+//! Runtime adapters for `callback_timeout`.
+//!
+//! A machine declared with `callback_timeout: Duration::from_secs(2)` bounds
+//! its async guards and callbacks with [`Tokio`] unless it names another
+//! [`AsyncTimer`](crate::AsyncTimer) via `callback_timeout: { after: ..., timer: ... }`.
+
+use core::time::Duration;
+
+use crate::AsyncTimer;
+
+/// Sleeps with `tokio::time::sleep`, so it needs a Tokio runtime with the
+/// time driver enabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tokio;
+
+impl AsyncTimer for Tokio {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(duration: Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+}
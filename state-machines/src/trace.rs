@@ -0,0 +1,248 @@
+//! Recording the order of guard and callback invocations.
+//!
+//! With the `trace` feature enabled, every generated transition method reports
+//! each around callback, guard, chooser, before callback, action, compensation
+//! hook, state change, and after callback it runs. A [`TraceRecorder`] collects
+//! those steps on the current thread so tests can assert on the exact order:
+//!
+//! ```rust
+//! use state_machines::{state_machine, trace::TraceRecorder};
+//!
+//! state_machine! {
+//!     name: Door,
+//!     initial: Closed,
+//!     states: [Closed, Open],
+//!     events {
+//!         open {
+//!             guards: [unlocked],
+//!             before: [log_open],
+//!             transition: { from: Closed, to: Open }
+//!         }
+//!     }
+//! }
+//!
+//! impl<C, S> Door<C, S> {
+//!     fn unlocked(&self, _ctx: &C) -> bool {
+//!         true
+//!     }
+//!
+//!     fn log_open(&self) {}
+//! }
+//!
+//! let recorder = TraceRecorder::start();
+//! let _open = Door::new(()).open().unwrap();
+//! let trace = recorder.finish();
+//!
+//! assert_eq!(
+//!     trace.to_string(),
+//!     "Door.open: guard unlocked -> true\n\
+//!      Door.open: before log_open\n\
+//!      Door.open: Closed -> Open\n"
+//! );
+//! ```
+//!
+//! The text form (and [`Trace::to_mermaid`]) is stable, so it works well with
+//! snapshot testing tools such as `insta`.
+
+extern crate std;
+
+use core::cell::RefCell;
+use core::fmt;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::{AroundStage, MachineId};
+
+/// A single step of a transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceStep {
+    /// An around callback is about to run.
+    Around {
+        callback: &'static str,
+        stage: AroundStage,
+    },
+    /// A guard (or `unless` guard) method returned `result`.
+    Guard { guard: &'static str, result: bool },
+    /// A `choose:` method is about to pick the target.
+    Choose { chooser: &'static str },
+    /// A before callback is about to run.
+    Before { callback: &'static str },
+    /// The transition action returned `accepted`.
+    Action {
+        action: &'static str,
+        accepted: bool,
+    },
+    /// A compensation hook is about to run.
+    Compensate { hook: &'static str },
+    /// The machine moved from one state to another.
+    Transition {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// An after callback is about to run.
+    After { callback: &'static str },
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Around { callback, stage } => write!(f, "around {} ({:?})", callback, stage),
+            Self::Guard { guard, result } => write!(f, "guard {} -> {}", guard, result),
+            Self::Choose { chooser } => write!(f, "choose {}", chooser),
+            Self::Before { callback } => write!(f, "before {}", callback),
+            Self::Action { action, accepted } => write!(f, "action {} -> {}", action, accepted),
+            Self::Compensate { hook } => write!(f, "compensate {}", hook),
+            Self::Transition { from, to } => write!(f, "{} -> {}", from, to),
+            Self::After { callback } => write!(f, "after {}", callback),
+        }
+    }
+}
+
+/// A step together with the machine and event it belongs to.
+///
+/// `id` is the machine's [`MachineId`], if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub machine: &'static str,
+    pub id: Option<MachineId>,
+    pub event: &'static str,
+    pub step: TraceStep,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.machine)?;
+        if let Some(id) = self.id {
+            write!(f, "{}", id)?;
+        }
+        write!(f, ".{}: {}", self.event, self.step)
+    }
+}
+
+/// The steps recorded by a [`TraceRecorder`], in invocation order.
+///
+/// `Display` renders one entry per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// The recorded entries.
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Render the trace as a Mermaid sequence diagram.
+    ///
+    /// Each machine is a participant; guards and callbacks are messages to
+    /// itself and state changes are notes.
+    pub fn to_mermaid(&self) -> String {
+        use core::fmt::Write;
+
+        let mut out = String::from("sequenceDiagram\n");
+        let mut participants: Vec<&str> = Vec::new();
+        for entry in &self.entries {
+            if !participants.contains(&entry.machine) {
+                participants.push(entry.machine);
+                let _ = writeln!(out, "    participant {}", entry.machine);
+            }
+        }
+        for entry in &self.entries {
+            let _ = match entry.step {
+                TraceStep::Transition { from, to } => writeln!(
+                    out,
+                    "    Note over {}: {}: {} to {}",
+                    entry.machine, entry.event, from, to
+                ),
+                step => writeln!(
+                    out,
+                    "    {}->>{}: {}: {}",
+                    entry.machine, entry.machine, entry.event, step
+                ),
+            };
+        }
+        out
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+std::thread_local! {
+    static ACTIVE: RefCell<Option<Vec<TraceEntry>>> = const { RefCell::new(None) };
+}
+
+/// Collects trace entries from machines running on the current thread.
+///
+/// Recording stops when the recorder is finished or dropped. Async machines
+/// are traced as long as they're polled on the recording thread.
+#[derive(Debug)]
+pub struct TraceRecorder {
+    _not_send: core::marker::PhantomData<*const ()>,
+}
+
+impl TraceRecorder {
+    /// Start recording on the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another recorder is already active on this thread.
+    pub fn start() -> Self {
+        ACTIVE.with(|active| {
+            let mut active = active.borrow_mut();
+            assert!(
+                active.is_none(),
+                "a TraceRecorder is already active on this thread"
+            );
+            *active = Some(Vec::new());
+        });
+        Self {
+            _not_send: core::marker::PhantomData,
+        }
+    }
+
+    /// Stop recording and return everything recorded so far.
+    pub fn finish(self) -> Trace {
+        let entries = ACTIVE.with(|active| active.borrow_mut().take());
+        Trace {
+            entries: entries.unwrap_or_default(),
+        }
+    }
+}
+
+impl Drop for TraceRecorder {
+    fn drop(&mut self) {
+        // `try_with` because the thread-local may already be gone at thread exit
+        let _ = ACTIVE.try_with(|active| active.borrow_mut().take());
+    }
+}
+
+/// Append a step to the active recorder, if any, and note state changes for
+/// transition coverage.
+pub(crate) fn record(
+    machine: &'static str,
+    id: Option<MachineId>,
+    event: &'static str,
+    step: TraceStep,
+) {
+    if let TraceStep::Transition { from, to } = step {
+        crate::coverage::record(machine, event, from, to);
+    }
+    let _ = ACTIVE.try_with(|active| {
+        if let Some(entries) = active.borrow_mut().as_mut() {
+            entries.push(TraceEntry {
+                machine,
+                id,
+                event,
+                step,
+            });
+        }
+    });
+}
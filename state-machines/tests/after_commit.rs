@@ -0,0 +1,247 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::rc::Rc;
+
+use pollster::block_on;
+use state_machines::{DynamicError, DynamicMachine, state_machine};
+
+/// The order of the callbacks that ran, shared with the test.
+#[derive(Debug, Default, Clone)]
+pub struct Outbox {
+    pub log: Rc<RefCell<Vec<&'static str>>>,
+    pub approve: bool,
+}
+
+impl Outbox {
+    fn push(&self, entry: &'static str) {
+        self.log.borrow_mut().push(entry);
+    }
+}
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    persistent: true,
+    context: Outbox,
+    initial: Cart,
+    states: [Cart, Placed, Approved, Rejected],
+    events {
+        place {
+            after: [log_placed],
+            after_commit: [send_confirmation],
+            transition: { from: Cart, to: Placed, action: charge }
+        }
+        review {
+            after_commit: [notify_customer],
+            transition: { from: Placed, to: [Approved, Rejected], choose: decide }
+        }
+        audit {
+            before: [audit_or_panic],
+            transition: { from: Cart, to: Cart }
+        }
+    }
+}
+
+impl<S> Order<S> {
+    fn log_placed(&self) {
+        self.ctx.push("after");
+    }
+    fn send_confirmation(&self) {
+        self.ctx.push("after_commit");
+    }
+    fn notify_customer(&self) {
+        self.ctx.push("notify");
+    }
+    fn audit_or_panic(&self) {
+        panic!("audit log unavailable");
+    }
+    fn charge(ctx: &mut Outbox) -> bool {
+        ctx.push("action");
+        ctx.approve
+    }
+    fn decide(&self, ctx: &Outbox) -> OrderReviewChoice {
+        if ctx.approve {
+            OrderReviewChoice::Approved
+        } else {
+            OrderReviewChoice::Rejected
+        }
+    }
+}
+
+fn outbox(approve: bool) -> (Outbox, Rc<RefCell<Vec<&'static str>>>) {
+    let outbox = Outbox {
+        approve,
+        ..Outbox::default()
+    };
+    let log = Rc::clone(&outbox.log);
+    (outbox, log)
+}
+
+#[test]
+fn after_commit_runs_last() {
+    let (ctx, log) = outbox(true);
+    let _placed = Order::new(ctx).place().expect("charged");
+    assert_eq!(*log.borrow(), ["action", "after", "after_commit"]);
+}
+
+#[test]
+fn after_commit_skips_rejected_transitions() {
+    let (ctx, log) = outbox(false);
+    let (_cart, err) = Order::new(ctx).place().expect_err("charge declined");
+    assert_eq!(err.guard, "charge");
+    assert_eq!(*log.borrow(), ["action"]);
+}
+
+#[test]
+fn after_commit_runs_for_every_choice_target() {
+    let (ctx, log) = outbox(true);
+    let placed = Order::new(ctx).place().unwrap();
+    log.borrow_mut().clear();
+    assert!(matches!(
+        placed.review(),
+        Ok(OrderReviewOutcome::Approved(_))
+    ));
+    assert_eq!(*log.borrow(), ["notify"]);
+}
+
+#[test]
+fn handle_runs_after_commit_once_the_state_is_stored() {
+    let (ctx, log) = outbox(true);
+    let mut order = DynamicOrder::new(ctx);
+    order.handle(OrderEvent::Place).unwrap();
+    order.handle(OrderEvent::Review).unwrap();
+
+    assert_eq!(order.current_state(), "Approved");
+    assert_eq!(*log.borrow(), ["action", "after", "after_commit", "notify"]);
+}
+
+#[test]
+fn refused_uncommitted_dispatch_does_not_hold_later_commits() {
+    let (ctx, log) = outbox(true);
+    let mut order = DynamicOrder::new(ctx);
+    let result = catch_unwind(AssertUnwindSafe(|| order.handle(OrderEvent::Audit)));
+    assert!(result.is_err());
+
+    // The poisoned machine refuses the event before it gets to the commit
+    assert_eq!(
+        block_on(order.dispatch_uncommitted(OrderEvent::Place)),
+        Err(DynamicError::reentrant_dispatch("place"))
+    );
+
+    let (ctx, log_after_recovery) = outbox(true);
+    assert!(order.recover(ctx, OrderState::Cart));
+    order.handle(OrderEvent::Place).unwrap();
+    assert!(log.borrow().is_empty());
+    assert_eq!(
+        *log_after_recovery.borrow(),
+        ["action", "after", "after_commit"]
+    );
+}
+
+mod asynchronous {
+    use super::Outbox;
+    use pollster::block_on;
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Mailer,
+        dynamic: true,
+        persistent: true,
+        async: true,
+        context: Outbox,
+        initial: Draft,
+        states: [Draft, Sent],
+        events {
+            send {
+                after_commit: [deliver],
+                transition: { from: Draft, to: Sent }
+            }
+        }
+    }
+
+    impl<S> Mailer<S> {
+        async fn deliver(&self) {
+            self.ctx.push("deliver");
+        }
+    }
+
+    #[test]
+    fn async_machines_await_after_commit() {
+        let outbox = Outbox::default();
+        let log = std::rc::Rc::clone(&outbox.log);
+        let mut mailer = DynamicMailer::new(outbox);
+        block_on(mailer.handle(MailerEvent::Send)).unwrap();
+        assert_eq!(*log.borrow(), ["deliver"]);
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod shared {
+    use super::*;
+
+    use pollster::block_on;
+    use state_machines::store::{SharedError, SharedMachine, StateStore, Swap, Versioned};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// A single-entry store that can be told to fail.
+    #[derive(Debug, Clone, Default)]
+    struct FlakyStore {
+        entry: Arc<Mutex<Option<Versioned>>>,
+        down: Arc<AtomicBool>,
+    }
+
+    impl StateStore for FlakyStore {
+        type Error = ();
+
+        async fn load(&self, _id: &str) -> Result<Option<Versioned>, ()> {
+            Ok(self.entry.lock().unwrap().clone())
+        }
+
+        async fn save(&self, _id: &str, state: &str) -> Result<u64, ()> {
+            let mut entry = self.entry.lock().unwrap();
+            let version = entry.as_ref().map_or(0, |entry| entry.version) + 1;
+            let state = state.to_string();
+            *entry = Some(Versioned { state, version });
+            Ok(version)
+        }
+
+        async fn compare_and_swap(
+            &self,
+            id: &str,
+            _expected: u64,
+            state: &str,
+        ) -> Result<Swap, ()> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(());
+            }
+            Ok(Swap::Swapped(self.save(id, state).await?))
+        }
+    }
+
+    #[test]
+    fn shared_machines_run_after_commit_once_the_store_write_succeeds() {
+        let (ctx, log) = outbox(true);
+        let store = FlakyStore::default();
+        let mut order = block_on(SharedMachine::open(
+            store.clone(),
+            "order:1",
+            DynamicOrder::new(ctx),
+        ))
+        .unwrap();
+
+        store.down.store(true, Ordering::SeqCst);
+        let err = block_on(order.transition(OrderEvent::Place)).unwrap_err();
+        assert_eq!(err, SharedError::Store(()));
+        assert_eq!(order.machine().current_state(), "Cart");
+        assert_eq!(*log.borrow(), ["action", "after"]);
+
+        store.down.store(false, Ordering::SeqCst);
+        log.borrow_mut().clear();
+        block_on(order.transition(OrderEvent::Place)).unwrap();
+        assert_eq!(*log.borrow(), ["action", "after", "after_commit"]);
+    }
+}
@@ -0,0 +1,100 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{
+    core::{AroundOutcome, AroundStage, TransitionError},
+    state_machine,
+};
+use std::cell::RefCell;
+
+thread_local! {
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn take_log() -> Vec<String> {
+    LOG.with(|log| log.take())
+}
+
+state_machine! {
+    name: Vault,
+    dynamic: true,
+    initial: Unlocked,
+    states: [Unlocked, Sealed],
+    events {
+        deposit {
+            payload: u32,
+            around: [limit_check { payload: true }, log_stage],
+            transition: { from: Unlocked, to: Sealed }
+        }
+        reopen {
+            around: log_stage,
+            transition: { from: Sealed, to: Unlocked }
+        }
+    }
+}
+
+impl<C, S> Vault<C, S> {
+    fn limit_check(&self, stage: AroundStage, amount: &u32) -> AroundOutcome<Unlocked> {
+        LOG.with(|log| {
+            log.borrow_mut()
+                .push(format!("limit_check {stage:?} {amount}"))
+        });
+        if stage == AroundStage::Before && *amount > 1000 {
+            return AroundOutcome::Abort(TransitionError::guard_failed(
+                Unlocked,
+                "deposit",
+                "limit_check",
+            ));
+        }
+        AroundOutcome::Proceed
+    }
+
+    fn log_stage(&self, stage: AroundStage) -> AroundOutcome<Unlocked> {
+        LOG.with(|log| log.borrow_mut().push(format!("log_stage {stage:?}")));
+        AroundOutcome::Proceed
+    }
+}
+
+#[test]
+fn around_callback_receives_the_payload_in_both_stages() {
+    let sealed = Vault::new(()).deposit(250).unwrap();
+    assert_eq!(
+        take_log(),
+        [
+            "limit_check Before 250",
+            "log_stage Before",
+            "limit_check AfterSuccess 250",
+            "log_stage AfterSuccess",
+        ]
+    );
+
+    // The same plain around callback still works on an event without a payload
+    let _unlocked = sealed.reopen().unwrap();
+    assert_eq!(take_log(), ["log_stage Before", "log_stage AfterSuccess"]);
+}
+
+#[test]
+fn around_callback_can_abort_based_on_the_payload() {
+    let (_unlocked, err) = Vault::new(()).deposit(5000).unwrap_err();
+    assert_eq!(err.guard, "limit_check");
+    assert_eq!(take_log(), ["limit_check Before 5000"]);
+}
+
+#[test]
+fn dynamic_dispatch_passes_the_payload_to_around_callbacks() {
+    let mut vault = DynamicVault::new(());
+    assert!(vault.handle(VaultEvent::Deposit(2000)).is_err());
+    vault.handle(VaultEvent::Deposit(10)).unwrap();
+
+    assert_eq!(vault.state_enum(), VaultState::Sealed);
+    assert_eq!(
+        take_log(),
+        [
+            "limit_check Before 2000",
+            "limit_check Before 10",
+            "log_stage Before",
+            "limit_check AfterSuccess 10",
+            "log_stage AfterSuccess",
+        ]
+    );
+}
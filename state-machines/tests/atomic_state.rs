@@ -0,0 +1,62 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::thread;
+
+use state_machines::{AtomicState, StateRepr, state_machine};
+
+state_machine! {
+    name: Pump,
+    dynamic: true,
+    atomic_state: true,
+    initial: Off,
+    states: [Off, Priming, Running],
+    events {
+        start {
+            transition: { from: Off, to: Priming }
+        }
+        primed {
+            transition: { from: Priming, to: Running }
+        }
+        stop {
+            transition: { from: [Priming, Running], to: Off }
+        }
+    }
+}
+
+static PUMP_STATE: AtomicState<PumpState> = AtomicState::from_raw(PumpState::Off as u8);
+
+#[test]
+fn state_repr_round_trips() {
+    for state in [PumpState::Off, PumpState::Priming, PumpState::Running] {
+        assert_eq!(PumpState::from_raw(state.to_raw()), state);
+    }
+}
+
+#[test]
+fn shared_cell_tracks_dispatches_across_threads() {
+    let mut pump = DynamicPump::new(());
+    pump.share_state(&PUMP_STATE);
+
+    thread::scope(|scope| {
+        // Sampler only ever observes states the machine has been in
+        scope.spawn(|| {
+            for _ in 0..1_000 {
+                let _ = PUMP_STATE.load().name();
+            }
+        });
+
+        for _ in 0..100 {
+            pump.handle(PumpEvent::Start).unwrap();
+            pump.handle(PumpEvent::Primed).unwrap();
+            pump.handle(PumpEvent::Stop).unwrap();
+        }
+        pump.handle(PumpEvent::Start).unwrap();
+    });
+
+    assert_eq!(PUMP_STATE.load(), PumpState::Priming);
+
+    // Failed dispatches leave the mirrored state untouched
+    assert!(pump.handle(PumpEvent::Start).is_err());
+    assert_eq!(PUMP_STATE.load(), pump.state_enum());
+}
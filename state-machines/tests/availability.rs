@@ -0,0 +1,65 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    initial: Pending,
+    states: [
+        Pending,
+        superstate Open {
+            state Paid,
+            state Packed,
+        },
+        Shipped,
+        Cancelled,
+    ],
+    events {
+        pay {
+            payload: u32,
+            transition: { from: Pending, to: Paid }
+        }
+        pack {
+            transition: { from: Paid, to: Packed }
+        }
+        ship {
+            guards: [in_stock],
+            unless: [on_hold],
+            transition: { from: Packed, to: Shipped }
+        }
+        cancel {
+            transition: { from: [Pending, Open], to: Cancelled }
+        }
+    }
+}
+
+impl<C, S> Order<C, S> {
+    fn in_stock(&self, _ctx: &C) -> bool {
+        true
+    }
+
+    fn on_hold(&self, _ctx: &C) -> bool {
+        false
+    }
+}
+
+#[test]
+fn tabulates_events_guards_and_targets_per_state() {
+    assert_eq!(
+        OrderState::definition().availability().to_string(),
+        "\
+| State | Event | Guards | Target |
+| --- | --- | --- | --- |
+| Pending | `pay(u32)` |  | Paid |
+| Pending | `cancel` |  | Cancelled |
+| Paid | `pack` |  | Packed |
+| Paid | `cancel` |  | Cancelled |
+| Packed | `ship` | `in_stock`, `!on_hold` | Shipped |
+| Packed | `cancel` |  | Cancelled |
+| Shipped | — |  |  |
+| Cancelled | — |  |  |
+"
+    );
+}
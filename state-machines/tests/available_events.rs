@@ -0,0 +1,125 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use pollster::block_on;
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Cart {
+    pub items: u32,
+    pub blocked: bool,
+}
+
+state_machine! {
+    name: Checkout,
+    dynamic: true,
+    context: Cart,
+    initial: Browsing,
+    states: [Browsing, Paying, Done],
+    events {
+        add_item {
+            payload: u32,
+            guards: [in_stock],
+            transition: { from: Browsing, to: Browsing }
+        }
+        pay {
+            guards: [has_items],
+            unless: [blocked],
+            transition: { from: Browsing, to: Paying }
+        }
+        cancel {
+            transition: { from: [Browsing, Paying], to: Browsing }
+        }
+        confirm {
+            transition: { from: Paying, to: Done }
+        }
+    }
+}
+
+impl<S> Checkout<S> {
+    fn in_stock(&self, _cart: &Cart, _sku: &u32) -> bool {
+        false
+    }
+
+    fn has_items(&self, cart: &Cart) -> bool {
+        cart.items > 0
+    }
+
+    fn blocked(&self, cart: &Cart) -> bool {
+        cart.blocked
+    }
+}
+
+state_machine! {
+    name: Upload,
+    dynamic: true,
+    async: true,
+    context: bool,
+    initial: Queued,
+    states: [Queued, Sending],
+    events {
+        send {
+            guards: [online],
+            transition: { from: Queued, to: Sending }
+        }
+        drop_upload {
+            transition: { from: Queued, to: Queued }
+        }
+    }
+}
+
+impl<S> Upload<S> {
+    async fn online(&self, online: &bool) -> bool {
+        *online
+    }
+}
+
+fn available(checkout: &DynamicCheckout) -> Vec<&'static str> {
+    checkout.available_events().collect()
+}
+
+#[test]
+fn lists_events_from_the_definition() {
+    assert_eq!(
+        DynamicCheckout::events_from(&CheckoutState::Browsing),
+        ["add_item", "pay", "cancel"]
+    );
+    assert_eq!(
+        DynamicCheckout::events_from(&CheckoutState::Paying),
+        ["cancel", "confirm"]
+    );
+    assert!(DynamicCheckout::events_from(&CheckoutState::Done).is_empty());
+}
+
+#[test]
+fn available_events_evaluate_guards_against_the_live_machine() {
+    let checkout = DynamicCheckout::new(Cart::default());
+    // `pay` needs items; `add_item` guards need a payload, so it's listed
+    assert_eq!(available(&checkout), ["add_item", "cancel"]);
+
+    let mut checkout = DynamicCheckout::new(Cart {
+        items: 2,
+        blocked: false,
+    });
+    assert_eq!(available(&checkout), ["add_item", "pay", "cancel"]);
+
+    checkout.handle(CheckoutEvent::Pay).unwrap();
+    assert_eq!(available(&checkout), ["cancel", "confirm"]);
+
+    let checkout = DynamicCheckout::new(Cart {
+        items: 2,
+        blocked: true,
+    });
+    assert_eq!(available(&checkout), ["add_item", "cancel"]);
+}
+
+#[test]
+fn async_machines_await_their_guards() {
+    let upload = DynamicUpload::new(false);
+    let events: Vec<_> = block_on(upload.available_events()).collect();
+    assert_eq!(events, ["drop_upload"]);
+
+    let upload = DynamicUpload::new(true);
+    let events: Vec<_> = block_on(upload.available_events()).collect();
+    assert_eq!(events, ["send", "drop_upload"]);
+}
@@ -0,0 +1,168 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+/// Context that borrows a buffer owned by the caller.
+#[derive(Debug)]
+pub struct Bus<'a> {
+    pub received: &'a mut Vec<u8>,
+}
+
+state_machine! {
+    name: Link,
+    dynamic: true,
+    context: Bus<'a>,
+    initial: Listening,
+    states: [Listening, Closed],
+    events {
+        receive {
+            payload: &'p [u8],
+            guards: [non_empty],
+            transition: { from: Listening, to: Listening, action: store }
+        }
+        close {
+            payload: &'a str,
+            transition: { from: Listening, to: Closed }
+        }
+    }
+}
+
+impl<'a, S> Link<'a, S> {
+    fn non_empty(&self, _ctx: &Bus<'a>, frame: &&[u8]) -> bool {
+        !frame.is_empty()
+    }
+
+    fn store(ctx: &mut Bus<'a>, frame: &&[u8]) -> bool {
+        ctx.received.extend_from_slice(frame);
+        true
+    }
+}
+
+/// A parser fed straight from a receive buffer: the elided `&[u8]` borrows
+/// only for the call.
+#[derive(Debug, Default)]
+pub struct Framer {
+    pub frames: usize,
+    pub last_len: usize,
+}
+
+state_machine! {
+    name: Decoder,
+    dynamic: true,
+    context: Framer,
+    initial: Hunting,
+    states: [Hunting, Synced],
+    events {
+        sync {
+            payload: &[u8],
+            guards: [has_preamble],
+            transition: { from: Hunting, to: Synced }
+        }
+        frame {
+            payload: (&[u8], Option<&str>),
+            transition: { from: Synced, to: Synced, action: count }
+        }
+    }
+}
+
+impl<S> Decoder<S> {
+    fn has_preamble(&self, _framer: &Framer, bytes: &&[u8]) -> bool {
+        bytes.starts_with(&[0xAA, 0x55])
+    }
+
+    fn count(framer: &mut Framer, (bytes, _tag): &(&[u8], Option<&str>)) -> bool {
+        framer.frames += 1;
+        framer.last_len = bytes.len();
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Window<'buf> {
+    pub bytes: &'buf [u8],
+}
+
+state_machine! {
+    name: Reader,
+    generics: <'buf>,
+    mailbox: { capacity: 4, overflow: error },
+    initial: Idle,
+    states: [Idle, Reading(Window<'buf>)],
+    events {
+        open {
+            payload: &'p str,
+            transition: { from: Idle, to: Reading }
+        }
+    }
+}
+
+#[test]
+fn typestate_methods_accept_borrowed_payloads() {
+    let mut received = Vec::new();
+    let link = Link::new(Bus {
+        received: &mut received,
+    });
+
+    let frame = vec![1, 2, 3];
+    let link = link.receive(&frame).unwrap();
+    drop(frame);
+    let (link, err) = link.receive(&[]).unwrap_err();
+    assert_eq!(err.guard, "non_empty");
+
+    let reason = String::from("done");
+    let _closed = link.close(&reason).unwrap();
+    assert_eq!(received, [1, 2, 3]);
+}
+
+#[test]
+fn dynamic_events_borrow_their_payloads() {
+    let mut received = Vec::new();
+    {
+        let mut link = DynamicLink::new(Bus {
+            received: &mut received,
+        });
+        for chunk in [vec![4u8], vec![5, 6]] {
+            link.handle(LinkEvent::Receive(&chunk)).unwrap();
+        }
+        link.handle(LinkEvent::Close("bye")).unwrap();
+        assert!(link.is_closed());
+    }
+    assert_eq!(received, [4, 5, 6]);
+}
+
+#[test]
+fn explicit_lifetimes_reach_state_data_and_mailbox() {
+    let data = [7u8, 8, 9];
+    let mut reader = Reader::new(()).open("data.bin").unwrap();
+    reader.reading_data_mut().bytes = &data[1..];
+    assert_eq!(reader.reading_data().bytes, [8, 9]);
+
+    let mailbox = ReaderMailbox::new();
+    mailbox.post(ReaderEvent::Open("queued.bin")).unwrap();
+    let mut reader = DynamicReader::new(());
+    assert_eq!(reader.process(&mailbox), Ok(1));
+    assert!(reader.is_reading());
+}
+
+#[test]
+fn elided_payload_lifetimes_are_scoped_to_the_call() {
+    let mut buffer = [0u8; 8];
+    buffer[..3].copy_from_slice(&[0xAA, 0x55, 1]);
+    let decoder = Decoder::new(Framer::default()).sync(&buffer[..3]).unwrap();
+
+    // The receive buffer is free to be reused once the call returns
+    buffer[..2].copy_from_slice(&[9, 9]);
+    let decoder = decoder.frame((&buffer[..2], Some("ack"))).unwrap();
+    assert_eq!(decoder.ctx.last_len, 2);
+
+    let mut decoder = DynamicDecoder::new(Framer::default());
+    let rejected = decoder.handle(DecoderEvent::Sync(&buffer[..2]));
+    assert!(rejected.is_err());
+    buffer[..2].copy_from_slice(&[0xAA, 0x55]);
+    decoder.handle(DecoderEvent::Sync(&buffer)).unwrap();
+    decoder
+        .handle(DecoderEvent::Frame((&buffer[2..], None)))
+        .unwrap();
+    assert!(decoder.is_synced());
+}
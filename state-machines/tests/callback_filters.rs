@@ -0,0 +1,128 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Log {
+    pub calls: RefCell<Vec<&'static str>>,
+}
+
+state_machine! {
+    name: Player,
+    dynamic: true,
+    context: Log,
+    initial: Stopped,
+    states: [
+        Stopped,
+        superstate Playing {
+            state Streaming,
+            state Buffering,
+        },
+        Paused,
+    ],
+    events {
+        play {
+            before: [
+                log_play,
+                { name: warm_up, from: Stopped },
+                { name: resume_position, from: Paused },
+            ],
+            after: [{ name: announce, to: Streaming }],
+            transition: { from: [Stopped, Paused], to: Streaming }
+        }
+        stall {
+            transition: { from: Streaming, to: Buffering }
+        }
+        pause {
+            transition: { from: Streaming, to: Paused }
+        }
+        stop {
+            transition: {
+                from: [Playing, Paused],
+                to: Stopped,
+                before: [{ name: flush, from: Playing }],
+                after: [{ name: release, from: [Buffering, Paused] }, log_stop]
+            }
+        }
+    }
+}
+
+impl<S> Player<S> {
+    fn record(&self, call: &'static str) {
+        self.ctx.calls.borrow_mut().push(call);
+    }
+
+    fn log_play(&self) {
+        self.record("log_play");
+    }
+
+    fn warm_up(&self) {
+        self.record("warm_up");
+    }
+
+    fn resume_position(&self) {
+        self.record("resume_position");
+    }
+
+    fn announce(&self) {
+        self.record("announce");
+    }
+
+    fn flush(&self) {
+        self.record("flush");
+    }
+
+    fn release(&self) {
+        self.record("release");
+    }
+
+    fn log_stop(&self) {
+        self.record("log_stop");
+    }
+}
+
+fn take_calls(player: &Player<impl Sized>) -> Vec<&'static str> {
+    player.ctx.calls.take()
+}
+
+#[test]
+fn event_callbacks_run_only_on_matching_edges() {
+    let player = Player::new(Log::default()).play().unwrap();
+    assert_eq!(take_calls(&player), ["log_play", "warm_up", "announce"]);
+
+    let player = player.pause().unwrap().play().unwrap();
+    assert_eq!(
+        take_calls(&player),
+        ["log_play", "resume_position", "announce"]
+    );
+}
+
+#[test]
+fn transition_callbacks_filter_on_expanded_superstate_sources() {
+    let player = Player::new(Log::default()).play().unwrap().stall().unwrap();
+    player.ctx.calls.take();
+
+    let stopped = player.stop().unwrap();
+    assert_eq!(take_calls(&stopped), ["flush", "release", "log_stop"]);
+
+    let paused = stopped.play().unwrap().pause().unwrap();
+    paused.ctx.calls.take();
+    let stopped = paused.stop().unwrap();
+    assert_eq!(take_calls(&stopped), ["release", "log_stop"]);
+}
+
+#[test]
+fn dynamic_dispatch_uses_the_same_filters() {
+    let mut player = DynamicPlayer::new(Log::default());
+    player.handle(PlayerEvent::Play).unwrap();
+    player.handle(PlayerEvent::Stop).unwrap();
+
+    let player = player.into_stopped().unwrap();
+    assert_eq!(
+        take_calls(&player),
+        ["log_play", "warm_up", "announce", "flush", "log_stop"]
+    );
+}
@@ -0,0 +1,348 @@
+//! Every kind of guard and callback logs its phase, and both backends, sync
+//! and async, must log them in `TransitionPhase::ORDER`.
+
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use state_machines::core::{Sink, TransitionNotification};
+use state_machines::{Forward, TransitionPhase};
+
+/// The phases that ran, shared between the context, its sink and forward
+/// target, and the test.
+#[derive(Debug, Default, Clone)]
+pub struct Log(Rc<RefCell<Vec<TransitionPhase>>>);
+
+impl Log {
+    fn push(&self, phase: TransitionPhase) {
+        self.0.borrow_mut().push(phase);
+    }
+
+    fn take(&self) -> Vec<TransitionPhase> {
+        self.0.take()
+    }
+}
+
+impl<N> Sink<N> for Log {
+    fn send(&mut self, _notification: TransitionNotification<N>) {
+        self.push(TransitionPhase::Emit);
+    }
+}
+
+impl Forward<u8> for Log {
+    fn forward(&mut self, _event: u8) {
+        self.push(TransitionPhase::Forward);
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Depot {
+    pub log: Log,
+    pub sink: Log,
+    pub courier: Log,
+}
+
+impl Depot {
+    fn new() -> (Self, Log) {
+        let log = Log::default();
+        let depot = Self {
+            log: log.clone(),
+            sink: log.clone(),
+            courier: log.clone(),
+        };
+        (depot, log)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Label(u32);
+
+/// The phases of `TransitionPhase::ORDER` that appear in `used`, in order.
+fn in_order(used: &[TransitionPhase]) -> Vec<TransitionPhase> {
+    TransitionPhase::ORDER
+        .into_iter()
+        .filter(|phase| used.contains(phase))
+        .collect()
+}
+
+/// Every phase except `Choose`, which `ship` doesn't use.
+fn ship_phases() -> Vec<TransitionPhase> {
+    let mut phases = TransitionPhase::ORDER.to_vec();
+    phases.retain(|phase| *phase != TransitionPhase::Choose);
+    phases
+}
+
+fn route_phases() -> Vec<TransitionPhase> {
+    in_order(&[
+        TransitionPhase::Guards,
+        TransitionPhase::Choose,
+        TransitionPhase::Before,
+        TransitionPhase::After,
+    ])
+}
+
+mod sync {
+    use super::*;
+    use state_machines::core::{AroundOutcome, AroundStage};
+    use state_machines::state_machine;
+    use std::ops::ControlFlow;
+
+    state_machine! {
+        name: Parcel,
+        dynamic: true,
+        context: Depot,
+        sink: outbox,
+        initial: Packed,
+        states: [Packed, Shipped(Label), Express, Freight],
+        events {
+            ship {
+                payload: u32,
+                transform: weigh,
+                around: [audit],
+                guards: [in_stock],
+                before: [reserve],
+                after: [notify],
+                after_commit: [invoice],
+                transition: {
+                    from: Packed,
+                    to: Shipped,
+                    action: charge,
+                    init: print_label,
+                    emit: ParcelShipped,
+                    forward: { to: courier, as: 7u8 }
+                }
+            }
+            route {
+                guards: [routable],
+                before: [load],
+                after: [dispatch],
+                transition: { from: Packed, to: [Express, Freight], choose: pick }
+            }
+        }
+        callbacks: {
+            before_transition [{ name: open_books, on: ship }],
+            after_transition [{ name: close_books, on: ship }]
+        }
+    }
+
+    impl<S> Parcel<S> {
+        fn weigh(&self, ctx: &Depot, grams: u32) -> u32 {
+            ctx.log.push(TransitionPhase::Transform);
+            grams
+        }
+        fn audit(&self, stage: AroundStage) -> AroundOutcome<Packed> {
+            self.ctx.log.push(match stage {
+                AroundStage::Before => TransitionPhase::AroundBefore,
+                AroundStage::AfterSuccess => TransitionPhase::AroundAfter,
+            });
+            AroundOutcome::Proceed
+        }
+        fn in_stock(&self, ctx: &Depot, _grams: &u32) -> bool {
+            ctx.log.push(TransitionPhase::Guards);
+            true
+        }
+        fn routable(&self, ctx: &Depot) -> bool {
+            ctx.log.push(TransitionPhase::Guards);
+            true
+        }
+        fn pick(&self, ctx: &Depot) -> ParcelRouteChoice {
+            ctx.log.push(TransitionPhase::Choose);
+            ParcelRouteChoice::Express
+        }
+        fn open_books(&self) -> ControlFlow<()> {
+            self.ctx.log.push(TransitionPhase::BeforeTransition);
+            ControlFlow::Continue(())
+        }
+        fn reserve(&self, _grams: &u32) {
+            self.ctx.log.push(TransitionPhase::Before);
+        }
+        fn charge(ctx: &mut Depot, _grams: &u32) -> bool {
+            ctx.log.push(TransitionPhase::Action);
+            true
+        }
+        fn print_label(&self, ctx: &Depot, grams: &u32) -> Label {
+            ctx.log.push(TransitionPhase::Enter);
+            Label(*grams)
+        }
+        fn notify(&self, _grams: &u32) {
+            self.ctx.log.push(TransitionPhase::After);
+        }
+        fn load(&self) {
+            self.ctx.log.push(TransitionPhase::Before);
+        }
+        fn dispatch(&self) {
+            self.ctx.log.push(TransitionPhase::After);
+        }
+        fn close_books(&self) {
+            self.ctx.log.push(TransitionPhase::AfterTransition);
+        }
+        fn outbox(&mut self) -> &mut Log {
+            &mut self.ctx.sink
+        }
+        fn courier(&mut self) -> &mut Log {
+            &mut self.ctx.courier
+        }
+        fn invoice(&self) {
+            self.ctx.log.push(TransitionPhase::AfterCommit);
+        }
+    }
+
+    #[test]
+    fn typestate_methods_run_phases_in_order() {
+        let (depot, log) = Depot::new();
+        let shipped = Parcel::new(depot).ship(1200).unwrap();
+        assert_eq!(shipped.shipped_data(), &Label(1200));
+        assert_eq!(log.take(), ship_phases());
+
+        let (depot, log) = Depot::new();
+        assert!(Parcel::new(depot).route().is_ok());
+        assert_eq!(log.take(), route_phases());
+    }
+
+    #[test]
+    fn dynamic_dispatch_runs_phases_in_order() {
+        let (depot, log) = Depot::new();
+        let mut parcel = DynamicParcel::new(depot.clone());
+        parcel.handle(ParcelEvent::Ship(1200)).unwrap();
+        assert_eq!(log.take(), ship_phases());
+
+        let mut parcel = DynamicParcel::new(depot);
+        parcel.handle(ParcelEvent::Route).unwrap();
+        assert_eq!(log.take(), route_phases());
+    }
+}
+
+mod asynchronous {
+    use super::*;
+    use pollster::block_on;
+    use state_machines::core::{AroundOutcome, AroundStage};
+    use state_machines::state_machine;
+    use std::ops::ControlFlow;
+
+    state_machine! {
+        name: Parcel,
+        dynamic: true,
+        async: true,
+        context: Depot,
+        sink: outbox,
+        initial: Packed,
+        states: [Packed, Shipped(Label), Express, Freight],
+        events {
+            ship {
+                payload: u32,
+                transform: weigh,
+                around: [audit],
+                guards: [in_stock],
+                before: [reserve],
+                after: [notify],
+                after_commit: [invoice],
+                transition: {
+                    from: Packed,
+                    to: Shipped,
+                    action: charge,
+                    init: print_label,
+                    emit: ParcelShipped,
+                    forward: { to: courier, as: 7u8 }
+                }
+            }
+            route {
+                guards: [routable],
+                before: [load],
+                after: [dispatch],
+                transition: { from: Packed, to: [Express, Freight], choose: pick }
+            }
+        }
+        callbacks: {
+            before_transition [{ name: open_books, on: ship }],
+            after_transition [{ name: close_books, on: ship }]
+        }
+    }
+
+    impl<S> Parcel<S> {
+        fn weigh(&self, ctx: &Depot, grams: u32) -> u32 {
+            ctx.log.push(TransitionPhase::Transform);
+            grams
+        }
+        async fn audit(&self, stage: AroundStage) -> AroundOutcome<Packed> {
+            self.ctx.log.push(match stage {
+                AroundStage::Before => TransitionPhase::AroundBefore,
+                AroundStage::AfterSuccess => TransitionPhase::AroundAfter,
+            });
+            AroundOutcome::Proceed
+        }
+        async fn in_stock(&self, ctx: &Depot, _grams: &u32) -> bool {
+            ctx.log.push(TransitionPhase::Guards);
+            true
+        }
+        async fn routable(&self, ctx: &Depot) -> bool {
+            ctx.log.push(TransitionPhase::Guards);
+            true
+        }
+        async fn pick(&self, ctx: &Depot) -> ParcelRouteChoice {
+            ctx.log.push(TransitionPhase::Choose);
+            ParcelRouteChoice::Freight
+        }
+        async fn open_books(&self) -> ControlFlow<()> {
+            self.ctx.log.push(TransitionPhase::BeforeTransition);
+            ControlFlow::Continue(())
+        }
+        async fn reserve(&self, _grams: &u32) {
+            self.ctx.log.push(TransitionPhase::Before);
+        }
+        async fn charge(ctx: &mut Depot, _grams: &u32) -> bool {
+            ctx.log.push(TransitionPhase::Action);
+            true
+        }
+        fn print_label(&self, ctx: &Depot, grams: &u32) -> Label {
+            ctx.log.push(TransitionPhase::Enter);
+            Label(*grams)
+        }
+        async fn notify(&self, _grams: &u32) {
+            self.ctx.log.push(TransitionPhase::After);
+        }
+        async fn load(&self) {
+            self.ctx.log.push(TransitionPhase::Before);
+        }
+        async fn dispatch(&self) {
+            self.ctx.log.push(TransitionPhase::After);
+        }
+        async fn close_books(&self) {
+            self.ctx.log.push(TransitionPhase::AfterTransition);
+        }
+        fn outbox(&mut self) -> &mut Log {
+            &mut self.ctx.sink
+        }
+        fn courier(&mut self) -> &mut Log {
+            &mut self.ctx.courier
+        }
+        async fn invoice(&self) {
+            self.ctx.log.push(TransitionPhase::AfterCommit);
+        }
+    }
+
+    #[test]
+    fn typestate_methods_run_phases_in_order() {
+        let (depot, log) = Depot::new();
+        block_on(Parcel::new(depot).ship(1200)).unwrap();
+        assert_eq!(log.take(), ship_phases());
+
+        let (depot, log) = Depot::new();
+        assert!(block_on(Parcel::new(depot).route()).is_ok());
+        assert_eq!(log.take(), route_phases());
+    }
+
+    #[test]
+    fn dynamic_dispatch_runs_phases_in_order() {
+        let (depot, log) = Depot::new();
+        let mut parcel = DynamicParcel::new(depot.clone());
+        block_on(parcel.handle(ParcelEvent::Ship(1200))).unwrap();
+        assert_eq!(log.take(), ship_phases());
+
+        let mut parcel = DynamicParcel::new(depot);
+        block_on(parcel.handle(ParcelEvent::Route)).unwrap();
+        assert_eq!(log.take(), route_phases());
+    }
+}
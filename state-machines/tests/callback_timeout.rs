@@ -0,0 +1,191 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use core::future::{Ready, pending, ready};
+use core::time::Duration;
+use pollster::block_on;
+use state_machines::{
+    AsyncTimer, DynamicError, TransitionErrorKind, core::AroundOutcome, core::AroundStage,
+    state_machine,
+};
+use std::cell::Cell;
+
+/// A timer whose deadline has always passed, so any call that isn't ready on
+/// its first poll times out.
+pub struct Expired;
+
+impl AsyncTimer for Expired {
+    type Sleep = Ready<()>;
+
+    fn sleep(_duration: Duration) -> Self::Sleep {
+        ready(())
+    }
+}
+
+thread_local! {
+    static UNDONE: Cell<u32> = const { Cell::new(0) };
+}
+
+state_machine! {
+    name: Watchdog,
+    async: true,
+    dynamic: true,
+    callback_timeout: { after: Duration::from_millis(10), timer: Expired },
+    initial: Armed,
+    states: [Armed, Tripped],
+    events {
+        check {
+            guards: [stalled],
+            transition: { from: Armed, to: Tripped }
+        }
+        arm {
+            before: [stall],
+            transition: { from: Armed, to: Tripped, compensate: [undo] }
+        }
+        fire {
+            transition: { from: Armed, to: Tripped, action: stall_action, compensate: [undo] }
+        }
+        wrap {
+            around: [stall_around],
+            transition: { from: Armed, to: Tripped }
+        }
+        finish {
+            guards: [responsive],
+            after: [stall],
+            transition: { from: Armed, to: Tripped }
+        }
+    }
+}
+
+impl<C, S> Watchdog<C, S> {
+    async fn stalled(&self, _ctx: &C) -> bool {
+        pending().await
+    }
+
+    async fn responsive(&self, _ctx: &C) -> bool {
+        true
+    }
+
+    async fn stall(&self) {
+        pending::<()>().await
+    }
+
+    async fn stall_action(_ctx: &mut C) -> bool {
+        pending().await
+    }
+
+    async fn stall_around(&self, _stage: AroundStage) -> AroundOutcome<Armed> {
+        pending().await
+    }
+
+    fn undo(&self) {
+        UNDONE.with(|undone| undone.set(undone.get() + 1));
+    }
+}
+
+#[test]
+fn hung_guard_fails_the_transition_with_a_timeout() {
+    let (_armed, err) = block_on(Watchdog::new(()).check()).unwrap_err();
+    assert_eq!(err.kind, TransitionErrorKind::Timeout { name: "stalled" });
+    assert_eq!(err.guard, "stalled");
+    assert_eq!(
+        err.to_string(),
+        "`stalled` timed out for event `check` in state `Armed`"
+    );
+}
+
+#[test]
+fn hung_before_callback_and_action_run_compensation() {
+    UNDONE.with(|undone| undone.set(0));
+
+    let (armed, err) = block_on(Watchdog::new(()).arm()).unwrap_err();
+    assert_eq!(err.kind, TransitionErrorKind::Timeout { name: "stall" });
+    assert_eq!(UNDONE.with(Cell::get), 1);
+
+    let (_armed, err) = block_on(armed.fire()).unwrap_err();
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::Timeout {
+            name: "stall_action"
+        }
+    );
+    assert_eq!(UNDONE.with(Cell::get), 2);
+}
+
+#[test]
+fn hung_around_callback_fails_the_transition() {
+    let (_armed, err) = block_on(Watchdog::new(()).wrap()).unwrap_err();
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::Timeout {
+            name: "stall_around"
+        }
+    );
+}
+
+#[test]
+fn hung_after_callback_is_abandoned() {
+    // Ready guards win the race even against an expired deadline
+    let _tripped: Watchdog<(), Tripped> = block_on(Watchdog::new(()).finish()).unwrap();
+}
+
+#[test]
+fn dynamic_dispatch_reports_timeouts() {
+    let mut watchdog = DynamicWatchdog::new(());
+    assert_eq!(
+        block_on(watchdog.handle(WatchdogEvent::Check)),
+        Err(DynamicError::Timeout {
+            name: "stalled",
+            event: "check"
+        })
+    );
+    assert!(watchdog.is_armed());
+
+    // A timed-out guard counts as failing
+    let available: Vec<_> = block_on(watchdog.available_events()).collect();
+    assert_eq!(available, ["arm", "fire", "wrap", "finish"]);
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_timer {
+    use super::*;
+
+    state_machine! {
+        name: Probe,
+        async: true,
+        callback_timeout: Duration::from_millis(20),
+        context: Duration,
+        initial: Waiting,
+        states: [Waiting, Answered],
+        events {
+            ask {
+                guards: [remote_ok],
+                transition: { from: Waiting, to: Answered }
+            }
+        }
+    }
+
+    impl<S> Probe<S> {
+        async fn remote_ok(&self, delay: &Duration) -> bool {
+            tokio::time::sleep(*delay).await;
+            true
+        }
+    }
+
+    #[test]
+    fn tokio_timer_bounds_slow_guards() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        let (_waiting, err) = runtime
+            .block_on(Probe::new(Duration::from_secs(10)).ask())
+            .unwrap_err();
+        assert_eq!(err.kind, TransitionErrorKind::Timeout { name: "remote_ok" });
+
+        let _answered = runtime
+            .block_on(Probe::new(Duration::from_millis(1)).ask())
+            .unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Desk {
+    pub auto_approve_limit: u32,
+    pub reviews: u32,
+}
+
+state_machine! {
+    name: Expense,
+    dynamic: true,
+    context: Desk,
+    initial: Draft,
+    states: [
+        Draft,
+        Review,
+        Approved,
+        Rejected,
+        superstate Escalated {
+            state Pending,
+            state Audited,
+        },
+    ],
+    events {
+        submit {
+            transition: { from: Draft, to: Review }
+        }
+        decide {
+            payload: u32,
+            guards: [reviewer_available],
+            transition: { from: Review, to: [Approved, Rejected, Escalated], choose: route, action: count_review }
+        }
+        reopen {
+            transition: { from: [Approved, Rejected], to: Draft }
+        }
+    }
+}
+
+impl<S> Expense<S> {
+    fn reviewer_available(&self, ctx: &Desk, _amount: &u32) -> bool {
+        ctx.reviews < 3
+    }
+
+    fn route(&self, ctx: &Desk, amount: &u32) -> ExpenseDecideChoice {
+        if *amount == 0 {
+            ExpenseDecideChoice::Rejected
+        } else if *amount <= ctx.auto_approve_limit {
+            ExpenseDecideChoice::Approved
+        } else {
+            ExpenseDecideChoice::Escalated
+        }
+    }
+
+    fn count_review(ctx: &mut Desk, _amount: &u32) -> bool {
+        ctx.reviews += 1;
+        true
+    }
+}
+
+fn desk() -> Desk {
+    Desk {
+        auto_approve_limit: 500,
+        reviews: 0,
+    }
+}
+
+#[test]
+fn typestate_method_returns_outcome_for_chosen_target() {
+    let review = Expense::new(desk()).submit().unwrap();
+    match review.decide(120).unwrap() {
+        ExpenseDecideOutcome::Approved(approved) => {
+            let _draft: Expense<Draft> = approved.reopen().unwrap();
+        }
+        other => panic!("expected Approved, got {other:?}"),
+    }
+
+    let review = Expense::new(desk()).submit().unwrap();
+    assert!(matches!(
+        review.decide(0).unwrap(),
+        ExpenseDecideOutcome::Rejected(_)
+    ));
+}
+
+#[test]
+fn superstate_choice_enters_initial_child() {
+    let review = Expense::new(desk()).submit().unwrap();
+    let ExpenseDecideOutcome::Escalated(pending) = review.decide(9_000).unwrap() else {
+        panic!("expected Escalated");
+    };
+    let _pending: Expense<Pending> = pending;
+}
+
+#[test]
+fn guards_run_before_the_chooser() {
+    let review = Expense::new(Desk {
+        auto_approve_limit: 500,
+        reviews: 3,
+    })
+    .submit()
+    .unwrap();
+    let (_review, err) = review.decide(10).unwrap_err();
+    assert_eq!(err.guard, "reviewer_available");
+}
+
+#[test]
+fn dynamic_dispatch_follows_the_choice() {
+    let mut expense = DynamicExpense::new(desk());
+    expense.handle(ExpenseEvent::Submit).unwrap();
+    expense.handle(ExpenseEvent::Decide(800)).unwrap();
+    assert!(expense.is_pending());
+
+    let mut expense = DynamicExpense::new(desk());
+    expense.handle(ExpenseEvent::Submit).unwrap();
+    expense.handle(ExpenseEvent::Decide(0)).unwrap();
+    assert!(expense.is_rejected());
+    expense.handle(ExpenseEvent::Reopen).unwrap();
+    expense.handle(ExpenseEvent::Submit).unwrap();
+    expense.handle(ExpenseEvent::Decide(40)).unwrap();
+    assert_eq!(expense.current_state(), "Approved");
+    assert_eq!(expense.into_approved().unwrap().ctx.reviews, 2);
+}
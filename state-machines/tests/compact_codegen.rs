@@ -0,0 +1,103 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use state_machines::{DynamicError, state_machine};
+
+static PUMP_PRIMED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default)]
+struct FlowData {
+    litres: u32,
+}
+
+state_machine! {
+    name: CoolantLoop,
+    dynamic: true,
+    compact_codegen: true,
+    initial: Offline,
+    states: [Offline, Standby, Flowing(FlowData), Fault],
+    events {
+        power_up {
+            transition: { from: Offline, to: Standby }
+        }
+        open_valve {
+            // Guarded edge keeps calling the typed method
+            guards: [pump_primed],
+            transition: { from: Standby, to: Flowing }
+        }
+        close_valve {
+            transition: { from: Flowing, to: Standby }
+        }
+        trip {
+            transition: { from: [Offline, Standby, Flowing], to: Fault }
+        }
+        reset {
+            transition: { from: Fault, to: Offline }
+        }
+    }
+}
+
+impl<C, S> CoolantLoop<C, S> {
+    fn pump_primed(&self, _ctx: &C) -> bool {
+        PUMP_PRIMED.load(Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn table_driven_transitions_match_typed_semantics() {
+    PUMP_PRIMED.store(true, Ordering::SeqCst);
+    let mut coolant = DynamicCoolantLoop::new(());
+
+    coolant.handle(CoolantLoopEvent::PowerUp).unwrap();
+    assert_eq!(coolant.state_enum(), CoolantLoopState::Standby);
+
+    coolant.handle(CoolantLoopEvent::OpenValve).unwrap();
+    coolant.flowing_data_mut().unwrap().litres = 40;
+
+    // Leaving a data state through the table clears its storage like a typed transition
+    coolant.handle(CoolantLoopEvent::Trip).unwrap();
+    assert!(coolant.is_fault());
+    assert!(coolant.flowing_data().is_none());
+
+    coolant.handle(CoolantLoopEvent::Reset).unwrap();
+    assert!(coolant.is_offline());
+}
+
+#[test]
+fn table_lookup_rejects_unknown_pairs() {
+    let mut coolant = DynamicCoolantLoop::new(());
+
+    let err = coolant.handle(CoolantLoopEvent::CloseValve).unwrap_err();
+    assert_eq!(
+        err,
+        DynamicError::invalid_transition("Offline", "close_valve")
+    );
+    assert!(coolant.is_offline());
+}
+
+state_machine! {
+    name: Shutter,
+    context: u8,
+    dynamic: true,
+    compact_codegen: true,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        toggle {
+            transition: { from: Closed, to: Open }
+            transition: { from: Open, to: Closed }
+        }
+    }
+}
+
+#[test]
+fn table_driven_transitions_with_concrete_context() {
+    let mut shutter = DynamicShutter::new(7);
+    shutter.handle(ShutterEvent::Toggle).unwrap();
+    assert!(shutter.is_open());
+
+    let typed = shutter.into_open().unwrap();
+    let _closed = typed.toggle().unwrap();
+}
@@ -0,0 +1,138 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::rc::Rc;
+
+use state_machines::{core::TransitionErrorKind, state_machine};
+
+type Journal = Rc<RefCell<Vec<&'static str>>>;
+
+state_machine! {
+    name: Booking,
+    context: Journal,
+    initial: Requested,
+    states: [Requested, Confirmed],
+    events {
+        confirm {
+            payload: bool,
+            transition: {
+                from: Requested,
+                to: Confirmed,
+                before: [reserve_seat, charge_card],
+                action: issue_ticket,
+                compensate: [release_seat, refund_card]
+            }
+        }
+        confirm_unchecked {
+            transition: {
+                from: Requested,
+                to: Confirmed,
+                before: [reserve_seat_or_panic],
+                compensate: [release_seat_quietly]
+            }
+        }
+        hold_then_confirm {
+            transition: {
+                from: Requested,
+                to: Confirmed,
+                before: [hold_seat, reserve_seat_or_panic],
+                compensate: [release_seat_quietly]
+            }
+        }
+    }
+}
+
+impl<S> Booking<S> {
+    fn reserve_seat(&self, _: &bool) {
+        self.ctx.borrow_mut().push("reserve_seat");
+    }
+
+    fn charge_card(&self, _: &bool) {
+        self.ctx.borrow_mut().push("charge_card");
+    }
+
+    fn issue_ticket(journal: &mut Journal, available: &bool) -> bool {
+        journal.borrow_mut().push("issue_ticket");
+        *available
+    }
+
+    fn release_seat(&self, _: &bool) {
+        self.ctx.borrow_mut().push("release_seat");
+    }
+
+    fn refund_card(&self, _: &bool) {
+        self.ctx.borrow_mut().push("refund_card");
+    }
+
+    fn reserve_seat_or_panic(&self) {
+        self.ctx.borrow_mut().push("reserve_seat");
+        panic!("seat map unavailable");
+    }
+
+    fn hold_seat(&mut self) {
+        self.ctx.borrow_mut().push("hold_seat");
+    }
+
+    fn release_seat_quietly(&self) {
+        self.ctx.borrow_mut().push("release_seat");
+    }
+}
+
+#[test]
+fn successful_transition_skips_compensation() {
+    let journal = Journal::default();
+    let _booking = Booking::new(journal.clone()).confirm(true).unwrap();
+
+    assert_eq!(
+        *journal.borrow(),
+        ["reserve_seat", "charge_card", "issue_ticket"]
+    );
+}
+
+#[test]
+fn action_veto_runs_compensation_in_reverse() {
+    let journal = Journal::default();
+    let (_booking, err) = Booking::new(journal.clone()).confirm(false).unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::ActionFailed {
+            action: "issue_ticket"
+        }
+    );
+    assert_eq!(
+        *journal.borrow(),
+        [
+            "reserve_seat",
+            "charge_card",
+            "issue_ticket",
+            "refund_card",
+            "release_seat"
+        ]
+    );
+}
+
+#[test]
+fn panicking_before_callback_runs_compensation() {
+    let journal = Journal::default();
+    let booking = Booking::new(journal.clone());
+
+    let result = catch_unwind(AssertUnwindSafe(move || booking.confirm_unchecked()));
+    assert!(result.is_err());
+    assert_eq!(*journal.borrow(), ["reserve_seat", "release_seat"]);
+}
+
+#[test]
+fn mutable_before_callbacks_run_under_compensation() {
+    let journal = Journal::default();
+    let booking = Booking::new(journal.clone());
+
+    let result = catch_unwind(AssertUnwindSafe(move || booking.hold_then_confirm()));
+    assert!(result.is_err());
+    assert_eq!(
+        *journal.borrow(),
+        ["hold_seat", "reserve_seat", "release_seat"]
+    );
+}
@@ -211,7 +211,7 @@ fn test_concrete_context_dynamic_guard_failure() {
     assert!(result.is_err(), "Launch should fail with 30% fuel");
 
     match result {
-        Err(DynamicError::GuardFailed { guard, event }) => {
+        Err(DynamicError::GuardFailed { guard, event, .. }) => {
             assert_eq!(guard, "has_fuel");
             assert_eq!(event, "launch");
         }
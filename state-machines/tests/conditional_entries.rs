@@ -0,0 +1,98 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+// `cfg(test)` is on in this crate; `cfg(any())` never is
+state_machine! {
+    name: Pump,
+    dynamic: true,
+    initial: Idle,
+    states: [
+        Idle,
+        Running,
+        #[cfg(test)]
+        Servicing,
+        #[cfg(any())]
+        Calibrating,
+    ],
+    events {
+        start {
+            transition: { from: Idle, to: Running }
+        }
+        stop {
+            transition: { from: Running, to: Idle }
+            #[cfg(test)]
+            transition: { from: Servicing, to: Idle }
+        }
+        #[cfg(test)]
+        service {
+            transition: { from: [Idle, Running], to: Servicing }
+        }
+        #[cfg(any())]
+        calibrate {
+            transition: { from: Idle, to: Calibrating }
+        }
+    }
+}
+
+#[test]
+fn enabled_entries_are_generated() {
+    let pump = Pump::new(()).start().unwrap().service().unwrap();
+    let _idle: Pump<(), Idle> = pump.stop().unwrap();
+
+    let mut pump = DynamicPump::new(());
+    pump.handle(PumpEvent::Service).unwrap();
+    assert_eq!(pump.current_state(), "Servicing");
+}
+
+#[test]
+fn disabled_entries_are_left_out() {
+    let names: Vec<_> = PumpState::definition()
+        .states
+        .iter()
+        .map(PumpState::name)
+        .collect();
+    assert_eq!(names, ["Idle", "Running", "Servicing"]);
+
+    let events: Vec<_> = PumpState::definition()
+        .events
+        .iter()
+        .map(|event| event.name)
+        .collect();
+    assert_eq!(events, ["start", "stop", "service"]);
+}
+
+// The struct form takes the same attributes
+state_machine!(Machine {
+    name: Valve,
+    initial: Shut,
+    states: [
+        Shut,
+        Open,
+        #[cfg(any())]
+        Stuck
+    ],
+    events: [
+        Event {
+            name: open,
+            transition: Transition {
+                from: Shut,
+                to: Open
+            }
+        },
+        #[cfg(any())]
+        Event {
+            name: jam,
+            transition: Transition {
+                from: Open,
+                to: Stuck
+            }
+        },
+    ],
+});
+
+#[test]
+fn struct_form_entries_can_be_gated() {
+    let _open: Valve<(), Open> = Valve::new(()).open().unwrap();
+}
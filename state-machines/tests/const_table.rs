@@ -0,0 +1,113 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Uplink,
+    dynamic: true,
+    initial: Idle,
+    states: [
+        Idle,
+        superstate Linked {
+            state Handshaking,
+            state Streaming,
+        },
+        Fault,
+        Closed,
+    ],
+    events {
+        dial {
+            guards: [has_route],
+            transition: { from: Idle, to: Handshaking }
+        }
+        accept {
+            transition: { from: Handshaking, to: Streaming }
+        }
+        fail {
+            transition: { from: [Idle, Linked], to: Fault }
+        }
+        reset {
+            transition: { from: Fault, to: Idle }
+        }
+        close {
+            transition: { from: [Idle, Fault], to: Closed }
+        }
+    }
+}
+
+impl<C, S> Uplink<C, S> {
+    fn has_route(&self, _ctx: &C) -> bool {
+        true
+    }
+}
+
+// Checked by the compiler: a misspelled name or a missing edge fails the build
+const _: () = assert!(UplinkState::TABLE.has_transition("Streaming", "fail"));
+const _: () = assert!(!UplinkState::TABLE.has_transition("Closed", "reset"));
+const _: () = assert!(UplinkState::TABLE.can_reach_from_all("Closed"));
+const _: () = assert!(UplinkState::TABLE.is_terminal("Closed"));
+
+const FAULT_IS_ALWAYS_REACHABLE: bool = {
+    let table = UplinkState::TABLE;
+    let mut index = 0;
+    let mut reachable = true;
+    while index < table.states.len() {
+        let state = table.states[index];
+        if !table.is_terminal(state) && !table.can_reach(state, "Fault") {
+            reachable = false;
+        }
+        index += 1;
+    }
+    reachable
+};
+const _: () = assert!(FAULT_IS_ALWAYS_REACHABLE);
+
+#[test]
+fn the_table_lists_leaf_states_and_events_in_order() {
+    let table = UplinkState::TABLE;
+    assert_eq!(
+        table.states,
+        ["Idle", "Handshaking", "Streaming", "Fault", "Closed"]
+    );
+    assert_eq!(table.events, ["dial", "accept", "fail", "reset", "close"]);
+    assert_eq!(table.state("Fault"), 3);
+    assert_eq!(table.event("close"), 4);
+}
+
+#[test]
+fn superstate_sources_are_expanded_to_leaves() {
+    let table = UplinkState::TABLE;
+    assert!(table.has_transition("Handshaking", "fail"));
+    assert!(table.has_transition("Streaming", "fail"));
+    assert!(!table.has_transition("Closed", "fail"));
+}
+
+#[test]
+fn reachability_matches_the_definition() {
+    let table = UplinkState::TABLE;
+    let definition = UplinkState::definition();
+    for (from_index, from) in definition.states.iter().enumerate() {
+        for (to_index, to) in definition.states.iter().enumerate() {
+            let expected = definition
+                .transitions_from(from)
+                .any(|(_, target)| target == to);
+            let edge = table
+                .edges
+                .iter()
+                .any(|edge| edge.from == from_index && edge.to == to_index);
+            assert_eq!(edge, expected, "{from:?} -> {to:?}");
+        }
+    }
+    assert!(table.can_reach("Idle", "Closed"));
+    assert!(table.can_reach("Closed", "Closed"));
+    assert!(!table.can_reach("Closed", "Idle"));
+    // `Closed` is terminal, so it can't get back to `Fault`
+    assert!(!table.can_reach_from_all("Fault"));
+}
+
+#[test]
+#[should_panic(expected = "no state with that name")]
+fn unknown_names_panic() {
+    UplinkState::TABLE.has_transition("Offline", "dial");
+}
@@ -0,0 +1,56 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::collections::BTreeSet;
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Valve,
+    dynamic: true,
+    state_derives: [PartialOrd, Ord],
+    event_derives: [Clone, PartialEq],
+    state_attrs: [#[repr(u8)]],
+    machine_attrs: [#[must_use]],
+    initial: Closed,
+    states: [Closed, Opening, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Opening }
+        }
+        settle {
+            payload: u8,
+            transition: { from: Opening, to: Open }
+        }
+    }
+}
+
+#[test]
+fn state_enum_gets_extra_derives_and_attrs() {
+    let visited: BTreeSet<ValveState> = [ValveState::Open, ValveState::Closed, ValveState::Opening]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        visited.into_iter().collect::<Vec<_>>(),
+        [ValveState::Closed, ValveState::Opening, ValveState::Open]
+    );
+
+    assert_eq!(ValveState::Open as u8, 2);
+    assert_eq!(size_of::<ValveState>(), 1);
+}
+
+#[test]
+fn event_enum_gets_extra_derives() {
+    let event = ValveEvent::Settle(40);
+    assert_eq!(event.clone(), ValveEvent::Settle(40));
+    assert_ne!(event, ValveEvent::Open);
+}
+
+#[test]
+fn machine_struct_gets_extra_attrs() {
+    let valve = Valve::new(());
+    let copy = valve.clone();
+
+    let _opening = valve.open().unwrap();
+    let _also_opening = copy.open().unwrap();
+}
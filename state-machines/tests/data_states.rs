@@ -0,0 +1,126 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    pub percent: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FailureInfo {
+    pub code: u32,
+    pub message: String,
+}
+
+state_machine! {
+    name: Job,
+    dynamic: true,
+    data_states: true,
+    transition_records: true,
+    state_derives: [PartialEq],
+    initial: Queued,
+    states: [Queued, Running(Progress), Failed(FailureInfo), Done],
+    events {
+        start {
+            transition: { from: Queued, to: Running }
+        }
+        fail {
+            transition: { from: Running, to: Failed }
+        }
+        retry {
+            transition: { from: Failed, to: Queued }
+        }
+        finish {
+            transition: { from: Running, to: Done }
+        }
+    }
+}
+
+#[test]
+fn state_enum_variants_carry_state_data() {
+    let mut job = DynamicJob::new(());
+    assert_eq!(job.state_enum(), JobState::Queued);
+
+    job.handle(JobEvent::Start).unwrap();
+    job.running_data_mut().unwrap().percent = 40;
+    assert_eq!(
+        job.state_enum(),
+        JobState::Running(Progress { percent: 40 })
+    );
+
+    job.handle(JobEvent::Fail).unwrap();
+    job.set_failed_data(FailureInfo {
+        code: 503,
+        message: "upstream unavailable".into(),
+    })
+    .unwrap();
+
+    match job.state_enum() {
+        JobState::Failed(info) => {
+            assert_eq!(info.code, 503);
+            assert_eq!(info.message, "upstream unavailable");
+        }
+        other => panic!("expected Failed, got {other:?}"),
+    }
+    assert!(job.is_failed());
+    assert_eq!(job.current_state(), "Failed");
+}
+
+#[test]
+fn freshly_entered_state_carries_default_data() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+    assert_eq!(job.state_enum(), JobState::Running(Progress::default()));
+}
+
+state_machine! {
+    name: Upload,
+    dynamic: true,
+    data_states: true,
+    state_derives: [PartialEq],
+    initial: Sending,
+    states: [Sending(Progress), Sent],
+    events {
+        finish {
+            transition: { from: Sending, to: Sent }
+        }
+    }
+}
+
+#[test]
+fn initial_state_carries_its_data_from_the_start() {
+    let mut upload = DynamicUpload::new(());
+    assert_eq!(
+        upload.state_enum(),
+        UploadState::Sending(Progress::default())
+    );
+
+    upload.sending_data_mut().unwrap().percent = 60;
+    assert_eq!(
+        upload.state_enum(),
+        UploadState::Sending(Progress { percent: 60 })
+    );
+    assert_eq!(Upload::new(()).sending_data(), &Progress::default());
+}
+
+#[test]
+fn transition_records_snapshot_data_on_both_sides() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+    job.running_data_mut().unwrap().percent = 90;
+
+    let record = job.handle(JobEvent::Finish).unwrap();
+    assert_eq!(record.from, JobState::Running(Progress { percent: 90 }));
+    assert_eq!(record.to, JobState::Done);
+    assert_eq!(record.to.name(), "Done");
+}
+
+#[test]
+fn typestate_api_is_unchanged() {
+    let job = Job::new(()).start().unwrap();
+    let failed = job.fail().unwrap();
+    assert_eq!(failed.failed_data(), &FailureInfo::default());
+    let _queued = failed.retry().unwrap();
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "alloc")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{TransitionChange, state_machine};
+
+/// The machine as a previous release shipped it; only its definition is used.
+// `OrderState`'s generated `Projector` impl matches on every `OrderEvent`
+// variant, which makes the never-constructed variants warn
+#[allow(dead_code)]
+mod v1 {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Order,
+        dynamic: true,
+        initial: Pending,
+        states: [Pending, Paid, Shipped, Cancelled],
+        events {
+            pay {
+                transition: { from: Pending, to: Paid }
+            }
+            ship {
+                transition: { from: Paid, to: Shipped }
+            }
+            cancel {
+                transition: { from: [Pending, Paid], to: Cancelled }
+            }
+        }
+    }
+}
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    initial: Pending,
+    states: [Pending, Paid, Dispatched, Cancelled],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        dispatch {
+            transition: { from: Paid, to: Dispatched }
+        }
+        cancel {
+            transition: { from: [Pending, Paid, Dispatched], to: Cancelled }
+        }
+    }
+}
+
+fn change(event: &'static str, from: &str, to: &str) -> TransitionChange {
+    TransitionChange {
+        event,
+        from: from.into(),
+        to: to.into(),
+    }
+}
+
+#[test]
+fn a_definition_has_no_changes_from_itself() {
+    let diff = OrderState::definition().diff(OrderState::definition());
+
+    assert!(diff.is_empty());
+    assert!(diff.is_backward_compatible());
+    assert_eq!(diff.to_string(), "");
+}
+
+#[test]
+fn lists_what_each_side_lacks() {
+    let diff = v1::OrderState::definition().diff(OrderState::definition());
+
+    assert_eq!(diff.added_states, ["Dispatched"]);
+    assert_eq!(diff.removed_states, ["Shipped"]);
+    assert_eq!(diff.added_events, ["dispatch"]);
+    assert_eq!(diff.removed_events, ["ship"]);
+    assert_eq!(
+        diff.added_transitions,
+        [
+            change("dispatch", "Paid", "Dispatched"),
+            change("cancel", "Dispatched", "Cancelled"),
+        ]
+    );
+    assert_eq!(
+        diff.removed_transitions,
+        [change("ship", "Paid", "Shipped")]
+    );
+}
+
+#[test]
+fn removals_break_backward_compatibility() {
+    let upgrade = v1::OrderState::definition().diff(OrderState::definition());
+    assert!(!upgrade.is_backward_compatible());
+    assert!(!upgrade.can_restore("Shipped"));
+    assert!(upgrade.can_restore("Paid"));
+
+    // Going back the other way removes what the upgrade added
+    let rollback = OrderState::definition().diff(v1::OrderState::definition());
+    assert_eq!(rollback.removed_states, ["Dispatched"]);
+    assert_eq!(rollback.added_states, ["Shipped"]);
+}
+
+#[test]
+fn displays_one_line_per_change() {
+    let diff = v1::OrderState::definition().diff(OrderState::definition());
+
+    assert_eq!(
+        diff.to_string(),
+        "- state Shipped\n\
+         + state Dispatched\n\
+         - event ship\n\
+         + event dispatch\n\
+         - transition ship: Paid -> Shipped\n\
+         + transition dispatch: Paid -> Dispatched\n\
+         + transition cancel: Dispatched -> Cancelled\n"
+    );
+}
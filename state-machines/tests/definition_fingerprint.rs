@@ -0,0 +1,106 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+/// The machine as a previous release shipped it.
+mod v1 {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Order,
+        dynamic: true,
+        initial: Pending,
+        states: [Pending, Paid, Shipped],
+        events {
+            pay {
+                transition: { from: Pending, to: Paid }
+            }
+            ship {
+                transition: { from: Paid, to: Shipped }
+            }
+        }
+    }
+}
+
+/// The same structure, redeclared.
+mod v1_again {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Order,
+        dynamic: true,
+        initial: Pending,
+        states: [Pending, Paid, Shipped],
+        events {
+            pay {
+                transition: { from: Pending, to: Paid }
+            }
+            ship {
+                transition: { from: Paid, to: Shipped }
+            }
+        }
+    }
+}
+
+/// Only a guard differs.
+mod v2 {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Order,
+        dynamic: true,
+        initial: Pending,
+        states: [Pending, Paid, Shipped],
+        events {
+            pay {
+                transition: { from: Pending, to: Paid }
+            }
+            ship {
+                guards: [address_confirmed],
+                transition: { from: Paid, to: Shipped }
+            }
+        }
+    }
+
+    impl<C, S> Order<C, S> {
+        fn address_confirmed(&self, _ctx: &C) -> bool {
+            true
+        }
+    }
+}
+
+#[test]
+fn definitions_compare_and_hash_structurally() {
+    let definition = v1::OrderState::definition();
+    // Without `ship`
+    let mut trimmed = definition.clone();
+    trimmed.events = &definition.events[..1];
+    assert_eq!(*definition, definition.clone());
+    assert_ne!(*definition, trimmed);
+
+    // Usable as a cache key
+    let mut diagrams = HashMap::new();
+    diagrams.insert(definition.clone(), "v1 diagram");
+    assert_eq!(diagrams.get(definition), Some(&"v1 diagram"));
+    assert_eq!(diagrams.get(&trimmed), None);
+    assert_ne!(definition.fingerprint(), trimmed.fingerprint());
+}
+
+#[test]
+fn fingerprints_follow_the_structure_not_the_type() {
+    let v1 = v1::OrderState::definition().fingerprint();
+    assert_eq!(v1, v1_again::OrderState::definition().fingerprint());
+    assert_ne!(v1, v2::OrderState::definition().fingerprint());
+}
+
+#[test]
+fn fingerprints_are_stable() {
+    // Stored fingerprints stay valid across builds; changing how they're
+    // computed invalidates every cache keyed by them
+    assert_eq!(
+        v1::OrderState::definition().fingerprint(),
+        0x7aff_19a9_a1f1_93b8
+    );
+}
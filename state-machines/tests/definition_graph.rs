@@ -0,0 +1,178 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Reactor,
+    dynamic: true,
+    initial: Cold,
+    states: [
+        Cold,
+        superstate Running {
+            state Warming,
+            state Critical,
+        },
+        Venting,
+        Shutdown,
+    ],
+    events {
+        ignite {
+            guards: [fuel_loaded],
+            transition: { from: Cold, to: Running }
+        }
+        overheat {
+            transition: { from: Warming, to: Critical }
+        }
+        vent {
+            transition: { from: Running, to: Venting }
+        }
+        cool {
+            transition: { from: Venting, to: Cold }
+        }
+        scram {
+            payload: u8,
+            transition: { from: [Cold, Running, Venting], to: Shutdown }
+        }
+        dampen {
+            transition: { from: Critical, to: Warming }
+        }
+    }
+}
+
+impl<C, S> Reactor<C, S> {
+    fn fuel_loaded(&self, _ctx: &C) -> bool {
+        true
+    }
+}
+
+#[test]
+fn definition_describes_the_machine() {
+    let definition = ReactorState::definition();
+
+    assert_eq!(definition.name, "Reactor");
+    assert_eq!(definition.initial, ReactorState::Cold);
+    assert_eq!(
+        definition.states,
+        &[
+            ReactorState::Cold,
+            ReactorState::Warming,
+            ReactorState::Critical,
+            ReactorState::Venting,
+            ReactorState::Shutdown
+        ]
+    );
+
+    let running = &definition.superstates[0];
+    assert_eq!(running.name, "Running");
+    assert_eq!(
+        running.descendants,
+        &[ReactorState::Warming, ReactorState::Critical]
+    );
+    assert_eq!(running.initial, ReactorState::Warming);
+
+    let ignite = &definition.events[0];
+    assert_eq!(ignite.guards, &["fuel_loaded"]);
+    assert_eq!(ignite.transitions[0].target, ReactorState::Warming);
+
+    let vent = &definition.events[2];
+    assert_eq!(
+        vent.transitions[0].sources,
+        &[ReactorState::Warming, ReactorState::Critical]
+    );
+
+    let scram = &definition.events[4];
+    assert_eq!(scram.payload, Some("u8"));
+}
+
+#[test]
+fn transitions_from_lists_outgoing_edges() {
+    let definition = ReactorState::definition();
+    let edges: Vec<_> = definition
+        .transitions_from(&ReactorState::Critical)
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            ("vent", &ReactorState::Venting),
+            ("scram", &ReactorState::Shutdown),
+            ("dampen", &ReactorState::Warming)
+        ]
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn shortest_event_sequence_finds_the_happy_path() {
+    let definition = ReactorState::definition();
+
+    assert_eq!(
+        definition.shortest_event_sequence(&ReactorState::Cold, &ReactorState::Venting),
+        Some(vec!["ignite", "vent"])
+    );
+    assert_eq!(
+        definition.shortest_event_sequence(&ReactorState::Critical, &ReactorState::Cold),
+        Some(vec!["vent", "cool"])
+    );
+    assert_eq!(
+        definition.shortest_event_sequence(&ReactorState::Cold, &ReactorState::Cold),
+        Some(vec![])
+    );
+    assert_eq!(
+        definition.shortest_event_sequence(&ReactorState::Shutdown, &ReactorState::Cold),
+        None
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn every_state_can_reach_shutdown() {
+    let definition = ReactorState::definition();
+
+    assert!(
+        definition
+            .states
+            .iter()
+            .all(|state| definition.can_reach(state, &ReactorState::Shutdown))
+    );
+    assert!(!definition.can_reach(&ReactorState::Shutdown, &ReactorState::Cold));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn paths_between_lists_simple_paths() {
+    let definition = ReactorState::definition();
+
+    assert_eq!(
+        definition.paths_between(&ReactorState::Cold, &ReactorState::Venting),
+        [vec!["ignite", "overheat", "vent"], vec!["ignite", "vent"]]
+    );
+    assert_eq!(
+        definition.paths_between(&ReactorState::Shutdown, &ReactorState::Cold),
+        Vec::<Vec<&str>>::new()
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn cycles_lists_elementary_cycles() {
+    let definition = ReactorState::definition();
+
+    assert_eq!(
+        definition.cycles(),
+        [
+            vec![
+                ReactorState::Cold,
+                ReactorState::Warming,
+                ReactorState::Critical,
+                ReactorState::Venting
+            ],
+            vec![
+                ReactorState::Cold,
+                ReactorState::Warming,
+                ReactorState::Venting
+            ],
+            vec![ReactorState::Warming, ReactorState::Critical],
+        ]
+    );
+}
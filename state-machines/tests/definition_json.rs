@@ -0,0 +1,84 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::{DEFINITION_SCHEMA, state_machine};
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    initial: Pending,
+    states: [
+        superstate Open {
+            state Pending,
+            state Paid,
+        },
+        Shipped,
+    ],
+    events {
+        pay {
+            payload: u32,
+            guards: [funds_cleared],
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            after: [notify],
+            transition: { from: Paid, to: Shipped, unless: [on_hold] }
+        }
+    }
+}
+
+impl<C, S> Order<C, S> {
+    fn funds_cleared(&self, _ctx: &C, _amount: &u32) -> bool {
+        true
+    }
+
+    fn on_hold(&self, _ctx: &C) -> bool {
+        false
+    }
+
+    fn notify(&mut self) {}
+}
+
+#[test]
+fn exports_states_hierarchy_and_events() {
+    let definition = OrderState::definition();
+    let json = definition.to_json_value();
+
+    assert_eq!(json["schema"], DEFINITION_SCHEMA);
+    assert_eq!(json["name"], "Order");
+    assert_eq!(json["async"], false);
+    assert_eq!(json["initial"], "Pending");
+    assert_eq!(
+        json["fingerprint"],
+        format!("{:016x}", definition.fingerprint())
+    );
+    assert_eq!(
+        json["states"].to_string(),
+        r#"["Pending","Paid","Shipped"]"#
+    );
+    assert_eq!(
+        json["superstates"].to_string(),
+        r#"[{"initial":"Pending","name":"Open","states":["Pending","Paid"]}]"#
+    );
+
+    let pay = &json["events"][0];
+    assert_eq!(pay["name"], "pay");
+    assert_eq!(pay["payload"], "u32");
+    assert_eq!(pay["guards"][0], "funds_cleared");
+
+    let ship = &json["events"][1];
+    assert!(ship["payload"].is_null());
+    assert_eq!(ship["after"][0], "notify");
+    assert_eq!(
+        ship["transitions"].to_string(),
+        r#"[{"after":[],"around":[],"before":[],"from":["Paid"],"guards":[],"to":"Shipped","unless":["on_hold"]}]"#
+    );
+}
+
+#[test]
+fn to_json_is_the_value_as_a_string() {
+    let definition = OrderState::definition();
+    assert_eq!(definition.to_json(), definition.to_json_value().to_string());
+}
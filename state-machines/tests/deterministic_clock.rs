@@ -0,0 +1,157 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use core::future::pending;
+use core::time::Duration;
+use pollster::block_on;
+use state_machines::clock::TestClock;
+use state_machines::core::{Latency, Rng, Simulation, TransitionProfile};
+use state_machines::{Clock, DynamicError, state_machine};
+use std::cell::Cell;
+
+const SECOND: Duration = Duration::from_secs(1);
+
+thread_local! {
+    static FAILURES_LEFT: Cell<u32> = const { Cell::new(0) };
+}
+
+state_machine! {
+    name: Job,
+    async: true,
+    dynamic: true,
+    watchdog: true,
+    clock: TestClock,
+    callback_timeout: { after: Duration::from_secs(5), timer: TestClock },
+    initial: Queued,
+    states: [Queued, Running { max_duration: Duration::from_secs(30) }, Done],
+    events {
+        start {
+            retry: { attempts: 3, backoff: exponential(1s), timer: TestClock },
+            guards: [worker_free],
+            transition: { from: Queued, to: Running }
+        }
+        finish {
+            guards: [results_uploaded],
+            transition: { from: Running, to: Done }
+        }
+    }
+}
+
+impl<C, S> Job<C, S> {
+    async fn worker_free(&self, _ctx: &C) -> bool {
+        FAILURES_LEFT.with(|left| match left.get() {
+            0 => true,
+            n => {
+                left.set(n - 1);
+                false
+            }
+        })
+    }
+
+    async fn results_uploaded(&self, _ctx: &C) -> bool {
+        pending().await
+    }
+}
+
+#[test]
+fn watchdog_reads_the_test_clock() {
+    TestClock::reset();
+    let mut job = DynamicJob::new(());
+    block_on(job.handle(JobEvent::Start)).unwrap();
+    assert_eq!(job.time_in_state(), Some(Duration::ZERO));
+
+    TestClock::advance(29 * SECOND);
+    assert_eq!(job.stuck_check(Duration::MAX), None);
+
+    TestClock::advance(2 * SECOND);
+    let stuck = job.stuck_check(Duration::MAX).unwrap();
+    assert_eq!(stuck.state, JobState::Running);
+    assert_eq!(stuck.elapsed, 31 * SECOND);
+}
+
+#[test]
+fn retry_backoff_passes_in_virtual_time() {
+    TestClock::reset();
+    FAILURES_LEFT.with(|left| left.set(2));
+    let mut job = DynamicJob::new(());
+    block_on(job.handle(JobEvent::Start)).unwrap();
+
+    assert_eq!(TestClock::sleeps(), [SECOND, 2 * SECOND]);
+    assert_eq!(TestClock::now(), 3 * SECOND);
+    // The machine entered `Running` after the backoff
+    assert_eq!(job.time_in_state(), Some(Duration::ZERO));
+}
+
+#[test]
+fn timeouts_fire_without_waiting() {
+    TestClock::reset();
+    let mut job = DynamicJob::new(());
+    block_on(job.handle(JobEvent::Start)).unwrap();
+
+    assert_eq!(
+        block_on(job.handle(JobEvent::Finish)),
+        Err(DynamicError::Timeout {
+            name: "results_uploaded",
+            event: "finish"
+        })
+    );
+    assert_eq!(job.time_in_state(), Some(5 * SECOND));
+}
+
+state_machine! {
+    name: Batch,
+    dynamic: true,
+    transition_records: true,
+    clock: TestClock,
+    initial: Idle,
+    states: [Idle, Processed],
+    events {
+        process {
+            transition: { from: Idle, to: Processed, action: crunch }
+        }
+    }
+}
+
+impl<C, S> Batch<C, S> {
+    fn crunch(_ctx: &mut C) -> bool {
+        TestClock::advance(Duration::from_millis(250));
+        true
+    }
+}
+
+#[test]
+fn transition_records_are_timed_on_the_test_clock() {
+    TestClock::reset();
+    let mut batch = DynamicBatch::new(());
+    let record = batch.handle(BatchEvent::Process).unwrap();
+    assert_eq!(record.duration, Some(Duration::from_millis(250)));
+}
+
+/// Always draws the low end: the first transition, at its minimum latency.
+struct Lowest;
+
+impl Rng for Lowest {
+    fn next_u64(&mut self) -> u64 {
+        0
+    }
+}
+
+#[test]
+fn simulations_accept_a_scripted_rng() {
+    let report = Simulation::new(BatchState::definition())
+        .profile(
+            "process",
+            TransitionProfile::new(
+                1.0,
+                Latency::Uniform {
+                    min: SECOND,
+                    max: 10 * SECOND,
+                },
+            ),
+        )
+        .run_with(10, &mut Lowest);
+
+    assert_eq!(report.completed(), 10);
+    assert_eq!(report.percentile(1.0), Some(SECOND));
+}
@@ -0,0 +1,92 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped, Cancelled],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            guards: [in_stock],
+            unless: [on_hold],
+            transition: { from: Paid, to: Shipped }
+        }
+        cancel {
+            transition: { from: [Pending, Paid], to: Cancelled }
+        }
+    }
+}
+
+impl<C, S> Order<C, S> {
+    fn in_stock(&self, _ctx: &C) -> bool {
+        true
+    }
+
+    fn on_hold(&self, _ctx: &C) -> bool {
+        false
+    }
+}
+
+#[test]
+fn current_state_gets_a_double_border() {
+    let diagram = OrderState::definition()
+        .diagram()
+        .current(&OrderState::Paid);
+    assert_eq!(
+        diagram.to_string(),
+        "\
+┌───────────┐
+│ Pending   │ initial
+└─┬─────────┘
+  ├─ pay ─────▶ Paid
+  └─ cancel ──▶ Cancelled
+╔═══════════╗
+║ Paid      ║ current
+╚═╤═════════╝
+  ├─ ship [in_stock, !on_hold] ──▶ Shipped
+  └─ cancel ─────────────────────▶ Cancelled
+┌───────────┐
+│ Shipped   │
+└───────────┘
+┌───────────┐
+│ Cancelled │
+└───────────┘
+"
+    );
+}
+
+#[test]
+fn ascii_diagrams_avoid_box_drawing() {
+    let diagram = OrderState::definition()
+        .diagram()
+        .current(&OrderState::Pending)
+        .ascii();
+    let drawn = diagram.to_string();
+    assert!(drawn.is_ascii());
+    assert!(drawn.starts_with(
+        "\
+#===========#
+# Pending   # initial, current
+#=+=========#
+  |- pay -----> Paid
+  `- cancel --> Cancelled
++-----------+
+| Paid      |
++-+---------+
+"
+    ));
+}
+
+#[test]
+fn diagrams_without_a_current_state_highlight_nothing() {
+    let drawn = OrderState::definition().diagram().to_string();
+    assert!(!drawn.contains('║'));
+    assert!(!drawn.contains("current"));
+    assert_eq!(drawn.lines().count(), 16);
+}
@@ -0,0 +1,204 @@
+#![cfg(feature = "alloc")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::{Cell, RefCell};
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use state_machines::core::{AroundOutcome, AroundStage, TransitionError};
+use state_machines::{DryRunOutcome, DynamicError, state_machine};
+
+/// Clones share the log, so it also records what ran on a dry-run clone.
+#[derive(Debug, Clone, Default)]
+pub struct Order {
+    pub stock: u32,
+    pub on_hold: bool,
+    pub log: Rc<RefCell<Vec<&'static str>>>,
+    pub attempts: Rc<Cell<u32>>,
+}
+
+state_machine! {
+    name: Fulfillment,
+    dynamic: true,
+    context: Order,
+    initial: Pending,
+    states: [Pending, Charged, Shipped, Delivered, Cancelled],
+    events {
+        charge {
+            guards: [in_stock],
+            transition: { from: Pending, to: Charged, action: take_payment }
+        }
+        ship {
+            payload: u32,
+            around: [hold_shipping],
+            transition: { from: Charged, to: [Shipped, Delivered], choose: carrier }
+        }
+        cancel {
+            transition: { from: [Pending, Charged], to: Cancelled }
+        }
+    },
+    callbacks: {
+        before_transition [count_attempt]
+        after_transition [send_email]
+    }
+}
+
+impl<S> Fulfillment<S> {
+    fn in_stock(&self, order: &Order) -> bool {
+        order.stock > 0
+    }
+
+    fn take_payment(order: &mut Order) -> bool {
+        order.log.borrow_mut().push("take_payment");
+        true
+    }
+
+    fn carrier(&self, _order: &Order, distance: &u32) -> FulfillmentShipChoice {
+        if *distance == 0 {
+            FulfillmentShipChoice::Delivered
+        } else {
+            FulfillmentShipChoice::Shipped
+        }
+    }
+
+    fn hold_shipping(&self, stage: AroundStage) -> AroundOutcome<Charged> {
+        match stage {
+            AroundStage::Before if self.ctx.on_hold => AroundOutcome::Abort(
+                TransitionError::guard_failed(Charged, "ship", "hold_shipping"),
+            ),
+            _ => AroundOutcome::Proceed,
+        }
+    }
+
+    fn count_attempt(&self) -> ControlFlow<()> {
+        self.ctx.attempts.set(self.ctx.attempts.get() + 1);
+        ControlFlow::Continue(())
+    }
+
+    fn send_email(&mut self) {
+        self.ctx.log.borrow_mut().push("send_email");
+    }
+}
+
+#[test]
+fn simulate_reports_the_transitions_that_would_fire() {
+    let order = DynamicFulfillment::new(Order {
+        stock: 1,
+        ..Order::default()
+    });
+
+    let report = order.simulate(&[FulfillmentEvent::Charge, FulfillmentEvent::Ship(40)]);
+
+    assert!(report.all_accepted());
+    assert_eq!(report.initial_state(), &FulfillmentState::Pending);
+    assert_eq!(report.final_state(), &FulfillmentState::Shipped);
+    let fired: Vec<_> = report.fired().collect();
+    assert_eq!(
+        fired,
+        vec![
+            (
+                &FulfillmentState::Pending,
+                "charge",
+                &FulfillmentState::Charged
+            ),
+            (
+                &FulfillmentState::Charged,
+                "ship",
+                &FulfillmentState::Shipped
+            ),
+        ]
+    );
+}
+
+#[test]
+fn simulate_runs_no_actions_or_callbacks() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut order = DynamicFulfillment::new(Order {
+        stock: 1,
+        log: log.clone(),
+        ..Order::default()
+    });
+
+    order.simulate(&[FulfillmentEvent::Charge, FulfillmentEvent::Ship(40)]);
+
+    assert_eq!(order.current_state(), "Pending");
+    assert!(log.borrow().is_empty());
+
+    order.handle(FulfillmentEvent::Charge).unwrap();
+    assert_eq!(*log.borrow(), vec!["take_payment", "send_email"]);
+}
+
+#[test]
+fn simulate_skips_before_transition_callbacks() {
+    let attempts = Rc::new(Cell::new(0));
+    let mut order = DynamicFulfillment::new(Order {
+        stock: 1,
+        attempts: attempts.clone(),
+        ..Order::default()
+    });
+
+    let report = order.simulate(&[FulfillmentEvent::Charge, FulfillmentEvent::Ship(40)]);
+
+    assert!(report.all_accepted());
+    assert_eq!(attempts.get(), 0);
+
+    order.handle(FulfillmentEvent::Charge).unwrap();
+    assert_eq!(attempts.get(), 1);
+}
+
+#[test]
+fn simulate_evaluates_guards_and_choosers() {
+    let order = DynamicFulfillment::new(Order::default());
+
+    let report = order.simulate(&[FulfillmentEvent::Charge, FulfillmentEvent::Cancel]);
+
+    assert_eq!(
+        report.first_rejection(),
+        Some(("charge", &DynamicError::guard_failed("in_stock", "charge")))
+    );
+    // A rejected event doesn't stop the run
+    assert_eq!(report.final_state(), &FulfillmentState::Cancelled);
+
+    let stocked = DynamicFulfillment::new(Order {
+        stock: 1,
+        ..Order::default()
+    });
+    let report = stocked.simulate(&[FulfillmentEvent::Charge, FulfillmentEvent::Ship(0)]);
+    assert_eq!(report.final_state(), &FulfillmentState::Delivered);
+}
+
+#[test]
+fn simulate_reports_invalid_events() {
+    let order = DynamicFulfillment::new(Order::default());
+
+    let report = order.simulate(&[FulfillmentEvent::Ship(1)]);
+
+    let step = &report.steps()[0];
+    assert_eq!(step.event, "ship");
+    assert_eq!(step.from, FulfillmentState::Pending);
+    assert_eq!(
+        step.outcome,
+        DryRunOutcome::Rejected(DynamicError::invalid_transition("Pending", "ship"))
+    );
+    assert!(!report.all_accepted());
+    assert_eq!(report.final_state(), &FulfillmentState::Pending);
+}
+
+#[test]
+fn simulate_does_not_consult_around_callbacks() {
+    let mut order = DynamicFulfillment::new(Order {
+        stock: 1,
+        on_hold: true,
+        ..Order::default()
+    });
+
+    let report = order.simulate(&[FulfillmentEvent::Charge, FulfillmentEvent::Ship(40)]);
+
+    assert!(report.all_accepted());
+    assert_eq!(report.final_state(), &FulfillmentState::Shipped);
+
+    // Only `handle()` runs the callback that aborts it
+    order.handle(FulfillmentEvent::Charge).unwrap();
+    assert!(order.handle(FulfillmentEvent::Ship(40)).is_err());
+}
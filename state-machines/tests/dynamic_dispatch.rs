@@ -50,6 +50,33 @@ fn test_typestate_to_dynamic_conversion() {
     assert_eq!(dynamic_light.current_state(), "Green");
 }
 
+/// Hands any typed light over to dynamic dispatch, whatever its state.
+fn hand_off<S: TrafficLightStateMarker>(
+    light: TrafficLight<(), S>,
+    log: &mut Vec<&'static str>,
+) -> DynamicTrafficLight<()> {
+    log.push(light.state_name());
+    light.into_dynamic()
+}
+
+#[test]
+fn test_into_dynamic_from_code_generic_over_the_state() {
+    let mut log = Vec::new();
+    let red = TrafficLight::new(());
+    let green = TrafficLight::new(()).next().unwrap();
+    let yellow = TrafficLight::new(()).next().unwrap().next().unwrap();
+
+    let lights = [
+        hand_off(red, &mut log),
+        hand_off(green, &mut log),
+        hand_off(yellow, &mut log),
+    ];
+    assert_eq!(log, ["Red", "Green", "Yellow"]);
+    let states: Vec<_> = lights.iter().map(|light| light.current_state()).collect();
+    assert_eq!(states, log);
+    assert_eq!(<Yellow as TrafficLightStateMarker>::NAME, "Yellow");
+}
+
 #[test]
 fn test_dynamic_to_typestate_conversion() {
     // Start in dynamic mode
@@ -66,6 +93,73 @@ fn test_dynamic_to_typestate_conversion() {
     let _ = typed_light.next();
 }
 
+#[test]
+fn test_into_typed_covers_every_state() {
+    let mut light = DynamicTrafficLight::new(());
+    light.handle(TrafficLightEvent::Next).unwrap();
+    light.handle(TrafficLightEvent::Next).unwrap();
+
+    // Extraction for a state other than the current one hands the wrapper back
+    let light = light.into_red().unwrap_err();
+    let light = light.into_yellow().unwrap().into_dynamic();
+
+    match light.into_typed() {
+        AnyTrafficLightState::Yellow(typed) => {
+            let _red = typed.next().unwrap();
+        }
+        other => panic!("expected Yellow, got {}", other.name()),
+    }
+}
+
+#[test]
+fn test_any_state_collection_dispatch() {
+    let mut lights: Vec<AnyTrafficLightState<()>> = vec![
+        TrafficLight::new(()).into(),
+        TrafficLight::new(()).next().unwrap().into(),
+    ];
+
+    lights = lights
+        .into_iter()
+        .map(|light| light.dispatch(TrafficLightEvent::Next).unwrap())
+        .collect();
+
+    let states: Vec<_> = lights.iter().map(|light| light.state()).collect();
+    assert_eq!(
+        states,
+        [TrafficLightState::Green, TrafficLightState::Yellow]
+    );
+}
+
+#[test]
+fn test_any_state_dispatch_returns_machine_on_error() {
+    let machine: AnyCounterState<()> = Counter::new(()).into();
+
+    let (machine, err) = machine.dispatch(CounterEvent::Stop).unwrap_err();
+    assert_eq!(
+        err,
+        state_machines::DynamicError::invalid_transition("Stopped", "stop")
+    );
+    assert_eq!(machine.state(), CounterState::Stopped);
+}
+
+#[test]
+fn test_state_enum_and_predicates() {
+    let mut light = DynamicTrafficLight::new(());
+    assert_eq!(light.state_enum(), TrafficLightState::Red);
+    assert!(light.is_red());
+    assert!(!light.is_green());
+
+    light.handle(TrafficLightEvent::Next).unwrap();
+    let description = match light.state_enum() {
+        TrafficLightState::Red => "stop",
+        TrafficLightState::Yellow => "slow",
+        TrafficLightState::Green => "go",
+    };
+    assert_eq!(description, "go");
+    assert!(light.is_green());
+    assert_eq!(light.state_enum().name(), light.current_state());
+}
+
 #[test]
 fn test_event_enum() {
     // Event enum variant is PascalCase (Next), but name() returns snake_case (next)
@@ -141,7 +235,7 @@ fn test_guard_failure() {
     assert!(result.is_err());
 
     match result.unwrap_err() {
-        DynamicError::GuardFailed { guard, event } => {
+        DynamicError::GuardFailed { guard, event, .. } => {
             assert_eq!(guard, "is_allowed");
             assert_eq!(event, "proceed"); // Event name is snake_case
         }
@@ -251,7 +345,8 @@ fn test_dynamic_state_data_with_typestate_conversion() {
     // Access data via typestate-specific accessor (guaranteed non-null)
     assert_eq!(typed.running_data().count, 50);
 
-    // Convert back to dynamic
+    // Convert back to dynamic, keeping the state's data
+    assert_eq!(typed.state_name(), "Running");
     let mut dynamic = typed.into_dynamic();
     assert_eq!(dynamic.current_state(), "Running");
     assert_eq!(dynamic.running_data().unwrap().count, 50);
@@ -0,0 +1,84 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Dimmer {
+    pub level: u8,
+}
+
+state_machine! {
+    name: Lamp,
+    dynamic: true,
+    context: Dimmer,
+    initial: Off,
+    states: [Off, On],
+    events {
+        turn_on {
+            alias: [power_up, switch_on],
+            guards: [has_power],
+            transition: { from: Off, to: On }
+        }
+        dim {
+            payload: u8,
+            alias: set_level,
+            after: [apply_level],
+            transition: { from: On, to: On }
+        }
+        turn_off {
+            transition: { from: On, to: Off }
+        }
+    }
+}
+
+impl<S> Lamp<S> {
+    fn has_power(&self, _ctx: &Dimmer) -> bool {
+        true
+    }
+
+    fn apply_level(&mut self, level: &u8) {
+        self.ctx.level = *level;
+    }
+}
+
+#[test]
+fn aliases_fire_the_same_transition() {
+    let lamp = Lamp::new(Dimmer::default()).power_up().unwrap();
+    let lamp = lamp.turn_off().unwrap().switch_on().unwrap();
+    let _off: Lamp<Off> = lamp.turn_off().unwrap();
+}
+
+#[test]
+fn aliases_take_the_event_payload() {
+    let lamp = Lamp::new(Dimmer::default()).turn_on().unwrap();
+    let lamp = lamp.set_level(40).unwrap();
+    assert_eq!(lamp.ctx.level, 40);
+}
+
+state_machine! {
+    name: Fan,
+    initial: Idle,
+    states: [
+        Idle,
+        superstate Running {
+            state Low,
+            state High,
+        },
+    ],
+    events {
+        start {
+            transition: { from: Idle, to: Low }
+        }
+        stop {
+            alias: halt,
+            transition: { from: Running, to: Idle }
+        }
+    }
+}
+
+#[test]
+fn superstate_transitions_get_aliases_too() {
+    let fan = Fan::new(()).start().unwrap();
+    let _idle: Fan<(), Idle> = fan.halt().unwrap();
+}
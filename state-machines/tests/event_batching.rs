@@ -0,0 +1,191 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{AtomicState, BatchError, DynamicError, state_machine};
+
+/// Ledger entries written by the import.
+#[derive(Debug, Default, Clone)]
+pub struct Ledger {
+    pub entries: Vec<u32>,
+}
+
+state_machine! {
+    name: Import,
+    dynamic: true,
+    atomic_state: true,
+    history: 8,
+    context: Ledger,
+    initial: Pending,
+    states: [Pending, Loading, Done],
+    events {
+        begin {
+            transition: { from: Pending, to: Loading }
+        }
+        row {
+            payload: u32,
+            transition: { from: Loading, to: Loading, action: write }
+        }
+        finish {
+            transition: { from: Loading, to: Done }
+        }
+    }
+}
+
+impl<S> Import<S> {
+    fn write(ledger: &mut Ledger, amount: &u32) -> bool {
+        ledger.entries.push(*amount);
+        *amount > 0
+    }
+}
+
+#[test]
+fn handle_all_applies_every_event() {
+    let mut import = DynamicImport::new(Ledger::default());
+    let applied = import.handle_all([
+        ImportEvent::Begin,
+        ImportEvent::Row(10),
+        ImportEvent::Row(20),
+        ImportEvent::Finish,
+    ]);
+
+    assert_eq!(applied, Ok(4));
+    assert_eq!(import.version(), 4);
+    let done = import.into_done().unwrap();
+    assert_eq!(done.ctx.entries, [10, 20]);
+}
+
+#[test]
+fn a_failed_event_rolls_the_whole_batch_back() {
+    let mut import = DynamicImport::new(Ledger::default());
+    import.handle(ImportEvent::Begin).unwrap();
+    import.handle(ImportEvent::Row(5)).unwrap();
+
+    let err = import
+        .handle_all([
+            ImportEvent::Row(10),
+            ImportEvent::Row(0),
+            ImportEvent::Finish,
+        ])
+        .unwrap_err();
+
+    assert_eq!(
+        err,
+        BatchError {
+            index: 1,
+            error: DynamicError::ActionFailed {
+                action: "write",
+                event: "row"
+            },
+        }
+    );
+    assert_eq!(import.version(), 2);
+    assert_eq!(import.history().len(), 2);
+    let loading = import.into_loading().unwrap();
+    assert_eq!(loading.ctx.entries, [5]);
+}
+
+static IMPORT_STATE: AtomicState<ImportState> = AtomicState::from_raw(ImportState::Pending as u8);
+
+#[test]
+fn rollback_keeps_the_shared_state_cell() {
+    let mut import = DynamicImport::new(Ledger::default());
+    import.share_state(&IMPORT_STATE);
+
+    let err = import
+        .handle_all([ImportEvent::Begin, ImportEvent::Begin])
+        .unwrap_err();
+    assert_eq!(err.index, 1);
+    assert_eq!(IMPORT_STATE.load(), ImportState::Pending);
+
+    import.handle(ImportEvent::Begin).unwrap();
+    assert_eq!(IMPORT_STATE.load(), ImportState::Loading);
+}
+
+/// Holding `after_commit` callbacks for a batch takes the `alloc` feature.
+#[cfg(feature = "alloc")]
+mod commits {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Export,
+        dynamic: true,
+        context: Rc<RefCell<Vec<&'static str>>>,
+        initial: Idle,
+        states: [Idle, Open, Sealed],
+        events {
+            open {
+                after_commit: [announce],
+                transition: { from: Idle, to: Open }
+            }
+            seal {
+                after_commit: [announce],
+                transition: { from: Open, to: Sealed }
+            }
+        }
+    }
+
+    impl<S> Export<S> {
+        fn announce(&self) {
+            self.ctx.borrow_mut().push(::core::any::type_name::<S>());
+        }
+    }
+
+    #[test]
+    fn after_commit_waits_for_the_whole_batch() {
+        let log = Rc::default();
+        let mut export = DynamicExport::new(Rc::clone(&log));
+
+        let err = export
+            .handle_all([ExportEvent::Open, ExportEvent::Open])
+            .unwrap_err();
+        assert_eq!(err.index, 1);
+        assert!(log.borrow().is_empty());
+
+        export
+            .handle_all([ExportEvent::Open, ExportEvent::Seal])
+            .unwrap();
+        let log = log.borrow();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].ends_with("Open") && log[1].ends_with("Sealed"));
+    }
+}
+
+mod asynchronous {
+    use pollster::block_on;
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Sync,
+        dynamic: true,
+        async: true,
+        context: Vec<u8>,
+        initial: Idle,
+        states: [Idle, Pulling],
+        events {
+            pull {
+                payload: u8,
+                transition: { from: [Idle, Pulling], to: Pulling, action: fetch }
+            }
+        }
+    }
+
+    impl<S> Sync<S> {
+        async fn fetch(chunks: &mut Vec<u8>, chunk: &u8) -> bool {
+            chunks.push(*chunk);
+            *chunk != 0
+        }
+    }
+
+    #[test]
+    fn async_batches_roll_back_too() {
+        let mut sync = DynamicSync::new(Vec::new());
+        assert_eq!(block_on(sync.handle_all([SyncEvent::Pull(1)])), Ok(1));
+
+        let err = block_on(sync.handle_all([SyncEvent::Pull(2), SyncEvent::Pull(0)])).unwrap_err();
+        assert_eq!(err.index, 1);
+        assert_eq!(sync.into_pulling().unwrap().ctx, [1]);
+    }
+}
@@ -0,0 +1,150 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{Forward, state_machine};
+
+state_machine! {
+    name: Session,
+    dynamic: true,
+    initial: Idle,
+    states: [Idle, Active, Suspended],
+    events {
+        start {
+            transition: { from: Idle, to: Active }
+        }
+        suspend {
+            transition: { from: Active, to: Suspended }
+        }
+    }
+}
+
+/// Records forwarded events next to the connection's own callbacks.
+#[derive(Debug, Default)]
+pub struct Audit {
+    pub log: Vec<String>,
+}
+
+impl Forward<SessionEvent> for Audit {
+    fn forward(&mut self, event: SessionEvent) {
+        self.log.push(format!("forward {}", event.name()));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Link {
+    pub session: DynamicSession<()>,
+    pub audit: Audit,
+}
+
+state_machine! {
+    name: Connection,
+    context: Link,
+    initial: Offline,
+    states: [Offline, Online],
+    events {
+        connect {
+            after: [log_connect],
+            transition: {
+                from: Offline,
+                to: Online,
+                forward: [
+                    { to: audit, as: SessionEvent::Start },
+                    { to: session, as: SessionEvent::Start }
+                ]
+            }
+        }
+        disconnect {
+            transition: {
+                from: Online,
+                to: Offline,
+                forward: { to: session, as: SessionEvent::Suspend }
+            }
+        }
+    }
+}
+
+impl<S> Connection<S> {
+    fn session(&mut self) -> &mut DynamicSession<()> {
+        &mut self.ctx.session
+    }
+
+    fn audit(&mut self) -> &mut Audit {
+        &mut self.ctx.audit
+    }
+
+    fn log_connect(&mut self) {
+        self.ctx.audit.log.push("after log_connect".into());
+    }
+}
+
+#[test]
+fn transitions_forward_events_to_the_sibling_machine() {
+    let connection = Connection::new(Link::default());
+
+    let connection = connection.connect().unwrap();
+    assert_eq!(connection.ctx.session.current_state(), "Active");
+
+    let connection = connection.disconnect().unwrap();
+    assert_eq!(connection.ctx.session.current_state(), "Suspended");
+}
+
+#[test]
+fn forwards_run_after_callbacks_in_declaration_order() {
+    let connection = Connection::new(Link::default()).connect().unwrap();
+
+    assert_eq!(
+        connection.ctx.audit.log,
+        ["after log_connect", "forward start"]
+    );
+}
+
+#[test]
+fn rejected_forwards_leave_the_sibling_in_place() {
+    let mut link = Link::default();
+    link.session.handle(SessionEvent::Start).unwrap();
+    link.session.handle(SessionEvent::Suspend).unwrap();
+
+    // `start` has no transition from `Suspended`; the connection still moves
+    let connection = Connection::new(link).connect().unwrap();
+    assert_eq!(connection.ctx.session.current_state(), "Suspended");
+}
+
+#[test]
+fn closures_receive_forwarded_events() {
+    let mut received = Vec::new();
+    let mut forward = |event: SessionEvent| received.push(event.name());
+
+    Forward::forward(&mut forward, SessionEvent::Suspend);
+    assert_eq!(received, ["suspend"]);
+}
+
+// The struct form spells the `as` key `r#as`
+state_machine!(Machine {
+    name: Heartbeat,
+    context: Link,
+    initial: Waiting,
+    states: [Waiting, Beating],
+    events: [Event {
+        name: beat,
+        transition: Transition {
+            from: Waiting,
+            to: Beating,
+            forward: Forward {
+                to: session,
+                r#as: SessionEvent::Start
+            }
+        }
+    }],
+});
+
+impl<S> Heartbeat<S> {
+    fn session(&mut self) -> &mut DynamicSession<()> {
+        &mut self.ctx.session
+    }
+}
+
+#[test]
+fn struct_form_forwards_too() {
+    let heartbeat = Heartbeat::new(Link::default()).beat().unwrap();
+    assert_eq!(heartbeat.ctx.session.current_state(), "Active");
+}
@@ -0,0 +1,207 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::{ProjectionError, Projector, state_machine};
+use std::cell::Cell;
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    initial: Pending,
+    states: [
+        Pending,
+        superstate Open {
+            ignore: [remind],
+            state Paid,
+            state Packed,
+        },
+        Shipped,
+        Cancelled,
+    ],
+    events {
+        pay {
+            payload: u32,
+            guards: [funds_cleared],
+            after: [charge],
+            transition: { from: Pending, to: Paid }
+        }
+        pack {
+            transition: { from: Paid, to: Packed }
+        }
+        ship {
+            transition: { from: Packed, to: Shipped }
+        }
+        cancel {
+            transition: { from: [Pending, Open], to: Cancelled }
+        }
+        remind {
+            transition: { from: Pending, to: Pending }
+        }
+    }
+}
+
+thread_local! {
+    static CHARGES: Cell<u32> = const { Cell::new(0) };
+}
+
+impl<C, S> Order<C, S> {
+    fn funds_cleared(&self, _ctx: &C, _amount: &u32) -> bool {
+        false
+    }
+
+    fn charge(&mut self, _amount: &u32) {
+        CHARGES.set(CHARGES.get() + 1);
+    }
+}
+
+#[test]
+fn projects_a_stream_without_guards_or_callbacks() {
+    let stream = [
+        OrderEvent::Pay(40),
+        OrderEvent::Remind,
+        OrderEvent::Pack,
+        OrderEvent::Ship,
+    ];
+    assert_eq!(OrderState::project(&stream), Ok(OrderState::Shipped));
+    // The guard would have rejected the payment, and `charge` never ran
+    assert_eq!(CHARGES.get(), 0);
+}
+
+#[test]
+fn projection_is_resumable_from_a_checkpoint() {
+    let checkpoint = OrderState::project(&[OrderEvent::Pay(40)]).unwrap();
+    assert_eq!(checkpoint, OrderState::Paid);
+    assert_eq!(
+        checkpoint.project_from(&[OrderEvent::Cancel]),
+        Ok(OrderState::Cancelled)
+    );
+    assert_eq!(OrderState::initial(), OrderState::Pending);
+}
+
+#[test]
+fn rejects_events_the_machine_would_reject() {
+    assert_eq!(
+        OrderState::Shipped.apply(&OrderEvent::Cancel),
+        Err(ProjectionError::InvalidTransition {
+            from: "Shipped",
+            event: "cancel",
+        })
+    );
+    assert_eq!(
+        OrderState::project(&[OrderEvent::Pack]),
+        Err(ProjectionError::InvalidTransition {
+            from: "Pending",
+            event: "pack",
+        })
+    );
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parcel {
+    pub tracking: u64,
+}
+
+state_machine! {
+    name: Shipment,
+    dynamic: true,
+    data_states: true,
+    no_default_init: true,
+    initial: Booked,
+    states: [Booked, Labelled(Parcel), InTransit(Parcel), Returned(Parcel)],
+    events {
+        label {
+            payload: Parcel,
+            transition: { from: Booked, to: Labelled }
+        }
+        dispatch {
+            transition: { from: Labelled, to: InTransit, init: carry_parcel }
+        }
+        bounce {
+            payload: Parcel,
+            transition: { from: [Labelled, InTransit], to: Returned }
+        }
+    }
+}
+
+impl<C> Shipment<C, Labelled> {
+    fn carry_parcel(&self, _ctx: &C) -> Parcel {
+        self.labelled_data().clone()
+    }
+}
+
+#[test]
+fn projects_state_data_it_can_build() {
+    let label = ShipmentEvent::Label(Parcel { tracking: 7 });
+    let labelled = ShipmentState::project(&[label]).unwrap();
+    assert!(matches!(&labelled, ShipmentState::Labelled(parcel) if parcel.tracking == 7));
+
+    let bounce = ShipmentEvent::Bounce(Parcel { tracking: 8 });
+    let returned = labelled.clone().apply(&bounce).unwrap();
+    assert!(matches!(returned, ShipmentState::Returned(parcel) if parcel.tracking == 8));
+
+    // Only the machine can run `init:`
+    assert!(matches!(
+        labelled.apply(&ShipmentEvent::Dispatch),
+        Err(ProjectionError::Undetermined {
+            from: "Labelled",
+            event: "dispatch",
+        })
+    ));
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    pub percent: u8,
+}
+
+state_machine! {
+    name: Job,
+    dynamic: true,
+    data_states: true,
+    initial: Queued,
+    states: [Queued(Progress), Done],
+    events {
+        finish {
+            transition: { from: Queued, to: Done }
+        }
+        requeue {
+            transition: { from: Done, to: Queued }
+        }
+    }
+}
+
+#[test]
+fn initial_state_data_starts_at_its_default() {
+    assert!(matches!(
+        JobState::initial(),
+        JobState::Queued(Progress { percent: 0 })
+    ));
+    let requeued = JobState::project(&[JobEvent::Finish, JobEvent::Requeue]).unwrap();
+    assert!(matches!(requeued, JobState::Queued(progress) if progress == Progress::default()));
+}
+
+/// A read model of its own, fed the same stream.
+#[derive(Debug, Default, PartialEq)]
+struct Revenue(u32);
+
+impl Projector<OrderEvent> for Revenue {
+    type Error = core::convert::Infallible;
+
+    fn initial() -> Self {
+        Revenue::default()
+    }
+
+    fn apply(self, event: &OrderEvent) -> Result<Self, Self::Error> {
+        Ok(match event {
+            OrderEvent::Pay(amount) => Revenue(self.0 + amount),
+            _ => self,
+        })
+    }
+}
+
+#[test]
+fn read_models_implement_the_trait_too() {
+    let stream = [OrderEvent::Pay(40), OrderEvent::Cancel, OrderEvent::Pay(15)];
+    assert_eq!(Revenue::project(&stream), Ok(Revenue(55)));
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use pollster::block_on;
+use state_machines::source::{Delivery, EventSource, Handled, Pumped, pump, pump_one};
+use state_machines::{DynamicError, state_machine};
+use std::collections::VecDeque;
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    persistent: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            transition: { from: Paid, to: Shipped }
+        }
+    }
+}
+
+/// A queue handing out events with their offsets, recording how each was
+/// settled.
+#[derive(Debug, Default)]
+struct Queue {
+    pending: VecDeque<OrderEvent>,
+    offset: u64,
+    acked: Vec<u64>,
+    nacked: Vec<(u64, DynamicError)>,
+    broken: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct Disconnected;
+
+impl Queue {
+    fn of(events: impl IntoIterator<Item = OrderEvent>) -> Self {
+        Queue {
+            pending: events.into_iter().collect(),
+            ..Queue::default()
+        }
+    }
+}
+
+impl EventSource for Queue {
+    type Event = OrderEvent;
+    type Receipt = u64;
+    type Error = Disconnected;
+
+    async fn next(&mut self) -> Result<Option<Delivery<OrderEvent, u64>>, Disconnected> {
+        if self.broken {
+            return Err(Disconnected);
+        }
+        Ok(self.pending.pop_front().map(|event| {
+            self.offset += 1;
+            Delivery {
+                event,
+                receipt: self.offset,
+            }
+        }))
+    }
+
+    async fn ack(&mut self, receipt: u64) -> Result<(), Disconnected> {
+        self.acked.push(receipt);
+        Ok(())
+    }
+
+    async fn nack(&mut self, receipt: u64, error: DynamicError) -> Result<(), Disconnected> {
+        self.nacked.push((receipt, error));
+        Ok(())
+    }
+}
+
+#[test]
+fn accepted_events_are_acked_and_rejected_ones_nacked() {
+    let mut order = DynamicOrder::new(());
+    let mut queue = Queue::of([OrderEvent::Ship, OrderEvent::Pay, OrderEvent::Ship]);
+
+    let pumped = block_on(pump(&mut order, &mut queue)).unwrap();
+    assert_eq!(
+        pumped,
+        Pumped {
+            acked: 2,
+            nacked: 1
+        }
+    );
+    assert_eq!(order.current_state(), "Shipped");
+    assert_eq!(queue.acked, [2, 3]);
+    assert_eq!(
+        queue.nacked,
+        [(
+            1,
+            DynamicError::InvalidTransition {
+                from: "Pending",
+                event: "ship"
+            }
+        )]
+    );
+}
+
+#[test]
+fn pump_one_reports_each_delivery() {
+    let mut order = DynamicOrder::new(());
+    let mut queue = Queue::of([OrderEvent::Pay, OrderEvent::Pay]);
+
+    assert_eq!(
+        block_on(pump_one(&mut order, &mut queue)),
+        Ok(Some(Handled::Acked(())))
+    );
+    assert!(matches!(
+        block_on(pump_one(&mut order, &mut queue)),
+        Ok(Some(Handled::Nacked(
+            DynamicError::InvalidTransition { .. }
+        )))
+    ));
+    assert_eq!(block_on(pump_one(&mut order, &mut queue)), Ok(None));
+}
+
+#[test]
+fn source_errors_stop_the_pump() {
+    let mut order = DynamicOrder::new(());
+    let mut queue = Queue::of([OrderEvent::Pay]);
+    queue.broken = true;
+
+    assert_eq!(block_on(pump(&mut order, &mut queue)), Err(Disconnected));
+    assert_eq!(order.current_state(), "Pending");
+    assert!(queue.acked.is_empty());
+}
+
+#[test]
+fn pumping_runs_on_spawned_tasks() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let task = runtime.spawn(async {
+        let mut order = DynamicOrder::new(());
+        let mut queue = Queue::of([OrderEvent::Pay, OrderEvent::Ship]);
+        pump(&mut order, &mut queue).await.unwrap()
+    });
+    assert_eq!(runtime.block_on(task).unwrap().acked, 2);
+}
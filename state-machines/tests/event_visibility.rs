@@ -0,0 +1,96 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Lock {
+    pub overrides: u32,
+}
+
+state_machine! {
+    name: Vault,
+    context: Lock,
+    initial: Locked,
+    states: [
+        Locked,
+        superstate Open {
+            state Ajar,
+            state Wide,
+        },
+        Sealed,
+    ],
+    events {
+        open {
+            transition: { from: Locked, to: Ajar }
+        }
+        swing {
+            transition: { from: Ajar, to: Wide }
+        }
+        pub(crate) unsafe force_unlock {
+            doc: "Skip the time lock; only the recovery tool may do this.",
+            alias: [override_lock],
+            after: [count_override],
+            transition: { from: Sealed, to: Ajar }
+        }
+        pub(crate) seal {
+            transition: { from: Locked, to: Sealed }
+        }
+        unsafe slam {
+            transition: { from: Open, to: Locked }
+        }
+    }
+}
+
+impl<S> Vault<S> {
+    fn count_override(&mut self) {
+        self.ctx.overrides += 1;
+    }
+}
+
+#[test]
+fn unsafe_events_are_fired_from_unsafe_blocks() {
+    let sealed = Vault::new(Lock::default()).seal().unwrap();
+    // SAFETY: the test stands in for the recovery tool
+    let ajar = unsafe { sealed.force_unlock() }.unwrap();
+    assert_eq!(ajar.ctx.overrides, 1);
+
+    let sealed = unsafe { ajar.slam() }.unwrap().seal().unwrap();
+    // SAFETY: as above, through the alias
+    let ajar = unsafe { sealed.override_lock() }.unwrap();
+    assert_eq!(ajar.ctx.overrides, 2);
+}
+
+#[test]
+fn superstate_transitions_keep_the_markers() {
+    let wide = Vault::new(Lock::default()).open().unwrap().swing().unwrap();
+    // SAFETY: slamming is fine in a test
+    assert!(unsafe { wide.slam() }.is_ok());
+}
+
+/// The struct form spells the markers as fields.
+mod struct_form {
+    use state_machines::state_machine;
+
+    state_machine!(Machine {
+        name: Hatch,
+        initial: Shut,
+        states: [Shut, Blown],
+        events: [Event {
+            name: blow,
+            visibility: "pub(crate)",
+            r#unsafe: true,
+            transitions: [Transition {
+                from: Shut,
+                to: Blown
+            }],
+        }],
+    });
+
+    #[test]
+    fn fields_become_the_prefix() {
+        // SAFETY: nobody is standing near the hatch
+        assert!(unsafe { Hatch::new(()).blow() }.is_ok());
+    }
+}
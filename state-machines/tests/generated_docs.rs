@@ -0,0 +1,55 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Ticket,
+    dynamic: true,
+    initial: Open,
+    states: [
+        Open { doc: "Reported, waiting for triage." },
+        superstate Active {
+            doc: "Someone is working on the ticket.",
+            state Assigned { doc: "Owned by an engineer." },
+            state Blocked(BlockReason),
+        },
+        Closed,
+    ],
+    events {
+        assign {
+            doc: "Hand the ticket to an engineer.",
+            guards: [has_owner],
+            transition: { from: Open, to: Assigned }
+        }
+        block {
+            transition: { from: Assigned, to: Blocked }
+        }
+        close {
+            doc: "Resolve the ticket.
+                  Closed tickets can't be reopened.",
+            transition: { from: [Open, Active], to: Closed }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BlockReason;
+
+impl<C, S> Ticket<C, S> {
+    fn has_owner(&self, _ctx: &C) -> bool {
+        true
+    }
+}
+
+#[test]
+fn documented_machine_behaves_like_undocumented_one() {
+    let ticket = Ticket::new(());
+    let ticket = ticket.assign().unwrap();
+    let ticket = ticket.block().unwrap();
+    let _closed = ticket.close().unwrap();
+
+    let mut ticket = DynamicTicket::new(());
+    ticket.handle(TicketEvent::Close).unwrap();
+    assert_eq!(ticket.state_enum(), TicketState::Closed);
+}
@@ -0,0 +1,147 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::fmt::Debug;
+
+use state_machines::state_machine;
+
+pub trait Sensor: Debug {
+    type Reading: Copy + Debug + PartialOrd;
+
+    fn read(&self) -> Self::Reading;
+    fn threshold(&self) -> Self::Reading;
+}
+
+#[derive(Debug)]
+pub struct Thermistor {
+    celsius: f32,
+}
+
+impl Sensor for Thermistor {
+    type Reading = f32;
+
+    fn read(&self) -> f32 {
+        self.celsius
+    }
+
+    fn threshold(&self) -> f32 {
+        21.5
+    }
+}
+
+#[derive(Debug)]
+pub struct Adc {
+    raw: u16,
+}
+
+impl Sensor for Adc {
+    type Reading = u16;
+
+    fn read(&self) -> u16 {
+        self.raw
+    }
+
+    fn threshold(&self) -> u16 {
+        2048
+    }
+}
+
+#[derive(Debug)]
+pub struct Board<T: Sensor> {
+    pub sensor: T,
+}
+
+/// Readings collected while heating; the element type depends on the backend.
+#[derive(Debug)]
+pub struct HeatLog<T: Sensor> {
+    pub readings: Vec<T::Reading>,
+}
+
+impl<T: Sensor> Default for HeatLog<T> {
+    fn default() -> Self {
+        Self {
+            readings: Vec::new(),
+        }
+    }
+}
+
+state_machine! {
+    name: Thermostat,
+    dynamic: true,
+    generics: <T: Sensor>,
+    context: Board<T>,
+    initial: Idle,
+    states: [Idle, Heating(HeatLog<T>)],
+    events {
+        heat {
+            guards: [below_threshold],
+            transition: { from: Idle, to: Heating }
+        }
+        rest {
+            transition: { from: Heating, to: Idle }
+        }
+    }
+}
+
+impl<T: Sensor, S> Thermostat<T, S> {
+    fn below_threshold(&self, ctx: &Board<T>) -> bool {
+        ctx.sensor.read() < ctx.sensor.threshold()
+    }
+}
+
+state_machine! {
+    name: Probe,
+    dynamic: true,
+    generics: <T: Sensor>,
+    initial: Parked,
+    states: [Parked, Sampling(HeatLog<T>)],
+    events {
+        deploy {
+            transition: { from: Parked, to: Sampling }
+        }
+    }
+}
+
+#[test]
+fn same_machine_runs_over_different_backends() {
+    let thermistor = Thermostat::new(Board {
+        sensor: Thermistor { celsius: 18.0 },
+    });
+    let mut heating = thermistor.heat().unwrap();
+    heating.heating_data_mut().readings.push(18.0);
+    assert_eq!(heating.heating_data().readings, [18.0]);
+
+    let adc = Thermostat::new(Board {
+        sensor: Adc { raw: 4000 },
+    });
+    let (adc, err) = adc.heat().unwrap_err();
+    assert_eq!(err.guard, "below_threshold");
+    let _idle: Thermostat<Adc, Idle> = adc;
+}
+
+#[test]
+fn dynamic_wrapper_carries_user_parameters() {
+    let mut thermostat: DynamicThermostat<Adc> = DynamicThermostat::new(Board {
+        sensor: Adc { raw: 100 },
+    });
+    thermostat.handle(ThermostatEvent::Heat).unwrap();
+    thermostat.heating_data_mut().unwrap().readings.push(100);
+    assert_eq!(thermostat.heating_data().unwrap().readings, [100]);
+
+    let typed = thermostat.into_heating().unwrap();
+    let idle = typed.rest().unwrap();
+    let mut thermostat = idle.into_dynamic();
+    assert!(thermostat.is_idle());
+    thermostat.handle(ThermostatEvent::Rest).unwrap_err();
+}
+
+#[test]
+fn user_parameters_follow_generic_context() {
+    let probe: Probe<(), Thermistor, Parked> = Probe::new(());
+    let sampling = probe.deploy().unwrap();
+    assert!(sampling.sampling_data().readings.is_empty());
+
+    let mut probe = DynamicProbe::<u8, Adc>::default();
+    probe.handle(ProbeEvent::Deploy).unwrap();
+    assert!(probe.is_sampling());
+}
@@ -0,0 +1,174 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Journal {
+    pub verbose: bool,
+    pub calls: RefCell<Vec<&'static str>>,
+}
+
+state_machine! {
+    name: Kiln,
+    dynamic: true,
+    context: Journal,
+    initial: Cold,
+    states: [Cold, Heating, Firing, Cooling],
+    events {
+        heat {
+            before: [light_burner],
+            after: [log_heat],
+            transition: { from: Cold, to: Heating }
+        }
+        fire {
+            payload: u16,
+            transition: { from: Heating, to: Firing }
+        }
+        cool {
+            transition: { from: [Heating, Firing], to: Cooling }
+        }
+        reset {
+            transition: { from: Cooling, to: Cold }
+        }
+    }
+    callbacks: {
+        before_transition [
+            { name: audit, priority: -10 },
+            { name: debug_logger, priority: 20 },
+            { name: default_logger, priority: 10 },
+            { name: check_temperature, to: Firing },
+        ],
+        after_transition [
+            notify,
+            { name: vent, from: Firing, on: cool },
+        ]
+    }
+}
+
+impl<S> Kiln<S> {
+    fn record(&self, call: &'static str) {
+        self.ctx.calls.borrow_mut().push(call);
+    }
+
+    fn debug_logger(&self) -> ControlFlow<()> {
+        if self.ctx.verbose {
+            self.record("debug_logger");
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn default_logger(&self) -> ControlFlow<()> {
+        self.record("default_logger");
+        ControlFlow::Continue(())
+    }
+
+    fn check_temperature(&self) -> ControlFlow<()> {
+        self.record("check_temperature");
+        ControlFlow::Continue(())
+    }
+
+    fn audit(&self) -> ControlFlow<()> {
+        self.record("audit");
+        ControlFlow::Continue(())
+    }
+
+    fn light_burner(&self) {
+        self.record("light_burner");
+    }
+
+    fn log_heat(&self) {
+        self.record("log_heat");
+    }
+
+    fn notify(&self) {
+        self.record("notify");
+    }
+
+    fn vent(&self) {
+        self.record("vent");
+    }
+}
+
+fn take_calls(kiln: &Kiln<impl Sized>) -> Vec<&'static str> {
+    kiln.ctx.calls.take()
+}
+
+#[test]
+fn global_callbacks_run_by_priority_around_event_callbacks() {
+    let kiln = Kiln::new(Journal::default()).heat().unwrap();
+    assert_eq!(
+        take_calls(&kiln),
+        [
+            "default_logger",
+            "audit",
+            "light_burner",
+            "log_heat",
+            "notify"
+        ]
+    );
+}
+
+#[test]
+fn break_skips_the_rest_of_the_chain_but_not_the_transition() {
+    let journal = Journal {
+        verbose: true,
+        ..Journal::default()
+    };
+    let kiln = Kiln::new(journal).heat().unwrap();
+    assert_eq!(
+        take_calls(&kiln),
+        ["debug_logger", "light_burner", "log_heat", "notify"]
+    );
+}
+
+#[test]
+fn filters_select_global_callbacks_per_edge() {
+    let kiln = Kiln::new(Journal::default()).heat().unwrap();
+    kiln.ctx.calls.take();
+
+    let kiln = kiln.fire(1200).unwrap();
+    assert_eq!(
+        take_calls(&kiln),
+        ["default_logger", "check_temperature", "audit", "notify"]
+    );
+
+    let kiln = kiln.cool().unwrap();
+    assert_eq!(
+        take_calls(&kiln),
+        ["default_logger", "audit", "notify", "vent"]
+    );
+
+    // `vent` only runs when cooling down from `Firing`
+    let kiln = kiln.reset().unwrap().heat().unwrap();
+    kiln.ctx.calls.take();
+    let kiln = kiln.cool().unwrap();
+    assert_eq!(take_calls(&kiln), ["default_logger", "audit", "notify"]);
+}
+
+#[test]
+fn dynamic_dispatch_runs_global_callbacks() {
+    let mut kiln = DynamicKiln::new(Journal::default());
+    kiln.handle(KilnEvent::Heat).unwrap();
+    kiln.handle(KilnEvent::Cool).unwrap();
+
+    let kiln = kiln.into_cooling().unwrap();
+    assert_eq!(
+        take_calls(&kiln),
+        [
+            "default_logger",
+            "audit",
+            "light_burner",
+            "log_heat",
+            "notify",
+            "default_logger",
+            "audit",
+            "notify"
+        ]
+    );
+}
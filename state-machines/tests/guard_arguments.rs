@@ -0,0 +1,85 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Flight {
+    pub altitude: i32,
+    pub role: &'static str,
+}
+
+state_machine! {
+    name: Drone,
+    context: Flight,
+    dynamic: true,
+    initial: Grounded,
+    states: [Grounded, Cruising, Diving, Landed],
+    events {
+        cruise {
+            guards: [min_altitude(100) && has_role("pilot")],
+            transition: { from: Grounded, to: Cruising }
+        }
+        dive {
+            guards: [min_altitude(-50)],
+            unless: [has_role("trainee")],
+            transition: { from: Cruising, to: Diving }
+        }
+        land {
+            payload: i32,
+            guards: [within(10)],
+            transition: { from: [Cruising, Diving], to: Landed }
+        }
+    }
+}
+
+impl<S> Drone<S> {
+    fn min_altitude(&self, flight: &Flight, meters: i32) -> bool {
+        flight.altitude >= meters
+    }
+
+    fn has_role(&self, flight: &Flight, role: &str) -> bool {
+        flight.role == role
+    }
+
+    fn within(&self, flight: &Flight, target: &i32, tolerance: i32) -> bool {
+        (flight.altitude - target).abs() <= tolerance
+    }
+}
+
+fn flight(altitude: i32, role: &'static str) -> Flight {
+    Flight { altitude, role }
+}
+
+#[test]
+fn arguments_follow_the_context() {
+    assert!(Drone::new(flight(150, "pilot")).cruise().is_ok());
+    assert!(Drone::new(flight(50, "pilot")).cruise().is_err());
+    assert!(Drone::new(flight(150, "trainee")).cruise().is_err());
+}
+
+#[test]
+fn one_guard_serves_several_constants() {
+    let drone = Drone::new(flight(150, "pilot")).cruise().unwrap();
+    let (drone, err) = drone.land(100).unwrap_err();
+    assert_eq!(err.guard, "within(10)");
+
+    let _landed = drone.land(145).unwrap();
+}
+
+#[test]
+fn failures_name_the_call() {
+    let (_, err) = Drone::new(flight(0, "pilot")).cruise().unwrap_err();
+    assert_eq!(err.guard, "min_altitude(100) && has_role(\"pilot\")");
+}
+
+#[test]
+fn dynamic_dispatch_passes_the_arguments() {
+    let mut drone = DynamicDrone::new(flight(200, "trainee"));
+    assert!(drone.handle(DroneEvent::Cruise).is_err());
+
+    let mut drone = DynamicDrone::new(flight(200, "pilot"));
+    drone.handle(DroneEvent::Cruise).unwrap();
+    drone.handle(DroneEvent::Dive).unwrap();
+    assert_eq!(drone.current_state(), "Diving");
+}
@@ -0,0 +1,98 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use state_machines::state_machine;
+
+static CACHED_READS: AtomicUsize = AtomicUsize::new(0);
+static UNCACHED_READS: AtomicUsize = AtomicUsize::new(0);
+static VOLTAGE_READS: AtomicUsize = AtomicUsize::new(0);
+
+state_machine! {
+    name: SensorBus,
+    initial: Idle,
+    states: [Idle, Sampling, Reporting],
+    events {
+        sample {
+            // Event-level guard marked for caching...
+            guards: [bus_ready { cache: true }],
+            transition: {
+                from: Idle,
+                to: Sampling,
+                // ...so repeating it here reuses the first result
+                guards: [bus_ready],
+            }
+        }
+        report {
+            guards: [link_ready],
+            transition: { from: Sampling, to: Reporting, guards: [link_ready] }
+        }
+    }
+}
+
+state_machine! {
+    name: PowerRail,
+    dynamic: true,
+    initial: Off,
+    states: [Off, On],
+    events {
+        enable {
+            // The cached read feeds `&&`, `||`, and an `unless` entry
+            guards: [voltage_ok { cache: true } && enabled, voltage_ok || forced],
+            unless: [!voltage_ok && !forced],
+            transition: { from: Off, to: On }
+        }
+    }
+}
+
+impl<C, S> PowerRail<C, S> {
+    fn voltage_ok(&self, _ctx: &C) -> bool {
+        VOLTAGE_READS.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn enabled(&self, _ctx: &C) -> bool {
+        true
+    }
+
+    fn forced(&self, _ctx: &C) -> bool {
+        false
+    }
+}
+
+impl<C, S> SensorBus<C, S> {
+    fn bus_ready(&self, _ctx: &C) -> bool {
+        CACHED_READS.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn link_ready(&self, _ctx: &C) -> bool {
+        UNCACHED_READS.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+}
+
+#[test]
+fn cached_guard_evaluated_once_per_transition() {
+    CACHED_READS.store(0, Ordering::SeqCst);
+    UNCACHED_READS.store(0, Ordering::SeqCst);
+
+    let bus = SensorBus::new(());
+    let bus = bus.sample().expect("bus_ready passes");
+    assert_eq!(CACHED_READS.load(Ordering::SeqCst), 1);
+
+    // Uncached guards keep their existing evaluate-every-time behaviour
+    let _bus = bus.report().expect("link_ready passes");
+    assert_eq!(UNCACHED_READS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn cached_guard_in_compound_and_unless_entries_is_read_once() {
+    let mut rail = DynamicPowerRail::new(());
+    rail.handle(PowerRailEvent::Enable)
+        .expect("voltage_ok passes");
+
+    assert!(rail.is_on());
+    assert_eq!(VOLTAGE_READS.load(Ordering::SeqCst), 1);
+}
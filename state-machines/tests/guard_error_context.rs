@@ -0,0 +1,76 @@
+use state_machines::{
+    core::{GuardError, TransitionErrorKind},
+    state_machine,
+};
+
+state_machine! {
+    name: Hatch,
+    context: bool,
+    initial: Sealed,
+    states: [Sealed, Venting, Open],
+    events {
+        vent {
+            guards: [pressure_equalized],
+            transition: { from: Sealed, to: Venting }
+        }
+        open {
+            transition: { from: Venting, to: Open, action: release_latch }
+        }
+    }
+}
+
+impl<S> Hatch<S> {
+    fn pressure_equalized(&self, equalized: &bool) -> bool {
+        *equalized
+    }
+
+    fn release_latch(_ctx: &mut bool) -> bool {
+        false
+    }
+}
+
+// Generic handling code only sees the error
+fn describe(err: GuardError) -> String {
+    err.with_message("hatch stays shut").to_string()
+}
+
+#[test]
+fn guard_errors_name_the_source_state() {
+    let (_hatch, err) = Hatch::new(false).vent().unwrap_err();
+    assert_eq!(err.from_state, "Sealed");
+    assert_eq!(err.message, None);
+    assert_eq!(
+        describe(err),
+        "guard `pressure_equalized` failed for event `vent` in state `Sealed`: hatch stays shut"
+    );
+}
+
+#[test]
+fn action_errors_name_the_source_state() {
+    let hatch = Hatch::new(true).vent().unwrap();
+    let (_hatch, err) = hatch.open().unwrap_err();
+    assert_eq!(err.from_state, "Venting");
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::ActionFailed {
+            action: "release_latch"
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "action `release_latch` failed for event `open` in state `Venting`"
+    );
+}
+
+#[test]
+fn builder_fills_in_context_on_hand_made_errors() {
+    let err = GuardError::new("door_closed", "depart");
+    assert_eq!(
+        err.to_string(),
+        "guard `door_closed` failed for event `depart`"
+    );
+
+    let err = err.with_from_state("Boarding").with_message("door ajar");
+    assert_eq!(err.from_state, "Boarding");
+    assert_eq!(err.message, Some("door ajar"));
+}
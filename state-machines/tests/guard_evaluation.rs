@@ -0,0 +1,183 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use state_machines::{DynamicError, state_machine};
+
+/// Preflight readings, and a log of the guards that checked them.
+#[derive(Debug, Default)]
+pub struct Preflight {
+    pub checked: Rc<RefCell<Vec<&'static str>>>,
+    pub fuel: bool,
+    pub weather: bool,
+}
+
+impl Preflight {
+    fn check(&self, guard: &'static str, reading: bool) -> bool {
+        self.checked.borrow_mut().push(guard);
+        reading
+    }
+}
+
+state_machine! {
+    name: Launch,
+    context: Preflight,
+    initial: Hold,
+    states: [Hold, Ignition],
+    events {
+        ignite {
+            guards: [fuel_loaded, weather_clear],
+            unless: [range_fouled],
+            transition: { from: Hold, to: Ignition }
+        }
+    }
+}
+
+impl<S> Launch<S> {
+    fn fuel_loaded(&self, ctx: &Preflight) -> bool {
+        ctx.check("fuel_loaded", ctx.fuel)
+    }
+    fn weather_clear(&self, ctx: &Preflight) -> bool {
+        ctx.check("weather_clear", ctx.weather)
+    }
+    fn range_fouled(&self, ctx: &Preflight) -> bool {
+        ctx.check("range_fouled", false)
+    }
+}
+
+mod audited {
+    use super::Preflight;
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Audited,
+        dynamic: true,
+        context: Preflight,
+        evaluate_all_guards: true,
+        initial: Hold,
+        states: [Hold, Ignition],
+        events {
+            ignite {
+                guards: [fuel_loaded, weather_clear],
+                unless: [range_fouled],
+                transition: { from: Hold, to: Ignition }
+            }
+        }
+    }
+
+    impl<S> Audited<S> {
+        fn fuel_loaded(&self, ctx: &Preflight) -> bool {
+            ctx.check("fuel_loaded", ctx.fuel)
+        }
+        fn weather_clear(&self, ctx: &Preflight) -> bool {
+            ctx.check("weather_clear", ctx.weather)
+        }
+        fn range_fouled(&self, ctx: &Preflight) -> bool {
+            ctx.check("range_fouled", false)
+        }
+    }
+}
+
+use audited::{Audited, AuditedEvent, DynamicAudited};
+
+#[test]
+fn guards_stop_at_the_first_failure() {
+    let preflight = Preflight::default();
+    let checked = Rc::clone(&preflight.checked);
+
+    let (_, err) = Launch::new(preflight).ignite().expect_err("no fuel");
+    assert_eq!(err.guard, "fuel_loaded");
+    assert_eq!(*checked.borrow(), ["fuel_loaded"]);
+    assert!(err.failed_guards.is_empty());
+}
+
+#[test]
+fn evaluate_all_guards_runs_every_entry_and_reports_the_first_failure() {
+    let preflight = Preflight::default();
+    let checked = Rc::clone(&preflight.checked);
+
+    let (_, err) = Audited::new(preflight)
+        .ignite()
+        .expect_err("no fuel, bad weather");
+    assert_eq!(err.guard, "fuel_loaded");
+    assert_eq!(
+        *checked.borrow(),
+        ["fuel_loaded", "weather_clear", "range_fouled"]
+    );
+}
+
+#[test]
+fn evaluate_all_guards_applies_to_dynamic_dispatch() {
+    let preflight = Preflight {
+        fuel: true,
+        ..Preflight::default()
+    };
+    let checked = Rc::clone(&preflight.checked);
+    let mut audited = DynamicAudited::new(preflight);
+
+    let err = audited.handle(AuditedEvent::Ignite).unwrap_err();
+    assert!(matches!(
+        err,
+        DynamicError::GuardFailed {
+            guard: "weather_clear",
+            ..
+        }
+    ));
+    assert_eq!(
+        *checked.borrow(),
+        ["fuel_loaded", "weather_clear", "range_fouled"]
+    );
+}
+
+#[test]
+fn evaluate_all_guards_lists_every_failed_entry() {
+    let (_, err) = Audited::new(Preflight::default())
+        .ignite()
+        .expect_err("no fuel, bad weather");
+    assert_eq!(
+        err.failed_guards.iter().collect::<Vec<_>>(),
+        ["fuel_loaded", "weather_clear"]
+    );
+    assert!(!err.failed_guards.contains("range_fouled"));
+    assert_eq!(
+        err.to_string(),
+        "guards `fuel_loaded`, `weather_clear` failed for event `ignite` in state `Hold`"
+    );
+
+    let (_, err) = Audited::new(Preflight {
+        fuel: true,
+        ..Preflight::default()
+    })
+    .ignite()
+    .expect_err("bad weather");
+    assert_eq!(err.failed_guards.len(), 1);
+    assert_eq!(
+        err.to_string(),
+        "guard `weather_clear` failed for event `ignite` in state `Hold`"
+    );
+}
+
+#[test]
+fn dynamic_errors_keep_every_failed_entry() {
+    let mut audited = DynamicAudited::new(Preflight::default());
+
+    let DynamicError::GuardFailed {
+        guard,
+        failed_guards,
+        ..
+    } = audited.handle(AuditedEvent::Ignite).unwrap_err()
+    else {
+        panic!("expected GuardFailed");
+    };
+    assert_eq!(guard, "fuel_loaded");
+    assert_eq!(
+        failed_guards.iter().collect::<Vec<_>>(),
+        ["fuel_loaded", "weather_clear"]
+    );
+    assert_eq!(
+        format!("{failed_guards:?}"),
+        r#"["fuel_loaded", "weather_clear"]"#
+    );
+}
@@ -0,0 +1,98 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use state_machines::state_machine;
+
+static IN_ORBIT: AtomicBool = AtomicBool::new(false);
+static ENGINES_OFF: AtomicBool = AtomicBool::new(false);
+static MANUAL_OVERRIDE: AtomicBool = AtomicBool::new(false);
+static ALARM: AtomicBool = AtomicBool::new(false);
+static TELEMETRY_READS: AtomicUsize = AtomicUsize::new(0);
+
+state_machine! {
+    name: DockingPort,
+    initial: Sealed,
+    states: [Sealed, Extending, Docked],
+    events {
+        extend {
+            guards: [in_orbit && (engines_off || manual_override)],
+            unless: [alarm && !manual_override],
+            transition: { from: Sealed, to: Extending }
+        }
+        capture {
+            // Cached guard referenced twice: one read, and `||` still short-circuits
+            guards: [telemetry_ok { cache: true } || manual_override, telemetry_ok],
+            transition: { from: Extending, to: Docked }
+        }
+    }
+}
+
+impl<C, S> DockingPort<C, S> {
+    fn in_orbit(&self, _ctx: &C) -> bool {
+        IN_ORBIT.load(Ordering::SeqCst)
+    }
+    fn engines_off(&self, _ctx: &C) -> bool {
+        ENGINES_OFF.load(Ordering::SeqCst)
+    }
+    fn manual_override(&self, _ctx: &C) -> bool {
+        MANUAL_OVERRIDE.load(Ordering::SeqCst)
+    }
+    fn alarm(&self, _ctx: &C) -> bool {
+        ALARM.load(Ordering::SeqCst)
+    }
+    fn telemetry_ok(&self, _ctx: &C) -> bool {
+        TELEMETRY_READS.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+}
+
+// Single test so the shared flags aren't raced by parallel test threads
+#[test]
+fn guard_expressions_combine_and_report_source_text() {
+    IN_ORBIT.store(true, Ordering::SeqCst);
+    ENGINES_OFF.store(false, Ordering::SeqCst);
+    MANUAL_OVERRIDE.store(false, Ordering::SeqCst);
+    ALARM.store(false, Ordering::SeqCst);
+
+    let port = DockingPort::new(());
+    let (port, err) = port.extend().expect_err("engines running and no override");
+    assert_eq!(err.guard, "in_orbit && (engines_off || manual_override)");
+
+    // Override satisfies the OR branch but also disarms the unless condition
+    MANUAL_OVERRIDE.store(true, Ordering::SeqCst);
+    ALARM.store(true, Ordering::SeqCst);
+    let port = port.extend().expect("override allows extension");
+
+    TELEMETRY_READS.store(0, Ordering::SeqCst);
+    let _port = port.capture().expect("telemetry ok");
+    assert_eq!(TELEMETRY_READS.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn unless_expression_blocks_when_true() {
+    state_machine! {
+        name: Hatch,
+        initial: Shut,
+        states: [Shut, Open],
+        events {
+            open {
+                unless: [!pressurized || (locked)],
+                transition: { from: Shut, to: Open }
+            }
+        }
+    }
+
+    impl<C, S> Hatch<C, S> {
+        fn pressurized(&self, _ctx: &C) -> bool {
+            true
+        }
+        fn locked(&self, _ctx: &C) -> bool {
+            true
+        }
+    }
+
+    let (_hatch, err) = Hatch::new(()).open().expect_err("locked hatch stays shut");
+    assert_eq!(err.guard, "!pressurized || locked");
+}
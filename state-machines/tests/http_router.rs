@@ -0,0 +1,195 @@
+//! The `axum` router over `http: true` machines.
+#![cfg(feature = "axum")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use state_machines::http::{EventError, HttpMachine, router};
+use state_machines::state_machine;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tracking {
+    pub carrier: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Warehouse {
+    pub carrier: Option<String>,
+}
+
+state_machine! {
+    name: Order,
+    context: Warehouse,
+    http: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            payload: Tracking,
+            alias: dispatch,
+            after: [record_carrier],
+            transition: { from: Paid, to: Shipped }
+        }
+    }
+}
+
+impl<S> Order<S> {
+    fn record_carrier(&mut self, tracking: &Tracking) {
+        self.ctx.carrier = Some(tracking.carrier.clone());
+    }
+}
+
+type Shared = Arc<Mutex<DynamicOrder>>;
+
+/// Send one request to a router over `order` and return its status and JSON body.
+fn call(order: &Shared, method: &str, uri: &str, body: &str) -> (StatusCode, Value) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router(order.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    })
+}
+
+fn order() -> Shared {
+    Arc::new(Mutex::new(DynamicOrder::new(Warehouse::default())))
+}
+
+#[test]
+fn state_reports_the_current_state() {
+    let order = order();
+    assert_eq!(
+        call(&order, "GET", "/state", ""),
+        (StatusCode::OK, json!({ "state": "Pending" }))
+    );
+}
+
+#[test]
+fn posting_an_event_dispatches_it() {
+    let order = order();
+    assert_eq!(
+        call(&order, "POST", "/events/pay", ""),
+        (StatusCode::OK, json!({ "state": "Paid" }))
+    );
+    assert_eq!(
+        call(&order, "POST", "/events/ship", r#"{"carrier": "DHL"}"#),
+        (StatusCode::OK, json!({ "state": "Shipped" }))
+    );
+
+    let shipped = Arc::try_unwrap(order).unwrap().into_inner();
+    let shipped = shipped.into_shipped().unwrap();
+    assert_eq!(shipped.ctx.carrier.as_deref(), Some("DHL"));
+}
+
+#[test]
+fn rejected_events_conflict() {
+    let order = order();
+    let (status, body) = call(&order, "POST", "/events/ship", r#"{"carrier": "DHL"}"#);
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["state"], "Pending");
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap()
+            .contains("InvalidTransition")
+    );
+}
+
+#[test]
+fn bad_requests_never_reach_the_machine() {
+    let order = order();
+    order.try_lock().unwrap().handle(OrderEvent::Pay).unwrap();
+
+    let (status, body) = call(&order, "POST", "/events/refund", "");
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "unknown event `refund`");
+
+    let (status, _) = call(&order, "POST", "/events/ship", "");
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(&order, "POST", "/events/ship", r#"{"courier": 1}"#);
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(&order, "POST", "/events/ship", "{");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    assert_eq!(order.try_lock().unwrap().current_state(), "Paid");
+}
+
+#[test]
+fn definition_describes_the_machine() {
+    let (status, body) = call(&order(), "GET", "/definition", "");
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "Order");
+    assert_eq!(body["initial"], "Pending");
+    assert_eq!(body["states"], json!(["Pending", "Paid", "Shipped"]));
+    assert_eq!(body["events"][1]["name"], "ship");
+    assert_eq!(body["events"][1]["payload"], "Tracking");
+    assert_eq!(
+        body["events"][1]["transitions"],
+        json!([{ "from": ["Paid"], "to": "Shipped", "guards": [], "unless": [] }])
+    );
+}
+
+#[test]
+fn events_decode_by_name() {
+    assert!(matches!(
+        DynamicOrder::event_from_json("ship", Some(json!({ "carrier": "UPS" }))),
+        Ok(OrderEvent::Ship(Tracking { carrier })) if carrier == "UPS"
+    ));
+    assert!(matches!(
+        DynamicOrder::event_from_json("dispatch", Some(json!({ "carrier": "UPS" }))),
+        Ok(OrderEvent::Ship(_))
+    ));
+    assert!(matches!(
+        DynamicOrder::event_from_json("ship", None),
+        Err(EventError::MissingPayload("ship"))
+    ));
+}
+
+state_machine! {
+    name: Upload,
+    async: true,
+    http: true,
+    initial: Queued,
+    states: [Queued, Stored],
+    events {
+        store {
+            guards: [bucket_ready],
+            transition: { from: Queued, to: Stored }
+        }
+    }
+}
+
+impl<C, S> Upload<C, S> {
+    async fn bucket_ready(&self, _ctx: &C) -> bool {
+        true
+    }
+}
+
+#[test]
+fn async_machines_are_served_too() {
+    let upload = Arc::new(Mutex::new(DynamicUpload::<()>::new(())));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let response = runtime.block_on(async {
+        let request = Request::post("/events/store").body(Body::empty()).unwrap();
+        router(upload.clone()).oneshot(request).await.unwrap()
+    });
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(upload.try_lock().unwrap().current_state(), "Stored");
+}
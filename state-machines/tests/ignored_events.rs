@@ -0,0 +1,73 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Uplink,
+    dynamic: true,
+    transition_records: true,
+    initial: Offline,
+    states: [
+        Offline,
+        superstate Online {
+            ignore: [metrics_tick],
+            state Syncing { ignore: [heartbeat] },
+            state Streaming { ignore: [heartbeat] },
+        },
+    ],
+    events {
+        connect {
+            transition: { from: Offline, to: Online }
+        }
+        stream {
+            transition: { from: Syncing, to: Streaming }
+        }
+        disconnect {
+            transition: { from: Online, to: Offline }
+        }
+        heartbeat {
+            payload: u64,
+            transition: { from: Offline, to: Offline }
+        }
+        metrics_tick {
+            transition: { from: Offline, to: Offline }
+        }
+    }
+}
+
+#[test]
+fn ignored_events_are_successful_no_ops() {
+    let mut uplink = DynamicUplink::new(());
+    uplink.handle(UplinkEvent::Connect).unwrap();
+    assert!(uplink.is_syncing());
+
+    let record = uplink.handle(UplinkEvent::Heartbeat(42)).unwrap();
+    assert_eq!(record.from, UplinkState::Syncing);
+    assert_eq!(record.to, UplinkState::Syncing);
+    assert!(uplink.is_syncing());
+}
+
+#[test]
+fn superstate_ignores_apply_to_every_child() {
+    let mut uplink = DynamicUplink::new(());
+    uplink.handle(UplinkEvent::Connect).unwrap();
+    uplink.handle(UplinkEvent::MetricsTick).unwrap();
+    uplink.handle(UplinkEvent::Stream).unwrap();
+    uplink.handle(UplinkEvent::MetricsTick).unwrap();
+    uplink.handle(UplinkEvent::Heartbeat(7)).unwrap();
+    assert!(uplink.is_streaming());
+}
+
+#[test]
+fn events_not_ignored_still_fail() {
+    let mut uplink = DynamicUplink::new(());
+    uplink.handle(UplinkEvent::Connect).unwrap();
+    uplink.handle(UplinkEvent::Stream).unwrap();
+    assert!(uplink.handle(UplinkEvent::Stream).is_err());
+
+    uplink.handle(UplinkEvent::Disconnect).unwrap();
+    uplink.handle(UplinkEvent::Heartbeat(1)).unwrap();
+    assert!(uplink.is_offline());
+    assert!(uplink.handle(UplinkEvent::Stream).is_err());
+}
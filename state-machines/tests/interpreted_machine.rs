@@ -0,0 +1,210 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::{
+    BehaviorRegistry, DefinitionError, InterpretError, InterpretedMachine, MissingBehavior,
+    OwnedDefinition, OwnedTransition, state_machine,
+};
+use std::sync::Arc;
+
+state_machine! {
+    name: Ticket,
+    dynamic: true,
+    initial: Open,
+    states: [Open, Assigned, Closed],
+    events {
+        assign {
+            before: [log],
+            transition: { from: Open, to: Assigned, guards: [has_agent] }
+        }
+        close {
+            after: [log],
+            transition: { from: [Open, Assigned], to: Closed, unless: [disputed] }
+        }
+    }
+}
+
+impl<C, S> Ticket<C, S> {
+    fn has_agent(&self, _ctx: &C) -> bool {
+        true
+    }
+
+    fn disputed(&self, _ctx: &C) -> bool {
+        false
+    }
+
+    fn log(&mut self) {}
+}
+
+#[derive(Debug, Default)]
+struct Desk {
+    agents: u32,
+    disputed: bool,
+    log: Vec<&'static str>,
+}
+
+fn rules() -> Arc<BehaviorRegistry<Desk>> {
+    let mut rules = BehaviorRegistry::new();
+    rules.register_guard("has_agent", |desk: &Desk| desk.agents > 0);
+    rules.register_guard("disputed", |desk: &Desk| desk.disputed);
+    rules.register_guard("escalated", |desk: &Desk| desk.agents > 1);
+    rules.register_callback("log", |desk: &mut Desk| desk.log.push("log"));
+    Arc::new(rules)
+}
+
+#[test]
+fn runs_an_exported_definition() {
+    let definition = OwnedDefinition::from_json(&TicketState::definition().to_json()).unwrap();
+    assert_eq!(definition.name(), "Ticket");
+    assert_eq!(
+        definition.states().collect::<Vec<_>>(),
+        ["Open", "Assigned", "Closed"]
+    );
+
+    let desk = Desk {
+        agents: 1,
+        ..Desk::default()
+    };
+    let mut ticket = InterpretedMachine::new(Arc::new(definition), rules(), desk).unwrap();
+    assert_eq!(ticket.current_state(), "Open");
+
+    ticket.handle("assign").unwrap();
+    assert_eq!(ticket.current_state(), "Assigned");
+    ticket.handle("close").unwrap();
+    assert_eq!(ticket.current_state(), "Closed");
+    // Event-level callbacks run as part of each transition
+    assert_eq!(ticket.ctx().log, ["log", "log"]);
+}
+
+#[test]
+fn rejects_events_like_the_generated_machine() {
+    let definition = OwnedDefinition::from_json(&TicketState::definition().to_json()).unwrap();
+    let mut ticket =
+        InterpretedMachine::new(Arc::new(definition), rules(), Desk::default()).unwrap();
+
+    assert_eq!(
+        ticket.handle("assign"),
+        Err(InterpretError::GuardFailed {
+            guard: "has_agent".into(),
+            event: "assign".into(),
+        })
+    );
+    assert_eq!(
+        ticket.handle("reopen"),
+        Err(InterpretError::UnknownEvent {
+            event: "reopen".into()
+        })
+    );
+
+    ticket.ctx_mut().disputed = true;
+    assert!(matches!(
+        ticket.handle("close"),
+        Err(InterpretError::GuardFailed { guard, .. }) if guard == "disputed"
+    ));
+    ticket.ctx_mut().disputed = false;
+    ticket.handle("close").unwrap();
+    assert_eq!(
+        ticket.handle("close"),
+        Err(InterpretError::InvalidTransition {
+            from: "Closed".into(),
+            event: "close".into(),
+        })
+    );
+    // Rejected events ran no callbacks
+    assert_eq!(ticket.into_ctx().log, ["log"]);
+}
+
+#[test]
+fn runs_an_edited_definition() {
+    // A stricter variant: closing needs an escalation, and only from Assigned
+    let mut document = TicketState::definition().to_json_value();
+    let close = &mut document["events"][1]["transitions"][0];
+    close["from"] = vec!["Assigned"].into();
+    close["guards"] = vec!["escalated"].into();
+    let definition = OwnedDefinition::from_json(&document.to_string()).unwrap();
+
+    let desk = Desk {
+        agents: 1,
+        ..Desk::default()
+    };
+    let mut ticket = InterpretedMachine::new(Arc::new(definition), rules(), desk).unwrap();
+    assert!(matches!(
+        ticket.handle("close"),
+        Err(InterpretError::InvalidTransition { .. })
+    ));
+    ticket.handle("assign").unwrap();
+    assert!(matches!(
+        ticket.handle("close"),
+        Err(InterpretError::GuardFailed { guard, .. }) if guard == "escalated"
+    ));
+    ticket.ctx_mut().agents = 2;
+    ticket.handle("close").unwrap();
+    assert_eq!(ticket.current_state(), "Closed");
+}
+
+#[test]
+fn builds_a_definition_without_json() {
+    let definition = OwnedDefinition::builder("Ticket", "Open")
+        .state("Closed")
+        .transition(
+            "close",
+            OwnedTransition::new(["Open"], "Closed").after("log"),
+        )
+        .build()
+        .unwrap();
+    let mut ticket =
+        InterpretedMachine::new(Arc::new(definition), rules(), Desk::default()).unwrap();
+    ticket.handle("close").unwrap();
+    assert_eq!(ticket.ctx().log, ["log"]);
+
+    let dangling = OwnedDefinition::builder("Ticket", "Open")
+        .transition("close", OwnedTransition::new(["Open"], "Closed"))
+        .build();
+    assert_eq!(
+        dangling,
+        Err(DefinitionError::UnknownState {
+            state: "Closed".into()
+        })
+    );
+}
+
+#[test]
+fn refuses_a_behavior_missing_names_the_definition_uses() {
+    let definition =
+        Arc::new(OwnedDefinition::from_json(&TicketState::definition().to_json()).unwrap());
+    let mut partial = BehaviorRegistry::new();
+    partial.register_guard("has_agent", |desk: &Desk| desk.agents > 0);
+    let missing = definition.missing(&partial);
+    assert_eq!(
+        missing,
+        MissingBehavior {
+            guards: vec!["disputed".into()],
+            callbacks: vec!["log".into()],
+        }
+    );
+
+    // Without `disputed`, `unless: [disputed]` would otherwise let `close` through
+    let refused = InterpretedMachine::new(definition, Arc::new(partial), Desk::default());
+    assert_eq!(refused.err(), Some(missing));
+}
+
+#[test]
+fn rejects_unreadable_documents() {
+    let mut document = TicketState::definition().to_json_value();
+    document["schema"] = 99.into();
+    assert_eq!(
+        OwnedDefinition::from_json(&document.to_string()),
+        Err(DefinitionError::UnsupportedSchema { found: 99 })
+    );
+
+    assert!(matches!(
+        OwnedDefinition::from_json(r#"{"schema": 1, "name": "Ticket"}"#),
+        Err(DefinitionError::InvalidJson { .. })
+    ));
+    assert!(matches!(
+        OwnedDefinition::from_json("not json"),
+        Err(DefinitionError::InvalidJson { .. })
+    ));
+}
@@ -0,0 +1,89 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{assert_machine, state_machine};
+
+state_machine! {
+    name: Door,
+    dynamic: true,
+    initial: Closed,
+    states: [Closed, Open, Locked, Scrapped],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+        close {
+            transition: { from: Open, to: Closed }
+        }
+        lock {
+            transition: { from: Closed, to: Locked }
+        }
+        unlock {
+            transition: { from: Locked, to: Closed }
+        }
+        scrap {
+            transition: { from: [Closed, Open, Locked], to: Scrapped }
+        }
+    }
+}
+
+assert_machine!(Door:
+    can_reach(Closed => Open),
+    can_reach(Locked => Open),
+    can_reach_from_all(Scrapped),
+    has_transition(Open, close),
+    terminal(Scrapped),
+    no_orphan_states,
+    deterministic,
+);
+
+mod hallway {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Light,
+        dynamic: true,
+        context: u8,
+        initial: Off,
+        states: [Off, On, Dimmed],
+        events {
+            toggle {
+                transition: { from: Off, to: [On, Dimmed], choose: brightness }
+            }
+        }
+    }
+
+    impl<S> Light<S> {
+        fn brightness(&self, level: &u8) -> LightToggleChoice {
+            if *level < 50 {
+                LightToggleChoice::Dimmed
+            } else {
+                LightToggleChoice::On
+            }
+        }
+    }
+}
+
+// Machines in other modules are named by path
+assert_machine!(hallway::Light: can_reach(Off => Dimmed), terminal(On));
+
+#[test]
+fn the_checks_match_the_table() {
+    let table = hallway::LightState::TABLE;
+    assert!(table.all_states_reachable());
+    // `choose` picks one of two targets at runtime
+    assert!(!table.is_deterministic());
+    assert!(DoorState::TABLE.is_deterministic());
+}
+
+#[test]
+fn checked_machines_run_as_declared() {
+    let mut door = DynamicDoor::new(());
+    door.handle(DoorEvent::Lock).unwrap();
+    door.handle(DoorEvent::Scrap).unwrap();
+    assert_eq!(door.state_enum(), DoorState::Scrapped);
+
+    let mut light = hallway::DynamicLight::new(20);
+    light.handle(hallway::LightEvent::Toggle).unwrap();
+    assert_eq!(light.state_enum(), hallway::LightState::Dimmed);
+}
@@ -0,0 +1,115 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cart {
+    pub items: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Receipt {
+    pub total: u32,
+}
+
+state_machine! {
+    name: Checkout,
+    dynamic: true,
+    history: 4,
+    context: Cart,
+    initial: Browsing,
+    states: [Browsing, Paying, Paid(Receipt)],
+    events {
+        pay {
+            guards: [has_items],
+            transition: { from: Browsing, to: Paying }
+        }
+        confirm {
+            transition: { from: Paying, to: Paid }
+        }
+        cancel {
+            transition: { from: Paying, to: Browsing }
+        }
+    }
+}
+
+impl<S> Checkout<S> {
+    fn has_items(&self, cart: &Cart) -> bool {
+        cart.items > 0
+    }
+}
+
+#[test]
+fn typestate_machines_clone_with_their_context_and_data() {
+    let paying = Checkout::new(Cart { items: 2 }).pay().unwrap();
+    let mut paid = paying.clone().confirm().unwrap();
+    paid.paid_data_mut().total = 30;
+
+    let again = paid.clone();
+    assert_eq!(again.paid_data(), &Receipt { total: 30 });
+    assert_eq!(again.ctx.items, 2);
+
+    // The original is still usable
+    let _browsing = paying.cancel().unwrap();
+}
+
+#[test]
+fn dynamic_clones_run_what_if_sequences() {
+    let mut checkout = DynamicCheckout::new(Cart { items: 1 });
+    checkout.handle(CheckoutEvent::Pay).unwrap();
+
+    let mut what_if = checkout.clone();
+    what_if.handle(CheckoutEvent::Confirm).unwrap();
+    assert!(what_if.is_paid());
+    assert_eq!(what_if.history().len(), 2);
+
+    assert!(checkout.is_paying());
+    assert_eq!(checkout.version(), 1);
+    assert_eq!(checkout.history().len(), 1);
+}
+
+#[test]
+fn dynamic_machines_compare_state_and_data() {
+    let mut first = DynamicCheckout::new(Cart { items: 1 });
+    let mut second = DynamicCheckout::new(Cart { items: 5 });
+    assert_eq!(first, second);
+
+    first.handle(CheckoutEvent::Pay).unwrap();
+    assert_ne!(first, second);
+
+    // Same state, reached by a different route: still equal
+    first.handle(CheckoutEvent::Cancel).unwrap();
+    assert_eq!(first, second);
+
+    for machine in [&mut first, &mut second] {
+        machine.handle(CheckoutEvent::Pay).unwrap();
+        machine.handle(CheckoutEvent::Confirm).unwrap();
+    }
+    assert_eq!(first, second);
+    first.set_paid_data(Receipt { total: 9 }).unwrap();
+    assert_ne!(first, second);
+}
+
+#[derive(Debug, Default)]
+pub struct Port;
+
+state_machine! {
+    name: Uart,
+    dynamic: true,
+    context: Port,
+    initial: Idle,
+    states: [Idle, Busy],
+    events {
+        send {
+            transition: { from: Idle, to: Busy }
+        }
+    }
+}
+
+#[test]
+fn machines_without_a_cloneable_context_still_compile() {
+    let uart = DynamicUart::new(Port);
+    assert!(uart.is_idle());
+    assert!(uart == DynamicUart::new(Port));
+}
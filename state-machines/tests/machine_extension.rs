@@ -0,0 +1,194 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::state_machine;
+
+/// Events the devices logged, shared by every variant.
+#[derive(Debug, Default)]
+pub struct Log {
+    pub entries: Vec<&'static str>,
+    pub charged: bool,
+}
+
+state_machine! {
+    name: Device,
+    extendable: true,
+    dynamic: true,
+    context: Log,
+    initial: Off,
+    states: [Off, Booting, Ready, Failed],
+    events {
+        power_on {
+            after: [note_power],
+            transition: { from: Off, to: Booting }
+        }
+        booted {
+            transition: { from: Booting, to: Ready }
+        }
+        fail {
+            transition: { from: [Booting, Ready], to: Failed }
+        }
+        reset {
+            transition: { from: Failed, to: Off }
+        }
+    }
+    callbacks: {
+        after_transition [{ name: trace }]
+    }
+}
+
+impl<S> Device<S> {
+    fn note_power(&mut self) {
+        self.ctx.entries.push("power");
+    }
+
+    fn trace(&mut self) {
+        self.ctx.entries.push("trace");
+    }
+}
+
+#[test]
+fn base_machine_is_unchanged() {
+    let device = Device::new(Log::default()).power_on().unwrap();
+    let ready = device.booted().unwrap();
+    assert_eq!(ready.ctx.entries, ["power", "trace", "trace"]);
+}
+
+/// Each variant lives in its own module: machines in one module would
+/// declare the same state types.
+mod sensor {
+    use super::Log;
+    use state_machines::state_machine;
+
+    // Adds a state, a guard on an inherited event, and replaces one source of
+    // `fail`
+    state_machine! {
+        name: Sensor,
+        extends: super::Device,
+        states: [Calibrating],
+        events {
+            power_on {
+                guards: [charged],
+            }
+            booted {
+                transition: { from: Booting, to: Calibrating }
+            }
+            calibrated {
+                transition: { from: Calibrating, to: Ready }
+            }
+            fail {
+                transition: { from: Ready, to: Off }
+            }
+        }
+    }
+
+    impl<S> Sensor<S> {
+        fn note_power(&mut self) {
+            self.ctx.entries.push("power");
+        }
+
+        fn trace(&mut self) {
+            self.ctx.entries.push("trace");
+        }
+
+        fn charged(&self, log: &Log) -> bool {
+            log.charged
+        }
+    }
+
+    #[test]
+    fn child_inherits_states_events_and_callbacks() {
+        let log = Log {
+            charged: true,
+            ..Log::default()
+        };
+        let sensor = Sensor::new(log).power_on().unwrap();
+        let ready = sensor.booted().unwrap().calibrated().unwrap();
+        assert_eq!(ready.ctx.entries, ["power", "trace", "trace", "trace"]);
+
+        let (_off, err) = Sensor::new(Log::default()).power_on().unwrap_err();
+        assert_eq!(err.guard, "charged");
+    }
+
+    #[test]
+    fn child_transitions_replace_the_base_ones_from_the_same_sources() {
+        let log = Log {
+            charged: true,
+            ..Log::default()
+        };
+        let mut sensor = DynamicSensor::new(log);
+        sensor.handle(SensorEvent::PowerOn).unwrap();
+        // `fail` from `Booting` is still the base's
+        assert!(sensor.can_handle(&SensorEvent::Fail));
+        sensor.handle(SensorEvent::Booted).unwrap();
+        assert_eq!(sensor.current_state(), "Calibrating");
+
+        sensor.handle(SensorEvent::Calibrated).unwrap();
+        sensor.handle(SensorEvent::Fail).unwrap();
+        assert_eq!(sensor.current_state(), "Off");
+
+        let states: Vec<&str> = SensorState::definition()
+            .states
+            .iter()
+            .map(|state| state.name())
+            .collect();
+        assert_eq!(states, ["Off", "Booting", "Ready", "Failed", "Calibrating"]);
+    }
+}
+
+mod variants {
+    use super::Log;
+    use state_machines::state_machine;
+
+    // An extendable child, extended in turn by a machine in its own module
+    state_machine! {
+        name: Gateway,
+        extends: super::Device,
+        extendable: true,
+        states: [Updating],
+        events {
+            update {
+                transition: { from: Ready, to: Updating }
+            }
+        }
+    }
+
+    state_machine!(Machine {
+        name: Router,
+        extends: Gateway,
+        module: router,
+        dynamic: false,
+        events: [Event {
+            name: updated,
+            transition: Transition {
+                from: Updating,
+                to: Ready
+            },
+        }],
+    });
+
+    impl<S> Gateway<S> {
+        fn note_power(&mut self) {}
+        fn trace(&mut self) {}
+    }
+
+    impl<S> router::Router<S> {
+        fn note_power(&mut self) {}
+        fn trace(&mut self) {
+            self.ctx.entries.push("trace");
+        }
+    }
+
+    #[test]
+    fn extensions_chain_and_accept_the_struct_form() {
+        let ready = router::Router::new(Log::default())
+            .power_on()
+            .unwrap()
+            .booted()
+            .unwrap();
+        let ready = ready.update().unwrap().updated().unwrap();
+        assert_eq!(ready.ctx.entries.len(), 4);
+        assert!(GatewayState::definition().states.len() == 5);
+    }
+}
@@ -0,0 +1,165 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::fragment;
+
+// Recovery shared by every job: `give_up` returns to the including machine's
+// `Idle`
+fragment! {
+    name: Retryable,
+    states: [Failed, Retrying],
+    events {
+        retry {
+            transition: { from: Failed, to: Retrying }
+        }
+        give_up {
+            transition: { from: [Failed, Retrying], to: Idle }
+        }
+    }
+    callbacks: {
+        after_transition [{ name: count_retry, on: [retry] }]
+    }
+}
+
+/// Each machine lives in its own module: machines in one module would
+/// declare the same state types.
+mod upload {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Upload,
+        dynamic: true,
+        context: u32,
+        initial: Idle,
+        states: [Idle, Sending, Done],
+        include: [super::Retryable],
+        events {
+            send {
+                transition: { from: [Idle, Retrying], to: Sending }
+            }
+            fail {
+                transition: { from: Sending, to: Failed }
+            }
+            finish {
+                transition: { from: Sending, to: Done }
+            }
+            // Adds a guard to the fragment's event
+            retry {
+                guards: [retries_left],
+            }
+        }
+    }
+
+    impl<S> Upload<S> {
+        fn count_retry(&mut self) {
+            self.ctx += 1;
+        }
+
+        fn retries_left(&self, retries: &u32) -> bool {
+            *retries < 2
+        }
+    }
+
+    #[test]
+    fn fragment_states_and_events_are_merged_in() {
+        let failed = Upload::new(0).send().unwrap().fail().unwrap();
+        let sending = failed.retry().unwrap().send().unwrap();
+        let failed = sending
+            .fail()
+            .unwrap()
+            .retry()
+            .unwrap()
+            .send()
+            .unwrap()
+            .fail()
+            .unwrap();
+        assert_eq!(failed.ctx, 2);
+
+        let (failed, err) = failed.retry().unwrap_err();
+        assert_eq!(err.guard, "retries_left");
+        let _idle: Upload<Idle> = failed.give_up().unwrap();
+
+        let states: Vec<&str> = UploadState::definition()
+            .states
+            .iter()
+            .map(|state| state.name())
+            .collect();
+        assert_eq!(states, ["Idle", "Sending", "Done", "Failed", "Retrying"]);
+    }
+}
+
+mod mirror {
+    use state_machines::state_machine;
+
+    // The same fragment twice, once per phase
+    state_machine! {
+        name: Mirror,
+        dynamic: true,
+        context: u32,
+        initial: Idle,
+        states: [Idle, Pushing, Verifying, Synced],
+        include: [
+            super::Retryable { prefix: Push },
+            super::Retryable { prefix: Verify },
+        ],
+        events {
+            push {
+                transition: { from: [Idle, PushRetrying], to: Pushing }
+            }
+            pushed {
+                transition: { from: Pushing, to: Verifying }
+            }
+            verify {
+                transition: { from: VerifyRetrying, to: Verifying }
+            }
+            verified {
+                transition: { from: Verifying, to: Synced }
+            }
+            fail {
+                transition: { from: Pushing, to: PushFailed },
+                transition: { from: Verifying, to: VerifyFailed }
+            }
+        }
+    }
+
+    impl<S> Mirror<S> {
+        fn count_retry(&mut self) {
+            self.ctx += 1;
+        }
+    }
+
+    #[test]
+    fn prefixes_rename_each_inclusion() {
+        let mut mirror = DynamicMirror::new(0);
+        mirror.handle(MirrorEvent::Push).unwrap();
+        mirror.handle(MirrorEvent::Fail).unwrap();
+        assert_eq!(mirror.current_state(), "PushFailed");
+        mirror.handle(MirrorEvent::PushRetry).unwrap();
+        mirror.handle(MirrorEvent::Push).unwrap();
+        mirror.handle(MirrorEvent::Pushed).unwrap();
+        mirror.handle(MirrorEvent::Fail).unwrap();
+        assert_eq!(mirror.current_state(), "VerifyFailed");
+        mirror.handle(MirrorEvent::VerifyGiveUp).unwrap();
+        assert_eq!(mirror.current_state(), "Idle");
+
+        let states: Vec<&str> = MirrorState::definition()
+            .states
+            .iter()
+            .map(|state| state.name())
+            .collect();
+        assert_eq!(
+            states,
+            [
+                "Idle",
+                "Pushing",
+                "Verifying",
+                "Synced",
+                "PushFailed",
+                "PushRetrying",
+                "VerifyFailed",
+                "VerifyRetrying"
+            ]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{DynamicMachine, MachineId, Sink, TransitionNotification, state_machine};
+
+#[derive(Debug, Default)]
+pub struct Desk {
+    pub sent: Vec<TransitionNotification<TicketNotification>>,
+}
+
+impl Sink<TicketNotification> for Desk {
+    fn send(&mut self, notification: TransitionNotification<TicketNotification>) {
+        self.sent.push(notification);
+    }
+}
+
+state_machine! {
+    name: Ticket,
+    context: Desk,
+    dynamic: true,
+    persistent: true,
+    identity: true,
+    transition_records: true,
+    sink: desk,
+    initial: Open,
+    states: [Open, Assigned, Closed],
+    events {
+        assign {
+            transition: { from: Open, to: Assigned, emit: TicketAssigned }
+        }
+        close {
+            transition: { from: [Open, Assigned], to: Closed }
+        }
+    }
+}
+
+impl<S> Ticket<S> {
+    fn desk(&mut self) -> &mut Desk {
+        &mut self.ctx
+    }
+}
+
+#[test]
+fn machines_have_no_id_by_default() {
+    let ticket = Ticket::new(Desk::default());
+    assert_eq!(ticket.id(), None);
+
+    let ticket = DynamicTicket::new(Desk::default());
+    assert_eq!(ticket.id(), None);
+}
+
+#[test]
+fn typestate_id_carries_through_transitions() {
+    let ticket = Ticket::new(Desk::default()).with_id(7);
+    let ticket = ticket.assign().unwrap();
+    assert_eq!(ticket.id(), Some(MachineId(7)));
+
+    let ticket = ticket.close().unwrap();
+    assert_eq!(ticket.id(), Some(MachineId(7)));
+    assert_eq!(ticket.ctx.sent.len(), 1);
+    assert_eq!(ticket.ctx.sent[0].id, Some(MachineId(7)));
+}
+
+#[test]
+fn dynamic_id_is_in_records_and_notifications() {
+    let mut ticket = DynamicTicket::new(Desk::default()).with_id(42);
+
+    let record = ticket.handle(TicketEvent::Assign).unwrap();
+    assert_eq!(record.id, Some(MachineId(42)));
+    assert_eq!(ticket.id(), Some(MachineId(42)));
+    assert_eq!(DynamicMachine::id(&ticket), Some(MachineId(42)));
+
+    let ticket = ticket.into_assigned().unwrap();
+    assert_eq!(ticket.ctx.sent[0].id, Some(MachineId(42)));
+    assert_eq!(ticket.id(), Some(MachineId(42)));
+}
+
+#[test]
+fn restoring_a_state_keeps_the_id() {
+    let mut ticket = DynamicTicket::new(Desk::default()).with_id(3);
+    ticket.restore_state(TicketState::Closed);
+
+    assert_eq!(ticket.current_state(), "Closed");
+    assert_eq!(ticket.id(), Some(MachineId(3)));
+}
+
+#[test]
+fn ids_display_after_a_hash() {
+    assert_eq!(MachineId::from(42).to_string(), "#42");
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn trace_entries_name_the_machine_instance() {
+    use state_machines::trace::TraceRecorder;
+
+    let recorder = TraceRecorder::start();
+    let _ticket = Ticket::new(Desk::default()).with_id(9).close().unwrap();
+    let trace = recorder.finish();
+
+    assert_eq!(trace.entries()[0].id, Some(MachineId(9)));
+    assert_eq!(trace.to_string(), "Ticket#9.close: Open -> Closed\n");
+}
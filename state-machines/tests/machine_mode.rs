@@ -0,0 +1,111 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{
+    core::{AroundOutcome, AroundStage},
+    state_machine,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static WRAPPED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Progress {
+    pub percent: u8,
+}
+
+state_machine! {
+    name: Upload,
+    mode: runtime,
+    initial: Idle,
+    states: [
+        Idle,
+        superstate Active {
+            state Sending(Progress),
+            state Paused,
+        },
+        Done,
+    ],
+    events {
+        start {
+            around: [wrap],
+            transition: { from: Idle, to: Sending }
+        }
+        pause {
+            transition: { from: Sending, to: Paused }
+        }
+        finish {
+            transition: { from: Active, to: Done }
+        }
+    }
+}
+
+impl<C, S> Upload<C, S> {
+    fn wrap(&self, stage: AroundStage) -> AroundOutcome<Idle> {
+        if stage == AroundStage::Before {
+            WRAPPED.fetch_add(1, Ordering::SeqCst);
+        }
+        AroundOutcome::Proceed
+    }
+}
+
+#[test]
+fn runtime_mode_generates_the_dynamic_machine() {
+    let mut upload = DynamicUpload::new(());
+    upload.handle(UploadEvent::Start).unwrap();
+    assert_eq!(WRAPPED.load(Ordering::SeqCst), 1);
+
+    // State data and superstates work through the runtime machine
+    upload.sending_data_mut().unwrap().percent = 60;
+    assert_eq!(upload.sending_data(), Some(&Progress { percent: 60 }));
+    assert!(upload.is_active());
+
+    upload.handle(UploadEvent::Pause).unwrap();
+    assert!(upload.is_paused() && upload.is_active());
+    upload.handle(UploadEvent::Finish).unwrap();
+    assert!(upload.is_done());
+}
+
+state_machine! {
+    name: Gauge,
+    mode: typestate,
+    initial: Low,
+    states: [Low, High],
+    events {
+        rise {
+            transition: { from: Low, to: High }
+        }
+    }
+}
+
+// Would conflict with a generated wrapper, which `mode: typestate` leaves
+// out even when the `dynamic` feature is enabled
+#[allow(dead_code)]
+struct DynamicGauge;
+
+#[test]
+fn typestate_mode_generates_only_the_typestate_machine() {
+    let gauge = Gauge::new(());
+    let _gauge: Gauge<(), High> = gauge.rise().unwrap();
+}
+
+state_machine! {
+    name: Switch,
+    mode: both,
+    initial: Off,
+    states: [Off, On],
+    events {
+        flip {
+            transition: { from: Off, to: On }
+            transition: { from: On, to: Off }
+        }
+    }
+}
+
+#[test]
+fn both_mode_generates_both_machines() {
+    let switch = Switch::new(()).flip().unwrap();
+    let mut switch = switch.into_dynamic();
+    switch.handle(SwitchEvent::Flip).unwrap();
+    assert!(switch.is_off());
+}
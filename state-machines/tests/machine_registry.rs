@@ -0,0 +1,177 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::registry::{MachineRegistry, MachineStatus};
+use state_machines::state_machine;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::thread;
+
+state_machine! {
+    name: Session,
+    dynamic: true,
+    persistent: true,
+    initial: Active,
+    states: [Active, Idle, Closed],
+    events {
+        pause {
+            transition: { from: Active, to: Idle }
+        }
+        close {
+            transition: { from: [Active, Idle], to: Closed }
+        }
+    }
+}
+
+state_machine! {
+    name: Upload,
+    dynamic: true,
+    persistent: true,
+    initial: Queued,
+    states: [Queued, Stored],
+    events {
+        store {
+            transition: { from: Queued, to: Stored }
+        }
+        scan {
+            before: [scan_or_panic],
+            transition: { from: Queued, to: Queued }
+        }
+    }
+}
+
+impl<C, S> Upload<C, S> {
+    fn scan_or_panic(&self) {
+        panic!("scanner offline");
+    }
+}
+
+#[test]
+fn snapshot_lists_every_machine_with_its_state() {
+    let registry = MachineRegistry::new();
+    let a = registry.register("session:a", DynamicSession::new(()));
+    let _b = registry.register("session:b", DynamicSession::new(()));
+    let upload = registry.register("upload:1", DynamicUpload::new(()));
+    a.lock().unwrap().handle(SessionEvent::Pause).unwrap();
+    upload.lock().unwrap().handle(UploadEvent::Store).unwrap();
+
+    assert_eq!(
+        registry.snapshot(),
+        vec![
+            MachineStatus {
+                name: "session:a".into(),
+                machine: "Session",
+                state: Some("Idle"),
+                id: None,
+            },
+            MachineStatus {
+                name: "session:b".into(),
+                machine: "Session",
+                state: Some("Active"),
+                id: None,
+            },
+            MachineStatus {
+                name: "upload:1".into(),
+                machine: "Upload",
+                state: Some("Stored"),
+                id: None,
+            },
+        ]
+    );
+
+    let counts = registry.state_counts();
+    assert_eq!(counts[&("Session", "Idle")], 1);
+    assert_eq!(counts[&("Session", "Active")], 1);
+    assert_eq!(counts[&("Upload", "Stored")], 1);
+}
+
+#[test]
+fn dropped_and_unregistered_machines_disappear() {
+    let registry = MachineRegistry::new();
+    let a = registry.register("session:a", DynamicSession::new(()));
+    let b = registry.register("session:b", DynamicSession::new(()));
+    assert_eq!(registry.len(), 2);
+
+    drop(a);
+    assert_eq!(registry.names(), ["session:b"]);
+    assert_eq!(registry.state("session:a"), None);
+
+    assert!(registry.unregister("session:b"));
+    assert!(!registry.unregister("session:b"));
+    assert!(registry.is_empty());
+    // Unregistering doesn't touch the machine itself
+    assert_eq!(b.lock().unwrap().current_state(), "Active");
+}
+
+#[test]
+fn get_hands_back_the_typed_machine() {
+    let registry = MachineRegistry::new();
+    let _session = registry.register("session:a", DynamicSession::new(()));
+
+    let session = registry.get::<DynamicSession<()>>("session:a").unwrap();
+    session.lock().unwrap().handle(SessionEvent::Close).unwrap();
+    assert_eq!(registry.state("session:a"), Some("Closed"));
+
+    assert!(registry.get::<DynamicUpload<()>>("session:a").is_none());
+    assert!(registry.get::<DynamicSession<()>>("session:z").is_none());
+}
+
+#[test]
+fn diagram_highlights_the_current_state() {
+    let registry = MachineRegistry::new();
+    let session = registry.register("session:a", DynamicSession::new(()));
+    session.lock().unwrap().handle(SessionEvent::Pause).unwrap();
+
+    let diagram = registry.diagram("session:a").unwrap();
+    assert!(diagram.contains("║ Idle"));
+    assert!(diagram.contains("current"));
+}
+
+#[test]
+fn machines_register_from_any_thread() {
+    let registry = MachineRegistry::global();
+    let handles: Vec<_> = (0..4)
+        .map(|id| {
+            thread::spawn(move || {
+                registry.register(format!("thread-session:{id}"), DynamicSession::new(()))
+            })
+        })
+        .collect();
+    let sessions: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    let names = registry.names();
+    for id in 0..4 {
+        assert!(names.contains(&format!("thread-session:{id}")));
+    }
+    drop(sessions);
+}
+
+#[test]
+fn poisoned_machines_are_reported_without_a_state() {
+    let registry = MachineRegistry::new();
+    let upload = registry.register("upload:1", DynamicUpload::new(()));
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        upload.lock().unwrap().handle(UploadEvent::Scan)
+    }));
+    assert!(result.is_err());
+
+    assert_eq!(
+        registry.snapshot(),
+        vec![MachineStatus {
+            name: "upload:1".into(),
+            machine: "Upload",
+            state: None,
+            id: None,
+        }]
+    );
+    assert_eq!(registry.state("upload:1"), None);
+    assert!(registry.state_counts().is_empty());
+    assert!(!registry.diagram("upload:1").unwrap().contains("current"));
+
+    let mut machine = upload
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    assert!(machine.recover((), UploadState::Queued));
+    drop(machine);
+    assert_eq!(registry.state("upload:1"), Some("Queued"));
+}
@@ -0,0 +1,167 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::thread;
+
+use state_machines::{DynamicError, state_machine};
+
+state_machine! {
+    name: Motor,
+    mailbox: { capacity: 4, overflow: error },
+    initial: Stopped,
+    states: [Stopped, Running],
+    events {
+        start {
+            transition: { from: Stopped, to: Running }
+        }
+        stop {
+            transition: { from: Running, to: Stopped }
+        }
+    }
+}
+
+state_machine! {
+    name: Sensor,
+    mailbox: { capacity: 2, overflow: drop_oldest },
+    initial: Idle,
+    states: [Idle, Sampling],
+    events {
+        sample {
+            payload: u16,
+            transition: { from: [Idle, Sampling], to: Sampling }
+        }
+    }
+}
+
+state_machine! {
+    name: Meter,
+    mailbox: { capacity: 8, overflow: error },
+    initial: Counting,
+    states: [Counting],
+    events {
+        tick {
+            payload: u16,
+            transition: { from: Counting, to: Counting }
+        }
+    }
+}
+
+static MOTOR_MAILBOX: MotorMailbox = MotorMailbox::new();
+static METER_MAILBOX: MeterMailbox = MeterMailbox::new();
+
+#[test]
+fn process_dispatches_events_in_post_order() {
+    let mailbox = MotorMailbox::new();
+    let mut motor = DynamicMotor::new(());
+
+    mailbox.post(MotorEvent::Start).unwrap();
+    mailbox.post(MotorEvent::Stop).unwrap();
+    mailbox.post(MotorEvent::Start).unwrap();
+    assert_eq!(mailbox.len(), 3);
+
+    assert_eq!(motor.process(&mailbox), Ok(3));
+    assert!(motor.is_running());
+    assert!(mailbox.is_empty());
+}
+
+#[test]
+fn error_policy_hands_back_overflowing_events() {
+    let mailbox = MotorMailbox::new();
+    for _ in 0..4 {
+        mailbox.post(MotorEvent::Stop).unwrap();
+    }
+
+    assert!(matches!(
+        mailbox.post(MotorEvent::Start),
+        Err(MotorEvent::Start)
+    ));
+}
+
+#[test]
+fn process_stops_at_first_failed_event() {
+    let mailbox = MotorMailbox::new();
+    let mut motor = DynamicMotor::new(());
+
+    mailbox.post(MotorEvent::Stop).unwrap();
+    mailbox.post(MotorEvent::Start).unwrap();
+
+    assert_eq!(
+        motor.process(&mailbox),
+        Err(DynamicError::invalid_transition("Stopped", "stop"))
+    );
+    assert_eq!(mailbox.len(), 1);
+    assert_eq!(motor.process(&mailbox), Ok(1));
+    assert!(motor.is_running());
+}
+
+#[test]
+fn drop_oldest_policy_keeps_latest_events() {
+    let mailbox = SensorMailbox::new();
+    let mut sensor = DynamicSensor::new(());
+
+    for reading in 1..=5 {
+        mailbox.post(SensorEvent::Sample(reading)).unwrap();
+    }
+    assert_eq!(mailbox.len(), 2);
+    assert_eq!(sensor.process(&mailbox), Ok(2));
+}
+
+#[test]
+fn events_posted_from_another_thread_are_all_processed() {
+    let mut motor = DynamicMotor::new(());
+    let mut handled = 0;
+
+    thread::scope(|scope| {
+        // Stand-in for an interrupt handler
+        scope.spawn(|| {
+            for round in 0..1_000 {
+                let mut event = if round % 2 == 0 {
+                    MotorEvent::Start
+                } else {
+                    MotorEvent::Stop
+                };
+                while let Err(rejected) = MOTOR_MAILBOX.post(event) {
+                    event = rejected;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        while handled < 1_000 {
+            handled += motor
+                .process(&MOTOR_MAILBOX)
+                .expect("events arrive in order");
+        }
+    });
+
+    assert_eq!(handled, 1_000);
+    assert!(motor.is_stopped());
+}
+
+#[test]
+fn handlers_that_preempt_each_other_can_post_to_one_mailbox() {
+    let mut meter = DynamicMeter::new(());
+    let mut handled = 0;
+
+    thread::scope(|scope| {
+        // Stand-ins for two interrupt handlers of different priority
+        for handler in 0..2u16 {
+            scope.spawn(move || {
+                for round in 0..500 {
+                    let mut event = MeterEvent::Tick(handler * 1_000 + round);
+                    while let Err(rejected) = METER_MAILBOX.post(event) {
+                        event = rejected;
+                        thread::yield_now();
+                    }
+                }
+            });
+        }
+
+        while handled < 1_000 {
+            handled += meter.process(&METER_MAILBOX).unwrap();
+        }
+    });
+
+    assert_eq!(handled, 1_000);
+    assert!(METER_MAILBOX.is_empty());
+}
@@ -0,0 +1,76 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+mod billing {
+    use state_machines::state_machine;
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Ledger {
+        pub(crate) lines: u32,
+        pub(crate) reminders: u32,
+    }
+
+    state_machine! {
+        name: Invoice,
+        visibility: pub(crate),
+        module: invoice_machine,
+        dynamic: true,
+        context: Ledger,
+        initial: Draft,
+        states: [Draft, Sent, Paid],
+        events {
+            send {
+                guards: [has_lines],
+                transition: { from: Draft, to: Sent }
+            }
+            remind {
+                before: [count_reminder],
+                transition: { from: Sent, to: Sent }
+            }
+            pay {
+                transition: { from: Sent, to: Paid }
+            }
+        }
+    }
+
+    // Guards and callbacks stay next to the invocation, outside the module
+    impl<S> invoice_machine::Invoice<S> {
+        fn has_lines(&self, ctx: &Ledger) -> bool {
+            ctx.lines > 0
+        }
+
+        fn count_reminder(&self) {
+            assert!(self.ctx.reminders < u32::MAX);
+        }
+    }
+
+    // Same names as the generated markers, without clashing
+    pub(crate) struct Draft;
+
+    pub(crate) fn draft_marker() -> Draft {
+        Draft
+    }
+}
+
+use billing::Ledger;
+use billing::invoice_machine::{DynamicInvoice, Invoice, InvoiceEvent, InvoiceState};
+
+#[test]
+fn generated_items_live_in_the_named_module() {
+    let ledger = Ledger {
+        lines: 2,
+        ..Default::default()
+    };
+    let invoice = Invoice::new(ledger).send().unwrap();
+    let invoice = invoice.remind().unwrap();
+    let _paid: Invoice<billing::invoice_machine::Paid> = invoice.pay().unwrap();
+
+    let _ = billing::draft_marker();
+}
+
+#[test]
+fn dynamic_wrapper_is_generated_inside_the_module() {
+    let mut invoice = DynamicInvoice::new(Ledger::default());
+    assert!(invoice.handle(InvoiceEvent::Send).is_err());
+    assert_eq!(invoice.state_enum(), InvoiceState::Draft);
+}
@@ -0,0 +1,175 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use core::future::{Ready, ready};
+use core::time::Duration;
+use pollster::block_on;
+use state_machines::{AsyncTimer, DynamicError, EventFailure, TransitionErrorKind, state_machine};
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    static REPORTED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn take_reported() -> Vec<String> {
+    REPORTED.with(|reported| reported.take())
+}
+
+fn report(failure: EventFailure<'_>) {
+    let line = match failure {
+        EventFailure::Rejected(err) => format!("{}: rejected {}", failure.event(), err),
+        EventFailure::Dispatch(err) => format!("{}: dispatch {:?}", failure.event(), err),
+    };
+    REPORTED.with(|reported| reported.borrow_mut().push(line));
+}
+
+#[derive(Debug, Default)]
+pub struct Tank {
+    pub primed: bool,
+}
+
+state_machine! {
+    name: Pump,
+    dynamic: true,
+    context: Tank,
+    on_error: { handler: log_failure, recover: fault, after: 2 },
+    initial: Idle,
+    states: [Idle, Running, Faulted],
+    events {
+        start {
+            guards: [primed],
+            transition: { from: Idle, to: Running }
+        }
+        stop {
+            transition: { from: Running, to: Idle }
+        }
+        fault {
+            transition: { from: [Idle, Running], to: Faulted }
+        }
+    }
+}
+
+impl<S> Pump<S> {
+    fn primed(&self, tank: &Tank) -> bool {
+        tank.primed
+    }
+
+    fn log_failure(&self, failure: EventFailure<'_>) {
+        report(failure);
+    }
+}
+
+#[test]
+fn typed_methods_report_rejections() {
+    let (idle, err) = Pump::new(Tank::default()).start().unwrap_err();
+    assert_eq!(err.guard, "primed");
+    assert_eq!(
+        take_reported(),
+        ["start: rejected guard `primed` failed for event `start` in state `Idle`"]
+    );
+
+    // Successful transitions report nothing
+    let mut idle = idle;
+    idle.ctx.primed = true;
+    let _running = idle.start().unwrap();
+    assert!(take_reported().is_empty());
+}
+
+#[test]
+fn dynamic_dispatch_reports_each_failure_once() {
+    let mut pump = DynamicPump::new(Tank::default());
+
+    assert_eq!(
+        pump.handle(PumpEvent::Stop),
+        Err(DynamicError::InvalidTransition {
+            from: "Idle",
+            event: "stop"
+        })
+    );
+    assert_eq!(
+        take_reported(),
+        [r#"stop: dispatch InvalidTransition { from: "Idle", event: "stop" }"#]
+    );
+}
+
+#[test]
+fn consecutive_failures_fire_the_recovery_event() {
+    let mut pump = DynamicPump::new(Tank::default());
+
+    assert!(pump.handle(PumpEvent::Start).is_err());
+    assert!(pump.is_idle());
+
+    // The second failure in a row moves the pump to `Faulted`
+    assert!(pump.handle(PumpEvent::Start).is_err());
+    assert!(pump.is_faulted());
+    assert_eq!(take_reported().len(), 2);
+}
+
+#[test]
+fn success_resets_the_failure_count() {
+    let mut pump = DynamicPump::new(Tank { primed: true });
+
+    pump.handle(PumpEvent::Start).unwrap();
+    assert!(pump.handle(PumpEvent::Start).is_err());
+    pump.handle(PumpEvent::Stop).unwrap();
+    assert!(pump.handle(PumpEvent::Stop).is_err());
+    assert!(pump.is_idle());
+    take_reported();
+}
+
+/// Retries immediately.
+pub struct Immediate;
+
+impl AsyncTimer for Immediate {
+    type Sleep = Ready<()>;
+
+    fn sleep(_duration: Duration) -> Self::Sleep {
+        ready(())
+    }
+}
+
+thread_local! {
+    static ATTEMPTS: Cell<u32> = const { Cell::new(0) };
+}
+
+state_machine! {
+    name: Beacon,
+    async: true,
+    on_error: log_failure,
+    initial: Silent,
+    states: [Silent, Broadcasting],
+    events {
+        broadcast {
+            retry: { attempts: 3, backoff: fixed(1ms), timer: Immediate },
+            guards: [antenna_up],
+            transition: { from: Silent, to: Broadcasting }
+        }
+    }
+}
+
+impl<C, S> Beacon<C, S> {
+    async fn antenna_up(&self, _ctx: &C) -> bool {
+        ATTEMPTS.with(|attempts| attempts.set(attempts.get() + 1));
+        false
+    }
+
+    async fn log_failure(&self, failure: EventFailure<'_>) {
+        report(failure);
+    }
+}
+
+#[test]
+fn handler_sees_the_final_error_after_retries() {
+    let (_silent, err) = block_on(Beacon::new(()).broadcast()).unwrap_err();
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::GuardFailed {
+            guard: "antenna_up"
+        }
+    );
+    assert_eq!(ATTEMPTS.with(Cell::get), 3);
+    assert_eq!(
+        take_reported(),
+        ["broadcast: rejected guard `antenna_up` failed for event `broadcast` in state `Silent`"]
+    );
+}
@@ -0,0 +1,86 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+/// A buffer that can't be cloned, so every callback but the last has to
+/// borrow it.
+#[derive(Debug, PartialEq)]
+pub struct Frame(pub Vec<u8>);
+
+#[derive(Debug, Default)]
+pub struct Radio {
+    pub sent: Vec<Frame>,
+    pub seen: usize,
+}
+
+state_machine! {
+    name: Link,
+    context: Radio,
+    initial: Idle,
+    states: [Idle, Sent, Loaded(Vec<u8>), Checked],
+    events {
+        send {
+            payload: Frame by value,
+            guards: [not_empty],
+            before: [measure],
+            after: [measure, keep],
+            transition: { from: Idle, to: Sent }
+        }
+        load {
+            payload: Frame by value,
+            transition: { from: Idle, to: Loaded, init: unpack }
+        }
+        check {
+            payload: Frame by ref,
+            after: [measure],
+            transition: { from: Idle, to: Checked }
+        }
+    }
+}
+
+impl<S> Link<S> {
+    fn not_empty(&self, _radio: &Radio, frame: &Frame) -> bool {
+        !frame.0.is_empty()
+    }
+
+    fn measure(&mut self, frame: &Frame) {
+        self.ctx.seen += frame.0.len();
+    }
+
+    fn keep(&mut self, frame: Frame) {
+        self.ctx.sent.push(frame);
+    }
+
+    fn unpack(&self, _radio: &Radio, frame: Frame) -> Vec<u8> {
+        frame.0
+    }
+}
+
+#[test]
+fn last_after_callback_takes_the_payload() {
+    let link = Link::new(Radio::default())
+        .send(Frame(vec![1, 2, 3]))
+        .unwrap();
+    assert_eq!(link.ctx.sent, [Frame(vec![1, 2, 3])]);
+    assert_eq!(link.ctx.seen, 6);
+}
+
+#[test]
+fn rejected_transitions_hand_the_payload_back_untouched() {
+    let (link, err) = Link::new(Radio::default()).send(Frame(vec![])).unwrap_err();
+    assert_eq!(err.guard, "not_empty");
+    assert!(link.ctx.sent.is_empty());
+}
+
+#[test]
+fn init_takes_the_payload_without_after_callbacks() {
+    let link = Link::new(Radio::default()).load(Frame(vec![4, 5])).unwrap();
+    assert_eq!(link.loaded_data(), &vec![4, 5]);
+}
+
+#[test]
+fn by_ref_payloads_stay_borrowed() {
+    let link = Link::new(Radio::default()).check(Frame(vec![6])).unwrap();
+    assert_eq!(link.ctx.seen, 1);
+}
@@ -0,0 +1,125 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub priority: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Console {
+    pub default_priority: u8,
+    pub ran: Vec<Command>,
+}
+
+state_machine! {
+    name: Shell,
+    context: Console,
+    dynamic: true,
+    initial: Ready,
+    states: [
+        Ready,
+        superstate Busy {
+            state Running,
+            state Paused,
+        },
+    ],
+    events {
+        run {
+            payload: Command,
+            transform: normalize,
+            guards: [named],
+            after: [record],
+            transition: { from: Ready, to: Running }
+        }
+        queue {
+            payload: Command,
+            transform: normalize,
+            guards: [named],
+            transition: { from: Busy, to: Busy }
+        }
+    }
+}
+
+impl<S> Shell<S> {
+    fn normalize(&self, console: &Console, mut command: Command) -> Command {
+        command.name = command.name.trim().to_lowercase();
+        if command.priority == 0 {
+            command.priority = console.default_priority;
+        }
+        command
+    }
+
+    fn named(&self, _console: &Console, command: &Command) -> bool {
+        !command.name.is_empty()
+    }
+
+    fn record(&mut self, command: &Command) {
+        self.ctx.ran.push(command.clone());
+    }
+}
+
+fn command(name: &str) -> Command {
+    Command {
+        name: name.into(),
+        priority: 0,
+    }
+}
+
+fn console() -> Console {
+    Console {
+        default_priority: 5,
+        ran: Vec::new(),
+    }
+}
+
+#[test]
+fn guards_and_callbacks_see_the_transformed_payload() {
+    let shell = Shell::new(console()).run(command("  Deploy ")).unwrap();
+
+    assert_eq!(
+        shell.ctx.ran,
+        [Command {
+            name: "deploy".into(),
+            priority: 5,
+        }]
+    );
+}
+
+#[test]
+fn guards_reject_payloads_the_transform_empties() {
+    let (shell, err) = Shell::new(console()).run(command("   ")).unwrap_err();
+
+    assert_eq!(err.guard, "named");
+    assert!(shell.ctx.ran.is_empty());
+}
+
+#[test]
+fn superstate_probes_transform_a_copy() {
+    let shell = Shell::new(console()).run(command("build")).unwrap();
+
+    assert!(shell.can_queue(&command("test")));
+    assert!(!shell.can_queue(&command("  ")));
+}
+
+#[test]
+fn dynamic_dispatch_transforms_too() {
+    let mut shell = DynamicShell::new(console());
+
+    assert!(shell.handle(ShellEvent::Run(command(" "))).is_err());
+    shell.handle(ShellEvent::Run(command("Lint"))).unwrap();
+    let shell = shell.into_running().unwrap();
+    assert_eq!(shell.ctx.ran[0].name, "lint");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn dry_runs_check_guards_against_the_transformed_payload() {
+    let shell = DynamicShell::new(console());
+
+    let report = shell.simulate(&[ShellEvent::Run(command("\t"))]);
+    assert_eq!(report.final_state(), &ShellState::Ready);
+}
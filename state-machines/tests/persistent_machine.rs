@@ -0,0 +1,154 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{DynamicError, DynamicMachine, state_machine};
+
+#[derive(Debug, Default)]
+pub struct Ledger {
+    pub shipped: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Tracking {
+    pub number: u32,
+}
+
+state_machine! {
+    name: Order,
+    context: Ledger,
+    dynamic: true,
+    persistent: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped(Tracking), Cancelled],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            after: [count_shipment],
+            transition: { from: Paid, to: Shipped }
+        }
+        cancel {
+            transition: { from: [Pending, Paid], to: Cancelled }
+        }
+    }
+}
+
+impl<S> Order<S> {
+    fn count_shipment(&mut self) {
+        self.ctx.shipped += 1;
+    }
+}
+
+state_machine! {
+    name: Upload,
+    dynamic: true,
+    persistent: true,
+    async: true,
+    initial: Queued,
+    states: [Queued, Sending, Done],
+    events {
+        start {
+            transition: { from: Queued, to: Sending }
+        }
+        finish {
+            transition: { from: Sending, to: Done }
+        }
+    }
+}
+
+#[test]
+fn state_names_round_trip() {
+    for state in [
+        OrderState::Pending,
+        OrderState::Paid,
+        OrderState::Shipped,
+        OrderState::Cancelled,
+    ] {
+        assert_eq!(OrderState::from_name(state.name()), Some(state));
+    }
+    assert_eq!(OrderState::from_name("Refunded"), None);
+}
+
+fn shipments(order: DynamicOrder) -> u32 {
+    match order.into_typed() {
+        AnyOrderState::Pending(m) => m.ctx.shipped,
+        AnyOrderState::Paid(m) => m.ctx.shipped,
+        AnyOrderState::Shipped(m) => m.ctx.shipped,
+        AnyOrderState::Cancelled(m) => m.ctx.shipped,
+    }
+}
+
+#[test]
+fn restore_enters_the_state_without_callbacks() {
+    let order = DynamicOrder::restore(Ledger::default(), OrderState::Shipped);
+    assert_eq!(order.state_enum(), OrderState::Shipped);
+    assert_eq!(order.shipped_data(), Some(&Tracking::default()));
+    assert_eq!(shipments(order), 0);
+
+    let mut order = DynamicOrder::restore(Ledger::default(), OrderState::Paid);
+    order.handle(OrderEvent::Ship).unwrap();
+    assert!(order.is_shipped());
+    assert_eq!(shipments(order), 1);
+}
+
+#[test]
+fn restore_state_keeps_the_context() {
+    let mut order = DynamicOrder::new(Ledger::default());
+    order.handle(OrderEvent::Pay).unwrap();
+    order.handle(OrderEvent::Ship).unwrap();
+    order.set_shipped_data(Tracking { number: 7 }).unwrap();
+
+    order.restore_state(OrderState::Paid);
+    assert_eq!(order.current_state(), "Paid");
+    assert_eq!(order.shipped_data(), None);
+    assert_eq!(shipments(order), 1);
+}
+
+/// Replay a stored state and event log the way a persistence layer would,
+/// knowing only the trait.
+fn replay<M: DynamicMachine>(
+    ctx: M::Context,
+    stored: &str,
+    events: impl IntoIterator<Item = M::Event>,
+) -> Result<M, DynamicError> {
+    let state = M::state_from_name(stored).expect("unknown stored state");
+    let mut machine = M::restore(ctx, state);
+    for event in events {
+        let before = machine.state();
+        if let Err(err) = pollster::block_on(machine.dispatch(event)) {
+            machine.restore_state(before);
+            return Err(err);
+        }
+    }
+    Ok(machine)
+}
+
+#[test]
+fn sync_machines_dispatch_through_the_trait() {
+    let order: DynamicOrder = replay(
+        Ledger::default(),
+        "Pending",
+        [OrderEvent::Pay, OrderEvent::Ship],
+    )
+    .unwrap();
+    assert_eq!(DynamicOrder::state_name(&order.state()), "Shipped");
+
+    let err =
+        replay::<DynamicOrder>(Ledger::default(), "Cancelled", [OrderEvent::Pay]).unwrap_err();
+    assert!(matches!(err, DynamicError::InvalidTransition { .. }));
+    assert_eq!(DynamicOrder::event_name(&OrderEvent::Cancel), "cancel");
+}
+
+#[test]
+fn async_machines_dispatch_through_the_trait() {
+    let upload: DynamicUpload<()> = replay((), "Sending", [UploadEvent::Finish]).unwrap();
+    assert_eq!(upload.current_state(), "Done");
+}
+
+#[test]
+fn dispatch_futures_stay_send() {
+    fn assert_send<T: Send>(_: T) {}
+    let mut upload = DynamicUpload::new(());
+    assert_send(DynamicMachine::dispatch(&mut upload, UploadEvent::Start));
+}
@@ -0,0 +1,148 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::future::Future;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use pollster::block_on;
+
+use state_machines::{DynamicError, state_machine};
+
+state_machine! {
+    name: Furnace,
+    dynamic: true,
+    initial: Cold,
+    states: [Cold, Heating],
+    events {
+        ignite {
+            payload: bool,
+            guards: [igniter_ok],
+            transition: { from: Cold, to: Heating }
+        }
+        extinguish {
+            transition: { from: Heating, to: Cold }
+        }
+    }
+}
+
+state_machine! {
+    name: Kiln,
+    dynamic: true,
+    async: true,
+    initial: Unlit,
+    states: [Unlit, Firing],
+    events {
+        fire {
+            before: [wait_for_gas],
+            transition: { from: Unlit, to: Firing }
+        }
+        cool {
+            transition: { from: Firing, to: Unlit }
+        }
+    }
+}
+
+/// Pending on its first poll, ready on the next.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl<C, S> Kiln<C, S> {
+    async fn wait_for_gas(&self) {
+        YieldOnce(false).await;
+    }
+}
+
+impl<C, S> Furnace<C, S> {
+    fn igniter_ok(&self, _ctx: &C, sparks: &bool) -> bool {
+        if !*sparks {
+            panic!("igniter shorted");
+        }
+        true
+    }
+}
+
+#[test]
+fn completed_dispatches_do_not_block_later_events() {
+    let mut furnace = DynamicFurnace::new(());
+
+    furnace.handle(FurnaceEvent::Ignite(true)).unwrap();
+    furnace.handle(FurnaceEvent::Extinguish).unwrap();
+    assert!(furnace.is_cold());
+}
+
+#[test]
+fn abandoned_dispatch_is_reported_instead_of_corrupting_state() {
+    let mut furnace = DynamicFurnace::new(());
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        furnace.handle(FurnaceEvent::Ignite(false))
+    }));
+    assert!(result.is_err());
+
+    assert_eq!(
+        furnace.handle(FurnaceEvent::Ignite(true)),
+        Err(DynamicError::reentrant_dispatch("ignite"))
+    );
+}
+
+#[test]
+fn poisoned_machine_can_be_recovered() {
+    let mut furnace = DynamicFurnace::new(());
+    furnace.handle(FurnaceEvent::Ignite(true)).unwrap();
+    furnace.handle(FurnaceEvent::Extinguish).unwrap();
+    assert!(!furnace.is_poisoned());
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        furnace.handle(FurnaceEvent::Ignite(false))
+    }));
+    assert!(result.is_err());
+    assert!(furnace.is_poisoned());
+
+    assert!(furnace.recover((), FurnaceState::Cold));
+    assert!(!furnace.is_poisoned());
+    assert_eq!(furnace.version(), 2);
+    furnace.handle(FurnaceEvent::Ignite(true)).unwrap();
+    assert!(furnace.is_heating());
+
+    // Only a poisoned machine is rebuilt
+    assert!(!furnace.recover((), FurnaceState::Cold));
+    assert!(furnace.is_heating());
+}
+
+#[test]
+fn dispatch_dropped_inside_a_callback_poisons_until_recovered() {
+    let mut kiln = DynamicKiln::new(());
+
+    {
+        // The `before` callback suspends; dropping the dispatch there
+        // abandons it with the state taken out
+        let mut dispatch = pin!(kiln.handle(KilnEvent::Fire));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(dispatch.as_mut().poll(&mut cx).is_pending());
+    }
+
+    assert!(kiln.is_poisoned());
+    assert_eq!(
+        block_on(kiln.handle(KilnEvent::Cool)),
+        Err(DynamicError::reentrant_dispatch("cool"))
+    );
+
+    assert!(kiln.recover((), KilnState::Unlit));
+    block_on(kiln.handle(KilnEvent::Fire)).unwrap();
+    assert!(kiln.is_firing());
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::repl::Repl;
+use state_machines::state_machine;
+
+#[derive(Debug, Clone)]
+pub struct Tracking {
+    pub carrier: &'static str,
+}
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    persistent: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped, Cancelled],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            payload: Tracking,
+            transition: { from: Paid, to: Shipped }
+        }
+        cancel {
+            guards: [refundable],
+            transition: { from: [Pending, Paid], to: Cancelled }
+        }
+    }
+}
+
+impl<C, S> Order<C, S> {
+    fn refundable(&self, _ctx: &C) -> bool {
+        false
+    }
+}
+
+/// Run `input` through a fresh REPL and return everything it printed.
+fn session(input: &str) -> (String, Repl<DynamicOrder<()>>) {
+    let mut repl = Repl::new(DynamicOrder::new(()));
+    let mut output = Vec::new();
+    repl.run_with(input.as_bytes(), &mut output).unwrap();
+    (String::from_utf8(output).unwrap(), repl)
+}
+
+#[test]
+fn firing_events_moves_the_machine() {
+    let (output, repl) = session("pay\nfire cancel\nstate\n");
+    assert_eq!(
+        output,
+        "Order: type `help` for commands\n\
+         Pending> Pending --pay--> Paid\n\
+         Paid> rejected: GuardFailed { guard: \"refundable\", event: \"cancel\", failed_guards: [] }\n\
+         Paid> Paid\n\
+         Paid> \n"
+    );
+    assert_eq!(repl.into_inner().current_state(), "Paid");
+}
+
+#[test]
+fn listings_mark_the_current_state() {
+    let (output, _) = session("states\nevents\n");
+    assert!(output.contains("* Pending\n  Paid\n  Shipped\n  Cancelled\n"));
+    assert!(output.contains("  pay -> Paid\n  cancel -> Cancelled\n  (not from Pending: ship)\n"));
+}
+
+#[test]
+fn history_keeps_rejections_too() {
+    let (output, _) = session("history\npay\npay\nhistory\n");
+    assert!(output.contains("no events fired yet\n"));
+    assert!(output.contains(
+        "  1. pay: Pending -> Paid\n  2. pay: Paid -> Paid (rejected: InvalidTransition { from: \"Paid\", event: \"pay\" })\n"
+    ));
+}
+
+#[test]
+fn diagram_draws_every_transition() {
+    let (output, _) = session("pay\ndiagram\n");
+    let drawn = OrderState::definition()
+        .diagram()
+        .current(&OrderState::Paid)
+        .to_string();
+    assert!(output.contains(&drawn));
+    assert!(drawn.contains("║ Paid      ║ current"));
+}
+
+#[test]
+fn payload_and_unknown_events_are_explained() {
+    let (output, repl) = session("pay\nship\nrefund\nfire\nfire pay now\nquit\npay\n");
+    assert!(output.contains("`ship` takes Tracking and can't be fired from the prompt\n"));
+    assert!(output.contains("unknown command or event `refund`; try `help`\n"));
+    assert!(output.contains("usage: fire <event>\n"));
+    assert!(output.contains("unknown command `fire pay now`; try `help`\n"));
+    // Nothing after `quit` runs
+    assert_eq!(repl.machine().current_state(), "Paid");
+    assert!(!output.ends_with("\n"));
+}
+
+state_machine! {
+    name: Upload,
+    async: true,
+    dynamic: true,
+    persistent: true,
+    initial: Queued,
+    states: [Queued, Stored],
+    events {
+        store {
+            guards: [bucket_ready],
+            transition: { from: Queued, to: Stored }
+        }
+    }
+}
+
+impl<C, S> Upload<C, S> {
+    async fn bucket_ready(&self, _ctx: &C) -> bool {
+        true
+    }
+}
+
+#[test]
+fn async_machines_are_driven_to_completion() {
+    let mut repl = Repl::new(DynamicUpload::new(()));
+    repl.run_with("store\n".as_bytes(), std::io::sink())
+        .unwrap();
+    assert_eq!(repl.machine().current_state(), "Stored");
+}
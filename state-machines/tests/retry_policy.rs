@@ -0,0 +1,155 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use core::future::{Ready, ready};
+use core::time::Duration;
+use pollster::block_on;
+use state_machines::{AsyncTimer, Backoff, DynamicError, TransitionErrorKind, state_machine};
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    static SLEPT: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+    static FAILURES_LEFT: Cell<u32> = const { Cell::new(0) };
+    static CALLS: Cell<u32> = const { Cell::new(0) };
+}
+
+/// A timer that records each requested delay and returns immediately.
+pub struct Recorded;
+
+impl AsyncTimer for Recorded {
+    type Sleep = Ready<()>;
+
+    fn sleep(duration: Duration) -> Self::Sleep {
+        SLEPT.with(|slept| slept.borrow_mut().push(duration));
+        ready(())
+    }
+}
+
+fn fail_next(times: u32) {
+    FAILURES_LEFT.with(|left| left.set(times));
+    CALLS.with(|calls| calls.set(0));
+    SLEPT.with(|slept| slept.borrow_mut().clear());
+}
+
+fn flaky() -> bool {
+    CALLS.with(|calls| calls.set(calls.get() + 1));
+    FAILURES_LEFT.with(|left| match left.get() {
+        0 => true,
+        n => {
+            left.set(n - 1);
+            false
+        }
+    })
+}
+
+state_machine! {
+    name: Upload,
+    async: true,
+    dynamic: true,
+    initial: Queued,
+    states: [Queued, Sent, Acked],
+    events {
+        send {
+            payload: Vec<u8>,
+            retry: { attempts: 3, backoff: exponential(100ms), timer: Recorded },
+            guards: [server_up],
+            transition: { from: Queued, to: Sent }
+        }
+        ack {
+            retry: { attempts: 2, backoff: fixed(Duration::from_millis(5)), timer: Recorded },
+            transition: { from: Sent, to: Acked, action: confirm }
+        }
+        resend {
+            retry: { attempts: 4 },
+            guards: [link_up],
+            transition: { from: Sent, to: Sent }
+        }
+    }
+}
+
+impl<C, S> Upload<C, S> {
+    async fn server_up(&self, _ctx: &C, _bytes: &Vec<u8>) -> bool {
+        flaky()
+    }
+
+    async fn link_up(&self, _ctx: &C) -> bool {
+        flaky()
+    }
+
+    async fn confirm(_ctx: &mut C) -> bool {
+        flaky()
+    }
+}
+
+#[test]
+fn failing_guard_is_retried_with_exponential_backoff() {
+    fail_next(2);
+    let _sent = block_on(Upload::new(()).send(vec![1, 2, 3])).unwrap();
+
+    assert_eq!(CALLS.with(Cell::get), 3);
+    assert_eq!(
+        SLEPT.with(|slept| slept.take()),
+        [Duration::from_millis(100), Duration::from_millis(200)]
+    );
+}
+
+#[test]
+fn error_surfaces_once_attempts_are_exhausted() {
+    fail_next(5);
+    let (_queued, err) = block_on(Upload::new(()).send(vec![])).unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::GuardFailed { guard: "server_up" }
+    );
+    assert_eq!(CALLS.with(Cell::get), 3);
+    // No wait after the final attempt
+    assert_eq!(SLEPT.with(|slept| slept.take()).len(), 2);
+}
+
+#[test]
+fn failing_action_is_retried_with_fixed_backoff() {
+    fail_next(0);
+    let sent = block_on(Upload::new(()).send(vec![])).unwrap();
+
+    fail_next(1);
+    let _acked = block_on(sent.ack()).unwrap();
+    assert_eq!(CALLS.with(Cell::get), 2);
+    assert_eq!(SLEPT.with(|slept| slept.take()), [Duration::from_millis(5)]);
+}
+
+#[test]
+fn retries_without_backoff_never_sleep() {
+    fail_next(0);
+    let sent = block_on(Upload::new(()).send(vec![])).unwrap();
+
+    fail_next(3);
+    let _sent = block_on(sent.resend()).unwrap();
+    assert_eq!(CALLS.with(Cell::get), 4);
+    assert!(SLEPT.with(|slept| slept.take()).is_empty());
+}
+
+#[test]
+fn dynamic_dispatch_retries_too() {
+    fail_next(1);
+    let mut upload = DynamicUpload::new(());
+    block_on(upload.handle(UploadEvent::Send(vec![9]))).unwrap();
+    assert!(upload.is_sent());
+
+    fail_next(4);
+    assert_eq!(
+        block_on(upload.handle(UploadEvent::Resend)),
+        Err(DynamicError::guard_failed("link_up", "resend"))
+    );
+    assert_eq!(CALLS.with(Cell::get), 4);
+}
+
+#[test]
+fn backoff_delays() {
+    let base = Duration::from_millis(100);
+    assert_eq!(Backoff::None.delay(3), Duration::ZERO);
+    assert_eq!(Backoff::Fixed(base).delay(3), base);
+    assert_eq!(Backoff::Exponential(base).delay(1), base);
+    assert_eq!(Backoff::Exponential(base).delay(4), base * 8);
+    assert_eq!(Backoff::Exponential(base).delay(64), Duration::MAX);
+}
@@ -0,0 +1,139 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+
+use state_machines::{RewindTooFar, state_machine};
+
+thread_local! {
+    static CALLS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+fn take_calls() -> Vec<&'static str> {
+    CALLS.with(|calls| calls.take())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    pub percent: u8,
+}
+
+state_machine! {
+    name: Job,
+    dynamic: true,
+    data_states: true,
+    history: 8,
+    state_derives: [PartialEq],
+    initial: Queued,
+    states: [Queued, Running(Progress), Failed, Done],
+    events {
+        start {
+            after: [announce],
+            transition: { from: Queued, to: Running }
+        }
+        fail {
+            after: [announce],
+            transition: { from: Running, to: Failed }
+        }
+        retry {
+            after: [announce],
+            transition: { from: Failed, to: Queued }
+        }
+        finish {
+            transition: { from: Running, to: Done }
+        }
+    }
+}
+
+impl<C, S> Job<C, S> {
+    fn announce(&self) {
+        CALLS.with(|calls| calls.borrow_mut().push("announce"));
+    }
+}
+
+#[test]
+fn rewind_restores_the_state_and_its_data() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+    job.running_data_mut().unwrap().percent = 40;
+    job.handle(JobEvent::Fail).unwrap();
+    job.handle(JobEvent::Retry).unwrap();
+    take_calls();
+
+    job.rewind(2).unwrap();
+    assert_eq!(
+        job.state_enum(),
+        JobState::Running(Progress { percent: 40 })
+    );
+    assert_eq!(job.version(), 1);
+    assert_eq!(job.history().len(), 1);
+    assert!(take_calls().is_empty());
+
+    // The rewound machine carries on from there
+    job.handle(JobEvent::Finish).unwrap();
+    assert!(job.is_done());
+    assert_eq!(job.version(), 2);
+}
+
+#[test]
+fn rewind_restores_the_data_each_visit_left_with() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+    job.running_data_mut().unwrap().percent = 40;
+    job.handle(JobEvent::Fail).unwrap();
+    job.handle(JobEvent::Retry).unwrap();
+    job.handle(JobEvent::Start).unwrap();
+    job.running_data_mut().unwrap().percent = 90;
+    job.handle(JobEvent::Fail).unwrap();
+
+    job.rewind(1).unwrap();
+    assert_eq!(
+        job.state_enum(),
+        JobState::Running(Progress { percent: 90 })
+    );
+
+    job.rewind(3).unwrap();
+    assert_eq!(
+        job.state_enum(),
+        JobState::Running(Progress { percent: 40 })
+    );
+    assert_eq!(job.version(), 1);
+}
+
+#[test]
+fn rewinding_everything_returns_to_the_first_state() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+    job.handle(JobEvent::Fail).unwrap();
+
+    job.rewind(2).unwrap();
+    assert!(job.is_queued());
+    assert_eq!(job.version(), 0);
+    assert!(job.history().is_empty());
+}
+
+#[test]
+fn rewind_zero_changes_nothing() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+
+    job.rewind(0).unwrap();
+    assert!(job.is_running());
+    assert_eq!(job.history().len(), 1);
+}
+
+#[test]
+fn rewinding_past_the_history_fails() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+
+    assert_eq!(
+        job.rewind(2),
+        Err(RewindTooFar {
+            requested: 2,
+            recorded: 1
+        })
+    );
+    assert!(job.is_running());
+    assert_eq!(job.version(), 1);
+}
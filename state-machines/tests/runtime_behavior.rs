@@ -0,0 +1,157 @@
+#![cfg(feature = "alloc")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{
+    BehaviorNames, BehaviorRegistry, DynamicError, MissingBehavior, WithBehavior, state_machine,
+};
+use std::sync::Arc;
+
+/// A claim moving through a rules engine; what the rules are is up to the
+/// registry.
+#[derive(Debug, Default)]
+pub struct Claim {
+    pub amount: u32,
+    pub log: Vec<&'static str>,
+}
+
+state_machine! {
+    name: ClaimFlow,
+    dynamic: true,
+    behavior: true,
+    context: WithBehavior<Claim>,
+    initial: Submitted,
+    states: [Submitted, Approved, Rejected, Paid],
+    events {
+        approve {
+            guards: [within_limit],
+            unless: [flagged],
+            after: [notify],
+            transition: { from: Submitted, to: Approved }
+        }
+        reject {
+            transition: { from: Submitted, to: Rejected }
+        }
+        pay {
+            payload: u32,
+            guards: [funds_available],
+            before: [reserve],
+            transition: { from: Approved, to: Paid }
+        }
+    }
+    callbacks: {
+        before_transition [{ name: audit }]
+        after_transition [{ name: settle, on: [pay] }]
+    }
+}
+
+fn rules(limit: u32) -> Arc<BehaviorRegistry<Claim>> {
+    let mut rules = BehaviorRegistry::new();
+    rules
+        .register_guard("within_limit", move |claim: &Claim| claim.amount <= limit)
+        .register_guard("flagged", |_: &Claim| false)
+        .register_guard("funds_available", |_: &Claim| true)
+        .register_callback("notify", |claim: &mut Claim| claim.log.push("notify"))
+        .register_callback("reserve", |claim: &mut Claim| claim.log.push("reserve"))
+        .register_callback("audit", |claim: &mut Claim| claim.log.push("audit"))
+        .register_callback("settle", |claim: &mut Claim| claim.log.push("settle"));
+    Arc::new(rules)
+}
+
+fn claim(amount: u32, rules: Arc<BehaviorRegistry<Claim>>) -> WithBehavior<Claim> {
+    WithBehavior::new(
+        Claim {
+            amount,
+            ..Claim::default()
+        },
+        rules,
+    )
+}
+
+#[test]
+fn guards_and_callbacks_come_from_the_registry() {
+    let paid = ClaimFlow::new(claim(50, rules(100)))
+        .approve()
+        .unwrap()
+        .pay(50)
+        .unwrap();
+    assert_eq!(
+        paid.ctx.log,
+        ["audit", "notify", "audit", "reserve", "settle"]
+    );
+
+    let (_submitted, err) = ClaimFlow::new(claim(500, rules(100)))
+        .approve()
+        .unwrap_err();
+    assert_eq!(err.guard, "within_limit");
+}
+
+#[test]
+fn unregistered_guards_fail_and_callbacks_do_nothing() {
+    // `flagged` was never registered, so `unless: [flagged]` refuses too
+    let mut rules = BehaviorRegistry::new();
+    rules.register_guard("within_limit", |_: &Claim| true);
+    let (_submitted, err) = ClaimFlow::new(claim(10, Arc::new(rules)))
+        .approve()
+        .unwrap_err();
+    assert_eq!(err.guard, "flagged");
+
+    let mut rules = BehaviorRegistry::new();
+    rules
+        .register_guard("within_limit", |_: &Claim| true)
+        .register_guard("flagged", |_: &Claim| false);
+    let approved = ClaimFlow::new(claim(10, Arc::new(rules)))
+        .approve()
+        .unwrap();
+    assert!(approved.ctx.log.is_empty());
+
+    // Nor was `funds_available`
+    let (_approved, err) = approved.pay(10).unwrap_err();
+    assert_eq!(err.guard, "funds_available");
+}
+
+#[test]
+fn behavior_names_the_guards_and_callbacks_to_register() {
+    assert_eq!(
+        ClaimFlow::BEHAVIOR,
+        BehaviorNames {
+            guards: &["within_limit", "flagged", "funds_available"],
+            callbacks: &["notify", "audit", "reserve", "settle"],
+        }
+    );
+
+    assert!(WithBehavior::checked(Claim::default(), rules(100), &ClaimFlow::BEHAVIOR).is_ok());
+
+    let mut partial = BehaviorRegistry::new();
+    partial
+        .register_guard("within_limit", |_: &Claim| true)
+        .register_callback("notify", |_: &mut Claim| {});
+    let missing = WithBehavior::checked(Claim::default(), Arc::new(partial), &ClaimFlow::BEHAVIOR)
+        .unwrap_err();
+    assert_eq!(
+        missing,
+        MissingBehavior {
+            guards: vec!["flagged".into(), "funds_available".into()],
+            callbacks: vec!["audit".into(), "reserve".into(), "settle".into()],
+        }
+    );
+    assert_eq!(
+        missing.to_string(),
+        "missing guards: `flagged`, `funds_available`; missing callbacks: `audit`, `reserve`, `settle`"
+    );
+}
+
+#[test]
+fn one_registry_serves_dynamic_machines_too() {
+    let rules = rules(100);
+    let mut cheap = DynamicClaimFlow::new(claim(10, rules.clone()));
+    let mut pricey = DynamicClaimFlow::new(claim(1000, rules));
+
+    cheap.handle(ClaimFlowEvent::Approve).unwrap();
+    assert_eq!(cheap.current_state(), "Approved");
+    assert!(matches!(
+        pricey.handle(ClaimFlowEvent::Approve),
+        Err(DynamicError::GuardFailed { .. })
+    ));
+    assert_eq!(pricey.current_state(), "Submitted");
+}
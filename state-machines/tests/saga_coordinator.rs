@@ -0,0 +1,133 @@
+#![cfg(feature = "alloc")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use pollster::block_on;
+use state_machines::saga::{SagaCoordinator, SagaError};
+use state_machines::{DynamicError, state_machine};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Default)]
+pub struct Booking {
+    pub id: u64,
+}
+
+state_machine! {
+    name: Trip,
+    context: Booking,
+    dynamic: true,
+    persistent: true,
+    initial: Requested,
+    states: [Requested, Reserved, Confirmed, Cancelled],
+    events {
+        reserve {
+            payload: u64,
+            transition: { from: Requested, to: Reserved }
+        }
+        confirm {
+            transition: { from: Reserved, to: Confirmed }
+        }
+        cancel {
+            transition: { from: [Requested, Reserved], to: Cancelled }
+        }
+    }
+}
+
+fn coordinator() -> SagaCoordinator<u64, DynamicTrip> {
+    SagaCoordinator::new(|id: &u64| DynamicTrip::new(Booking { id: *id })).start_on("reserve")
+}
+
+#[test]
+fn start_events_create_instances_that_later_events_reach() {
+    let spawned = Arc::new(Mutex::new(Vec::new()));
+    let mut trips = SagaCoordinator::new({
+        let spawned = spawned.clone();
+        move |id: &u64| {
+            spawned.lock().unwrap().push(*id);
+            DynamicTrip::new(Booking { id: *id })
+        }
+    })
+    .start_on("reserve");
+
+    let reserved = block_on(trips.handle(7, TripEvent::Reserve(7))).unwrap();
+    assert!(reserved.started);
+    assert!(reserved.finished.is_none());
+    block_on(trips.handle(8, TripEvent::Reserve(8))).unwrap();
+    assert_eq!(trips.ids().copied().collect::<Vec<_>>(), [7, 8]);
+    assert_eq!(*spawned.lock().unwrap(), [7, 8]);
+
+    let confirmed = block_on(trips.handle(7, TripEvent::Confirm)).unwrap();
+    assert!(!confirmed.started);
+    // `Confirmed` is terminal, so the instance is handed back
+    let trip = confirmed.finished.expect("a finished saga");
+    assert_eq!(trip.current_state(), "Confirmed");
+    assert_eq!(trips.ids().copied().collect::<Vec<_>>(), [8]);
+    assert_eq!(trips.get(&8).unwrap().current_state(), "Reserved");
+}
+
+#[test]
+fn other_events_need_a_running_instance() {
+    let mut trips = coordinator();
+    assert_eq!(
+        block_on(trips.handle(7, TripEvent::Confirm)).unwrap_err(),
+        SagaError::NoInstance {
+            id: 7,
+            event: "confirm",
+        }
+    );
+    assert!(trips.is_empty());
+}
+
+#[test]
+fn rejected_events_leave_instances_where_they_were() {
+    let mut trips = coordinator();
+    block_on(trips.handle(7, TripEvent::Reserve(7))).unwrap();
+
+    let err = block_on(trips.handle(7, TripEvent::Reserve(7))).unwrap_err();
+    assert_eq!(
+        err,
+        SagaError::Dynamic {
+            id: 7,
+            error: DynamicError::invalid_transition("Reserved", "reserve"),
+        }
+    );
+    assert_eq!(trips.get(&7).unwrap().current_state(), "Reserved");
+}
+
+#[test]
+fn routes_by_the_id_events_carry() {
+    let mut trips = coordinator().correlate_with(|event| match event {
+        TripEvent::Reserve(id) => Some(*id),
+        _ => None,
+    });
+
+    let routed = block_on(trips.route(TripEvent::Reserve(3))).unwrap();
+    assert_eq!(routed.id, 3);
+    assert_eq!(trips.len(), 1);
+    assert_eq!(
+        block_on(trips.route(TripEvent::Cancel)).unwrap_err(),
+        SagaError::Uncorrelated { event: "cancel" }
+    );
+
+    let cancelled = block_on(trips.handle(3, TripEvent::Cancel)).unwrap();
+    assert!(cancelled.finished.is_some());
+    assert!(trips.is_empty());
+}
+
+#[test]
+fn restored_instances_can_be_taken_over() {
+    let mut trips = coordinator();
+    let mut restored = DynamicTrip::new(Booking { id: 9 });
+    restored.handle(TripEvent::Reserve(9)).unwrap();
+    assert!(trips.insert(9, restored).is_none());
+
+    block_on(trips.handle(9, TripEvent::Confirm)).unwrap();
+    assert!(trips.remove(&9).is_none());
+}
+
+#[test]
+#[should_panic(expected = "`depart` isn't an event of `Trip`")]
+fn start_events_must_exist() {
+    let _ = coordinator().start_on("depart");
+}
@@ -0,0 +1,230 @@
+#![cfg(feature = "std")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use pollster::block_on;
+use state_machines::state_machine;
+use state_machines::store::{SharedError, SharedMachine, StateStore, Swap, Versioned};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    persistent: true,
+    initial: Pending,
+    states: [Pending, Paid, Shipped],
+    events {
+        pay {
+            transition: { from: Pending, to: Paid }
+        }
+        ship {
+            transition: { from: Paid, to: Shipped }
+        }
+        touch {
+            transition: { from: Paid, to: Paid }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Items {
+    pub skus: Vec<u32>,
+}
+
+state_machine! {
+    name: Basket,
+    dynamic: true,
+    persistent: true,
+    initial: Filling,
+    states: [Filling(Items), CheckedOut],
+    events {
+        check_out {
+            transition: { from: Filling, to: CheckedOut }
+        }
+    }
+}
+
+/// A store shared by every "node" holding a clone, which can be told to fail.
+#[derive(Debug, Clone, Default)]
+struct MemoryStore {
+    entries: Arc<Mutex<HashMap<String, Versioned>>>,
+    down: Arc<AtomicBool>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Unavailable;
+
+impl MemoryStore {
+    fn check(&self) -> Result<(), Unavailable> {
+        if self.down.load(Ordering::SeqCst) {
+            Err(Unavailable)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<Versioned> {
+        self.entries.lock().unwrap().get(id).cloned()
+    }
+}
+
+impl StateStore for MemoryStore {
+    type Error = Unavailable;
+
+    async fn load(&self, id: &str) -> Result<Option<Versioned>, Unavailable> {
+        self.check()?;
+        Ok(self.get(id))
+    }
+
+    async fn save(&self, id: &str, state: &str) -> Result<u64, Unavailable> {
+        self.check()?;
+        let mut entries = self.entries.lock().unwrap();
+        let version = entries.get(id).map_or(0, |entry| entry.version) + 1;
+        let state = state.to_string();
+        entries.insert(id.to_string(), Versioned { state, version });
+        Ok(version)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        id: &str,
+        expected: u64,
+        state: &str,
+    ) -> Result<Swap, Unavailable> {
+        self.check()?;
+        let mut entries = self.entries.lock().unwrap();
+        let current = entries.get(id).cloned();
+        if current.as_ref().map_or(0, |entry| entry.version) != expected {
+            return Ok(Swap::Stale(current));
+        }
+        let version = expected + 1;
+        let state = state.to_string();
+        entries.insert(id.to_string(), Versioned { state, version });
+        Ok(Swap::Swapped(version))
+    }
+}
+
+type Shared = SharedMachine<DynamicOrder<()>, MemoryStore>;
+
+/// Open `order:1` on a new "node" sharing `store`.
+fn open(store: &MemoryStore) -> Shared {
+    block_on(Shared::open(
+        store.clone(),
+        "order:1",
+        DynamicOrder::new(()),
+    ))
+    .unwrap()
+}
+
+fn versioned(state: &str, version: u64) -> Versioned {
+    Versioned {
+        state: state.to_string(),
+        version,
+    }
+}
+
+#[test]
+fn opening_an_empty_key_stores_the_current_state() {
+    let store = MemoryStore::default();
+    let order = open(&store);
+    assert_eq!(order.version(), 1);
+    assert_eq!(order.id(), "order:1");
+    assert_eq!(store.get("order:1"), Some(versioned("Pending", 1)));
+}
+
+#[test]
+fn opening_a_stored_key_adopts_its_state() {
+    let store = MemoryStore::default();
+    block_on(store.save("order:1", "Paid")).unwrap();
+
+    let order = block_on(Shared::open(store, "order:1", DynamicOrder::new(()))).unwrap();
+    assert_eq!(order.machine().current_state(), "Paid");
+    assert_eq!(order.version(), 1);
+}
+
+#[test]
+fn transitions_bump_the_version() {
+    let store = MemoryStore::default();
+    let mut order = open(&store);
+    block_on(order.transition(OrderEvent::Pay)).unwrap();
+    block_on(order.transition(OrderEvent::Touch)).unwrap();
+
+    assert_eq!(order.version(), 3);
+    assert_eq!(store.get("order:1"), Some(versioned("Paid", 3)));
+}
+
+#[test]
+fn the_second_node_to_transition_gets_stale_state() {
+    let store = MemoryStore::default();
+    let mut first = open(&store);
+    let mut second = open(&store);
+
+    block_on(first.transition(OrderEvent::Pay)).unwrap();
+    let err = block_on(second.transition(OrderEvent::Pay)).unwrap_err();
+    assert_eq!(
+        err,
+        SharedError::StaleState {
+            stored: Some(versioned("Paid", 2)),
+            expected: 1,
+        }
+    );
+    assert_eq!(second.machine().current_state(), "Pending");
+    assert_eq!(store.get("order:1"), Some(versioned("Paid", 2)));
+
+    assert!(block_on(second.refresh()).unwrap());
+    block_on(second.transition(OrderEvent::Ship)).unwrap();
+    assert_eq!(store.get("order:1"), Some(versioned("Shipped", 3)));
+}
+
+#[test]
+fn losing_a_race_keeps_the_state_data() {
+    let store = MemoryStore::default();
+    let mut basket = DynamicBasket::new(());
+    basket.filling_data_mut().unwrap().skus.push(7);
+    let mut second = block_on(SharedMachine::open(store.clone(), "basket:1", basket)).unwrap();
+    let mut first = block_on(SharedMachine::open(
+        store.clone(),
+        "basket:1",
+        DynamicBasket::new(()),
+    ))
+    .unwrap();
+
+    block_on(first.transition(BasketEvent::CheckOut)).unwrap();
+    let err = block_on(second.transition(BasketEvent::CheckOut)).unwrap_err();
+
+    assert!(matches!(err, SharedError::StaleState { .. }));
+    assert!(second.machine().is_filling());
+    assert_eq!(second.machine().filling_data().unwrap().skus, [7]);
+}
+
+#[test]
+fn rejected_events_write_nothing() {
+    let store = MemoryStore::default();
+    let mut order = open(&store);
+    let err = block_on(order.transition(OrderEvent::Ship)).unwrap_err();
+    assert!(matches!(err, SharedError::Dynamic(_)));
+    assert_eq!(store.get("order:1"), Some(versioned("Pending", 1)));
+}
+
+#[test]
+fn store_failures_roll_back_the_machine() {
+    let store = MemoryStore::default();
+    let mut order = open(&store);
+    store.down.store(true, Ordering::SeqCst);
+
+    let err = block_on(order.transition(OrderEvent::Pay)).unwrap_err();
+    assert_eq!(err, SharedError::Store(Unavailable));
+    assert_eq!(order.machine().current_state(), "Pending");
+    assert_eq!(order.version(), 1);
+}
+
+#[test]
+fn unknown_stored_states_are_rejected() {
+    let store = MemoryStore::default();
+    block_on(store.save("order:1", "Refunded")).unwrap();
+
+    let err = block_on(Shared::open(store, "order:1", DynamicOrder::new(()))).unwrap_err();
+    assert_eq!(err, SharedError::UnknownState("Refunded".to_string()));
+}
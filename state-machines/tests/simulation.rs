@@ -0,0 +1,147 @@
+#![cfg(feature = "alloc")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use core::time::Duration;
+use state_machines::core::{Latency, Simulation, TransitionProfile};
+use state_machines::state_machine;
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+
+state_machine! {
+    name: Order,
+    dynamic: true,
+    initial: Placed,
+    states: [Placed, Paid, Shipped, Delivered, Cancelled],
+    events {
+        pay {
+            transition: { from: Placed, to: Paid }
+        }
+        cancel {
+            transition: { from: [Placed, Paid], to: Cancelled }
+        }
+        ship {
+            transition: { from: Paid, to: Shipped }
+        }
+        deliver {
+            transition: { from: Shipped, to: Delivered }
+        }
+    }
+}
+
+fn order_workflow() -> Simulation<'static, OrderState> {
+    Simulation::new(OrderState::definition())
+        .profile("pay", TransitionProfile::new(0.9, Latency::Fixed(HOUR)))
+        .profile(
+            "cancel",
+            TransitionProfile::new(0.1, Latency::Fixed(MINUTE)),
+        )
+        .profile(
+            "ship",
+            TransitionProfile::new(
+                1.0,
+                Latency::Uniform {
+                    min: 2 * HOUR,
+                    max: 4 * HOUR,
+                },
+            ),
+        )
+        .profile(
+            "deliver",
+            TransitionProfile::new(1.0, Latency::Fixed(24 * HOUR)),
+        )
+        // Paid orders are never cancelled
+        .profile_edge(
+            OrderState::Paid,
+            "cancel",
+            OrderState::Cancelled,
+            TransitionProfile::new(0.0, Latency::Fixed(MINUTE)),
+        )
+        .complete_at(&[OrderState::Delivered])
+}
+
+#[test]
+fn completion_rate_follows_the_weights() {
+    let report = order_workflow().run(10_000);
+
+    assert_eq!(report.runs, 10_000);
+    let rate = report.completion_rate();
+    assert!((0.88..0.92).contains(&rate), "completion rate {rate}");
+}
+
+#[test]
+fn completion_times_stay_within_the_latency_bounds() {
+    let report = order_workflow().run(1_000);
+
+    let fastest = report.percentile(0.0).unwrap();
+    let slowest = report.percentile(1.0).unwrap();
+    assert!(fastest >= 27 * HOUR);
+    assert!(slowest <= 29 * HOUR);
+
+    let median = report.percentile(0.5).unwrap();
+    let mean = report.mean_completion_time().unwrap();
+    assert!(median > 27 * HOUR + 30 * MINUTE && median < 28 * HOUR + 30 * MINUTE);
+    assert!(mean > 27 * HOUR + 30 * MINUTE && mean < 28 * HOUR + 30 * MINUTE);
+}
+
+#[test]
+fn residency_shows_where_orders_wait() {
+    let report = order_workflow().run(1_000);
+
+    let shipped = report.residency_share(&OrderState::Shipped);
+    assert!(shipped > 0.8, "shipped share {shipped}");
+    assert_eq!(report.residency_share(&OrderState::Delivered), 0.0);
+
+    let placed = &report.residency[0];
+    assert_eq!(placed.state, OrderState::Placed);
+    assert_eq!(placed.visits, 1_000);
+}
+
+#[test]
+fn default_completion_is_any_dead_end() {
+    let report = Simulation::new(OrderState::definition()).run(500);
+    assert_eq!(report.completed(), 500);
+    assert_eq!(report.mean_completion_time(), Some(Duration::ZERO));
+}
+
+state_machine! {
+    name: Poller,
+    dynamic: true,
+    initial: Waiting,
+    states: [Waiting, Done],
+    events {
+        poll {
+            transition: { from: Waiting, to: Waiting }
+        }
+        resolve {
+            transition: { from: Waiting, to: Done }
+        }
+    }
+}
+
+#[test]
+fn runs_give_up_after_max_steps() {
+    let report = Simulation::new(PollerState::definition())
+        .profile("poll", TransitionProfile::new(1.0, Latency::Fixed(MINUTE)))
+        .profile(
+            "resolve",
+            TransitionProfile::new(0.0, Latency::Fixed(MINUTE)),
+        )
+        .max_steps(3)
+        .run(100);
+
+    assert_eq!(report.completed(), 0);
+    assert_eq!(report.percentile(0.5), None);
+    assert_eq!(report.residency[0].visits, 400);
+    assert_eq!(report.residency[0].time, 300 * MINUTE);
+}
+
+#[test]
+fn the_same_seed_reproduces_the_report() {
+    assert_eq!(order_workflow().run(200), order_workflow().run(200));
+    assert_ne!(
+        order_workflow().seed(1).run(200),
+        order_workflow().seed(2).run(200)
+    );
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "postcard")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{SNAPSHOT_FORMAT, Snapshot, SnapshotCodecError, state_machine};
+
+state_machine! {
+    name: Heater,
+    dynamic: true,
+    initial: Off,
+    states: [Off, Warming, Holding],
+    state_derives: [serde::Serialize, serde::Deserialize],
+    events {
+        power_on {
+            transition: { from: Off, to: Warming }
+        }
+        reach_setpoint {
+            transition: { from: Warming, to: Holding }
+        }
+    }
+}
+
+fn warmed_up() -> DynamicHeater<()> {
+    let mut heater = DynamicHeater::new(());
+    heater.handle(HeaterEvent::PowerOn).unwrap();
+    heater.handle(HeaterEvent::ReachSetpoint).unwrap();
+    heater
+}
+
+#[test]
+fn snapshots_round_trip_in_a_few_bytes() {
+    let snapshot = warmed_up().snapshot();
+
+    let mut buf = [0; 32];
+    let len = snapshot.encode(&mut buf).unwrap();
+    assert_eq!(len, 8); // header, state, version, crc
+    assert_eq!(buf[0], SNAPSHOT_FORMAT);
+
+    let decoded = Snapshot::<HeaterState>::decode(&buf[..len]).unwrap();
+    assert_eq!(decoded, snapshot);
+    let heater = DynamicHeater::from_snapshot((), decoded);
+    assert_eq!(heater.state_enum(), HeaterState::Holding);
+    assert_eq!(heater.version(), 2);
+}
+
+#[test]
+fn damaged_records_are_rejected() {
+    let mut buf = [0; 32];
+    let len = warmed_up().snapshot().encode(&mut buf).unwrap();
+
+    let mut flipped = buf;
+    flipped[2] ^= 1;
+    assert_eq!(
+        Snapshot::<HeaterState>::decode(&flipped[..len]),
+        Err(SnapshotCodecError::Corrupted)
+    );
+    assert_eq!(
+        Snapshot::<HeaterState>::decode(&buf[..len - 1]),
+        Err(SnapshotCodecError::Corrupted)
+    );
+
+    // Erased flash reads back as 0xFF
+    assert_eq!(
+        Snapshot::<HeaterState>::decode(&[0xFF; 16]),
+        Err(SnapshotCodecError::UnknownFormat(0xFF))
+    );
+}
+
+#[test]
+fn small_buffers_are_reported() {
+    let mut buf = [0; 6];
+    assert_eq!(
+        warmed_up().snapshot().encode(&mut buf),
+        Err(SnapshotCodecError::BufferTooSmall)
+    );
+}
+
+#[cfg(feature = "embedded-storage")]
+mod storage {
+    use super::*;
+    use embedded_storage::{ReadStorage, Storage};
+    use state_machines::PersistError;
+
+    /// An EEPROM stand-in.
+    struct Eeprom([u8; 128]);
+
+    #[derive(Debug, PartialEq)]
+    struct OutOfBounds;
+
+    impl ReadStorage for Eeprom {
+        type Error = OutOfBounds;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), OutOfBounds> {
+            let start = offset as usize;
+            let cells = self.0.get(start..start + bytes.len()).ok_or(OutOfBounds)?;
+            bytes.copy_from_slice(cells);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl Storage for Eeprom {
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), OutOfBounds> {
+            let start = offset as usize;
+            let cells = self
+                .0
+                .get_mut(start..start + bytes.len())
+                .ok_or(OutOfBounds)?;
+            cells.copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn persists_to_and_loads_from_storage() {
+        let mut eeprom = Eeprom([0xFF; 128]);
+        let snapshot = warmed_up().snapshot();
+
+        snapshot.persist_to(&mut eeprom, 16).unwrap();
+        assert_eq!(Snapshot::load_from(&mut eeprom, 16), Ok(snapshot));
+    }
+
+    #[test]
+    fn reports_blank_and_failing_storage() {
+        let mut eeprom = Eeprom([0xFF; 128]);
+
+        assert_eq!(
+            Snapshot::<HeaterState>::load_from(&mut eeprom, 0),
+            Err(PersistError::Codec(SnapshotCodecError::UnknownFormat(0xFF)))
+        );
+        assert_eq!(
+            warmed_up().snapshot().persist_to(&mut eeprom, 124),
+            Err(PersistError::Storage(OutOfBounds))
+        );
+    }
+}
@@ -0,0 +1,108 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{Snapshot, VersionMismatch, state_machine};
+
+state_machine! {
+    name: Turnstile,
+    dynamic: true,
+    unhandled: ignore,
+    on_error: { recover: reset },
+    initial: Locked,
+    states: [Locked, Unlocked, Broken],
+    events {
+        coin {
+            transition: { from: Locked, to: Unlocked }
+        }
+        push {
+            transition: { from: Unlocked, to: Locked }
+        }
+        kick {
+            guards: [never],
+            transition: { from: Locked, to: Broken }
+        }
+        reset {
+            transition: { from: [Locked, Unlocked, Broken], to: Locked }
+        }
+        nudge {
+            transition: { from: Unlocked, to: Unlocked }
+        }
+    }
+}
+
+impl<C, S> Turnstile<C, S> {
+    fn never(&self, _ctx: &C) -> bool {
+        false
+    }
+}
+
+fn snapshot(state: TurnstileState, version: u64) -> Snapshot<TurnstileState> {
+    Snapshot { state, version }
+}
+
+#[test]
+fn successful_transitions_bump_the_version() {
+    let mut turnstile = DynamicTurnstile::new(());
+    assert_eq!(turnstile.version(), 0);
+
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+    turnstile.handle(TurnstileEvent::Nudge).unwrap();
+    assert_eq!(turnstile.version(), 2);
+    assert_eq!(turnstile.snapshot(), snapshot(TurnstileState::Unlocked, 2));
+}
+
+#[test]
+fn ignored_events_leave_the_version_alone() {
+    let mut turnstile = DynamicTurnstile::new(());
+    turnstile.handle(TurnstileEvent::Push).unwrap();
+    turnstile.handle_or_ignore(TurnstileEvent::Nudge).unwrap();
+    assert_eq!(turnstile.version(), 0);
+}
+
+#[test]
+fn recovery_transitions_count() {
+    let mut turnstile = DynamicTurnstile::new(());
+    // The guard rejects `kick`, and `reset` recovers from Locked to Locked
+    assert!(turnstile.handle(TurnstileEvent::Kick).is_err());
+    assert_eq!(turnstile.version(), 1);
+}
+
+#[test]
+fn restore_if_version_applies_matching_snapshots() {
+    let mut turnstile = DynamicTurnstile::new(());
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+
+    turnstile
+        .restore_if_version(snapshot(TurnstileState::Broken, 7), 1)
+        .unwrap();
+    assert_eq!(turnstile.current_state(), "Broken");
+    assert_eq!(turnstile.version(), 7);
+}
+
+#[test]
+fn restore_if_version_rejects_moved_machines() {
+    let mut turnstile = DynamicTurnstile::new(());
+    let before = turnstile.snapshot();
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+
+    let err = turnstile.restore_if_version(before, 0).unwrap_err();
+    assert_eq!(
+        err,
+        VersionMismatch {
+            expected: 0,
+            actual: 1
+        }
+    );
+    assert_eq!(err.to_string(), "expected machine version 0, found 1");
+    assert_eq!(turnstile.snapshot(), snapshot(TurnstileState::Unlocked, 1));
+}
+
+#[test]
+fn from_snapshot_resumes_at_the_snapshot_version() {
+    let mut turnstile = DynamicTurnstile::from_snapshot((), snapshot(TurnstileState::Unlocked, 41));
+    turnstile.handle(TurnstileEvent::Push).unwrap();
+    assert_eq!(turnstile.snapshot(), snapshot(TurnstileState::Locked, 42));
+
+    let typed = turnstile.into_locked().unwrap();
+    assert_eq!(typed.into_dynamic().version(), 0);
+}
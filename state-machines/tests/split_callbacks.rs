@@ -0,0 +1,79 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Calibration {
+    pub offset: i32,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Window {
+    pub samples: Vec<i32>,
+    pub sealed: bool,
+}
+
+state_machine! {
+    name: Sensor,
+    context: Calibration,
+    initial: Idle,
+    states: [Idle, Sampling(Window), Reporting(Window)],
+    events {
+        start {
+            after: [{ name: reset_window, split: true }],
+            transition: { from: Idle, to: Sampling }
+        }
+        sample {
+            payload: i32,
+            after: [{ name: record, split: true }],
+            transition: { from: Sampling, to: Sampling, init: carry_window }
+        }
+        report {
+            before: [{ name: seal, split: true }],
+            transition: { from: Sampling, to: Reporting, init: seal_window }
+        }
+    }
+}
+
+impl<S> Sensor<S> {
+    /// Writes the target state's data while reading the context, which a
+    /// `&mut self` callback could only do through `self` twice.
+    fn record(window: &mut Window, calibration: &Calibration, reading: &i32) {
+        window.samples.push(reading + calibration.offset);
+    }
+
+    fn reset_window(window: &mut Window, calibration: &Calibration) {
+        window.samples.push(calibration.offset);
+    }
+
+    fn seal(window: &mut Window, _calibration: &Calibration) {
+        window.sealed = true;
+    }
+}
+
+impl Sensor<Sampling> {
+    fn carry_window(&self, _calibration: &Calibration, _reading: &i32) -> Window {
+        self.sampling_data().clone()
+    }
+
+    fn seal_window(&self, _calibration: &Calibration) -> Window {
+        self.sampling_data().clone()
+    }
+}
+
+#[test]
+fn split_callbacks_borrow_state_data_and_context_separately() {
+    let sampling = Sensor::new(Calibration { offset: 10 }).start().unwrap();
+    assert_eq!(sampling.sampling_data().samples, [10]);
+
+    let sampling = sampling.sample(1).unwrap().sample(2).unwrap();
+    assert_eq!(sampling.sampling_data().samples, [10, 11, 12]);
+
+    // `before` callbacks get the source state's data, here carried over by
+    // the target's `init:`
+    let reporting = sampling.report().unwrap();
+    assert!(reporting.reporting_data().sealed);
+    assert_eq!(reporting.reporting_data().samples, [10, 11, 12]);
+}
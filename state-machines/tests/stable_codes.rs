@@ -0,0 +1,83 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+// Codes follow the wire protocol, not declaration order
+state_machine! {
+    name: Valve,
+    dynamic: true,
+    initial: Closed,
+    states: [
+        Open = 2,
+        Closed = 1,
+        superstate Fault {
+            state Jammed = 10,
+            state Leaking = 11,
+        },
+    ],
+    events {
+        open = 1 {
+            transition: { from: Closed, to: Open }
+        }
+        close = 2 {
+            transition: { from: Open, to: Closed }
+        }
+        report = 7 {
+            payload: u16,
+            transition: { from: [Open, Closed], to: Jammed }
+        }
+    }
+}
+
+#[test]
+fn states_round_trip_through_their_codes() {
+    assert_eq!(ValveState::Closed.as_code(), 1);
+    assert_eq!(ValveState::Leaking.as_code(), 11);
+
+    for state in ValveState::definition().states {
+        assert_eq!(ValveState::from_code(state.as_code()), Some(*state));
+    }
+    assert_eq!(ValveState::from_code(3), None);
+}
+
+#[test]
+fn events_round_trip_through_their_codes() {
+    assert_eq!(ValveEvent::Open.as_code(), 1);
+    assert_eq!(ValveEvent::Report(500).as_code(), 7);
+
+    assert!(matches!(ValveEvent::from_code(2), Some(ValveEvent::Close)));
+    // A payload can't be conjured from a code
+    assert!(ValveEvent::from_code(7).is_none());
+}
+
+#[test]
+fn decoded_events_drive_the_machine() {
+    let mut valve = DynamicValve::new(());
+
+    let event = ValveEvent::from_code(1).unwrap();
+    valve.handle(event).unwrap();
+    assert_eq!(valve.state_enum().as_code(), 2);
+}
+
+// The struct form takes event codes as a `code` key
+state_machine!(Machine {
+    name: Latch,
+    dynamic: true,
+    initial: Released,
+    states: [Released = 0, Held = 1],
+    events: [Event {
+        name: hold,
+        code: 4,
+        transition: Transition {
+            from: Released,
+            to: Held
+        }
+    }],
+});
+
+#[test]
+fn struct_form_codes() {
+    assert_eq!(LatchState::Held.as_code(), 1);
+    assert!(matches!(LatchEvent::from_code(4), Some(LatchEvent::Hold)));
+}
@@ -0,0 +1,106 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+/// No `Default`: a connection only exists once it has a socket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub socket: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub reason: &'static str,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Dialer {
+    pub next_socket: u32,
+}
+
+state_machine! {
+    name: Link,
+    context: Dialer,
+    dynamic: true,
+    data_states: true,
+    no_default_init: true,
+    state_derives: [PartialEq],
+    initial: Down,
+    states: [Down, Up(Connection), Failed(Failure)],
+    events {
+        connect {
+            transition: { from: Down, to: Up, init: open_socket }
+        }
+        adopt {
+            payload: Connection,
+            transition: { from: Down, to: Up }
+        }
+        fail {
+            payload: &'static str,
+            transition: { from: [Down, Up], to: Failed, init: failure }
+        }
+        reset {
+            transition: { from: Failed, to: Down }
+        }
+    }
+}
+
+impl<S> Link<S> {
+    fn open_socket(&self, dialer: &Dialer) -> Connection {
+        Connection {
+            socket: dialer.next_socket,
+        }
+    }
+
+    fn failure(&self, _dialer: &Dialer, reason: &&'static str) -> Failure {
+        Failure { reason }
+    }
+}
+
+#[test]
+fn init_builds_the_target_data() {
+    let link = Link::new(Dialer { next_socket: 7 });
+
+    let link = link.connect().unwrap();
+    assert_eq!(link.up_data(), &Connection { socket: 7 });
+
+    let link = link.fail("timeout").unwrap();
+    assert_eq!(link.failed_data().reason, "timeout");
+}
+
+#[test]
+fn a_payload_of_the_data_type_is_moved_in() {
+    let link = Link::new(Dialer::default());
+
+    let link = link.adopt(Connection { socket: 3 }).unwrap();
+    assert_eq!(link.up_data().socket, 3);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn dynamic_dispatch_and_dry_runs_build_the_same_data() {
+    let mut link = DynamicLink::new(Dialer { next_socket: 9 });
+
+    let report = link.simulate(&[LinkEvent::Adopt(Connection { socket: 1 })]);
+    assert_eq!(
+        report.final_state(),
+        &LinkState::Up(Connection { socket: 1 })
+    );
+
+    link.handle(LinkEvent::Connect).unwrap();
+    assert_eq!(link.state_enum(), LinkState::Up(Connection { socket: 9 }));
+}
+
+#[test]
+fn restoring_takes_the_data_from_the_state() {
+    let link = DynamicLink::restore(
+        Dialer::default(),
+        LinkState::Failed(Failure { reason: "dns" }),
+    );
+
+    assert_eq!(
+        link.state_enum(),
+        LinkState::Failed(Failure { reason: "dns" })
+    );
+}
@@ -0,0 +1,66 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Airlock,
+    dynamic: true,
+    initial: Sealed,
+    states: [
+        Sealed,
+        superstate Cycling {
+            state Pressurizing,
+            state Venting,
+        },
+        Open,
+    ],
+    events {
+        pressurize {
+            transition: { from: Sealed, to: Pressurizing }
+        }
+        vent {
+            transition: { from: Pressurizing, to: Venting }
+        }
+        open {
+            transition: { from: Cycling, to: Open }
+        }
+    }
+}
+
+#[test]
+fn state_enum_predicates() {
+    assert!(AirlockState::Sealed.is_sealed());
+    assert!(!AirlockState::Sealed.is_open());
+    assert!(AirlockState::Venting.within_cycling());
+    assert!(!AirlockState::Open.within_cycling());
+}
+
+#[test]
+fn any_state_predicates() {
+    let airlock = DynamicAirlock::new(());
+    let typed = airlock.into_typed();
+    assert!(typed.is_sealed());
+    assert!(!typed.within_cycling());
+
+    let typed = AnyAirlockState::from(Airlock::new(()).pressurize().unwrap());
+    assert!(typed.is_pressurizing());
+    assert!(typed.within_cycling());
+}
+
+#[test]
+fn dynamic_predicates_follow_the_current_state() {
+    let mut airlock = DynamicAirlock::new(());
+    assert!(airlock.is_sealed());
+    assert!(!airlock.within_cycling());
+
+    airlock.handle(AirlockEvent::Pressurize).unwrap();
+    airlock.handle(AirlockEvent::Vent).unwrap();
+    assert!(airlock.is_venting());
+    assert!(airlock.within_cycling());
+    assert!(airlock.is_cycling());
+
+    airlock.handle(AirlockEvent::Open).unwrap();
+    assert!(airlock.is_open());
+    assert!(!airlock.within_cycling());
+}
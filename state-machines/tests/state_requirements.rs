@@ -0,0 +1,137 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use state_machines::state_machine;
+
+/// Capabilities a board may or may not have.
+pub trait Radio {
+    fn key_up(&mut self);
+}
+
+pub trait Gps {
+    fn has_fix(&self) -> bool;
+}
+
+/// The flight board has both.
+#[derive(Debug, Default)]
+pub struct FlightBoard {
+    pub keyed: u32,
+}
+
+impl Radio for FlightBoard {
+    fn key_up(&mut self) {
+        self.keyed += 1;
+    }
+}
+
+impl Gps for FlightBoard {
+    fn has_fix(&self) -> bool {
+        true
+    }
+}
+
+/// The bench board only has a GPS receiver.
+#[derive(Debug, Default)]
+pub struct BenchBoard;
+
+impl Gps for BenchBoard {
+    fn has_fix(&self) -> bool {
+        false
+    }
+}
+
+state_machine! {
+    name: Beacon,
+    initial: Idle,
+    states: [
+        Idle,
+        Transmitting { requires: [Radio] },
+        superstate Navigating {
+            requires: [Gps],
+            state Locating,
+            state Tracking { requires: [Radio] },
+        },
+    ],
+    events {
+        transmit {
+            transition: { from: Idle, to: Transmitting }
+        }
+        locate {
+            transition: { from: Idle, to: Locating }
+        }
+        track {
+            guards: [has_fix],
+            transition: { from: Locating, to: Tracking }
+        }
+        stop {
+            transition: { from: [Transmitting, Locating, Tracking], to: Idle }
+        }
+    }
+}
+
+impl<C: Gps, S> Beacon<C, S> {
+    fn has_fix(&self, ctx: &C) -> bool {
+        ctx.has_fix()
+    }
+}
+
+#[test]
+fn a_board_with_every_capability_enters_every_state() {
+    let mut transmitting = Beacon::new(FlightBoard::default()).transmit().unwrap();
+    // Once in the state, the capability is there to use
+    transmitting.ctx.key_up();
+    let tracking = transmitting
+        .stop()
+        .unwrap()
+        .locate()
+        .unwrap()
+        .track()
+        .unwrap();
+    assert_eq!(tracking.ctx.keyed, 1);
+}
+
+#[test]
+fn a_board_enters_the_states_its_capabilities_allow() {
+    // `BenchBoard` has no `Radio`, so `transmit()` and `track()` don't
+    // compile for it; `locate()` only needs the superstate's `Gps`
+    let locating = Beacon::new(BenchBoard).locate().unwrap();
+    assert!(locating.stop().is_ok());
+}
+
+/// With a concrete context the runtime wrapper works too, as long as the
+/// context has every capability.
+mod concrete {
+    use super::{FlightBoard, Radio};
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Transmitter,
+        dynamic: true,
+        context: FlightBoard,
+        initial: Idle,
+        states: [Idle, Transmitting { requires: [Radio] }],
+        events {
+            transmit {
+                after: [key_up],
+                transition: { from: Idle, to: Transmitting }
+            }
+        }
+    }
+
+    impl<S> Transmitter<S> {
+        fn key_up(&mut self) {
+            self.ctx.key_up();
+        }
+    }
+
+    #[test]
+    fn the_runtime_wrapper_enters_states_the_context_can_support() {
+        let transmitting = Transmitter::new(FlightBoard::default()).transmit().unwrap();
+        assert_eq!(transmitting.ctx.keyed, 1);
+
+        let mut dynamic = DynamicTransmitter::new(FlightBoard::default());
+        dynamic.handle(TransmitterEvent::Transmit).unwrap();
+        assert_eq!(dynamic.current_state(), "Transmitting");
+    }
+}
@@ -0,0 +1,122 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{EventFailure, state_machine};
+
+#[derive(Debug, Default)]
+pub struct Line {
+    pub connected: bool,
+}
+
+state_machine! {
+    name: Modem,
+    dynamic: true,
+    context: Line,
+    stats: true,
+    on_error: { handler: ignore_failure, recover: reset, after: 3 },
+    initial: Offline,
+    states: [Offline, Dialing, Online],
+    events {
+        dial {
+            transition: { from: [Offline, Dialing], to: Dialing }
+        }
+        connect {
+            guards: [carrier],
+            transition: { from: Dialing, to: Online }
+        }
+        hang_up {
+            transition: { from: Online, to: Offline }
+        }
+        reset {
+            transition: { from: [Offline, Dialing, Online], to: Offline }
+        }
+    }
+}
+
+impl<S> Modem<S> {
+    fn carrier(&self, line: &Line) -> bool {
+        line.connected
+    }
+
+    fn ignore_failure(&self, _failure: EventFailure<'_>) {}
+}
+
+#[cfg(not(feature = "stats"))]
+#[test]
+fn without_the_feature_nothing_is_counted() {
+    let mut modem = DynamicModem::new(Line::default());
+    modem.handle(ModemEvent::Dial).unwrap();
+    assert!(modem.handle(ModemEvent::Connect).is_err());
+
+    let stats = modem.stats();
+    assert_eq!(stats.transitions(), 0);
+    assert_eq!(stats.guard_rejections(), 0);
+    assert_eq!(stats.get("Dialing").unwrap().entries, 0);
+}
+
+#[cfg(feature = "stats")]
+mod counting {
+    use super::*;
+    use state_machines::StateStats;
+
+    #[test]
+    fn transitions_count_an_exit_and_an_entry() {
+        let mut modem = DynamicModem::new(Line { connected: true });
+        modem.handle(ModemEvent::Dial).unwrap();
+        modem.handle(ModemEvent::Dial).unwrap();
+        modem.handle(ModemEvent::Connect).unwrap();
+
+        let stats = modem.stats();
+        assert_eq!(
+            stats.get("Dialing"),
+            Some(&StateStats {
+                entries: 2,
+                exits: 2,
+                guard_rejections: 0
+            })
+        );
+        assert_eq!(stats.get("Offline").unwrap().exits, 1);
+        assert_eq!(stats.get("Online").unwrap().entries, 1);
+        assert_eq!(stats.transitions(), 3);
+        assert_eq!(stats.get("Nowhere"), None);
+    }
+
+    #[test]
+    fn guard_rejections_are_counted_in_the_current_state() {
+        let mut modem = DynamicModem::new(Line::default());
+        modem.handle(ModemEvent::Dial).unwrap();
+        assert!(modem.handle(ModemEvent::Connect).is_err());
+
+        // Invalid transitions aren't guard rejections
+        assert!(modem.handle(ModemEvent::HangUp).is_err());
+
+        let stats = modem.stats();
+        assert_eq!(stats.get("Dialing").unwrap().guard_rejections, 1);
+        assert_eq!(stats.guard_rejections(), 1);
+    }
+
+    #[test]
+    fn recovery_transitions_are_counted() {
+        let mut modem = DynamicModem::new(Line::default());
+        modem.handle(ModemEvent::Dial).unwrap();
+        for _ in 0..3 {
+            assert!(modem.handle(ModemEvent::Connect).is_err());
+        }
+        assert!(modem.is_offline());
+
+        let stats = modem.stats();
+        assert_eq!(stats.get("Dialing").unwrap().exits, 1);
+        assert_eq!(stats.get("Offline").unwrap().entries, 1);
+    }
+
+    #[test]
+    fn reset_zeroes_the_counters() {
+        let mut modem = DynamicModem::new(Line::default());
+        modem.handle(ModemEvent::Dial).unwrap();
+        modem.reset_stats();
+
+        assert_eq!(modem.stats().transitions(), 0);
+        let names: Vec<_> = modem.stats().iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["Offline", "Dialing", "Online"]);
+    }
+}
@@ -0,0 +1,122 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Plant {
+    pub fuel: bool,
+}
+
+// The same machine as `definition_graph.rs`, in the struct form
+state_machine!(Machine {
+    name: Reactor,
+    dynamic: true,
+    context: Plant,
+    initial: Cold,
+    states: [
+        Cold,
+        Superstate {
+            name: Running,
+            initial: Warming,
+            states: [Warming, Critical]
+        },
+        Venting {
+            doc: "Releasing pressure"
+        },
+        Shutdown
+    ],
+    events: [
+        Event {
+            name: ignite,
+            guards: [fuel_loaded],
+            transition: Transition {
+                from: Cold,
+                to: Running
+            }
+        },
+        Event {
+            name: overheat,
+            transition: Transition {
+                from: Warming,
+                to: Critical
+            }
+        },
+        Event {
+            name: vent,
+            after: [Callback {
+                name: log_vent,
+                from: Critical
+            }],
+            transition: Transition {
+                from: Running,
+                to: Venting
+            }
+        },
+        Event {
+            name: cool,
+            transition: Transition {
+                from: Venting,
+                to: Cold
+            }
+        },
+        Event {
+            name: scram,
+            payload: "Option<u8>",
+            transitions: [
+                Transition {
+                    from: [Cold, Running],
+                    to: Shutdown
+                },
+                Transition {
+                    from: Venting,
+                    to: Shutdown
+                }
+            ]
+        },
+    ],
+});
+
+impl<S> Reactor<S> {
+    fn fuel_loaded(&self, plant: &Plant) -> bool {
+        plant.fuel
+    }
+
+    fn log_vent(&self) {}
+}
+
+#[test]
+fn the_struct_form_defines_the_same_machine() {
+    let definition = ReactorState::definition();
+    assert_eq!(definition.name, "Reactor");
+    assert_eq!(
+        definition.states,
+        &[
+            ReactorState::Cold,
+            ReactorState::Warming,
+            ReactorState::Critical,
+            ReactorState::Venting,
+            ReactorState::Shutdown
+        ]
+    );
+    assert_eq!(definition.superstates[0].name, "Running");
+
+    let scram = &definition.events[4];
+    // Spliced from a string, so the type comes back spaced out
+    assert_eq!(
+        scram.payload.map(|ty| ty.replace(' ', "")).as_deref(),
+        Some("Option<u8>")
+    );
+    assert_eq!(scram.transitions.len(), 2);
+}
+
+#[test]
+fn struct_form_machines_run() {
+    let mut reactor = DynamicReactor::new(Plant { fuel: true });
+    reactor.handle(ReactorEvent::Ignite).unwrap();
+    assert!(reactor.is_warming());
+    reactor.handle(ReactorEvent::Overheat).unwrap();
+    reactor.handle(ReactorEvent::Vent).unwrap();
+    reactor.handle(ReactorEvent::Scram(Some(3))).unwrap();
+    assert!(reactor.is_shutdown());
+}
@@ -0,0 +1,156 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{SubstateOf, state_machine};
+
+#[derive(Debug, Default)]
+pub struct Range {
+    pub abort_armed: bool,
+    pub crew_override: bool,
+}
+
+state_machine! {
+    name: Booster,
+    context: Range,
+    initial: Pad,
+    states: [
+        Pad,
+        superstate Ascent {
+            state StageOne,
+            state StageTwo,
+        },
+        Recovered,
+    ],
+    events {
+        ignite {
+            transition: { from: Pad, to: Ascent }
+        }
+        separate {
+            transition: { from: StageOne, to: StageTwo }
+        }
+        abort {
+            guards: [abort_armed],
+            unless: [crew_override],
+            transition: { from: Ascent, to: Recovered }
+        }
+        telemetry {
+            payload: u32,
+            guards: [in_range],
+            transition: { from: Ascent, to: Ascent }
+        }
+    }
+}
+
+impl<S> Booster<S> {
+    fn abort_armed(&self, range: &Range) -> bool {
+        range.abort_armed
+    }
+
+    fn crew_override(&self, range: &Range) -> bool {
+        range.crew_override
+    }
+
+    fn in_range(&self, _range: &Range, altitude: &u32) -> bool {
+        *altitude < 100_000
+    }
+}
+
+// Generic over the substate, so only the superstate probes are available
+fn abort_is_possible<S: SubstateOf<Ascent>>(booster: &Booster<S>) -> bool {
+    booster.can_abort()
+}
+
+#[test]
+fn probes_evaluate_guards_from_any_substate() {
+    let range = Range {
+        abort_armed: true,
+        crew_override: false,
+    };
+    let booster = Booster::new(range).ignite().unwrap();
+    assert!(abort_is_possible(&booster));
+
+    let booster = booster.separate().unwrap();
+    assert!(abort_is_possible(&booster));
+    assert!(booster.abort().is_ok());
+}
+
+#[test]
+fn probes_respect_guards_and_unless() {
+    let booster = Booster::new(Range::default()).ignite().unwrap();
+    assert!(!booster.can_abort());
+
+    let range = Range {
+        abort_armed: true,
+        crew_override: true,
+    };
+    let booster = Booster::new(range).ignite().unwrap().separate().unwrap();
+    assert!(!booster.can_abort());
+    assert!(booster.abort().is_err());
+}
+
+#[test]
+fn probes_take_the_event_payload() {
+    let booster = Booster::new(Range::default()).ignite().unwrap();
+    assert!(booster.can_telemetry(&42_000));
+    assert!(!booster.can_telemetry(&250_000));
+}
+
+state_machine! {
+    name: Lander,
+    context: Range,
+    dynamic: true,
+    initial: Orbit,
+    states: [
+        Orbit,
+        superstate Descent {
+            state Braking,
+            state Hover,
+        },
+        Landed,
+    ],
+    events {
+        deorbit {
+            transition: { from: Orbit, to: Descent }
+        }
+        hover {
+            transition: { from: Braking, to: Hover }
+        }
+        abort {
+            guards: [abort_armed],
+            transition: { from: Descent, to: Orbit }
+        }
+    }
+}
+
+impl<S> Lander<S> {
+    fn abort_armed(&self, range: &Range) -> bool {
+        range.abort_armed
+    }
+}
+
+#[test]
+fn probes_and_dynamic_dispatch_pick_the_same_transition() {
+    let range = Range {
+        abort_armed: true,
+        crew_override: false,
+    };
+    let lander = Lander::new(range).deorbit().unwrap().hover().unwrap();
+    assert!(lander.can_abort());
+
+    let mut lander = DynamicLander::new(Range {
+        abort_armed: true,
+        crew_override: false,
+    });
+    lander.handle(LanderEvent::Deorbit).unwrap();
+    lander.handle(LanderEvent::Hover).unwrap();
+    assert!(lander.can_handle(&LanderEvent::Abort));
+    assert_eq!(lander.available_events().collect::<Vec<_>>(), ["abort"]);
+    assert!(lander.handle(LanderEvent::Abort).is_ok());
+    assert_eq!(lander.current_state(), "Orbit");
+
+    let mut lander = DynamicLander::new(Range::default());
+    lander.handle(LanderEvent::Deorbit).unwrap();
+    assert!(lander.can_handle(&LanderEvent::Abort));
+    assert_eq!(lander.available_events().collect::<Vec<_>>(), ["hover"]);
+    assert!(lander.handle(LanderEvent::Abort).is_err());
+}
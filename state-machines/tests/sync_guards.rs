@@ -0,0 +1,102 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use pollster::block_on;
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Link {
+    pub configured: bool,
+    pub reachable: bool,
+}
+
+state_machine! {
+    name: Uplink,
+    async: true,
+    dynamic: true,
+    context: Link,
+    initial: Offline,
+    states: [
+        Offline,
+        superstate Connected {
+            state Idle,
+            state Streaming,
+        },
+    ],
+    events {
+        connect {
+            guards: [configured { sync: true }, reachable],
+            transition: { from: Offline, to: Connected }
+        }
+        stream {
+            guards: [configured && !paused { sync: true }],
+            transition: { from: Idle, to: Streaming }
+        }
+        disconnect {
+            unless: [paused],
+            transition: { from: Connected, to: Offline }
+        }
+    }
+}
+
+impl<S> Uplink<S> {
+    // Plain in-memory checks: never awaited
+    fn configured(&self, link: &Link) -> bool {
+        link.configured
+    }
+
+    fn paused(&self, _link: &Link) -> bool {
+        false
+    }
+
+    // A genuinely async guard
+    async fn reachable(&self, link: &Link) -> bool {
+        link.reachable
+    }
+}
+
+#[test]
+fn sync_and_async_guards_mix_in_async_transitions() {
+    let offline = Uplink::new(Link {
+        configured: true,
+        reachable: false,
+    });
+    let (_offline, err) = block_on(offline.connect()).unwrap_err();
+    assert_eq!(err.guard, "reachable");
+
+    let offline = Uplink::new(Link {
+        configured: true,
+        reachable: true,
+    });
+    let idle = block_on(offline.connect()).unwrap();
+    let streaming = block_on(idle.stream()).unwrap();
+    let _offline = block_on(streaming.disconnect()).unwrap();
+}
+
+#[test]
+fn sync_marks_apply_to_every_event_using_the_guard() {
+    let mut uplink = DynamicUplink::new(Link {
+        configured: true,
+        reachable: true,
+    });
+    block_on(uplink.handle(UplinkEvent::Connect)).unwrap();
+
+    let events: Vec<_> = block_on(uplink.available_events()).collect();
+    assert_eq!(events, ["stream", "disconnect"]);
+
+    block_on(uplink.handle(UplinkEvent::Stream)).unwrap();
+    assert!(uplink.is_streaming());
+}
+
+#[test]
+fn superstate_probes_skip_awaiting_sync_guards() {
+    let idle = block_on(
+        Uplink::new(Link {
+            configured: true,
+            reachable: true,
+        })
+        .connect(),
+    )
+    .unwrap();
+    assert!(block_on(idle.can_disconnect()));
+}
@@ -0,0 +1,130 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use core::time::Duration;
+use state_machines::state_machine;
+
+#[derive(Debug, Default)]
+pub struct Attempts {
+    pub failures: u32,
+}
+
+state_machine! {
+    name: Uplink,
+    extendable: true,
+    dynamic: true,
+    watchdog: true,
+    context: Attempts,
+    params: { MaxRetries: 3, ConnectTimeout: 30s },
+    initial: Idle,
+    states: [Idle, Connecting { max_duration: ConnectTimeout }, Online, Offline],
+    events {
+        connect {
+            transition: { from: Idle, to: Connecting }
+        }
+        connected {
+            transition: { from: Connecting, to: Online }
+        }
+        retry {
+            guards: [failures_below(MaxRetries)],
+            transition: { from: Connecting, to: Connecting }
+        }
+        give_up {
+            transition: { from: Connecting, to: Offline }
+        }
+    }
+}
+
+impl<S> Uplink<S> {
+    fn failures_below(&self, attempts: &Attempts, limit: u32) -> bool {
+        attempts.failures < limit
+    }
+}
+
+#[test]
+fn params_are_substituted_into_guards_and_durations() {
+    assert_eq!(
+        UplinkState::Connecting.max_duration(),
+        Some(Duration::from_secs(30))
+    );
+
+    let connecting = Uplink::new(Attempts { failures: 2 }).connect().unwrap();
+    assert!(connecting.retry().is_ok());
+    let connecting = Uplink::new(Attempts { failures: 3 }).connect().unwrap();
+    let (_connecting, err) = connecting.retry().unwrap_err();
+    assert_eq!(err.guard, "failures_below(3)");
+}
+
+/// A deployment with its own constants; a module of its own, since it
+/// declares the same state types.
+mod satellite {
+    use super::Attempts;
+    use core::time::Duration;
+    use state_machines::state_machine;
+
+    // `MaxRetries` stays the base's; the gated entry wins over the default
+    state_machine! {
+        name: SatelliteUplink,
+        extends: super::Uplink,
+        params: {
+            ConnectTimeout: 10s,
+            #[cfg(test)]
+            ConnectTimeout: 2s,
+        },
+    }
+
+    impl<S> SatelliteUplink<S> {
+        fn failures_below(&self, attempts: &Attempts, limit: u32) -> bool {
+            attempts.failures < limit
+        }
+    }
+
+    #[test]
+    fn extensions_replace_params_by_name() {
+        assert_eq!(
+            SatelliteUplinkState::Connecting.max_duration(),
+            Some(Duration::from_secs(2))
+        );
+        let connecting = SatelliteUplink::new(Attempts { failures: 3 })
+            .connect()
+            .unwrap();
+        assert!(connecting.retry().is_err());
+    }
+}
+
+mod struct_form {
+    use core::time::Duration;
+    use state_machines::state_machine;
+
+    state_machine!(Machine {
+        name: Pump,
+        dynamic: true,
+        watchdog: true,
+        params: Params {
+            PrimeTimeout: 500ms
+        },
+        initial: Idle,
+        states: [
+            Idle,
+            Priming {
+                max_duration: PrimeTimeout
+            }
+        ],
+        events: [Event {
+            name: prime,
+            transition: Transition {
+                from: Idle,
+                to: Priming
+            },
+        }],
+    });
+
+    #[test]
+    fn struct_form_takes_a_params_block() {
+        assert_eq!(
+            PumpState::Priming.max_duration(),
+            Some(Duration::from_millis(500))
+        );
+    }
+}
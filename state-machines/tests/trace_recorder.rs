@@ -0,0 +1,156 @@
+//! Invocation-order traces from the `trace` feature.
+#![cfg(feature = "trace")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{
+    core::{AroundOutcome, AroundStage},
+    state_machine,
+    trace::{TraceRecorder, TraceStep},
+};
+
+#[derive(Debug, Default)]
+pub struct Pad {
+    pub fuel: u8,
+}
+
+state_machine! {
+    name: Rocket,
+    dynamic: true,
+    context: Pad,
+    initial: Docked,
+    states: [Docked, InFlight],
+    events {
+        launch {
+            around: [countdown],
+            guards: [fueled],
+            unless: [storm],
+            before: [arm],
+            after: [celebrate],
+            transition: { from: Docked, to: InFlight, action: burn, compensate: [disarm] }
+        }
+        land {
+            before: [arm],
+            transition: { from: InFlight, to: Docked }
+        }
+    }
+}
+
+impl<S> Rocket<S> {
+    fn countdown(&self, _stage: AroundStage) -> AroundOutcome<Docked> {
+        AroundOutcome::Proceed
+    }
+
+    fn fueled(&self, ctx: &Pad) -> bool {
+        ctx.fuel > 0
+    }
+
+    fn storm(&self, _ctx: &Pad) -> bool {
+        false
+    }
+
+    fn arm(&self) {}
+
+    fn disarm(&self) {}
+
+    fn celebrate(&self) {}
+
+    fn burn(ctx: &mut Pad) -> bool {
+        if ctx.fuel < 50 {
+            return false;
+        }
+        ctx.fuel -= 50;
+        true
+    }
+}
+
+#[test]
+fn records_full_transition_order() {
+    let recorder = TraceRecorder::start();
+    let _rocket = Rocket::new(Pad { fuel: 80 }).launch().unwrap();
+    let trace = recorder.finish();
+
+    assert_eq!(
+        trace.to_string(),
+        "\
+Rocket.launch: around countdown (Before)
+Rocket.launch: guard fueled -> true
+Rocket.launch: guard storm -> false
+Rocket.launch: before arm
+Rocket.launch: action burn -> true
+Rocket.launch: Docked -> InFlight
+Rocket.launch: after celebrate
+Rocket.launch: around countdown (AfterSuccess)
+"
+    );
+}
+
+#[test]
+fn records_rejections_and_compensation() {
+    let recorder = TraceRecorder::start();
+    let (_empty, _) = Rocket::new(Pad { fuel: 0 }).launch().unwrap_err();
+    let (_low, _) = Rocket::new(Pad { fuel: 10 }).launch().unwrap_err();
+    let trace = recorder.finish();
+
+    let steps: Vec<TraceStep> = trace.entries().iter().map(|entry| entry.step).collect();
+    assert_eq!(
+        steps,
+        [
+            TraceStep::Around {
+                callback: "countdown",
+                stage: AroundStage::Before
+            },
+            TraceStep::Guard {
+                guard: "fueled",
+                result: false
+            },
+            TraceStep::Around {
+                callback: "countdown",
+                stage: AroundStage::Before
+            },
+            TraceStep::Guard {
+                guard: "fueled",
+                result: true
+            },
+            TraceStep::Guard {
+                guard: "storm",
+                result: false
+            },
+            TraceStep::Before { callback: "arm" },
+            TraceStep::Action {
+                action: "burn",
+                accepted: false
+            },
+            TraceStep::Compensate { hook: "disarm" },
+        ]
+    );
+}
+
+#[test]
+fn dynamic_dispatch_is_traced_and_renders_as_mermaid() {
+    let mut rocket = DynamicRocket::new(Pad { fuel: 100 });
+    rocket.handle(RocketEvent::Launch).unwrap();
+
+    let recorder = TraceRecorder::start();
+    rocket.handle(RocketEvent::Land).unwrap();
+    let trace = recorder.finish();
+
+    assert_eq!(
+        trace.to_mermaid(),
+        "\
+sequenceDiagram
+    participant Rocket
+    Rocket->>Rocket: land: before arm
+    Note over Rocket: land: InFlight to Docked
+"
+    );
+}
+
+#[test]
+fn nothing_is_recorded_without_a_recorder() {
+    let _rocket = Rocket::new(Pad { fuel: 80 }).launch().unwrap();
+
+    let recorder = TraceRecorder::start();
+    let trace = recorder.finish();
+    assert!(trace.entries().is_empty());
+}
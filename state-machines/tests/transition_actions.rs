@@ -0,0 +1,73 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{DynamicError, core::TransitionErrorKind, state_machine};
+
+#[derive(Debug, Default)]
+struct CarrierApi {
+    available: bool,
+    shipments: Vec<u32>,
+}
+
+state_machine! {
+    name: Order,
+    context: CarrierApi,
+    dynamic: true,
+    initial: Packed,
+    states: [Packed, Shipped, Delivered],
+    events {
+        ship {
+            payload: u32,
+            transition: { from: Packed, to: Shipped, action: book_carrier }
+        }
+        deliver {
+            payload: u32,
+            transition: { from: Shipped, to: Delivered }
+        }
+    }
+}
+
+impl<S> Order<S> {
+    fn book_carrier(ctx: &mut CarrierApi, parcel: &u32) -> bool {
+        if !ctx.available {
+            return false;
+        }
+        ctx.shipments.push(*parcel);
+        true
+    }
+}
+
+#[test]
+fn action_receives_mutable_context_and_payload() {
+    let api = CarrierApi {
+        available: true,
+        ..Default::default()
+    };
+    let order = Order::new(api).ship(7).expect("carrier booked");
+    assert_eq!(order.ctx.shipments, vec![7]);
+
+    // Transitions without an action don't call it
+    let order = order.deliver(7).unwrap();
+    assert_eq!(order.ctx.shipments, vec![7]);
+}
+
+#[test]
+fn action_veto_keeps_source_state() {
+    let order = Order::new(CarrierApi::default());
+
+    let (order, err) = order.ship(9).expect_err("carrier unavailable");
+    assert_eq!(err.guard, "book_carrier");
+    assert_eq!(
+        err.kind,
+        TransitionErrorKind::ActionFailed {
+            action: "book_carrier"
+        }
+    );
+
+    let mut order = order.into_dynamic();
+    assert_eq!(
+        order.handle(OrderEvent::Ship(9)),
+        Err(DynamicError::action_failed("book_carrier", "ship"))
+    );
+    assert!(order.is_packed());
+}
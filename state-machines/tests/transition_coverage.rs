@@ -0,0 +1,134 @@
+//! Transition coverage from the `trace` feature.
+#![cfg(feature = "trace")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{
+    coverage::{TransitionCoverage, TransitionTriple},
+    state_machine,
+};
+
+state_machine! {
+    name: Ticket,
+    dynamic: true,
+    initial: Open,
+    states: [
+        Open,
+        superstate Active {
+            state Assigned,
+            state Blocked,
+        },
+        Closed,
+    ],
+    events {
+        assign {
+            transition: { from: Open, to: Active }
+        }
+        block {
+            transition: { from: Assigned, to: Blocked }
+        }
+        close {
+            transition: { from: [Open, Active], to: Closed }
+        }
+    }
+}
+
+state_machine! {
+    name: Valve,
+    dynamic: true,
+    compact_codegen: true,
+    initial: Shut,
+    states: [Shut, Flowing],
+    events {
+        open {
+            transition: { from: Shut, to: Flowing }
+        }
+        shut {
+            transition: { from: Flowing, to: Shut }
+        }
+    }
+}
+
+#[test]
+fn expected_expands_sources_and_resolves_targets() {
+    let coverage = TransitionCoverage::start(TicketState::definition());
+    let expected: Vec<String> = coverage
+        .expected()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        expected,
+        [
+            "assign: Open -> Assigned",
+            "block: Assigned -> Blocked",
+            "close: Open -> Closed",
+            "close: Assigned -> Closed",
+            "close: Blocked -> Closed",
+        ]
+    );
+}
+
+#[test]
+fn typestate_and_dynamic_transitions_are_recorded() {
+    let coverage = TransitionCoverage::start(TicketState::definition());
+
+    let assigned = Ticket::new(()).assign().unwrap();
+    let _closed = assigned.close().unwrap();
+
+    let mut ticket = DynamicTicket::new(());
+    ticket.handle(TicketEvent::Close).unwrap();
+
+    assert!(!coverage.complete());
+    assert_eq!(
+        coverage.missing(),
+        [
+            TransitionTriple {
+                event: "block",
+                from: TicketState::Assigned,
+                to: TicketState::Blocked,
+            },
+            TransitionTriple {
+                event: "close",
+                from: TicketState::Blocked,
+                to: TicketState::Closed,
+            },
+        ]
+    );
+    assert_eq!(
+        coverage.report().to_string(),
+        "3 of 5 transitions covered; missing:\n  \
+         block: Assigned -> Blocked\n  \
+         close: Blocked -> Closed\n"
+    );
+}
+
+#[test]
+fn only_transitions_after_start_count() {
+    let _assigned = Ticket::new(()).assign().unwrap();
+
+    let coverage = TransitionCoverage::start(TicketState::definition());
+    assert!(coverage.covered().is_empty());
+}
+
+#[test]
+fn recorders_only_count_their_own_machine() {
+    let tickets = TransitionCoverage::start(TicketState::definition());
+    let valves = TransitionCoverage::start(ValveState::definition());
+
+    // Table-driven dispatch in compact mode is recorded too
+    let mut valve = DynamicValve::new(());
+    valve.handle(ValveEvent::Open).unwrap();
+    valve.handle(ValveEvent::Shut).unwrap();
+
+    valves.assert_complete();
+    assert_eq!(valves.report().to_string(), "all 2 transitions covered\n");
+    assert!(tickets.covered().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "0 of 2 transitions covered")]
+fn assert_complete_lists_the_gaps() {
+    let coverage = TransitionCoverage::start(ValveState::definition());
+    coverage.assert_complete();
+}
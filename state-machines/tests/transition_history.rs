@@ -0,0 +1,126 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{EventFailure, state_machine};
+
+state_machine! {
+    name: Turnstile,
+    dynamic: true,
+    history: 3,
+    initial: Locked,
+    states: [Locked, Unlocked],
+    events {
+        coin {
+            transition: { from: [Locked, Unlocked], to: Unlocked }
+        }
+        push {
+            transition: { from: Unlocked, to: Locked }
+        }
+    }
+}
+
+fn steps(turnstile: &DynamicTurnstile<()>) -> Vec<(TurnstileState, &'static str, TurnstileState)> {
+    turnstile
+        .history()
+        .iter()
+        .map(|t| (t.from, t.event, t.to))
+        .collect()
+}
+
+#[test]
+fn a_new_machine_has_no_history() {
+    let turnstile = DynamicTurnstile::new(());
+    assert!(turnstile.history().is_empty());
+    assert_eq!(turnstile.history().capacity(), 3);
+    assert!(turnstile.last_transition().is_none());
+}
+
+#[test]
+fn transitions_are_kept_oldest_first() {
+    use TurnstileState::*;
+    let mut turnstile = DynamicTurnstile::new(());
+
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+    assert_eq!(
+        steps(&turnstile),
+        [(Locked, "coin", Unlocked), (Unlocked, "coin", Unlocked)]
+    );
+
+    let last = turnstile.last_transition().unwrap();
+    assert_eq!((last.from, last.to), (Unlocked, Unlocked));
+}
+
+#[test]
+fn the_oldest_transition_is_dropped_once_full() {
+    use TurnstileState::*;
+    let mut turnstile = DynamicTurnstile::new(());
+
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+    turnstile.handle(TurnstileEvent::Push).unwrap();
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+    turnstile.handle(TurnstileEvent::Push).unwrap();
+
+    assert_eq!(turnstile.history().len(), 3);
+    assert_eq!(
+        steps(&turnstile),
+        [
+            (Unlocked, "push", Locked),
+            (Locked, "coin", Unlocked),
+            (Unlocked, "push", Locked),
+        ]
+    );
+}
+
+#[test]
+fn rejected_events_and_restores_are_not_recorded() {
+    let mut turnstile = DynamicTurnstile::new(());
+
+    assert!(turnstile.handle(TurnstileEvent::Push).is_err());
+    assert!(turnstile.history().is_empty());
+
+    turnstile.handle(TurnstileEvent::Coin).unwrap();
+    turnstile.restore_state(TurnstileState::Locked);
+    assert_eq!(turnstile.history().len(), 1);
+    assert_eq!(
+        turnstile.last_transition().unwrap().to,
+        TurnstileState::Unlocked
+    );
+}
+
+state_machine! {
+    name: Valve,
+    dynamic: true,
+    history: 4,
+    on_error: { handler: ignore_failure, recover: fault, after: 1 },
+    initial: Closed,
+    states: [Closed, Open, Faulted],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+        close {
+            transition: { from: Open, to: Closed }
+        }
+        fault {
+            transition: { from: [Closed, Open], to: Faulted }
+        }
+    }
+}
+
+impl<C, S> Valve<C, S> {
+    fn ignore_failure(&self, _failure: EventFailure<'_>) {}
+}
+
+#[test]
+fn recovery_events_are_recorded() {
+    let mut valve = DynamicValve::new(());
+
+    assert!(valve.handle(ValveEvent::Close).is_err());
+    let last = valve.last_transition().unwrap();
+    assert_eq!(
+        (last.from, last.event, last.to),
+        (ValveState::Closed, "fault", ValveState::Faulted)
+    );
+    assert_eq!(valve.history().len(), 1);
+}
@@ -0,0 +1,214 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{Sink, TransitionNotification, state_machine};
+
+#[derive(Debug, Default)]
+pub struct Outbox {
+    pub sent: Vec<TransitionNotification<OrderNotification>>,
+}
+
+impl Sink<OrderNotification> for Outbox {
+    fn send(&mut self, notification: TransitionNotification<OrderNotification>) {
+        self.sent.push(notification);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Shop {
+    pub outbox: Outbox,
+    pub in_stock: bool,
+    pub log: Vec<&'static str>,
+}
+
+state_machine! {
+    name: Order,
+    context: Shop,
+    dynamic: true,
+    sink: outbox,
+    initial: Pending,
+    states: [Pending, Paid, Shipped, Cancelled],
+    events {
+        pay {
+            after: [log_payment],
+            transition: { from: Pending, to: Paid, emit: OrderPaid }
+        }
+        ship {
+            guards: [in_stock],
+            transition: { from: Paid, to: Shipped, emit: OrderShipped }
+        }
+        cancel {
+            transition: { from: Pending, to: Cancelled, emit: OrderCancelled }
+            transition: { from: Paid, to: Cancelled, emit: RefundDue }
+        }
+        reopen {
+            transition: { from: Cancelled, to: Pending }
+        }
+    }
+}
+
+impl<S> Order<S> {
+    fn outbox(&mut self) -> &mut Outbox {
+        &mut self.ctx.outbox
+    }
+
+    fn in_stock(&self, ctx: &Shop) -> bool {
+        ctx.in_stock
+    }
+
+    fn log_payment(&mut self) {
+        self.ctx.log.push("after");
+        assert!(self.ctx.outbox.sent.is_empty(), "notified before `after`");
+    }
+}
+
+fn notification(
+    kind: OrderNotification,
+    event: &'static str,
+    from: &'static str,
+    to: &'static str,
+) -> TransitionNotification<OrderNotification> {
+    TransitionNotification {
+        kind,
+        event,
+        from,
+        to,
+        id: None,
+    }
+}
+
+#[test]
+fn typestate_transitions_emit_after_their_callbacks() {
+    let order = Order::new(Shop::default()).pay().unwrap();
+    assert_eq!(order.ctx.log, ["after"]);
+    assert_eq!(
+        order.ctx.outbox.sent,
+        [notification(
+            OrderNotification::OrderPaid,
+            "pay",
+            "Pending",
+            "Paid"
+        )]
+    );
+}
+
+#[test]
+fn each_transition_emits_its_own_kind() {
+    let paid = Order::new(Shop::default()).pay().unwrap().cancel().unwrap();
+    let pending = Order::new(Shop::default()).cancel().unwrap();
+
+    assert_eq!(
+        paid.ctx.outbox.sent.last(),
+        Some(&notification(
+            OrderNotification::RefundDue,
+            "cancel",
+            "Paid",
+            "Cancelled"
+        ))
+    );
+    assert_eq!(
+        pending.ctx.outbox.sent,
+        [notification(
+            OrderNotification::OrderCancelled,
+            "cancel",
+            "Pending",
+            "Cancelled"
+        )]
+    );
+
+    // Transitions without `emit` stay quiet
+    let reopened = pending.reopen().unwrap();
+    assert_eq!(reopened.ctx.outbox.sent.len(), 1);
+}
+
+#[test]
+fn rejected_transitions_emit_nothing() {
+    let paid = Order::new(Shop::default()).pay().unwrap();
+    let (paid, _) = paid.ship().unwrap_err();
+    assert_eq!(paid.ctx.outbox.sent.len(), 1);
+}
+
+#[test]
+fn dynamic_dispatch_emits_too() {
+    let shop = Shop {
+        in_stock: true,
+        ..Shop::default()
+    };
+    let mut order = DynamicOrder::new(shop);
+    order.handle(OrderEvent::Pay).unwrap();
+    order.handle(OrderEvent::Ship).unwrap();
+
+    let shipped = order.into_shipped().unwrap();
+    let kinds: Vec<_> = shipped.ctx.outbox.sent.iter().map(|n| n.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            OrderNotification::OrderPaid,
+            OrderNotification::OrderShipped
+        ]
+    );
+}
+
+type Hook = Box<dyn FnMut(TransitionNotification<BuildNotification>)>;
+
+pub struct Ci {
+    pub webhook: Hook,
+}
+
+impl Default for Ci {
+    fn default() -> Self {
+        Ci {
+            webhook: Box::new(|_| {}),
+        }
+    }
+}
+
+impl std::fmt::Debug for Ci {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ci").finish_non_exhaustive()
+    }
+}
+
+state_machine! {
+    name: Build,
+    context: Ci,
+    sink: webhook,
+    initial: Queued,
+    states: [Queued, Running, Passed, Failed],
+    events {
+        start {
+            transition: { from: Queued, to: Running }
+        }
+        finish {
+            transition: { from: Running, to: [Passed, Failed], choose: verdict, emit: Finished }
+        }
+    }
+}
+
+impl<S> Build<S> {
+    fn webhook(&mut self) -> &mut Hook {
+        &mut self.ctx.webhook
+    }
+
+    fn verdict(&self, _ctx: &Ci) -> BuildFinishChoice {
+        BuildFinishChoice::Failed
+    }
+}
+
+#[test]
+fn closures_are_sinks_and_choices_report_the_chosen_target() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let ci = Ci {
+        webhook: Box::new(move |notification| tx.send(notification).unwrap()),
+    };
+
+    let running = Build::new(ci).start().unwrap();
+    assert!(rx.try_recv().is_err());
+
+    let BuildFinishOutcome::Failed(_) = running.finish().unwrap() else {
+        panic!("verdict picks Failed");
+    };
+    let sent = rx.try_recv().unwrap();
+    assert_eq!(sent.kind, BuildNotification::Finished);
+    assert_eq!((sent.from, sent.to), ("Running", "Failed"));
+}
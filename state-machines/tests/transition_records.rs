@@ -0,0 +1,51 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::{DynamicError, state_machine};
+
+state_machine! {
+    name: Elevator,
+    dynamic: true,
+    transition_records: true,
+    initial: Idle,
+    states: [Idle, MovingUp, MovingDown],
+    events {
+        call_up {
+            transition: { from: Idle, to: MovingUp }
+        }
+        call_down {
+            transition: { from: Idle, to: MovingDown }
+        }
+        arrive {
+            transition: { from: [MovingUp, MovingDown], to: Idle }
+        }
+    }
+}
+
+#[test]
+fn handle_returns_transition_record() {
+    let mut elevator = DynamicElevator::new(());
+
+    let record = elevator.handle(ElevatorEvent::CallUp).unwrap();
+    assert_eq!(record.from, ElevatorState::Idle);
+    assert_eq!(record.to, ElevatorState::MovingUp);
+    assert_eq!(record.event, "call_up");
+    // Durations are only measured with a clock
+    assert_eq!(record.duration.is_some(), cfg!(feature = "std"));
+
+    let record = elevator.handle(ElevatorEvent::Arrive).unwrap();
+    assert_eq!(
+        (record.from, record.to),
+        (ElevatorState::MovingUp, ElevatorState::Idle)
+    );
+}
+
+#[test]
+fn failed_dispatch_returns_error() {
+    let mut elevator = DynamicElevator::new(());
+
+    assert_eq!(
+        elevator.handle(ElevatorEvent::Arrive),
+        Err(DynamicError::invalid_transition("Idle", "arrive"))
+    );
+}
@@ -0,0 +1,120 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Rocket,
+    dynamic: true,
+    initial: Idle,
+    states: [
+        Idle,
+        superstate Flight {
+            state Armed,
+            state Ascending,
+        },
+        Scrubbed,
+    ],
+    events {
+        ignite {
+            guards: [fueled],
+            transition: { from: Idle, to: Armed }
+        }
+        launch {
+            transition: { from: Armed, to: Ascending }
+        }
+        scrub {
+            transition: { from: [Idle, Flight], to: Scrubbed }
+        }
+    }
+}
+
+impl<C, S> Rocket<C, S> {
+    fn fueled(&self, _ctx: &C) -> bool {
+        true
+    }
+}
+
+state_machine! {
+    name: Probe,
+    module: probe,
+    dynamic: true,
+    initial: Docked,
+    states: [Docked, Drifting],
+    events {
+        release {
+            transition: { from: Docked, to: Drifting }
+        }
+    }
+}
+
+#[test]
+fn constants_name_each_edge() {
+    let ignite = RocketTransitions::IGNITE_IDLE_TO_ARMED;
+
+    assert_eq!(ignite.name(), "ignite");
+    assert_eq!(ignite.from(), &RocketState::Idle);
+    assert_eq!(ignite.to(), &RocketState::Armed);
+}
+
+#[test]
+fn refs_index_into_the_definition() {
+    let ignite = RocketTransitions::IGNITE_IDLE_TO_ARMED;
+
+    assert!(core::ptr::eq(
+        ignite.definition(),
+        RocketState::definition()
+    ));
+    assert_eq!(ignite.event().name, "ignite");
+    assert_eq!(ignite.event().guards, ["fueled"]);
+    assert_eq!(ignite.transition().target, RocketState::Armed);
+}
+
+#[test]
+fn superstate_sources_get_one_constant_per_leaf() {
+    let names: Vec<_> = RocketTransitions::ALL
+        .iter()
+        .map(|edge| format!("{}: {:?} -> {:?}", edge.name(), edge.from(), edge.to()))
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "ignite: Idle -> Armed",
+            "launch: Armed -> Ascending",
+            "scrub: Idle -> Scrubbed",
+            "scrub: Armed -> Scrubbed",
+            "scrub: Ascending -> Scrubbed",
+        ]
+    );
+    assert_eq!(
+        RocketTransitions::SCRUB_ASCENDING_TO_SCRUBBED
+            .transition()
+            .sources,
+        [
+            RocketState::Idle,
+            RocketState::Armed,
+            RocketState::Ascending
+        ]
+    );
+}
+
+#[test]
+fn module_machines_nest_the_handles_in_their_module() {
+    let release = probe::transitions::RELEASE_DOCKED_TO_DRIFTING;
+
+    assert_eq!(release.to(), &probe::ProbeState::Drifting);
+    assert_eq!(probe::transitions::ALL.len(), 1);
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn coverage_checks_a_single_edge() {
+    use state_machines::coverage::TransitionCoverage;
+
+    let coverage = TransitionCoverage::start(RocketState::definition());
+    let _armed = Rocket::new(()).ignite().unwrap();
+
+    assert!(coverage.covers(&RocketTransitions::IGNITE_IDLE_TO_ARMED));
+    assert!(!coverage.covers(&RocketTransitions::LAUNCH_ARMED_TO_ASCENDING));
+}
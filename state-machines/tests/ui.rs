@@ -0,0 +1,15 @@
+//! Golden-file tests for the macro's compile errors.
+//!
+//! Each file in `tests/ui/` is a machine the macro must reject, with the
+//! expected diagnostic in the matching `.stderr` file. After adding a check
+//! or rewording a message, regenerate them with:
+//!
+//! ```text
+//! TRYBUILD=overwrite cargo test -p state-machines --test ui
+//! ```
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Closed,
+    states: [Closed, Open, Broken],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+            transition: { from: Closed, to: Broken }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use state_machines::{assert_machine, state_machine};
+
+state_machine! {
+    name: Door,
+    dynamic: true,
+    initial: Closed,
+    states: [Closed, Open, Jammed],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+        close {
+            transition: { from: Open, to: Closed }
+        }
+    }
+}
+
+assert_machine!(Door: can_reach(Open => Closed), no_orphan_states);
+
+fn main() {}
@@ -0,0 +1,17 @@
+use state_machines::{assert_machine, state_machine};
+
+state_machine! {
+    name: Door,
+    dynamic: true,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+assert_machine!(Door: no_orphans);
+
+fn main() {}
@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+pub struct Claim;
+
+state_machine! {
+    name: ClaimFlow,
+    behavior: true,
+    context: Claim,
+    initial: Submitted,
+    states: [Submitted, Approved],
+    events {
+        approve {
+            guards: [below_limit(100)],
+            transition: { from: Submitted, to: Approved }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use state_machines::state_machine;
+
+pub struct Claim;
+
+state_machine! {
+    name: ClaimFlow,
+    behavior: true,
+    context: Claim,
+    initial: Submitted,
+    states: [Submitted, Approved, Rejected],
+    events {
+        approve {
+            guards: [flagged],
+            transition: { from: Submitted, to: Approved }
+        }
+        reject {
+            unless: [flagged],
+            transition: { from: Submitted, to: Rejected }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Claim,
+    behavior: true,
+    initial: Submitted,
+    states: [Submitted, Approved],
+    events {
+        approve {
+            guards: [within_limit],
+            transition: { from: Submitted, to: Approved }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    machine_attrs: [#[derive(Clone)]],
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    state_derives: [Clone],
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Drone,
+    initial: Grounded,
+    states: [Grounded, Cruising],
+    events {
+        cruise {
+            guards: [min_altitude(100) { cache: true }],
+            transition: { from: Grounded, to: Cruising }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            before: [{ name: creak, from: Open }],
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
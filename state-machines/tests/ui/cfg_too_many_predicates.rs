@@ -0,0 +1,17 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Pump,
+    initial: Idle,
+    states: [
+        Idle,
+        #[cfg(feature = "a")] A,
+        #[cfg(feature = "b")] B,
+        #[cfg(feature = "c")] C,
+        #[cfg(feature = "d")] D,
+        #[cfg(feature = "e")] E,
+    ],
+    events {}
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Pump,
+    initial: Idle,
+    states: [Idle, #[cfg(feature = "maintenance")] Servicing],
+    events {
+        service {
+            transition: { from: Idle, to: Servicing }
+        }
+    }
+}
+
+fn main() {}
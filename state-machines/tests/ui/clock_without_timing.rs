@@ -0,0 +1,16 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Lamp,
+    dynamic: true,
+    clock: Ticks,
+    initial: Off,
+    states: [Off, On],
+    events {
+        toggle {
+            transition: { from: Off, to: On }
+        }
+    }
+}
+
+fn main() {}
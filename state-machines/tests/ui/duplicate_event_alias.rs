@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Lamp,
+    initial: Off,
+    states: [Off, On],
+    events {
+        turn_on {
+            alias: [power_up],
+            transition: { from: Off, to: On }
+        }
+        turn_off {
+            alias: [turn_on],
+            transition: { from: On, to: Off }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Open,
+    states: [
+        Open,
+        superstate Shut {
+            state Closed,
+        },
+        superstate Shut {
+            state Locked,
+        },
+    ],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
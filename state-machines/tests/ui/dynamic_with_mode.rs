@@ -0,0 +1,16 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    dynamic: true,
+    mode: both,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Open, emit: Opened }
+        }
+    }
+}
+
+fn main() {}
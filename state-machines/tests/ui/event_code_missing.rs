@@ -0,0 +1,18 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Valve,
+    dynamic: true,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open = 1 {
+            transition: { from: Closed, to: Open }
+        }
+        close {
+            transition: { from: Open, to: Closed }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+mod vault {
+    use state_machines::state_machine;
+
+    state_machine! {
+        name: Vault,
+        initial: Locked,
+        states: [Locked, Open],
+        events {
+            pub(self) force_unlock {
+                transition: { from: Locked, to: Open }
+            }
+        }
+    }
+}
+
+fn main() {
+    let _ = vault::Vault::new(()).force_unlock();
+}
@@ -0,0 +1,16 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Vault,
+    initial: Locked,
+    states: [Locked, Open],
+    events {
+        unsafe force_unlock {
+            transition: { from: Locked, to: Open }
+        }
+    }
+}
+
+fn main() {
+    let _ = Vault::new(()).force_unlock();
+}
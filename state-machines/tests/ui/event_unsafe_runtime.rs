@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Vault,
+    dynamic: true,
+    initial: Locked,
+    states: [Locked, Open],
+    events {
+        unsafe force_unlock {
+            transition: { from: Locked, to: Open }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {}
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Lamp,
+    extends: Light,
+    initial: Off,
+    states: [Off, On],
+    events {
+        toggle {
+            transition: { from: Off, to: On }
+        }
+    }
+}
+
+fn main() {}
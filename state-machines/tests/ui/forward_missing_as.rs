@@ -0,0 +1,14 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Connection,
+    initial: Offline,
+    states: [Offline, Online],
+    events {
+        connect {
+            transition: { from: Offline, to: Online, forward: { to: session } }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use state_machines::fragment;
+
+fragment! {
+    name: Retryable,
+    initial: Failed,
+    states: [Failed, Retrying],
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use state_machines::state_machine;
+
+const FLOOR: i32 = 100;
+
+state_machine! {
+    name: Drone,
+    initial: Grounded,
+    states: [Grounded, Cruising],
+    events {
+        cruise {
+            guards: [min_altitude(FLOOR)],
+            transition: { from: Grounded, to: Cruising }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+#[derive(Default)]
+pub struct Connection {
+    pub socket: u32,
+}
+
+state_machine! {
+    name: Link,
+    initial: Down,
+    states: [Down, Up(Connection), Failed],
+    events {
+        connect {
+            transition: { from: Down, to: [Up, Failed], choose: dial, init: open_socket }
+        }
+    }
+}
+
+fn main() {}
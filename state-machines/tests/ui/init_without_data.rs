@@ -0,0 +1,14 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Link,
+    initial: Down,
+    states: [Down, Up],
+    events {
+        connect {
+            transition: { from: Down, to: Up, init: open_socket }
+        }
+    }
+}
+
+fn main() {}
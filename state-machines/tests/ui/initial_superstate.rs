@@ -0,0 +1,20 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Shut,
+    states: [
+        Open,
+        superstate Shut {
+            state Closed,
+            state Locked,
+        },
+    ],
+    events {
+        open {
+            transition: { from: Shut, to: Open }
+        }
+    }
+}
+
+fn main() {}
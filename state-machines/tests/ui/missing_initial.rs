@@ -0,0 +1,13 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    states: [Closed, Open],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
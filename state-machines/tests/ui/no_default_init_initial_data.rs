@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+pub struct Connection {
+    pub socket: u32,
+}
+
+state_machine! {
+    name: Link,
+    no_default_init: true,
+    initial: Up,
+    states: [Down, Up(Connection)],
+    events {
+        disconnect {
+            transition: { from: Up, to: Down }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use state_machines::state_machine;
+
+pub struct Connection {
+    pub socket: u32,
+}
+
+state_machine! {
+    name: Link,
+    dynamic: true,
+    no_default_init: true,
+    initial: Down,
+    states: [Down, Up(Connection)],
+    events {
+        connect {
+            payload: Connection,
+            transition: { from: Down, to: Up }
+        }
+    }
+}
+
+fn main() {}
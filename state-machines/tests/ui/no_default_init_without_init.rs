@@ -0,0 +1,20 @@
+use state_machines::state_machine;
+
+pub struct Connection {
+    pub socket: u32,
+}
+
+state_machine! {
+    name: Link,
+    no_default_init: true,
+    initial: Down,
+    states: [Down, Up(Connection)],
+    events {
+        connect {
+            payload: u32,
+            transition: { from: Down, to: Up }
+        }
+    }
+}
+
+fn main() {}
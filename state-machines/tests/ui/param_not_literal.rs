@@ -0,0 +1,18 @@
+use state_machines::state_machine;
+
+const RETRIES: u32 = 3;
+
+state_machine! {
+    name: Uplink,
+    params: { MaxRetries: RETRIES },
+    initial: Idle,
+    states: [Idle, Connecting],
+    events {
+        connect {
+            guards: [failures_below(MaxRetries)],
+            transition: { from: Idle, to: Connecting }
+        }
+    }
+}
+
+fn main() {}
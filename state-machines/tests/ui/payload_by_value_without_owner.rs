@@ -0,0 +1,18 @@
+use state_machines::state_machine;
+
+pub struct Frame(pub Vec<u8>);
+
+state_machine! {
+    name: Link,
+    initial: Idle,
+    states: [Idle, Sent],
+    events {
+        send {
+            payload: Frame by value,
+            before: [log_frame],
+            transition: { from: Idle, to: Sent }
+        }
+    }
+}
+
+fn main() {}
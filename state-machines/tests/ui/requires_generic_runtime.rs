@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+pub trait Radio {
+    fn key_up(&mut self);
+}
+
+state_machine! {
+    name: Beacon,
+    dynamic: true,
+    initial: Idle,
+    states: [Idle, Transmitting { requires: [Radio] }],
+    events {
+        transmit {
+            transition: { from: Idle, to: Transmitting }
+        }
+    }
+}
+
+fn main() {}
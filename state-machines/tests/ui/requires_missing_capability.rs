@@ -0,0 +1,22 @@
+use state_machines::state_machine;
+
+pub trait Radio {
+    fn key_up(&mut self);
+}
+
+pub struct BenchBoard;
+
+state_machine! {
+    name: Beacon,
+    initial: Idle,
+    states: [Idle, Transmitting { requires: [Radio] }],
+    events {
+        transmit {
+            transition: { from: Idle, to: Transmitting }
+        }
+    }
+}
+
+fn main() {
+    let _ = Beacon::new(BenchBoard).transmit();
+}
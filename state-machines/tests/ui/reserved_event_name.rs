@@ -0,0 +1,14 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Account,
+    initial: Pending,
+    states: [Pending, Active],
+    events {
+        new {
+            transition: { from: Pending, to: Active }
+        }
+    }
+}
+
+fn main() {}
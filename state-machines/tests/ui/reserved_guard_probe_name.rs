@@ -0,0 +1,21 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Rocket,
+    initial: Docked,
+    states: [Docked, Aborted, superstate Flight { state Ascent, state Orbit }],
+    events {
+        launch {
+            transition: { from: Docked, to: Ascent }
+        }
+        abort {
+            guards: [safe_to_abort],
+            transition: { from: Flight, to: Aborted }
+        }
+        can_abort {
+            transition: { from: Docked, to: Docked }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Closed,
+    states: [Closed, Open],
+    events {
+        open {
+            retry: { attempts: 3 },
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+use state_machines::state_machine;
+
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub percent: u8,
+}
+
+// The history can't bring `Running`'s progress back, so there's no `rewind()`
+state_machine! {
+    name: Job,
+    dynamic: true,
+    history: 8,
+    initial: Queued,
+    states: [Queued, Running(Progress)],
+    events {
+        start {
+            transition: { from: Queued, to: Running }
+        }
+        requeue {
+            transition: { from: Running, to: Queued }
+        }
+    }
+}
+
+fn main() {
+    let mut job = DynamicJob::new(());
+    job.handle(JobEvent::Start).unwrap();
+    job.handle(JobEvent::Requeue).unwrap();
+    let _ = job.rewind(1);
+}
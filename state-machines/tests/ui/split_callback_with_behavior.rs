@@ -0,0 +1,20 @@
+use state_machines::state_machine;
+
+#[derive(Default)]
+struct Window;
+
+state_machine! {
+    name: Sensor,
+    context: u32,
+    behavior: true,
+    initial: Idle,
+    states: [Idle, Sampling(Window)],
+    events {
+        start {
+            after: [{ name: record, split: true }],
+            transition: { from: Idle, to: Sampling }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Sensor,
+    initial: Idle,
+    states: [Idle, Sampling],
+    events {
+        start {
+            after: [{ name: record, split: true }],
+            transition: { from: Idle, to: Sampling }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Valve,
+    dynamic: true,
+    initial: Closed,
+    states: [Closed = 1, Open = 2, Jammed = 1],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
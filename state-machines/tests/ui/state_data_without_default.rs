@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+#[derive(Debug)]
+pub struct Connection {
+    pub socket: u32,
+}
+
+state_machine! {
+    name: Link,
+    initial: Down,
+    states: [Down, Up(Connection)],
+    events {
+        connect {
+            transition: { from: Down, to: Up }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Open,
+    states: [
+        Open,
+        superstate Open {
+            state Closed,
+        },
+    ],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Door,
+    initial: Open,
+    states: [
+        Open,
+        superstate Shut {
+            state Closed,
+            state Locked,
+        },
+    ],
+    events {
+        open {
+            transition: { from: Closed, to: Open }
+            transition: { from: Shut, to: Open }
+        }
+    }
+}
+
+fn main() {}
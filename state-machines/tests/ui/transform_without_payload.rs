@@ -0,0 +1,15 @@
+use state_machines::state_machine;
+
+state_machine! {
+    name: Shell,
+    initial: Ready,
+    states: [Ready, Running],
+    events {
+        run {
+            transform: normalize,
+            transition: { from: Ready, to: Running }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,146 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::cell::RefCell;
+
+use state_machines::{DynamicError, state_machine};
+
+state_machine! {
+    name: Strict,
+    dynamic: true,
+    initial: Stopped,
+    states: [Stopped, Active],
+    events {
+        start {
+            transition: { from: Stopped, to: Active }
+        }
+        stop {
+            guards: [can_stop],
+            transition: { from: Active, to: Stopped }
+        }
+    }
+}
+
+impl<C, S> Strict<C, S> {
+    fn can_stop(&self, _ctx: &C) -> bool {
+        false
+    }
+}
+
+state_machine! {
+    name: Lenient,
+    dynamic: true,
+    transition_records: true,
+    unhandled: ignore,
+    initial: Waiting,
+    states: [Waiting, Busy],
+    events {
+        start {
+            transition: { from: Waiting, to: Busy }
+        }
+    }
+}
+
+state_machine! {
+    name: Fussy,
+    dynamic: true,
+    unhandled: panic,
+    initial: Parked,
+    states: [Parked, Driving],
+    events {
+        start {
+            transition: { from: Parked, to: Driving }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DeadLetters {
+    pub events: RefCell<Vec<(RouterState, RouterEvent)>>,
+}
+
+state_machine! {
+    name: Router,
+    dynamic: true,
+    context: DeadLetters,
+    unhandled: callback(dead_letter),
+    initial: Idle,
+    states: [Idle, Routing],
+    events {
+        route {
+            payload: u16,
+            transition: { from: Idle, to: Routing }
+        }
+        reset {
+            transition: { from: Routing, to: Idle }
+        }
+    }
+}
+
+impl<S> Router<S> {
+    fn dead_letter(&self, state: RouterState, event: RouterEvent) {
+        self.ctx.events.borrow_mut().push((state, event));
+    }
+}
+
+#[test]
+fn default_policy_reports_invalid_transitions() {
+    let mut machine = DynamicStrict::new(());
+    assert!(!machine.can_handle(&StrictEvent::Stop));
+    assert!(matches!(
+        machine.handle(StrictEvent::Stop),
+        Err(DynamicError::InvalidTransition { .. })
+    ));
+}
+
+#[test]
+fn handle_or_ignore_skips_only_unhandled_events() {
+    let mut machine = DynamicStrict::new(());
+    machine.handle_or_ignore(StrictEvent::Stop).unwrap();
+    assert!(machine.is_stopped());
+
+    machine.handle_or_ignore(StrictEvent::Start).unwrap();
+    assert!(machine.is_active());
+
+    // Guard failures are not "unhandled"
+    assert!(matches!(
+        machine.handle_or_ignore(StrictEvent::Stop),
+        Err(DynamicError::GuardFailed { .. })
+    ));
+}
+
+#[test]
+fn ignore_policy_turns_unhandled_events_into_no_ops() {
+    let mut machine = DynamicLenient::new(());
+    machine.handle(LenientEvent::Start).unwrap();
+
+    let record = machine.handle(LenientEvent::Start).unwrap();
+    assert_eq!(record.from, LenientState::Busy);
+    assert_eq!(record.to, LenientState::Busy);
+    assert_eq!(record.event, "start");
+}
+
+#[test]
+#[should_panic(expected = "unhandled event `start` in state `Driving`")]
+fn panic_policy_panics_on_unhandled_events() {
+    let mut machine = DynamicFussy::new(());
+    machine.handle(FussyEvent::Start).unwrap();
+    let _ = machine.handle(FussyEvent::Start);
+}
+
+#[test]
+fn callback_policy_receives_dead_letters() {
+    let mut machine = DynamicRouter::new(DeadLetters::default());
+    machine.handle(RouterEvent::Reset).unwrap();
+    machine.handle(RouterEvent::Route(8080)).unwrap();
+    machine.handle(RouterEvent::Route(443)).unwrap();
+    assert!(machine.is_routing());
+
+    let router = machine.into_routing().unwrap();
+    let events = router.ctx.events.borrow();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].0, RouterState::Idle);
+    assert!(matches!(events[0].1, RouterEvent::Reset));
+    assert_eq!(events[1].0, RouterState::Routing);
+    assert!(matches!(events[1].1, RouterEvent::Route(443)));
+}
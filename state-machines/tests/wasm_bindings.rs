@@ -0,0 +1,37 @@
+//! Native smoke test for `wasm: true` machines.
+//!
+//! `JsValue` operations only work on wasm32, so this exercises the
+//! success paths that stay on the Rust side.
+#![cfg(feature = "wasm-bindgen")]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use state_machines::state_machine;
+
+state_machine! {
+    name: Checkout,
+    wasm: true,
+    initial: Cart,
+    states: [Cart, Payment, Confirmed],
+    events {
+        proceed {
+            transition: { from: Cart, to: Payment }
+        }
+        pay {
+            payload: u32,
+            transition: { from: Payment, to: Confirmed }
+        }
+    }
+}
+
+#[test]
+fn wasm_class_dispatches_by_name() {
+    let mut checkout = WasmCheckout::new();
+    assert_eq!(checkout.current_state(), "Cart");
+
+    checkout.handle("proceed", None).unwrap();
+    assert_eq!(checkout.current_state(), "Payment");
+
+    checkout.handle("pay", Some("4200".to_string())).unwrap();
+    assert_eq!(checkout.current_state(), "Confirmed");
+}
@@ -0,0 +1,109 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use core::time::Duration;
+use state_machines::state_machine;
+
+state_machine! {
+    name: Modem,
+    dynamic: true,
+    watchdog: true,
+    initial: Offline,
+    states: [
+        Offline,
+        Connecting { max_duration: 10ms },
+        superstate Online {
+            state Idle,
+            state Transferring { max_duration: Duration::from_secs(60) },
+        },
+    ],
+    events {
+        dial {
+            transition: { from: Offline, to: Connecting }
+        }
+        redial {
+            transition: { from: Connecting, to: Connecting }
+        }
+        connected {
+            transition: { from: Connecting, to: Online }
+        }
+    }
+}
+
+#[test]
+fn max_duration_is_exposed_on_the_state_enum() {
+    assert_eq!(
+        ModemState::Connecting.max_duration(),
+        Some(Duration::from_millis(10))
+    );
+    assert_eq!(
+        ModemState::Transferring.max_duration(),
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(ModemState::Offline.max_duration(), None);
+}
+
+#[cfg(not(feature = "std"))]
+#[test]
+fn without_a_clock_nothing_is_measured() {
+    let modem = DynamicModem::new(());
+    assert_eq!(modem.time_in_state(), None);
+    assert_eq!(modem.stuck_check(Duration::ZERO), None);
+}
+
+#[cfg(feature = "std")]
+mod clock {
+    use super::*;
+    use state_machines::StuckState;
+    use std::thread::sleep;
+
+    #[test]
+    fn machine_stuck_past_max_duration_is_reported() {
+        let mut modem = DynamicModem::new(());
+        modem.handle(ModemEvent::Dial).unwrap();
+        assert_eq!(modem.stuck_check(Duration::from_secs(3600)), None);
+
+        sleep(Duration::from_millis(20));
+        let stuck = modem.stuck_check(Duration::from_secs(3600)).unwrap();
+        assert_eq!(stuck.state, ModemState::Connecting);
+        assert_eq!(stuck.limit, Duration::from_millis(10));
+        assert!(stuck.elapsed >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn self_transitions_keep_the_clock_running() {
+        let mut modem = DynamicModem::new(());
+        modem.handle(ModemEvent::Dial).unwrap();
+        sleep(Duration::from_millis(20));
+
+        modem.handle(ModemEvent::Redial).unwrap();
+        assert!(modem.time_in_state().unwrap() >= Duration::from_millis(20));
+        assert!(modem.stuck_check(Duration::from_secs(3600)).is_some());
+    }
+
+    #[test]
+    fn entering_a_new_state_restarts_the_clock() {
+        let mut modem = DynamicModem::new(());
+        modem.handle(ModemEvent::Dial).unwrap();
+        sleep(Duration::from_millis(20));
+
+        modem.handle(ModemEvent::Connected).unwrap();
+        assert!(modem.time_in_state().unwrap() < Duration::from_millis(20));
+    }
+
+    #[test]
+    fn threshold_applies_to_states_without_a_limit() {
+        let modem = DynamicModem::new(());
+        sleep(Duration::from_millis(5));
+
+        assert_eq!(modem.stuck_check(Duration::from_secs(3600)), None);
+        assert!(matches!(
+            modem.stuck_check(Duration::from_millis(1)),
+            Some(StuckState {
+                state: ModemState::Offline,
+                limit,
+                ..
+            }) if limit == Duration::from_millis(1)
+        ));
+    }
+}